@@ -1,5 +1,5 @@
 use criterion::{ black_box, criterion_group, Criterion };
-use distaff::math::{ field, fft };
+use distaff::math::{ field, fft, polynom };
 
 pub fn fft_in_place(c: &mut Criterion) {
 
@@ -13,4 +13,32 @@ pub fn fft_in_place(c: &mut Criterion) {
     });
 }
 
-criterion_group!(group, fft_in_place);
\ No newline at end of file
+pub fn extend_register(c: &mut Criterion) {
+
+    let trace_length: usize = 1 << 10;
+    let domain_size = trace_length * 32;
+    let register = field::rand_vector(trace_length);
+    let ctx = fft::FftContext::new(domain_size);
+
+    c.bench_function("Register extension (new twiddles)", |bench| {
+        bench.iter(|| {
+            let mut values = black_box(&register).clone();
+            polynom::interpolate_fft(&mut values, true);
+            values.resize(domain_size, field::ZERO);
+            polynom::eval_fft(&mut values, true);
+            values
+        })
+    });
+
+    c.bench_function("Register extension (cached twiddles)", |bench| {
+        bench.iter(|| {
+            let mut values = black_box(&register).clone();
+            fft::interpolate_with_ctx(&mut values, black_box(&ctx));
+            values.resize(domain_size, field::ZERO);
+            fft::evaluate_with_ctx(&mut values, black_box(&ctx));
+            values
+        })
+    });
+}
+
+criterion_group!(group, fft_in_place, extend_register);
\ No newline at end of file
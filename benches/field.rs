@@ -17,6 +17,19 @@ pub fn mul128(c: &mut Criterion) {
     });
 }
 
+pub fn mul128_many(c: &mut Criterion) {
+
+    let n = (1 << 10) as usize;
+    let x = field::rand_vector(n);
+    let y = field::rand_vector(n);
+
+    c.bench_function("mul128 (1024 random pairs)", |bench| {
+        bench.iter(|| black_box(&x).iter().zip(black_box(&y).iter())
+            .map(|(&a, &b)| field::mul(a, b))
+            .fold(field::ZERO, field::add))
+    });
+}
+
 pub fn mul_parallel(c: &mut Criterion) {
 
     let n = (1 << 10) as usize;
@@ -56,4 +69,4 @@ pub fn inv128(c: &mut Criterion) {
     });
 }
 
-criterion_group!(group, add128, mul128, mul_parallel, mul_parallel_in_place, exp128, inv128);
\ No newline at end of file
+criterion_group!(group, add128, mul128, mul128_many, mul_parallel, mul_parallel_in_place, exp128, inv128);
\ No newline at end of file
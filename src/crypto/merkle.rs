@@ -1,10 +1,14 @@
 use std::slice;
 use std::collections::{ HashMap, BTreeSet };
 use serde::{ Serialize, Deserialize };
+#[cfg(feature = "concurrent")]
+use rayon::prelude::*;
 use crate::crypto::{ HashFunction };
+use crate::utils::CONCURRENT;
 
 // TYPES AND INTERFACES
 // ================================================================================================
+#[derive(Serialize, Deserialize)]
 pub struct MerkleTree {
     nodes   : Vec<[u8; 32]>,
     values  : Vec<[u8; 32]>
@@ -131,7 +135,7 @@ impl MerkleTree {
         let r = index & 1;
         &buf[0..32].copy_from_slice(&proof[r]);
         &buf[32..64].copy_from_slice(&proof[1 - r]);
-        hash(&buf, &mut v);
+        hash.digest(&buf, &mut v);
 
         let mut index = (index + usize::pow(2, (proof.len() - 1) as u32)) >> 1;
         for i in 2..proof.len() {
@@ -143,7 +147,7 @@ impl MerkleTree {
                 &buf[0..32].copy_from_slice(&proof[i]);
                 &buf[32..64].copy_from_slice(&v);
             }
-            hash(&buf, &mut v);
+            hash.digest(&buf, &mut v);
             index = index >> 1;
         }
 
@@ -199,7 +203,7 @@ impl MerkleTree {
 
             // hash sibling nodes into their parent
             let mut parent = [0u8; 32];
-            hash(&buf, &mut parent);
+            hash.digest(&buf, &mut parent);
 
             let parent_index = offset + index >> 1;
             v.insert(parent_index, parent);
@@ -248,7 +252,7 @@ impl MerkleTree {
                     &buf[32..64].copy_from_slice(sibling);
                 }
                 let mut parent = [0u8; 32];
-                hash(&buf, &mut parent);
+                hash.digest(&buf, &mut parent);
 
                 // add the parent node to the next set of nodes
                 let parent_index = node_index >> 1;
@@ -266,7 +270,16 @@ impl MerkleTree {
 // HELPER FUNCTIONS
 // ================================================================================================
 
+/// Builds internal nodes of a Merkle tree; node i is the parent of nodes 2i and 2i + 1, and
+/// node 1 is the root. When the crate is built with `concurrent` feature, nodes at each level
+/// of the tree are hashed on all available cores.
 pub fn build_merkle_nodes(leaves: &[[u8; 32]], hash: HashFunction) -> Vec<[u8; 32]> {
+    return build_nodes(leaves, hash, CONCURRENT);
+}
+
+/// Builds internal nodes of a Merkle tree either sequentially, or, if `concurrent` is set,
+/// level by level on all available cores; both ways produce the same nodes.
+pub(crate) fn build_nodes(leaves: &[[u8; 32]], hash: HashFunction, concurrent: bool) -> Vec<[u8; 32]> {
     let n = leaves.len() / 2;
 
     // create un-initialized array to hold all intermediate nodes
@@ -278,19 +291,36 @@ pub fn build_merkle_nodes(leaves: &[[u8; 32]], hash: HashFunction) -> Vec<[u8; 3
     let two_leaves = unsafe { slice::from_raw_parts(leaves.as_ptr() as *const [u8; 64], n) };
 
     // build first row of internal nodes (parents of leaves)
-    for (i, j) in (0..n).zip(n..nodes.len()) {
-        hash(&two_leaves[i], &mut nodes[j]);
+    hash_children(&mut nodes[n..], two_leaves, hash, concurrent);
+
+    // calculate all other tree nodes one level at a time; nodes of the level starting at index
+    // i are parents of the nodes of the level starting at index 2i
+    let mut level_start = n / 2;
+    while level_start > 0 {
+        let (parents, children) = nodes.split_at_mut(2 * level_start);
+
+        // re-interpret nodes as an array of two nodes fused together
+        let two_nodes = unsafe { slice::from_raw_parts(children.as_ptr() as *const [u8; 64], level_start) };
+        hash_children(&mut parents[level_start..], two_nodes, hash, concurrent);
+        level_start = level_start / 2;
     }
 
-    // re-interpret nodes as an array of two nodes fused together
-    let two_nodes = unsafe { slice::from_raw_parts(nodes.as_ptr() as *const [u8; 64], n) };
+    return nodes;
+}
 
-    // calculate all other tree nodes
-    for i in (1..n).rev() {
-        hash(&two_nodes[i], &mut nodes[i]);
+fn hash_children(parents: &mut [[u8; 32]], children: &[[u8; 64]], hash: HashFunction, concurrent: bool) {
+    #[cfg(feature = "concurrent")]
+    if concurrent {
+        parents.par_iter_mut().zip(children.par_iter())
+            .for_each(|(parent, children)| hash.digest(children, parent));
+        return;
     }
 
-    return nodes;
+    #[cfg(not(feature = "concurrent"))]
+    let _ = concurrent;
+    for (parent, children) in parents.iter_mut().zip(children.iter()) {
+        hash.digest(children, parent);
+    }
 }
 
 fn map_indexes(indexes: &[usize], max_valid: usize) -> HashMap<usize, usize> {
@@ -316,7 +346,7 @@ fn normalize_indexes(indexes: &[usize]) -> Vec<usize> {
 #[cfg(test)]
 mod tests {
 
-    use crate::crypto::hash;
+    use crate::crypto::HashFunction;
 
     static LEAVES4: [[u8; 32]; 4] = [
         [166, 168,  47, 140, 153, 86, 156,  86, 226, 229, 149,  76,  70, 132, 209, 109, 166, 193, 113, 197,  42, 116, 170, 144,  74, 104,  29, 110, 220, 49, 224, 123],
@@ -339,7 +369,7 @@ mod tests {
     #[test]
     fn new_tree() {
         let leaves = LEAVES4.to_vec();
-        let tree = super::MerkleTree::new(leaves, hash::poseidon);
+        let tree = super::MerkleTree::new(leaves, HashFunction::Rescue);
         let root = hash_2x1(
             &hash_2x1(&LEAVES4[0], &LEAVES4[1]),
             &hash_2x1(&LEAVES4[2], &LEAVES4[3])
@@ -347,7 +377,7 @@ mod tests {
         assert_eq!(&root, tree.root());
 
         let leaves = LEAVES8.to_vec();
-        let tree = super::MerkleTree::new(leaves, hash::poseidon);
+        let tree = super::MerkleTree::new(leaves, HashFunction::Rescue);
         let root = hash_2x1(
             &hash_2x1(
                 &hash_2x1(&LEAVES8[0], &LEAVES8[1]),
@@ -365,7 +395,7 @@ mod tests {
     fn prove() {
         // depth 4
         let leaves = LEAVES4.to_vec();
-        let tree = super::MerkleTree::new(leaves, hash::poseidon);
+        let tree = super::MerkleTree::new(leaves, HashFunction::Rescue);
 
         let proof = vec![
             LEAVES4[1],
@@ -383,7 +413,7 @@ mod tests {
 
         // depth 5
         let leaves = LEAVES8.to_vec();
-        let tree = super::MerkleTree::new(leaves, hash::poseidon);
+        let tree = super::MerkleTree::new(leaves, HashFunction::Rescue);
 
         let proof = vec![
             LEAVES8[1],
@@ -406,27 +436,27 @@ mod tests {
     fn verify() {
         // depth 4
         let leaves = LEAVES4.to_vec();
-        let tree = super::MerkleTree::new(leaves, hash::poseidon);
+        let tree = super::MerkleTree::new(leaves, HashFunction::Rescue);
         let proof = tree.prove(1);
-        assert_eq!(true, super::MerkleTree::verify(tree.root(), 1, &proof, hash::poseidon));
+        assert_eq!(true, super::MerkleTree::verify(tree.root(), 1, &proof, HashFunction::Rescue));
 
         let proof = tree.prove(2);
-        assert_eq!(true, super::MerkleTree::verify(tree.root(), 2, &proof, hash::poseidon));
+        assert_eq!(true, super::MerkleTree::verify(tree.root(), 2, &proof, HashFunction::Rescue));
 
         // depth 5
         let leaves = LEAVES8.to_vec();
-        let tree = super::MerkleTree::new(leaves, hash::poseidon);
+        let tree = super::MerkleTree::new(leaves, HashFunction::Rescue);
         let proof = tree.prove(1);
-        assert_eq!(true, super::MerkleTree::verify(tree.root(), 1, &proof, hash::poseidon));
+        assert_eq!(true, super::MerkleTree::verify(tree.root(), 1, &proof, HashFunction::Rescue));
 
         let proof = tree.prove(6);
-        assert_eq!(true, super::MerkleTree::verify(tree.root(), 6, &proof, hash::poseidon));
+        assert_eq!(true, super::MerkleTree::verify(tree.root(), 6, &proof, HashFunction::Rescue));
     }
 
     #[test]
     fn prove_batch() {
         let leaves = LEAVES8.to_vec();
-        let tree = super::MerkleTree::new(leaves, hash::poseidon);
+        let tree = super::MerkleTree::new(leaves, HashFunction::Rescue);
         
         // 1 index
         let proof = tree.prove_batch(&[1]);
@@ -495,26 +525,26 @@ mod tests {
     #[test]
     fn verify_batch() {
         let leaves = LEAVES8.to_vec();
-        let tree = super::MerkleTree::new(leaves, hash::poseidon);
+        let tree = super::MerkleTree::new(leaves, HashFunction::Rescue);
 
         let proof = tree.prove_batch(&[1]);
-        assert_eq!(true, super::MerkleTree::verify_batch(tree.root(), &[1], &proof, hash::poseidon));
-        assert_eq!(false, super::MerkleTree::verify_batch(tree.root(), &[2], &proof, hash::poseidon));
+        assert_eq!(true, super::MerkleTree::verify_batch(tree.root(), &[1], &proof, HashFunction::Rescue));
+        assert_eq!(false, super::MerkleTree::verify_batch(tree.root(), &[2], &proof, HashFunction::Rescue));
 
         let proof = tree.prove_batch(&[1, 2]);
-        assert_eq!(true, super::MerkleTree::verify_batch(tree.root(), &[1, 2], &proof, hash::poseidon));
-        assert_eq!(false, super::MerkleTree::verify_batch(tree.root(), &[1], &proof, hash::poseidon));
-        assert_eq!(false, super::MerkleTree::verify_batch(tree.root(), &[1, 3], &proof, hash::poseidon));
-        assert_eq!(false, super::MerkleTree::verify_batch(tree.root(), &[1, 2, 3], &proof, hash::poseidon));
+        assert_eq!(true, super::MerkleTree::verify_batch(tree.root(), &[1, 2], &proof, HashFunction::Rescue));
+        assert_eq!(false, super::MerkleTree::verify_batch(tree.root(), &[1], &proof, HashFunction::Rescue));
+        assert_eq!(false, super::MerkleTree::verify_batch(tree.root(), &[1, 3], &proof, HashFunction::Rescue));
+        assert_eq!(false, super::MerkleTree::verify_batch(tree.root(), &[1, 2, 3], &proof, HashFunction::Rescue));
 
         let proof = tree.prove_batch(&[1, 6]);
-        assert_eq!(true, super::MerkleTree::verify_batch(tree.root(), &[1, 6], &proof, hash::poseidon));
+        assert_eq!(true, super::MerkleTree::verify_batch(tree.root(), &[1, 6], &proof, HashFunction::Rescue));
 
         let proof = tree.prove_batch(&[1, 3, 6]);
-        assert_eq!(true, super::MerkleTree::verify_batch(tree.root(), &[1, 3, 6], &proof, hash::poseidon));
+        assert_eq!(true, super::MerkleTree::verify_batch(tree.root(), &[1, 3, 6], &proof, HashFunction::Rescue));
 
         let proof = tree.prove_batch(&[0, 1, 2, 3, 4, 5, 6, 7]);
-        assert_eq!(true, super::MerkleTree::verify_batch(tree.root(), &[0, 1, 2, 3, 4, 5, 6, 7], &proof, hash::poseidon));
+        assert_eq!(true, super::MerkleTree::verify_batch(tree.root(), &[0, 1, 2, 3, 4, 5, 6, 7], &proof, HashFunction::Rescue));
     }
 
     // HELPER FUNCTIONS
@@ -525,7 +555,7 @@ mod tests {
         buf[32..].copy_from_slice(v2);
 
         let mut result = [0u8; 32];
-        hash::poseidon(&buf, &mut result);
+        HashFunction::Rescue.digest(&buf, &mut result);
         return result;
     }
 }
\ No newline at end of file
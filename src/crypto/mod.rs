@@ -1,6 +1,33 @@
 pub mod hash;
+pub mod ots;
+pub mod rescue;
 
 mod merkle;
 pub use merkle::{ MerkleTree, BatchMerkleProof, build_merkle_nodes };
+#[cfg(test)]
+pub(crate) use merkle::build_nodes;
 
-pub type HashFunction = fn(&[u8], &mut [u8]);
\ No newline at end of file
+// HASH FUNCTION
+// ================================================================================================
+
+/// Hash function used to commit to execution traces, constraint evaluations, and FRI layers,
+/// and to derive random values from these commitments. This choice is independent of the hash
+/// function used by the VM itself (see `hash.n` instruction), which is always Rescue.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum HashFunction {
+    Blake3,
+    Sha256,
+    Rescue,
+}
+
+impl HashFunction {
+
+    /// Hashes `values` into `result`; `result` must be exactly 32 bytes long.
+    pub fn digest(&self, values: &[u8], result: &mut [u8]) {
+        match self {
+            HashFunction::Blake3 => hash::blake3(values, result),
+            HashFunction::Sha256 => hash::sha256(values, result),
+            HashFunction::Rescue => hash::rescue_chained(values, result),
+        }
+    }
+}
@@ -0,0 +1,213 @@
+use crate::{ math::field, utils::hasher };
+
+// CONSTANTS
+// ================================================================================================
+pub const MIN_MESSAGE_BITS: u32 = 4;
+pub const MAX_MESSAGE_BITS: u32 = 119;
+
+// TYPES AND INTERFACES
+// ================================================================================================
+
+/// Secret key of a Winternitz-style one-time signature scheme with chains of length 1 built
+/// from Rescue hash function; a key can sign a single message of up to `message_bits` bits.
+pub struct SecretKey {
+    message_bits: u32,
+    chains      : Vec<[u128; 2]>,
+}
+
+/// Signature over a message; binary digits of the message and of its checksum are included
+/// in the signature so that they can be placed onto input tapes next to chain values.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Signature {
+    message_bits: u32,
+    digits      : Vec<u128>,
+    chains      : Vec<[u128; 2]>,
+}
+
+// SECRET KEY IMPLEMENTATION
+// ================================================================================================
+impl SecretKey {
+
+    /// Generates a new secret key for messages of `message_bits` bits from the provided `seed`.
+    pub fn new(message_bits: u32, seed: [u8; 32]) -> SecretKey {
+        assert!(message_bits >= MIN_MESSAGE_BITS && message_bits <= MAX_MESSAGE_BITS,
+            "message bits must be between {} and {}, but was {}",
+            MIN_MESSAGE_BITS, MAX_MESSAGE_BITS, message_bits);
+
+        let num_chains = num_chains(message_bits);
+        let values = field::prng_vector(seed, num_chains * 2);
+        let chains = values.chunks(2).map(|c| [c[0], c[1]]).collect();
+        return SecretKey { message_bits, chains };
+    }
+
+    /// Returns the number of bits in messages which can be signed by this key.
+    pub fn message_bits(&self) -> u32 {
+        return self.message_bits;
+    }
+
+    /// Returns the public key for this secret key. The key is 2 field elements in the order
+    /// in which they are expected to be at the top of the stack.
+    pub fn public_key(&self) -> [u128; 2] {
+        let mut result = [field::ZERO; 2];
+        for chain in self.chains.iter() {
+            let end = hash_stack(chain);
+            result = hash_stack(&[end[0], end[1], result[0], result[1]]);
+        }
+        return result;
+    }
+
+    /// Signs the `message`; the message must fit into `message_bits` bits.
+    pub fn sign(&self, message: u128) -> Signature {
+        let digits = get_digits(message, self.message_bits);
+        let chains = digits.iter().zip(self.chains.iter()).map(|(&d, chain)| {
+            // chains for 1 digits are advanced by one step, chains for 0 digits are revealed
+            if d == field::ONE { hash_stack(chain) } else { *chain }
+        }).collect();
+        return Signature { message_bits: self.message_bits, digits, chains };
+    }
+}
+
+// SIGNATURE IMPLEMENTATION
+// ================================================================================================
+impl Signature {
+
+    /// Returns true if this signature is a valid signature of the `message` against the
+    /// `public_key`.
+    pub fn verify(&self, message: u128, public_key: [u128; 2]) -> bool {
+        if message >> self.message_bits != 0 || self.digits != get_digits(message, self.message_bits) {
+            return false;
+        }
+
+        let mut result = [field::ZERO; 2];
+        for (&d, chain) in self.digits.iter().zip(self.chains.iter()) {
+            let end = if d == field::ONE { *chain } else { hash_stack(chain) };
+            result = hash_stack(&[end[0], end[1], result[0], result[1]]);
+        }
+        return result == public_key;
+    }
+
+    /// Returns contents of input tapes A and B expected by `stdlib::ots_verify()` routine.
+    /// For every chain, tape A holds the digit followed by the second chain value, and tape B
+    /// holds the first chain value.
+    pub fn to_tapes(&self) -> (Vec<u128>, Vec<u128>) {
+        let mut a = Vec::with_capacity(self.chains.len() * 2);
+        let mut b = Vec::with_capacity(self.chains.len());
+        for (&d, chain) in self.digits.iter().zip(self.chains.iter()) {
+            a.push(d);
+            a.push(chain[1]);
+            b.push(chain[0]);
+        }
+        return (a, b);
+    }
+
+    /// Returns binary digits of the message followed by binary digits of the checksum.
+    pub fn digits(&self) -> &[u128] {
+        return &self.digits;
+    }
+
+    /// Returns chain values of the signature.
+    pub fn chains(&self) -> &[[u128; 2]] {
+        return &self.chains;
+    }
+
+    #[cfg(test)]
+    pub fn set_digit(&mut self, index: usize, value: u128) {
+        self.digits[index] = value;
+    }
+
+    #[cfg(test)]
+    pub fn set_chain(&mut self, index: usize, value: [u128; 2]) {
+        self.chains[index] = value;
+    }
+}
+
+// HELPER FUNCTIONS
+// ================================================================================================
+
+/// Returns the number of bits needed to encode the checksum of a message of `message_bits` bits;
+/// the checksum is the number of 0 bits in the message, and so it is at most `message_bits`.
+pub fn checksum_bits(message_bits: u32) -> u32 {
+    return 32 - message_bits.leading_zeros();
+}
+
+/// Returns the weight with which the digit at the specified `index` contributes to the value
+/// accumulated by `stdlib::ots_verify()` routine. Message digits contribute their place value
+/// plus 2^n, and checksum digits contribute their place value scaled by 2^n; for a valid
+/// signature the accumulated value is therefore m + n * 2^n.
+pub fn digit_weight(message_bits: u32, index: usize) -> u128 {
+    let n = message_bits as usize;
+    if index < n {
+        return (1u128 << (n - 1 - index)) + (1u128 << n);
+    }
+    else {
+        let c = checksum_bits(message_bits) as usize;
+        return 1u128 << (n + c - 1 - (index - n));
+    }
+}
+
+fn num_chains(message_bits: u32) -> usize {
+    return (message_bits + checksum_bits(message_bits)) as usize;
+}
+
+fn get_digits(message: u128, message_bits: u32) -> Vec<u128> {
+    let n = message_bits;
+    assert!(message >> n == 0, "message {} does not fit into {} bits", message, n);
+
+    let mut digits = Vec::with_capacity(num_chains(n));
+    for i in 0..n {
+        digits.push((message >> (n - 1 - i)) & 1);
+    }
+
+    let c = checksum_bits(n);
+    let checksum = n as u128 - digits.iter().sum::<u128>();
+    for i in 0..c {
+        digits.push((checksum >> (c - 1 - i)) & 1);
+    }
+
+    return digits;
+}
+
+/// Hashes values the same way hash.n assembly instruction hashes top n values of the stack:
+/// `values` and the result are listed in the order in which they appear on the stack.
+fn hash_stack(values: &[u128]) -> [u128; 2] {
+    let mut values = values.to_vec();
+    values.reverse();
+    let digest = hasher::digest(&values);
+    return [digest[1], digest[0]];
+}
+
+// TESTS
+// ================================================================================================
+#[cfg(test)]
+mod tests {
+
+    use super::{ SecretKey, checksum_bits, digit_weight };
+
+    #[test]
+    fn sign_verify() {
+        let key = SecretKey::new(16, [1; 32]);
+        let public_key = key.public_key();
+
+        let signature = key.sign(12345);
+        assert_eq!(16 + checksum_bits(16) as usize, signature.digits().len());
+        assert!(signature.verify(12345, public_key));
+        assert!(!signature.verify(12344, public_key));
+
+        let other_key = SecretKey::new(16, [2; 32]);
+        assert!(!signature.verify(12345, other_key.public_key()));
+    }
+
+    #[test]
+    fn accumulated_digits() {
+        let n = 16;
+        let key = SecretKey::new(n, [1; 32]);
+        let message = 0xA5F0;
+        let signature = key.sign(message);
+
+        let mut acc = 0;
+        for (i, &d) in signature.digits().iter().enumerate() {
+            acc += d * digit_weight(n, i);
+        }
+        assert_eq!(message + (n as u128) * (1 << n), acc);
+    }
+}
@@ -15,4 +15,49 @@ pub struct Example {
     pub num_outputs     : usize,
     pub options         : ProofOptions,
     pub expected_result : Vec<u128>
-}
\ No newline at end of file
+}
+
+// TESTS
+// ================================================================================================
+#[cfg(test)]
+mod tests {
+
+    use distaff::{ Program, hash_blocks };
+
+    #[test]
+    fn json_round_trip() {
+        let args = vec![String::from("example")];
+        let examples = vec![
+            super::collatz::get_example(&args),
+            super::comparison::get_example(&args),
+            super::conditional::get_example(&[String::from("conditional"), String::from("1")]),
+            super::fibonacci::get_example(&args),
+            super::merkle::get_example(&args),
+            super::range::get_example(&args),
+        ];
+
+        for example in examples {
+            let json = example.program.to_json();
+            let program = Program::from_json(&json).unwrap();
+            assert_eq!(example.program.hash(), program.hash());
+            assert_eq!(format!("{:?}", example.program), format!("{:?}", program));
+        }
+    }
+
+    #[test]
+    fn program_hash_from_blocks() {
+        let args = vec![String::from("example")];
+        let examples = vec![
+            super::collatz::get_example(&args),
+            super::comparison::get_example(&args),
+            super::conditional::get_example(&[String::from("conditional"), String::from("1")]),
+            super::fibonacci::get_example(&args),
+            super::merkle::get_example(&args),
+            super::range::get_example(&args),
+        ];
+
+        for example in examples {
+            assert_eq!(*example.program.hash(), hash_blocks(example.program.root().body()));
+        }
+    }
+}
@@ -1,6 +1,8 @@
 use log::debug;
 use std::ops::Range;
 use std::time::Instant;
+use std::convert::TryFrom;
+use crossbeam_utils::thread;
 
 #[cfg(test)]
 mod tests;
@@ -10,58 +12,303 @@ mod tests;
 pub mod crypto;
 pub mod math;
 pub mod utils;
+pub use crypto::HashFunction;
+
+#[cfg(any(test, feature = "coverage"))]
+pub mod testing;
 
 mod stark;
-pub use stark::{ StarkProof, ProofOptions };
+pub use stark::{
+    StarkProof, ProofSizeReport, ProofOptions, Minimize, Assertion, AssertionStep, Statement, link_assertions,
+    ProverCheckpoint, ProverPhase, CheckpointFn, resume, ProvingTimings, TraceRegion, ConstraintDegrees,
+    TraceTable, TraceLayout, RegisterGroup, SessionProof, VerificationError, VerificationItem, Commitment, SecurityPolicy };
 
 mod processor;
-pub use processor::{ OpCode, OpHint };
+pub use processor::{ OpCode, OpHint, HintKind, ExecutionError };
 
 mod programs;
-pub use programs::{ Program, ProgramInputs, assembly, blocks };
+pub use programs::{
+    Program, ProgramBuilder, ProgramInputs, ProgramInputsBuilder, WitnessRecord, TapeId, StackView, ProgramStats, LoopBounds, analyze, commit_public_inputs, compute_merkle_root, pack_bytes,
+    get_procedure_paths, get_proc_path, selector_inputs, hash_blocks, assembly, blocks, stdlib };
 
 // EXECUTOR
 // ================================================================================================
 
+/// Executes the specified `program` without generating a proof of execution, and returns the
+/// result together with the number of steps executed by the VM, excluding the steps which pad
+/// the execution trace to a power of 2. This is useful for checking program logic and for
+/// estimating the length of the execution trace before proving.
+///
+/// Returns the same outputs as `execute()`, and fails on the same errors.
+pub fn run(program: &Program, inputs: &ProgramInputs, num_outputs: usize) -> Result<(Vec<u128>, usize), ExecutionError>
+{
+    check_num_outputs(num_outputs)?;
+
+    let (mut trace, ctx_depth, loop_depth, step_count) = processor::execute_and_count(program, inputs)?;
+    widen_stack(&mut trace, ctx_depth, loop_depth, num_outputs);
+
+    // copy the user stack state at the last step to return as output
+    let stack_depth = stark::TraceState::compute_stack_depth(trace.len(), ctx_depth, loop_depth);
+    let mut last_state = stark::TraceState::new(ctx_depth, loop_depth, stack_depth);
+    last_state.update_from_trace(&trace, trace[0].len() - 1);
+    let outputs = last_state.user_stack()[..num_outputs].to_vec();
+
+    return Ok((outputs, step_count));
+}
+
 /// Executes the specified `program` and returns the result together with a STARK-based proof of execution.
 /// 
 /// * `inputs` specifies the initial stack state and provides secret input tapes;
 /// * `num_outputs` specifies the number of elements from the top of the stack to be returned;
-pub fn execute(program: &Program, inputs: &ProgramInputs, num_outputs: usize, options: &ProofOptions) -> (Vec<u128>, StarkProof)
+///
+/// Returns an error if the program cannot be executed against the specified inputs (e.g. an
+/// assertion fails or a secret input tape runs out of values).
+pub fn execute(program: &Program, inputs: &ProgramInputs, num_outputs: usize, options: &ProofOptions) -> Result<(Vec<u128>, StarkProof), ExecutionError>
+{
+    return execute_with_assertions(program, inputs, num_outputs, &[], options);
+}
+
+/// Executes the specified `program` and returns the result together with a STARK-based proof of
+/// execution; in addition to the outputs, the proof attests that all `assertions` hold against
+/// the first or the last state of the user stack.
+pub fn execute_with_assertions(program: &Program, inputs: &ProgramInputs, num_outputs: usize, assertions: &[Assertion], options: &ProofOptions) -> Result<(Vec<u128>, StarkProof), ExecutionError>
 {
-    assert!(num_outputs <= MAX_OUTPUTS, 
-        "cannot produce more than {} outputs, but requested {}", MAX_OUTPUTS, num_outputs);
+    let (outputs, proof, _, _) = execute_program(program, inputs, num_outputs, assertions, options, false)?;
+    return Ok((outputs, proof));
+}
+
+/// Executes the specified `program` in the same way as `execute()`, and also returns time spent
+/// in each phase of proof generation together with the most expensive regions of the program.
+pub fn execute_with_timings(program: &Program, inputs: &ProgramInputs, num_outputs: usize, options: &ProofOptions) -> Result<(Vec<u128>, StarkProof, ProvingTimings), ExecutionError>
+{
+    let (outputs, proof, timings, _) = execute_program(program, inputs, num_outputs, &[], options, false)?;
+    return Ok((outputs, proof, timings));
+}
+
+/// Executes the specified `program` in the same way as `execute()`, and also returns a record of
+/// all inputs consumed by the program. Executing the program against inputs built via
+/// `ProgramInputs::from_witness_record()` reproduces exactly the same execution trace.
+pub fn execute_and_record(program: &Program, inputs: &ProgramInputs, num_outputs: usize, options: &ProofOptions) -> Result<(Vec<u128>, StarkProof, WitnessRecord), ExecutionError>
+{
+    let (outputs, proof, _, record) = execute_program(program, inputs, num_outputs, &[], options, true)?;
+    return Ok((outputs, proof, record.unwrap()));
+}
+
+/// Executes the procedure with the specified index of a `library` program (see
+/// `get_procedure_paths()`) and returns a statement about the call together with a STARK-based
+/// proof of execution. The path to the procedure is recorded in the statement (see
+/// `Statement::set_proc_path()`), and so the proof is verified against the hash of the library
+/// via `verify_statement()`.
+///
+/// Returns an error if the `library` is not a library program, or if it does not contain a
+/// procedure with the specified index.
+pub fn execute_procedure(library: &Program, procedure: usize, inputs: &ProgramInputs, num_outputs: usize, options: &ProofOptions) -> Result<(Statement, StarkProof), ExecutionError>
+{
+    let path = programs::get_proc_path(library, procedure)?;
+    return call_procedure(library, &path, inputs, num_outputs, options);
+}
+
+/// Executes a sequence of calls to procedures of a `library` program (see `get_procedure_paths()`)
+/// and proves each call. Every call specifies the index of the procedure, inputs to the procedure,
+/// and the number of outputs to return. Returns statements about all calls, together with a
+/// proof of the session; fails on the same errors as `execute_procedure()`.
+pub fn prove_session(library: &Program, calls: &[(usize, ProgramInputs, usize)], options: &ProofOptions) -> Result<(Vec<Statement>, SessionProof), ExecutionError>
+{
+    let mut statements = Vec::with_capacity(calls.len());
+    let mut session = SessionProof::new();
+    for (procedure, inputs, num_outputs) in calls.iter() {
+        let path = programs::get_proc_path(library, *procedure)?;
+        let (statement, proof) = call_procedure(library, &path, inputs, *num_outputs, options)?;
+        statements.push(statement);
+        session.push(&path, proof);
+    }
+
+    return Ok((statements, session));
+}
+
+/// Executes the procedure of a `library` program with the specified `path` by placing the
+/// procedure selector at the top of the stack.
+fn call_procedure(library: &Program, path: &[bool], inputs: &ProgramInputs, num_outputs: usize, options: &ProofOptions) -> Result<(Statement, StarkProof), ExecutionError>
+{
+    let mut statement = Statement::new(library.hash(), inputs.get_public_inputs(), &[]);
+    statement.set_proc_path(path);
+    let inputs = inputs.with_public_inputs(&statement.stack_inputs());
+
+    let (outputs, proof) = execute(library, &inputs, num_outputs, options)?;
+    statement.outputs = outputs;
+    return Ok((statement, proof));
+}
+
+/// Executes the specified `program` against each set of `inputs`, and returns outputs of every
+/// execution together with a single STARK-based proof of all executions. The proof is verified
+/// against the list of (public inputs, outputs) pairs via `verify_batch()`.
+///
+/// * `inputs` specifies initial stack state and secret input tapes for each execution; no more
+///   than `MAX_BATCH_SIZE` executions can be proven together;
+/// * `num_outputs` specifies the number of elements from the top of the stack to be returned
+///   from each execution;
+pub fn execute_batch(program: &Program, inputs: &[ProgramInputs], num_outputs: usize, options: &ProofOptions) -> Result<(Vec<Vec<u128>>, StarkProof), ExecutionError>
+{
+    check_num_outputs(num_outputs)?;
+    assert!(inputs.len() > 0 && inputs.len() <= MAX_BATCH_SIZE,
+        "a batch must contain between 1 and {} executions, but {} were requested", MAX_BATCH_SIZE, inputs.len());
+
+    // execute the program against every set of inputs, and stack the resulting execution
+    // traces on top of each other
+    let now = Instant::now();
+    let traces = inputs.iter().map(|inputs| processor::execute(program, inputs)).collect::<Result<_, _>>()?;
+    let (mut trace, ctx_depth, loop_depth, mut steps) = stark::stitch_traces(traces);
+    widen_stack(&mut trace, ctx_depth, loop_depth, num_outputs);
+    pad_trace(&mut trace, options.min_trace_length());
+    steps.last_mut().unwrap().end = trace[0].len();
+    let trace = stark::TraceTable::new(trace, ctx_depth, loop_depth, options.extension_factor());
+    debug!("Generated execution trace of {} registers and {} steps for {} executions in {} ms",
+        trace.register_count(),
+        trace.unextended_length(),
+        inputs.len(),
+        now.elapsed().as_millis());
+
+    let mut outputs = Vec::with_capacity(inputs.len());
+    let mut segments = Vec::with_capacity(inputs.len());
+    for (steps, inputs) in steps.into_iter().zip(inputs.iter()) {
+        let last_state = trace.get_state(steps.end - 1);
+        check_last_state(program, &last_state)?;
+
+        let result = last_state.user_stack()[..num_outputs].to_vec();
+        segments.push(stark::TraceSegment::from_trace(&trace, steps, inputs.get_public_inputs(), &result));
+        outputs.push(result);
+    }
+
+    // generate STARK proof
+    // depths are bounded by the VM and the number of operations by check_last_state(), and so
+    // the trace always fits into a proof
+    let (proof, _) = stark::prove_batch(trace, segments, options).expect("execution trace cannot be proven");
+    return Ok((outputs, proof));
+}
+
+fn execute_program(program: &Program, inputs: &ProgramInputs, num_outputs: usize, assertions: &[Assertion], options: &ProofOptions, record_witness: bool) -> Result<(Vec<u128>, StarkProof, ProvingTimings, Option<WitnessRecord>), ExecutionError>
+{
+    check_num_outputs(num_outputs)?;
 
     // execute the program to create an execution trace
     let now = Instant::now();
-    let (trace, ctx_depth, loop_depth) = processor::execute(program, inputs);
-    let mut trace = stark::TraceTable::new(trace, ctx_depth, loop_depth, options.extension_factor());
-    debug!("Generated execution trace of {} registers and {} steps in {} ms",
+    let (mut trace, ctx_depth, loop_depth, record) = if record_witness {
+        let (trace, ctx_depth, loop_depth, record) = processor::execute_and_record(program, inputs)?;
+        (trace, ctx_depth, loop_depth, Some(record))
+    }
+    else {
+        let (trace, ctx_depth, loop_depth) = processor::execute(program, inputs)?;
+        (trace, ctx_depth, loop_depth, None)
+    };
+    widen_stack(&mut trace, ctx_depth, loop_depth, num_outputs);
+    pad_trace(&mut trace, options.min_trace_length());
+    let trace = stark::TraceTable::new(trace, ctx_depth, loop_depth, options.extension_factor());
+    debug!("Generated execution trace of {} registers and {} steps ({} padding steps) in {} ms",
         trace.register_count(),
         trace.unextended_length(),
+        trace.padding_length(),
         now.elapsed().as_millis());
+    debug!("Execution trace layout: {}", trace.layout());
 
     // copy the user stack state the the last step to return as output
     let last_state = trace.get_last_state();
     let outputs = last_state.user_stack()[..num_outputs].to_vec();
+    check_last_state(program, &last_state)?;
+
+    // make sure all assertions are valid and hold against the execution trace
+    if let Some(i) = stark::find_invalid_assertion(assertions, trace.stack_depth()) {
+        return Err(ExecutionError::InvalidAssertion {
+            register    : assertions[i].register,
+            at          : assertions[i].step,
+            stack_depth : trace.stack_depth(),
+        });
+    }
+    let first_state = trace.get_state(0);
+    for assertion in assertions.iter() {
+        let state = match assertion.step {
+            AssertionStep::First => &first_state,
+            AssertionStep::Last  => &last_state,
+        };
+        if !assertion.holds_at(state) {
+            return Err(ExecutionError::UnsatisfiedAssertion {
+                register    : assertion.register,
+                at          : assertion.step,
+                expected    : assertion.value,
+                actual      : state.user_stack()[assertion.register],
+            });
+        }
+    }
+
+    // generate STARK proof
+    // depths are bounded by the VM and the number of operations by check_last_state(), and so
+    // the trace always fits into a proof
+    let (proof, timings) = stark::prove(trace, inputs.get_public_inputs(), &outputs, assertions, options)
+        .expect("execution trace cannot be proven");
+
+    #[cfg(feature = "coverage")]
+    testing::coverage::record_proof(program.hash());
+
+    return Ok((outputs, proof, timings, record));
+}
 
-    // make sure number of executed operations was sufficient
-    assert!(last_state.op_counter() as usize >= MIN_TRACE_LENGTH,
-        "a program must consist of at least {} operation, but only {} were executed",
-        MIN_TRACE_LENGTH,
-        last_state.op_counter());
+/// Makes sure no more than `MAX_OUTPUTS` outputs are requested.
+fn check_num_outputs(num_outputs: usize) -> Result<(), ExecutionError> {
+    if num_outputs > MAX_OUTPUTS {
+        return Err(ExecutionError::TooManyOutputs { requested: num_outputs, max: MAX_OUTPUTS });
+    }
+    return Ok(());
+}
+
+/// Makes sure the number of executed operations was sufficient and can be included in a proof,
+/// and that the program hash generated by the VM matches the hash of the program.
+fn check_last_state(program: &Program, last_state: &stark::TraceState) -> Result<(), ExecutionError> {
+    if u32::try_from(last_state.op_counter()).is_err() {
+        return Err(ExecutionError::TooManyOperations {
+            executed: last_state.op_counter(),
+            max     : u32::MAX as u128,
+        });
+    }
+
+    if (last_state.op_counter() as usize) < MIN_TRACE_LENGTH {
+        return Err(ExecutionError::TooFewOperations {
+            executed: last_state.op_counter() as usize,
+            min     : MIN_TRACE_LENGTH,
+        });
+    }
 
-    // make sure program hash generated by the VM matches the hash of the program
     let program_hash = utils::as_bytes(last_state.program_hash());
-    assert!(program.hash() == program_hash,
-        "expected program hash {} does not match trace hash {}",
-        hex::encode(program.hash()),
-        hex::encode(program_hash));
+    if program.hash() != program_hash {
+        let mut actual = [0u8; 32];
+        actual.copy_from_slice(program_hash);
+        return Err(ExecutionError::ProgramHashMismatch { expected: *program.hash(), actual });
+    }
+    return Ok(());
+}
 
-    // generate STARK proof
-    let proof = stark::prove(&mut trace, inputs.get_public_inputs(), &outputs, options);
+/// Adds user stack registers to the trace so that the top `num_outputs` values of the stack
+/// can be bound to the proof. Registers beyond the depth of the stack are always zeros, and so
+/// the added registers are filled with zeros; every added register increases the size of the
+/// proof, but this happens only for programs which return more than `MIN_STACK_DEPTH` outputs.
+fn widen_stack(trace: &mut Vec<Vec<u128>>, ctx_depth: usize, loop_depth: usize, num_outputs: usize) {
+    let stack_depth = stark::TraceState::compute_stack_depth(trace.len(), ctx_depth, loop_depth);
+    if num_outputs > std::cmp::max(stack_depth, MIN_STACK_DEPTH) {
+        // stack depth registers are the last registers of the trace
+        let depth_register = trace.len() - NUM_DEPTH_BITS;
+        let trace_length = trace[0].len();
+        for _ in stack_depth..num_outputs {
+            trace.insert(depth_register, vec![math::field::ZERO; trace_length]);
+        }
+    }
+}
 
-    return (outputs, proof);
+/// Pads register traces to `min_length` steps by repeating the last state of the trace.
+fn pad_trace(trace: &mut Vec<Vec<u128>>, min_length: usize) {
+    for register in trace.iter_mut() {
+        if register.len() < min_length {
+            let last_value = register[register.len() - 1];
+            register.resize(min_length, last_value);
+        }
+    }
 }
 
 // VERIFIER
@@ -69,9 +316,156 @@ pub fn execute(program: &Program, inputs: &ProgramInputs, num_outputs: usize, op
 
 /// Verifies that if a program with the specified `program_hash` is executed with the 
 /// provided `public_inputs` and some secret inputs, the result is equal to the `outputs`.
-pub fn verify(program_hash: &[u8; 32], public_inputs: &[u128], outputs: &[u128], proof: &StarkProof) -> Result<bool, String>
+pub fn verify(program_hash: &[u8; 32], public_inputs: &[u128], outputs: &[u128], proof: &StarkProof) -> Result<(), VerificationError>
 {
-    return stark::verify(program_hash, public_inputs, outputs, proof);
+    return verify_with_assertions(program_hash, public_inputs, outputs, &[], proof);
+}
+
+/// Verifies the proof in the same way as `verify()`, but only if parameters of the proof (see
+/// `StarkProof::options()`) provide at least `min_security` bits of security as reported by
+/// `ProofOptions::security_level(true)`; otherwise, the proof is rejected without verification.
+pub fn verify_with_min_security(program_hash: &[u8; 32], public_inputs: &[u128], outputs: &[u128], min_security: u32, proof: &StarkProof) -> Result<(), VerificationError>
+{
+    return verify_with_policy(program_hash, public_inputs, outputs, proof, &SecurityPolicy::new(min_security));
+}
+
+/// Verifies the proof in the same way as `verify()`, but only if parameters of the proof (see
+/// `StarkProof::options()`) satisfy the `policy`; otherwise, the proof is rejected without
+/// verification with `InsufficientSecurity` or `PolicyViolation` error.
+pub fn verify_with_policy(program_hash: &[u8; 32], public_inputs: &[u128], outputs: &[u128], proof: &StarkProof, policy: &SecurityPolicy) -> Result<(), VerificationError>
+{
+    stark::check_version(proof)?;
+    policy.check(proof.options())?;
+    return verify(program_hash, public_inputs, outputs, proof);
+}
+
+/// Verifies that if a program with the specified `program_hash` is executed with the provided
+/// `public_inputs` preceded by a commitment to these inputs (see
+/// `ProgramInputs::from_public_with_commitment()`), the result is equal to the `outputs`. The
+/// number of `public_inputs` is not limited: if the inputs do not fit onto the stack together
+/// with the commitment, the program is expected to start with the commitment only.
+pub fn verify_with_commitment(program_hash: &[u8; 32], public_inputs: &[u128], outputs: &[u128], proof: &StarkProof) -> Result<(), VerificationError>
+{
+    let public_inputs = programs::public_inputs_with_commitment(public_inputs);
+    return verify(program_hash, &public_inputs, outputs, proof);
+}
+
+/// Verifies that if a program with the specified `program_hash` is executed with the 
+/// provided `public_inputs` and some secret inputs, the result is equal to the `outputs`,
+/// and all `assertions` hold against the first or the last state of the user stack.
+pub fn verify_with_assertions(program_hash: &[u8; 32], public_inputs: &[u128], outputs: &[u128], assertions: &[Assertion], proof: &StarkProof) -> Result<(), VerificationError>
+{
+    let result = stark::verify(program_hash, public_inputs, outputs, assertions, proof);
+
+    #[cfg(feature = "coverage")]
+    if result.is_ok() {
+        testing::coverage::record_verification(program_hash);
+    }
+
+    return result;
+}
+
+/// Verifies that a program with the specified `program_hash` was executed with the provided
+/// `public_inputs` and some secret inputs, and returns the top `num_outputs` outputs carried in
+/// the `proof`; the outputs are returned only if the proof is valid.
+pub fn verify_and_extract(program_hash: &[u8; 32], public_inputs: &[u128], num_outputs: usize, proof: &StarkProof) -> Result<Vec<u128>, VerificationError>
+{
+    let outputs = proof.outputs();
+    if outputs.len() < num_outputs {
+        return Err(VerificationError::StatementMismatch(format!(
+            "proof contains {} outputs, but {} were requested", outputs.len(), num_outputs)));
+    }
+
+    verify(program_hash, public_inputs, outputs, proof)?;
+    return Ok(outputs[..num_outputs].to_vec());
+}
+
+/// Verifies many proofs at once, and returns the result of verifying each item; the results are
+/// the same as the ones returned by `verify()` for each item separately. Items are verified in
+/// parallel, and work which depends only on parameters of the execution trace (e.g. extending
+/// round constants for constraint evaluation) is shared between proofs with the same parameters.
+pub fn verify_many(items: &[VerificationItem]) -> Vec<Result<(), VerificationError>>
+{
+    let results = stark::verify_many(items);
+
+    #[cfg(feature = "coverage")]
+    for (item, result) in items.iter().zip(results.iter()) {
+        if result.is_ok() {
+            testing::coverage::record_verification(item.program_hash);
+        }
+    }
+
+    return results;
+}
+
+/// Verifies that a program with the specified `program_hash` was executed several times, and
+/// that for every (public inputs, outputs) pair in the `statement`, executing the program with
+/// the public inputs and some secret inputs produced the outputs.
+pub fn verify_batch(program_hash: &[u8; 32], statement: &[(Vec<u128>, Vec<u128>)], proof: &StarkProof) -> Result<(), VerificationError>
+{
+    return stark::verify_batch(program_hash, statement, proof);
+}
+
+/// Verifies that the `proof` attests to the specified `statement`.
+pub fn verify_statement(statement: &Statement, proof: &StarkProof) -> Result<(), VerificationError>
+{
+    if proof.stack_depth() < statement.stack_depth {
+        return Err(VerificationError::StatementMismatch(format!(
+            "verification of stack depth failed: expected at least {} registers, but was {}",
+            statement.stack_depth, proof.stack_depth())));
+    }
+    if proof.trace_length() < statement.min_trace_length {
+        return Err(VerificationError::StatementMismatch(format!(
+            "verification of trace length failed: expected at least {} steps, but was {}",
+            statement.min_trace_length, proof.trace_length())));
+    }
+    return verify_with_assertions(&statement.program_hash, &statement.stack_inputs(), &statement.outputs, &statement.assertions, proof);
+}
+
+/// Verifies proofs of two consecutive execution segments; in addition to verifying each proof
+/// against its statement, makes sure that the `next` statement is linked to the `prev` statement
+/// (see `link_assertions()`).
+pub fn verify_linked(prev: &Statement, prev_proof: &StarkProof, next: &Statement, next_proof: &StarkProof) -> Result<(), VerificationError>
+{
+    stark::check_link(prev, next).map_err(VerificationError::StatementMismatch)?;
+    verify_statement(prev, prev_proof)?;
+    return verify_statement(next, next_proof);
+}
+
+/// Verifies proofs of all calls made in a session against a library program with the
+/// specified `library_hash`; calls are verified in parallel. Returns the result of verifying each
+/// call, or an error if the `proof` does not match the `statements`.
+pub fn verify_session(library_hash: &[u8; 32], statements: &[Statement], proof: &SessionProof) -> Result<Vec<Result<(), VerificationError>>, VerificationError>
+{
+    proof.validate().map_err(VerificationError::MalformedProof)?;
+    if statements.len() != proof.num_calls() {
+        return Err(VerificationError::StatementMismatch(format!(
+            "expected {} statements, but received {}", proof.num_calls(), statements.len())));
+    }
+
+    let results = thread::scope(|s| {
+        let handles: Vec<_> = statements.iter().enumerate()
+            .map(|(i, statement)| s.spawn(move |_| verify_call(library_hash, statement, proof.procedure_path(i), proof.proof(i))))
+            .collect();
+        handles.into_iter().map(|handle| handle.join().unwrap()).collect()
+    }).unwrap();
+
+    return Ok(results);
+}
+
+fn verify_call(library_hash: &[u8; 32], statement: &Statement, path: &[bool], proof: &StarkProof) -> Result<(), VerificationError>
+{
+    if statement.program_hash != *library_hash {
+        return Err(VerificationError::ProgramHashMismatch {
+            expected: *library_hash,
+            actual  : statement.program_hash,
+        });
+    }
+    if statement.proc_path != path {
+        return Err(VerificationError::StatementMismatch(String::from(
+            "verification of procedure path failed: statement does not match the session proof")));
+    }
+    return verify_statement(statement, proof);
 }
 
 // GLOBAL CONSTANTS
@@ -79,6 +473,7 @@ pub fn verify(program_hash: &[u8; 32], public_inputs: &[u128], outputs: &[u128],
 
 pub const MAX_CONTEXT_DEPTH : usize = 16;
 pub const MAX_LOOP_DEPTH    : usize = 8;
+pub const MAX_BATCH_SIZE    : usize = 8;    // bounded by the max degree of transition constraints
 const MIN_TRACE_LENGTH      : usize = 16;
 const MAX_REGISTER_COUNT    : usize = 128;
 const MIN_EXTENSION_FACTOR  : usize = 16;
@@ -109,9 +504,9 @@ const HACC_NUM_ROUNDS       : usize = 14;
 // DECODER LAYOUT
 // ------------------------------------------------------------------------------------------------
 //
-//  ctr ╒═════ sponge ══════╕╒═══ cf_ops ══╕╒═══════ ld_ops ═══════╕╒═ hd_ops ╕╒═ ctx ══╕╒═ loop ═╕
-//   0    1    2    3    4    5    6    7    8    9    10   11   12   13   14   15   ..   ..   ..
-// ├────┴────┴────┴────┴────┴────┴────┴────┴────┴────┴────┴────┴────┴────┴────┴────┴────┴────┴────┤
+//  ctr ╒═════ sponge ══════╕╒═══ cf_ops ══╕╒═══════ ld_ops ═══════╕╒═ hd_ops ╕╒═══ ld_prods ═══╕╒═ ctx ══╕╒═ loop ═╕
+//   0    1    2    3    4    5    6    7    8    9    10   11   12   13   14   15   ..   22   23   ..   ..   ..
+// ├────┴────┴────┴────┴────┴────┴────┴────┴────┴────┴────┴────┴────┴────┴────┴────┴────┴────┴────┴────┴────┴────┤
 
 const NUM_CF_OP_BITS        : usize = 3;
 const NUM_LD_OP_BITS        : usize = 5;
@@ -121,19 +516,28 @@ const NUM_CF_OPS            : usize = 8;
 const NUM_LD_OPS            : usize = 32;
 const NUM_HD_OPS            : usize = 4;
 
+// products of ld_op bit pairs (0, 1) and (2, 3); these are kept in dedicated registers to
+// reduce the degree of ld_op flags from 5 to 3
+const NUM_LD_OP_PRODS       : usize = 8;
+
 const OP_COUNTER_IDX        : usize = 0;
 const SPONGE_RANGE          : Range<usize> = Range { start:  1, end:  5 };
 const CF_OP_BITS_RANGE      : Range<usize> = Range { start:  5, end:  8 };
 const LD_OP_BITS_RANGE      : Range<usize> = Range { start:  8, end: 13 };
 const HD_OP_BITS_RANGE      : Range<usize> = Range { start: 13, end: 15 };
+const LD_OP_PRODS_RANGE     : Range<usize> = Range { start: 15, end: 23 };
 
 // STACK LAYOUT
 // ------------------------------------------------------------------------------------------------
 //
-// ╒═══════════════════ user registers ════════════════════════╕
-//    0      1    2    .................................    31
-// ├─────┴─────┴─────┴─────┴─────┴─────┴─────┴─────┴─────┴─────┤
+// ╒═══════════════════ user registers ════════════════════════╕╒══ depth bits ══╕
+//    0      1    2    .................................    31    0   ...   5
+// ├─────┴─────┴─────┴─────┴─────┴─────┴─────┴─────┴─────┴─────┤├───┴─────┴─────┤
 
 pub const MAX_PUBLIC_INPUTS : usize = 8;
-pub const MAX_OUTPUTS       : usize = MAX_PUBLIC_INPUTS;
-pub const MAX_STACK_DEPTH   : usize = 32;
\ No newline at end of file
+pub const MAX_STACK_DEPTH   : usize = 32;
+pub const MAX_OUTPUTS       : usize = MAX_STACK_DEPTH;
+
+// logical depth of the stack is kept in binary form so that it cannot go below 0; 6 bits are
+// enough to hold any depth up to MAX_STACK_DEPTH
+const NUM_DEPTH_BITS        : usize = 6;
\ No newline at end of file
@@ -1,19 +1,38 @@
-use std::{ env, io::Write, time::Instant };
-use distaff::{ self, StarkProof };
+use std::{ env, fs, io::Write, time::Instant };
+use distaff::{ self, Program, StarkProof, ProofOptions, Minimize, assembly, utils::fmt::{ self as element_fmt, Radix } };
 
 mod examples;
 use examples::{ Example };
 
 fn main() {
 
+    // compile a program from a file and print it out, if requested
+    let args: Vec<String> = env::args().collect();
+    if args.len() > 1 && args[1] == "compile" {
+        let (emit, args) = take_flag_value(args, "--emit");
+        assert!(args.len() == 3, "usage: distaff compile <program file> [--emit json|asm]");
+        let program = load_program(&args[2]);
+        match emit.as_deref() {
+            Some("json")        => println!("{}", program.to_json()),
+            None | Some("asm")  => println!("{:?}", program),
+            Some(value)         => panic!("'{}' is not a valid value for --emit", value),
+        }
+        return;
+    }
+
     // configure logging
     env_logger::Builder::new()
         .format(|buf, record| writeln!(buf, "{}", record.args()))
         .filter_level(log::LevelFilter::Debug).init();
 
-    // determine the example to run based on command-line inputs
+    // determine the example to run based on command-line inputs; --stats, --target-security,
+    // --minimize, and --radix flags can be passed anywhere in the list of arguments
     let ex: Example;
-    let args: Vec<String> = env::args().collect();
+    let print_stats = args.iter().any(|arg| arg == "--stats");
+    let args: Vec<String> = args.into_iter().filter(|arg| arg != "--stats").collect();
+    let (target_security, args) = take_flag_value(args, "--target-security");
+    let (minimize, args) = take_flag_value(args, "--minimize");
+    let (radix, args) = take_flag_value(args, "--radix");
     if args.len() < 2 {
         ex = examples::fibonacci::get_example(&args);
     }
@@ -28,31 +47,94 @@ fn main() {
             _ => panic!("Could not find example program for '{}'", args[1])
         }
     }
-    let Example { program, inputs, num_outputs, options, expected_result } = ex;
+    let Example { program, inputs, num_outputs, mut options, expected_result } = ex;
     println!("--------------------------------");
 
+    // set the notation in which field elements are printed, if requested
+    let radix: Radix = match radix {
+        Some(radix) => radix.parse().unwrap_or_else(|err: String| panic!("{}", err)),
+        None => Radix::Dec,
+    };
+    element_fmt::set_display_radix(radix);
+
+    // tune proof options for the target security level, if requested
+    if let Some(target_security) = target_security {
+        let target_security: u32 = target_security.parse().expect("target security is invalid");
+        let minimize = match minimize.as_deref() {
+            None | Some("size") => Minimize::ProofSize,
+            Some("time")        => Minimize::ProverTime,
+            Some(value)         => panic!("'{}' is not a valid value for --minimize", value),
+        };
+        let trace_length = program.estimate_trace_length(None).start;
+        options = ProofOptions::tune(target_security, trace_length, minimize);
+        println!("Tuned proof options: extension factor {}, {} queries, grinding factor {}",
+            options.extension_factor(), options.num_queries(), options.grinding_factor());
+        println!("--------------------------------");
+    }
+
+    // print program stats, if requested
+    if print_stats {
+        println!("{}", program.stats(None));
+        println!("--------------------------------");
+    }
+
     // execute the program and generate the proof of execution
     let now = Instant::now();
-    let (outputs, proof) = distaff::execute(&program, &inputs, num_outputs, &options);
+    let (outputs, proof, timings) = distaff::execute_with_timings(&program, &inputs, num_outputs, &options)
+        .unwrap_or_else(|err| panic!("program execution failed: {}", err));
     println!("--------------------------------");
+    if print_stats {
+        println!("{}", timings);
+        println!("--------------------------------");
+    }
     println!("Executed program with hash {} in {} ms", 
         hex::encode(program.hash()),
         now.elapsed().as_millis());
-    println!("Program output: {:?}", outputs);
+    println!("Program output: {}", element_fmt::fmt_elements(&outputs, radix));
     assert_eq!(expected_result, outputs, "Program result was computed incorrectly");
 
     // serialize the proof to see how big it is
-    let proof_bytes = bincode::serialize(&proof).unwrap();
-    println!("Execution proof size: {} KB", proof_bytes.len() / 1024);
+    let proof_bytes = proof.to_bytes();
+    println!("Execution proof size: {} KB ({} KB with bincode)",
+        proof_bytes.len() / 1024, bincode::serialized_size(&proof).unwrap() / 1024);
+    if print_stats {
+        println!("{}", proof.size_breakdown());
+    }
     println!("Execution proof security: {} bits", options.security_level(true));
     println!("--------------------------------");
 
     // verify that executing a program with a given hash and given inputs
     // results in the expected output
-    let proof = bincode::deserialize::<StarkProof>(&proof_bytes).unwrap();
+    let proof = StarkProof::from_bytes(&proof_bytes).unwrap();
     let now = Instant::now();
     match distaff::verify(program.hash(), inputs.get_public_inputs(), &outputs, &proof) {
         Ok(_) => println!("Execution verified in {} ms", now.elapsed().as_millis()),
         Err(msg) => println!("Failed to verify execution: {}", msg)
     }
-}
\ No newline at end of file
+}
+
+/// Reads a program from the specified file; files with `.json` extension are expected to contain
+/// programs in JSON format, all other files are expected to contain assembly source code.
+fn load_program(path: &str) -> Program {
+    let source = fs::read_to_string(path).unwrap_or_else(|err| panic!("failed to read {}: {}", path, err));
+    let program = if path.ends_with(".json") {
+        Program::from_json(&source)
+    }
+    else {
+        assembly::compile(&source).map_err(|err| err.to_string())
+    };
+    return program.unwrap_or_else(|err| panic!("failed to compile {}: {}", path, err));
+}
+
+/// Removes `flag` and the value following it from the list of arguments, and returns the value.
+fn take_flag_value(mut args: Vec<String>, flag: &str) -> (Option<String>, Vec<String>) {
+    let value = match args.iter().position(|arg| arg == flag) {
+        Some(i) => {
+            assert!(i + 1 < args.len(), "value for {} flag is missing", flag);
+            args.remove(i);
+            Some(args.remove(i))
+        },
+        None => None,
+    };
+    return (value, args);
+}
@@ -6,6 +6,21 @@ use crate::math::field;
 const USIZE_BITS: usize = 0_usize.count_zeros() as usize;
 const MAX_LOOP: usize = 256;
 
+// TYPES AND INTERFACES
+// ================================================================================================
+
+/// Precomputed domain, twiddles and inverse twiddles for FFTs over a domain of a given size.
+/// Since twiddles are stored in permuted order, twiddles for any smaller power-of-two domain
+/// are a prefix of twiddles for the full domain, and so a single context built for the largest
+/// domain can be shared by FFTs over all smaller domains.
+#[derive(Clone, Debug, PartialEq)]
+pub struct FftContext {
+    domain          : Vec<u128>,
+    twiddles        : Vec<u128>,
+    inv_twiddles    : Vec<u128>,
+    inv_size        : u128,
+}
+
 // PUBLIC FUNCTIONS
 // ================================================================================================
 
@@ -78,6 +93,102 @@ pub fn permute(v: &mut [u128]) {
     }
 }
 
+/// Evaluates polynomial `p` over the domain of `p.len()` points using twiddles from `ctx`; the
+/// evaluation is done in-place, and the evaluations are left in natural order.
+pub fn evaluate_with_ctx(p: &mut [u128], ctx: &FftContext) {
+    fft_in_place(p, ctx.twiddles(p.len()), 1, 1, 0, 1);
+    permute(p);
+}
+
+/// Interpolates a polynomial from its evaluations `v` over the domain of `v.len()` points using
+/// inverse twiddles from `ctx`; the interpolation is done in-place, and `v` is updated with
+/// coefficients of the polynomial.
+pub fn interpolate_with_ctx(v: &mut [u128], ctx: &FftContext) {
+    fft_in_place(v, ctx.inv_twiddles(v.len()), 1, 1, 0, 1);
+    let inv_length = ctx.inv_size(v.len());
+    for e in v.iter_mut() {
+        *e = field::mul(*e, inv_length);
+    }
+    permute(v);
+}
+
+/// Evaluates polynomial `p` over the coset `offset * H`, where H is the group of `result.len()`
+/// roots of unity, and writes the evaluations into `result` in natural order. Unlike with other
+/// FFT functions, the polynomial may have more coefficients than there are points in the coset.
+pub fn evaluate_on_coset(p: &[u128], offset: u128, result: &mut [u128], ctx: &FftContext) {
+    // p(offset * x) for x in H is the same as q(x), where q(x) is p(offset * x) reduced
+    // modulo x^m - 1; the reduction just adds up coefficients with the same index mod m
+    let m = result.len();
+    result.iter_mut().for_each(|value| *value = field::ZERO);
+    let mut offset_power = field::ONE;
+    for (i, &coefficient) in p.iter().enumerate() {
+        result[i % m] = field::add(result[i % m], field::mul(coefficient, offset_power));
+        offset_power = field::mul(offset_power, offset);
+    }
+    evaluate_with_ctx(result, ctx);
+}
+
+// FFT CONTEXT IMPLEMENTATION
+// ================================================================================================
+impl FftContext {
+
+    /// Precomputes the domain of `size` roots of unity together with forward and inverse
+    /// twiddles for FFTs over this domain; `size` must be a power of 2.
+    pub fn new(size: usize) -> FftContext {
+        assert!(size.is_power_of_two() && size > 1, "domain size must be a power of 2 greater than 1");
+        let root = field::get_root_of_unity(size);
+        let domain = field::get_power_series(root, size);
+
+        // forward twiddles are the first half of the domain in permuted order; inverse twiddles
+        // are powers of the inverse root, which are the same values in reverse order
+        let mut twiddles = domain[..(size / 2)].to_vec();
+        permute(&mut twiddles);
+        let mut inv_twiddles: Vec<u128> = (0..(size / 2)).map(|i| domain[(size - i) % size]).collect();
+        permute(&mut inv_twiddles);
+
+        return FftContext {
+            domain          : domain,
+            twiddles        : twiddles,
+            inv_twiddles    : inv_twiddles,
+            inv_size        : field::inv(size as u128),
+        };
+    }
+
+    /// Returns the size of the largest domain supported by this context.
+    pub fn size(&self) -> usize {
+        return self.domain.len();
+    }
+
+    /// Returns all points of the largest domain supported by this context in natural order.
+    pub fn domain(&self) -> &[u128] {
+        return &self.domain;
+    }
+
+    /// Returns twiddles for FFT evaluation over a domain of the specified `size`.
+    pub fn twiddles(&self, size: usize) -> &[u128] {
+        self.check_size(size);
+        return &self.twiddles[..(size / 2)];
+    }
+
+    /// Returns inverse twiddles for FFT interpolation over a domain of the specified `size`.
+    pub fn inv_twiddles(&self, size: usize) -> &[u128] {
+        self.check_size(size);
+        return &self.inv_twiddles[..(size / 2)];
+    }
+
+    /// Returns the inverse of `size` in the field; this is the constant by which results of
+    /// FFT interpolation over a domain of `size` points are scaled.
+    pub fn inv_size(&self, size: usize) -> u128 {
+        self.check_size(size);
+        return field::mul(self.inv_size, (self.size() / size) as u128);
+    }
+
+    fn check_size(&self, size: usize) {
+        assert!(size.is_power_of_two() && size > 1 && size <= self.size(),
+            "domain size must be a power of 2 between 2 and {}, but was {}", self.size(), size);
+    }
+}
+
 // HELPER FUNCTIONS
 // ================================================================================================
 fn permute_index(size: usize, index: usize) -> usize {
@@ -155,4 +266,73 @@ mod tests {
         super::permute(&mut p);
         assert_eq!(expected, p);
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn fft_context() {
+        let ctx = super::FftContext::new(1 << 10);
+        assert_eq!(ctx, super::FftContext::new(1 << 10).clone());
+        assert_eq!(field::get_power_series(field::get_root_of_unity(1 << 10), 1 << 10), ctx.domain());
+
+        // twiddles for every smaller domain must match twiddles computed for that domain directly
+        for &size in [2, 4, 8, 16, 64, 256, 1024].iter() {
+            let root = field::get_root_of_unity(size);
+            assert_eq!(super::get_twiddles(root, size), ctx.twiddles(size));
+            assert_eq!(super::get_inv_twiddles(root, size), ctx.inv_twiddles(size));
+            assert_eq!(field::ONE, field::mul(ctx.inv_size(size), size as u128));
+
+            // evaluation and interpolation over the domain are inverses of each other
+            let poly = field::rand_vector(size);
+            let mut values = poly.clone();
+            super::evaluate_with_ctx(&mut values, &ctx);
+            let domain = field::get_power_series(root, size);
+            assert_eq!(domain.iter().map(|&x| polynom::eval(&poly, x)).collect::<Vec<u128>>(), values);
+            super::interpolate_with_ctx(&mut values, &ctx);
+            assert_eq!(poly, values);
+        }
+    }
+
+    #[test]
+    #[should_panic(expected = "domain size must be a power of 2 between 2 and 16, but was 32")]
+    fn fft_context_too_small() {
+        super::FftContext::new(16).twiddles(32);
+    }
+
+    #[test]
+    fn evaluate_on_coset() {
+        let ctx = super::FftContext::new(64);
+        let offset = ctx.domain()[3];
+        for &length in [8, 16, 20].iter() {
+            let poly = field::rand_vector(length);
+            let mut actual = vec![field::ZERO; 16];
+            super::evaluate_on_coset(&poly, offset, &mut actual, &ctx);
+            let expected: Vec<u128> = field::get_power_series(field::get_root_of_unity(16), 16).into_iter()
+                .map(|x| polynom::eval(&poly, field::mul(x, offset)))
+                .collect();
+            assert_eq!(expected, actual);
+        }
+    }
+
+    #[test]
+    fn cached_twiddles() {
+        // extend a number of registers in the same way as the prover does, once computing
+        // twiddles for every register and once using a shared context; results must be the same
+        let trace_length = 1 << 8;
+        let domain_size = trace_length * 32;
+        let ctx = super::FftContext::new(domain_size);
+        for _ in 0..16 {
+            let register = field::rand_vector(trace_length);
+
+            let mut expected = register.clone();
+            polynom::interpolate_fft(&mut expected, true);
+            expected.resize(domain_size, field::ZERO);
+            polynom::eval_fft(&mut expected, true);
+
+            let mut actual = register.clone();
+            super::interpolate_with_ctx(&mut actual, &ctx);
+            actual.resize(domain_size, field::ZERO);
+            super::evaluate_with_ctx(&mut actual, &ctx);
+
+            assert_eq!(expected, actual);
+        }
+    }
+}
@@ -13,6 +13,9 @@ pub const M: u128 = 340282366920938463463374557953744961537;
 // 2^40 root of unity
 pub const G: u128 = 23953097886125630542083529559205016746;
 
+// 2^128 - M
+const C: u128 = 45 * (1 << 40) - 1;
+
 // public constants
 pub const MODULUS: u128 = M;
 pub const RANGE: Range<u128> = Range { start: 0, end: M };
@@ -37,39 +40,24 @@ pub fn sub(a: u128, b: u128) -> u128 {
 /// Computes (a * b) % m; a and b are assumed to be valid field elements.
 pub fn mul(a: u128, b: u128) -> u128 {
 
-    let (x0, x1, x2) = mul_128x64(a, (b >> 64) as u64);         // x = a * b_hi
-    let (mut x0, mut x1, x2) = mul_reduce(x0, x1, x2);          // x = x - (x >> 128) * m
-    if x2 == 1 {
-        // if there was an overflow beyond 128 bits, subtract
-        // modulus from the result to make sure it fits into 
-        // 128 bits; this can potentially be removed in favor
-        // of checking overflow later
-        let (t0, t1) = sub_modulus(x0, x1);                     // x = x - m
-        x0 = t0; x1 = t1;
-    }
+    let (z_lo, z_hi) = mul_128x128(a, b);                       // z = a * b
 
-    let (y0, y1, y2) = mul_128x64(a, b as u64);                 // y = a * b_lo
-
-    let (mut y1, carry) = add64_with_carry(y1, x0, 0);          // y = y + (x << 64)
-    let (mut y2, y3) = add64_with_carry(y2, x1, carry);
-    if y3 == 1 {
-        // if there was an overflow beyond 192 bits, subtract
-        // modulus * 2^64 from the result to make sure it fits
-        // into 192 bits; this can potentially replace the
-        // previous overflow check (but needs to be proven)
-        let (t0, t1) = sub_modulus(y1, y2);                     // y = y - (m << 64)
-        y1 = t0; y2 = t1;
-    }
-    
-    let (mut z0, mut z1, z2) = mul_reduce(y0, y1, y2);          // z = y - (y >> 128) * m
+    // the modulus is 2^128 - C, and so z_hi * 2^128 + z_lo is congruent to
+    // z_hi * C + z_lo; C fits into 46 bits, and so each such reduction
+    // shrinks the value by about 82 bits
+    let (x_lo, x_hi) = mul_128x64(z_hi, C as u64);              // x = z_hi * C
+    let (y_lo, carry) = z_lo.overflowing_add(x_lo);             // y = z_lo + x
+    let y_hi = x_hi + (carry as u64);
 
-    // make sure z is smaller than m
-    if z2 == 1 || (z1 == (M >> 64) as u64 && z0 >= (M as u64)) {
-        let (t0, t1) = sub_modulus(z0, z1);                     // z = z - m
-        z0 = t0; z1 = t1;
+    let (mut r, carry) = y_lo.overflowing_add((y_hi as u128) * C); // r = y_lo + y_hi * C
+    if carry {
+        // y_hi * C is smaller than 2^93, and so after an overflow r is small
+        // and adding 2^128 (which is congruent to C) to it cannot overflow
+        r = r + C;
     }
 
-    return ((z1 as u128) << 64) + (z0 as u128);
+    // make sure r is smaller than m
+    return if r >= M { r - M } else { r };
 }
 
 /// Computes a[i] + b[i] * c for all i and saves result into a.
@@ -161,7 +149,8 @@ pub fn inv(x: u128) -> u128 {
     return a;
 }
 
-/// Computes multiplicative inverses of all slice elements using batch inversion method.
+/// Computes multiplicative inverses of all slice elements using batch inversion method; this
+/// takes a single inversion and 3 multiplications per element. Inverses of zeros are zeros.
 pub fn inv_many(values: &[u128]) -> Vec<u128> {
     let mut result = uninit_vector(values.len());
     inv_many_fill(values, &mut result);
@@ -191,6 +180,29 @@ pub fn inv_many_fill(values: &[u128], result: &mut [u128]) {
     }
 }
 
+/// Replaces all slice elements with their multiplicative inverses using batch inversion method;
+/// zero elements are left unchanged. Prefix products are kept in a temporary vector, and so this
+/// allocates as much memory as `inv_many()` does, but does not allocate the result.
+pub fn inv_many_in_place(values: &mut [u128]) {
+    let mut prefixes = uninit_vector(values.len());
+    let mut last = ONE;
+    for (prefix, &value) in prefixes.iter_mut().zip(values.iter()) {
+        *prefix = last;
+        if value != ZERO {
+            last = mul(last, value);
+        }
+    }
+
+    last = inv(last);
+    for i in (0..values.len()).rev() {
+        let value = values[i];
+        if value != ZERO {
+            values[i] = mul(last, prefixes[i]);
+            last = mul(last, value);
+        }
+    }
+}
+
 /// Computes y = (a / b) such that (b * y) % m = a; a and b are assumed to be valid field elements.
 pub fn div(a: u128, b: u128) -> u128 {
     let b = inv(b);
@@ -284,33 +296,21 @@ pub fn from_bytes(bytes: &[u8]) -> u128 {
 // ================================================================================================
 
 #[inline(always)]
-fn mul_128x64(a: u128, b: u64) -> (u64, u64, u64) {
-    let z_lo = ((a as u64) as u128) * (b as u128);
-    let z_hi = (a >> 64) * (b as u128);
-    let z_hi = z_hi + (z_lo >> 64);
-    return (z_lo as u64, z_hi as u64, (z_hi >> 64) as u64);
+fn mul_128x128(a: u128, b: u128) -> (u128, u128) {
+    let (a0, a1) = (a as u64 as u128, a >> 64);
+    let (b0, b1) = (b as u64 as u128, b >> 64);
+    let (mid, mid_carry) = (a0 * b1).overflowing_add(a1 * b0);
+    let (lo, lo_carry) = (a0 * b0).overflowing_add(mid << 64);
+    let hi = a1 * b1 + (mid >> 64) + ((mid_carry as u128) << 64) + (lo_carry as u128);
+    return (lo, hi);
 }
 
 #[inline(always)]
-fn mul_reduce(z0: u64, z1: u64, z2: u64) -> (u64, u64, u64) {
-    let (q0, q1, q2) = mul_by_modulus(z2);
-    let (z0, z1, z2) = sub_192x192(z0, z1, z2, q0, q1, q2);
-    return (z0, z1, z2);
-}
-
-#[inline(always)]
-fn mul_by_modulus(a: u64) -> (u64, u64, u64) {
-    let a_lo = (a as u128).wrapping_mul(M);
-    let a_hi = if a == 0 { 0 } else { a - 1 };
-    return (a_lo as u64, (a_lo >> 64) as u64, a_hi);
-}
-
-#[inline(always)]
-fn sub_modulus(a_lo: u64, a_hi: u64) -> (u64, u64) {
-    let mut z = 0u128.wrapping_sub(M);
-    z = z.wrapping_add(a_lo as u128);
-    z = z.wrapping_add((a_hi as u128) << 64);
-    return (z as u64, (z >> 64) as u64);
+fn mul_128x64(a: u128, b: u64) -> (u128, u64) {
+    let z_lo = ((a as u64) as u128) * (b as u128);
+    let z_hi = (a >> 64) * (b as u128);
+    let (z, carry) = z_lo.overflowing_add(z_hi << 64);
+    return (z, ((z_hi >> 64) as u64) + (carry as u64));
 }
 
 #[inline(always)]
@@ -421,6 +421,29 @@ mod tests {
         }
     }
 
+    #[test]
+    fn mul_matches_reference() {
+        // edge cases; the last two pairs exercise rarely taken branches of the reduction
+        let m: u128 = super::MODULUS;
+        let values = [0, 1, 2, m - 1, m - 2, (m + 1) / 2, u64::MAX as u128, 1 << 64, super::G];
+        for &a in values.iter() {
+            for &b in values.iter() {
+                assert_eq!(reference_mul(a, b), super::mul(a, b), "failed for: {} * {}", a, b);
+            }
+        }
+        let pairs = [((1 << 64) + 1, (1 << 64) - 1), (1 << 127, 14423060584153026976747423268864)];
+        for &(a, b) in pairs.iter() {
+            assert_eq!(reference_mul(a, b), super::mul(a, b), "failed for: {} * {}", a, b);
+        }
+
+        // random values
+        let v1: Vec<u128> = super::rand_vector(10000);
+        let v2: Vec<u128> = super::rand_vector(10000);
+        for (&a, &b) in v1.iter().zip(v2.iter()) {
+            assert_eq!(reference_mul(a, b), super::mul(a, b), "failed for: {} * {}", a, b);
+        }
+    }
+
     #[test]
     fn inv() {
         // identity
@@ -435,6 +458,36 @@ mod tests {
         }
     }
 
+    #[test]
+    fn inv_many() {
+        // zeros at the start, in the middle, and at the end of the batch map to zeros, and do
+        // not affect inverses of other values
+        let mut values = super::rand_vector(16);
+        values[0] = 0;
+        values[7] = 0;
+        values[8] = 0;
+        values[15] = 0;
+        let expected: Vec<u128> = values.iter().map(|&v| super::inv(v)).collect();
+        assert_eq!(expected, super::inv_many(&values));
+        super::inv_many_in_place(&mut values);
+        assert_eq!(expected, values);
+
+        // edge cases
+        let mut values = vec![0, 1, super::MODULUS - 1, 0];
+        let expected = vec![0, 1, super::MODULUS - 1, 0];
+        assert_eq!(expected, super::inv_many(&values));
+        super::inv_many_in_place(&mut values);
+        assert_eq!(expected, values);
+
+        let mut values = vec![0; 4];
+        super::inv_many_in_place(&mut values);
+        assert_eq!(vec![0; 4], values);
+
+        let mut values: Vec<u128> = Vec::new();
+        super::inv_many_in_place(&mut values);
+        assert!(values.is_empty());
+    }
+
     #[test]
     fn get_root_of_unity() {
         let root_40: u128 = super::get_root_of_unity(usize::pow(2, 40));
@@ -446,4 +499,55 @@ mod tests {
         assert_eq!(expected, root_39);
         assert_eq!(1, super::exp(root_39, u128::pow(2, 39)));
     }
-}
\ No newline at end of file
+
+    // REFERENCE IMPLEMENTATION
+    // --------------------------------------------------------------------------------------------
+
+    /// Multiplication with the generic reduction which `mul()` used before the reduction was
+    /// specialized for the form of the modulus.
+    fn reference_mul(a: u128, b: u128) -> u128 {
+        let (x0, x1, x2) = mul_128x64(a, (b >> 64) as u64);     // x = a * b_hi
+        let (mut x0, mut x1, x2) = mul_reduce(x0, x1, x2);      // x = x - (x >> 128) * m
+        if x2 == 1 {
+            let (t0, t1) = sub_modulus(x0, x1);                 // x = x - m
+            x0 = t0; x1 = t1;
+        }
+
+        let (y0, y1, y2) = mul_128x64(a, b as u64);             // y = a * b_lo
+
+        let (mut y1, carry) = super::add64_with_carry(y1, x0, 0); // y = y + (x << 64)
+        let (mut y2, y3) = super::add64_with_carry(y2, x1, carry);
+        if y3 == 1 {
+            let (t0, t1) = sub_modulus(y1, y2);                 // y = y - (m << 64)
+            y1 = t0; y2 = t1;
+        }
+
+        let (mut z0, mut z1, z2) = mul_reduce(y0, y1, y2);      // z = y - (y >> 128) * m
+        if z2 == 1 || (z1 == (super::M >> 64) as u64 && z0 >= (super::M as u64)) {
+            let (t0, t1) = sub_modulus(z0, z1);                 // z = z - m
+            z0 = t0; z1 = t1;
+        }
+
+        return ((z1 as u128) << 64) + (z0 as u128);
+    }
+
+    fn mul_128x64(a: u128, b: u64) -> (u64, u64, u64) {
+        let z_lo = ((a as u64) as u128) * (b as u128);
+        let z_hi = (a >> 64) * (b as u128);
+        let z_hi = z_hi + (z_lo >> 64);
+        return (z_lo as u64, z_hi as u64, (z_hi >> 64) as u64);
+    }
+
+    fn mul_reduce(z0: u64, z1: u64, z2: u64) -> (u64, u64, u64) {
+        let a_lo = (z2 as u128).wrapping_mul(super::M);         // q = z2 * m
+        let a_hi = if z2 == 0 { 0 } else { z2 - 1 };
+        return super::sub_192x192(z0, z1, z2, a_lo as u64, (a_lo >> 64) as u64, a_hi);
+    }
+
+    fn sub_modulus(a_lo: u64, a_hi: u64) -> (u64, u64) {
+        let mut z = 0u128.wrapping_sub(super::M);
+        z = z.wrapping_add(a_lo as u128);
+        z = z.wrapping_add((a_hi as u128) << 64);
+        return (z as u64, (z >> 64) as u64);
+    }
+}
@@ -2,10 +2,10 @@ use crate::{
     math::field,
     utils::sponge,
     MAX_CONTEXT_DEPTH, MAX_LOOP_DEPTH,
-    NUM_CF_OP_BITS, NUM_LD_OP_BITS, NUM_HD_OP_BITS,
+    NUM_CF_OP_BITS, NUM_LD_OP_BITS, NUM_HD_OP_BITS, NUM_LD_OP_PRODS,
     SPONGE_WIDTH, BASE_CYCLE_LENGTH, PUSH_OP_ALIGNMENT,
 };
-use super::opcodes::{ FlowOps, UserOps };
+use super::{ opcodes::{ FlowOps, UserOps }, ExecutionError };
 
 #[cfg(test)]
 mod tests;
@@ -23,6 +23,7 @@ pub struct Decoder {
     cf_op_bits  : [Vec<u128>; NUM_CF_OP_BITS],
     ld_op_bits  : [Vec<u128>; NUM_LD_OP_BITS],
     hd_op_bits  : [Vec<u128>; NUM_HD_OP_BITS],
+    ld_op_prods : [Vec<u128>; NUM_LD_OP_PRODS],
 
     ctx_stack   : Vec<Vec<u128>>,
     ctx_depth   : usize,
@@ -62,6 +63,14 @@ impl Decoder {
             vec![field::ZERO; init_trace_length], vec![field::ZERO; init_trace_length]
         ];
 
+        // initialize ld_op bit product registers
+        let ld_op_prods = [
+            vec![field::ZERO; init_trace_length], vec![field::ZERO; init_trace_length],
+            vec![field::ZERO; init_trace_length], vec![field::ZERO; init_trace_length],
+            vec![field::ZERO; init_trace_length], vec![field::ZERO; init_trace_length],
+            vec![field::ZERO; init_trace_length], vec![field::ZERO; init_trace_length]
+        ];
+
         // initialize the stacks
         let ctx_stack = vec![vec![field::ZERO; init_trace_length]];
         let ctx_depth = ctx_stack.len();
@@ -73,7 +82,7 @@ impl Decoder {
         return Decoder {
             step: 0, 
             op_counter, sponge, sponge_trace,
-            cf_op_bits, ld_op_bits, hd_op_bits,
+            cf_op_bits, ld_op_bits, hd_op_bits, ld_op_prods,
             ctx_stack, ctx_depth, loop_stack, loop_depth,
         };
     }
@@ -84,7 +93,6 @@ impl Decoder {
     }
 
     /// Returns value of the current step pointer.
-    #[cfg(test)]
     pub fn current_step(&self) -> usize {
         return self.step;
     }
@@ -110,6 +118,7 @@ impl Decoder {
         for register in self.cf_op_bits.iter()   { state.push(register[step]); }
         for register in self.ld_op_bits.iter()   { state.push(register[step]); }
         for register in self.hd_op_bits.iter()   { state.push(register[step]); }
+        for register in self.ld_op_prods.iter()  { state.push(register[step]); }
         for register in self.ctx_stack.iter()    { state.push(register[step]); }
         for register in self.loop_stack.iter()   { state.push(register[step]); }
 
@@ -144,6 +153,16 @@ impl Decoder {
         registers.push(r0);
         registers.push(r1);
 
+        let [r0, r1, r2, r3, r4, r5, r6, r7] = self.ld_op_prods;
+        registers.push(r0);
+        registers.push(r1);
+        registers.push(r2);
+        registers.push(r3);
+        registers.push(r4);
+        registers.push(r5);
+        registers.push(r6);
+        registers.push(r7);
+
         // for context stack, first get rid of the outer-most context because it is always 0
         self.ctx_stack.pop();
         registers.append(&mut self.ctx_stack);
@@ -156,16 +175,18 @@ impl Decoder {
     // OPERATION DECODERS
     // --------------------------------------------------------------------------------------------
 
-    /// Initiates a new program block (Group or Switch).
-    pub fn start_block(&mut self) {
+    /// Initiates a new program block (Group or Switch); returns an error if the block would
+    /// overflow the context stack.
+    pub fn start_block(&mut self) -> Result<(), ExecutionError> {
         assert!(self.step % BASE_CYCLE_LENGTH == BASE_CYCLE_LENGTH - 1,
             "cannot start context block at step {}: operation alignment is not valid", self.step);
 
         self.advance_step(false);
-        self.save_context();
+        self.save_context()?;
         self.copy_loop_stack();
         self.set_op_bits(FlowOps::Begin, UserOps::Noop);
         self.set_sponge([0, 0, 0, 0]);
+        return Ok(());
     }
 
     /// Terminates a program block (Group, Switch, or Loop).
@@ -190,16 +211,18 @@ impl Decoder {
         }
     }
 
-    /// Initiates a new Loop block
-    pub fn start_loop(&mut self, loop_image: u128) {
+    /// Initiates a new Loop block; returns an error if the loop would overflow the context
+    /// stack or the loop stack.
+    pub fn start_loop(&mut self, loop_image: u128) -> Result<(), ExecutionError> {
         assert!(self.step % BASE_CYCLE_LENGTH == BASE_CYCLE_LENGTH - 1,
             "cannot start a loop at step {}: operation alignment is not valid", self.step);
 
         self.advance_step(false);
-        self.save_context();
-        self.save_loop_image(loop_image);
+        self.save_context()?;
+        self.save_loop_image(loop_image)?;
         self.set_op_bits(FlowOps::Loop, UserOps::Noop);
         self.set_sponge([0, 0, 0, 0]);
+        return Ok(());
     }
 
     /// Prepares the decoder for the next iteration of a loop.
@@ -260,6 +283,12 @@ impl Decoder {
         for register in self.ld_op_bits.iter_mut() { fill_register(register, self.step, field::ONE); }
         for register in self.hd_op_bits.iter_mut() { fill_register(register, self.step, field::ONE); }
 
+        // for NOOP operation, only products of all-ones bit pairs are set to 1
+        let noop_prods = get_ld_op_prods(UserOps::Noop as u8);
+        for (register, &value) in self.ld_op_prods.iter_mut().zip(noop_prods.iter()) {
+            fill_register(register, self.step, value);
+        }
+
         // for sponge and stack registers, just copy the value of the last state of the register
         for register in self.sponge_trace.iter_mut() { fill_register(register, self.step + 1, register[self.step]); }
         for register in self.ctx_stack.iter_mut()    { fill_register(register, self.step + 1, register[self.step]); }
@@ -286,6 +315,7 @@ impl Decoder {
             for register in self.cf_op_bits.iter_mut()   { register.resize(new_length, field::ZERO); }
             for register in self.ld_op_bits.iter_mut()   { register.resize(new_length, field::ZERO); }
             for register in self.hd_op_bits.iter_mut()   { register.resize(new_length, field::ZERO); }
+            for register in self.ld_op_prods.iter_mut()  { register.resize(new_length, field::ZERO); }
             for register in self.ctx_stack.iter_mut()    { register.resize(new_length, field::ZERO); }
             for register in self.loop_stack.iter_mut()   { register.resize(new_length, field::ZERO); }
         }
@@ -318,16 +348,23 @@ impl Decoder {
         for i in 0..NUM_HD_OP_BITS {
             self.hd_op_bits[i][step] = ((user_op >> (i + NUM_LD_OP_BITS)) & 1) as u128;
         }
+
+        let ld_op_prods = get_ld_op_prods(user_op);
+        for i in 0..NUM_LD_OP_PRODS {
+            self.ld_op_prods[i][step] = ld_op_prods[i];
+        }
     }
 
     // CONTEXT STACK HELPERS
     // --------------------------------------------------------------------------------------------
 
     /// Pushes hash of the current program block onto the context stack.
-    fn save_context(&mut self) {
-        // increment context depth and make sure it doesn't overflow the stack
+    fn save_context(&mut self) -> Result<(), ExecutionError> {
+        // make sure the stack doesn't overflow, and increment context depth
+        if self.ctx_depth == MAX_CONTEXT_DEPTH {
+            return Err(ExecutionError::ContextOverflow { step: self.step });
+        }
         self.ctx_depth += 1;
-        assert!(self.ctx_depth <= MAX_CONTEXT_DEPTH, "context stack overflow at step {}", self.step);
 
         // if the depth exceeds current number of registers allocated for the context stack,
         // add a new register trace to the stack
@@ -342,7 +379,8 @@ impl Decoder {
 
         // set the top of the stack to the hash of the current program block
         // which is located in the first register of the sponge
-        self.ctx_stack[0][self.step] = self.sponge[0]
+        self.ctx_stack[0][self.step] = self.sponge[0];
+        return Ok(());
     }
 
     /// Removes the top value from the context stack and returns it.
@@ -372,10 +410,12 @@ impl Decoder {
     // --------------------------------------------------------------------------------------------
 
     /// Pushes `loop_image` onto the loop stack.
-    fn save_loop_image(&mut self, loop_image: u128) {
-        // increment loop depth and make sure it doesn't overflow the stack
+    fn save_loop_image(&mut self, loop_image: u128) -> Result<(), ExecutionError> {
+        // make sure the stack doesn't overflow, and increment loop depth
+        if self.loop_depth == MAX_LOOP_DEPTH {
+            return Err(ExecutionError::LoopOverflow { step: self.step });
+        }
         self.loop_depth += 1;
-        assert!(self.loop_depth <= MAX_LOOP_DEPTH, "loop stack overflow at step {}", self.step);
 
         // if the depth exceeds current number of registers allocated for the loop stack,
         // add a new register trace to the stack
@@ -390,6 +430,7 @@ impl Decoder {
 
         // set the top of the stack to loop_image
         self.loop_stack[0][self.step] = loop_image;
+        return Ok(());
     }
 
     /// Copies contents of the loop stack from the previous to the current step and returns
@@ -463,4 +504,13 @@ fn fill_register(register: &mut Vec<u128>, from: usize, value: u128) {
     let to = register.len();
     register.resize(from, field::ZERO);
     register.resize(to, value);
-}
\ No newline at end of file
+}
+/// Returns values of ld_op bit product registers for the specified user operation; the first
+/// 4 products are for bits 0 and 1, and the last 4 products are for bits 2 and 3. Exactly one
+/// product in each group is set to 1.
+fn get_ld_op_prods(user_op: u8) -> [u128; NUM_LD_OP_PRODS] {
+    let mut result = [field::ZERO; NUM_LD_OP_PRODS];
+    result[(user_op & 3) as usize] = field::ONE;
+    result[4 + ((user_op >> 2) & 3) as usize] = field::ONE;
+    return result;
+}
@@ -0,0 +1,175 @@
+use super::OpCode;
+use crate::stark::AssertionStep;
+
+// TYPES AND INTERFACES
+// ================================================================================================
+
+/// Describes why execution of a program failed; errors which occur while the program is being
+/// executed carry the step of the execution trace at which the failure occurred.
+#[derive(Copy, Clone, PartialEq)]
+pub enum ExecutionError {
+    /// An operation required more values than there were on the stack.
+    StackUnderflow      { step: usize, op: OpCode },
+    /// An operation pushed the stack beyond `MAX_STACK_DEPTH` values.
+    StackOverflow       { step: usize, op: OpCode },
+    /// ASSERT operation was applied to a value other than 1.
+    FailedAssertion     { step: usize, op: OpCode, value: u128 },
+    /// ASSERTEQ operation was applied to two different values.
+    UnequalValues       { step: usize, op: OpCode, values: [u128; 2] },
+    /// An operation which works only with binary values was applied to a non-binary value.
+    NonBinaryValue      { step: usize, op: OpCode, value: u128 },
+    /// INV operation was applied to zero.
+    InverseOfZero       { step: usize, op: OpCode },
+    /// Integer division was attempted with zero as the divisor.
+    DivisionByZero      { step: usize, op: OpCode },
+    /// An operation was applied to a value outside of the range it accepts.
+    InvalidOperand      { step: usize, op: OpCode, value: u128 },
+    /// An operation attempted to read from an exhausted secret input tape.
+    EmptyTape           { step: usize, op: OpCode, tape: char },
+    /// An operation was executed with an execution hint it does not accept.
+    InvalidHint         { step: usize, op: OpCode },
+    /// A branch or a loop was entered or exited based on a non-binary condition.
+    NonBinaryCondition  { step: usize, value: u128 },
+    /// Program blocks were nested deeper than `MAX_CONTEXT_DEPTH`.
+    ContextOverflow     { step: usize },
+    /// Loops were nested deeper than `MAX_LOOP_DEPTH`.
+    LoopOverflow        { step: usize },
+    /// A loop was about to start more iterations than its declared iteration cap.
+    IterationCapExceeded { step: usize, max: usize },
+    /// A sequence of program blocks, or the skip block of a loop, did not start with a Span block.
+    MalformedBlock      { step: usize },
+    /// More than `MAX_OUTPUTS` outputs were requested.
+    TooManyOutputs      { requested: usize, max: usize },
+    /// Fewer than `MIN_TRACE_LENGTH` operations were executed.
+    TooFewOperations    { executed: usize, min: usize },
+    /// More operations were executed than can be described by a proof.
+    TooManyOperations   { executed: u128, max: u128 },
+    /// The program hash computed by the VM did not match the hash of the program.
+    ProgramHashMismatch { expected: [u8; 32], actual: [u8; 32] },
+    /// An assertion was made against a register beyond the depth of the stack, or the same
+    /// register was asserted more than once at the same step.
+    InvalidAssertion    { register: usize, at: AssertionStep, stack_depth: usize },
+    /// An assertion did not hold against the execution trace.
+    UnsatisfiedAssertion { register: usize, at: AssertionStep, expected: u128, actual: u128 },
+    /// A procedure was called on a program which is not a library.
+    InvalidLibrary,
+    /// A procedure index was beyond the number of procedures in a library.
+    InvalidProcedure    { index: usize, count: usize },
+}
+
+// EXECUTION ERROR IMPLEMENTATION
+// ================================================================================================
+impl ExecutionError {
+
+    /// Returns the step of the execution trace at which execution failed, or None if the
+    /// failure was detected before or after the program was executed.
+    pub fn step(&self) -> Option<usize> {
+        return match *self {
+            ExecutionError::StackUnderflow { step, .. }     => Some(step),
+            ExecutionError::StackOverflow { step, .. }      => Some(step),
+            ExecutionError::FailedAssertion { step, .. }    => Some(step),
+            ExecutionError::UnequalValues { step, .. }      => Some(step),
+            ExecutionError::NonBinaryValue { step, .. }     => Some(step),
+            ExecutionError::InverseOfZero { step, .. }      => Some(step),
+            ExecutionError::DivisionByZero { step, .. }     => Some(step),
+            ExecutionError::InvalidOperand { step, .. }     => Some(step),
+            ExecutionError::EmptyTape { step, .. }          => Some(step),
+            ExecutionError::InvalidHint { step, .. }        => Some(step),
+            ExecutionError::NonBinaryCondition { step, .. } => Some(step),
+            ExecutionError::ContextOverflow { step }        => Some(step),
+            ExecutionError::LoopOverflow { step }           => Some(step),
+            ExecutionError::IterationCapExceeded { step, .. } => Some(step),
+            ExecutionError::MalformedBlock { step }         => Some(step),
+            _ => None,
+        };
+    }
+
+    /// Returns the operation which caused execution to fail, or None if execution failed
+    /// while entering or exiting a program block.
+    pub fn op(&self) -> Option<OpCode> {
+        return match *self {
+            ExecutionError::StackUnderflow { op, .. }   => Some(op),
+            ExecutionError::StackOverflow { op, .. }    => Some(op),
+            ExecutionError::FailedAssertion { op, .. }  => Some(op),
+            ExecutionError::UnequalValues { op, .. }    => Some(op),
+            ExecutionError::NonBinaryValue { op, .. }   => Some(op),
+            ExecutionError::InverseOfZero { op, .. }    => Some(op),
+            ExecutionError::DivisionByZero { op, .. }   => Some(op),
+            ExecutionError::InvalidOperand { op, .. }   => Some(op),
+            ExecutionError::EmptyTape { op, .. }        => Some(op),
+            ExecutionError::InvalidHint { op, .. }      => Some(op),
+            _ => None,
+        };
+    }
+}
+
+// COMMON TRAIT IMPLEMENTATIONS
+// ================================================================================================
+
+impl std::fmt::Debug for ExecutionError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self)
+    }
+}
+
+impl std::fmt::Display for ExecutionError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        return match self {
+            ExecutionError::StackUnderflow { step, op } =>
+                write!(f, "stack underflow at step {}: not enough values for {} operation", step, name(op)),
+            ExecutionError::StackOverflow { step, op } =>
+                write!(f, "stack overflow at step {}: {} operation exceeds maximum stack depth", step, name(op)),
+            ExecutionError::FailedAssertion { step, op, value } =>
+                write!(f, "{} failed at step {}: expected 1 but got {}", name(op), step, value),
+            ExecutionError::UnequalValues { step, op, values } =>
+                write!(f, "{} failed at step {}: {} is not equal to {}", name(op), step, values[0], values[1]),
+            ExecutionError::NonBinaryValue { step, op, value } =>
+                write!(f, "{} on a non-binary value {} at step {}", name(op), value, step),
+            ExecutionError::InverseOfZero { step, .. } =>
+                write!(f, "cannot compute INV of 0 at step {}", step),
+            ExecutionError::DivisionByZero { step, .. } =>
+                write!(f, "cannot divide by 0 at step {}", step),
+            ExecutionError::InvalidOperand { step, op, value } =>
+                write!(f, "invalid operand {} for {} operation at step {}", value, name(op), step),
+            ExecutionError::EmptyTape { step, op, tape } =>
+                write!(f, "{} attempted to read from empty tape {} at step {}", name(op), tape, step),
+            ExecutionError::InvalidHint { step, op } =>
+                write!(f, "invalid execution hint for {} operation at step {}", name(op), step),
+            ExecutionError::NonBinaryCondition { step, value } =>
+                write!(f, "cannot branch on a non-binary condition {} at step {}", value, step),
+            ExecutionError::ContextOverflow { step } =>
+                write!(f, "context stack overflow at step {}", step),
+            ExecutionError::LoopOverflow { step } =>
+                write!(f, "loop stack overflow at step {}", step),
+            ExecutionError::IterationCapExceeded { step, max } =>
+                write!(f, "loop cannot execute more than {} iterations at step {}", max, step),
+            ExecutionError::MalformedBlock { step } =>
+                write!(f, "expected a Span block at step {}", step),
+            ExecutionError::TooManyOutputs { requested, max } =>
+                write!(f, "cannot produce more than {} outputs, but requested {}", max, requested),
+            ExecutionError::TooFewOperations { executed, min } =>
+                write!(f, "a program must consist of at least {} operations, but only {} were executed", min, executed),
+            ExecutionError::TooManyOperations { executed, max } =>
+                write!(f, "a proof can describe at most {} operations, but {} were executed", max, executed),
+            ExecutionError::ProgramHashMismatch { expected, actual } =>
+                write!(f, "expected program hash {} does not match trace hash {}", hex::encode(expected), hex::encode(actual)),
+            ExecutionError::InvalidAssertion { register, stack_depth, .. } if *register >= *stack_depth =>
+                write!(f, "assertion against register {} is invalid: stack depth is {}", register, stack_depth),
+            ExecutionError::InvalidAssertion { register, at, .. } =>
+                write!(f, "register {} is asserted more than once at {:?} step", register, at),
+            ExecutionError::UnsatisfiedAssertion { register, at, expected, actual } =>
+                write!(f, "assertion against register {} at {:?} step does not hold: expected {}, but was {}",
+                    register, at, expected, actual),
+            ExecutionError::InvalidLibrary =>
+                write!(f, "library must consist of a BEGIN operation followed by an if.true block"),
+            ExecutionError::InvalidProcedure { index, count } =>
+                write!(f, "procedure {} does not exist in a library of {} procedures", index, count),
+        };
+    }
+}
+
+// HELPER FUNCTIONS
+// ================================================================================================
+fn name(op: &OpCode) -> String {
+    return op.to_string().to_uppercase();
+}
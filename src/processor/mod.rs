@@ -1,9 +1,12 @@
 use crate::{
     math::field,
-    programs::{ Program, ProgramInputs, blocks::{ ProgramBlock, Span, Loop } },
+    programs::{ Program, ProgramInputs, WitnessRecord, blocks::{ ProgramBlock, Span, Loop } },
     MIN_TRACE_LENGTH, HACC_NUM_ROUNDS,
 };
 
+#[cfg(feature = "coverage")]
+use crate::testing::coverage::{ self, FlowPath };
+
 // RE-EXPORTS
 // ================================================================================================
 
@@ -14,21 +17,58 @@ mod stack;
 pub use stack::{ Stack };
 
 pub mod opcodes;
-pub use opcodes::{ UserOps as OpCode, OpHint };
+pub use opcodes::{ UserOps as OpCode, OpHint, HintKind };
+
+mod errors;
+pub use errors::{ ExecutionError };
+
+#[cfg(test)]
+pub mod reference;
 
 // PUBLIC FUNCTIONS
 // ================================================================================================
 
-/// Returns register traces resulting from executing the `program` against the specified inputs.
-pub fn execute(program: &Program, inputs: &ProgramInputs) -> (Vec<Vec<u128>>, usize, usize)
+/// Returns register traces resulting from executing the `program` against the specified inputs;
+/// returns an error if the program cannot be executed against these inputs.
+pub fn execute(program: &Program, inputs: &ProgramInputs) -> Result<(Vec<Vec<u128>>, usize, usize), ExecutionError>
+{
+    let (register_traces, context_depth, loop_depth, _, _) = execute_program(program, inputs)?;
+    return Ok((register_traces, context_depth, loop_depth));
+}
+
+/// Executes the `program` in the same way as `execute()`, and also returns the number of steps
+/// executed by the VM; this excludes steps added to pad the trace to a power of 2.
+pub fn execute_and_count(program: &Program, inputs: &ProgramInputs) -> Result<(Vec<Vec<u128>>, usize, usize, usize), ExecutionError>
+{
+    let (register_traces, context_depth, loop_depth, _, step_count) = execute_program(program, inputs)?;
+    return Ok((register_traces, context_depth, loop_depth, step_count));
+}
+
+/// Executes the `program` in the same way as `execute()`, and also returns a record of all
+/// inputs consumed by the program; the record can be used to replay the execution.
+pub fn execute_and_record(program: &Program, inputs: &ProgramInputs) -> Result<(Vec<Vec<u128>>, usize, usize, WitnessRecord), ExecutionError>
+{
+    let (register_traces, context_depth, loop_depth, consumed, _) = execute_program(program, inputs)?;
+    let record = WitnessRecord::new(program.hash(), inputs, consumed);
+
+    return Ok((register_traces, context_depth, loop_depth, record));
+}
+
+// HELPER FUNCTIONS
+// ================================================================================================
+fn execute_program(program: &Program, inputs: &ProgramInputs) -> Result<(Vec<Vec<u128>>, usize, usize, [Vec<u128>; 2], usize), ExecutionError>
 {
+    #[cfg(feature = "coverage")]
+    coverage::start_execution();
+
     // initialize decoder and stack components
     let mut decoder = Decoder::new(MIN_TRACE_LENGTH);
     let mut stack = Stack::new(inputs, MIN_TRACE_LENGTH);
 
     // execute body of the program
-    execute_blocks(program.root().body(), &mut decoder, &mut stack);
-    close_block(&mut decoder, &mut stack, field::ZERO, true);
+    execute_blocks(program.root().body(), &mut decoder, &mut stack)?;
+    close_block(&mut decoder, &mut stack, field::ZERO, true)?;
+    let step_count = decoder.current_step();
 
     // fill in remaining steps to make sure the length of the trace is a power of 2
     decoder.finalize_trace();
@@ -37,145 +77,184 @@ pub fn execute(program: &Program, inputs: &ProgramInputs) -> (Vec<Vec<u128>>, us
     // save context and loop depths into variables before decoder is consumed
     let context_depth = decoder.max_ctx_stack_depth();
     let loop_depth = decoder.max_loop_stack_depth();
+    let consumed_inputs = consumed_inputs(inputs, &stack);
 
     // merge decoder and stack register traces into a single vector
     let mut register_traces = decoder.into_register_traces();
     register_traces.append(&mut stack.into_register_traces());
 
-    return (register_traces, context_depth, loop_depth);
+    return Ok((register_traces, context_depth, loop_depth, consumed_inputs, step_count));
 }
 
-// HELPER FUNCTIONS
-// ================================================================================================
-fn execute_blocks(blocks: &[ProgramBlock], decoder: &mut Decoder, stack: &mut Stack)
+/// Returns values read from secret input tapes A and B; values supplied by the tape provider
+/// are always read after all values of the static tapes.
+fn consumed_inputs(inputs: &ProgramInputs, stack: &Stack) -> [Vec<u128>; 2] {
+    let unread = stack.unread_inputs();
+    let provided = stack.provided_inputs();
+    let mut result = inputs.get_secret_inputs().clone();
+    for i in 0..2 {
+        result[i].extend_from_slice(&provided[i]);
+        result[i].truncate(result[i].len() - unread[i]);
+    }
+    return result;
+}
+
+fn execute_blocks(blocks: &[ProgramBlock], decoder: &mut Decoder, stack: &mut Stack) -> Result<(), ExecutionError>
 {
     // execute first block in the sequence, which mast be a Span block
     match &blocks[0] {
-        ProgramBlock::Span(block) => execute_span(block, decoder, stack, true),
-        _ => panic!("first block in a sequence must be a Span block"),
+        ProgramBlock::Span(block) => execute_span(block, decoder, stack, true)?,
+        _ => return Err(ExecutionError::MalformedBlock { step: decoder.current_step() }),
     }
 
     // execute all other blocks in the sequence one after another
     for block in blocks.iter().skip(1) {
         match block {
-            ProgramBlock::Span(block) => execute_span(block, decoder, stack, false),
+            ProgramBlock::Span(block) => execute_span(block, decoder, stack, false)?,
             ProgramBlock::Group(block) => {
-                start_block(decoder, stack);
-                execute_blocks(block.body(), decoder, stack);
-                close_block(decoder, stack, field::ZERO, true);
+                start_block(decoder, stack)?;
+                execute_blocks(block.body(), decoder, stack)?;
+                close_block(decoder, stack, field::ZERO, true)?;
             },
             ProgramBlock::Switch(block) => {
-                start_block(decoder, stack);
+                start_block(decoder, stack)?;
                 let condition = stack.get_stack_top();
                 match condition {
                     0 => {
-                        execute_blocks(block.false_branch(), decoder, stack);
-                        close_block(decoder, stack, block.true_branch_hash(), false);
+                        #[cfg(feature = "coverage")]
+                        coverage::record_flow(FlowPath::FalseBranch);
+                        execute_blocks(block.false_branch(), decoder, stack)?;
+                        close_block(decoder, stack, block.true_branch_hash(), false)?;
                     },
                     1 => {
-                        execute_blocks(block.true_branch(), decoder, stack);
-                        close_block(decoder, stack, block.false_branch_hash(), true);
+                        #[cfg(feature = "coverage")]
+                        coverage::record_flow(FlowPath::TrueBranch);
+                        execute_blocks(block.true_branch(), decoder, stack)?;
+                        close_block(decoder, stack, block.false_branch_hash(), true)?;
                     },
-                    _ => panic!("cannot select a branch based on a non-binary condition {}", condition)
+                    _ => return Err(ExecutionError::NonBinaryCondition { step: decoder.current_step(), value: condition }),
                 };
             },
             ProgramBlock::Loop(block) => {
                 let condition = stack.get_stack_top();
                 match condition {
                     0 => {
-                        start_block(decoder, stack);
-                        execute_blocks(block.skip(), decoder, stack);
-                        close_block(decoder, stack, block.body_hash(), false);
+                        #[cfg(feature = "coverage")]
+                        coverage::record_flow(FlowPath::LoopSkip);
+                        start_block(decoder, stack)?;
+                        execute_blocks(block.skip(), decoder, stack)?;
+                        close_block(decoder, stack, block.body_hash(), false)?;
                     },
-                    1 => execute_loop(block, decoder, stack),
-                    _ => panic!("cannot enter loop based on a non-binary condition {}", condition)
+                    1 if block.max_iterations() == Some(0) => return Err(
+                        ExecutionError::IterationCapExceeded { step: decoder.current_step(), max: 0 }),
+                    1 => execute_loop(block, decoder, stack)?,
+                    _ => return Err(ExecutionError::NonBinaryCondition { step: decoder.current_step(), value: condition }),
                 }
             },
         }
     }
+
+    return Ok(());
 }
 
 /// Executes all instructions in a Span block.
-fn execute_span(block: &Span, decoder: &mut Decoder, stack: &mut Stack, is_first: bool)
+fn execute_span(block: &Span, decoder: &mut Decoder, stack: &mut Stack, is_first: bool) -> Result<(), ExecutionError>
 {
     // if this is the first Span block in a sequence of blocks, it needs to be
     // pre-padded with a NOOP to make sure the first instruction in the block
     // starts executing on a step which is a multiple of 16
     if !is_first {
         decoder.decode_op(OpCode::Noop, field::ZERO);
-        stack.execute(OpCode::Noop, OpHint::None);
+        stack.execute(OpCode::Noop, OpHint::None)?;
     }
 
     // execute all other instructions in the block
     for i in 0..block.length() {
         let (op_code, op_hint) = block.get_op(i);
+        #[cfg(feature = "coverage")]
+        coverage::record_op(op_code);
         decoder.decode_op(op_code, op_hint.value());
-        stack.execute(op_code, op_hint);
+        stack.execute(op_code, op_hint)?;
     }
+
+    return Ok(());
 }
 
 /// Starts executing a new program block.
-fn start_block(decoder: &mut Decoder, stack: &mut Stack)
+fn start_block(decoder: &mut Decoder, stack: &mut Stack) -> Result<(), ExecutionError>
 {
-    decoder.start_block();
-    stack.execute(OpCode::Noop, OpHint::None);
+    decoder.start_block()?;
+    return stack.execute(OpCode::Noop, OpHint::None);
 }
 
 /// Closes the currently executing program block.
-fn close_block(decoder: &mut Decoder, stack: &mut Stack, sibling_hash: u128, is_true_branch: bool)
+fn close_block(decoder: &mut Decoder, stack: &mut Stack, sibling_hash: u128, is_true_branch: bool) -> Result<(), ExecutionError>
 {
     // a sequence of blocks always ends on a step which is one less than a multiple of 16;
     // all sequences end one operation short of multiple of 16 - so, we need to pad them
     // with a single NOOP ensure proper alignment
     decoder.decode_op(OpCode::Noop, field::ZERO);
-    stack.execute(OpCode::Noop, OpHint::None);
+    stack.execute(OpCode::Noop, OpHint::None)?;
 
     // end the block, this prepares decoder registers for merging block hash into
     // program hash
     decoder.end_block(sibling_hash, is_true_branch);
-    stack.execute(OpCode::Noop, OpHint::None);
+    stack.execute(OpCode::Noop, OpHint::None)?;
 
     // execute NOOPs to merge block hash into the program hash
     for _ in 0..HACC_NUM_ROUNDS {
         decoder.decode_op(OpCode::Noop, field::ZERO);
-        stack.execute(OpCode::Noop, OpHint::None);
+        stack.execute(OpCode::Noop, OpHint::None)?;
     }
+
+    return Ok(());
 }
 
 /// Executes the specified loop.
-fn execute_loop(block: &Loop, decoder: &mut Decoder, stack: &mut Stack)
+fn execute_loop(block: &Loop, decoder: &mut Decoder, stack: &mut Stack) -> Result<(), ExecutionError>
 {
     // mark the beginning of the loop block
-    decoder.start_loop(block.image());
-    stack.execute(OpCode::Noop, OpHint::None);
+    #[cfg(feature = "coverage")]
+    coverage::record_flow(FlowPath::LoopEnter);
+    decoder.start_loop(block.image())?;
+    stack.execute(OpCode::Noop, OpHint::None)?;
 
     // execute blocks in loop body until top of the stack becomes 0
+    let mut num_iterations = 0;
     loop {
-        execute_blocks(block.body(), decoder, stack);
+        execute_blocks(block.body(), decoder, stack)?;
+        num_iterations += 1;
 
         let condition = stack.get_stack_top();
         match condition {
             0 => {
+                #[cfg(feature = "coverage")]
+                coverage::record_flow(FlowPath::LoopExit);
                 decoder.break_loop();
-                stack.execute(OpCode::Noop, OpHint::None);
+                stack.execute(OpCode::Noop, OpHint::None)?;
                 break;
             },
             1 => {
+                if let Some(max) = block.max_iterations().filter(|&max| num_iterations == max) {
+                    return Err(ExecutionError::IterationCapExceeded { step: decoder.current_step(), max });
+                }
+                #[cfg(feature = "coverage")]
+                coverage::record_flow(FlowPath::LoopWrap);
                 decoder.wrap_loop();
-                stack.execute(OpCode::Noop, OpHint::None);
+                stack.execute(OpCode::Noop, OpHint::None)?;
             },
-            _ => panic!("cannot exit loop based on a non-binary condition {}", condition)
+            _ => return Err(ExecutionError::NonBinaryCondition { step: decoder.current_step(), value: condition }),
         };
     }
 
     // execute the contents of the skip block to make sure the loop was exited correctly
     match &block.skip()[0] {
-        ProgramBlock::Span(block) => execute_span(block, decoder, stack, true),
-        _ => panic!("invalid skip block content: content must be a Span block"),
+        ProgramBlock::Span(block) => execute_span(block, decoder, stack, true)?,
+        _ => return Err(ExecutionError::MalformedBlock { step: decoder.current_step() }),
     }
 
     // close block
-    close_block(decoder, stack, block.skip_hash(), true);
+    return close_block(decoder, stack, block.skip_hash(), true);
 }
 
 // TESTS
@@ -192,11 +271,11 @@ mod tests {
         let program = assembly::compile("begin add push.5 mul push.7 end").unwrap();
         let inputs = ProgramInputs::from_public(&[1, 2]);
 
-        let (trace, ctx_depth, loop_depth) = super::execute(&program, &inputs);
+        let (trace, ctx_depth, loop_depth) = super::execute(&program, &inputs).unwrap();
         let trace_length = trace[0].len();
 
         assert_eq!(64, trace_length);
-        assert_eq!(17, trace.len());
+        assert_eq!(31, trace.len());
         let mut state = build_trace_state(trace.len(), ctx_depth, loop_depth) ;
         state.update_from_trace(&trace, trace_length - 1);
 
@@ -214,11 +293,11 @@ mod tests {
         let program = assembly::compile("begin add block push.5 mul push.7 end end").unwrap();
         let inputs = ProgramInputs::from_public(&[1, 2]);
 
-        let (trace, ctx_depth, loop_depth) = super::execute(&program, &inputs);
+        let (trace, ctx_depth, loop_depth) = super::execute(&program, &inputs).unwrap();
         let trace_length = trace[0].len();
 
         assert_eq!(64, trace_length);
-        assert_eq!(18, trace.len());
+        assert_eq!(32, trace.len());
 
         let mut state = build_trace_state(trace.len(), ctx_depth, loop_depth) ;
         state.update_from_trace(&trace, trace_length - 1);
@@ -240,11 +319,11 @@ mod tests {
         
         // execute true branch
         let inputs = ProgramInputs::new(&[5, 3], &[1], &[]);
-        let (trace, ctx_depth, loop_depth) = super::execute(&program, &inputs);
+        let (trace, ctx_depth, loop_depth) = super::execute(&program, &inputs).unwrap();
         let trace_length = trace[0].len();
 
         assert_eq!(128, trace_length);
-        assert_eq!(19, trace.len());
+        assert_eq!(33, trace.len());
 
         let mut state = build_trace_state(trace.len(), ctx_depth, loop_depth) ;
         state.update_from_trace(&trace, trace_length - 1);
@@ -260,11 +339,11 @@ mod tests {
 
         // execute false branch
         let inputs = ProgramInputs::new(&[5, 3], &[0], &[]);
-        let (trace, ctx_depth, loop_depth) = super::execute(&program, &inputs);
+        let (trace, ctx_depth, loop_depth) = super::execute(&program, &inputs).unwrap();
         let trace_length = trace[0].len();
 
         assert_eq!(128, trace_length);
-        assert_eq!(19, trace.len());
+        assert_eq!(33, trace.len());
 
         let mut state = build_trace_state(trace.len(), ctx_depth, loop_depth) ;
         state.update_from_trace(&trace, trace_length - 1);
@@ -286,11 +365,11 @@ mod tests {
 
         // don't enter the loop
         let inputs = ProgramInputs::new(&[5, 3], &[0], &[]);
-        let (trace, ctx_depth, loop_depth) = super::execute(&program, &inputs);
+        let (trace, ctx_depth, loop_depth) = super::execute(&program, &inputs).unwrap();
         let trace_length = trace[0].len();
 
         assert_eq!(64, trace_length);
-        assert_eq!(18, trace.len());
+        assert_eq!(32, trace.len());
 
         let mut state = build_trace_state(trace.len(), ctx_depth, loop_depth) ;
         state.update_from_trace(&trace, trace_length - 1);
@@ -306,11 +385,11 @@ mod tests {
 
         // execute one iteration
         let inputs = ProgramInputs::new(&[5, 3], &[1, 0], &[]);
-        let (trace, ctx_depth, loop_depth) = super::execute(&program, &inputs);
+        let (trace, ctx_depth, loop_depth) = super::execute(&program, &inputs).unwrap();
         let trace_length = trace[0].len();
 
         assert_eq!(128, trace_length);
-        assert_eq!(19, trace.len());
+        assert_eq!(33, trace.len());
 
         let mut state = build_trace_state(trace.len(), ctx_depth, loop_depth) ;
         state.update_from_trace(&trace, trace_length - 1);
@@ -326,11 +405,11 @@ mod tests {
 
         // execute five iteration
         let inputs = ProgramInputs::new(&[5, 3], &[1, 1, 1, 1, 1, 0], &[]);
-        let (trace, ctx_depth, loop_depth) = super::execute(&program, &inputs);
+        let (trace, ctx_depth, loop_depth) = super::execute(&program, &inputs).unwrap();
         let trace_length = trace[0].len();
 
         assert_eq!(256, trace_length);
-        assert_eq!(19, trace.len());
+        assert_eq!(33, trace.len());
 
         let mut state = build_trace_state(trace.len(), ctx_depth, loop_depth) ;
         state.update_from_trace(&trace, trace_length - 1);
@@ -346,8 +425,7 @@ mod tests {
     }
 
     fn build_trace_state(num_registers: usize, ctx_depth: usize, loop_depth: usize) -> TraceState {
-        let decoder_width = TraceState::compute_decoder_width(ctx_depth, loop_depth);
-        let stack_depth = num_registers - decoder_width;
+        let stack_depth = TraceState::compute_stack_depth(num_registers, ctx_depth, loop_depth);
         return TraceState::new(ctx_depth, loop_depth, stack_depth);
     }
 }
\ No newline at end of file
@@ -1,3 +1,5 @@
+use serde::{ Serialize, Serializer, Deserialize, Deserializer, de::Error };
+
 // FLOW CONTROL OPERATIONS
 // ================================================================================================
 #[repr(u8)]
@@ -62,7 +64,7 @@ pub enum UserOps {
     Inv         = 0b0_11_01100,         // no shift
     Neg         = 0b0_11_01101,         // no shift
     Not         = 0b0_11_01110,         // no shift
-    //???       = 0b0_11_01111,
+    CSwap4      = 0b0_11_01111,         // left shift: 2
 
     Read        = 0b0_11_10000,         // right shift: 1
     Read2       = 0b0_11_10001,         // right shift: 2
@@ -70,8 +72,8 @@ pub enum UserOps {
     Dup2        = 0b0_11_10011,         // right shift: 2
     Dup4        = 0b0_11_10100,         // right shift: 4
     Pad2        = 0b0_11_10101,         // right shift: 2
-    //???       = 0b0_11_10110,
-    //???       = 0b0_11_10111,
+    Read4       = 0b0_11_10110,         // right shift: 4
+    Dup8        = 0b0_11_10111,         // right shift: 8
 
     Swap        = 0b0_11_11000,         // no shift
     Swap2       = 0b0_11_11001,         // no shift
@@ -79,7 +81,7 @@ pub enum UserOps {
     Roll4       = 0b0_11_11011,         // no shift
     Roll8       = 0b0_11_11100,         // no shift
     BinAcc      = 0b0_11_11101,         // no shift
-    //???       = 0b0_11_11110,
+    ExpAcc      = 0b0_11_11110,         // no shift
 
     // high-degree operations
     Push        = 0b0_00_11111,         // right shift: 1
@@ -91,8 +93,26 @@ pub enum UserOps {
     Noop        = 0b0_11_11111,         // no shift
 }
 
+pub(crate) const USER_OPS: [UserOps; 36] = [
+    UserOps::Assert,    UserOps::AssertEq,  UserOps::Eq,        UserOps::Drop,
+    UserOps::Drop4,     UserOps::Choose,    UserOps::Choose2,   UserOps::CSwap2,
+    UserOps::Add,       UserOps::Mul,       UserOps::And,       UserOps::Or,
+    UserOps::Inv,       UserOps::Neg,       UserOps::Not,       UserOps::CSwap4,
+    UserOps::Read,      UserOps::Read2,     UserOps::Dup,       UserOps::Dup2,
+    UserOps::Dup4,      UserOps::Pad2,      UserOps::Read4,     UserOps::Dup8,
+    UserOps::Swap,      UserOps::Swap2,     UserOps::Swap4,     UserOps::Roll4,
+    UserOps::Roll8,     UserOps::BinAcc,    UserOps::ExpAcc,    UserOps::Push,
+    UserOps::Cmp,       UserOps::RescR,     UserOps::Begin,     UserOps::Noop,
+];
+
 impl UserOps {
 
+    /// Returns the operation encoded by the specified op code, or None if the code does not
+    /// encode any operation.
+    pub fn from_code(code: u8) -> Option<UserOps> {
+        return USER_OPS.iter().find(|&&op| op as u8 == code).copied();
+    }
+
     pub fn ld_index(&self) -> usize {
         return match self {
             UserOps::Push | UserOps::Cmp | UserOps::RescR => {
@@ -114,6 +134,126 @@ impl UserOps {
             }
         };
     }
+
+    /// Returns the number of items this operation takes from the top of the stack; the stack
+    /// must be at least this deep for the operation to execute. Some hints require a deeper
+    /// stack (e.g. CMP with a start hint reads the compared values from positions 8 and 9).
+    pub fn pops(&self) -> usize {
+        return self.stack_effect().0;
+    }
+
+    /// Returns the number of items this operation places onto the top of the stack.
+    pub fn pushes(&self) -> usize {
+        return self.stack_effect().1;
+    }
+
+    /// Returns the net change in stack depth caused by executing this operation.
+    pub fn stack_delta(&self) -> i32 {
+        let (pops, pushes) = self.stack_effect();
+        return pushes as i32 - pops as i32;
+    }
+
+    /// Returns true if the operation is emitted for control flow rather than for stack
+    /// manipulation; such operations do not affect the stack.
+    pub fn is_control_flow(&self) -> bool {
+        return *self == UserOps::Begin;
+    }
+
+    /// Returns the kind of hint which must accompany this operation, if any; hints of other
+    /// operations are optional.
+    pub fn requires_hint(&self) -> Option<HintKind> {
+        return match self {
+            UserOps::Push => Some(HintKind::PushValue),
+            _ => None,
+        };
+    }
+
+    /// Returns true if hints of the specified kind can accompany this operation.
+    pub fn accepts_hint(&self, kind: HintKind) -> bool {
+        return match kind {
+            HintKind::PushValue     => *self == UserOps::Push,
+            HintKind::EqStart       => *self == UserOps::Read,
+            HintKind::UnpackStart   => *self == UserOps::Read,
+            HintKind::BitsStart     => *self == UserOps::Read,
+            HintKind::PmpathStart   => *self == UserOps::Read2,
+            HintKind::CmpStart      => *self == UserOps::Cmp,
+            HintKind::RcStart       => *self == UserOps::BinAcc,
+            HintKind::ExpStart      => *self == UserOps::ExpAcc,
+            HintKind::DivStart      => *self == UserOps::Read2,
+        };
+    }
+
+    /// Returns the number of items the operation takes from the stack and the number of items
+    /// it places onto the stack. Items which are only rearranged or copied are counted as
+    /// taken and placed back.
+    pub(crate) fn stack_effect(&self) -> (usize, usize) {
+        return match self {
+            UserOps::Begin      => (0, 0),
+            UserOps::Noop       => (0, 0),
+
+            UserOps::Assert     => (1, 0),
+            UserOps::AssertEq   => (2, 0),
+
+            UserOps::Push       => (0, 1),
+            UserOps::Read       => (0, 1),
+            UserOps::Read2      => (0, 2),
+            UserOps::Read4      => (0, 4),
+
+            UserOps::Dup        => (1, 2),
+            UserOps::Dup2       => (2, 4),
+            UserOps::Dup4       => (4, 8),
+            UserOps::Dup8       => (8, 16),
+            UserOps::Pad2       => (0, 2),
+
+            UserOps::Drop       => (1, 0),
+            UserOps::Drop4      => (4, 0),
+
+            UserOps::Swap       => (2, 2),
+            UserOps::Swap2      => (4, 4),
+            UserOps::Swap4      => (8, 8),
+
+            UserOps::Roll4      => (4, 4),
+            UserOps::Roll8      => (8, 8),
+
+            UserOps::Choose     => (3, 1),
+            UserOps::Choose2    => (6, 2),
+            UserOps::CSwap2     => (6, 4),
+            UserOps::CSwap4     => (10, 8),
+
+            UserOps::Add        => (2, 1),
+            UserOps::Mul        => (2, 1),
+            UserOps::Inv        => (1, 1),
+            UserOps::Neg        => (1, 1),
+            UserOps::Not        => (1, 1),
+            UserOps::And        => (2, 1),
+            UserOps::Or         => (2, 1),
+
+            UserOps::Eq         => (3, 1),
+            UserOps::Cmp        => (8, 8),
+            UserOps::BinAcc     => (4, 4),
+            UserOps::ExpAcc     => (5, 5),
+
+            UserOps::RescR      => (6, 6),
+        };
+    }
+}
+
+/// Operations are serialized as their op codes, and so the encoding of an operation does not
+/// depend on where it is declared in the enum.
+impl Serialize for UserOps {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        return serializer.serialize_u8(*self as u8);
+    }
+}
+
+impl<'de> Deserialize<'de> for UserOps {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let code = u8::deserialize(deserializer)?;
+        return match UserOps::from_code(code) {
+            Some(op) => Ok(op),
+            None => Err(D::Error::custom(format!("{:#010b} is not a valid op code", code))),
+        };
+    }
 }
 
 impl std::fmt::Display for UserOps {
@@ -130,10 +270,12 @@ impl std::fmt::Display for UserOps {
             UserOps::Push       => write!(f, "push"),
             UserOps::Read       => write!(f, "read"),
             UserOps::Read2      => write!(f, "read2"),
+            UserOps::Read4      => write!(f, "read4"),
     
             UserOps::Dup        => write!(f, "dup"),
             UserOps::Dup2       => write!(f, "dup2"),
             UserOps::Dup4       => write!(f, "dup4"),
+            UserOps::Dup8       => write!(f, "dup8"),
             UserOps::Pad2       => write!(f, "pad2"),
     
             UserOps::Drop       => write!(f, "drop"),
@@ -149,6 +291,7 @@ impl std::fmt::Display for UserOps {
             UserOps::Choose     => write!(f, "choose"),
             UserOps::Choose2    => write!(f, "choose2"),
             UserOps::CSwap2     => write!(f, "cswap2"),
+            UserOps::CSwap4     => write!(f, "cswap4"),
     
             UserOps::Add        => write!(f, "add"),
             UserOps::Mul        => write!(f, "mul"),
@@ -161,6 +304,7 @@ impl std::fmt::Display for UserOps {
             UserOps::Eq         => write!(f, "eq"),
             UserOps::Cmp        => write!(f, "cmp"),
             UserOps::BinAcc     => write!(f, "binacc"),
+            UserOps::ExpAcc     => write!(f, "expacc"),
     
             UserOps::RescR      => write!(f, "rescr")
         };
@@ -169,16 +313,34 @@ impl std::fmt::Display for UserOps {
 
 // OPERATION HINTS
 // ================================================================================================
-#[derive(Copy, Clone, Debug)]
+#[derive(Copy, Clone, Debug, PartialEq, Serialize, Deserialize)]
 pub enum OpHint {
     EqStart,
     RcStart(u32),
     CmpStart(u32),
     PmpathStart(u32),
+    UnpackStart(u32),
+    ExpStart(u32),
+    DivStart,
+    BitsStart(u32),
     PushValue(u128),
     None,
 }
 
+/// Kinds of operation hints without their parameters.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum HintKind {
+    EqStart,
+    RcStart,
+    CmpStart,
+    PmpathStart,
+    UnpackStart,
+    ExpStart,
+    DivStart,
+    BitsStart,
+    PushValue,
+}
+
 impl OpHint {
     pub fn value(&self) -> u128 {
         return match self {
@@ -186,6 +348,22 @@ impl OpHint {
             _ => 0,
         };
     }
+
+    /// Returns the kind of this hint, or None if there is no hint.
+    pub fn kind(&self) -> Option<HintKind> {
+        return match self {
+            OpHint::EqStart         => Some(HintKind::EqStart),
+            OpHint::RcStart(_)      => Some(HintKind::RcStart),
+            OpHint::CmpStart(_)     => Some(HintKind::CmpStart),
+            OpHint::PmpathStart(_)  => Some(HintKind::PmpathStart),
+            OpHint::UnpackStart(_)  => Some(HintKind::UnpackStart),
+            OpHint::ExpStart(_)     => Some(HintKind::ExpStart),
+            OpHint::DivStart        => Some(HintKind::DivStart),
+            OpHint::BitsStart(_)    => Some(HintKind::BitsStart),
+            OpHint::PushValue(_)    => Some(HintKind::PushValue),
+            OpHint::None            => None,
+        };
+    }
 }
 
 impl std::fmt::Display for OpHint {
@@ -195,6 +373,10 @@ impl std::fmt::Display for OpHint {
             OpHint::RcStart(value)   => write!(f, ".{}", value),
             OpHint::CmpStart(value)     => write!(f, ".{}", value),
             OpHint::PmpathStart(value)  => write!(f, ".{}", value),
+            OpHint::UnpackStart(value)  => write!(f, ".{}", value),
+            OpHint::ExpStart(value)     => write!(f, ".{}", value),
+            OpHint::DivStart         => write!(f, "::div"),
+            OpHint::BitsStart(value)    => write!(f, ".{}", value),
             OpHint::PushValue(value)    => write!(f, "({})", value),
             OpHint::None             => Ok(()),
         };
@@ -0,0 +1,634 @@
+use std::{ cmp, collections::HashMap, ops::Range };
+use rand::{ Rng, rngs::StdRng };
+use crate::{
+    math::field,
+    utils::hasher,
+    programs::{ Program, ProgramInputs, blocks::{ ProgramBlock, Span, Group } },
+    HASH_STATE_WIDTH, MAX_STACK_DEPTH, BASE_CYCLE_LENGTH, PUSH_OP_ALIGNMENT, MAX_PUBLIC_INPUTS,
+};
+use super::{ OpCode, OpHint };
+
+// REFERENCE INTERPRETER
+// ================================================================================================
+
+/// A straightforward interpreter of user operations used to cross-check the processor. It keeps
+/// the stack in a plain vector and produces no execution trace. Only programs consisting of a
+/// single Span block are supported since control flow does not affect the stack beyond the
+/// conditions checked by the processor.
+struct Interpreter {
+    stack   : Vec<u128>,
+    tape_a  : Vec<u128>,
+    tape_b  : Vec<u128>,
+    step    : usize,
+}
+
+/// Executes the `program` against the specified inputs and returns the final state of the stack
+/// with the top of the stack first; if the program traps, an error is returned instead.
+pub fn execute(program: &Program, inputs: &ProgramInputs) -> Result<Vec<u128>, String> {
+    let body = program.root().body();
+    let span = match body {
+        [ProgramBlock::Span(span)] => span,
+        _ => return Err(String::from("only programs with a single Span block are supported")),
+    };
+
+    let mut stack = inputs.get_public_inputs().to_vec();
+    stack.reverse();
+
+    // reverse secret inputs so that they are consumed in FIFO order
+    let [tape_a, tape_b] = inputs.get_secret_inputs();
+    let mut tape_a = tape_a.clone();
+    tape_a.reverse();
+    let mut tape_b = tape_b.clone();
+    tape_b.reverse();
+
+    let mut interpreter = Interpreter { stack, tape_a, tape_b, step: 0 };
+    for i in 0..span.length() {
+        let (op_code, op_hint) = span.get_op(i);
+        interpreter.execute(op_code, op_hint)?;
+        interpreter.step += 1;
+    }
+
+    let mut result = interpreter.stack;
+    result.reverse();
+    return Ok(result);
+}
+
+impl Interpreter {
+
+    fn execute(&mut self, op_code: OpCode, op_hint: OpHint) -> Result<(), String> {
+        // hints which inspect the stack may require it to be deeper; this is checked below
+        self.check_depth(op_code.pops())?;
+
+        match op_code {
+            OpCode::Begin | OpCode::Noop => (),
+
+            OpCode::Assert => {
+                let x = self.pop();
+                if x != field::ONE { return self.trap("ASSERT failed"); }
+            },
+            OpCode::AssertEq => {
+                let x = self.pop();
+                let y = self.pop();
+                if x != y { return self.trap("ASSERTEQ failed"); }
+            },
+
+            OpCode::Push => match op_hint {
+                OpHint::PushValue(value) => self.push(value)?,
+                _ => return self.trap("PUSH without a value"),
+            },
+            OpCode::Read => {
+                match op_hint {
+                    OpHint::EqStart => {
+                        self.check_depth(2)?;
+                        let (x, y) = (self.peek(0), self.peek(1));
+                        self.tape_a.push(if x == y { field::ONE } else { field::inv(field::sub(x, y)) });
+                    },
+                    OpHint::UnpackStart(n) => {
+                        self.check_depth(1)?;
+                        let value = self.peek(0);
+                        if value >> (8 * n) != 0 { return self.trap("value too large to unpack"); }
+                        for i in 0..n { self.tape_a.push((value >> (8 * i)) & 0xFF); }
+                    },
+                    OpHint::BitsStart(n) => {
+                        self.check_depth(1)?;
+                        let value = self.peek(0);
+                        if n >= 128 || value >> n != 0 { return self.trap("value too large to decompose"); }
+                        for i in 1..n { self.tape_a.push((value >> i) & 1); }
+                    },
+                    OpHint::None => (),
+                    _ => return self.trap("invalid READ hint"),
+                }
+                let a = self.read_a()?;
+                self.push(a)?;
+            },
+            OpCode::Read2 => {
+                match op_hint {
+                    OpHint::PmpathStart(n) => {
+                        self.check_depth(3)?;
+                        let n = (n - 1) as usize;
+                        if self.tape_a.len() < n || self.tape_b.len() < n {
+                            return self.trap("too few items on tapes for pmpath");
+                        }
+                        let index = self.peek(2);
+                        let nodes = self.tape_a.split_off(self.tape_a.len() - n);
+                        for i in 0..n {
+                            self.tape_a.push((index >> (n - i - 1)) & 1);
+                            self.tape_a.push(nodes[i]);
+                        }
+                    },
+                    OpHint::DivStart => {
+                        self.check_depth(2)?;
+                        let (b, a) = (self.peek(0), self.peek(1));
+                        if a >> 32 != 0 || b >> 32 != 0 { return self.trap("operands too large to divide"); }
+                        if b == 0 { return self.trap("division by zero"); }
+                        self.tape_a.push(a / b);
+                        self.tape_b.push(a % b);
+                    },
+                    OpHint::None => (),
+                    _ => return self.trap("invalid READ2 hint"),
+                }
+                let a = self.read_a()?;
+                let b = self.read_b()?;
+                self.push(a)?;
+                self.push(b)?;
+            },
+            OpCode::Read4 => {
+                if op_hint != OpHint::None { return self.trap("invalid READ4 hint"); }
+                if self.tape_a.len() < 2 { return self.trap("empty tape A"); }
+                if self.tape_b.len() < 2 { return self.trap("empty tape B"); }
+                for _ in 0..2 {
+                    let a = self.read_a()?;
+                    let b = self.read_b()?;
+                    self.push(a)?;
+                    self.push(b)?;
+                }
+            },
+
+            OpCode::Dup     => self.dup(1)?,
+            OpCode::Dup2    => self.dup(2)?,
+            OpCode::Dup4    => self.dup(4)?,
+            OpCode::Dup8    => self.dup(8)?,
+            OpCode::Pad2    => { self.push(field::ZERO)?; self.push(field::ZERO)?; },
+
+            OpCode::Drop    => { self.pop(); },
+            OpCode::Drop4   => { for _ in 0..4 { self.pop(); } },
+
+            OpCode::Swap    => self.permute(&[1, 0])?,
+            OpCode::Swap2   => self.permute(&[2, 3, 0, 1])?,
+            OpCode::Swap4   => self.permute(&[4, 5, 6, 7, 0, 1, 2, 3])?,
+            OpCode::Roll4   => self.permute(&[3, 0, 1, 2])?,
+            OpCode::Roll8   => self.permute(&[7, 0, 1, 2, 3, 4, 5, 6])?,
+
+            OpCode::Choose => {
+                let (x, y, condition) = (self.pop(), self.pop(), self.pop());
+                match condition {
+                    field::ONE  => self.push(x)?,
+                    field::ZERO => self.push(y)?,
+                    _ => return self.trap("CHOOSE on a non-binary condition"),
+                }
+            },
+            OpCode::Choose2 => {
+                let values: Vec<u128> = (0..6).map(|_| self.pop()).collect();
+                match values[4] {
+                    field::ONE  => { self.push(values[1])?; self.push(values[0])?; },
+                    field::ZERO => { self.push(values[3])?; self.push(values[2])?; },
+                    _ => return self.trap("CHOOSE2 on a non-binary condition"),
+                }
+            },
+            OpCode::CSwap2 => {
+                let values: Vec<u128> = (0..6).map(|_| self.pop()).collect();
+                let order = match values[4] {
+                    field::ZERO => [3, 2, 1, 0],
+                    field::ONE  => [1, 0, 3, 2],
+                    _ => return self.trap("CSWAP2 on a non-binary condition"),
+                };
+                for &i in order.iter() { self.push(values[i])?; }
+            },
+            OpCode::CSwap4 => {
+                let values: Vec<u128> = (0..10).map(|_| self.pop()).collect();
+                let order = match values[8] {
+                    field::ZERO => [7, 6, 5, 4, 3, 2, 1, 0],
+                    field::ONE  => [3, 2, 1, 0, 7, 6, 5, 4],
+                    _ => return self.trap("CSWAP4 on a non-binary condition"),
+                };
+                for &i in order.iter() { self.push(values[i])?; }
+            },
+
+            OpCode::Add     => self.binary_op(|x, y| Ok(field::add(x, y)))?,
+            OpCode::Mul     => self.binary_op(|x, y| Ok(field::mul(x, y)))?,
+            OpCode::And     => self.binary_op(|x, y| Ok(to_bool(x)? & to_bool(y)?))?,
+            OpCode::Or      => self.binary_op(|x, y| Ok(to_bool(x)? | to_bool(y)?))?,
+            OpCode::Inv     => self.unary_op(|x| if x == field::ZERO { Err(()) } else { Ok(field::inv(x)) })?,
+            OpCode::Neg     => self.unary_op(|x| Ok(field::neg(x)))?,
+            OpCode::Not     => self.unary_op(|x| Ok(field::ONE - to_bool(x)?))?,
+
+            OpCode::Eq => {
+                let (aux, x, y) = (self.pop(), self.pop(), self.pop());
+                if x == y {
+                    self.push(field::ONE)?;
+                }
+                else if aux == field::inv(field::sub(x, y)) {
+                    self.push(field::ZERO)?;
+                }
+                else {
+                    return self.trap("invalid AUX value for EQ");
+                }
+            },
+            OpCode::Cmp => {
+                match op_hint {
+                    OpHint::CmpStart(n) => {
+                        self.check_depth(10)?;
+                        let (a, b) = (self.peek(8), self.peek(9));
+                        for i in 0..n {
+                            self.tape_a.push((a >> i) & 1);
+                            self.tape_b.push((b >> i) & 1);
+                        }
+                    },
+                    OpHint::None => (),
+                    _ => return self.trap("invalid CMP hint"),
+                }
+
+                let a_bit = to_bool(self.read_a()?).or_else(|_| self.trap("non-binary CMP input"))?;
+                let b_bit = to_bool(self.read_b()?).or_else(|_| self.trap("non-binary CMP input"))?;
+                let values: Vec<u128> = (0..8).map(|_| self.pop()).collect();
+
+                let power_of_two = values[0];
+                if !power_of_two.is_power_of_two() { return self.trap("CMP without a power of 2"); }
+                let next_power_of_two = if power_of_two == 1 { field::div(1, 2) } else { power_of_two >> 1 };
+
+                // the result is updated only for the most significant differing bits
+                let (gt, lt) = (values[4], values[5]);
+                let not_set = field::mul(field::sub(1, gt), field::sub(1, lt));
+                let bit_gt = field::mul(a_bit, field::sub(1, b_bit));
+                let bit_lt = field::mul(b_bit, field::sub(1, a_bit));
+
+                let result = [
+                    next_power_of_two, a_bit, b_bit, not_set,
+                    field::add(gt, field::mul(bit_gt, not_set)),
+                    field::add(lt, field::mul(bit_lt, not_set)),
+                    field::add(values[6], field::mul(b_bit, power_of_two)),
+                    field::add(values[7], field::mul(a_bit, power_of_two)),
+                ];
+                for &value in result.iter().rev() { self.push(value)?; }
+            },
+            OpCode::BinAcc => {
+                match op_hint {
+                    OpHint::RcStart(n) => {
+                        self.check_depth(5)?;
+                        let value = self.peek(4);
+                        for i in 0..n { self.tape_a.push((value >> (n - i - 1)) & 1); }
+                    },
+                    OpHint::None => (),
+                    _ => return self.trap("invalid BINACC hint"),
+                }
+
+                let bit = to_bool(self.read_a()?).or_else(|_| self.trap("non-binary BINACC input"))?;
+                let values: Vec<u128> = (0..4).map(|_| self.pop()).collect();
+
+                let power_of_two = values[2];
+                if !power_of_two.is_power_of_two() { return self.trap("BINACC without a power of 2"); }
+
+                let result = [
+                    bit, field::ZERO, field::mul(power_of_two, 2),
+                    field::add(values[3], field::mul(bit, power_of_two)),
+                ];
+                for &value in result.iter().rev() { self.push(value)?; }
+            },
+            OpCode::ExpAcc => {
+                match op_hint {
+                    OpHint::ExpStart(n) => {
+                        self.check_depth(6)?;
+                        let exp = self.peek(5);
+                        for i in 0..n { self.tape_a.push((exp >> (n - i - 1)) & 1); }
+                    },
+                    OpHint::None => (),
+                    _ => return self.trap("invalid EXPACC hint"),
+                }
+
+                let bit = to_bool(self.read_a()?).or_else(|_| self.trap("non-binary EXPACC input"))?;
+                let values: Vec<u128> = (0..5).map(|_| self.pop()).collect();
+
+                let power_of_two = values[3];
+                if !power_of_two.is_power_of_two() { return self.trap("EXPACC without a power of 2"); }
+
+                let (base, result) = (values[1], values[2]);
+                let result = [
+                    bit, field::mul(base, base),
+                    if bit == field::ONE { field::mul(result, base) } else { result },
+                    field::mul(power_of_two, 2),
+                    field::add(values[4], field::mul(bit, power_of_two)),
+                ];
+                for &value in result.iter().rev() { self.push(value)?; }
+            },
+
+            OpCode::RescR => {
+                let mut state: Vec<u128> = (0..HASH_STATE_WIDTH).map(|_| self.pop()).collect();
+                hasher::apply_round(&mut state, self.step);
+                for &value in state.iter().rev() { self.push(value)?; }
+            },
+        }
+
+        return Ok(());
+    }
+
+    // HELPER METHODS
+    // --------------------------------------------------------------------------------------------
+
+    fn trap<T>(&self, message: &str) -> Result<T, String> {
+        return Err(format!("{} at step {}", message, self.step));
+    }
+
+    fn check_depth(&self, depth: usize) -> Result<(), String> {
+        if self.stack.len() < depth { return self.trap("stack underflow"); }
+        return Ok(());
+    }
+
+    fn peek(&self, index: usize) -> u128 {
+        return self.stack[self.stack.len() - 1 - index];
+    }
+
+    fn pop(&mut self) -> u128 {
+        return self.stack.pop().unwrap();
+    }
+
+    fn push(&mut self, value: u128) -> Result<(), String> {
+        if self.stack.len() == MAX_STACK_DEPTH { return self.trap("stack overflow"); }
+        self.stack.push(value);
+        return Ok(());
+    }
+
+    fn read_a(&mut self) -> Result<u128, String> {
+        return self.tape_a.pop().map_or_else(|| self.trap("empty tape A"), Ok);
+    }
+
+    fn read_b(&mut self) -> Result<u128, String> {
+        return self.tape_b.pop().map_or_else(|| self.trap("empty tape B"), Ok);
+    }
+
+    fn dup(&mut self, n: usize) -> Result<(), String> {
+        for _ in 0..n { self.push(self.peek(n - 1))?; }
+        return Ok(());
+    }
+
+    /// Rearranges the top items of the stack such that item at position i is taken from
+    /// position `sources[i]`.
+    fn permute(&mut self, sources: &[usize]) -> Result<(), String> {
+        let values: Vec<u128> = (0..sources.len()).map(|_| self.pop()).collect();
+        for &source in sources.iter().rev() { self.stack.push(values[source]); }
+        return Ok(());
+    }
+
+    fn unary_op(&mut self, op: fn(u128) -> Result<u128, ()>) -> Result<(), String> {
+        let x = self.pop();
+        let result = op(x).or_else(|_| self.trap("invalid operand"))?;
+        return self.push(result);
+    }
+
+    fn binary_op(&mut self, op: fn(u128, u128) -> Result<u128, ()>) -> Result<(), String> {
+        let (x, y) = (self.pop(), self.pop());
+        let result = op(x, y).or_else(|_| self.trap("invalid operands"))?;
+        return self.push(result);
+    }
+}
+
+fn to_bool(value: u128) -> Result<u128, ()> {
+    return if value == field::ZERO || value == field::ONE { Ok(value) } else { Err(()) };
+}
+
+// RANDOM PROGRAMS
+// ================================================================================================
+
+/// Operations which can be placed at any step of a program; BEGIN starts every program, and
+/// PUSH can be placed only on steps which are multiples of 8. Operations after the first
+/// 16 check values they consume and may trap even when the stack is deep enough.
+const OPS: [OpCode; 34] = [
+    OpCode::Noop,   OpCode::Read2,  OpCode::Read4,    OpCode::Dup,     OpCode::Dup2,
+    OpCode::Dup4,   OpCode::Dup8,   OpCode::Pad2,     OpCode::Drop,    OpCode::Drop4,
+    OpCode::Swap,   OpCode::Swap2,  OpCode::Swap4,    OpCode::Roll4,   OpCode::Roll8,
+    OpCode::Add,    OpCode::Mul,    OpCode::Neg,      OpCode::RescR,   OpCode::Assert,
+    OpCode::AssertEq, OpCode::Read, OpCode::Choose,   OpCode::Choose2, OpCode::CSwap2,
+    OpCode::CSwap4, OpCode::And,    OpCode::Or,       OpCode::Inv,     OpCode::Not,
+    OpCode::Eq,     OpCode::Cmp,    OpCode::BinAcc,   OpCode::ExpAcc,
+];
+const NUM_UNCHECKED_OPS: usize = 19;
+
+/// Builds a random single-span program together with random inputs. Operations are picked
+/// so that the stack is usually deep enough for them and does not overflow, and values are drawn mostly from a
+/// small set so that operations which expect binary values or powers of 2 succeed reasonably
+/// often; programs which trap are still generated regularly. Input tapes are usually long
+/// enough for all reads of the program. Operations which check their operands are picked with
+/// probability `p_checked`.
+pub fn build_random_program(rng: &mut StdRng, num_cycles: Range<usize>, p_checked: f64) -> (Program, ProgramInputs) {
+    let public: Vec<u128> = (0..rng.gen_range(1, MAX_PUBLIC_INPUTS + 1)).map(|_| random_value(rng)).collect();
+
+    let length = rng.gen_range(num_cycles.start, num_cycles.end) * BASE_CYCLE_LENGTH - 1;
+    let mut instructions = vec![OpCode::Begin];
+    let mut hints = HashMap::new();
+    let mut depth = public.len() as isize;
+
+    while instructions.len() < length {
+        let step = instructions.len();
+        let op_code = if step % PUSH_OP_ALIGNMENT == 0 && depth < MAX_STACK_DEPTH as isize && rng.gen_bool(0.5) {
+            OpCode::Push
+        }
+        else {
+            // retry a few times to find an operation which does not underflow or overflow the stack
+            let mut op_code = random_op(rng, p_checked);
+            for _ in 0..8 {
+                let new_depth = depth + op_code.stack_delta() as isize;
+                if required_depth(op_code) <= depth && new_depth <= MAX_STACK_DEPTH as isize { break; }
+                op_code = random_op(rng, p_checked);
+            }
+            op_code
+        };
+
+        let op_hint = match op_code {
+            OpCode::Push => OpHint::PushValue(random_value(rng)),
+            OpCode::Read => match rng.gen_range(0, 5) {
+                0 => OpHint::EqStart,
+                1 => OpHint::UnpackStart(rng.gen_range(1, 16)),
+                2 => OpHint::BitsStart(rng.gen_range(2, 16)),
+                _ => OpHint::None,
+            },
+            OpCode::Read2 => match rng.gen_range(0, 10) {
+                0 | 1 => OpHint::PmpathStart(rng.gen_range(2, 5)),
+                2 => OpHint::DivStart,
+                _ => OpHint::None,
+            },
+            OpCode::Cmp    if rng.gen_bool(0.5) => OpHint::CmpStart(rng.gen_range(1, 8)),
+            OpCode::BinAcc if rng.gen_bool(0.5) => OpHint::RcStart(rng.gen_range(1, 8)),
+            OpCode::ExpAcc if rng.gen_bool(0.5) => OpHint::ExpStart(rng.gen_range(1, 8)),
+            _ => OpHint::None,
+        };
+
+        match op_hint {
+            OpHint::None => (),
+            _ => { hints.insert(step, op_hint); },
+        }
+        instructions.push(op_code);
+        depth += op_code.stack_delta() as isize;
+    }
+
+    // size input tapes to the number of values the program reads from them; one in 10 tapes
+    // is cut short so that programs which run out of tape values are generated as well; tape B
+    // cannot be longer than tape A
+    let (reads_a, reads_b) = count_tape_reads(&instructions);
+    let tape_a: Vec<u128> = (0..random_tape_length(rng, reads_a)).map(|_| random_value(rng)).collect();
+    let length_b = cmp::min(random_tape_length(rng, reads_b), tape_a.len());
+    let tape_b: Vec<u128> = (0..length_b).map(|_| random_value(rng)).collect();
+
+    let root = vec![ProgramBlock::Span(Span::new(instructions, hints).unwrap())];
+    let program = Program::new(Group::new(root));
+    return (program, ProgramInputs::new(&public, &tape_a, &tape_b));
+}
+
+/// Returns the number of values read from input tapes A and B by the specified operations.
+fn count_tape_reads(instructions: &[OpCode]) -> (usize, usize) {
+    return instructions.iter().fold((0, 0), |(a, b), op_code| match op_code {
+        OpCode::Read  => (a + 1, b),
+        OpCode::Read2 => (a + 1, b + 1),
+        OpCode::Read4 => (a + 2, b + 2),
+        _ => (a, b),
+    });
+}
+
+/// Returns a random length of a tape from which `num_reads` values are read.
+fn random_tape_length(rng: &mut StdRng, num_reads: usize) -> usize {
+    return if rng.gen_bool(0.1) {
+        rng.gen_range(0, num_reads + 1)
+    }
+    else {
+        num_reads + rng.gen_range(0, 4)
+    };
+}
+
+/// Returns a random operation; operations which check their operands are picked with
+/// probability `p_checked`.
+fn random_op(rng: &mut StdRng, p_checked: f64) -> OpCode {
+    return if rng.gen_bool(p_checked) {
+        OPS[rng.gen_range(NUM_UNCHECKED_OPS, OPS.len())]
+    }
+    else {
+        OPS[rng.gen_range(0, NUM_UNCHECKED_OPS)]
+    };
+}
+
+/// Returns the stack depth needed by the operation together with any hint it may have.
+fn required_depth(op_code: OpCode) -> isize {
+    let hint_depth = match op_code {
+        OpCode::Read    => 2,
+        OpCode::Read2   => 3,
+        OpCode::Cmp     => 10,
+        OpCode::BinAcc  => 5,
+        OpCode::ExpAcc  => 6,
+        _ => 0,
+    };
+    return usize::max(op_code.pops(), hint_depth) as isize;
+}
+
+pub fn random_value(rng: &mut StdRng) -> u128 {
+    return match rng.gen_range(0, 8) {
+        0 | 1 | 2 => field::ZERO,
+        3 | 4 | 5 => field::ONE,
+        6 => 1 << rng.gen_range(1, 8),
+        _ => field::rand(),
+    };
+}
+
+// TESTS
+// ================================================================================================
+#[cfg(test)]
+mod tests {
+
+    use rand::{ Rng, SeedableRng, rngs::StdRng };
+    use crate::{
+        math::field,
+        programs::{ Program, ProgramInputs },
+        stark::TraceState,
+        testing::shrink,
+        NUM_DEPTH_BITS,
+    };
+    use crate::processor::{ HintKind, ExecutionError, opcodes::USER_OPS };
+    use super::{ Interpreter, OpHint, build_random_program, random_value };
+
+    const NUM_PROGRAMS: u64 = 2000;
+    const NUM_STACKS: usize = 20;
+
+    #[test]
+    fn processor_matches_reference() {
+        let mut num_trapped = 0;
+        for seed in 0..NUM_PROGRAMS {
+            let mut rng = StdRng::seed_from_u64(seed);
+            let (program, inputs) = build_random_program(&mut rng, 1..4, 0.1);
+
+            match compare_with_processor(&program, &inputs) {
+                Ok(true) => num_trapped += 1,
+                Ok(false) => (),
+                Err(_) => {
+                    // report the simplest program which still shows the difference
+                    let (program, inputs) = shrink(&program, &inputs, |p, i| compare_with_processor(p, i).is_err());
+                    let err = compare_with_processor(&program, &inputs).unwrap_err();
+                    panic!("{} with seed {}; shrunk program: {:?}; inputs: {:?}", err, seed, program, inputs);
+                },
+            }
+        }
+
+        // make sure both outcomes are well represented
+        assert!(num_trapped > NUM_PROGRAMS / 10 && num_trapped < NUM_PROGRAMS * 9 / 10,
+            "{} out of {} programs trapped", num_trapped, NUM_PROGRAMS);
+    }
+
+    #[test]
+    fn stack_effects_match_reference() {
+        let mut rng = StdRng::seed_from_u64(0);
+        for &op_code in USER_OPS.iter() {
+            let (pops, pushes) = (op_code.pops(), op_code.pushes());
+
+            for _ in 0..NUM_STACKS {
+                // items taken by the operation are ONEs so that checks of their values pass; items
+                // below them are random and must not be affected by the operation
+                let num_untouched = rng.gen_range(0, 8);
+                let mut stack: Vec<u128> = (0..num_untouched).map(|_| random_value(&mut rng)).collect();
+                stack.resize(num_untouched + pops, field::ONE);
+
+                let op_hint = match op_code.requires_hint() {
+                    Some(HintKind::PushValue) => OpHint::PushValue(random_value(&mut rng)),
+                    _ => OpHint::None,
+                };
+
+                let mut interpreter = build_interpreter(stack.clone());
+                if let Err(err) = interpreter.execute(op_code, op_hint) {
+                    panic!("{} failed on stack {:?}: {}", op_code, stack, err);
+                }
+                assert_eq!(num_untouched + pushes, interpreter.stack.len(), "wrong stack effect for {}", op_code);
+                assert_eq!(&stack[..num_untouched], &interpreter.stack[..num_untouched],
+                    "{} modified items it does not take from the stack", op_code);
+            }
+
+            // the operation cannot be executed on a stack which is shallower than it needs
+            if pops > 0 {
+                let mut interpreter = build_interpreter(vec![field::ONE; pops - 1]);
+                let result = interpreter.execute(op_code, OpHint::None);
+                assert_eq!(Err(String::from("stack underflow at step 1")), result, "{} did not underflow", op_code);
+            }
+
+            // operations which require a hint cannot be executed without one
+            if op_code.requires_hint().is_some() {
+                let mut interpreter = build_interpreter(vec![field::ONE; pops]);
+                assert!(interpreter.execute(op_code, OpHint::None).is_err(), "{} executed without a hint", op_code);
+            }
+
+            assert!(!op_code.is_control_flow() || (pops == 0 && pushes == 0));
+        }
+    }
+
+    fn build_interpreter(stack: Vec<u128>) -> Interpreter {
+        let tape = vec![field::ONE; 2];
+        return Interpreter { stack, tape_a: tape.clone(), tape_b: tape, step: 1 };
+    }
+
+    /// Executes the program using both the reference interpreter and the processor; returns true
+    /// if both trapped, false if both produced the same stack, or a description of the difference.
+    fn compare_with_processor(program: &Program, inputs: &ProgramInputs) -> Result<bool, String> {
+        let expected = super::execute(program, inputs);
+        let actual = execute_processor(program, inputs);
+
+        return match (expected, actual) {
+            (Ok(expected), Ok(actual)) if expected == actual => Ok(false),
+            (Ok(expected), Ok(actual)) => Err(format!("final stacks differ: {:?} vs. {:?}", expected, actual)),
+            (Err(_), Err(_)) => Ok(true),
+            (Ok(_), Err(_)) => Err(String::from("processor trapped")),
+            (Err(e), Ok(_)) => Err(format!("reference trapped ({})", e)),
+        };
+    }
+
+    /// Executes the program using the processor and reads the final stack from the trace.
+    fn execute_processor(program: &Program, inputs: &ProgramInputs) -> Result<Vec<u128>, ExecutionError> {
+        let (trace, ctx_depth, loop_depth) = crate::processor::execute(program, inputs)?;
+        let decoder_width = TraceState::compute_decoder_width(ctx_depth, loop_depth);
+        let last_step = trace[0].len() - 1;
+        let depth = trace[(trace.len() - NUM_DEPTH_BITS)..].iter().enumerate()
+            .fold(0, |depth, (i, r)| depth | ((r[last_step] as usize) << i));
+        return Ok(trace[decoder_width..(decoder_width + depth)].iter().map(|r| r[last_step]).collect());
+    }
+}
@@ -1,10 +1,13 @@
 use crate::{
     math::field,
     utils::hasher,
-    ProgramInputs, OpCode, OpHint,
-    HASH_STATE_WIDTH, MIN_STACK_DEPTH, MAX_STACK_DEPTH,
+    programs::TapeProvider,
+    ProgramInputs, TapeId, StackView, OpCode, OpHint,
+    HASH_STATE_WIDTH, MIN_STACK_DEPTH, MAX_STACK_DEPTH, NUM_DEPTH_BITS,
 };
 
+use super::ExecutionError;
+
 #[cfg(test)]
 mod tests;
 
@@ -12,8 +15,12 @@ mod tests;
 // ================================================================================================
 pub struct Stack {
     registers   : Vec<Vec<u128>>,
+    depth_trace : Vec<u128>,
     tape_a      : Vec<u128>,
     tape_b      : Vec<u128>,
+    unread      : [usize; 2],
+    provider    : Option<TapeProvider>,
+    provided    : [Vec<u128>; 2],
     max_depth   : usize,
     depth       : usize,
     step        : usize,
@@ -47,42 +54,67 @@ impl Stack {
         let mut tape_b = secret_inputs_b.clone();
         tape_b.reverse();
 
+        // the depth register tracks depth of the stack at each step, less the number of items
+        // read by the operation executed at that step
+        let mut depth_trace = vec![field::ZERO; init_trace_length];
+        depth_trace[0] = public_inputs.len() as u128;
+
         return Stack {
             registers,
+            depth_trace,
+            unread: [tape_a.len(), tape_b.len()],
             tape_a,
             tape_b,
+            provider: inputs.get_tape_provider().cloned(),
+            provided: [Vec::new(), Vec::new()],
             max_depth: public_inputs.len(),
             depth: public_inputs.len(),
             step: 0,
         };
     }
 
-    /// Executes `opcode` against the current state of the stack.
-    pub fn execute(&mut self, op_code: OpCode, op_hint: OpHint) {
+    /// Executes `opcode` against the current state of the stack; returns an error if the
+    /// operation cannot be applied to the current state of the stack.
+    pub fn execute(&mut self, op_code: OpCode, op_hint: OpHint) -> Result<(), ExecutionError> {
 
         // increment step pointer and make sure there is enough memory allocated to hold the trace
         self.advance_step();
 
+        // make sure the stack holds enough values for the operation, and will not overflow
+        let (pops, pushes) = op_code.stack_effect();
+        if self.depth < pops {
+            return Err(ExecutionError::StackUnderflow { step: self.step, op: op_code });
+        }
+        if self.depth - pops + pushes > MAX_STACK_DEPTH {
+            return Err(ExecutionError::StackOverflow { step: self.step, op: op_code });
+        }
+
+        // depth registers at the previous step hold the number of items left below the items
+        // read by the operation; this way, constraints can make sure the items exist
+        self.depth_trace[self.step - 1] = (self.depth - pops) as u128;
+
         // execute the appropriate action against the current state of the stack
         match op_code {
 
             OpCode::Begin       => self.op_noop(),
             OpCode::Noop        => self.op_noop(),
 
-            OpCode::Assert      => self.op_assert(),
-            OpCode::AssertEq    => self.op_asserteq(),
+            OpCode::Assert      => self.op_assert()?,
+            OpCode::AssertEq    => self.op_asserteq()?,
 
-            OpCode::Push        => self.op_push(op_hint),
-            OpCode::Read        => self.op_read(op_hint),
-            OpCode::Read2       => self.op_read2(op_hint),
+            OpCode::Push        => self.op_push(op_hint)?,
+            OpCode::Read        => self.op_read(op_hint)?,
+            OpCode::Read2       => self.op_read2(op_hint)?,
+            OpCode::Read4       => self.op_read4(op_hint)?,
 
-            OpCode::Dup         => self.op_dup(),
-            OpCode::Dup2        => self.op_dup2(),
-            OpCode::Dup4        => self.op_dup4(),
-            OpCode::Pad2        => self.op_pad2(),
+            OpCode::Dup         => self.op_dup()?,
+            OpCode::Dup2        => self.op_dup2()?,
+            OpCode::Dup4        => self.op_dup4()?,
+            OpCode::Dup8        => self.op_dup8()?,
+            OpCode::Pad2        => self.op_pad2()?,
 
-            OpCode::Drop        => self.op_drop(),
-            OpCode::Drop4       => self.op_drop4(),
+            OpCode::Drop        => self.op_drop()?,
+            OpCode::Drop4       => self.op_drop4()?,
 
             OpCode::Swap        => self.op_swap(),
             OpCode::Swap2       => self.op_swap2(),
@@ -91,24 +123,30 @@ impl Stack {
             OpCode::Roll4       => self.op_roll4(),
             OpCode::Roll8       => self.op_roll8(),
 
-            OpCode::Choose      => self.op_choose(),
-            OpCode::Choose2     => self.op_choose2(),
-            OpCode::CSwap2      => self.op_cswap2(),
+            OpCode::Choose      => self.op_choose()?,
+            OpCode::Choose2     => self.op_choose2()?,
+            OpCode::CSwap2      => self.op_cswap2()?,
+            OpCode::CSwap4      => self.op_cswap4()?,
 
-            OpCode::Add         => self.op_add(),
-            OpCode::Mul         => self.op_mul(),
-            OpCode::Inv         => self.op_inv(),
+            OpCode::Add         => self.op_add()?,
+            OpCode::Mul         => self.op_mul()?,
+            OpCode::Inv         => self.op_inv()?,
             OpCode::Neg         => self.op_neg(),
-            OpCode::Not         => self.op_not(),
-            OpCode::And         => self.op_and(),
-            OpCode::Or          => self.op_or(),
+            OpCode::Not         => self.op_not()?,
+            OpCode::And         => self.op_and()?,
+            OpCode::Or          => self.op_or()?,
+            OpCode::ExpAcc      => self.op_expacc(op_hint)?,
 
-            OpCode::Eq          => self.op_eq(),
-            OpCode::Cmp         => self.op_cmp(op_hint),
-            OpCode::BinAcc      => self.op_binacc(op_hint),
+            OpCode::Eq          => self.op_eq()?,
+            OpCode::Cmp         => self.op_cmp(op_hint)?,
+            OpCode::BinAcc      => self.op_binacc(op_hint)?,
 
             OpCode::RescR       => self.op_rescr(),
         }
+
+        // record stack depth after the operation was executed
+        self.depth_trace[self.step] = self.depth as u128;
+        return Ok(());
     }
 
     /// Returns trace length of register traces in the decoder.
@@ -122,6 +160,18 @@ impl Stack {
         return self.step;
     }
 
+    /// Returns the number of values from secret input tapes A and B which have not been
+    /// consumed so far; values placed onto the tapes by execution hints are not counted.
+    pub fn unread_inputs(&self) -> [usize; 2] {
+        return self.unread;
+    }
+
+    /// Returns the values which the tape provider supplied for secret input tapes A and B,
+    /// in the order in which they were supplied.
+    pub fn provided_inputs(&self) -> &[Vec<u128>; 2] {
+        return &self.provided;
+    }
+
     /// Returns the value at the top of the stack at the current step.
     pub fn get_stack_top(&self) -> u128 {
         return self.registers[0][self.step];
@@ -135,14 +185,21 @@ impl Stack {
             register.resize(self.step + 1, field::ZERO);
             register.resize(trace_length, register[self.step]);
         }
+        self.depth_trace.resize(self.step + 1, field::ZERO);
+        self.depth_trace.resize(trace_length, self.depth_trace[self.step]);
 
         // update the step pointer to point to the last step
         self.step = self.trace_length() - 1;
     }
 
-    /// Merges all register traces into a single vector of traces.
+    /// Merges all register traces into a single vector of traces; the depth floor is decomposed
+    /// into NUM_DEPTH_BITS binary registers (least significant bit first) which are placed
+    /// after all stack registers.
     pub fn into_register_traces(mut self) -> Vec<Vec<u128>> {
         self.registers.truncate(self.max_depth);
+        for i in 0..NUM_DEPTH_BITS {
+            self.registers.push(self.depth_trace.iter().map(|&d| (d >> i) & 1).collect());
+        }
         return self.registers;
     }
 
@@ -152,40 +209,45 @@ impl Stack {
         self.copy_state(0);
     }
 
-    fn op_assert(&mut self) {
-        assert!(self.depth >= 1, "stack underflow at step {}", self.step);
+    fn op_assert(&mut self) -> Result<(), ExecutionError> {
         let value = self.registers[0][self.step - 1];
-        assert!(value == field::ONE, "ASSERT failed at step {}", self.step);
-        self.shift_left(1, 1);
+        if value != field::ONE {
+            return Err(ExecutionError::FailedAssertion { step: self.step, op: OpCode::Assert, value });
+        }
+        self.shift_left(1, 1, OpCode::Assert)?;
+        return Ok(());
     }
 
-    fn op_asserteq(&mut self) {
-        assert!(self.depth >= 2, "stack underflow at step {}", self.step);
+    fn op_asserteq(&mut self) -> Result<(), ExecutionError> {
         let x = self.registers[0][self.step - 1];
         let y = self.registers[1][self.step - 1];
-        assert!(x == y, "ASSERTEQ failed at step {}", self.step);
-        self.shift_left(2, 2);
+        if x != y {
+            return Err(ExecutionError::UnequalValues { step: self.step, op: OpCode::AssertEq, values: [x, y] });
+        }
+        self.shift_left(2, 2, OpCode::AssertEq)?;
+        return Ok(());
     }
 
     // INPUT OPERATIONS
     // --------------------------------------------------------------------------------------------
-    fn op_push(&mut self, hint: OpHint) {
-        self.shift_right(0, 1);
+    fn op_push(&mut self, hint: OpHint) -> Result<(), ExecutionError> {
         let op_value = match hint {
             OpHint::PushValue(value) => value,
-            _ => panic!("invalid value for PUSH operation at step {}", self.step),
+            _ => return Err(ExecutionError::InvalidHint { step: self.step, op: OpCode::Push }),
         };
+        self.shift_right(0, 1, OpCode::Push)?;
         self.registers[0][self.step] = op_value;
+        return Ok(());
     }
 
-    fn op_read(&mut self, hint: OpHint) {
+    fn op_read(&mut self, hint: OpHint) -> Result<(), ExecutionError> {
         // process execution hint
         match hint {
             OpHint::EqStart => {
                 // if we are about to equality comparison sequence, push inverse of the difference
                 // between top two stack values onto secret tape A, if they are equal; otherwise
                 // push value 1
-                assert!(self.depth >= 2, "stack underflow at step {}", self.step);
+                self.check_depth(2, OpCode::Read)?;
                 let x = self.registers[0][self.step - 1];
                 let y = self.registers[1][self.step - 1];
                 if x == y {
@@ -195,26 +257,52 @@ impl Stack {
                     self.tape_a.push(field::inv(field::sub(x, y)));
                 }
             },
-            OpHint::None => {
-                assert!(self.tape_a.len() > 0, "attempt to read from empty tape A at step {}", self.step);
+            OpHint::UnpackStart(n) => {
+                // if we are about to start unpacking a value into bytes, push the bytes onto
+                // tape A so that the most significant byte is read first
+                self.check_depth(1, OpCode::Read)?;
+                let value = self.registers[0][self.step - 1];
+                if value >> (8 * n) != 0 {
+                    return Err(ExecutionError::InvalidOperand { step: self.step, op: OpCode::Read, value });
+                }
+                for i in 0..n {
+                    self.tape_a.push((value >> (8 * i)) & 0xFF);
+                }
             },
-            _ => panic!("execution hint {:?} is not valid for READ operation", hint)
+            OpHint::BitsStart(n) => {
+                // if we are about to start decomposing a value into n bits, push all bits but
+                // the least significant one onto tape A so that the most significant bit is
+                // read first; the least significant bit is derived from the other bits
+                self.check_depth(1, OpCode::Read)?;
+                let value = self.registers[0][self.step - 1];
+                if n >= 128 || value >> n != 0 {
+                    return Err(ExecutionError::InvalidOperand { step: self.step, op: OpCode::Read, value });
+                }
+                for i in 1..n {
+                    self.tape_a.push((value >> i) & 1);
+                }
+            },
+            OpHint::None => self.fill_tape(TapeId::A, 1, OpCode::Read)?,
+            _ => return Err(ExecutionError::InvalidHint { step: self.step, op: OpCode::Read }),
         }
 
-        self.shift_right(0, 1);
+        self.shift_right(0, 1, OpCode::Read)?;
         let value = self.tape_a.pop().unwrap();
         self.registers[0][self.step] = value;
+        self.update_unread_inputs();
+        return Ok(());
     }
 
-    fn op_read2(&mut self, hint: OpHint) {
+    fn op_read2(&mut self, hint: OpHint) -> Result<(), ExecutionError> {
         // process execution hint
         match hint {
+            OpHint::PmpathStart(0) => return Err(ExecutionError::InvalidHint { step: self.step, op: OpCode::Read2 }),
             OpHint::PmpathStart(n) => {
-                assert!(self.depth >= 3, "stack underflow at step {}", self.step);
+                self.check_depth(3, OpCode::Read2)?;
 
                 let n = (n - 1) as usize;
-                assert!(self.tape_a.len() >= n, "too few items on tape A for pmpath macro");
-                assert!(self.tape_b.len() >= n, "too few items on tape B for pmpath macro");
+                self.fill_tape(TapeId::A, n, OpCode::Read2)?;
+                self.fill_tape(TapeId::B, n, OpCode::Read2)?;
 
                 let idx = self.registers[2][self.step - 1];
 
@@ -222,6 +310,7 @@ impl Stack {
                 // sure it is interlaced with node values already present there. To do this,
                 // we first remove top n values from tape A
                 let v_a = self.tape_a.split_off(self.tape_a.len() - n);
+                self.update_unread_inputs();
 
                 // then, we reinsert them while interlacing node and leaf index binary values
                 for i in 0..n {
@@ -230,69 +319,116 @@ impl Stack {
                     self.tape_a.push(v_a[i]);
                 }
             },
+            OpHint::DivStart => {
+                // if we are about to divide two 32-bit integers, push the quotient onto tape A
+                // and the remainder onto tape B; the divisor is at the top of the stack
+                self.check_depth(2, OpCode::Read2)?;
+                let b = self.registers[0][self.step - 1];
+                let a = self.registers[1][self.step - 1];
+                for &value in [b, a].iter() {
+                    if value >> 32 != 0 {
+                        return Err(ExecutionError::InvalidOperand { step: self.step, op: OpCode::Read2, value });
+                    }
+                }
+                if b == 0 {
+                    return Err(ExecutionError::DivisionByZero { step: self.step, op: OpCode::Read2 });
+                }
+                self.tape_a.push(a / b);
+                self.tape_b.push(a % b);
+            },
             OpHint::None => {
-                assert!(self.tape_a.len() > 0, "attempt to read from empty tape A at step {}", self.step);
-                assert!(self.tape_b.len() > 0, "attempt to read from empty tape B at step {}", self.step);
+                self.fill_tape(TapeId::A, 1, OpCode::Read2)?;
+                self.fill_tape(TapeId::B, 1, OpCode::Read2)?;
             },
-            _ => panic!("execution hint {:?} is not valid for READ2 operation", hint)
+            _ => return Err(ExecutionError::InvalidHint { step: self.step, op: OpCode::Read2 }),
         }
 
-        self.shift_right(0, 2);
+        self.shift_right(0, 2, OpCode::Read2)?;
         let value_a = self.tape_a.pop().unwrap();
         let value_b = self.tape_b.pop().unwrap();
         self.registers[0][self.step] = value_b;
         self.registers[1][self.step] = value_a;
+        self.update_unread_inputs();
+        return Ok(());
+    }
+
+    fn op_read4(&mut self, hint: OpHint) -> Result<(), ExecutionError> {
+        match hint {
+            OpHint::None => (),
+            _ => return Err(ExecutionError::InvalidHint { step: self.step, op: OpCode::Read4 }),
+        }
+
+        // READ4 is equivalent to two READ2 operations, and so it needs two values on each tape
+        self.fill_tape(TapeId::A, 2, OpCode::Read4)?;
+        self.fill_tape(TapeId::B, 2, OpCode::Read4)?;
+
+        self.shift_right(0, 4, OpCode::Read4)?;
+        let value_a0 = self.tape_a.pop().unwrap();
+        let value_b0 = self.tape_b.pop().unwrap();
+        let value_a1 = self.tape_a.pop().unwrap();
+        let value_b1 = self.tape_b.pop().unwrap();
+        self.registers[0][self.step] = value_b1;
+        self.registers[1][self.step] = value_a1;
+        self.registers[2][self.step] = value_b0;
+        self.registers[3][self.step] = value_a0;
+        self.update_unread_inputs();
+        return Ok(());
     }
 
     // STACK MANIPULATION OPERATIONS
     // --------------------------------------------------------------------------------------------
-    fn op_dup(&mut self) {
-        assert!(self.depth >= 1, "stack underflow at step {}", self.step);
-        self.shift_right(0, 1);
+    fn op_dup(&mut self) -> Result<(), ExecutionError> {
+        self.shift_right(0, 1, OpCode::Dup)?;
         self.registers[0][self.step] = self.registers[0][self.step - 1];
+        return Ok(());
     }
 
-    fn op_dup2(&mut self) {
-        assert!(self.depth >= 2, "stack underflow at step {}", self.step);
-        self.shift_right(0, 2);
+    fn op_dup2(&mut self) -> Result<(), ExecutionError> {
+        self.shift_right(0, 2, OpCode::Dup2)?;
         self.registers[0][self.step] = self.registers[0][self.step - 1];
         self.registers[1][self.step] = self.registers[1][self.step - 1];
+        return Ok(());
     }
 
-    fn op_dup4(&mut self) {
-        assert!(self.depth >= 4, "stack underflow at step {}", self.step);
-        self.shift_right(0, 4);
+    fn op_dup4(&mut self) -> Result<(), ExecutionError> {
+        self.shift_right(0, 4, OpCode::Dup4)?;
         self.registers[0][self.step] = self.registers[0][self.step - 1];
         self.registers[1][self.step] = self.registers[1][self.step - 1];
         self.registers[2][self.step] = self.registers[2][self.step - 1];
         self.registers[3][self.step] = self.registers[3][self.step - 1];
+        return Ok(());
     }
 
-    fn op_pad2(&mut self) {
-        self.shift_right(0, 2);
+    fn op_dup8(&mut self) -> Result<(), ExecutionError> {
+        self.shift_right(0, 8, OpCode::Dup8)?;
+        for i in 0..8 {
+            self.registers[i][self.step] = self.registers[i][self.step - 1];
+        }
+        return Ok(());
+    }
+
+    fn op_pad2(&mut self) -> Result<(), ExecutionError> {
+        self.shift_right(0, 2, OpCode::Pad2)?;
         self.registers[0][self.step] = field::ZERO;
         self.registers[1][self.step] = field::ZERO;
+        return Ok(());
     }
 
-    fn op_drop(&mut self) {
-        assert!(self.depth >= 1, "stack underflow at step {}", self.step);
-        self.shift_left(1, 1);
+    fn op_drop(&mut self) -> Result<(), ExecutionError> {
+        return self.shift_left(1, 1, OpCode::Drop);
     }
 
-    fn op_drop4(&mut self) {
-        assert!(self.depth >= 4, "stack underflow at step {}", self.step);
-        self.shift_left(4, 4);
+    fn op_drop4(&mut self) -> Result<(), ExecutionError> {
+        return self.shift_left(4, 4, OpCode::Drop4);
     }
 
     fn op_swap(&mut self) {
-        assert!(self.depth >= 2, "stack underflow at step {}", self.step);
         self.registers[0][self.step] = self.registers[1][self.step - 1];
         self.registers[1][self.step] = self.registers[0][self.step - 1];
         self.copy_state(2);
     }
 
     fn op_swap2(&mut self) {
-        assert!(self.depth >= 4, "stack underflow at step {}", self.step);
         self.registers[0][self.step] = self.registers[2][self.step - 1];
         self.registers[1][self.step] = self.registers[3][self.step - 1];
         self.registers[2][self.step] = self.registers[0][self.step - 1];
@@ -301,7 +437,6 @@ impl Stack {
     }
 
     fn op_swap4(&mut self) {
-        assert!(self.depth >= 8, "stack underflow at step {}", self.step);
         self.registers[0][self.step] = self.registers[4][self.step - 1];
         self.registers[1][self.step] = self.registers[5][self.step - 1];
         self.registers[2][self.step] = self.registers[6][self.step - 1];
@@ -314,7 +449,6 @@ impl Stack {
     }
 
     fn op_roll4(&mut self) {
-        assert!(self.depth >= 4, "stack underflow at step {}", self.step);
         self.registers[0][self.step] = self.registers[3][self.step - 1];
         self.registers[1][self.step] = self.registers[0][self.step - 1];
         self.registers[2][self.step] = self.registers[1][self.step - 1];
@@ -323,7 +457,6 @@ impl Stack {
     }
 
     fn op_roll8(&mut self) {
-        assert!(self.depth >= 8, "stack underflow at step {}", self.step);
         self.registers[0][self.step] = self.registers[7][self.step - 1];
         self.registers[1][self.step] = self.registers[0][self.step - 1];
         self.registers[2][self.step] = self.registers[1][self.step - 1];
@@ -337,8 +470,7 @@ impl Stack {
 
     // SELECTION OPERATIONS
     // --------------------------------------------------------------------------------------------
-    fn op_choose(&mut self) {
-        assert!(self.depth >= 3, "stack underflow at step {}", self.step);
+    fn op_choose(&mut self) -> Result<(), ExecutionError> {
         let condition = self.registers[2][self.step - 1];
         if condition == field::ONE {
             self.registers[0][self.step] = self.registers[0][self.step - 1];
@@ -347,13 +479,13 @@ impl Stack {
             self.registers[0][self.step] = self.registers[1][self.step - 1];
         }
         else {
-            assert!(false, "CHOOSE on a non-binary condition at step {}", self.step);
+            return Err(ExecutionError::NonBinaryValue { step: self.step, op: OpCode::Choose, value: condition });
         }
-        self.shift_left(3, 2);
+        self.shift_left(3, 2, OpCode::Choose)?;
+        return Ok(());
     }
 
-    fn op_choose2(&mut self) {
-        assert!(self.depth >= 6, "stack underflow at step {}", self.step);
+    fn op_choose2(&mut self) -> Result<(), ExecutionError> {
         let condition = self.registers[4][self.step - 1];
         if condition == field::ONE {
             self.registers[0][self.step] = self.registers[0][self.step - 1];
@@ -364,13 +496,13 @@ impl Stack {
             self.registers[1][self.step] = self.registers[3][self.step - 1];
         }
         else {
-            assert!(false, "CHOOSE2 on a non-binary condition at step {}", self.step);
+            return Err(ExecutionError::NonBinaryValue { step: self.step, op: OpCode::Choose2, value: condition });
         }
-        self.shift_left(6, 4);
+        self.shift_left(6, 4, OpCode::Choose2)?;
+        return Ok(());
     }
 
-    fn op_cswap2(&mut self) {
-        assert!(self.depth >= 6, "stack underflow at step {}", self.step);
+    fn op_cswap2(&mut self) -> Result<(), ExecutionError> {
         let condition = self.registers[4][self.step - 1];
         if condition == field::ZERO {
             self.registers[0][self.step] = self.registers[0][self.step - 1];
@@ -385,79 +517,143 @@ impl Stack {
             self.registers[3][self.step] = self.registers[1][self.step - 1];
         }
         else {
-            assert!(false, "CSWAP2 on a non-binary condition at step {}", self.step);
+            return Err(ExecutionError::NonBinaryValue { step: self.step, op: OpCode::CSwap2, value: condition });
+        }
+        self.shift_left(6, 2, OpCode::CSwap2)?;
+        return Ok(());
+    }
+
+    fn op_cswap4(&mut self) -> Result<(), ExecutionError> {
+        let condition = self.registers[8][self.step - 1];
+        if condition == field::ZERO {
+            for i in 0..8 {
+                self.registers[i][self.step] = self.registers[i][self.step - 1];
+            }
+        }
+        else if condition == field::ONE {
+            for i in 0..4 {
+                self.registers[i][self.step] = self.registers[i + 4][self.step - 1];
+                self.registers[i + 4][self.step] = self.registers[i][self.step - 1];
+            }
+        }
+        else {
+            return Err(ExecutionError::NonBinaryValue { step: self.step, op: OpCode::CSwap4, value: condition });
         }
-        self.shift_left(6, 2);
+        self.shift_left(10, 2, OpCode::CSwap4)?;
+        return Ok(());
     }
 
     // ARITHMETIC AND BOOLEAN OPERATIONS
     // --------------------------------------------------------------------------------------------
-    fn op_add(&mut self) {
-        assert!(self.depth >= 2, "stack underflow at step {}", self.step);
+    fn op_add(&mut self) -> Result<(), ExecutionError> {
         let x = self.registers[0][self.step - 1];
         let y = self.registers[1][self.step - 1];
         self.registers[0][self.step] = field::add(x, y);
-        self.shift_left(2, 1);
+        self.shift_left(2, 1, OpCode::Add)?;
+        return Ok(());
     }
 
-    fn op_mul(&mut self) {
-        assert!(self.depth >= 2, "stack underflow at step {}", self.step);
+    fn op_mul(&mut self) -> Result<(), ExecutionError> {
         let x = self.registers[0][self.step - 1];
         let y = self.registers[1][self.step - 1];
         self.registers[0][self.step] = field::mul(x, y);
-        self.shift_left(2, 1);
+        self.shift_left(2, 1, OpCode::Mul)?;
+        return Ok(());
     }
 
-    fn op_inv(&mut self) {
-        assert!(self.depth >= 1, "stack underflow at step {}", self.step);
+    fn op_inv(&mut self) -> Result<(), ExecutionError> {
         let x = self.registers[0][self.step - 1];
-        assert!(x != field::ZERO, "cannot compute INV of {} at step {}", field::ZERO, self.step);
+        if x == field::ZERO {
+            return Err(ExecutionError::InverseOfZero { step: self.step, op: OpCode::Inv });
+        }
         self.registers[0][self.step] = field::inv(x);
         self.copy_state(1);
+        return Ok(());
     }
 
     fn op_neg(&mut self) {
-        assert!(self.depth >= 1, "stack underflow at step {}", self.step);
         let x = self.registers[0][self.step - 1];
         self.registers[0][self.step] = field::neg(x);
         self.copy_state(1);
     }
 
-    fn op_not(&mut self) {
-        assert!(self.depth >= 1, "stack underflow at step {}", self.step);
+    fn op_not(&mut self) -> Result<(), ExecutionError> {
         let x = self.registers[0][self.step - 1];
-        assert!(is_binary(x), "cannot compute NOT of a non-binary value at step {}", self.step);
+        self.check_binary(x, OpCode::Not)?;
         self.registers[0][self.step] = field::sub(field::ONE, x);
         self.copy_state(1);
+        return Ok(());
     }
 
-    fn op_and(&mut self) {
-        assert!(self.depth >= 2, "stack underflow at step {}", self.step);
+    fn op_and(&mut self) -> Result<(), ExecutionError> {
         let x = self.registers[0][self.step - 1];
         let y = self.registers[1][self.step - 1];
-        assert!(is_binary(x), "cannot compute AND for a non-binary value at step {}", self.step);
-        assert!(is_binary(y), "cannot compute AND for a non-binary value at step {}", self.step);
+        self.check_binary(x, OpCode::And)?;
+        self.check_binary(y, OpCode::And)?;
 
         self.registers[0][self.step] = if x == field::ONE && y == field::ONE { field::ONE } else { field::ZERO };
-        self.shift_left(2, 1);
+        self.shift_left(2, 1, OpCode::And)?;
+        return Ok(());
     }
 
-    fn op_or(&mut self) {
-        assert!(self.depth >= 2, "stack underflow at step {}", self.step);
+    fn op_or(&mut self) -> Result<(), ExecutionError> {
         let x = self.registers[0][self.step - 1];
         let y = self.registers[1][self.step - 1];
-        assert!(is_binary(x), "cannot compute OR for a non-binary value at step {}", self.step);
-        assert!(is_binary(y), "cannot compute OR for a non-binary value at step {}", self.step);
+        self.check_binary(x, OpCode::Or)?;
+        self.check_binary(y, OpCode::Or)?;
 
         self.registers[0][self.step] = if x == field::ONE || y == field::ONE { field::ONE } else { field::ZERO };
-        self.shift_left(2, 1);
+        self.shift_left(2, 1, OpCode::Or)?;
+        return Ok(());
+    }
+
+    fn op_expacc(&mut self, hint: OpHint) -> Result<(), ExecutionError> {
+        // process execution hint
+        match hint {
+            OpHint::ExpStart(n) => {
+                // if we are about to start exponentiation, push binary decomposition of
+                // the exponent onto tape A
+                self.check_depth(6, OpCode::ExpAcc)?;
+                let exp = self.registers[5][self.step - 1];
+                for i in 0..n {
+                    // most significant bit is pushed first
+                    self.tape_a.push((exp >> (n - i - 1)) & 1);
+                }
+            },
+            OpHint::None => self.fill_tape(TapeId::A, 1, OpCode::ExpAcc)?,
+            _ => return Err(ExecutionError::InvalidHint { step: self.step, op: OpCode::ExpAcc }),
+        }
+
+        // get the next bit of the exponent from tape A
+        let bit = self.tape_a.pop().unwrap();
+        self.check_binary(bit, OpCode::ExpAcc)?;
+        self.update_unread_inputs();
+
+        // compute current power of 2 for binary decomposition of the exponent
+        let power_of_two = self.registers[3][self.step - 1];
+        if !power_of_two.is_power_of_two() {
+            return Err(ExecutionError::InvalidOperand { step: self.step, op: OpCode::ExpAcc, value: power_of_two });
+        }
+
+        let base = self.registers[1][self.step - 1];
+        let result = self.registers[2][self.step - 1];
+        let acc = self.registers[4][self.step - 1];
+
+        // square the base, and multiply the result by the base only if the bit is set
+        self.registers[0][self.step] = bit;
+        self.registers[1][self.step] = field::mul(base, base);
+        self.registers[2][self.step] = if bit == field::ONE { field::mul(result, base) } else { result };
+        self.registers[3][self.step] = field::mul(power_of_two, 2);
+        self.registers[4][self.step] = field::add(acc, field::mul(bit, power_of_two));
+
+        self.copy_state(5);
+        return Ok(());
     }
 
 
     // COMPARISON OPERATIONS
     // --------------------------------------------------------------------------------------------
-    fn op_eq(&mut self) {
-        assert!(self.depth >= 3, "stack underflow at step {}", self.step);
+    fn op_eq(&mut self) -> Result<(), ExecutionError> {
         let aux = self.registers[0][self.step - 1];
         let x = self.registers[1][self.step - 1];
         let y = self.registers[2][self.step - 1];
@@ -465,19 +661,22 @@ impl Stack {
             self.registers[0][self.step] = field::ONE;
         } else {
             let diff = field::sub(x, y);
-            assert!(aux == field::inv(diff), "invalid AUX value for EQ operation at step {}", self.step);
+            if aux != field::inv(diff) {
+                return Err(ExecutionError::InvalidOperand { step: self.step, op: OpCode::Eq, value: aux });
+            }
             self.registers[0][self.step] = field::ZERO;
         }
-        self.shift_left(3, 2);
+        self.shift_left(3, 2, OpCode::Eq)?;
+        return Ok(());
     }
 
-    fn op_cmp(&mut self, hint: OpHint) {
+    fn op_cmp(&mut self, hint: OpHint) -> Result<(), ExecutionError> {
         // process execution hint
         match hint {
             OpHint::CmpStart(n) => {
                 // if we are about to start comparison sequence, push binary decompositions
                 // of a and b values onto the tapes
-                assert!(self.depth >= 10, "stack underflow at step {}", self.step);
+                self.check_depth(10, OpCode::Cmp)?;
                 let a_val = self.registers[8][self.step - 1];
                 let b_val = self.registers[9][self.step - 1];
                 for i in 0..n {
@@ -486,20 +685,18 @@ impl Stack {
                 }
             },
             OpHint::None => {
-                assert!(self.depth >= 8, "stack underflow at step {}", self.step);
-                assert!(self.tape_a.len() > 0, "attempt to read from empty tape A at step {}", self.step);
-                assert!(self.tape_b.len() > 0, "attempt to read from empty tape B at step {}", self.step);
+                self.fill_tape(TapeId::A, 1, OpCode::Cmp)?;
+                self.fill_tape(TapeId::B, 1, OpCode::Cmp)?;
             },
-            _ => panic!("execution hint {:?} is not valid for CMP operation", hint)
+            _ => return Err(ExecutionError::InvalidHint { step: self.step, op: OpCode::Cmp }),
         }
 
         // get next bits of a and b values from the tapes
         let a_bit = self.tape_a.pop().unwrap();
-        assert!(a_bit == field::ZERO || a_bit == field::ONE,
-            "expected binary input at step {} but received: {}", self.step, a_bit);
+        self.check_binary(a_bit, OpCode::Cmp)?;
         let b_bit = self.tape_b.pop().unwrap();
-        assert!(b_bit == field::ZERO || b_bit == field::ONE,
-            "expected binary input at step {} but received: {}", self.step, b_bit);
+        self.check_binary(b_bit, OpCode::Cmp)?;
+        self.update_unread_inputs();
 
         // determine which bit is greater
         let bit_gt = field::mul(a_bit, field::sub(field::ONE, b_bit));
@@ -507,8 +704,9 @@ impl Stack {
 
         // compute current power of 2 for binary decomposition
         let power_of_two = self.registers[0][self.step - 1];
-        assert!(power_of_two.is_power_of_two(),
-            "expected top of the stack at step {} to be a power of 2, but received {}", self.step, power_of_two);
+        if !power_of_two.is_power_of_two() {
+            return Err(ExecutionError::InvalidOperand { step: self.step, op: OpCode::Cmp, value: power_of_two });
+        }
         let next_power_of_two = if power_of_two == 1 {
             field::div(power_of_two, 2)
         }
@@ -532,38 +730,36 @@ impl Stack {
         self.registers[7][self.step] = field::add(self.registers[7][self.step - 1], field::mul(a_bit, power_of_two));
 
         self.copy_state(8);
+        return Ok(());
     }
 
-    fn op_binacc(&mut self, hint: OpHint) {
+    fn op_binacc(&mut self, hint: OpHint) -> Result<(), ExecutionError> {
         // process execution hint
         match hint {
             OpHint::RcStart(n) => {
                 // if we are about to start range check sequence, push binary decompositions
                 // of the value onto tape A
-                assert!(self.depth >= 5, "stack underflow at step {}", self.step);
+                self.check_depth(5, OpCode::BinAcc)?;
                 let val = self.registers[4][self.step - 1];
                 for i in 0..n {
                     // most significant bit is pushed first
                     self.tape_a.push((val >> (n - i - 1)) & 1);
                 }
             },
-            OpHint::None => {
-                assert!(self.depth >= 4, "stack underflow at step {}", self.step);
-                assert!(self.tape_a.len() > 0, "attempt to read from empty tape A at step {}", self.step);
-            },
-            _ => panic!("execution hint {:?} is not valid for BINACC operation", hint)
+            OpHint::None => self.fill_tape(TapeId::A, 1, OpCode::BinAcc)?,
+            _ => return Err(ExecutionError::InvalidHint { step: self.step, op: OpCode::BinAcc }),
         }
 
         // get the next bit of the value from tape A
         let bit = self.tape_a.pop().unwrap();
-        assert!(bit == field::ZERO || bit == field::ONE,
-            "expected binary input at step {} but received: {}", self.step, bit);
+        self.check_binary(bit, OpCode::BinAcc)?;
+        self.update_unread_inputs();
 
         // compute current power of 2 for binary decomposition
         let power_of_two = self.registers[2][self.step - 1];
-        assert!(power_of_two.is_power_of_two(),
-            "expected 3rd value from the top of the stack at step {} to be a power of 2, but received {}",
-            self.step, power_of_two);
+        if !power_of_two.is_power_of_two() {
+            return Err(ExecutionError::InvalidOperand { step: self.step, op: OpCode::BinAcc, value: power_of_two });
+        }
         let next_power_of_two = field::mul(power_of_two, 2);
 
         let acc = self.registers[3][self.step - 1];
@@ -575,12 +771,12 @@ impl Stack {
         self.registers[3][self.step] = field::add(acc, field::mul(bit, power_of_two));
 
         self.copy_state(4);
+        return Ok(());
     }
 
     // CRYPTOGRAPHIC OPERATIONS
     // --------------------------------------------------------------------------------------------
     fn op_rescr(&mut self) {
-        assert!(self.depth >= HASH_STATE_WIDTH, "stack underflow at step {}", self.step);
         let mut state = [
             self.registers[0][self.step - 1],
             self.registers[1][self.step - 1],
@@ -605,15 +801,63 @@ impl Stack {
     // HELPER METHODS
     // --------------------------------------------------------------------------------------------
 
+    /// Makes sure the stack holds at least `min_depth` values; this is needed for execution
+    /// hints which inspect more values than the operation itself consumes.
+    fn check_depth(&self, min_depth: usize, op: OpCode) -> Result<(), ExecutionError> {
+        if self.depth < min_depth {
+            return Err(ExecutionError::StackUnderflow { step: self.step, op });
+        }
+        return Ok(());
+    }
+
+    /// Makes sure the specified secret input tape holds at least `count` values. Missing values
+    /// are requested from the tape provider and are placed below all values already on the
+    /// tape; if there is no provider, an error is returned.
+    fn fill_tape(&mut self, tape: TapeId, count: usize, op: OpCode) -> Result<(), ExecutionError> {
+        let (index, name) = match tape { TapeId::A => (0, 'A'), TapeId::B => (1, 'B') };
+        let length = if index == 0 { self.tape_a.len() } else { self.tape_b.len() };
+        if length >= count {
+            return Ok(());
+        }
+
+        let provider = match &self.provider {
+            Some(provider) => provider.clone(),
+            None => return Err(ExecutionError::EmptyTape { step: self.step, op, tape: name }),
+        };
+
+        // the provider sees the stack as it was before the operation
+        let stack: Vec<u128> = (0..self.depth).map(|i| self.registers[i][self.step - 1]).collect();
+        for _ in length..count {
+            let value = provider.read(tape, &StackView::new(&stack));
+            if value >= field::MODULUS {
+                return Err(ExecutionError::InvalidOperand { step: self.step, op, value });
+            }
+            if index == 0 { self.tape_a.insert(0, value); } else { self.tape_b.insert(0, value); }
+            self.provided[index].push(value);
+            self.unread[index] += 1;
+        }
+        return Ok(());
+    }
+
+    /// Makes sure `value` is either 0 or 1.
+    fn check_binary(&self, value: u128, op: OpCode) -> Result<(), ExecutionError> {
+        if !is_binary(value) {
+            return Err(ExecutionError::NonBinaryValue { step: self.step, op, value });
+        }
+        return Ok(());
+    }
+
     fn copy_state(&mut self, start: usize) {
         for i in start..self.depth {
             self.registers[i][self.step] = self.registers[i][self.step - 1];
         }
     }
 
-    fn shift_left(&mut self, start: usize, pos_count: usize) {
-        assert!(self.depth >= pos_count, "stack underflow at step {}", self.step);
-        
+    fn shift_left(&mut self, start: usize, pos_count: usize, op: OpCode) -> Result<(), ExecutionError> {
+        if self.depth < pos_count {
+            return Err(ExecutionError::StackUnderflow { step: self.step, op });
+        }
+
         // shift all values by pos_count to the left
         for i in start..self.depth {
             self.registers[i - pos_count][self.step] = self.registers[i][self.step - 1];
@@ -626,15 +870,17 @@ impl Stack {
 
         // stack depth has been reduced by pos_count
         self.depth -= pos_count;
+        return Ok(());
     }
 
-    fn shift_right(&mut self, start: usize, pos_count: usize) {
-        
+    fn shift_right(&mut self, start: usize, pos_count: usize, op: OpCode) -> Result<(), ExecutionError> {
+        if self.depth + pos_count > MAX_STACK_DEPTH {
+            return Err(ExecutionError::StackOverflow { step: self.step, op });
+        }
         self.depth += pos_count;
-        assert!(self.depth <= MAX_STACK_DEPTH, "stack overflow at step {}", self.step);
 
         if self.depth > self.max_depth {
-            self.max_depth += pos_count;
+            self.max_depth = self.depth;
             if self.max_depth > self.registers.len() {
                 self.add_registers(self.max_depth - self.registers.len());
             }
@@ -643,6 +889,7 @@ impl Stack {
         for i in start..(self.depth - pos_count) {
             self.registers[i + pos_count][self.step] = self.registers[i][self.step - 1];
         }
+        return Ok(());
     }
 
     /// Extends the stack by the specified number of registers.
@@ -660,8 +907,16 @@ impl Stack {
         if self.step >= self.trace_length() {
             let new_length = self.trace_length() * 2;
             for register in self.registers.iter_mut() { register.resize(new_length, field::ZERO); }
+            self.depth_trace.resize(new_length, field::ZERO);
         }
     }
+
+    /// Secret inputs sit at the bottom of the tapes, below any values placed there by execution
+    /// hints; so, an input is consumed once a tape shrinks below the input's position.
+    fn update_unread_inputs(&mut self) {
+        self.unread[0] = std::cmp::min(self.unread[0], self.tape_a.len());
+        self.unread[1] = std::cmp::min(self.unread[1], self.tape_b.len());
+    }
 }
 
 // HELPER FUNCTIONS
@@ -1,4 +1,4 @@
-use super::{ init_stack, get_stack_state, OpCode, OpHint, TRACE_LENGTH };
+use super::{ init_stack, get_stack_state, ExecutionError, OpCode, OpHint, TRACE_LENGTH };
 
 // CHOOSE OPERATIONS
 // ================================================================================================
@@ -7,14 +7,14 @@ use super::{ init_stack, get_stack_state, OpCode, OpHint, TRACE_LENGTH };
 fn choose() {
     // choose on false
     let mut stack = init_stack(&[2, 3, 0], &[], &[], TRACE_LENGTH);
-    stack.execute(OpCode::Choose, OpHint::None);
+    stack.execute(OpCode::Choose, OpHint::None).unwrap();
     assert_eq!(vec![3, 0, 0, 0, 0, 0, 0, 0], get_stack_state(&stack, 1));
 
     assert_eq!(1, stack.depth);
     assert_eq!(3, stack.max_depth);
 
     let mut stack = init_stack(&[2, 3, 0, 4], &[], &[], TRACE_LENGTH);
-    stack.execute(OpCode::Choose, OpHint::None);
+    stack.execute(OpCode::Choose, OpHint::None).unwrap();
     assert_eq!(vec![3, 4, 0, 0, 0, 0, 0, 0], get_stack_state(&stack, 1));
 
     assert_eq!(2, stack.depth);
@@ -22,7 +22,7 @@ fn choose() {
 
     // choose on true
     let mut stack = init_stack(&[2, 3, 1, 4], &[], &[], TRACE_LENGTH);
-    stack.execute(OpCode::Choose, OpHint::None);
+    stack.execute(OpCode::Choose, OpHint::None).unwrap();
     assert_eq!(vec![2, 4, 0, 0, 0, 0, 0, 0], get_stack_state(&stack, 1));
 
     assert_eq!(2, stack.depth);
@@ -30,17 +30,17 @@ fn choose() {
 }
 
 #[test]
-#[should_panic(expected = "CHOOSE on a non-binary condition at step 1")]
 fn choose_fail() {
     let mut stack = init_stack(&[2, 3, 4], &[], &[], TRACE_LENGTH);
-    stack.execute(OpCode::Choose, OpHint::None);
+    let result = stack.execute(OpCode::Choose, OpHint::None);
+    assert_eq!(Err(ExecutionError::NonBinaryValue { step: 1, op: OpCode::Choose, value: 4 }), result);
 }
 
 #[test]
 fn choose2() {
     // choose on false
     let mut stack = init_stack(&[2, 3, 4, 5, 0, 6, 7], &[], &[], TRACE_LENGTH);
-    stack.execute(OpCode::Choose2, OpHint::None);
+    stack.execute(OpCode::Choose2, OpHint::None).unwrap();
     assert_eq!(vec![4, 5, 7, 0, 0, 0, 0, 0], get_stack_state(&stack, 1));
 
     assert_eq!(3, stack.depth);
@@ -48,7 +48,7 @@ fn choose2() {
 
     // choose on true
     let mut stack = init_stack(&[2, 3, 4, 5, 1, 6, 7], &[], &[], TRACE_LENGTH);
-    stack.execute(OpCode::Choose2, OpHint::None);
+    stack.execute(OpCode::Choose2, OpHint::None).unwrap();
     assert_eq!(vec![2, 3, 7, 0, 0, 0, 0, 0], get_stack_state(&stack, 1));
 
     assert_eq!(3, stack.depth);
@@ -56,10 +56,10 @@ fn choose2() {
 }
 
 #[test]
-#[should_panic(expected = "CHOOSE2 on a non-binary condition at step 1")]
 fn choose2_fail() {
     let mut stack = init_stack(&[2, 3, 4, 5, 6, 8, 8], &[], &[], TRACE_LENGTH);
-    stack.execute(OpCode::Choose2, OpHint::None);
+    let result = stack.execute(OpCode::Choose2, OpHint::None);
+    assert_eq!(Err(ExecutionError::NonBinaryValue { step: 1, op: OpCode::Choose2, value: 6 }), result);
 }
 
 // OTHER CONDITIONAL OPERATIONS
@@ -69,7 +69,7 @@ fn choose2_fail() {
 fn cswap2() {
     // don't swap on false
     let mut stack = init_stack(&[2, 3, 4, 5, 0, 6, 7], &[], &[], TRACE_LENGTH);
-    stack.execute(OpCode::CSwap2, OpHint::None);
+    stack.execute(OpCode::CSwap2, OpHint::None).unwrap();
     assert_eq!(vec![2, 3, 4, 5, 7, 0, 0, 0], get_stack_state(&stack, 1));
 
     assert_eq!(5, stack.depth);
@@ -77,7 +77,7 @@ fn cswap2() {
 
     // swap on true
     let mut stack = init_stack(&[2, 3, 4, 5, 1, 6, 7], &[], &[], TRACE_LENGTH);
-    stack.execute(OpCode::CSwap2, OpHint::None);
+    stack.execute(OpCode::CSwap2, OpHint::None).unwrap();
     assert_eq!(vec![4, 5, 2, 3, 7, 0, 0, 0], get_stack_state(&stack, 1));
 
     assert_eq!(5, stack.depth);
@@ -85,8 +85,39 @@ fn cswap2() {
 }
 
 #[test]
-#[should_panic(expected = "CSWAP2 on a non-binary condition at step 1")]
 fn cswap2_fail() {
     let mut stack = init_stack(&[2, 3, 4, 5, 6, 8, 8], &[], &[], TRACE_LENGTH);
-    stack.execute(OpCode::CSwap2, OpHint::None);
-}
\ No newline at end of file
+    let result = stack.execute(OpCode::CSwap2, OpHint::None);
+    assert_eq!(Err(ExecutionError::NonBinaryValue { step: 1, op: OpCode::CSwap2, value: 6 }), result);
+}
+#[test]
+fn cswap4() {
+    // don't swap on false
+    let mut stack = init_stack(&[6, 7, 8, 9, 0, 10, 11], &[5, 3], &[4, 2], TRACE_LENGTH);
+    stack.execute(OpCode::Read2, OpHint::None).unwrap();
+    stack.execute(OpCode::Read2, OpHint::None).unwrap();
+    stack.execute(OpCode::CSwap4, OpHint::None).unwrap();
+    assert_eq!(vec![2, 3, 4, 5, 6, 7, 8, 9, 11, 0, 0], get_stack_state(&stack, 3));
+
+    assert_eq!(9, stack.depth);
+    assert_eq!(11, stack.max_depth);
+
+    // swap on true
+    let mut stack = init_stack(&[6, 7, 8, 9, 1, 10, 11], &[5, 3], &[4, 2], TRACE_LENGTH);
+    stack.execute(OpCode::Read2, OpHint::None).unwrap();
+    stack.execute(OpCode::Read2, OpHint::None).unwrap();
+    stack.execute(OpCode::CSwap4, OpHint::None).unwrap();
+    assert_eq!(vec![6, 7, 8, 9, 2, 3, 4, 5, 11, 0, 0], get_stack_state(&stack, 3));
+
+    assert_eq!(9, stack.depth);
+    assert_eq!(11, stack.max_depth);
+}
+
+#[test]
+fn cswap4_fail() {
+    let mut stack = init_stack(&[6, 7, 8, 9, 3, 10], &[5, 3], &[4, 2], TRACE_LENGTH);
+    stack.execute(OpCode::Read2, OpHint::None).unwrap();
+    stack.execute(OpCode::Read2, OpHint::None).unwrap();
+    let result = stack.execute(OpCode::CSwap4, OpHint::None);
+    assert_eq!(Err(ExecutionError::NonBinaryValue { step: 3, op: OpCode::CSwap4, value: 3 }), result);
+}
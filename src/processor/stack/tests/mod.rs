@@ -1,7 +1,7 @@
 use crate::math::{ field };
 use crate::utils::{ hasher };
-use super::{ Stack, super::ProgramInputs, OpHint, OpCode };
-use crate::{ HASH_STATE_WIDTH };
+use super::{ Stack, super::ProgramInputs, super::ExecutionError, OpHint, OpCode };
+use crate::{ HASH_STATE_WIDTH, MAX_STACK_DEPTH };
 
 mod comparisons;
 mod conditional;
@@ -14,7 +14,7 @@ const TRACE_LENGTH: usize = 16;
 #[test]
 fn noop() {
     let mut stack = init_stack(&[1, 2, 3, 4], &[], &[], TRACE_LENGTH);
-    stack.execute(OpCode::Noop, OpHint::None);
+    stack.execute(OpCode::Noop, OpHint::None).unwrap();
     assert_eq!(vec![1, 2, 3, 4, 0, 0, 0, 0], get_stack_state(&stack, 1));
 
     assert_eq!(4, stack.depth);
@@ -24,7 +24,7 @@ fn noop() {
 #[test]
 fn assert() {
     let mut stack = init_stack(&[1, 2, 3, 4], &[], &[], TRACE_LENGTH);
-    stack.execute(OpCode::Assert, OpHint::None);
+    stack.execute(OpCode::Assert, OpHint::None).unwrap();
     assert_eq!(vec![2, 3, 4, 0, 0, 0, 0, 0], get_stack_state(&stack, 1));
 
     assert_eq!(3, stack.depth);
@@ -32,16 +32,16 @@ fn assert() {
 }
 
 #[test]
-#[should_panic(expected = "ASSERT failed at step 1")]
 fn assert_fail() {
     let mut stack = init_stack(&[2, 3, 4], &[], &[], TRACE_LENGTH);
-    stack.execute(OpCode::Assert, OpHint::None);
+    let result = stack.execute(OpCode::Assert, OpHint::None);
+    assert_eq!(Err(ExecutionError::FailedAssertion { step: 1, op: OpCode::Assert, value: 2 }), result);
 }
 
 #[test]
 fn asserteq() {
     let mut stack = init_stack(&[1, 1, 3, 4], &[], &[], TRACE_LENGTH);
-    stack.execute(OpCode::AssertEq, OpHint::None);
+    stack.execute(OpCode::AssertEq, OpHint::None).unwrap();
     assert_eq!(vec![3, 4, 0, 0, 0, 0, 0, 0], get_stack_state(&stack, 1));
 
     assert_eq!(2, stack.depth);
@@ -49,10 +49,10 @@ fn asserteq() {
 }
 
 #[test]
-#[should_panic(expected = "ASSERTEQ failed at step 1")]
 fn asserteq_fail() {
     let mut stack = init_stack(&[2, 3, 4], &[], &[], TRACE_LENGTH);
-    stack.execute(OpCode::AssertEq, OpHint::None);
+    let result = stack.execute(OpCode::AssertEq, OpHint::None);
+    assert_eq!(Err(ExecutionError::UnequalValues { step: 1, op: OpCode::AssertEq, values: [2, 3] }), result);
 }
 
 // INPUT OPERATIONS
@@ -61,7 +61,7 @@ fn asserteq_fail() {
 #[test]
 fn push() {
     let mut stack = init_stack(&[], &[], &[], TRACE_LENGTH);
-    stack.execute(OpCode::Push, OpHint::PushValue(3));
+    stack.execute(OpCode::Push, OpHint::PushValue(3)).unwrap();
     assert_eq!(vec![3, 0, 0, 0, 0, 0, 0, 0], get_stack_state(&stack, 1));
 
     assert_eq!(1, stack.depth);
@@ -72,43 +72,106 @@ fn push() {
 fn read() {
     let mut stack = init_stack(&[1], &[2, 3], &[], TRACE_LENGTH);
 
-    stack.execute(OpCode::Read, OpHint::None);
+    stack.execute(OpCode::Read, OpHint::None).unwrap();
     assert_eq!(vec![2, 1, 0, 0, 0, 0, 0, 0], get_stack_state(&stack, 1));
 
     assert_eq!(2, stack.depth);
     assert_eq!(2, stack.max_depth);
 
-    stack.execute(OpCode::Read, OpHint::None);
+    stack.execute(OpCode::Read, OpHint::None).unwrap();
     assert_eq!(vec![3, 2, 1, 0, 0, 0, 0, 0], get_stack_state(&stack, 2));
 
     assert_eq!(3, stack.depth);
     assert_eq!(3, stack.max_depth);
 }
 
+#[test]
+fn read_bits() {
+    // bits of 0b1101 except the least significant one are read starting with the most
+    // significant bit
+    let mut stack = init_stack(&[0b1101], &[], &[], TRACE_LENGTH);
+    stack.execute(OpCode::Read, OpHint::BitsStart(4)).unwrap();
+    stack.execute(OpCode::Read, OpHint::None).unwrap();
+    stack.execute(OpCode::Read, OpHint::None).unwrap();
+    assert_eq!(vec![0, 1, 1, 0b1101, 0, 0, 0, 0], get_stack_state(&stack, 3));
+
+    let mut stack = init_stack(&[0b10000], &[], &[], TRACE_LENGTH);
+    let result = stack.execute(OpCode::Read, OpHint::BitsStart(4));
+    assert_eq!(Err(ExecutionError::InvalidOperand { step: 1, op: OpCode::Read, value: 0b10000 }), result);
+}
+
 #[test]
 fn read2() {
     let mut stack = init_stack(&[1], &[2, 4], &[3, 5], TRACE_LENGTH);
 
-    stack.execute(OpCode::Read2, OpHint::None);
+    stack.execute(OpCode::Read2, OpHint::None).unwrap();
     assert_eq!(vec![3, 2, 1, 0, 0, 0, 0, 0], get_stack_state(&stack, 1));
 
     assert_eq!(3, stack.depth);
     assert_eq!(3, stack.max_depth);
 
-    stack.execute(OpCode::Read2, OpHint::None);
+    stack.execute(OpCode::Read2, OpHint::None).unwrap();
     assert_eq!(vec![5, 4, 3, 2, 1, 0, 0, 0], get_stack_state(&stack, 2));
 
     assert_eq!(5, stack.depth);
     assert_eq!(5, stack.max_depth);
 }
 
+#[test]
+fn read2_div() {
+    let mut stack = init_stack(&[7, 23], &[], &[], TRACE_LENGTH);
+    stack.execute(OpCode::Read2, OpHint::DivStart).unwrap();
+    assert_eq!(vec![2, 3, 7, 23, 0, 0, 0, 0], get_stack_state(&stack, 1));
+
+    let mut stack = init_stack(&[0, 23], &[], &[], TRACE_LENGTH);
+    let result = stack.execute(OpCode::Read2, OpHint::DivStart);
+    assert_eq!(Err(ExecutionError::DivisionByZero { step: 1, op: OpCode::Read2 }), result);
+
+    let mut stack = init_stack(&[7, 1 << 32], &[], &[], TRACE_LENGTH);
+    let result = stack.execute(OpCode::Read2, OpHint::DivStart);
+    assert_eq!(Err(ExecutionError::InvalidOperand { step: 1, op: OpCode::Read2, value: 1 << 32 }), result);
+}
+
+#[test]
+fn read2_empty_path() {
+    let mut stack = init_stack(&[1, 2, 3], &[], &[], TRACE_LENGTH);
+    let result = stack.execute(OpCode::Read2, OpHint::PmpathStart(0));
+    assert_eq!(Err(ExecutionError::InvalidHint { step: 1, op: OpCode::Read2 }), result);
+}
+
+#[test]
+fn read4() {
+    let mut stack = init_stack(&[1], &[2, 4], &[3, 5], TRACE_LENGTH);
+
+    stack.execute(OpCode::Read4, OpHint::None).unwrap();
+    assert_eq!(vec![5, 4, 3, 2, 1, 0, 0, 0], get_stack_state(&stack, 1));
+
+    assert_eq!(5, stack.depth);
+    assert_eq!(5, stack.max_depth);
+
+    // both tapes have been consumed
+    let result = stack.execute(OpCode::Read4, OpHint::None);
+    assert_eq!(Err(ExecutionError::EmptyTape { step: 2, op: OpCode::Read4, tape: 'A' }), result);
+}
+
+#[test]
+fn read4_fail() {
+    let mut stack = init_stack(&[1], &[2], &[3], TRACE_LENGTH);
+    let result = stack.execute(OpCode::Read4, OpHint::None);
+    assert_eq!(Err(ExecutionError::EmptyTape { step: 1, op: OpCode::Read4, tape: 'A' }), result);
+
+    let mut stack = init_stack(&[1], &[2, 4], &[3], TRACE_LENGTH);
+    let result = stack.execute(OpCode::Read4, OpHint::None);
+    assert_eq!(Err(ExecutionError::EmptyTape { step: 1, op: OpCode::Read4, tape: 'B' }), result);
+}
+
 // STACK MANIPULATION OPERATIONS
 // ================================================================================================
 
 #[test]
 fn dup() {
     let mut stack = init_stack(&[1, 2], &[], &[], TRACE_LENGTH);
-    stack.execute(OpCode::Dup, OpHint::None);
+    stack.execute(OpCode::Dup, OpHint::None).unwrap();
     assert_eq!(vec![1, 1, 2, 0, 0, 0, 0, 0], get_stack_state(&stack, 1));
 
     assert_eq!(3, stack.depth);
@@ -118,7 +181,7 @@ fn dup() {
 #[test]
 fn dup2() {
     let mut stack = init_stack(&[1, 2, 3, 4], &[], &[], TRACE_LENGTH);
-    stack.execute(OpCode::Dup2, OpHint::None);
+    stack.execute(OpCode::Dup2, OpHint::None).unwrap();
     assert_eq!(vec![1, 2, 1, 2, 3, 4, 0, 0], get_stack_state(&stack, 1));
 
     assert_eq!(6, stack.depth);
@@ -128,27 +191,51 @@ fn dup2() {
 #[test]
 fn dup4() {
     let mut stack = init_stack(&[1, 2, 3, 4], &[], &[], TRACE_LENGTH);
-    stack.execute(OpCode::Dup4, OpHint::None);
+    stack.execute(OpCode::Dup4, OpHint::None).unwrap();
     assert_eq!(vec![1, 2, 3, 4, 1, 2, 3, 4], get_stack_state(&stack, 1));
 
     assert_eq!(8, stack.depth);
     assert_eq!(8, stack.max_depth);
 }
 
+#[test]
+fn dup8() {
+    let mut stack = init_stack(&[1, 2, 3, 4, 5, 6, 7, 8], &[], &[], TRACE_LENGTH);
+    stack.execute(OpCode::Dup8, OpHint::None).unwrap();
+    assert_eq!(vec![1, 2, 3, 4, 5, 6, 7, 8, 1, 2, 3, 4, 5, 6, 7, 8], get_stack_state(&stack, 1));
+
+    assert_eq!(16, stack.depth);
+    assert_eq!(16, stack.max_depth);
+
+    // the stack cannot grow beyond 32 items
+    stack.execute(OpCode::Dup8, OpHint::None).unwrap();
+    stack.execute(OpCode::Pad2, OpHint::None).unwrap();
+    assert_eq!(26, stack.depth);
+
+    let result = stack.execute(OpCode::Dup8, OpHint::None);
+    assert_eq!(Err(ExecutionError::StackOverflow { step: 4, op: OpCode::Dup8 }), result);
+}
+
 #[test]
 fn pad2() {
     let mut stack = init_stack(&[1, 2], &[], &[], TRACE_LENGTH);
-    stack.execute(OpCode::Pad2, OpHint::None);
+    stack.execute(OpCode::Pad2, OpHint::None).unwrap();
     assert_eq!(vec![0, 0, 1, 2, 0, 0, 0, 0], get_stack_state(&stack, 1));
 
     assert_eq!(4, stack.depth);
     assert_eq!(4, stack.max_depth);
+
+    // max depth grows only by the amount by which it was exceeded
+    stack.execute(OpCode::Drop, OpHint::None).unwrap();
+    stack.execute(OpCode::Pad2, OpHint::None).unwrap();
+    assert_eq!(5, stack.depth);
+    assert_eq!(5, stack.max_depth);
 }
 
 #[test]
 fn drop() {
     let mut stack = init_stack(&[1, 2], &[], &[], TRACE_LENGTH);
-    stack.execute(OpCode::Drop, OpHint::None);
+    stack.execute(OpCode::Drop, OpHint::None).unwrap();
     assert_eq!(vec![2, 0, 0, 0, 0, 0, 0, 0], get_stack_state(&stack, 1));
 
     assert_eq!(1, stack.depth);
@@ -158,7 +245,7 @@ fn drop() {
 #[test]
 fn drop4() {
     let mut stack = init_stack(&[1, 2, 3, 4, 5], &[], &[], TRACE_LENGTH);
-    stack.execute(OpCode::Drop4, OpHint::None);
+    stack.execute(OpCode::Drop4, OpHint::None).unwrap();
     assert_eq!(vec![5, 0, 0, 0, 0, 0, 0, 0], get_stack_state(&stack, 1));
 
     assert_eq!(1, stack.depth);
@@ -168,7 +255,7 @@ fn drop4() {
 #[test]
 fn swap() {
     let mut stack = init_stack(&[1, 2, 3, 4], &[], &[], TRACE_LENGTH);
-    stack.execute(OpCode::Swap, OpHint::None);
+    stack.execute(OpCode::Swap, OpHint::None).unwrap();
     assert_eq!(vec![2, 1, 3, 4, 0, 0, 0, 0], get_stack_state(&stack, 1));
 
     assert_eq!(4, stack.depth);
@@ -178,7 +265,7 @@ fn swap() {
 #[test]
 fn swap2() {
     let mut stack = init_stack(&[1, 2, 3, 4], &[], &[], TRACE_LENGTH);
-    stack.execute(OpCode::Swap2, OpHint::None);
+    stack.execute(OpCode::Swap2, OpHint::None).unwrap();
     assert_eq!(vec![3, 4, 1, 2, 0, 0, 0, 0], get_stack_state(&stack, 1));
 
     assert_eq!(4, stack.depth);
@@ -188,7 +275,7 @@ fn swap2() {
 #[test]
 fn swap4() {
     let mut stack = init_stack(&[1, 2, 3, 4, 5, 6, 7, 8], &[], &[], TRACE_LENGTH);
-    stack.execute(OpCode::Swap4, OpHint::None);
+    stack.execute(OpCode::Swap4, OpHint::None).unwrap();
     assert_eq!(vec![5, 6, 7, 8, 1, 2, 3, 4], get_stack_state(&stack, 1));
 
     assert_eq!(8, stack.depth);
@@ -198,7 +285,7 @@ fn swap4() {
 #[test]
 fn roll4() {
     let mut stack = init_stack(&[1, 2, 3, 4], &[], &[], TRACE_LENGTH);
-    stack.execute(OpCode::Roll4, OpHint::None);
+    stack.execute(OpCode::Roll4, OpHint::None).unwrap();
     assert_eq!(vec![4, 1, 2, 3, 0, 0, 0, 0], get_stack_state(&stack, 1));
 
     assert_eq!(4, stack.depth);
@@ -208,7 +295,7 @@ fn roll4() {
 #[test]
 fn roll8() {
     let mut stack = init_stack(&[1, 2, 3, 4, 5, 6, 7, 8], &[], &[], TRACE_LENGTH);
-    stack.execute(OpCode::Roll8, OpHint::None);
+    stack.execute(OpCode::Roll8, OpHint::None).unwrap();
     assert_eq!(vec![8, 1, 2, 3, 4, 5, 6, 7], get_stack_state(&stack, 1));
 
     assert_eq!(8, stack.depth);
@@ -221,7 +308,7 @@ fn roll8() {
 #[test]
 fn add() {
     let mut stack = init_stack(&[1, 2], &[], &[], TRACE_LENGTH);
-    stack.execute(OpCode::Add, OpHint::None);
+    stack.execute(OpCode::Add, OpHint::None).unwrap();
     assert_eq!(vec![3, 0, 0, 0, 0, 0, 0, 0], get_stack_state(&stack, 1));
 
     assert_eq!(1, stack.depth);
@@ -231,7 +318,7 @@ fn add() {
 #[test]
 fn mul() {
     let mut stack = init_stack(&[2, 3], &[], &[], TRACE_LENGTH);
-    stack.execute(OpCode::Mul, OpHint::None);
+    stack.execute(OpCode::Mul, OpHint::None).unwrap();
     assert_eq!(vec![6, 0, 0, 0, 0, 0, 0, 0], get_stack_state(&stack, 1));
 
     assert_eq!(1, stack.depth);
@@ -241,7 +328,7 @@ fn mul() {
 #[test]
 fn inv() {
     let mut stack = init_stack(&[2, 3], &[], &[], TRACE_LENGTH);
-    stack.execute(OpCode::Inv, OpHint::None);
+    stack.execute(OpCode::Inv, OpHint::None).unwrap();
     assert_eq!(vec![field::inv(2), 3, 0, 0, 0, 0, 0, 0], get_stack_state(&stack, 1));
 
     assert_eq!(2, stack.depth);
@@ -249,16 +336,16 @@ fn inv() {
 }
 
 #[test]
-#[should_panic(expected = "cannot compute INV of 0 at step 1")]
 fn inv_zero() {
     let mut stack = init_stack(&[0], &[], &[], TRACE_LENGTH);
-    stack.execute(OpCode::Inv, OpHint::None);
+    let result = stack.execute(OpCode::Inv, OpHint::None);
+    assert_eq!(Err(ExecutionError::InverseOfZero { step: 1, op: OpCode::Inv }), result);
 }
 
 #[test]
 fn neg() {
     let mut stack = init_stack(&[2, 3], &[], &[], TRACE_LENGTH);
-    stack.execute(OpCode::Neg, OpHint::None);
+    stack.execute(OpCode::Neg, OpHint::None).unwrap();
     assert_eq!(vec![field::neg(2), 3, 0, 0, 0, 0, 0, 0], get_stack_state(&stack, 1));
 
     assert_eq!(2, stack.depth);
@@ -268,13 +355,13 @@ fn neg() {
 #[test]
 fn not() {
     let mut stack = init_stack(&[1, 2], &[], &[], TRACE_LENGTH);
-    stack.execute(OpCode::Not, OpHint::None);
+    stack.execute(OpCode::Not, OpHint::None).unwrap();
     assert_eq!(vec![0, 2, 0, 0, 0, 0, 0, 0], get_stack_state(&stack, 1));
 
     assert_eq!(2, stack.depth);
     assert_eq!(2, stack.max_depth);
 
-    stack.execute(OpCode::Not, OpHint::None);
+    stack.execute(OpCode::Not, OpHint::None).unwrap();
     assert_eq!(vec![1, 2, 0, 0, 0, 0, 0, 0], get_stack_state(&stack, 2));
 
     assert_eq!(2, stack.depth);
@@ -282,22 +369,22 @@ fn not() {
 }
 
 #[test]
-#[should_panic(expected = "cannot compute NOT of a non-binary value at step 1")]
 fn not_fail() {
     let mut stack = init_stack(&[2, 3], &[], &[], TRACE_LENGTH);
-    stack.execute(OpCode::Not, OpHint::None);
+    let result = stack.execute(OpCode::Not, OpHint::None);
+    assert_eq!(Err(ExecutionError::NonBinaryValue { step: 1, op: OpCode::Not, value: 2 }), result);
 }
 
 #[test]
 fn and() {
     let mut stack = init_stack(&[1, 1, 0], &[], &[], TRACE_LENGTH);
-    stack.execute(OpCode::And, OpHint::None);
+    stack.execute(OpCode::And, OpHint::None).unwrap();
     assert_eq!(vec![1, 0, 0, 0, 0, 0, 0, 0], get_stack_state(&stack, 1));
 
     assert_eq!(2, stack.depth);
     assert_eq!(3, stack.max_depth);
 
-    stack.execute(OpCode::And, OpHint::None);
+    stack.execute(OpCode::And, OpHint::None).unwrap();
     assert_eq!(vec![0, 0, 0, 0, 0, 0, 0, 0], get_stack_state(&stack, 2));
 
     assert_eq!(1, stack.depth);
@@ -305,22 +392,22 @@ fn and() {
 }
 
 #[test]
-#[should_panic(expected = "cannot compute AND for a non-binary value at step 1")]
 fn and_fail() {
     let mut stack = init_stack(&[1, 3], &[], &[], TRACE_LENGTH);
-    stack.execute(OpCode::And, OpHint::None);
+    let result = stack.execute(OpCode::And, OpHint::None);
+    assert_eq!(Err(ExecutionError::NonBinaryValue { step: 1, op: OpCode::And, value: 3 }), result);
 }
 
 #[test]
 fn or() {
     let mut stack = init_stack(&[0, 0, 1], &[], &[], TRACE_LENGTH);
-    stack.execute(OpCode::Or, OpHint::None);
+    stack.execute(OpCode::Or, OpHint::None).unwrap();
     assert_eq!(vec![0, 1, 0, 0, 0, 0, 0, 0], get_stack_state(&stack, 1));
 
     assert_eq!(2, stack.depth);
     assert_eq!(3, stack.max_depth);
 
-    stack.execute(OpCode::Or, OpHint::None);
+    stack.execute(OpCode::Or, OpHint::None).unwrap();
     assert_eq!(vec![1, 0, 0, 0, 0, 0, 0, 0], get_stack_state(&stack, 2));
 
     assert_eq!(1, stack.depth);
@@ -328,10 +415,38 @@ fn or() {
 }
 
 #[test]
-#[should_panic(expected = "cannot compute OR for a non-binary value at step 1")]
 fn and_or() {
     let mut stack = init_stack(&[1, 3], &[], &[], TRACE_LENGTH);
-    stack.execute(OpCode::Or, OpHint::None);
+    let result = stack.execute(OpCode::Or, OpHint::None);
+    assert_eq!(Err(ExecutionError::NonBinaryValue { step: 1, op: OpCode::Or, value: 3 }), result);
+}
+
+#[test]
+fn expacc() {
+    // compute 3^5; bits of the exponent are pushed onto tape A by the hint
+    let mut stack = init_stack(&[0, 3, 1, 1, 0, 5, 7], &[], &[], TRACE_LENGTH);
+    stack.execute(OpCode::ExpAcc, OpHint::ExpStart(3)).unwrap();
+    assert_eq!(vec![1, 9, 3, 2, 1, 5, 7, 0], get_stack_state(&stack, 1));
+
+    stack.execute(OpCode::ExpAcc, OpHint::None).unwrap();
+    assert_eq!(vec![0, 81, 3, 4, 1, 5, 7, 0], get_stack_state(&stack, 2));
+
+    stack.execute(OpCode::ExpAcc, OpHint::None).unwrap();
+    assert_eq!(vec![1, 6561, 243, 8, 5, 5, 7, 0], get_stack_state(&stack, 3));
+
+    assert_eq!(7, stack.depth);
+    assert_eq!(7, stack.max_depth);
+}
+
+#[test]
+fn expacc_fail() {
+    let mut stack = init_stack(&[0, 3, 1, 1, 0, 5], &[2], &[], TRACE_LENGTH);
+    let result = stack.execute(OpCode::ExpAcc, OpHint::None);
+    assert_eq!(Err(ExecutionError::NonBinaryValue { step: 1, op: OpCode::ExpAcc, value: 2 }), result);
+
+    let mut stack = init_stack(&[0, 3, 1, 1, 0, 5], &[], &[], TRACE_LENGTH);
+    let result = stack.execute(OpCode::ExpAcc, OpHint::None);
+    assert_eq!(Err(ExecutionError::EmptyTape { step: 1, op: OpCode::ExpAcc, tape: 'A' }), result);
 }
 
 // CRYPTOGRAPHIC OPERATIONS
@@ -342,11 +457,11 @@ fn rescr() {
     let mut stack = init_stack(&[0, 0, 1, 2, 3, 4], &[], &[], TRACE_LENGTH);
     let mut expected = vec![0, 0, 1, 2, 3, 4, 0, 0];
 
-    stack.execute(OpCode::RescR, OpHint::None);
+    stack.execute(OpCode::RescR, OpHint::None).unwrap();
     hasher::apply_round(&mut expected[..HASH_STATE_WIDTH], 0);
     assert_eq!(expected, get_stack_state(&stack, 1));
 
-    stack.execute(OpCode::RescR, OpHint::None);
+    stack.execute(OpCode::RescR, OpHint::None).unwrap();
     hasher::apply_round(&mut expected[..HASH_STATE_WIDTH], 1);
     assert_eq!(expected, get_stack_state(&stack, 2));
 
@@ -354,6 +469,28 @@ fn rescr() {
     assert_eq!(6, stack.max_depth);
 }
 
+// STACK SHIFTS
+// ================================================================================================
+
+#[test]
+fn shift_underflow() {
+    let mut stack = init_stack(&[1, 2, 3], &[], &[], TRACE_LENGTH);
+    stack.advance_step();
+    let result = stack.shift_left(4, 4, OpCode::Drop4);
+    assert_eq!(Err(ExecutionError::StackUnderflow { step: 1, op: OpCode::Drop4 }), result);
+    assert_eq!(3, stack.depth);
+}
+
+#[test]
+fn shift_overflow() {
+    let mut stack = init_stack(&[1, 2, 3], &[], &[], TRACE_LENGTH);
+    stack.depth = MAX_STACK_DEPTH - 4;
+    stack.advance_step();
+    let result = stack.shift_right(0, 8, OpCode::Dup8);
+    assert_eq!(Err(ExecutionError::StackOverflow { step: 1, op: OpCode::Dup8 }), result);
+    assert_eq!(MAX_STACK_DEPTH - 4, stack.depth);
+}
+
 // HELPER FUNCTIONS
 // ================================================================================================
 
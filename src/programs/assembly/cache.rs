@@ -0,0 +1,311 @@
+use std::{ collections::{ HashMap, VecDeque }, fs, path::PathBuf };
+use log::debug;
+use crate::crypto::hash::blake3;
+use super::{ Program, AssemblyError, compile };
+
+// CONSTANTS
+// ================================================================================================
+
+/// Version of the assembler; programs compiled by a different version are never read from cache.
+/// The suffix is incremented whenever the same source starts compiling into a different program.
+pub const ASSEMBLER_VERSION: &str = concat!(env!("CARGO_PKG_VERSION"), "-1");
+
+/// Default number of programs kept by the in-memory store.
+pub const DEFAULT_CACHE_CAPACITY: usize = 64;
+
+// TYPES AND INTERFACES
+// ================================================================================================
+
+/// Storage for compiled programs; entries are keyed by a hash of the program source.
+pub trait ProgramStore {
+    /// Returns the bytes stored under the `key`, if any.
+    fn get(&mut self, key: &[u8; 32]) -> Option<Vec<u8>>;
+
+    /// Stores `bytes` under the `key`, replacing any existing entry.
+    fn put(&mut self, key: &[u8; 32], bytes: Vec<u8>);
+
+    /// Removes the entry stored under the `key`, if any.
+    fn remove(&mut self, key: &[u8; 32]);
+}
+
+/// Assembly compiler which keeps compiled programs in a `ProgramStore`, and re-assembles a
+/// program only if its source has not been compiled before.
+pub struct CachingCompiler<S: ProgramStore = MemoryStore> {
+    store   : S,
+    stats   : CacheStats,
+}
+
+/// Counts of cache lookups made by a `CachingCompiler`.
+#[derive(Copy, Clone, Debug, Default, PartialEq)]
+pub struct CacheStats {
+    pub hits        : usize,
+    pub misses      : usize,
+    /// Number of cache entries discarded because they did not contain a valid program;
+    /// these are also counted as misses.
+    pub rejected    : usize,
+}
+
+/// In-memory program store which evicts the least recently used program once it is full.
+pub struct MemoryStore {
+    capacity    : usize,
+    entries     : HashMap<[u8; 32], Vec<u8>>,
+    order       : VecDeque<[u8; 32]>,
+}
+
+/// Program store which keeps each program in a separate file in a directory.
+pub struct DirectoryStore {
+    path        : PathBuf,
+}
+
+// CACHING COMPILER IMPLEMENTATION
+// ================================================================================================
+impl CachingCompiler<MemoryStore> {
+
+    /// Returns a compiler which keeps up to `DEFAULT_CACHE_CAPACITY` programs in memory.
+    pub fn new() -> CachingCompiler<MemoryStore> {
+        return CachingCompiler::with_store(MemoryStore::new(DEFAULT_CACHE_CAPACITY));
+    }
+}
+
+impl<S: ProgramStore> CachingCompiler<S> {
+
+    /// Returns a compiler which keeps compiled programs in the specified `store`.
+    pub fn with_store(store: S) -> CachingCompiler<S> {
+        return CachingCompiler { store, stats: CacheStats::default() };
+    }
+
+    /// Compiles provided assembly code into a program, or reads the program from the store if
+    /// the same code was compiled before. Programs read from the store are validated by
+    /// recomputing their hashes; invalid entries are discarded and the code is re-assembled.
+    pub fn compile(&mut self, source: &str) -> Result<Program, AssemblyError> {
+        let key = cache_key(source);
+
+        if let Some(bytes) = self.store.get(&key) {
+            match Program::from_bytes(&bytes) {
+                Ok(program) => {
+                    self.stats.hits += 1;
+                    return Ok(program);
+                },
+                Err(err) => {
+                    debug!("Discarded cached program {}: {}", hex::encode(key), err);
+                    self.store.remove(&key);
+                    self.stats.rejected += 1;
+                }
+            }
+        }
+
+        self.stats.misses += 1;
+        let program = compile(source)?;
+        self.store.put(&key, program.to_bytes());
+        return Ok(program);
+    }
+
+    pub fn stats(&self) -> CacheStats {
+        return self.stats;
+    }
+
+    pub fn store(&self) -> &S {
+        return &self.store;
+    }
+
+    pub fn store_mut(&mut self) -> &mut S {
+        return &mut self.store;
+    }
+}
+
+// MEMORY STORE IMPLEMENTATION
+// ================================================================================================
+impl MemoryStore {
+
+    /// Returns an empty store which can hold up to `capacity` programs.
+    pub fn new(capacity: usize) -> MemoryStore {
+        assert!(capacity > 0, "cache capacity must be greater than 0");
+        return MemoryStore { capacity, entries: HashMap::new(), order: VecDeque::new() };
+    }
+
+    pub fn len(&self) -> usize {
+        return self.entries.len();
+    }
+
+    /// moves the `key` to the back of the eviction queue
+    fn touch(&mut self, key: &[u8; 32]) {
+        if let Some(position) = self.order.iter().position(|k| k == key) {
+            self.order.remove(position);
+        }
+        self.order.push_back(*key);
+    }
+}
+
+impl ProgramStore for MemoryStore {
+
+    fn get(&mut self, key: &[u8; 32]) -> Option<Vec<u8>> {
+        let bytes = self.entries.get(key)?.clone();
+        self.touch(key);
+        return Some(bytes);
+    }
+
+    fn put(&mut self, key: &[u8; 32], bytes: Vec<u8>) {
+        if !self.entries.contains_key(key) && self.entries.len() == self.capacity {
+            if let Some(oldest) = self.order.pop_front() {
+                self.entries.remove(&oldest);
+            }
+        }
+        self.entries.insert(*key, bytes);
+        self.touch(key);
+    }
+
+    fn remove(&mut self, key: &[u8; 32]) {
+        self.entries.remove(key);
+        self.order.retain(|k| k != key);
+    }
+}
+
+// DIRECTORY STORE IMPLEMENTATION
+// ================================================================================================
+impl DirectoryStore {
+
+    /// Returns a store which keeps programs in the directory at `path`; the directory is
+    /// created if it does not exist.
+    pub fn new<P: Into<PathBuf>>(path: P) -> Result<DirectoryStore, String> {
+        let path = path.into();
+        if let Err(err) = fs::create_dir_all(&path) {
+            return Err(format!("failed to create cache directory {}: {}", path.display(), err));
+        }
+        return Ok(DirectoryStore { path });
+    }
+
+    /// Returns path to the file in which the program with the specified `key` is stored.
+    pub fn entry_path(&self, key: &[u8; 32]) -> PathBuf {
+        return self.path.join(format!("{}.bin", hex::encode(key)));
+    }
+}
+
+impl ProgramStore for DirectoryStore {
+
+    fn get(&mut self, key: &[u8; 32]) -> Option<Vec<u8>> {
+        return fs::read(self.entry_path(key)).ok();
+    }
+
+    fn put(&mut self, key: &[u8; 32], bytes: Vec<u8>) {
+        // failing to write an entry is not an error; the program will be compiled again
+        if let Err(err) = fs::write(self.entry_path(key), bytes) {
+            debug!("Failed to write cached program {}: {}", hex::encode(key), err);
+        }
+    }
+
+    fn remove(&mut self, key: &[u8; 32]) {
+        let _ = fs::remove_file(self.entry_path(key));
+    }
+}
+
+// HELPER FUNCTIONS
+// ================================================================================================
+
+/// Returns a hash of the assembler version and the `source` with comments removed and all
+/// whitespace collapsed into single spaces; neither affects how a program is compiled.
+fn cache_key(source: &str) -> [u8; 32] {
+    let (tokens, _) = super::tokenize(source);
+    let normalized = format!("{}\n{}", ASSEMBLER_VERSION, tokens.join(" "));
+
+    let mut key = [0u8; 32];
+    blake3(normalized.as_bytes(), &mut key);
+    return key;
+}
+
+// TESTS
+// ================================================================================================
+#[cfg(test)]
+mod tests {
+
+    use std::fs;
+    use super::{ CachingCompiler, CacheStats, DirectoryStore, MemoryStore, ProgramStore, cache_key };
+
+    const SOURCE: &str = "begin push.3 push.4 mul push.5 add hash.2 dup add end";
+
+    #[test]
+    fn repeated_compile_hits_cache() {
+        let mut compiler = CachingCompiler::new();
+        let program1 = compiler.compile(SOURCE).unwrap();
+        let program2 = compiler.compile(SOURCE).unwrap();
+        assert_eq!(CacheStats { hits: 1, misses: 1, rejected: 0 }, compiler.stats());
+        assert_eq!(program1.hash(), program2.hash());
+        assert_eq!(format!("{:?}", program1), format!("{:?}", program2));
+
+        // differences in whitespace do not affect the key
+        let program3 = compiler.compile("  begin push.3 push.4 mul push.5\n\tadd hash.2 dup add end ").unwrap();
+        assert_eq!(CacheStats { hits: 2, misses: 1, rejected: 0 }, compiler.stats());
+        assert_eq!(program1.hash(), program3.hash());
+
+        // neither do comments
+        let program4 = compiler.compile("begin push.3 push.4 mul # product\npush.5 add hash.2 dup add end").unwrap();
+        assert_eq!(CacheStats { hits: 3, misses: 1, rejected: 0 }, compiler.stats());
+        assert_eq!(program1.hash(), program4.hash());
+    }
+
+    #[test]
+    fn changed_source_misses_cache() {
+        let mut compiler = CachingCompiler::new();
+        let program1 = compiler.compile(SOURCE).unwrap();
+        let program2 = compiler.compile(&SOURCE.replace("push.5", "push.6")).unwrap();
+        assert_eq!(CacheStats { hits: 0, misses: 2, rejected: 0 }, compiler.stats());
+        assert_ne!(program1.hash(), program2.hash());
+        assert_eq!(2, compiler.store().len());
+    }
+
+    #[test]
+    fn corrupted_entry_is_recompiled() {
+        let mut compiler = CachingCompiler::new();
+        let expected = compiler.compile(SOURCE).unwrap();
+
+        // flip a bit in the stored program hash
+        let key = cache_key(SOURCE);
+        let mut bytes = compiler.store_mut().get(&key).unwrap();
+        let last = bytes.len() - 1;
+        bytes[last] ^= 1;
+        compiler.store_mut().put(&key, bytes);
+
+        let program = compiler.compile(SOURCE).unwrap();
+        assert_eq!(CacheStats { hits: 0, misses: 2, rejected: 1 }, compiler.stats());
+        assert_eq!(expected.hash(), program.hash());
+
+        // the entry was replaced with a valid program
+        compiler.compile(SOURCE).unwrap();
+        assert_eq!(CacheStats { hits: 1, misses: 2, rejected: 1 }, compiler.stats());
+    }
+
+    #[test]
+    fn least_recently_used_entry_is_evicted() {
+        let sources = ["begin push.1 end", "begin push.2 end", "begin push.3 end"];
+        let mut compiler = CachingCompiler::with_store(MemoryStore::new(2));
+        compiler.compile(sources[0]).unwrap();
+        compiler.compile(sources[1]).unwrap();
+        compiler.compile(sources[0]).unwrap();
+        compiler.compile(sources[2]).unwrap();
+        assert_eq!(2, compiler.store().len());
+
+        compiler.compile(sources[0]).unwrap();
+        compiler.compile(sources[1]).unwrap();
+        assert_eq!(CacheStats { hits: 2, misses: 4, rejected: 0 }, compiler.stats());
+    }
+
+    #[test]
+    fn directory_store() {
+        let path = std::env::temp_dir().join(format!("distaff-program-cache-{}", std::process::id()));
+        let expected = CachingCompiler::with_store(DirectoryStore::new(&path).unwrap()).compile(SOURCE).unwrap();
+
+        // a new compiler reads the program compiled by the previous one
+        let mut compiler = CachingCompiler::with_store(DirectoryStore::new(&path).unwrap());
+        let program = compiler.compile(SOURCE).unwrap();
+        assert_eq!(CacheStats { hits: 1, misses: 0, rejected: 0 }, compiler.stats());
+        assert_eq!(expected.hash(), program.hash());
+
+        // a truncated entry is discarded
+        let entry_path = compiler.store().entry_path(&cache_key(SOURCE));
+        fs::write(&entry_path, &fs::read(&entry_path).unwrap()[..10]).unwrap();
+        let program = compiler.compile(SOURCE).unwrap();
+        assert_eq!(CacheStats { hits: 1, misses: 1, rejected: 1 }, compiler.stats());
+        assert_eq!(expected.hash(), program.hash());
+
+        fs::remove_dir_all(&path).unwrap();
+    }
+}
@@ -0,0 +1,250 @@
+use std::collections::{ BTreeSet, HashMap };
+use super::{ Program, ProgramBlock, Span, OpCode, OpHint, HintMap, BASE_CYCLE_LENGTH, parse_op_token };
+
+// CONSTANTS
+// ================================================================================================
+const INDENT: &str = "    ";
+
+/// Instructions which always compile into the same sequence of VM operations (up to alignment).
+const FIXED_INSTRUCTIONS: [&str; 87] = [
+    "noop",    "assert",  "assert.eq", "assert.z", "read.a",  "read.ab", "read.4",
+    "dup.1",   "dup.2",   "dup.3",     "dup.4",   "dup.8",
+    "pad.1",   "pad.2",   "pad.3",     "pad.4",   "pad.5",   "pad.6",   "pad.7",   "pad.8",
+    "pick.1",  "pick.2",  "pick.3",    "select.2", "select.4",
+    "drop.1",  "drop.2",  "drop.3",    "drop.4",  "drop.5",  "drop.6",  "drop.7",  "drop.8",
+    "drop.9",  "drop.10", "drop.11",   "drop.12", "drop.13", "drop.14", "drop.15", "drop.16",
+    "swap.1",  "swap.2",  "swap.4",    "roll.4",  "roll.8",
+    "add",     "sub",     "mul",       "div",     "neg",     "inv",     "not",     "and",   "or",
+    "eq",      "ne",      "iszero",    "choose.1",  "choose.2",  "cswap.2", "cswap.4", "bits.1",
+    "u32.add", "u32.sub", "u32.mul",   "u32.and", "u32.or",  "u32.xor",
+    "hash.1",  "hash.2",  "hash.3",    "hash.4",
+    "movup.2", "movup.3", "movup.4",   "movup.5", "movup.6", "movup.7", "movup.8",
+    "movdn.2", "movdn.3", "movdn.4",   "movdn.5", "movdn.6", "movdn.7", "movdn.8",
+];
+
+/// First operations of a `smpath` instruction.
+const SMPATH_HEAD: [OpCode; 4] = [ OpCode::Read2, OpCode::Swap2, OpCode::Read2, OpCode::CSwap2 ];
+
+/// False branch which the assembler creates for `if` blocks without an `else` clause.
+const EMPTY_FALSE_BRANCH: [OpCode; 15] = [
+    OpCode::Not,  OpCode::Assert, OpCode::Noop, OpCode::Noop,
+    OpCode::Noop, OpCode::Noop,   OpCode::Noop, OpCode::Noop,
+    OpCode::Noop, OpCode::Noop,   OpCode::Noop, OpCode::Noop,
+    OpCode::Noop, OpCode::Noop,   OpCode::Noop,
+];
+
+// DECOMPILER
+// ================================================================================================
+
+/// Translates a program into assembly source code; compiling the returned source yields a
+/// program with the same hash. Control flow blocks are placed on separate lines, and their
+/// bodies are indented.
+///
+/// # Panics
+/// Panics if a Span block of the program contains a sequence of operations which cannot be
+/// produced by any sequence of assembly instructions; programs built by `compile()` never
+/// contain such sequences.
+pub fn decompile(program: &Program) -> String {
+    let mut lines = Vec::new();
+    lines.push(String::from("begin"));
+    decompile_body(program.root().body(), 1, 1, &mut lines);
+    lines.push(String::from("end"));
+    return lines.join("\n");
+}
+
+// HELPER FUNCTIONS
+// ================================================================================================
+
+/// Appends assembly instructions for a body of a program block to `lines`; `prefix_length` is
+/// the number of operations which the assembler inserts at the start of the body based on the
+/// block head, and `depth` is the nesting depth of the body.
+fn decompile_body(blocks: &[ProgramBlock], prefix_length: usize, depth: usize, lines: &mut Vec<String>) {
+    let indent = INDENT.repeat(depth);
+    let body_start = lines.len();
+
+    for (i, block) in blocks.iter().enumerate() {
+        match block {
+            ProgramBlock::Span(span) => {
+                let start = if i == 0 { prefix_length } else { 0 };
+                let instructions = decompile_span(span, start);
+
+                // the assembler always creates the first span of a body, but other spans
+                // are created only if they contain at least one instruction
+                if instructions.len() == 0 && i > 0 {
+                    lines.push(format!("{}noop", indent));
+                }
+                for instruction in instructions {
+                    lines.push(format!("{}{}", indent, instruction));
+                }
+            },
+            ProgramBlock::Group(group) => {
+                lines.push(format!("{}block", indent));
+                decompile_body(group.body(), 0, depth + 1, lines);
+                lines.push(format!("{}end", indent));
+            },
+            ProgramBlock::Switch(switch) => {
+                lines.push(format!("{}if.true", indent));
+                decompile_body(switch.true_branch(), 1, depth + 1, lines);
+                if !is_empty_false_branch(switch.false_branch()) {
+                    lines.push(format!("{}else", indent));
+                    decompile_body(switch.false_branch(), 2, depth + 1, lines);
+                }
+                lines.push(format!("{}end", indent));
+            },
+            ProgramBlock::Loop(block) => {
+                match block.max_iterations() {
+                    Some(n) => lines.push(format!("{}while.true.max={}", indent, n)),
+                    None    => lines.push(format!("{}while.true", indent)),
+                }
+                decompile_body(block.body(), 1, depth + 1, lines);
+                lines.push(format!("{}end", indent));
+            },
+        }
+    }
+
+    // a body of a block must contain at least one instruction
+    if lines.len() == body_start {
+        lines.push(format!("{}noop", indent));
+    }
+}
+
+/// Returns a sequence of assembly instructions which compiles into operations of the `span`
+/// starting with the operation at index `start`. Trailing NOOPs which the assembler adds to
+/// pad the span are omitted.
+fn decompile_span(span: &Span, start: usize) -> Vec<String> {
+    let length = span.length();
+    let candidates = get_candidates(span);
+    let mut templates: HashMap<(usize, usize), (Vec<OpCode>, HintMap)> = HashMap::new();
+
+    // find a way to cover operations with instructions going from the end of the span; for
+    // each step, choose the longest instruction after which the rest of the span can be covered
+    let mut choices: Vec<Option<Option<usize>>> = vec![None; length + 1];
+    choices[length] = Some(None);
+    for i in (start..length).rev() {
+        if length - i < BASE_CYCLE_LENGTH && (i..length).all(|j| span.get_op(j) == (OpCode::Noop, OpHint::None)) {
+            choices[i] = Some(None);
+            continue;
+        }
+
+        let mut best: Option<(usize, usize)> = None;
+        for (c, candidate) in candidates.iter().enumerate() {
+            let (op_codes, op_hints) = templates.entry((c, i % BASE_CYCLE_LENGTH))
+                .or_insert_with(|| compile_template(candidate, i % BASE_CYCLE_LENGTH));
+
+            let end = i + op_codes.len();
+            if end > length || choices[end].is_none() { continue; }
+            if best.map_or(false, |(_, best_end)| best_end >= end) { continue; }
+
+            let matches = (0..op_codes.len()).all(|k| {
+                let hint = op_hints.get(&k).cloned().unwrap_or(OpHint::None);
+                span.get_op(i + k) == (op_codes[k], hint)
+            });
+            if matches {
+                best = Some((c, end));
+            }
+        }
+
+        if let Some((c, _)) = best {
+            choices[i] = Some(Some(c));
+        }
+    }
+
+    // read the chosen instructions starting from the beginning of the span
+    let mut instructions = Vec::new();
+    let mut i = start;
+    while i < length {
+        match choices[i] {
+            Some(Some(c)) => {
+                instructions.push(candidates[c].clone());
+                i += templates[&(c, i % BASE_CYCLE_LENGTH)].0.len();
+            },
+            Some(None) => break,
+            None => panic!("operations {:?} cannot be expressed as assembly instructions", span),
+        }
+    }
+
+    return instructions;
+}
+
+/// Returns all instructions which could have been used to build the `span`; parameters of
+/// instructions which place hints into the span are derived from these hints.
+fn get_candidates(span: &Span) -> Vec<String> {
+    let mut candidates: Vec<String> = FIXED_INSTRUCTIONS.iter().map(|&s| String::from(s)).collect();
+
+    let mut hinted = BTreeSet::new();
+    let mut rescue_rounds = 0;
+    let mut has_smpath = false;
+    for i in 0..span.length() {
+        let (op_code, op_hint) = span.get_op(i);
+        match op_hint {
+            OpHint::PushValue(value) => { hinted.insert(format!("push.{}", value)); },
+            OpHint::CmpStart(n) => {
+                hinted.insert(format!("gt.{}", n));
+                hinted.insert(format!("lt.{}", n));
+            },
+            OpHint::RcStart(n) => {
+                hinted.insert(format!("rc.{}", n));
+                hinted.insert(format!("isodd.{}", n));
+                if n == 32 {
+                    // shifts and rotations do not record the number of bits in their hints
+                    for op in ["shl", "shr", "rotl", "rotr"].iter() {
+                        hinted.extend((0..32).map(|s| format!("u32.{}.{}", op, s)));
+                    }
+                }
+            },
+            OpHint::UnpackStart(n) => { hinted.insert(format!("unpack.{}", n)); },
+            OpHint::ExpStart(n) => { hinted.insert(format!("exp.{}", n)); },
+            OpHint::DivStart => { hinted.insert(String::from("u32.divmod")); },
+            OpHint::BitsStart(n) => { hinted.insert(format!("bits.{}", n)); },
+            OpHint::PmpathStart(n) => { hinted.insert(format!("pmpath.{}", n)); },
+            _ => (),
+        }
+
+        if op_code == OpCode::RescR {
+            rescue_rounds += 1;
+        }
+        if span.length() - i >= SMPATH_HEAD.len()
+            && (0..SMPATH_HEAD.len()).all(|k| span.get_op(i + k).0 == SMPATH_HEAD[k])
+        {
+            has_smpath = true;
+        }
+    }
+    candidates.extend(hinted.into_iter());
+
+    // smpath and mtree.verify do not place any hints into the span, and so all possible depths
+    // are matched
+    if has_smpath {
+        let max_depth = std::cmp::min(rescue_rounds / 10 + 1, 256);
+        candidates.extend((2..=max_depth).map(|n| format!("smpath.{}", n)));
+        candidates.extend((2..=max_depth).map(|n| format!("mtree.verify.{}", n)));
+    }
+
+    return candidates;
+}
+
+/// Compiles a single assembly instruction as if it were placed at the specified step of a span;
+/// returns operations and hints emitted by the instruction.
+fn compile_template(instruction: &str, step: usize) -> (Vec<OpCode>, HintMap) {
+    let mut op_codes = vec![OpCode::Noop; step];
+    let mut op_hints = HashMap::new();
+    let op: Vec<&str> = instruction.split(".").collect();
+    parse_op_token(op, &mut op_codes, &mut op_hints, 0)
+        .expect("failed to compile a decompiled instruction");
+
+    let op_hints = op_hints.into_iter().map(|(k, hint)| (k - step, hint)).collect();
+    return (op_codes.split_off(step), op_hints);
+}
+
+/// Returns true if the `branch` is the false branch which the assembler creates for `if` blocks
+/// without an `else` clause.
+fn is_empty_false_branch(branch: &[ProgramBlock]) -> bool {
+    return match branch {
+        [ProgramBlock::Span(span)] => span.length() == EMPTY_FALSE_BRANCH.len()
+            && span.starts_with(&EMPTY_FALSE_BRANCH)
+            && (0..span.length()).all(|i| span.get_hint(i) == OpHint::None),
+        _ => false,
+    };
+}
+
+
+
@@ -1,9 +1,14 @@
+use super::super::blocks::SpanError;
+
 // TYPES AND INTERFACES
 // ================================================================================================
 pub struct AssemblyError {
     message : String,
     step    : usize,
-    op      : String
+    op      : String,
+    line    : usize,
+    column  : usize,
+    token   : String,
 }
 
 // ASSEMBLY ERROR IMPLEMENTATION
@@ -18,6 +23,9 @@ impl AssemblyError {
             message : String::from("a program must contain at least one instruction"),
             step    : 0,
             op      : String::from("begin"),
+            line    : 0,
+            column  : 0,
+            token   : String::new(),
         };
     }
 
@@ -26,22 +34,31 @@ impl AssemblyError {
             message : String::from("a program block must contain at least one instruction"),
             step    : step,
             op      : op.join("."),
+            line    : 0,
+            column  : 0,
+            token   : String::new(),
         };
     }
 
     pub fn invalid_program_start(op: &str) -> AssemblyError {
         return AssemblyError {
-            message : String::from("a program must start with a 'being' instruction"),
+            message : String::from("a program must start with a 'begin' instruction"),
             step    : 0,
             op      : String::from(op),
+            line    : 0,
+            column  : 0,
+            token   : String::new(),
         };
     }
 
-    pub fn invalid_program_end(op: &str) -> AssemblyError {
+    pub fn invalid_program_end(op: &str, step: usize) -> AssemblyError {
         return AssemblyError {
             message : String::from("a program must end with an 'end' instruction"),
-            step    : 0,
+            step    : step,
             op      : String::from(op),
+            line    : 0,
+            column  : 0,
+            token   : String::new(),
         };
     }
 
@@ -50,14 +67,20 @@ impl AssemblyError {
             message : format!("dangling instructions after program end"),
             step    : step,
             op      : String::from("end"),
+            line    : 0,
+            column  : 0,
+            token   : String::new(),
         };
     }
 
     pub fn invalid_op(op: &[&str], step: usize) -> AssemblyError {
         return AssemblyError {
-            message : format!("instruction {} is invalid", op.join(".")),
+            message : format!("unknown instruction '{}'", op.join(".")),
             step    : step,
             op      : op.join("."),
+            line    : 0,
+            column  : 0,
+            token   : String::new(),
         };
     }
 
@@ -66,6 +89,9 @@ impl AssemblyError {
             message : format!("malformed instruction {}: parameter is missing", op[0]),
             step    : step,
             op      : op.join("."),
+            line    : 0,
+            column  : 0,
+            token   : String::new(),
         };
     }
 
@@ -74,6 +100,9 @@ impl AssemblyError {
             message : format!("malformed instruction {}: too many parameters provided", op[0]),
             step    : step,
             op      : op.join("."),
+            line    : 0,
+            column  : 0,
+            token   : String::new(),
         };
     }
 
@@ -82,6 +111,9 @@ impl AssemblyError {
             message : format!("malformed instruction {}: parameter '{}' is invalid", op[0], op[1]),
             step    : step,
             op      : op.join("."),
+            line    : 0,
+            column  : 0,
+            token   : String::new(),
         };
     }
 
@@ -90,6 +122,9 @@ impl AssemblyError {
             message : format!("malformed instruction {}: {}", op[0], reason),
             step    : step,
             op      : op.join("."),
+            line    : 0,
+            column  : 0,
+            token   : String::new(),
         };
     }
 
@@ -98,14 +133,20 @@ impl AssemblyError {
             message : format!("invalid block head '{}'", op.join(".")),
             step    : step,
             op      : op.join("."),
+            line    : 0,
+            column  : 0,
+            token   : String::new(),
         };
     }
 
     pub fn invalid_num_iterations(op: &[&str], step: usize) -> AssemblyError {
         return AssemblyError {
-            message : format!("invalid repeat statement '{}': 2 or more iterations must be specified", op.join(".")),
+            message : format!("invalid repeat statement '{}': at least 1 iteration must be specified", op.join(".")),
             step    : step,
             op      : op.join("."),
+            line    : 0,
+            column  : 0,
+            token   : String::new(),
         };
     }
 
@@ -114,6 +155,31 @@ impl AssemblyError {
             message : format!("else without matching if"),
             step    : step,
             op      : String::from("else"),
+            line    : 0,
+            column  : 0,
+            token   : String::new(),
+        };
+    }
+
+    pub fn dangling_case(op: &[&str], step: usize) -> AssemblyError {
+        return AssemblyError {
+            message : format!("case without matching switch"),
+            step    : step,
+            op      : op.join("."),
+            line    : 0,
+            column  : 0,
+            token   : String::new(),
+        };
+    }
+
+    pub fn invalid_case(op: &[&str], expected: usize, step: usize) -> AssemblyError {
+        return AssemblyError {
+            message : format!("invalid case '{}'; expected case.{}", op.join("."), expected),
+            step    : step,
+            op      : op.join("."),
+            line    : 0,
+            column  : 0,
+            token   : String::new(),
         };
     }
 
@@ -122,6 +188,9 @@ impl AssemblyError {
             message : format!("block without matching end"),
             step    : step,
             op      : String::from("block"),
+            line    : 0,
+            column  : 0,
+            token   : String::new(),
         };
     }
 
@@ -130,6 +199,9 @@ impl AssemblyError {
             message : format!("if without matching else/end"),
             step    : step,
             op      : String::from("if.true"),
+            line    : 0,
+            column  : 0,
+            token   : String::new(),
         };
     }
 
@@ -138,6 +210,31 @@ impl AssemblyError {
             message : format!("while without matching end"),
             step    : step,
             op      : String::from("while.true"),
+            line    : 0,
+            column  : 0,
+            token   : String::new(),
+        };
+    }
+
+    pub fn loop_budget_exceeded(op: &[&str], step: usize, steps: usize, budget: usize) -> AssemblyError {
+        return AssemblyError {
+            message : format!("loop {} may take up to {} steps, which exceeds the budget of {} steps", op.join("."), steps, budget),
+            step    : step,
+            op      : op.join("."),
+            line    : 0,
+            column  : 0,
+            token   : String::new(),
+        };
+    }
+
+    pub fn unmatched_switch(step: usize) -> AssemblyError {
+        return AssemblyError {
+            message : format!("switch without matching end"),
+            step    : step,
+            op      : String::from("switch"),
+            line    : 0,
+            column  : 0,
+            token   : String::new(),
         };
     }
 
@@ -146,6 +243,9 @@ impl AssemblyError {
             message : format!("repeat without matching end"),
             step    : step,
             op      : op.join("."),
+            line    : 0,
+            column  : 0,
+            token   : String::new(),
         };
     }
 
@@ -154,9 +254,131 @@ impl AssemblyError {
             message : format!("else without matching end"),
             step    : step,
             op      : String::from("else"),
+            line    : 0,
+            column  : 0,
+            token   : String::new(),
+        };
+    }
+
+    pub fn unmatched_expr(step: usize) -> AssemblyError {
+        return AssemblyError {
+            message : String::from("expr without matching }"),
+            step    : step,
+            op      : String::from("expr"),
+            line    : 0,
+            column  : 0,
+            token   : String::new(),
+        };
+    }
+
+    pub fn invalid_expr(reason: String, step: usize) -> AssemblyError {
+        return AssemblyError {
+            message : reason,
+            step    : step,
+            op      : String::from("expr"),
+            line    : 0,
+            column  : 0,
+            token   : String::new(),
+        };
+    }
+
+    pub fn unmatched_proc(step: usize, op: &[&str]) -> AssemblyError {
+        return AssemblyError {
+            message : format!("proc without matching end"),
+            step    : step,
+            op      : op.join("."),
+            line    : 0,
+            column  : 0,
+            token   : String::new(),
+        };
+    }
+
+    pub fn duplicate_proc(op: &[&str], step: usize) -> AssemblyError {
+        return AssemblyError {
+            message : format!("procedure '{}' is already defined", op[1]),
+            step    : step,
+            op      : op.join("."),
+            line    : 0,
+            column  : 0,
+            token   : String::new(),
+        };
+    }
+
+    pub fn undefined_proc(op: &[&str], step: usize) -> AssemblyError {
+        return AssemblyError {
+            message : format!("procedure '{}' is not defined", op[1]),
+            step    : step,
+            op      : op.join("."),
+            line    : 0,
+            column  : 0,
+            token   : String::new(),
+        };
+    }
+
+    pub fn misplaced_definition(op: &[&str], step: usize) -> AssemblyError {
+        return AssemblyError {
+            message : String::from("constants and procedures must be defined before the program 'begin' instruction"),
+            step    : step,
+            op      : op.join("."),
+            line    : 0,
+            column  : 0,
+            token   : String::new(),
+        };
+    }
+
+    pub fn duplicate_const(op: &[&str], name: &str, step: usize) -> AssemblyError {
+        return AssemblyError {
+            message : format!("constant '{}' is already defined", name),
+            step    : step,
+            op      : op.join("."),
+            line    : 0,
+            column  : 0,
+            token   : String::new(),
+        };
+    }
+
+    pub fn undefined_const(op: &[&str], step: usize) -> AssemblyError {
+        return AssemblyError {
+            message : format!("constant '{}' is not defined", op[1]),
+            step    : step,
+            op      : op.join("."),
+            line    : 0,
+            column  : 0,
+            token   : String::new(),
+        };
+    }
+
+    pub fn limits_exceeded(reason: String) -> AssemblyError {
+        return AssemblyError {
+            message : reason,
+            step    : 0,
+            op      : String::from("begin"),
+            line    : 0,
+            column  : 0,
+            token   : String::new(),
         };
     }
 
+    pub fn invalid_span(reason: SpanError, step: usize) -> AssemblyError {
+        return AssemblyError {
+            message : format!("invalid instruction sequence: {}", reason),
+            step    : step,
+            op      : String::from("span"),
+            line    : 0,
+            column  : 0,
+            token   : String::new(),
+        };
+    }
+
+    /// Sets the position of the error in the source code; `line` and `column` are 1-based,
+    /// and `token` is the source token at this position.
+    pub(super) fn at_position(mut self, line: usize, column: usize, token: &str) -> AssemblyError {
+        self.line = line;
+        self.column = column;
+        self.token = String::from(token);
+        return self;
+    }
+
     // PUBLIC ACCESSORS
     // --------------------------------------------------------------------------------------------
     pub fn message(&self) -> &String {
@@ -170,20 +392,38 @@ impl AssemblyError {
     pub fn step(&self) -> usize {
         return self.step;
     }
+
+    /// Returns the 1-based line of the source code at which the error occurred.
+    pub fn line(&self) -> usize {
+        return self.line;
+    }
+
+    /// Returns the 1-based column of the source code at which the error occurred.
+    pub fn column(&self) -> usize {
+        return self.column;
+    }
+
+    /// Returns the source token at which the error occurred; the token is empty if the source
+    /// code contains no tokens.
+    pub fn token(&self) -> &String {
+        return &self.token;
+    }
 }
 
 
 // COMMON TRAIT IMPLEMENTATIONS
 // ================================================================================================
 
+impl std::error::Error for AssemblyError {}
+
 impl std::fmt::Debug for AssemblyError {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        write!(f, "assembly error at {}: {}", self.step, self.message)
+        write!(f, "{}", self)
     }
 }
 
 impl std::fmt::Display for AssemblyError {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        write!(f, "assembly error at {}: {}", self.step, self.message)
+        write!(f, "assembly error at line {}, column {}: {}", self.line, self.column, self.message)
     }
 }
\ No newline at end of file
@@ -0,0 +1,504 @@
+use crate::MAX_STACK_DEPTH;
+use super::OpCode;
+
+// CONSTANTS
+// ================================================================================================
+
+/// Deepest stack position from which a value can be brought to the top of the stack.
+const MAX_ACCESS_DEPTH: usize = 7;
+
+/// Upper bound on the number of steps needed to place two operands at the top of the stack.
+const MAX_ARRANGE_STEPS: usize = 16;
+
+// TYPES AND INTERFACES
+// ================================================================================================
+
+/// Token of an expression in reverse Polish notation.
+#[derive(Copy, Clone, Debug, PartialEq)]
+enum Token {
+    Input(usize),
+    Neg,
+    Add,
+    Sub,
+    Mul,
+    Div,
+}
+
+/// Contents of a stack slot tracked by the scheduler.
+#[derive(Copy, Clone, Debug, PartialEq)]
+enum Slot {
+    Input(usize),
+    Value(usize),
+    Junk,
+}
+
+/// Keeps track of stack slots touched by the expression code while the code is being built.
+struct Scheduler {
+    program     : Vec<OpCode>,
+    stack       : Vec<Slot>,        // top of the stack first
+    uses        : Vec<usize>,       // remaining number of uses for each input
+    allow_moves : bool,
+    next_value  : usize,
+}
+
+// PUBLIC FUNCTIONS
+// ================================================================================================
+
+/// Compiles an arithmetic expression into a sequence of VM operations.
+///
+/// The expression may refer to the initial stack values as `in0`, `in1` etc. (with `in0` being
+/// the top of the stack), and may use `+`, `-`, `*`, `/` (multiplication by an inverse), unary
+/// `-` and parentheses. If the highest referenced input is `inN`, the produced code replaces the
+/// top N + 1 values of the stack with the result of the expression. An error is returned if the
+/// code would need to reach values deeper than the 8th position of the stack.
+pub fn compile_expr(source: &str) -> Result<Vec<OpCode>, String> {
+    let rpn = to_rpn(&tokenize(source)?)?;
+
+    let num_inputs = rpn.iter().fold(0, |n, token| match token {
+        Token::Input(i) => std::cmp::max(n, i + 1),
+        _ => n,
+    });
+    if num_inputs == 0 {
+        return Err(String::from("expression must refer to at least one input"));
+    }
+
+    // moving inputs to the top of the stack usually produces shorter code, but copying them
+    // keeps more of the stack accessible; so, we try both and pick the shortest code
+    let mut result: Result<Vec<OpCode>, String> = Err(String::new());
+    for &allow_moves in [true, false].iter() {
+        let program = Scheduler::new(num_inputs, allow_moves).build(&rpn);
+        result = match (result, program) {
+            (Ok(best), Ok(program)) if program.len() < best.len() => Ok(program),
+            (Ok(best), _) => Ok(best),
+            (Err(_), program) => program,
+        };
+    }
+
+    return result.map_err(|err| format!("cannot compile expression '{}': {}", source.trim(), err));
+}
+
+// PARSING
+// ================================================================================================
+
+fn tokenize(source: &str) -> Result<Vec<(char, usize)>, String> {
+    // tokens are represented as (kind, input index) tuples, where kind 'i' marks an input
+    let mut tokens = Vec::new();
+    let chars: Vec<char> = source.chars().collect();
+    let mut i = 0;
+    while i < chars.len() {
+        match chars[i] {
+            c if c.is_whitespace() => i += 1,
+            '+' | '-' | '*' | '/' | '(' | ')' => {
+                tokens.push((chars[i], 0));
+                i += 1;
+            },
+            'i' if chars.get(i + 1) == Some(&'n') => {
+                let start = i + 2;
+                let mut end = start;
+                while end < chars.len() && chars[end].is_ascii_digit() { end += 1; }
+                let name: String = chars[i..end].iter().collect();
+                let index = match chars[start..end].iter().collect::<String>().parse::<usize>() {
+                    Ok(index) => index,
+                    Err(_) => return Err(format!("input {} is invalid", name)),
+                };
+                tokens.push(('i', index));
+                i = end;
+            },
+            c => return Err(format!("unexpected character '{}'", c)),
+        }
+    }
+    return Ok(tokens);
+}
+
+/// Converts a list of tokens into reverse Polish notation using the shunting-yard algorithm.
+fn to_rpn(tokens: &[(char, usize)]) -> Result<Vec<Token>, String> {
+    let mut output = Vec::new();
+    let mut operators: Vec<char> = Vec::new();
+
+    // an operand is expected at the start of an expression, and after operators and '('
+    let mut expect_operand = true;
+    for &(kind, index) in tokens.iter() {
+        match kind {
+            'i' => {
+                if !expect_operand { return Err(format!("missing operator before in{}", index)); }
+                output.push(Token::Input(index));
+                expect_operand = false;
+            },
+            '(' => {
+                if !expect_operand { return Err(String::from("missing operator before '('")); }
+                operators.push('(');
+            },
+            ')' => {
+                if expect_operand { return Err(String::from("missing operand before ')'")); }
+                loop {
+                    match operators.pop() {
+                        Some('(') => break,
+                        Some(op) => output.push(operator_token(op)),
+                        None => return Err(String::from("unmatched ')'")),
+                    }
+                }
+            },
+            '-' if expect_operand => operators.push('~'),
+            op => {
+                if expect_operand { return Err(format!("missing operand before '{}'", op)); }
+                // all binary operators are left-associative
+                while let Some(&top) = operators.last() {
+                    if top == '(' || precedence(top) < precedence(op) { break; }
+                    output.push(operator_token(operators.pop().unwrap()));
+                }
+                operators.push(op);
+                expect_operand = true;
+            },
+        }
+    }
+
+    if expect_operand {
+        return Err(String::from(if tokens.is_empty() { "expression is empty" } else { "missing operand at the end" }));
+    }
+    while let Some(op) = operators.pop() {
+        if op == '(' { return Err(String::from("unmatched '('")); }
+        output.push(operator_token(op));
+    }
+
+    return Ok(output);
+}
+
+fn precedence(op: char) -> usize {
+    return match op {
+        '+' | '-' => 1,
+        '*' | '/' => 2,
+        _         => 3,     // unary minus
+    };
+}
+
+fn operator_token(op: char) -> Token {
+    return match op {
+        '+' => Token::Add,
+        '-' => Token::Sub,
+        '*' => Token::Mul,
+        '/' => Token::Div,
+        _   => Token::Neg,
+    };
+}
+
+// SCHEDULER IMPLEMENTATION
+// ================================================================================================
+impl Scheduler {
+
+    fn new(num_inputs: usize, allow_moves: bool) -> Scheduler {
+        return Scheduler {
+            program     : Vec::new(),
+            stack       : (0..num_inputs).map(|i| Slot::Input(i)).collect(),
+            uses        : vec![0; num_inputs],
+            allow_moves : allow_moves,
+            next_value  : 0,
+        };
+    }
+
+    /// Builds code evaluating the expression; after the code is executed, the result of the
+    /// expression is the only slot left on the stack.
+    fn build(mut self, rpn: &[Token]) -> Result<Vec<OpCode>, String> {
+        for token in rpn.iter() {
+            if let Token::Input(i) = token { self.uses[*i] += 1; }
+        }
+
+        // the values produced by the tokens so far
+        let mut values: Vec<usize> = Vec::new();
+        for &token in rpn.iter() {
+            let value = match token {
+                Token::Input(i) => self.fetch_input(i)?,
+                Token::Neg => {
+                    // operand of a unary operator is always at the top of the stack
+                    values.pop();
+                    self.apply(&[OpCode::Neg], 1)
+                },
+                _ => {
+                    let y = values.pop().unwrap();
+                    let x = values.pop().unwrap();
+                    self.arrange(x, y)?;
+                    match token {
+                        Token::Add => self.apply(&[OpCode::Add], 2),
+                        Token::Sub => self.apply(&[OpCode::Neg, OpCode::Add], 2),
+                        Token::Mul => self.apply(&[OpCode::Mul], 2),
+                        _          => self.apply(&[OpCode::Inv, OpCode::Mul], 2),
+                    }
+                },
+            };
+            values.push(value);
+            if self.stack.len() > MAX_STACK_DEPTH {
+                return Err(String::from("expression exceeds maximum stack depth"));
+            }
+        }
+
+        // remove all slots under the result
+        while self.stack.len() > 1 {
+            self.swap();
+            self.drop();
+        }
+
+        return Ok(self.program);
+    }
+
+    // SCHEDULING
+    // --------------------------------------------------------------------------------------------
+
+    /// Places the value of the input `i` at the top of the stack. If moves are allowed, the
+    /// last use of an input moves the input to the top; otherwise, the input is copied.
+    fn fetch_input(&mut self, i: usize) -> Result<usize, String> {
+        self.uses[i] -= 1;
+        if self.allow_moves && self.uses[i] == 0 {
+            self.move_to_top(Slot::Input(i))?;
+        }
+        else {
+            self.pick(Slot::Input(i))?;
+        }
+
+        let value = self.new_value();
+        self.stack[0] = Slot::Value(value);
+        return Ok(value);
+    }
+
+    /// Places value `y` at the top of the stack and value `x` right under it.
+    fn arrange(&mut self, x: usize, y: usize) -> Result<(), String> {
+        let (x, y) = (Slot::Value(x), Slot::Value(y));
+        for _ in 0..MAX_ARRANGE_STEPS {
+            match (self.position(y)?, self.position(x)?) {
+                (0, 1) => return Ok(()),
+                (1, 0) => self.swap(),
+                (py, px) if py <= 3 && px <= 3 => {
+                    // both operands are among the top 4 slots; permute them without copying
+                    if self.pad_to(4) { continue; }
+                    match (py, px) {
+                        (0, 2) => { self.swap(); self.swap2(); self.roll4(); },
+                        (0, 3) => { self.roll4(); self.swap(); },
+                        (1, 2) => { self.swap2(); self.roll4(); },
+                        (1, 3) => { self.swap(); self.roll4(); self.swap(); },
+                        (2, 0) => { self.roll4(); self.swap(); self.roll4(); },
+                        (2, 1) => { self.swap2(); self.roll4(); self.swap(); },
+                        (2, 3) => self.swap2(),
+                        (3, 0) => self.roll4(),
+                        (3, 1) => { self.swap(); self.roll4(); },
+                        _      => { self.swap2(); self.swap(); },   // (3, 2)
+                    }
+                },
+                (0, px) => {
+                    // rotating the stack by 4 would keep x and y 4 slots apart
+                    if self.pad_to(8) { continue; }
+                    self.rotate(if px == 4 { 3 } else { 8 - px });
+                },
+                _ => self.move_to_top(y)?,
+            }
+        }
+        unreachable!("failed to arrange expression operands");
+    }
+
+    /// Brings the `slot` to the top of the stack; the order of other slots may change.
+    fn move_to_top(&mut self, slot: Slot) -> Result<(), String> {
+        match self.prepare(slot)? {
+            0 => (),
+            1 => self.swap(),
+            2 => {
+                self.roll4();
+                self.roll4();
+            },
+            3 => self.roll4(),
+            depth => self.rotate(8 - depth),
+        }
+        return Ok(());
+    }
+
+    /// Places a copy of the `slot` at the top of the stack; the order of other slots is
+    /// preserved unless the slot is deeper than the 4th position.
+    fn pick(&mut self, slot: Slot) -> Result<(), String> {
+        let depth = self.prepare(slot)?;
+        match depth {
+            0 => self.program.push(OpCode::Dup),
+            1 => self.program.extend_from_slice(&[OpCode::Dup2, OpCode::Drop]),
+            2 => self.program.extend_from_slice(&[OpCode::Dup4, OpCode::Roll4, OpCode::Dup, OpCode::Drop4]),
+            3 => self.program.extend_from_slice(&[OpCode::Dup4, OpCode::Dup, OpCode::Drop4]),
+            _ => {
+                self.rotate(8 - depth);
+                self.program.push(OpCode::Dup);
+            },
+        }
+        self.stack.insert(0, slot);
+        return Ok(());
+    }
+
+    /// Makes sure the stack has enough slots for the operations which access the `slot`, and
+    /// returns the depth of the slot.
+    fn prepare(&mut self, slot: Slot) -> Result<usize, String> {
+        let depth = self.position(slot)?;
+        let num_slots = match depth { 0 => 1, 1 => 2, 2 | 3 => 4, _ => 8 };
+        if self.pad_to(num_slots) {
+            return self.position(slot);
+        }
+        return Ok(depth);
+    }
+
+    fn position(&self, slot: Slot) -> Result<usize, String> {
+        let depth = self.stack.iter().position(|&s| s == slot).expect("slot is not on the stack");
+        if depth > MAX_ACCESS_DEPTH {
+            return Err(String::from("expression exceeds available stack depth"));
+        }
+        return Ok(depth);
+    }
+
+    fn new_value(&mut self) -> usize {
+        self.next_value += 1;
+        return self.next_value;
+    }
+
+    /// Appends `op_codes` which consume `num_operands` values at the top of the stack and
+    /// produce a single value in their place.
+    fn apply(&mut self, op_codes: &[OpCode], num_operands: usize) -> usize {
+        self.program.extend_from_slice(op_codes);
+        self.stack.drain(..num_operands);
+        let value = self.new_value();
+        self.stack.insert(0, Slot::Value(value));
+        return value;
+    }
+
+    // STACK OPERATIONS
+    // --------------------------------------------------------------------------------------------
+
+    /// Pads the top of the stack with zeros so that it has at least `n` slots; this makes sure
+    /// operations touch only the values which belong to the expression. Returns true if any
+    /// slots were added.
+    fn pad_to(&mut self, n: usize) -> bool {
+        if self.stack.len() >= n { return false; }
+        let padding = n - self.stack.len();
+        for _ in 0..((padding + 1) / 2) {
+            self.program.push(OpCode::Pad2);
+            self.stack.insert(0, Slot::Junk);
+            self.stack.insert(0, Slot::Junk);
+        }
+        if padding % 2 == 1 {
+            self.drop();
+        }
+        return true;
+    }
+
+    fn swap(&mut self) {
+        self.program.push(OpCode::Swap);
+        self.stack.swap(0, 1);
+    }
+
+    fn swap2(&mut self) {
+        self.program.push(OpCode::Swap2);
+        self.stack[..4].rotate_left(2);
+    }
+
+    fn roll4(&mut self) {
+        self.program.push(OpCode::Roll4);
+        self.stack[..4].rotate_right(1);
+    }
+
+    /// Applies ROLL8 operation `n` times.
+    fn rotate(&mut self, n: usize) {
+        for _ in 0..n {
+            self.program.push(OpCode::Roll8);
+            self.stack[..8].rotate_right(1);
+        }
+    }
+
+    fn drop(&mut self) {
+        self.program.push(OpCode::Drop);
+        self.stack.remove(0);
+    }
+}
+
+// TESTS
+// ================================================================================================
+#[cfg(test)]
+mod tests {
+
+    use rand::{ Rng, SeedableRng, rngs::StdRng };
+    use crate::{ Program, ProgramInputs, ProofOptions, math::field, processor::reference, assembly };
+    use crate::blocks::{ Span, Group, ProgramBlock };
+    use super::{ compile_expr, OpCode };
+
+    type NativeFn = fn(&[u128]) -> u128;
+
+    const EXPRESSIONS: [(&str, NativeFn); 12] = [
+        ("in0",                             |x| x[0]),
+        ("in1 + in0",                       |x| field::add(x[1], x[0])),
+        ("in0 - in1",                       |x| field::sub(x[0], x[1])),
+        ("-in2",                            |x| field::neg(x[2])),
+        ("in0 / in1",                       |x| field::div(x[0], x[1])),
+        ("in0 * in0 * in0",                 |x| field::mul(field::mul(x[0], x[0]), x[0])),
+        ("in3 - in2 - in1 - in0",           |x| field::sub(field::sub(field::sub(x[3], x[2]), x[1]), x[0])),
+        ("(in0 + in1) * (in2 - in3*in4)",   |x| field::mul(field::add(x[0], x[1]), field::sub(x[2], field::mul(x[3], x[4])))),
+        ("in7 * (in1 - -in6)",              |x| field::mul(x[7], field::add(x[1], x[6]))),
+        ("in4 * in4 + in5 * in5 - in0 / in7", |x| field::sub(
+            field::add(field::mul(x[4], x[4]), field::mul(x[5], x[5])), field::div(x[0], x[7]))),
+        ("((in0 + in1) * (in2 + in3)) - ((in4 + in5) * (in6 + in7))", |x| field::sub(
+            field::mul(field::add(x[0], x[1]), field::add(x[2], x[3])),
+            field::mul(field::add(x[4], x[5]), field::add(x[6], x[7])))),
+        ("in2 * (in1 * (in0 + in2) - in1) / in0", |x| field::div(
+            field::mul(x[2], field::sub(field::mul(x[1], field::add(x[0], x[2])), x[1])), x[0])),
+    ];
+
+    #[test]
+    fn compile_expressions() {
+        let mut rng = StdRng::seed_from_u64(226);
+        for &(source, native) in EXPRESSIONS.iter() {
+            let op_codes = compile_expr(source).unwrap();
+            let num_inputs = max_input(source) + 1;
+            let program = build_program(&op_codes);
+
+            for _ in 0..10 {
+                // values below the inputs must not be affected by the expression
+                let mut inputs: Vec<u128> = (0..num_inputs).map(|_| rng.gen_range(1, field::M)).collect();
+                let expected = native(&inputs);
+                inputs.extend_from_slice(&[11, 12]);
+
+                let stack = reference::execute(&program, &ProgramInputs::from_public(&inputs)).unwrap();
+                assert_eq!(vec![expected, 11, 12], &stack[..3], "expression: {}", source);
+            }
+        }
+    }
+
+    #[test]
+    fn prove_expressions() {
+        let inputs = [3, 5, 7, 2, 4];
+        let expressions = [("(in0 + in1) * (in2 - in3*in4)", field::M - 8), ("in4 * (in3 - -in0) / in1", 4)];
+        for &(source, expected) in expressions.iter() {
+            let program = assembly::compile(&format!("begin expr{{ {} }} push.9 end", source)).unwrap();
+            let (outputs, proof) = crate::execute(&program, &ProgramInputs::from_public(&inputs), 2, &ProofOptions::default()).unwrap();
+            assert_eq!(vec![9, expected], outputs);
+            assert_eq!(Ok(()), crate::verify(program.hash(), &inputs, &outputs, &proof));
+        }
+    }
+
+    #[test]
+    fn invalid_expressions() {
+        assert_eq!(Err(String::from("expression is empty")), compile_expr("  "));
+        assert_eq!(Err(String::from("unexpected character '2'")), compile_expr("in0 + 2"));
+        assert_eq!(Err(String::from("missing operator before in1")), compile_expr("in0 in1"));
+        assert_eq!(Err(String::from("missing operand before '*'")), compile_expr("(* in1)"));
+        assert_eq!(Err(String::from("missing operand at the end")), compile_expr("in0 -"));
+        assert_eq!(Err(String::from("unmatched '('")), compile_expr("(in0 + in1"));
+        assert_eq!(Err(String::from("unmatched ')'")), compile_expr("in0 + in1)"));
+        assert_eq!(Err(String::from("input in is invalid")), compile_expr("in + in1"));
+        assert_eq!(Err(String::from("cannot compile expression 'in8 + in0': expression exceeds available stack depth")),
+            compile_expr("in8 + in0"));
+    }
+
+    // HELPER FUNCTIONS
+    // --------------------------------------------------------------------------------------------
+    fn build_program(op_codes: &[OpCode]) -> Program {
+        let mut instructions = vec![OpCode::Begin];
+        instructions.extend_from_slice(op_codes);
+        let length = (instructions.len() / 16 + 1) * 16 - 1;
+        instructions.resize(length, OpCode::Noop);
+        let root = vec![ProgramBlock::Span(Span::from_instructions(instructions))];
+        return Program::new(Group::new(root));
+    }
+
+    fn max_input(source: &str) -> usize {
+        return source.split("in").skip(1)
+            .map(|s| s.chars().take_while(|c| c.is_ascii_digit()).collect::<String>().parse::<usize>().unwrap())
+            .max().unwrap();
+    }
+}
@@ -1,25 +1,93 @@
-use std::collections::HashMap;
-use super::{ Program, ProgramBlock, Span, Group, Switch, Loop, OpCode, OpHint, BASE_CYCLE_LENGTH };
+use std::{ collections::HashMap, ops::Range };
+use super::{ Program, ProgramBlock, Span, Group, Switch, Loop, LoopBounds, OpCode, OpHint, BASE_CYCLE_LENGTH };
 
 mod parsers;
 use parsers::*;
 
 mod errors;
-use errors::{ AssemblyError };
+pub use errors::{ AssemblyError };
+
+pub mod expr;
+
+mod cache;
+pub use cache::{ CachingCompiler, CacheStats, ProgramStore, MemoryStore, DirectoryStore, ASSEMBLER_VERSION };
+
+mod decompiler;
+pub use decompiler::{ decompile };
 
 #[cfg(test)]
 mod tests;
 
 type HintMap = HashMap<usize, OpHint>;
 
+// CONSTANTS
+// ================================================================================================
+
+/// Max number of cases in a `switch` block; this keeps the depth of the branch tree into which
+/// the block is compiled within 8 levels.
+pub const MAX_SWITCH_CASES: usize = 256;
+
+/// Max number of steps which all iterations of a loop annotated with `while.true.max=N` may
+/// take; programs with such loops which could exceed this budget are rejected.
+pub const MAX_LOOP_STEPS: usize = 1 << 20;
+
 // ASSEMBLY COMPILER
 // ================================================================================================
 
-/// Compiles provided assembly code into a program.
+/// Compiles provided assembly code into a program. If the code is invalid, the returned error
+/// specifies the line and the column of the source at which the problem was encountered.
+///
+/// Procedures defined via `proc.<name> ... end` before the program `begin` instruction are
+/// inlined in place of every `exec.<name>` instruction before the program is compiled; in the
+/// same way, values of constants defined via `const.<NAME>=<value>` replace their names in
+/// instruction parameters.
 pub fn compile(source: &str) -> Result<Program, AssemblyError> {
 
-    // break assembly string into tokens
-    let tokens: Vec<&str> = source.split_whitespace().collect();
+    // break assembly string into tokens, and locate the position of each token in the source
+    let (tokens, positions) = tokenize(source);
+
+    // inline procedures and constants, keeping track of the source token from which every
+    // token came
+    let (expanded, origins) = match expand_definitions(&tokens) {
+        Ok(result) => result,
+        Err(err) => {
+            let step = err.step();
+            return Err(locate_error(err, Some(step), &tokens, &positions));
+        },
+    };
+
+    let expanded: Vec<&str> = expanded.iter().map(|token| token.as_str()).collect();
+    return match parse_program(&expanded) {
+        Ok(program) => Ok(program),
+        Err(err) => {
+            let origin = origins.get(err.step()).cloned();
+            Err(locate_error(err, origin, &tokens, &positions))
+        },
+    };
+}
+
+/// Compiles provided assembly code into a program in the same way as `compile()`, and then makes
+/// sure that executing the program with `num_inputs` values on the initial stack would not
+/// exceed stack, context, or loop depth limits of the VM along any execution path; see
+/// `ProgramStats::check_limits()`. The number of iterations of every loop is assumed to be
+/// within `loop_bounds`. Errors caused by exceeded limits point to the `begin` instruction.
+pub fn compile_checked(source: &str, num_inputs: usize, loop_bounds: Option<&LoopBounds>)
+    -> Result<Program, AssemblyError>
+{
+    let program = compile(source)?;
+    return match program.stats(loop_bounds).check_limits(num_inputs) {
+        Ok(()) => Ok(program),
+        Err(reason) => {
+            let (tokens, positions) = tokenize(source);
+            let begin = tokens.iter().position(|&token| token == "begin");
+            Err(locate_error(AssemblyError::limits_exceeded(reason), begin, &tokens, &positions))
+        },
+    };
+}
+
+/// Builds a program from the `tokens` stream; errors returned from this function specify the
+/// index of the offending token as their step.
+fn parse_program(tokens: &[&str]) -> Result<Program, AssemblyError> {
 
     // perform basic validation
     if tokens.len() == 0 {
@@ -29,17 +97,17 @@ pub fn compile(source: &str) -> Result<Program, AssemblyError> {
         return Err(AssemblyError::invalid_program_start(tokens[0]));
     }
     else if tokens[tokens.len() - 1] != "end" {
-        return Err(AssemblyError::invalid_program_end(tokens[tokens.len() - 1]));
+        return Err(AssemblyError::invalid_program_end(tokens[tokens.len() - 1], tokens.len() - 1));
     }
 
     // read the program from the token stream
     let mut root_blocks = Vec::new();
-    let i = parse_branch(&mut root_blocks, &tokens, 0)?;
+    let i = parse_branch(&mut root_blocks, tokens, 0)?;
     let root = Group::new(root_blocks);
 
     // make sure there is nothing left after the last token
     if i < tokens.len() - 1 {
-        return Err(AssemblyError::dangling_instructions(i));
+        return Err(AssemblyError::dangling_instructions(i + 1));
     }
 
     // build and return the program
@@ -49,6 +117,226 @@ pub fn compile(source: &str) -> Result<Program, AssemblyError> {
 // PARSER FUNCTIONS
 // ================================================================================================
 
+/// Removes constant and procedure definitions from the start of the `tokens` stream, and
+/// expands the rest of the stream by inlining bodies of procedures in place of `exec`
+/// instructions, unrolling `repeat` blocks, and substituting values of constants for their
+/// names. Returns the expanded stream together with indexes of the source tokens from which
+/// the tokens came.
+///
+/// Constants and procedures can be used only after they are defined, and so a procedure cannot
+/// execute itself.
+fn expand_definitions<'a>(tokens: &[&'a str]) -> Result<(Vec<String>, Vec<usize>), AssemblyError> {
+    let mut definitions = Definitions { constants: HashMap::new(), procedures: HashMap::new() };
+
+    let mut i = 0;
+    while i < tokens.len() {
+        let head: Vec<&str> = tokens[i].split(".").collect();
+        match head[0] {
+            "const" => {
+                let (name, value) = read_const(&head, i)?;
+                if definitions.constants.contains_key(name) {
+                    return Err(AssemblyError::duplicate_const(&head, name, i));
+                }
+                definitions.constants.insert(name, value);
+                i += 1;
+            },
+            "proc" => {
+                let name = read_proc_name(&head, i)?;
+                if definitions.procedures.contains_key(name) {
+                    return Err(AssemblyError::duplicate_proc(&head, i));
+                }
+
+                let end = match find_block_end(tokens, i, tokens.len())? {
+                    Some(end) => end,
+                    None => return Err(AssemblyError::unmatched_proc(i, &head)),
+                };
+                if end - i < 2 {
+                    return Err(AssemblyError::empty_block(&head, i));
+                }
+
+                let mut body = Vec::new();
+                expand_tokens(tokens, (i + 1)..end, &definitions, &mut body)?;
+                definitions.procedures.insert(name, body);
+                i = end + 1;
+            },
+            _ => break,
+        }
+    }
+
+    let mut expanded = Vec::with_capacity(tokens.len());
+    expand_tokens(tokens, i..tokens.len(), &definitions, &mut expanded)?;
+    return Ok(expanded.into_iter().unzip());
+}
+
+/// Constants and procedures defined at the start of a program.
+struct Definitions<'a> {
+    constants   : HashMap<&'a str, &'a str>,
+    procedures  : HashMap<&'a str, Vec<(String, usize)>>,
+}
+
+/// Appends tokens in the specified `range` to the `result` replacing `exec` instructions with
+/// bodies of the corresponding procedures, `repeat` blocks with copies of their bodies, `switch`
+/// blocks with trees of `if` blocks, and names of constants with their values.
+fn expand_tokens(tokens: &[&str], range: Range<usize>, definitions: &Definitions,
+    result: &mut Vec<(String, usize)>) -> Result<(), AssemblyError>
+{
+    let mut i = range.start;
+    while i < range.end {
+        let op: Vec<&str> = tokens[i].split(".").collect();
+        match op[0] {
+            "exec" => {
+                let name = read_proc_name(&op, i)?;
+                match definitions.procedures.get(name) {
+                    Some(body) => result.extend_from_slice(body),
+                    None => return Err(AssemblyError::undefined_proc(&op, i)),
+                }
+            },
+            "const" | "proc" => return Err(AssemblyError::misplaced_definition(&op, i)),
+            "case" => return Err(AssemblyError::dangling_case(&op, i)),
+            "switch" => {
+                if op.len() > 1 {
+                    return Err(AssemblyError::invalid_block_head(&op, i));
+                }
+                let end = match find_block_end(tokens, i, range.end)? {
+                    Some(end) => end,
+                    None => return Err(AssemblyError::unmatched_switch(i)),
+                };
+
+                // expand the body of each case; cases must be numbered sequentially from 0
+                if end - i < 2 {
+                    return Err(AssemblyError::empty_block(&op, i));
+                }
+                let starts = find_cases(tokens, i, end);
+                if starts.first() != Some(&(i + 1)) {
+                    let head: Vec<&str> = tokens[i + 1].split(".").collect();
+                    return Err(AssemblyError::invalid_case(&head, 0, i + 1));
+                }
+                let mut cases = Vec::with_capacity(starts.len());
+                for (j, &start) in starts.iter().enumerate() {
+                    let head: Vec<&str> = tokens[start].split(".").collect();
+                    if head.len() != 2 || head[1] != j.to_string() || j == MAX_SWITCH_CASES {
+                        return Err(AssemblyError::invalid_case(&head, j, start));
+                    }
+                    let case_end = if j + 1 < starts.len() { starts[j + 1] } else { end };
+                    if case_end - start < 2 {
+                        return Err(AssemblyError::empty_block(&head, start));
+                    }
+                    let mut body = Vec::new();
+                    expand_tokens(tokens, (start + 1)..case_end, definitions, &mut body)?;
+                    cases.push(body);
+                }
+
+                // decompose the selector into bits, and select the case using one if block
+                // per bit; if the selector is out of range, execution fails
+                let num_bits = std::cmp::max(1, cases.len().next_power_of_two().trailing_zeros() as usize);
+                result.push((format!("bits.{}", num_bits), i));
+                expand_switch_tree(&cases, 0, 0, num_bits, i, result);
+                i = end;
+            },
+            "repeat" => {
+                // read and validate number of iterations
+                let head = substitute_constant(&op, definitions, i)?;
+                let head: Vec<&str> = head.split(".").collect();
+                if head.len() == 1 {
+                    return Err(AssemblyError::missing_param(&head, i));
+                }
+                let num_iterations = read_param(&head, i)?;
+                if num_iterations == 0 {
+                    return Err(AssemblyError::invalid_num_iterations(&head, i));
+                }
+
+                // expand the body of the block, and append as many copies of it as needed
+                let end = match find_block_end(tokens, i, range.end)? {
+                    Some(end) => end,
+                    None => return Err(AssemblyError::unmatched_repeat(i, &head)),
+                };
+                if end - i < 2 {
+                    return Err(AssemblyError::empty_block(&head, i));
+                }
+
+                let mut body = Vec::new();
+                expand_tokens(tokens, (i + 1)..end, definitions, &mut body)?;
+                for _ in 0..num_iterations {
+                    result.extend_from_slice(&body);
+                }
+                i = end;
+            },
+            _ => result.push((substitute_constant(&op, definitions, i)?, i)),
+        }
+        i += 1;
+    }
+    return Ok(());
+}
+
+/// Appends a tree of `if` blocks which executes one of the `cases` based on the selector bits at
+/// the top of the stack, starting with the least significant one; `index` holds the bits which
+/// have been consumed so far. Leaves which do not correspond to any case fail the execution.
+fn expand_switch_tree(cases: &[Vec<(String, usize)>], index: usize, level: usize, num_bits: usize,
+    step: usize, result: &mut Vec<(String, usize)>)
+{
+    if level == num_bits {
+        match cases.get(index) {
+            Some(body) => result.extend_from_slice(body),
+            None => {
+                result.push((String::from("pad.1"), step));
+                result.push((String::from("assert"), step));
+            },
+        }
+        return;
+    }
+
+    result.push((String::from("if.true"), step));
+    expand_switch_tree(cases, index | (1 << level), level + 1, num_bits, step, result);
+    result.push((String::from("else"), step));
+    expand_switch_tree(cases, index, level + 1, num_bits, step, result);
+    result.push((String::from("end"), step));
+}
+
+/// Returns indexes of `case` tokens which belong to the switch block starting at `first_step`
+/// and ending at `end`; cases of nested switch blocks are skipped.
+fn find_cases(tokens: &[&str], first_step: usize, end: usize) -> Vec<usize> {
+    let mut depth = 0;
+    let mut result = Vec::new();
+    for i in (first_step + 1)..end {
+        match tokens[i].split(".").next().unwrap() {
+            "block" | "if" | "repeat" | "while" | "switch" => depth += 1,
+            "end" => depth -= 1,
+            "case" if depth == 0 => result.push(i),
+            _ => (),
+        }
+    }
+    return result;
+}
+
+/// Returns the instruction `op` with the name of a constant in its parameter replaced with the
+/// value of the constant.
+fn substitute_constant(op: &[&str], definitions: &Definitions, step: usize) -> Result<String, AssemblyError> {
+    if op.len() == 2 && is_const_name(op[1]) {
+        return match definitions.constants.get(op[1]) {
+            Some(value) => Ok(format!("{}.{}", op[0], value)),
+            None => Err(AssemblyError::undefined_const(op, step)),
+        };
+    }
+    return Ok(op.join("."));
+}
+
+/// Returns the index of the `end` token which closes the block starting at `first_step`,
+/// skipping over ends of nested blocks; only tokens before `limit` are examined. Returns None
+/// if the block is not closed.
+fn find_block_end(tokens: &[&str], first_step: usize, limit: usize) -> Result<Option<usize>, AssemblyError> {
+    let mut depth = 0;
+    for i in (first_step + 1)..limit {
+        match tokens[i].split(".").next().unwrap() {
+            "block" | "if" | "repeat" | "while" | "switch" => depth += 1,
+            "else" if depth == 0 => return Err(AssemblyError::dangling_else(i)),
+            "end" if depth == 0 => return Ok(Some(i)),
+            "end" => depth -= 1,
+            _ => (),
+        }
+    }
+    return Ok(None);
+}
+
 /// Parses a single program block from the `token` stream, and appends this block to the `parent`
 /// list of blocks.
 fn parse_block(parent: &mut Vec<ProgramBlock>, tokens: &[&str], mut i: usize) -> Result<usize, AssemblyError> {
@@ -98,33 +386,32 @@ fn parse_block(parent: &mut Vec<ProgramBlock>, tokens: &[&str], mut i: usize) ->
             parent.push(Switch::new_block(t_branch, f_branch));
             return Ok(i + 1);
         },
-        "repeat" => {
-            // read and validate number of loop iterations
-            let num_iterations = read_param(&head, i)? as usize;
-            if num_iterations < 2 {
-                return Err(AssemblyError::invalid_num_iterations(&head, i));
-            }
-
-            // parse loop body
-            let mut body_template = Vec::new();
-            i = parse_branch(&mut body_template, tokens, i)?;
-
-            // duplicate loop body as many times as needed
-            let body = repeat_block_sequence(body_template, num_iterations);
-
-            // create a Group block with all iterations expanded, and return
-            parent.push(Group::new_block(body));
-            return Ok(i + 1);
-        },
         "while" => {
             // make sure block head is valid
-            if head.len() == 1 || head[1] != "true" {
+            if head.len() == 1 || head.len() > 3 || head[1] != "true" {
                 return Err(AssemblyError::invalid_block_head(&head, i));
             }
-            // then parse the body of the block, add the new block to the parent, and return
+            let max_iterations = if head.len() == 3 { Some(read_max_iterations(&head, i)?) } else { None };
+
+            // then parse the body of the block
+            let first_step = i;
             let mut body = Vec::new();
             i = parse_branch(&mut body, tokens, i)?;
-            parent.push(Loop::new_block(body));
+
+            // if the number of iterations is bounded, make sure all iterations of the loop fit
+            // into the budget; every iteration takes an extra step to check the loop condition
+            if let Some(n) = max_iterations {
+                let max_steps = n.saturating_mul(count_steps(&body) + 1);
+                if max_steps > MAX_LOOP_STEPS {
+                    return Err(AssemblyError::loop_budget_exceeded(&head, first_step, max_steps, MAX_LOOP_STEPS));
+                }
+            }
+
+            // add the new block to the parent, and return
+            match max_iterations {
+                Some(n) => parent.push(Loop::new_bounded_block(body, n)),
+                None    => parent.push(Loop::new_block(body)),
+            }
             return Ok(i + 1);
         },
         _ => return Err(AssemblyError::invalid_block_head(&head, i)),
@@ -146,7 +433,6 @@ fn parse_branch(body: &mut Vec<ProgramBlock>, tokens: &[&str], mut i: usize) ->
         "block"  => vec![],
         "if"     => vec![OpCode::Assert],
         "else"   => vec![OpCode::Not, OpCode::Assert],
-        "repeat" => vec![],
         "while"  => vec![OpCode::Assert],
         _ => return Err(AssemblyError::invalid_block_head(&head, i)),
     };
@@ -161,9 +447,9 @@ fn parse_branch(body: &mut Vec<ProgramBlock>, tokens: &[&str], mut i: usize) ->
     while i < tokens.len() {
         let op: Vec<&str> = tokens[i].split(".").collect();
         i = match op[0] {
-            "block" | "if" | "repeat" | "while" => {
+            "block" | "if" | "while" => {
                 let force_span = body.len() == 0;
-                add_span(body, &mut op_codes, &mut op_hints, force_span);
+                add_span(body, &mut op_codes, &mut op_hints, force_span, i)?;
                 parse_block(body, tokens, i)?
             },
             "else" => {
@@ -173,16 +459,17 @@ fn parse_branch(body: &mut Vec<ProgramBlock>, tokens: &[&str], mut i: usize) ->
                 else if i - first_step < 2 {
                     return Err(AssemblyError::empty_block(&head, first_step));
                 }
-                add_span(body, &mut op_codes, &mut op_hints, false);
+                add_span(body, &mut op_codes, &mut op_hints, false, i)?;
                 return Ok(i);
             },
             "end" => {
                 if i - first_step < 2 {
                     return Err(AssemblyError::empty_block(&head, first_step));
                 }
-                add_span(body, &mut op_codes, &mut op_hints, false);
+                add_span(body, &mut op_codes, &mut op_hints, false, i)?;
                 return Ok(i);
             },
+            _ if tokens[i].starts_with("expr{") => parse_expr(tokens, &mut op_codes, i)?,
             _ => parse_op_token(op, &mut op_codes, &mut op_hints, i)?
         };
     }
@@ -192,7 +479,6 @@ fn parse_branch(body: &mut Vec<ProgramBlock>, tokens: &[&str], mut i: usize) ->
         "block"  => Err(AssemblyError::unmatched_block(first_step)),
         "if"     => Err(AssemblyError::unmatched_if(first_step)),
         "else"   => Err(AssemblyError::unmatched_else(first_step)),
-        "repeat" => Err(AssemblyError::unmatched_repeat(first_step, &head)),
         "while"  => Err(AssemblyError::unmatched_while(first_step)),
         _ => Err(AssemblyError::invalid_block_head(&head, first_step)),
     };
@@ -204,40 +490,53 @@ fn parse_op_token(op: Vec<&str>, op_codes: &mut Vec<OpCode>, op_hints: &mut Hint
     // based on the instruction, invoke the correct parser for the operation
     match op[0] {
         "noop"   => parse_noop(op_codes, &op, step),
+        "align"  => parse_align(op_codes, &op, step),
         "assert" => parse_assert(op_codes, &op, step),
 
         "push"   => parse_push(op_codes, op_hints, &op, step),
         "read"   => parse_read(op_codes, &op, step),
+        "unpack" => parse_unpack(op_codes, op_hints, &op, step),
+        "bits"   => parse_bits(op_codes, op_hints, &op, step),
 
         "dup"    => parse_dup(op_codes, &op, step),
         "pad"    => parse_pad(op_codes, &op, step),
         "pick"   => parse_pick(op_codes, &op, step),
+        "select" => parse_select(op_codes, op_hints, &op, step),
         "drop"   => parse_drop(op_codes, &op, step),
         "swap"   => parse_swap(op_codes, &op, step),
+        "swapw"  => parse_swapw(op_codes, &op, step),
         "roll"   => parse_roll(op_codes, &op, step),
+        "movup"  => parse_movup(op_codes, &op, step),
+        "movdn"  => parse_movdn(op_codes, &op, step),
 
-        "add"    => parse_add(op_codes, &op, step),
-        "sub"    => parse_sub(op_codes, &op, step),
-        "mul"    => parse_mul(op_codes, &op, step),
+        "add"    => parse_add(op_codes, op_hints, &op, step),
+        "sub"    => parse_sub(op_codes, op_hints, &op, step),
+        "mul"    => parse_mul(op_codes, op_hints, &op, step),
         "div"    => parse_div(op_codes, &op, step),
         "neg"    => parse_neg(op_codes, &op, step),
         "inv"    => parse_inv(op_codes, &op, step),
         "not"    => parse_not(op_codes, &op, step),
         "and"    => parse_and(op_codes, &op, step),
         "or"     => parse_or(op_codes, &op, step),
+        "exp"    => parse_exp(op_codes, op_hints, &op, step),
+        "u32"    => parse_u32(op_codes, op_hints, &op, step),
 
         "eq"     => parse_eq(op_codes, op_hints, &op, step),
         "ne"     => parse_ne(op_codes, op_hints, &op, step),
+        "neq"    => parse_ne(op_codes, op_hints, &op, step),
+        "iszero" => parse_iszero(op_codes, op_hints, &op, step),
         "gt"     => parse_gt(op_codes, op_hints, &op, step),
         "lt"     => parse_lt(op_codes, op_hints, &op, step),
         "rc"     => parse_rc(op_codes, op_hints, &op, step),
         "isodd"  => parse_isodd(op_codes, op_hints, &op, step),
 
         "choose" => parse_choose(op_codes, &op, step),
+        "cswap"  => parse_cswap(op_codes, &op, step),
 
         "hash"   => parse_hash(op_codes, &op, step),
         "smpath" => parse_smpath(op_codes, &op, step),
         "pmpath" => parse_pmpath(op_codes, op_hints, &op, step),
+        "mtree"  => parse_mtree(op_codes, &op, step),
 
         _ => return Err(AssemblyError::invalid_op(&op, step))
     }?;
@@ -246,61 +545,168 @@ fn parse_op_token(op: Vec<&str>, op_codes: &mut Vec<OpCode>, op_hints: &mut Hint
     return Ok(step + 1);
 }
 
+/// Compiles an expression directive `expr{ ... }` which may span several tokens into a sequence
+/// of VM instructions, and returns the index of the token following the directive.
+fn parse_expr(tokens: &[&str], op_codes: &mut Vec<OpCode>, step: usize) -> Result<usize, AssemblyError> {
+    let end = match tokens[step..].iter().position(|token| token.ends_with("}")) {
+        Some(offset) => step + offset,
+        None => return Err(AssemblyError::unmatched_expr(step)),
+    };
+
+    let source = tokens[step..=end].join(" ");
+    let source = &source["expr{".len()..(source.len() - 1)];
+    match expr::compile_expr(source) {
+        Ok(expr_op_codes) => op_codes.extend_from_slice(&expr_op_codes),
+        Err(reason) => return Err(AssemblyError::invalid_expr(reason, step)),
+    };
+
+    return Ok(end + 1);
+}
+
 // HELPER FUNCTIONS
 // ================================================================================================
 
-/// Adds a new Span block to a program block body based on currently parsed instructions.
-fn add_span(body: &mut Vec<ProgramBlock>, op_codes: &mut Vec<OpCode>, op_hints: &mut HintMap, force: bool) {
+/// Sets the position of the `err` to the position of the source token with the specified
+/// index; if there is no such token, the error is placed at the start of the source.
+fn locate_error(err: AssemblyError, token_idx: Option<usize>, tokens: &[&str], positions: &[(usize, usize)]) -> AssemblyError {
+    return match token_idx {
+        Some(i) if i < positions.len() => {
+            let (line, column) = positions[i];
+            err.at_position(line, column, tokens[i])
+        },
+        _ => err.at_position(1, 1, ""),
+    };
+}
 
-    // if there were no instructions in the current span, don't do anything
-    if op_codes.len() == 0 && !force { return };
+/// Breaks assembly `source` into whitespace-separated tokens, and returns the tokens together
+/// with 1-based (line, column) positions at which the tokens start. Line comments are removed
+/// from the source before it is broken into tokens.
+pub(super) fn tokenize(source: &str) -> (Vec<&str>, Vec<(usize, usize)>) {
+    let mut tokens = Vec::new();
+    let mut positions = Vec::new();
+
+    for (line_idx, line) in source.lines().enumerate() {
+        let line = strip_comment(line);
+        let mut token_start = None;
+        let chars = line.char_indices().chain(std::iter::once((line.len(), ' ')));
+        for (column, (i, c)) in chars.enumerate() {
+            match (c.is_whitespace(), token_start) {
+                (true, Some((start, start_column))) => {
+                    tokens.push(&line[start..i]);
+                    positions.push((line_idx + 1, start_column + 1));
+                    token_start = None;
+                },
+                (false, None) => token_start = Some((i, column)),
+                _ => (),
+            }
+        }
+    }
 
-    // pad the instructions to make ensure 16-cycle alignment
-    let mut span_op_codes = op_codes.clone();
-    let pad_length = BASE_CYCLE_LENGTH - (span_op_codes.len() % BASE_CYCLE_LENGTH) - 1;
-    span_op_codes.resize(span_op_codes.len() + pad_length, OpCode::Noop);
+    return (tokens, positions);
+}
 
-    // add a new Span block to the body
-    body.push(ProgramBlock::Span(Span::new(span_op_codes, op_hints.clone())));
+/// Removes a comment starting with `#` or `//` from the `line`.
+fn strip_comment(line: &str) -> &str {
+    let mut end = line.len();
+    for marker in ["#", "//"].iter() {
+        if let Some(i) = line.find(marker) {
+            end = std::cmp::min(end, i);
+        }
+    }
+    return &line[..end];
+}
+
+/// Adds a new Span block to a program block body based on currently parsed instructions; `step`
+/// is the index of the token which ends the span.
+fn add_span(body: &mut Vec<ProgramBlock>, op_codes: &mut Vec<OpCode>, op_hints: &mut HintMap, force: bool, step: usize)
+    -> Result<(), AssemblyError>
+{
+    // if there were no instructions in the current span, don't do anything
+    if op_codes.len() == 0 && !force { return Ok(()) };
+
+    // add a new Span block padded to ensure 16-cycle alignment to the body; this also makes sure
+    // that parsers emitted valid hints
+    match Span::new_padded(op_codes.clone(), op_hints.clone()) {
+        Ok(span) => body.push(ProgramBlock::Span(span)),
+        Err(err) => return Err(AssemblyError::invalid_span(err, step)),
+    }
 
     // clear op_codes and op_hints for the next Span block
     op_codes.clear();
     op_hints.clear();
+    return Ok(());
 }
 
-fn repeat_block_sequence(template: Vec<ProgramBlock>, num_iterations: usize) -> Vec<ProgramBlock> {
-    let mut body = Vec::with_capacity(template.len() * num_iterations);
+/// Returns the number of steps it takes to execute a sequence of program blocks once; for
+/// switch blocks the longer of the branches is counted, and nested loops are counted as
+/// executing their body once.
+fn count_steps(blocks: &[ProgramBlock]) -> usize {
+    return blocks.iter().map(|block| match block {
+        ProgramBlock::Span(span)     => span.length(),
+        ProgramBlock::Group(group)   => count_steps(group.body()) + 2,
+        ProgramBlock::Switch(switch) => std::cmp::max(
+            count_steps(switch.true_branch()), count_steps(switch.false_branch())) + 2,
+        ProgramBlock::Loop(block)    => count_steps(block.body()) + 2,
+    }).sum();
+}
 
-    let last_idx = template.len() - 1;
-    if !template[last_idx].is_span() {
-        for _ in 0..num_iterations {
-            body.extend_from_slice(&template);
-        }
+/// Reads the number of iterations from `max=N` annotation of a `while.true` block head.
+fn read_max_iterations(head: &[&str], step: usize) -> Result<usize, AssemblyError> {
+    return match head[2].strip_prefix("max=").map(|n| n.parse::<usize>()) {
+        Some(Ok(n)) if n > 0 => Ok(n),
+        _ => Err(AssemblyError::invalid_block_head(head, step)),
+    };
+}
+
+/// Makes sure a `proc` or `exec` instruction specifies a valid procedure name; a name must
+/// start with a letter or an underscore, and can contain letters, digits, and underscores.
+fn read_proc_name<'a>(op: &[&'a str], step: usize) -> Result<&'a str, AssemblyError> {
+    if op.len() == 1 {
+        return Err(AssemblyError::missing_param(op, step));
     }
-    else {
-        body.extend_from_slice(&template);
-        for _ in 1..num_iterations {
-            let last_idx = body.len() - 1;
-            body[last_idx] = merge_spans(&body[last_idx], &template[0]);
-            body.extend_from_slice(&template[1..]);
-        }
+    else if op.len() > 2 {
+        return Err(AssemblyError::extra_param(op, step));
     }
 
-    return body;
+    let name = op[1];
+    let is_valid = name.chars().enumerate()
+        .all(|(i, c)| c == '_' || c.is_ascii_alphabetic() || (i > 0 && c.is_ascii_digit()));
+    if name.is_empty() || !is_valid {
+        return Err(AssemblyError::invalid_param(op, step));
+    }
+
+    return Ok(name);
 }
 
-fn merge_spans(span1: &ProgramBlock, span2: &ProgramBlock) -> ProgramBlock {
-    return match span1 {
-        ProgramBlock::Span(first_span) => {
-            match span2 {
-                ProgramBlock::Span(last_span) => {
-                    ProgramBlock::Span(Span::merge(first_span, last_span))
-                },
-                _ => panic!("span1 is not a Span block")
-            }
-        },
-        _ => panic!("span2 is not a Span block")
+/// Reads a constant definition of the form `const.NAME=value`, and returns the name and the
+/// value of the constant; the value can be a decimal or a hexadecimal number.
+fn read_const<'a>(op: &[&'a str], step: usize) -> Result<(&'a str, &'a str), AssemblyError> {
+    if op.len() == 1 {
+        return Err(AssemblyError::missing_param(op, step));
+    }
+    else if op.len() > 2 {
+        return Err(AssemblyError::extra_param(op, step));
+    }
+
+    let (name, value) = match op[1].find('=') {
+        Some(i) => (&op[1][..i], &op[1][(i + 1)..]),
+        None => return Err(AssemblyError::invalid_param_reason(op, step,
+            format!("constant definition {} is invalid; expected NAME=value", op[1]))),
     };
+    if !is_const_name(name) {
+        return Err(AssemblyError::invalid_param_reason(op, step,
+            format!("constant name {} is invalid; names must consist of uppercase letters, digits, and underscores", name)));
+    }
+    read_value(&[op[0], value], step)?;
+
+    return Ok((name, value));
+}
+
+/// Returns true if `name` can be a name of a constant; a name must start with an uppercase
+/// letter, and can contain uppercase letters, digits, and underscores.
+fn is_const_name(name: &str) -> bool {
+    return name.starts_with(|c: char| c.is_ascii_uppercase())
+        && name.chars().all(|c| c == '_' || c.is_ascii_uppercase() || c.is_ascii_digit());
 }
 
 fn read_param(op: &[&str], step: usize) -> Result<u32, AssemblyError> {
@@ -308,8 +714,14 @@ fn read_param(op: &[&str], step: usize) -> Result<u32, AssemblyError> {
         return Err(AssemblyError::extra_param(op, step));
     }
 
-    // try to parse the parameter value
-    let result = match op[1].parse::<u32>() {
+    // try to parse the parameter value as a decimal or a hexadecimal number
+    let result = if op[1].starts_with("0x") {
+        u32::from_str_radix(&op[1][2..], 16)
+    }
+    else {
+        op[1].parse::<u32>()
+    };
+    let result = match result {
         Ok(i) => i,
         Err(_) => return Err(AssemblyError::invalid_param(op, step))
     };
@@ -1,4 +1,4 @@
-use crate::{ math::field };
+use crate::{ math::field, MAX_STACK_DEPTH };
 use super::{ AssemblyError, HintMap, OpCode, OpHint };
 
 // CONSTANTS
@@ -18,7 +18,34 @@ pub fn parse_noop(program: &mut Vec<OpCode>, op: &[&str], step: usize) -> Result
     return Ok(true);
 }
 
-/// Appends either ASSERT or ASSERTEQ operations to the program.
+/// Appends as many NOOP operations to the program as needed to make sure that the next operation
+/// is executed on a step which is equal to k modulo 16. Every span starts on a step which is a
+/// multiple of 16, and thus, the position of an operation within a span determines its alignment.
+pub fn parse_align(program: &mut Vec<OpCode>, op: &[&str], step: usize) -> Result<bool, AssemblyError> {
+    if op.len() == 1 {
+        return Err(AssemblyError::missing_param(op, step));
+    }
+    else if op.len() > 2 {
+        return Err(AssemblyError::extra_param(op, step));
+    }
+
+    let k = match op[1].parse::<usize>() {
+        Ok(k) => k,
+        Err(_) => return Err(AssemblyError::invalid_param(op, step))
+    };
+    if k >= HASH_OP_ALIGNMENT {
+        return Err(AssemblyError::invalid_param_reason(op, step,
+            format!("parameter {} is invalid; value must be between 0 and 15", k)));
+    }
+
+    let alignment = program.len() % HASH_OP_ALIGNMENT;
+    let pad_length = (HASH_OP_ALIGNMENT + k - alignment) % HASH_OP_ALIGNMENT;
+    program.resize(program.len() + pad_length, OpCode::Noop);
+    return Ok(true);
+}
+
+/// Appends either ASSERT or ASSERTEQ operations to the program; assert.z is compiled into an
+/// equality assertion against 0.
 pub fn parse_assert(program: &mut Vec<OpCode>, op: &[&str], step: usize) -> Result<bool, AssemblyError> {
     if op.len() > 2 {
         return Err(AssemblyError::extra_param(op, step));
@@ -29,9 +56,12 @@ pub fn parse_assert(program: &mut Vec<OpCode>, op: &[&str], step: usize) -> Resu
     else if op[1] == "eq" {
         program.push(OpCode::AssertEq);
     }
+    else if op[1] == "z" {
+        program.extend_from_slice(&[OpCode::Pad2, OpCode::Drop, OpCode::AssertEq]);
+    }
     else {
         return Err(AssemblyError::invalid_param_reason(op, step,
-            format!("parameter {} is invalid; allowed values are: [eq]", op[1])));
+            format!("parameter {} is invalid; allowed values are: [eq, z]", op[1])));
     }
     
     return Ok(true);
@@ -61,7 +91,7 @@ fn append_push_op(program: &mut Vec<OpCode>, hints: &mut HintMap, value: u128) {
     program.push(OpCode::Push);
 }
 
-/// Appends either READ or READ2 operation to the program.
+/// Appends READ, READ2, or READ4 operation to the program.
 pub fn parse_read(program: &mut Vec<OpCode>, op: &[&str], step: usize) -> Result<bool, AssemblyError> {
     if op.len() > 2 {
         return Err(AssemblyError::extra_param(op, step));
@@ -72,11 +102,65 @@ pub fn parse_read(program: &mut Vec<OpCode>, op: &[&str], step: usize) -> Result
     else if op[1] == "ab" {
         program.push(OpCode::Read2);
     }
+    else if op[1] == "4" {
+        program.push(OpCode::Read4);
+    }
     else {
         return Err(AssemblyError::invalid_param_reason(op, step,
-            format!("parameter {} is invalid; allowed values are: [a, ab]", op[1])));
+            format!("parameter {} is invalid; allowed values are: [a, ab, 4]", op[1])));
+    }
+
+    return Ok(true);
+}
+
+/// Appends a sequence of operations to the program to unpack the top value of the stack into
+/// n bytes (see `pack_bytes()`). Each byte is range-checked to be less than 256, and the bytes
+/// are checked to add up to the original value. The least significant byte ends up at the top
+/// of the stack.
+pub fn parse_unpack(program: &mut Vec<OpCode>, hints: &mut HintMap, op: &[&str], step: usize) -> Result<bool, AssemblyError> {
+    let n = read_param(op, step)?;
+    if n < 1 || n > 15 {
+        return Err(AssemblyError::invalid_param_reason(op, step,
+            format!("parameter {} is invalid; value must be between 1 and 15", n)))
+    }
+
+    // add a hint indicating that bytes of the value should be pushed onto tape A
+    hints.insert(program.len(), OpHint::UnpackStart(n));
+
+    // read the bytes starting with the most significant one; for each byte, subtract
+    // byte * 256^i from the remainder at the top of the stack
+    for i in (0..n).rev() {
+        // read the byte and make sure it is less than 256
+        program.extend_from_slice(&[OpCode::Read, OpCode::Dup]);
+        append_rc_ops(program, hints, 8);
+        program.push(OpCode::Assert);
+
+        // [b, r] -> [r - b * 256^i, b]
+        program.extend_from_slice(&[OpCode::Swap, OpCode::Dup2, OpCode::Swap]);
+        if i > 0 {
+            append_push_op(program, hints, 1u128 << (8 * i));
+            program.push(OpCode::Mul);
+        }
+        program.extend_from_slice(&[OpCode::Neg, OpCode::Add, OpCode::Swap, OpCode::Drop]);
+    }
+
+    // make sure the remainder is 0
+    program.extend_from_slice(&[OpCode::Pad2, OpCode::Drop, OpCode::AssertEq]);
+    return Ok(true);
+}
+
+/// Appends a sequence of operations to the program to decompose the top value of the stack
+/// into n bits. Each bit is checked to be binary, and the bits are checked to add up to the
+/// original value. The least significant bit ends up at the top of the stack.
+pub fn parse_bits(program: &mut Vec<OpCode>, hints: &mut HintMap, op: &[&str], step: usize) -> Result<bool, AssemblyError> {
+    // besides the n bits, the decomposition needs two more stack slots while it runs
+    let n = read_param(op, step)?;
+    if n > (MAX_STACK_DEPTH - 2) as u32 {
+        return Err(AssemblyError::invalid_param_reason(op, step,
+            format!("parameter {} is invalid; value must be between 1 and {}", n, MAX_STACK_DEPTH - 2)))
     }
 
+    append_bits_ops(program, hints, n);
     return Ok(true);
 }
 
@@ -91,8 +175,9 @@ pub fn parse_dup(program: &mut Vec<OpCode>, op: &[&str], step: usize) -> Result<
         2 => program.push(OpCode::Dup2),
         3 => program.extend_from_slice(&[OpCode::Dup4, OpCode::Roll4, OpCode::Drop]),
         4 => program.push(OpCode::Dup4),
+        8 => program.push(OpCode::Dup8),
         _ => return Err(AssemblyError::invalid_param_reason(op, step,
-            format!("parameter {} is invalid; allowed values are: [1, 2, 3, 4]", n)))
+            format!("parameter {} is invalid; allowed values are: [1, 2, 3, 4, 8]", n)))
     };
 
     return Ok(true);
@@ -133,20 +218,66 @@ pub fn parse_pick(program: &mut Vec<OpCode>, op: &[&str], step: usize) -> Result
     return Ok(true);
 }
 
+/// Appends a sequence of operations to the program to pop an index k from the stack and push a
+/// copy of the k-th item of the next n items onto the stack; the operation fails if k >= n.
+pub fn parse_select(program: &mut Vec<OpCode>, hints: &mut HintMap, op: &[&str], step: usize) -> Result<bool, AssemblyError> {
+    let n = read_param(op, step)?;
+    match n {
+        2 => {
+            // [k, a, b] -> [b, a, k, a, b]; CHOOSE makes sure k is binary
+            program.extend_from_slice(&[
+                OpCode::Dup,   OpCode::Swap2, OpCode::Dup2, OpCode::Drop, OpCode::Swap,
+                OpCode::Roll4, OpCode::Dup2,  OpCode::Drop, OpCode::Roll4, OpCode::Choose
+            ]);
+        },
+        4 => {
+            // decompose the index into 2 bits: [b0, b1, a, b, c, d]
+            append_bits_ops(program, hints, 2);
+
+            // arrange the items for selection as [c, d, a, b, b1, 0, b0, 0] and duplicate them,
+            // so that one copy is consumed by the selection
+            program.extend_from_slice(&[
+                OpCode::Pad2,  OpCode::Swap2, OpCode::Swap,  OpCode::Roll4, OpCode::Swap,
+                OpCode::Swap4, OpCode::Swap2, OpCode::Dup8,
+            ]);
+
+            // select a pair of items based on b1 and then one of the items based on b0
+            program.extend_from_slice(&[OpCode::Choose2, OpCode::Swap, OpCode::Choose]);
+
+            // drop everything but the selected item and the original items:
+            // [v, 0, c, d, a, b, b1, 0, b0, 0, ...] -> [v, a, b, c, d, ...]
+            program.extend_from_slice(&[
+                OpCode::Swap,  OpCode::Drop,  OpCode::Swap4, OpCode::Roll8, OpCode::Swap2,
+                OpCode::Drop,  OpCode::Roll8, OpCode::Drop,  OpCode::Drop,  OpCode::Swap2,
+                OpCode::Drop,
+            ]);
+        },
+        _ => return Err(AssemblyError::invalid_param_reason(op, step,
+            format!("parameter {} is invalid; allowed values are: [2, 4]", n)))
+    };
+
+    return Ok(true);
+}
+
 /// Appends a sequence of operations to the program to remove top n values from the stack.
 pub fn parse_drop(program: &mut Vec<OpCode>, op: &[&str], step: usize) -> Result<bool, AssemblyError> {
     let n = read_param(op, step)?;
-    match n {
+    if n > 16 {
+        return Err(AssemblyError::invalid_param_reason(op, step,
+            format!("parameter {} is invalid; value must be between 1 and 16", n)));
+    }
+
+    // drop the remainder first; 3 values are dropped by duplicating the top value and then
+    // dropping 4 values, and so one extra DROP4 is needed in this case
+    match n % 4 {
         1 => program.push(OpCode::Drop),
         2 => program.extend_from_slice(&[OpCode::Drop, OpCode::Drop]),
         3 => program.extend_from_slice(&[OpCode::Dup, OpCode::Drop4]),
-        4 => program.push(OpCode::Drop4),
-        5 => program.extend_from_slice(&[OpCode::Drop, OpCode::Drop4]),
-        6 => program.extend_from_slice(&[OpCode::Drop, OpCode::Drop, OpCode::Drop4]),
-        7 => program.extend_from_slice(&[OpCode::Dup, OpCode::Drop4, OpCode::Drop4]),
-        8 => program.extend_from_slice(&[OpCode::Drop4, OpCode::Drop4]),
-        _ => return Err(AssemblyError::invalid_param_reason(op, step,
-            format!("parameter {} is invalid; allowed values are: [1, 2, 3, 4, 5, 6, 7, 8]", n)))
+        _ => (),
+    }
+
+    for _ in 0..(n / 4) {
+        program.push(OpCode::Drop4);
     }
 
     return Ok(true);
@@ -167,6 +298,15 @@ pub fn parse_swap(program: &mut Vec<OpCode>, op: &[&str], step: usize) -> Result
     return Ok(true);
 }
 
+/// Appends SWAP4 operation to the program; this swaps the top two 4-element words of the stack.
+pub fn parse_swapw(program: &mut Vec<OpCode>, op: &[&str], step: usize) -> Result<bool, AssemblyError> {
+    if op.len() > 1 {
+        return Err(AssemblyError::extra_param(op, step));
+    }
+    program.push(OpCode::Swap4);
+    return Ok(true);
+}
+
 /// Appends either ROLL4 or ROLL8 operation to the program.
 pub fn parse_roll(program: &mut Vec<OpCode>, op: &[&str], step: usize) -> Result<bool, AssemblyError> {
     let n = read_param(op, step)?;
@@ -180,26 +320,96 @@ pub fn parse_roll(program: &mut Vec<OpCode>, op: &[&str], step: usize) -> Result
     return Ok(true);
 }
 
+/// Appends a sequence of operations to the program to move n-th item to the top of the stack.
+/// Operations which can reach stack items deeper than 8 do not exist, and so n can be at most 8;
+/// moving deeper items would require dropping all items above them.
+pub fn parse_movup(program: &mut Vec<OpCode>, op: &[&str], step: usize) -> Result<bool, AssemblyError> {
+    let n = read_param(op, step)?;
+    match n {
+        2 => program.push(OpCode::Swap),
+        3 => program.extend_from_slice(&[OpCode::Swap2, OpCode::Swap, OpCode::Swap2, OpCode::Roll4]),
+        4 => program.push(OpCode::Roll4),
+        5 => program.extend_from_slice(&[
+            OpCode::Swap4, OpCode::Swap2, OpCode::Roll4, OpCode::Swap4, OpCode::Roll8
+        ]),
+        6 => program.extend_from_slice(&[
+            OpCode::Swap4, OpCode::Swap, OpCode::Swap2, OpCode::Roll4, OpCode::Swap4, OpCode::Roll8
+        ]),
+        7 => program.extend_from_slice(&[
+            OpCode::Swap4, OpCode::Swap2, OpCode::Swap, OpCode::Swap2, OpCode::Swap4, OpCode::Roll8
+        ]),
+        8 => program.push(OpCode::Roll8),
+        9..=16 => return Err(AssemblyError::invalid_param_reason(op, step,
+            format!("parameter {} is invalid; items deeper than 8 cannot be moved without dropping the items above them", n))),
+        _ => return Err(AssemblyError::invalid_param_reason(op, step,
+            format!("parameter {} is invalid; allowed values are: [2, 3, 4, 5, 6, 7, 8]", n)))
+    }
+
+    return Ok(true);
+}
+
+/// Appends a sequence of operations to the program to move the top item of the stack to n-th
+/// position. Same as for `movup`, n can be at most 8.
+pub fn parse_movdn(program: &mut Vec<OpCode>, op: &[&str], step: usize) -> Result<bool, AssemblyError> {
+    let n = read_param(op, step)?;
+    match n {
+        2 => program.push(OpCode::Swap),
+        3 => program.extend_from_slice(&[OpCode::Roll4, OpCode::Swap, OpCode::Swap2]),
+        4 => program.extend_from_slice(&[OpCode::Swap2, OpCode::Roll4]),
+        5 => program.extend_from_slice(&[
+            OpCode::Roll8, OpCode::Roll8, OpCode::Roll8, OpCode::Roll4, OpCode::Swap4
+        ]),
+        6 => program.extend_from_slice(&[
+            OpCode::Swap, OpCode::Roll8, OpCode::Roll8, OpCode::Roll4, OpCode::Swap4, OpCode::Roll8
+        ]),
+        7 => program.extend_from_slice(&[
+            OpCode::Roll8, OpCode::Swap, OpCode::Swap4, OpCode::Roll8, OpCode::Roll8
+        ]),
+        8 => program.extend_from_slice(&[OpCode::Swap4, OpCode::Roll8, OpCode::Roll8, OpCode::Roll8]),
+        9..=16 => return Err(AssemblyError::invalid_param_reason(op, step,
+            format!("parameter {} is invalid; items deeper than 8 cannot be moved without dropping the items above them", n))),
+        _ => return Err(AssemblyError::invalid_param_reason(op, step,
+            format!("parameter {} is invalid; allowed values are: [2, 3, 4, 5, 6, 7, 8]", n)))
+    }
+
+    return Ok(true);
+}
+
 // ARITHMETIC AND BOOLEAN OPERATIONS
 // ================================================================================================
 
-/// Appends ADD operation to the program.
-pub fn parse_add(program: &mut Vec<OpCode>, op: &[&str], step: usize) -> Result<bool, AssemblyError> {
-    if op.len() > 1 { return Err(AssemblyError::extra_param(op, step)); }
+/// Appends ADD operation to the program; if an immediate value is provided, the value is
+/// pushed onto the stack first.
+pub fn parse_add(program: &mut Vec<OpCode>, hints: &mut HintMap, op: &[&str], step: usize) -> Result<bool, AssemblyError> {
+    if op.len() > 1 {
+        let value = read_value(op, step)?;
+        append_push_op(program, hints, value);
+    }
     program.push(OpCode::Add);
     return Ok(true);
 }
 
-/// Appends NEG ADD operations to the program.
-pub fn parse_sub(program: &mut Vec<OpCode>, op: &[&str], step: usize) -> Result<bool, AssemblyError> {
-    if op.len() > 1 { return Err(AssemblyError::extra_param(op, step)); }
-    program.extend_from_slice(&[OpCode::Neg, OpCode::Add]);
+/// Appends NEG ADD operations to the program; if an immediate value is provided, its additive
+/// inverse is pushed onto the stack and added to the top of the stack.
+pub fn parse_sub(program: &mut Vec<OpCode>, hints: &mut HintMap, op: &[&str], step: usize) -> Result<bool, AssemblyError> {
+    if op.len() > 1 {
+        let value = read_value(op, step)?;
+        append_push_op(program, hints, field::neg(value));
+        program.push(OpCode::Add);
+    }
+    else {
+        program.extend_from_slice(&[OpCode::Neg, OpCode::Add]);
+    }
     return Ok(true);
 }
 
-/// Appends MUL operation to the program.
-pub fn parse_mul(program: &mut Vec<OpCode>, op: &[&str], step: usize) -> Result<bool, AssemblyError> {
-    if op.len() > 1 { return Err(AssemblyError::extra_param(op, step)); }
+/// Appends MUL operation to the program; if an immediate value is provided, the value is
+/// pushed onto the stack first.
+pub fn parse_mul(program: &mut Vec<OpCode>, hints: &mut HintMap, op: &[&str], step: usize) -> Result<bool, AssemblyError> {
+    if op.len() > 1 {
+        let value = read_value(op, step)?;
+        append_push_op(program, hints, value);
+    }
     program.push(OpCode::Mul);
     return Ok(true);
 }
@@ -246,6 +456,35 @@ pub fn parse_or(program: &mut Vec<OpCode>, op: &[&str], step: usize) -> Result<b
     return Ok(true);
 }
 
+/// Appends a sequence of operations to the program to raise the second value on the stack to
+/// the power of the top value of the stack; the exponent must fit into n bits.
+pub fn parse_exp(program: &mut Vec<OpCode>, hints: &mut HintMap, op: &[&str], step: usize) -> Result<bool, AssemblyError> {
+    // if the number of bits is not specified, assume that the exponent can be any field element
+    let n = if op.len() == 1 { 128 } else { read_param(op, step)? };
+    if n > 128 {
+        return Err(AssemblyError::invalid_param_reason(op, step,
+            format!("parameter {} is invalid; value must be between 1 and 128", n)))
+    }
+
+    // prepare the stack: [e, x] -> [0, x, 1, 1, 0, e]
+    program.extend_from_slice(&[
+        OpCode::Swap, OpCode::Pad2, OpCode::Not, OpCode::Dup, OpCode::Roll4, OpCode::Pad2, OpCode::Drop
+    ]);
+
+    // add a hint indicating that bits of the exponent should be pushed onto tape A
+    hints.insert(program.len(), OpHint::ExpStart(n));
+
+    // append EXPACC operations; each operation consumes one bit of the exponent starting
+    // with the least significant one
+    program.resize(program.len() + (n as usize), OpCode::ExpAcc);
+
+    // make sure the accumulated bits match the exponent, and drop everything but the result
+    program.extend_from_slice(&[
+        OpCode::Drop, OpCode::Drop, OpCode::Swap2, OpCode::AssertEq, OpCode::Swap, OpCode::Drop
+    ]);
+    return Ok(true);
+}
+
 // COMPARISON OPERATIONS
 // ================================================================================================
 
@@ -267,6 +506,16 @@ pub fn parse_ne(program: &mut Vec<OpCode>, hints: &mut HintMap, op: &[&str], ste
     return Ok(true);
 }
 
+/// Appends a sequence of operations to the the program to determine whether the top value on the
+/// stack is equal to 0.
+pub fn parse_iszero(program: &mut Vec<OpCode>, hints: &mut HintMap, op: &[&str], step: usize) -> Result<bool, AssemblyError> {
+    if op.len() > 1 { return Err(AssemblyError::extra_param(op, step)); }
+    program.extend_from_slice(&[OpCode::Pad2, OpCode::Drop]);
+    hints.insert(program.len(), OpHint::EqStart);
+    program.extend_from_slice(&[OpCode::Read, OpCode::Eq]);
+    return Ok(true);
+}
+
 /// Appends a sequence of operations to the program to determine whether the top value on the 
 /// stack is greater than the following value.
 pub fn parse_gt(program: &mut Vec<OpCode>, hints: &mut HintMap, op: &[&str], step: usize) -> Result<bool, AssemblyError> {
@@ -310,23 +559,7 @@ pub fn parse_lt(program: &mut Vec<OpCode>, hints: &mut HintMap, op: &[&str], ste
             format!("parameter {} is invalid; value must be between 4 and 128", n)))
     }
 
-    // prepare the stack
-    program.extend_from_slice(&[OpCode::Pad2, OpCode::Pad2, OpCode::Pad2, OpCode::Dup]);
-    let power_of_two = u128::pow(2, n - 1);
-    append_push_op(program, hints, power_of_two);
-
-    // add a hint indicating that value comparison is about to start
-    hints.insert(program.len(), OpHint::CmpStart(n));
-
-    // append CMP operations
-    program.resize(program.len() + (n as usize), OpCode::Cmp);
-
-    // compare binary aggregation values with the original values, and drop everything
-    // but the LT value from the stack
-    program.extend_from_slice(&[
-        OpCode::Drop4,    OpCode::Pad2,     OpCode::Swap4, OpCode::Roll4,
-        OpCode::AssertEq, OpCode::AssertEq, OpCode::Dup,   OpCode::Drop4
-    ]);
+    append_lt_ops(program, hints, n);
     return Ok(true);
 }
 
@@ -340,21 +573,7 @@ pub fn parse_rc(program: &mut Vec<OpCode>, hints: &mut HintMap, op: &[&str], ste
             format!("parameter {} is invalid; value must be between 4 and 128", n)))
     }
 
-    // prepare the stack
-    program.push(OpCode::Pad2);
-    append_push_op(program, hints, field::ONE);
-    program.extend_from_slice(&[OpCode::Swap, OpCode::Dup]);
-
-    // add a hint indicating that range-checking is about to start
-    hints.insert(program.len(), OpHint::RcStart(n));
-
-    // append BINACC operations
-    program.resize(program.len() + (n as usize), OpCode::BinAcc);
-
-    // compare binary aggregation value with the original value
-    program.extend_from_slice(&[OpCode::Dup, OpCode::Drop4]);
-    hints.insert(program.len(), OpHint::EqStart);
-    program.extend_from_slice(&[OpCode::Read, OpCode::Eq]);
+    append_rc_ops(program, hints, n);
     return Ok(true);
 }
 
@@ -392,6 +611,106 @@ pub fn parse_isodd(program: &mut Vec<OpCode>, hints: &mut HintMap, op: &[&str],
     return Ok(true);
 }
 
+// U32 OPERATIONS
+// ================================================================================================
+
+/// Appends a sequence of operations to the program to perform 32-bit integer arithmetic or
+/// bitwise operations on the top two values of the stack, or to shift or rotate the top value
+/// of the stack by a constant number of bits. The operation fails if any of the operands does
+/// not fit into 32 bits.
+pub fn parse_u32(program: &mut Vec<OpCode>, hints: &mut HintMap, op: &[&str], step: usize) -> Result<bool, AssemblyError> {
+    // shifts and rotations take the number of bits as an additional parameter
+    let num_params = match op.get(1) {
+        Some(&"shl") | Some(&"shr") | Some(&"rotl") | Some(&"rotr") => 2,
+        _ => 1,
+    };
+    if op.len() < num_params + 1 {
+        return Err(AssemblyError::missing_param(op, step));
+    }
+    else if op.len() > num_params + 1 {
+        return Err(AssemblyError::extra_param(op, step));
+    }
+
+    match op[1] {
+        "add" => {
+            // [b, a] -> [carry, (a + b) mod 2^32]
+            append_u32_check_ops(program, hints);
+            program.push(OpCode::Add);
+            append_u32_split_ops(program, hints, 33, 32);
+        },
+        "sub" => {
+            // [b, a] -> [borrow, (a - b) mod 2^32]; 2^32 is added to the difference to make
+            // sure it is positive, and so the borrow is the inverse of the 33rd bit
+            append_u32_check_ops(program, hints);
+            program.extend_from_slice(&[OpCode::Neg, OpCode::Add]);
+            append_push_op(program, hints, 1 << 32);
+            program.push(OpCode::Add);
+            append_u32_split_ops(program, hints, 33, 32);
+            program.push(OpCode::Not);
+        },
+        "mul" => {
+            // [b, a] -> [hi, lo] where a * b = hi * 2^32 + lo
+            append_u32_check_ops(program, hints);
+            program.push(OpCode::Mul);
+            append_u32_split_ops(program, hints, 64, 32);
+        },
+        "and" => {
+            append_u32_and_ops(program, hints);
+        },
+        "or" => {
+            // a | b = a + b - (a & b)
+            program.push(OpCode::Dup2);
+            append_u32_and_ops(program, hints);
+            program.extend_from_slice(&[OpCode::Neg, OpCode::Add, OpCode::Add]);
+        },
+        "xor" => {
+            // a ^ b = a + b - 2 * (a & b)
+            program.push(OpCode::Dup2);
+            append_u32_and_ops(program, hints);
+            program.extend_from_slice(&[
+                OpCode::Dup, OpCode::Add, OpCode::Neg, OpCode::Add, OpCode::Add
+            ]);
+        },
+        "divmod" => {
+            // [b, a] -> [r, q] where a = q * b + r and r < b; the quotient and the remainder
+            // are read from the secret tapes and their ranges are checked against a and b
+            append_u32_divmod_ops(program, hints);
+        },
+        "shl" => {
+            // [a] -> [(a << s) mod 2^32]; the bits shifted out end up in hi and are dropped
+            let s = read_shift(op, step)?;
+            append_u32_split_ops(program, hints, 32, 32 - s);
+            program.push(OpCode::Drop);
+            append_u32_scale_ops(program, hints, s);
+        },
+        "shr" => {
+            // [a] -> [a >> s]; shifting by 0 bits is the same as splitting off the top 0 bits
+            let s = read_shift(op, step)?;
+            if s == 0 {
+                append_u32_split_ops(program, hints, 32, 32);
+                program.push(OpCode::Drop);
+            }
+            else {
+                append_u32_split_ops(program, hints, 32, s);
+                program.extend_from_slice(&[OpCode::Swap, OpCode::Drop]);
+            }
+        },
+        "rotl" => {
+            // [a] -> [a <<< s]
+            let s = read_shift(op, step)?;
+            append_u32_rotl_ops(program, hints, s);
+        },
+        "rotr" => {
+            // [a] -> [a >>> s]; rotating right by s bits is the same as rotating left by 32 - s
+            let s = read_shift(op, step)?;
+            append_u32_rotl_ops(program, hints, (32 - s) % 32);
+        },
+        _ => return Err(AssemblyError::invalid_param_reason(op, step,
+            format!("parameter {} is invalid; allowed values are: [add, sub, mul, divmod, and, or, xor, shl, shr, rotl, rotr]", op[1])))
+    }
+    return Ok(true);
+}
+
 // SELECTOR OPERATIONS
 // ================================================================================================
 
@@ -407,12 +726,28 @@ pub fn parse_choose(program: &mut Vec<OpCode>, op: &[&str], step: usize) -> Resu
     return Ok(true);
 }
 
+/// Appends either CSWAP2 or CSWAP4 operation to the program.
+pub fn parse_cswap(program: &mut Vec<OpCode>, op: &[&str], step: usize) -> Result<bool, AssemblyError> {
+    let n = read_param(op, step)?;
+    match n {
+        2 => program.push(OpCode::CSwap2),
+        4 => program.push(OpCode::CSwap4),
+        _ => return Err(AssemblyError::invalid_param_reason(op, step,
+            format!("parameter {} is invalid; allowed values are: [2, 4]", n)))
+    }
+    return Ok(true);
+}
+
 // CRYPTO OPERATIONS
 // ================================================================================================
 
-/// Appends a sequence of operations to the program to hash top n values of the stack.
+/// Appends a sequence of operations to the program to hash top n values of the stack. hash.2to1
+/// is an alias for hash.4 which merges two digests into the digest of their parent node.
 pub fn parse_hash(program: &mut Vec<OpCode>, op: &[&str], step: usize) -> Result<bool, AssemblyError> {
-    let n = read_param(op, step)?;
+    let n = match op.get(1) {
+        Some(&"2to1") if op.len() == 2 => 4,
+        _ => read_param(op, step)?,
+    };
     match n {
         1 => program.extend_from_slice(&[OpCode::Pad2, OpCode::Pad2, OpCode::Pad2, OpCode::Drop]),
         2 => program.extend_from_slice(&[OpCode::Pad2, OpCode::Pad2]),
@@ -448,37 +783,38 @@ pub fn parse_smpath(program: &mut Vec<OpCode>, op: &[&str], step: usize) -> Resu
             format!("parameter {} is invalid; value must be between 2 and 256", n)))
     }
 
-    // move the first bit of the leaf's index and the first node in the Merkle onto the stack,
-    // position them correctly, and pad the stack to prepare it for hashing.
-    program.extend_from_slice(&[
-        OpCode::Read2, OpCode::Swap2, OpCode::Read2, OpCode::CSwap2, OpCode::Pad2
-    ]);
-
-    // pad with NOOPs to make sure hashing starts on a step which is a multiple of 16
-    let alignment = program.len() % HASH_OP_ALIGNMENT;
-    let pad_length = (HASH_OP_ALIGNMENT - alignment) % HASH_OP_ALIGNMENT;
-    program.resize(program.len() + pad_length, OpCode::Noop);
+    append_smpath_ops(program, n as usize);
+    return Ok(true);
+}
 
-    // repeat the following cycle of operations once for each remaining node:
-    // 1. compute hash of the 2 nodes on the stack
-    // 2. read the index of the next node in the authentication path
-    // 3. read the next node in the authentication path
-    // 4. base on position index bit = 1, swaps the nodes on the stack (using cswap2 instruction)
-    // 5. pad the stack to prepare it for the next round of hashing
-    const SUB_CYCLE: [OpCode; 16] = [
-        OpCode::RescR, OpCode::RescR, OpCode::RescR,  OpCode::RescR,
-        OpCode::RescR, OpCode::RescR, OpCode::RescR,  OpCode::RescR,
-        OpCode::RescR, OpCode::RescR, OpCode::Drop4,  OpCode::Read2,
-        OpCode::Swap2, OpCode::Read2, OpCode::CSwap2, OpCode::Pad2,
-    ];
+/// Appends a sequence of operations to the program to verify that a Merkle authentication path
+/// for a tree of depth d resolves to the expected root. The stack is expected to contain the
+/// leaf node followed by the root; index bits and sibling nodes are read from input tapes A and B.
+pub fn parse_mtree(program: &mut Vec<OpCode>, op: &[&str], step: usize) -> Result<bool, AssemblyError> {
+    if op.len() < 3 {
+        return Err(AssemblyError::missing_param(op, step));
+    }
+    else if op.len() > 3 {
+        return Err(AssemblyError::extra_param(op, step));
+    }
+    else if op[1] != "verify" {
+        return Err(AssemblyError::invalid_op(op, step));
+    }
 
-    for _ in 0..(n - 2) {
-        program.extend_from_slice(&SUB_CYCLE);
+    let d = match op[2].parse::<usize>() {
+        Ok(d) => d,
+        Err(_) => return Err(AssemblyError::invalid_param(op, step))
+    };
+    if d < 2 || d > 256 {
+        return Err(AssemblyError::invalid_param_reason(op, step,
+            format!("parameter {} is invalid; value must be between 2 and 256", d)))
     }
 
-    // at the end, use the same cycle except for the last 5 operations
-    // since there is no need to read in any additional nodes
-    program.extend_from_slice(&SUB_CYCLE[..11]);
+    // compute the root of the path; this leaves the computed root on top of the expected root
+    append_smpath_ops(program, d);
+
+    // make sure the computed root is equal to the expected root
+    program.extend_from_slice(&[OpCode::Swap, OpCode::Roll4, OpCode::AssertEq, OpCode::AssertEq]);
 
     return Ok(true);
 }
@@ -540,6 +876,255 @@ pub fn parse_pmpath(program: &mut Vec<OpCode>, hints: &mut HintMap, op: &[&str],
 // HELPER FUNCTIONS
 // ================================================================================================
 
+/// Appends operations to compute the root of a Merkle authentication path for a tree of depth n;
+/// the leaf node is expected to be on the top of the stack.
+fn append_smpath_ops(program: &mut Vec<OpCode>, n: usize) {
+
+    // move the first bit of the leaf's index and the first node in the Merkle onto the stack,
+    // position them correctly, and pad the stack to prepare it for hashing.
+    program.extend_from_slice(&[
+        OpCode::Read2, OpCode::Swap2, OpCode::Read2, OpCode::CSwap2, OpCode::Pad2
+    ]);
+
+    // pad with NOOPs to make sure hashing starts on a step which is a multiple of 16
+    let alignment = program.len() % HASH_OP_ALIGNMENT;
+    let pad_length = (HASH_OP_ALIGNMENT - alignment) % HASH_OP_ALIGNMENT;
+    program.resize(program.len() + pad_length, OpCode::Noop);
+
+    // repeat the following cycle of operations once for each remaining node:
+    // 1. compute hash of the 2 nodes on the stack
+    // 2. read the index of the next node in the authentication path
+    // 3. read the next node in the authentication path
+    // 4. base on position index bit = 1, swaps the nodes on the stack (using cswap2 instruction)
+    // 5. pad the stack to prepare it for the next round of hashing
+    const SUB_CYCLE: [OpCode; 16] = [
+        OpCode::RescR, OpCode::RescR, OpCode::RescR,  OpCode::RescR,
+        OpCode::RescR, OpCode::RescR, OpCode::RescR,  OpCode::RescR,
+        OpCode::RescR, OpCode::RescR, OpCode::Drop4,  OpCode::Read2,
+        OpCode::Swap2, OpCode::Read2, OpCode::CSwap2, OpCode::Pad2,
+    ];
+
+    for _ in 0..(n - 2) {
+        program.extend_from_slice(&SUB_CYCLE);
+    }
+
+    // at the end, use the same cycle except for the last 5 operations
+    // since there is no need to read in any additional nodes
+    program.extend_from_slice(&SUB_CYCLE[..11]);
+}
+
+/// Appends a sequence of operations which replaces the top value of the stack with 1 if the
+/// value can be represented with n bits, and with 0 otherwise.
+fn append_rc_ops(program: &mut Vec<OpCode>, hints: &mut HintMap, n: u32) {
+    // prepare the stack
+    program.push(OpCode::Pad2);
+    append_push_op(program, hints, field::ONE);
+    program.extend_from_slice(&[OpCode::Swap, OpCode::Dup]);
+
+    // add a hint indicating that range-checking is about to start
+    hints.insert(program.len(), OpHint::RcStart(n));
+
+    // append BINACC operations
+    program.resize(program.len() + (n as usize), OpCode::BinAcc);
+
+    // compare binary aggregation value with the original value
+    program.extend_from_slice(&[OpCode::Dup, OpCode::Drop4]);
+    hints.insert(program.len(), OpHint::EqStart);
+    program.extend_from_slice(&[OpCode::Read, OpCode::Eq]);
+}
+
+/// Appends a sequence of operations which replaces the top two values of the stack with 1 if
+/// the top value is less than the following value, and with 0 otherwise; the operation fails
+/// if either of the values does not fit into n bits.
+fn append_lt_ops(program: &mut Vec<OpCode>, hints: &mut HintMap, n: u32) {
+    // prepare the stack
+    program.extend_from_slice(&[OpCode::Pad2, OpCode::Pad2, OpCode::Pad2, OpCode::Dup]);
+    let power_of_two = u128::pow(2, n - 1);
+    append_push_op(program, hints, power_of_two);
+
+    // add a hint indicating that value comparison is about to start
+    hints.insert(program.len(), OpHint::CmpStart(n));
+
+    // append CMP operations
+    program.resize(program.len() + (n as usize), OpCode::Cmp);
+
+    // compare binary aggregation values with the original values, and drop everything
+    // but the LT value from the stack
+    program.extend_from_slice(&[
+        OpCode::Drop4,    OpCode::Pad2,     OpCode::Swap4, OpCode::Roll4,
+        OpCode::AssertEq, OpCode::AssertEq, OpCode::Dup,   OpCode::Drop4
+    ]);
+}
+
+/// Appends a sequence of operations which replaces the top value of the stack with its n least
+/// significant bits; the least significant bit ends up at the top of the stack.
+fn append_bits_ops(program: &mut Vec<OpCode>, hints: &mut HintMap, n: u32) {
+    // the value is replaced with acc = -x / 2^n, and the bits are read starting with the most
+    // significant one; after each bit b is read, acc is updated as acc = 2 * acc + b, and so
+    // after reading all bits but the least significant one, acc = -b_0 / 2
+    let scale = field::neg(field::inv(1 << n));
+    for i in (1..n).rev() {
+        // read the bit and make sure it is binary: [acc, ...] -> [b, acc, ...]
+        if i == n - 1 {
+            hints.insert(program.len(), OpHint::BitsStart(n));
+        }
+        program.extend_from_slice(&[OpCode::Read, OpCode::Not, OpCode::Not, OpCode::Swap]);
+        if i == n - 1 {
+            append_push_op(program, hints, scale);
+            program.push(OpCode::Mul);
+        }
+
+        // [acc, b, ...] -> [2 * acc + b, b, ...]
+        program.extend_from_slice(&[OpCode::Dup2, OpCode::Add, OpCode::Add]);
+    }
+    if n == 1 {
+        append_push_op(program, hints, scale);
+        program.push(OpCode::Mul);
+    }
+
+    // derive the least significant bit from the accumulator and make sure it is binary
+    program.extend_from_slice(&[OpCode::Dup, OpCode::Add, OpCode::Neg, OpCode::Not, OpCode::Not]);
+}
+
+/// Appends a sequence of operations which fails unless both of the top two values of the stack
+/// can be represented with 32 bits; the values are left on the stack.
+fn append_u32_check_ops(program: &mut Vec<OpCode>, hints: &mut HintMap) {
+    // compare copies of the values using CMP operations; this decomposes both values into
+    // 32 bits at the same time
+    program.extend_from_slice(&[
+        OpCode::Dup2, OpCode::Pad2, OpCode::Pad2, OpCode::Pad2, OpCode::Dup
+    ]);
+    append_push_op(program, hints, 1 << 31);
+    hints.insert(program.len(), OpHint::CmpStart(32));
+    program.resize(program.len() + 32, OpCode::Cmp);
+
+    // make sure binary aggregation values are equal to the original values, and drop the
+    // result of the comparison
+    program.extend_from_slice(&[
+        OpCode::Drop4,    OpCode::Pad2,     OpCode::Swap4, OpCode::Roll4,
+        OpCode::AssertEq, OpCode::AssertEq, OpCode::Dup,   OpCode::Drop4,
+        OpCode::Drop
+    ]);
+}
+
+/// Appends a sequence of operations which replaces the top two values of the stack with their
+/// bitwise AND; the operation fails unless both values can be represented with 32 bits.
+fn append_u32_and_ops(program: &mut Vec<OpCode>, hints: &mut HintMap) {
+    // prepare the stack for CMP operations: [p, 0, 0, 0, r, 1, 0, 0, a, b]; the register which
+    // usually holds the GT flag is used to accumulate the result instead. Since the LT flag
+    // is set to 1, CMP operations never update the GT register.
+    program.extend_from_slice(&[
+        OpCode::Pad2, OpCode::Pad2, OpCode::Not, OpCode::Swap, OpCode::Pad2, OpCode::Dup
+    ]);
+    append_push_op(program, hints, 1 << 31);
+    hints.insert(program.len(), OpHint::CmpStart(32));
+
+    // decompose both values into bits starting with the most significant bit; after each
+    // CMP operation, add bit_a * bit_b * p / 2 to the result and restore the layout of the
+    // stack: [p / 2, bit_a, bit_b, 0, r, 1, acc_b, acc_a] -> [p / 2, ?, ?, ?, r', 1, acc_b, acc_a]
+    for _ in 0..32 {
+        program.extend_from_slice(&[
+            OpCode::Cmp,
+            OpCode::Dup,   OpCode::Swap2, OpCode::Mul, OpCode::Mul, OpCode::Roll4,
+            OpCode::Add,   OpCode::Dup,   OpCode::Swap2, OpCode::Dup,
+        ]);
+    }
+
+    // make sure the binary aggregation values are equal to the original values, and double
+    // the result to account for the powers of two being halved by CMP operations
+    program.extend_from_slice(&[
+        OpCode::Drop,     OpCode::Swap4,    OpCode::Drop, OpCode::Roll8,
+        OpCode::AssertEq, OpCode::AssertEq, OpCode::Drop, OpCode::Drop,
+        OpCode::Add,
+    ]);
+}
+
+/// Appends a sequence of operations which splits the top value of the stack into the lower m
+/// bits and the remaining n - m bits; the operation fails if the value does not fit into
+/// n bits. The stack is transformed as: [x] -> [hi, lo] where x = hi * 2^m + lo.
+fn append_u32_split_ops(program: &mut Vec<OpCode>, hints: &mut HintMap, n: u32, m: u32) {
+    debug_assert!(m > 0 && m <= n, "split point {} is invalid", m);
+
+    // prepare the stack and push all n bits of the value onto tape A
+    program.push(OpCode::Pad2);
+    append_push_op(program, hints, field::ONE);
+    program.extend_from_slice(&[OpCode::Swap, OpCode::Dup]);
+    hints.insert(program.len(), OpHint::RcStart(n));
+
+    // aggregate the lower m bits: [b, 0, 2^m, lo, x]
+    program.resize(program.len() + m as usize, OpCode::BinAcc);
+
+    // reset the aggregation state and aggregate the remaining bits: [b, 0, 2^(n-m), hi, lo, x]
+    program.extend_from_slice(&[OpCode::Drop, OpCode::Drop, OpCode::Drop, OpCode::Pad2]);
+    append_push_op(program, hints, field::ONE);
+    program.extend_from_slice(&[OpCode::Swap, OpCode::Dup]);
+    program.resize(program.len() + (n - m) as usize, OpCode::BinAcc);
+
+    // make sure hi * 2^m + lo = x, and drop everything but hi and lo from the stack
+    program.extend_from_slice(&[OpCode::Drop, OpCode::Drop, OpCode::Drop, OpCode::Dup2]);
+    append_push_op(program, hints, 1 << m);
+    program.extend_from_slice(&[OpCode::Mul, OpCode::Add, OpCode::Roll4, OpCode::AssertEq]);
+}
+
+/// Appends a sequence of operations which replaces the top two values of the stack [b, a] with
+/// the remainder and the quotient of a / b; the operation fails if b is 0, or if either of the
+/// values does not fit into 32 bits.
+fn append_u32_divmod_ops(program: &mut Vec<OpCode>, hints: &mut HintMap) {
+    // read the quotient and the remainder from the tapes: [r, q, b, a]
+    hints.insert(program.len(), OpHint::DivStart);
+    program.push(OpCode::Read2);
+
+    // make sure q and a fit into 32 bits: [q, a, r, b, r, q, b, a]
+    program.extend_from_slice(&[OpCode::Dup4, OpCode::Swap, OpCode::Roll4, OpCode::Swap]);
+    append_u32_check_ops(program, hints);
+
+    // make sure r < b; this also makes sure that r and b fit into 32 bits: [r, q, b, a]
+    program.extend_from_slice(&[OpCode::Drop, OpCode::Drop]);
+    append_lt_ops(program, hints, 32);
+    program.push(OpCode::Assert);
+
+    // make sure q * b + r = a; since all values fit into 32 bits, this cannot overflow
+    program.extend_from_slice(&[
+        OpCode::Dup4, OpCode::Roll4, OpCode::Swap2, OpCode::Mul, OpCode::Roll4, OpCode::Add,
+        OpCode::AssertEq,
+    ]);
+
+    // drop the original values from the stack
+    program.extend_from_slice(&[OpCode::Swap2, OpCode::Drop, OpCode::Drop]);
+}
+
+/// Appends a sequence of operations which rotates the top value of the stack left by s bits;
+/// the operation fails if the value does not fit into 32 bits.
+fn append_u32_rotl_ops(program: &mut Vec<OpCode>, hints: &mut HintMap, s: u32) {
+    // the top s bits of the value end up in hi and become the lower s bits of the result
+    append_u32_split_ops(program, hints, 32, 32 - s);
+    program.push(OpCode::Swap);
+    append_u32_scale_ops(program, hints, s);
+    program.push(OpCode::Add);
+}
+
+/// Appends a sequence of operations which multiplies the top value of the stack by 2^s.
+fn append_u32_scale_ops(program: &mut Vec<OpCode>, hints: &mut HintMap, s: u32) {
+    if s > 0 {
+        append_push_op(program, hints, 1 << s);
+        program.push(OpCode::Mul);
+    }
+}
+
+/// Reads the number of bits by which a value is to be shifted or rotated; the number of bits
+/// must be between 0 and 31.
+fn read_shift(op: &[&str], step: usize) -> Result<u32, AssemblyError> {
+    let s = match op[2].parse::<u32>() {
+        Ok(s) => s,
+        Err(_) => return Err(AssemblyError::invalid_param(op, step))
+    };
+    if s > 31 {
+        return Err(AssemblyError::invalid_param_reason(op, step,
+            format!("parameter value must be between 0 and 31")));
+    }
+    return Ok(s);
+}
+
 fn read_param(op: &[&str], step: usize) -> Result<u32, AssemblyError> {
     if op.len() == 1 {
         // if no parameters were provided, assume parameter value 1
@@ -548,8 +1133,14 @@ fn read_param(op: &[&str], step: usize) -> Result<u32, AssemblyError> {
         return Err(AssemblyError::extra_param(op, step));
     }
 
-    // try to parse the parameter value
-    let result = match op[1].parse::<u32>() {
+    // try to parse the parameter value as a decimal or a hexadecimal number
+    let result = if op[1].starts_with("0x") {
+        u32::from_str_radix(&op[1][2..], 16)
+    }
+    else {
+        op[1].parse::<u32>()
+    };
+    let result = match result {
         Ok(i) => i,
         Err(_) => return Err(AssemblyError::invalid_param(op, step))
     };
@@ -563,7 +1154,7 @@ fn read_param(op: &[&str], step: usize) -> Result<u32, AssemblyError> {
     return Ok(result);
 }
 
-fn read_value(op: &[&str], step: usize) -> Result<u128, AssemblyError> {
+pub fn read_value(op: &[&str], step: usize) -> Result<u128, AssemblyError> {
     // make sure exactly 1 parameter was supplied
     if op.len() == 1 {
         return Err(AssemblyError::missing_param(op, step));
@@ -572,6 +1163,15 @@ fn read_value(op: &[&str], step: usize) -> Result<u128, AssemblyError> {
         return Err(AssemblyError::extra_param(op, step));
     }
 
+    // constant expressions are evaluated in the field
+    if op[1] == "p" || op[1].contains(|c| "+-*^()".contains(c)) {
+        return match ConstExpr::new(op[1]).evaluate() {
+            Ok(value) => Ok(value),
+            Err(reason) => Err(AssemblyError::invalid_param_reason(op, step,
+                format!("parameter '{}' is invalid; {}", op[1], reason))),
+        };
+    }
+
     let result = if op[1].starts_with("0x") {
         // parse hexadecimal number
         match u128::from_str_radix(&op[1][2..], 16) {
@@ -594,4 +1194,133 @@ fn read_value(op: &[&str], step: usize) -> Result<u128, AssemblyError> {
     }
 
     return Ok(result);
-}
\ No newline at end of file
+}
+
+// CONSTANT EXPRESSIONS
+// ================================================================================================
+
+/// Evaluates constant expressions used as instruction parameters (e.g. `push.2^64-1`). An
+/// expression can contain decimal and hexadecimal literals, `p` for the field modulus, binary
+/// `+`, `-`, `*`, powers with literal exponents (`2^n`), unary minus, and parentheses. All
+/// operations, except for exponent literals, are performed in the field.
+struct ConstExpr {
+    chars   : Vec<char>,
+    pos     : usize,
+}
+
+impl ConstExpr {
+
+    fn new(source: &str) -> ConstExpr {
+        return ConstExpr { chars: source.chars().collect(), pos: 0 };
+    }
+
+    /// Returns the value of the expression, or a description of the error and its position
+    /// (starting with 1) within the expression.
+    fn evaluate(&mut self) -> Result<u128, String> {
+        let value = self.parse_sum()?;
+        if self.pos < self.chars.len() {
+            return Err(self.unexpected());
+        }
+        return Ok(value);
+    }
+
+    /// sum := product (('+' | '-') product)*
+    fn parse_sum(&mut self) -> Result<u128, String> {
+        let mut value = self.parse_product()?;
+        loop {
+            match self.peek() {
+                Some('+') => { self.pos += 1; value = field::add(value, self.parse_product()?); },
+                Some('-') => { self.pos += 1; value = field::sub(value, self.parse_product()?); },
+                _ => return Ok(value),
+            }
+        }
+    }
+
+    /// product := unary ('*' unary)*
+    fn parse_product(&mut self) -> Result<u128, String> {
+        let mut value = self.parse_unary()?;
+        while self.peek() == Some('*') {
+            self.pos += 1;
+            value = field::mul(value, self.parse_unary()?);
+        }
+        return Ok(value);
+    }
+
+    /// unary := '-' unary | power
+    fn parse_unary(&mut self) -> Result<u128, String> {
+        if self.peek() == Some('-') {
+            self.pos += 1;
+            return Ok(field::neg(self.parse_unary()?));
+        }
+        return self.parse_power();
+    }
+
+    /// power := atom ('^' literal)?
+    fn parse_power(&mut self) -> Result<u128, String> {
+        let base = self.parse_atom()?;
+        if self.peek() == Some('^') {
+            self.pos += 1;
+            let exponent = self.parse_literal()?;
+            return Ok(field::exp(base, exponent));
+        }
+        return Ok(base);
+    }
+
+    /// atom := literal | 'p' | '(' sum ')'
+    fn parse_atom(&mut self) -> Result<u128, String> {
+        match self.peek() {
+            Some('p') => {
+                // the modulus is equal to 0 in the field
+                self.pos += 1;
+                return Ok(field::ZERO);
+            },
+            Some('(') => {
+                self.pos += 1;
+                let value = self.parse_sum()?;
+                if self.peek() != Some(')') {
+                    return Err(self.unexpected());
+                }
+                self.pos += 1;
+                return Ok(value);
+            },
+            _ => {
+                let start = self.pos;
+                let value = self.parse_literal()?;
+                if value >= field::MODULUS {
+                    return Err(format!("value at position {} must be smaller than {}", start + 1, field::MODULUS));
+                }
+                return Ok(value);
+            }
+        }
+    }
+
+    /// Parses a decimal or a hexadecimal literal; the value is not reduced by the modulus.
+    fn parse_literal(&mut self) -> Result<u128, String> {
+        let start = self.pos;
+        let radix = if self.chars[start..].starts_with(&['0', 'x']) { self.pos += 2; 16 } else { 10 };
+        let digits_start = self.pos;
+        while self.peek().map_or(false, |c| c.is_digit(radix)) {
+            self.pos += 1;
+        }
+        if self.pos == digits_start {
+            return Err(self.unexpected());
+        }
+
+        let digits: String = self.chars[digits_start..self.pos].iter().collect();
+        return match u128::from_str_radix(&digits, radix) {
+            Ok(value) => Ok(value),
+            Err(_) => Err(format!("value at position {} does not fit into 128 bits", start + 1)),
+        };
+    }
+
+    fn peek(&self) -> Option<char> {
+        return self.chars.get(self.pos).copied();
+    }
+
+    fn unexpected(&self) -> String {
+        return match self.peek() {
+            Some(c) => format!("unexpected character '{}' at position {}", c, self.pos + 1),
+            None => String::from("unexpected end of expression"),
+        };
+    }
+}
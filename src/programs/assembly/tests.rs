@@ -267,13 +267,9 @@ fn repeat_2_spans() {
     let expected = "\
     begin \
         read read add read read::eq eq noop \
+        push(3) add noop noop noop noop noop noop \
+        push(3) add noop noop noop noop noop noop \
         noop noop noop noop noop noop noop \
-        block \
-            push(3) add noop noop noop noop noop noop \
-            noop noop noop noop noop noop noop noop \
-            push(3) add noop noop noop noop noop noop \
-            noop noop noop noop noop noop noop \
-        end \
     end";
 
     assert_eq!(expected, format!("{:?}", program));
@@ -293,19 +289,11 @@ fn repeat_5_spans() {
     let expected = "\
     begin \
         read read add read read::eq eq noop \
-        noop noop noop noop noop noop noop \
-        block \
-            push(3) add noop noop noop noop noop noop \
-            noop noop noop noop noop noop noop noop \
-            push(3) add noop noop noop noop noop noop \
-            noop noop noop noop noop noop noop noop \
-            push(3) add noop noop noop noop noop noop \
-            noop noop noop noop noop noop noop noop \
-            push(3) add noop noop noop noop noop noop \
-            noop noop noop noop noop noop noop noop \
-            push(3) add noop noop noop noop noop noop \
-            noop noop noop noop noop noop noop \
-        end \
+        push(3) add noop noop noop noop noop noop \
+        push(3) add noop noop noop noop noop noop \
+        push(3) add noop noop noop noop noop noop \
+        push(3) add noop noop noop noop noop noop \
+        push(3) add noop noop noop noop noop \
     end";
 
     assert_eq!(expected, format!("{:?}", program));
@@ -327,27 +315,23 @@ fn repeat_2_blocks() {
 
     let expected = "\
     begin \
-        read read add read read::eq eq noop \
+        read read add read read::eq eq read \
         noop noop noop noop noop noop noop \
-        block \
-            read noop noop noop noop noop noop noop \
+        if \
+            assert noop noop noop noop noop noop noop \
+            push(3) add mul noop noop noop noop \
+        else \
+            not assert noop noop noop noop noop noop \
             noop noop noop noop noop noop noop \
-            if \
-                assert noop noop noop noop noop noop noop \
-                push(3) add mul noop noop noop noop \
-            else \
-                not assert noop noop noop noop noop noop \
-                noop noop noop noop noop noop noop \
-            end \
-            read noop noop noop noop noop noop noop \
+        end \
+        read noop noop noop noop noop noop noop \
+        noop noop noop noop noop noop noop \
+        if \
+            assert noop noop noop noop noop noop noop \
+            push(3) add mul noop noop noop noop \
+        else \
+            not assert noop noop noop noop noop noop \
             noop noop noop noop noop noop noop \
-            if \
-                assert noop noop noop noop noop noop noop \
-                push(3) add mul noop noop noop noop \
-            else \
-                not assert noop noop noop noop noop noop \
-                noop noop noop noop noop noop noop \
-            end \
         end \
     end";
 
@@ -371,33 +355,563 @@ fn repeat_2_blocks_with_suffix() {
 
     let expected = "\
     begin \
-        read read add read read::eq eq noop \
+        read read add read read::eq eq read \
         noop noop noop noop noop noop noop \
-        block \
-            read noop noop noop noop noop noop noop \
-            noop noop noop noop noop noop noop \
-            if \
-                assert noop noop noop noop noop noop noop \
-                push(3) add mul noop noop noop noop \
-            else \
-                not assert noop noop noop noop noop noop \
-                noop noop noop noop noop noop noop \
-            end \
-            neg add inv noop noop noop noop noop \
-            noop noop noop noop noop noop noop noop \
-            read noop noop noop noop noop noop noop \
+        if \
+            assert noop noop noop noop noop noop noop \
+            push(3) add mul noop noop noop noop \
+        else \
+            not assert noop noop noop noop noop noop \
             noop noop noop noop noop noop noop \
-            if \
-                assert noop noop noop noop noop noop noop \
-                push(3) add mul noop noop noop noop \
-            else \
-                not assert noop noop noop noop noop noop \
-                noop noop noop noop noop noop noop \
-            end \
-            neg add inv noop noop noop noop noop \
+        end \
+        neg add inv read noop noop noop noop \
+        noop noop noop noop noop noop noop \
+        if \
+            assert noop noop noop noop noop noop noop \
+            push(3) add mul noop noop noop noop \
+        else \
+            not assert noop noop noop noop noop noop \
             noop noop noop noop noop noop noop \
         end \
+        neg add inv noop noop noop noop noop \
+        noop noop noop noop noop noop noop \
     end";
 
     assert_eq!(expected, format!("{:?}", program));
-}
\ No newline at end of file
+}
+#[test]
+fn repeat_unrolled() {
+    let program = super::compile("begin repeat.16 dup add end end").unwrap();
+    let expected = super::compile(&format!("begin {}end", "dup add ".repeat(16))).unwrap();
+    assert_eq!(expected.hash(), program.hash());
+    assert_eq!(format!("{:?}", expected), format!("{:?}", program));
+
+    // nested repeat blocks with push instructions; every push gets its own hint
+    let program = super::compile("begin repeat.2 push.1 repeat.3 push.2 mul end end end").unwrap();
+    let expected = "begin push.1 push.2 mul push.2 mul push.2 mul push.1 push.2 mul push.2 mul push.2 mul end";
+    let expected = super::compile(expected).unwrap();
+    assert_eq!(format!("{:?}", expected), format!("{:?}", program));
+
+    let program = super::compile("begin repeat.1 push.3 end end").unwrap();
+    assert_eq!(super::compile("begin push.3 end").unwrap().hash(), program.hash());
+}
+
+#[test]
+fn repeat_errors() {
+    let err = super::compile("begin push.1 repeat.0 add end end").unwrap_err();
+    assert_eq!("invalid repeat statement 'repeat.0': at least 1 iteration must be specified", err.message());
+
+    let err = super::compile("begin push.1 repeat add end end").unwrap_err();
+    assert_eq!("malformed instruction repeat: parameter is missing", err.message());
+
+    let err = super::compile("begin\n  push.1\n  repeat.2\n    add\n").unwrap_err();
+    assert_eq!((3, 3), (err.line(), err.column()));
+    assert_eq!("repeat without matching end", err.message());
+
+    let err = super::compile("begin push.1 read if.true repeat.2 add else sub end end end").unwrap_err();
+    assert_eq!("else without matching if", err.message());
+}
+
+// SWITCH BLOCKS
+// ================================================================================================
+
+#[test]
+fn switch_expansion() {
+    // cases are selected by a tree of if blocks over the bits of the selector
+    let program = super::compile("begin switch case.0 add case.1 mul case.2 neg end end").unwrap();
+    let expected = super::compile("
+        begin
+            bits.2
+            if.true
+                if.true pad.1 assert else mul end
+            else
+                if.true neg else add end
+            end
+        end").unwrap();
+    assert_eq!(expected.hash(), program.hash());
+
+    // a switch with a single case still checks that the selector is 0
+    let program = super::compile("begin switch case.0 add end end").unwrap();
+    let expected = super::compile("begin bits.1 if.true pad.1 assert else add end end").unwrap();
+    assert_eq!(expected.hash(), program.hash());
+}
+
+#[test]
+fn switch_errors() {
+    let err = super::compile("begin switch case.0 add case.2 mul end end").unwrap_err();
+    assert_eq!("invalid case 'case.2'; expected case.1", err.message());
+
+    let err = super::compile("begin switch add case.0 mul end end").unwrap_err();
+    assert_eq!("invalid case 'add'; expected case.0", err.message());
+
+    let err = super::compile("begin switch case.0 case.1 mul end end").unwrap_err();
+    assert_eq!("a program block must contain at least one instruction", err.message());
+
+    let err = super::compile("begin add case.0 mul end").unwrap_err();
+    assert_eq!("case without matching switch", err.message());
+
+    let err = super::compile("begin switch case.0 add end").unwrap_err();
+    assert_eq!("block without matching end", err.message());
+}
+
+// PROCEDURES
+// ================================================================================================
+#[test]
+fn inlined_procedures() {
+    let source = "
+    proc.square
+        dup mul
+    end
+    begin
+        push.3 exec.square
+        push.1 add exec.square
+    end";
+    let program = super::compile(source).unwrap();
+
+    let expected = super::compile("begin push.3 dup mul push.1 add dup mul end").unwrap();
+    assert_eq!(expected.hash(), program.hash());
+    assert_eq!(format!("{:?}", expected), format!("{:?}", program));
+}
+
+#[test]
+fn nested_procedures() {
+    // procedures can contain blocks and execute previously defined procedures
+    let source = "
+    proc.square
+        dup mul
+    end
+    proc.square_if
+        if.true
+            exec.square
+        else
+            push.1
+        end
+    end
+    begin
+        push.3 read exec.square_if
+        repeat.2
+            exec.square
+        end
+    end";
+    let program = super::compile(source).unwrap();
+
+    let expected = "
+    begin
+        push.3 read if.true dup mul else push.1 end
+        repeat.2 dup mul end
+    end";
+    let expected = super::compile(expected).unwrap();
+    assert_eq!(expected.hash(), program.hash());
+}
+
+#[test]
+fn procedure_errors() {
+    let source = "proc.foo\n  dup\nend\nproc.foo\n  mul\nend\nbegin exec.foo end";
+    let err = super::compile(source).unwrap_err();
+    assert_eq!((4, 1), (err.line(), err.column()));
+    assert_eq!("procedure 'foo' is already defined", err.message());
+
+    let source = "proc.foo\n  exec.bar\nend\nbegin exec.foo end";
+    let err = super::compile(source).unwrap_err();
+    assert_eq!((2, 3), (err.line(), err.column()));
+    assert_eq!("procedure 'bar' is not defined", err.message());
+
+    let source = "proc.foo\n  if.true dup end\n";
+    let err = super::compile(source).unwrap_err();
+    assert_eq!((1, 1), (err.line(), err.column()));
+    assert_eq!("proc without matching end", err.message());
+
+    let source = "begin\n  proc.foo dup end\n  push.1\nend";
+    let err = super::compile(source).unwrap_err();
+    assert_eq!((2, 3), (err.line(), err.column()));
+
+    let err = super::compile("proc.1foo dup end begin push.1 end").unwrap_err();
+    assert_eq!("malformed instruction proc: parameter '1foo' is invalid", err.message());
+
+    // errors in procedure bodies are reported at their position in the procedure
+    let source = "proc.foo\n  dup9\nend\nbegin\n  exec.foo\nend";
+    let err = super::compile(source).unwrap_err();
+    assert_eq!((2, 3), (err.line(), err.column()));
+    assert_eq!("dup9", err.token());
+}
+
+// CONSTANTS
+// ================================================================================================
+#[test]
+fn constants() {
+    let source = "
+    const.KEY=0x1f2e3d4c5b6a79881f2e3d4c5b6a7988
+    const.COUNT=3
+    proc.add_key
+        push.KEY add
+    end
+    begin
+        push.COUNT
+        repeat.COUNT
+            exec.add_key
+        end
+        dup.0x2
+    end";
+    let program = super::compile(source).unwrap();
+
+    let expected = "
+    begin
+        push.3
+        repeat.3
+            push.41446156801443023914885913451812256136 add
+        end
+        dup.2
+    end";
+    let expected = super::compile(expected).unwrap();
+    assert_eq!(expected.hash(), program.hash());
+    assert_eq!(format!("{:?}", expected), format!("{:?}", program));
+}
+
+#[test]
+fn constant_errors() {
+    let source = "const.A=1\nconst.B=2\nconst.A=3\nbegin push.A end";
+    let err = super::compile(source).unwrap_err();
+    assert_eq!((3, 1), (err.line(), err.column()));
+    assert_eq!("constant 'A' is already defined", err.message());
+
+    // constants must be defined before they are used
+    let source = "proc.foo\n  push.A\nend\nconst.A=1\nbegin exec.foo end";
+    let err = super::compile(source).unwrap_err();
+    assert_eq!((2, 3), (err.line(), err.column()));
+    assert_eq!("constant 'A' is not defined", err.message());
+
+    let source = "begin\n  push.1\n  push.A\nend";
+    let err = super::compile(source).unwrap_err();
+    assert_eq!((3, 3), (err.line(), err.column()));
+    assert_eq!("push.A", err.token());
+
+    let source = "begin\n  push.1\n  const.A=1\nend";
+    let err = super::compile(source).unwrap_err();
+    assert_eq!((3, 3), (err.line(), err.column()));
+
+    assert!(super::compile("const.a=1 begin push.1 end").is_err());
+    assert!(super::compile("const.A begin push.1 end").is_err());
+    assert!(super::compile("const.A=0xzz begin push.1 end").is_err());
+}
+
+// COMMENTS
+// ================================================================================================
+#[test]
+fn line_comments() {
+    let source = "
+    # computes 2 * 3 + 1 if the top of the stack is 1,
+    # and keeps the next item otherwise
+    begin
+        push.2 push.3   # push operands
+        mul             // multiply them
+
+        // add 1 if the condition holds
+        if.true
+            # condition is true
+            push.1 add  # increment
+        else
+            // condition is false
+            drop
+        end
+        while.true      // loop on the top value
+            push.0      # exit after the first iteration
+        end
+    end    // end of program";
+
+    let expected = "begin push.2 push.3 mul if.true push.1 add else drop end while.true push.0 end end";
+    let program = super::compile(source).unwrap();
+    assert_eq!(super::compile(expected).unwrap().hash(), program.hash());
+    assert_eq!(format!("{:?}", super::compile(expected).unwrap()), format!("{:?}", program));
+}
+
+#[test]
+fn comment_errors() {
+    // errors are reported at their position in the commented source
+    let source = "begin # start\n  push.1 // one\n  # dup9\n  dup9 add\nend";
+    let err = super::compile(source).unwrap_err();
+    assert_eq!((4, 3), (err.line(), err.column()));
+
+    // a program consisting only of comments is empty
+    let err = super::compile("# begin push.1 end").unwrap_err();
+    assert_eq!("a program must contain at least one instruction", err.message());
+}
+
+// INSTRUCTIONS
+// ================================================================================================
+#[test]
+fn drop_expansion() {
+    // values are dropped using the fewest DROP and DROP4 operations
+    let program = super::compile("begin drop.3 drop.14 end").unwrap();
+
+    let expected = "\
+        begin dup drop4 drop drop drop4 drop4 drop4 \
+        noop noop noop noop noop noop noop end";
+
+    assert_eq!(expected, format!("{:?}", program));
+
+    let err = super::compile("begin drop.17 end").unwrap_err();
+    assert_eq!("malformed instruction drop: parameter 17 is invalid; value must be between 1 and 16", err.message());
+}
+
+#[test]
+fn movement_depth_limit() {
+    // items deeper than 8 cannot be reached, and so the assembler rejects depths up to 16 explicitly
+    for &n in [9, 12, 16].iter() {
+        let err = super::compile(&format!("begin movup.{} end", n)).unwrap_err();
+        assert_eq!(format!("malformed instruction movup: parameter {} is invalid; items deeper than 8 \
+            cannot be moved without dropping the items above them", n), *err.message());
+
+        let err = super::compile(&format!("begin movdn.{} end", n)).unwrap_err();
+        assert_eq!(format!("malformed instruction movdn: parameter {} is invalid; items deeper than 8 \
+            cannot be moved without dropping the items above them", n), *err.message());
+    }
+
+    let err = super::compile("begin movup.17 end").unwrap_err();
+    assert_eq!("malformed instruction movup: parameter 17 is invalid; allowed values are: [2, 3, 4, 5, 6, 7, 8]", err.message());
+}
+
+#[test]
+fn exp_expansion() {
+    // exponent bits are consumed by a fixed number of EXPACC operations
+    let program = super::compile("begin exp.2 end").unwrap();
+
+    let expected = "\
+        begin swap pad2 not dup roll4 pad2 drop expacc.2 expacc drop drop swap2 asserteq swap \
+        drop noop noop noop noop noop noop noop noop noop noop noop noop noop noop noop end";
+
+    assert_eq!(expected, format!("{:?}", program));
+
+    let err = super::compile("begin exp.129 end").unwrap_err();
+    assert_eq!("malformed instruction exp: parameter 129 is invalid; value must be between 1 and 128", err.message());
+}
+
+#[test]
+fn align_expansion() {
+    // the next instruction is placed on the requested step; nothing is emitted if the
+    // instruction is already aligned, and spans of nested blocks always start at step 0
+    let program = super::compile("begin align.5 read align.6 add block read align.3 add end end").unwrap();
+
+    let expected = "\
+        begin noop noop noop noop read add noop noop noop noop noop noop noop \
+        noop block read noop noop add noop noop noop noop noop noop noop \
+        noop noop noop noop end end";
+
+    assert_eq!(expected, format!("{:?}", program));
+
+    // aligning to an earlier step wraps around to the next cycle
+    let program = super::compile("begin read read align.1 add end").unwrap();
+    let expected = "\
+        begin read read noop noop noop noop noop noop noop noop noop noop noop noop noop \
+        noop add noop noop noop noop noop noop noop noop noop noop noop noop noop end";
+
+    assert_eq!(expected, format!("{:?}", program));
+
+    let err = super::compile("begin align.16 end").unwrap_err();
+    assert_eq!("malformed instruction align: parameter 16 is invalid; value must be between 0 and 15", err.message());
+}
+
+#[test]
+fn immediate_expansion() {
+    // immediate values are pushed onto the stack with the usual PUSH alignment
+    let program = super::compile("begin read add.5 mul.3 end").unwrap();
+    assert_eq!(program.hash(), super::compile("begin read push.5 add push.3 mul end").unwrap().hash());
+
+    // subtraction pushes the additive inverse of the immediate value
+    let program = super::compile("begin sub.1 end").unwrap();
+    let expected = format!("push.{} add", crate::math::field::neg(1));
+    assert_eq!(program.hash(), super::compile(&format!("begin {} end", expected)).unwrap().hash());
+
+    let modulus = crate::math::field::MODULUS;
+    let err = super::compile(&format!("begin add.{} end", modulus)).unwrap_err();
+    assert_eq!(format!("malformed instruction add: parameter value must be smaller than {}", modulus), *err.message());
+}
+
+#[test]
+fn constant_expressions() {
+    use crate::math::field;
+
+    // expressions are evaluated in the field, and result in the same program as literals
+    let cases = [
+        ("2^64-1",          u64::MAX as u128),
+        ("2^64-2^32+1",     (1u128 << 64) - (1 << 32) + 1),
+        ("3*0x10+2",        50),
+        ("-1",              field::neg(1)),
+        ("p-1",             field::neg(1)),
+        ("p",               0),
+        ("--5",             5),
+        ("2*(3+4)^2",       98),
+        ("-(2^127)*2",      field::neg(field::mul(1 << 127, 2))),
+    ];
+    for &(expr, value) in cases.iter() {
+        let program = super::compile(&format!("begin push.{} end", expr)).unwrap();
+        let expected = super::compile(&format!("begin push.{} end", value)).unwrap();
+        assert_eq!(expected.hash(), program.hash(), "expression {} was not evaluated to {}", expr, value);
+    }
+
+    // expressions can also be used as immediate values of other instructions
+    let program = super::compile("begin add.-1 end").unwrap();
+    assert_eq!(program.hash(), super::compile("begin sub.1 end").unwrap().hash());
+
+    // malformed expressions are reported with their positions
+    let err = super::compile("begin\n    push.2^64-1 push.(2+3 end").unwrap_err();
+    assert_eq!("malformed instruction push: parameter '(2+3' is invalid; unexpected end of expression", err.message());
+    assert_eq!((2, 17), (err.line(), err.column()));
+
+    let err = super::compile("begin push.2+y end").unwrap_err();
+    assert_eq!("malformed instruction push: parameter '2+y' is invalid; unexpected character 'y' at position 3", err.message());
+
+    let err = super::compile(&format!("begin push.1+{} end", field::MODULUS)).unwrap_err();
+    assert_eq!(format!("malformed instruction push: parameter '1+{}' is invalid; value at position 3 must be smaller than {}",
+        field::MODULUS, field::MODULUS), *err.message());
+
+    let err = super::compile("begin push.2^340282366920938463463374607431768211456 end").unwrap_err();
+    assert_eq!("malformed instruction push: parameter '2^340282366920938463463374607431768211456' is invalid; \
+        value at position 3 does not fit into 128 bits", err.message());
+}
+
+// DECOMPILER
+// ================================================================================================
+#[test]
+fn decompile_round_trip() {
+    let sources = [
+        "begin push.1 push.2 add end",
+        "begin align.5 read align.3 push.1 align.0 add end",
+        "begin noop assert.eq assert.z read.a read.ab read.4 dup.1 dup.2 dup.3 dup.4 dup.8 pick.1 pick.2 pick.3 end",
+        "begin pad.1 pad.2 pad.3 pad.4 pad.5 pad.6 pad.7 pad.8 drop.1 drop.2 drop.3 drop.4 end",
+        "begin drop.9 drop.10 drop.11 drop.12 drop.13 drop.14 drop.15 drop.16 end",
+        "begin drop.5 drop.6 drop.7 drop.8 swap.1 swap.2 swap.4 swapw roll.4 roll.8 choose.1 choose.2 cswap.2 cswap.4 end",
+        "begin add sub mul div neg inv not and or eq ne iszero assert end",
+        "begin add.5 sub.1 mul.3 sub.0 end",
+        "begin movup.2 movup.3 movup.5 movup.6 movup.7 movdn.3 movdn.4 movdn.5 movdn.6 movdn.7 movdn.8 end",
+        "begin push.3 gt.8 push.0x1f lt.16 rc.32 isodd.8 unpack.3 end",
+        "begin exp.1 exp.16 exp end",
+        "begin u32.add u32.sub push.1 u32.mul u32.add end",
+        "begin u32.and u32.or push.1 u32.xor end",
+        "begin u32.divmod push.3 u32.divmod end",
+        "begin bits.1 push.5 bits.3 bits.30 end",
+        "begin select.2 push.1 select.4 select.4 end",
+        "begin u32.rotr.7 u32.shr.3 push.1 u32.shl.0 u32.rotl.31 end",
+        "begin hash.1 hash.2 hash.3 push.7 hash.4 smpath.3 pmpath.4 end",
+        "begin mtree.verify.2 push.1 smpath.2 mtree.verify.3 end",
+        "begin push.1 expr{ (in0 + in1) * in0 } noop noop noop noop push.2 end",
+        "begin read if.true push.1 end end",
+        "begin read if.true push.1 dup mul else push.2 while.true read end noop end end",
+        "begin block block push.1 end end push.2 block noop end repeat.3 add end end",
+        "begin read while.true if.true noop else block push.3 end end read end end",
+    ];
+
+    for source in sources.iter() {
+        let program = super::compile(source).unwrap();
+        let decompiled = super::compile(&super::decompile(&program)).unwrap();
+        assert_eq!(program.hash(), decompiled.hash(), "round trip failed for: {}", source);
+    }
+
+    let source = format!("begin {} end", crate::stdlib::ots_verify(32));
+    let program = super::compile(&source).unwrap();
+    let decompiled = super::compile(&program.to_assembly()).unwrap();
+    assert_eq!(program.hash(), decompiled.hash());
+}
+
+#[test]
+fn decompile_format() {
+    let program = super::compile("begin push.3 read if.true dup mul else push.2 end while.true read end end").unwrap();
+
+    let expected = "\
+begin
+    push.3
+    read.a
+    if.true
+        dup.1
+        mul
+    else
+        push.2
+    end
+    while.true
+        read.a
+    end
+end";
+    assert_eq!(expected, super::decompile(&program));
+}
+
+// ERRORS
+// ================================================================================================
+#[test]
+fn unknown_instruction() {
+    let source = "begin\n    push.1 push.2\n    add dup9\nend";
+    let err = super::compile(source).unwrap_err();
+    assert_eq!(3, err.line());
+    assert_eq!(9, err.column());
+    assert_eq!("dup9", err.token());
+    assert_eq!("assembly error at line 3, column 9: unknown instruction 'dup9'", err.to_string());
+}
+
+#[test]
+fn missing_parameter() {
+    let source = "begin\n  push.3\n\n  \tpush mul\nend";
+    let err = super::compile(source).unwrap_err();
+    assert_eq!((4, 4), (err.line(), err.column()));
+    assert_eq!("push", err.token());
+    assert_eq!("malformed instruction push: parameter is missing", err.message());
+}
+
+#[test]
+fn unmatched_blocks() {
+    // else outside of an if block
+    let source = "begin\n  push.1\n  block\n    push.2\n  else\n    push.3\n  end\nend";
+    let err = super::compile(source).unwrap_err();
+    assert_eq!((5, 3), (err.line(), err.column()));
+    assert_eq!("else without matching if", err.message());
+
+    // block without matching end is reported at its head; the last end of the program closes
+    // the innermost block, and so the outermost block is the one left unmatched
+    let source = "begin\n  push.1\n  if.true\n    push.2\n  else\n    push.3\nend";
+    let err = super::compile(source).unwrap_err();
+    assert_eq!((1, 1), (err.line(), err.column()));
+    assert_eq!("block without matching end", err.message());
+
+    // dangling end is reported at the first instruction after program end
+    let source = "begin\n  push.1\nend\nend";
+    let err = super::compile(source).unwrap_err();
+    assert_eq!((4, 1), (err.line(), err.column()));
+    assert_eq!("end", err.token());
+    assert_eq!("dangling instructions after program end", err.message());
+}
+
+#[test]
+fn invalid_program_bounds() {
+    let err = super::compile("begin push.1 push.2").unwrap_err();
+    assert_eq!((1, 14), (err.line(), err.column()));
+    assert_eq!("push.2", err.token());
+
+    let err = super::compile("  \n  ").unwrap_err();
+    assert_eq!((1, 1), (err.line(), err.column()));
+    assert_eq!("", err.token());
+}
+
+#[test]
+fn invalid_expression() {
+    let source = "begin\n  push.1\n  expr{ a + }\nend";
+    let err = super::compile(source).unwrap_err();
+    assert_eq!((3, 3), (err.line(), err.column()));
+    assert_eq!("expr{", err.token());
+}
+
+// LIMIT CHECKS
+// ================================================================================================
+
+#[test]
+fn compile_checked() {
+    let source = "begin\n    push.1 add\nend";
+    assert!(super::compile_checked(source, 1, None).is_ok());
+
+    let err = super::compile_checked(source, 0, None).unwrap_err();
+    assert_eq!("stack underflow: the program requires at least 1 inputs, but 0 were provided", err.message());
+    assert_eq!((1, 1), (err.line(), err.column()));
+
+    // loops which grow the stack are rejected only if the number of iterations is bounded
+    let source = "proc.foo push.1 end begin read while.true exec.foo read end end";
+    assert!(super::compile_checked(source, 0, None).is_ok());
+    let err = super::compile_checked(source, 0, Some(&super::LoopBounds::new(0, 40))).unwrap_err();
+    assert_eq!("stack overflow: stack depth could reach 41, but cannot exceed 32", err.message());
+    assert_eq!((1, 21), (err.line(), err.column()));
+}
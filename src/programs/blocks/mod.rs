@@ -1,4 +1,5 @@
-use std::collections::HashMap;
+use std::collections::{ BTreeMap, HashMap };
+use serde::{ Serialize, Deserialize };
 use super::{ OpCode, OpHint, hash_seq, hash_op, BASE_CYCLE_LENGTH };
 
 #[cfg(test)]
@@ -8,6 +9,7 @@ mod tests;
 // ================================================================================================
 const BLOCK_SUFFIX: [u8; 1] = [OpCode::Noop as u8];
 const BLOCK_SUFFIX_OFFSET: usize = BASE_CYCLE_LENGTH - 1;
+const PUSH_OP_ALIGNMENT: usize = 8;
 
 const LOOP_SKIP_BLOCK: [OpCode; 15] = [
     OpCode::Not,  OpCode::Assert, OpCode::Noop, OpCode::Noop,
@@ -26,7 +28,7 @@ const LOOP_BLOCK_SUFFIX: [u8; 16] = [
 // TYPES AND INTERFACES
 // ================================================================================================
 
-#[derive(Clone)]
+#[derive(Clone, Serialize, Deserialize)]
 pub enum ProgramBlock {
     Span(Span),
     Group(Group),
@@ -34,27 +36,38 @@ pub enum ProgramBlock {
     Loop(Loop),
 }
 
-#[derive(Clone)]
+#[derive(Clone, Serialize, Deserialize)]
 pub struct Span {
     op_codes    : Vec<OpCode>,
-    op_hints    : HashMap<usize, OpHint>,
+    op_hints    : BTreeMap<usize, OpHint>,
 }
 
-#[derive(Clone)]
+/// Errors which can occur when a Span is built from instructions and hints.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum SpanError {
+    InvalidLength { length: usize },
+    MisalignedPush { index: usize },
+    MissingPushValue { index: usize },
+    UnexpectedHint { index: usize, op: OpCode },
+    HintIndexOutOfRange { index: usize, length: usize },
+}
+
+#[derive(Clone, Serialize, Deserialize)]
 pub struct Group {
     body        : Vec<ProgramBlock>,
 }
 
-#[derive(Clone)]
+#[derive(Clone, Serialize, Deserialize)]
 pub struct Switch {
     t_branch    : Vec<ProgramBlock>,
     f_branch    : Vec<ProgramBlock>,
 }
 
-#[derive(Clone)]
+#[derive(Clone, Serialize, Deserialize)]
 pub struct Loop {
     body        : Vec<ProgramBlock>,
     skip        : Vec<ProgramBlock>,
+    max_iter    : Option<usize>,
 }
 
 // PROGRAM BLOCK IMPLEMENTATION
@@ -69,6 +82,19 @@ impl ProgramBlock {
         };
     }
 
+    /// Returns the pair of hashes (v0, v1) which is merged into the hash of the parent sequence
+    /// when this block is executed; these are the values the decoder binds when it exits the
+    /// block. Span blocks are absorbed into the parent sequence instruction by instruction, and
+    /// so for a Span block this returns the hash of a Group block containing only this Span.
+    pub fn hash(&self) -> (u128, u128) {
+        return match self {
+            ProgramBlock::Span(_)       => (hash_seq(&vec![self.clone()], &BLOCK_SUFFIX, BLOCK_SUFFIX_OFFSET), 0),
+            ProgramBlock::Group(block)  => block.get_hash(),
+            ProgramBlock::Switch(block) => block.get_hash(),
+            ProgramBlock::Loop(block)   => block.get_hash(),
+        };
+    }
+
 }
 
 impl std::fmt::Debug for ProgramBlock {
@@ -87,44 +113,44 @@ impl std::fmt::Debug for ProgramBlock {
 // ================================================================================================
 impl Span {
 
-    pub fn new(instructions: Vec<OpCode>, hints: HashMap<usize, OpHint>) -> Span {
-        let alignment = instructions.len() % BASE_CYCLE_LENGTH;
-        assert!(alignment == BASE_CYCLE_LENGTH - 1,
-            "invalid number of instructions: expected one less than a multiple of {}, but was {}",
-            BASE_CYCLE_LENGTH, instructions.len());
-
-        // make sure all instructions are valid
-        for i in 0..instructions.len() {
-            let op_code = instructions[i];
-            if op_code == OpCode::Push {
-                assert!(i % 8 == 0, "PUSH is not allowed on step {}, must be on step which is a multiple of 8", i);
-                let hint = hints.get(&i);
-                assert!(hint.is_some(), "invalid PUSH operation on step {}: operation value is missing", i);
-                match hint.unwrap() {
-                    OpHint::PushValue(_) => (),
-                    _ => panic!("invalid PUSH operation on step {}: operation value is of wrong type", i)
-                }
-            }
-        }
-
-        // make sure all hints are within bounds
-        for &step in hints.keys() {
-            assert!(step < instructions.len(), "hint out of bounds: step must be smaller than {} but is {}",
-                instructions.len(), step);
-        }
+    /// Returns a new Span; the number of instructions must be one less than a multiple of 16,
+    /// PUSH operations must be on steps which are multiples of 8, every PUSH operation must have
+    /// a value hint, and all other hints must be accepted by the operations they are attached to.
+    pub fn new(instructions: Vec<OpCode>, hints: HashMap<usize, OpHint>) -> Result<Span, SpanError> {
+        // keeping hints in a BTreeMap makes the order of serialized hints deterministic
+        let hints: BTreeMap<usize, OpHint> = hints.into_iter().collect();
+        validate_span(&instructions, &hints)?;
 
-        return Span {
+        return Ok(Span {
             op_codes: instructions,
             op_hints: hints
-        };
+        });
+    }
+
+    /// Returns a new Span padded with NOOPs to the nearest valid number of instructions; hints
+    /// keep pointing to the same instructions, and so hints beyond the padded instructions are
+    /// still rejected. An empty list of instructions is padded to a span of 15 NOOPs.
+    pub fn new_padded(mut instructions: Vec<OpCode>, hints: HashMap<usize, OpHint>) -> Result<Span, SpanError> {
+        let pad_length = BASE_CYCLE_LENGTH - (instructions.len() % BASE_CYCLE_LENGTH) - 1;
+        instructions.resize(instructions.len() + pad_length, OpCode::Noop);
+        return Span::new(instructions, hints);
+    }
+
+    /// Makes sure the span satisfies all requirements enforced by `new()`; this is useful for
+    /// spans which were deserialized rather than built via `new()`.
+    pub fn validate(&self) -> Result<(), String> {
+        return validate_span(&self.op_codes, &self.op_hints).map_err(|err| err.to_string());
     }
 
     pub fn new_block(instructions: Vec<OpCode>) -> ProgramBlock {
-        return ProgramBlock::Span(Span::new(instructions, HashMap::new()));
+        return ProgramBlock::Span(Span::from_instructions(instructions));
     }
 
     pub fn from_instructions(instructions: Vec<OpCode>) -> Span {
-        return Span::new(instructions, HashMap::new());
+        return match Span::new(instructions, HashMap::new()) {
+            Ok(span) => span,
+            Err(err) => panic!("{}", err),
+        };
     }
 
     pub fn length(&self) -> usize {
@@ -168,13 +194,13 @@ impl Span {
 
         // merge hints
         let offset = span1.length() + 1;
-        let mut new_hints = span1.op_hints.clone();
+        let mut new_hints: HashMap<usize, OpHint> = span1.op_hints.clone().into_iter().collect();
         for (step, &hint) in &span2.op_hints {
             new_hints.insert(step + offset, hint);
         }
 
-        // build and return a new Span
-        return Span::new(new_op_codes, new_hints);
+        // build and return a new Span; hints of both spans have already been validated
+        return Span::new(new_op_codes, new_hints).unwrap();
     }
 }
 
@@ -191,6 +217,42 @@ impl std::fmt::Debug for Span {
     }
 }
 
+// SPAN ERROR IMPLEMENTATION
+// ================================================================================================
+impl SpanError {
+
+    /// Returns the index of the instruction which caused the error; for errors caused by an
+    /// invalid number of instructions, this is the number of instructions.
+    pub fn index(&self) -> usize {
+        return match self {
+            SpanError::InvalidLength { length }             => *length,
+            SpanError::MisalignedPush { index }             => *index,
+            SpanError::MissingPushValue { index }           => *index,
+            SpanError::UnexpectedHint { index, .. }         => *index,
+            SpanError::HintIndexOutOfRange { index, .. }    => *index,
+        };
+    }
+}
+
+impl std::fmt::Display for SpanError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        return match self {
+            SpanError::InvalidLength { length } =>
+                write!(f, "invalid number of instructions: expected one less than a multiple of {}, but was {}",
+                    BASE_CYCLE_LENGTH, length),
+            SpanError::MisalignedPush { index } =>
+                write!(f, "PUSH is not allowed on step {}, must be on step which is a multiple of {}",
+                    index, PUSH_OP_ALIGNMENT),
+            SpanError::MissingPushValue { index } =>
+                write!(f, "PUSH operation on step {} is missing a value", index),
+            SpanError::UnexpectedHint { index, op } =>
+                write!(f, "hint on step {} is not valid for {} operation", index, op.to_string().to_uppercase()),
+            SpanError::HintIndexOutOfRange { index, length } =>
+                write!(f, "hint out of bounds: step must be smaller than {} but is {}", length, index),
+        };
+    }
+}
+
 // GROUP IMPLEMENTATION
 // ================================================================================================
 impl Group {
@@ -292,13 +354,26 @@ impl Loop {
         let skip_block = Span::from_instructions(LOOP_SKIP_BLOCK.to_vec());
         let skip = vec![ProgramBlock::Span(skip_block)];
 
-        return Loop { body, skip };
+        return Loop { body, skip, max_iter: None };
     }
 
     pub fn new_block(body: Vec<ProgramBlock>) -> ProgramBlock {
         return ProgramBlock::Loop(Loop::new(body));
     }
 
+    /// Returns a loop which is declared to execute its body at most `max_iterations` times;
+    /// the declared cap is enforced during execution, but it does not affect the hash of the loop.
+    pub fn new_bounded_block(body: Vec<ProgramBlock>, max_iterations: usize) -> ProgramBlock {
+        let mut block = Loop::new(body);
+        block.max_iter = Some(max_iterations);
+        return ProgramBlock::Loop(block);
+    }
+
+    /// Returns the declared max number of iterations of the loop, if there is one.
+    pub fn max_iterations(&self) -> Option<usize> {
+        return self.max_iter;
+    }
+
     pub fn body(&self) -> &[ProgramBlock] {
         return &self.body;
     }
@@ -328,7 +403,10 @@ impl Loop {
 
 impl std::fmt::Debug for Loop {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        write!(f, "while ")?;
+        match self.max_iter {
+            Some(n) => write!(f, "while.max={} ", n)?,
+            None    => write!(f, "while ")?,
+        }
         for block in self.body.iter() {
             write!(f, "{:?} ", block)?;
         }
@@ -338,6 +416,47 @@ impl std::fmt::Debug for Loop {
 
 // HELPER FUNCTIONS
 // ================================================================================================
+
+/// Makes sure the number of instructions is one less than a multiple of 16, and PUSH operations
+/// are aligned on steps which are multiples of 8; then makes sure hints are valid.
+fn validate_span(instructions: &[OpCode], hints: &BTreeMap<usize, OpHint>) -> Result<(), SpanError> {
+    if instructions.len() % BASE_CYCLE_LENGTH != BASE_CYCLE_LENGTH - 1 {
+        return Err(SpanError::InvalidLength { length: instructions.len() });
+    }
+    for i in 0..instructions.len() {
+        if instructions[i] == OpCode::Push && i % PUSH_OP_ALIGNMENT != 0 {
+            return Err(SpanError::MisalignedPush { index: i });
+        }
+    }
+    return validate_hints(instructions, hints);
+}
+
+/// Makes sure every PUSH operation has a value hint, and all hints are within bounds and are
+/// attached to operations which accept them; hints are checked in the order of their steps to
+/// make errors deterministic.
+fn validate_hints(instructions: &[OpCode], hints: &BTreeMap<usize, OpHint>) -> Result<(), SpanError> {
+    for i in 0..instructions.len() {
+        if instructions[i] == OpCode::Push {
+            match hints.get(&i) {
+                Some(OpHint::PushValue(_)) => (),
+                _ => return Err(SpanError::MissingPushValue { index: i }),
+            }
+        }
+    }
+
+    for (&step, hint) in hints.iter() {
+        if step >= instructions.len() {
+            return Err(SpanError::HintIndexOutOfRange { index: step, length: instructions.len() });
+        }
+        let op_code = instructions[step];
+        match hint.kind() {
+            Some(kind) if op_code.accepts_hint(kind) => (),
+            _ => return Err(SpanError::UnexpectedHint { index: step, op: op_code }),
+        }
+    }
+    return Ok(());
+}
+
 fn validate_block_list(blocks: &Vec<ProgramBlock>, starts_with: &[OpCode]) {
 
     assert!(blocks.len() > 0, "a sequence of blocks must contain at least one block");
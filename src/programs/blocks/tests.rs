@@ -1,4 +1,4 @@
-use super::{ OpCode, Span, HashMap, OpHint };
+use super::{ OpCode, Span, SpanError, HashMap, OpHint };
 
 #[test]
 fn span_hash() {
@@ -24,7 +24,7 @@ fn span_hash() {
         OpCode::Noop, OpCode::Noop, OpCode::Noop, OpCode::Noop,
         OpCode::Push, OpCode::Noop, OpCode::Noop, OpCode::Noop,
         OpCode::Noop, OpCode::Noop, OpCode::Noop
-    ], hints);
+    ], hints).unwrap();
 
     let hash = block.hash([0, 0, 0, 0]);
     assert_eq!([
@@ -40,11 +40,121 @@ fn span_hash() {
         OpCode::Noop, OpCode::Noop, OpCode::Noop, OpCode::Noop,
         OpCode::Push, OpCode::Noop, OpCode::Noop, OpCode::Noop,
         OpCode::Noop, OpCode::Noop, OpCode::Noop
-    ], hints);
+    ], hints).unwrap();
 
     let hash = block.hash([0, 0, 0, 0]);
     assert_eq!([
         238085520613464573032580920836572617149,  98362585914038709664139524327351111560,
         159064915881679512167348007665307977960, 152057468867502483682425300737565245134,
     ], hash);
-}
\ No newline at end of file
+}
+#[test]
+fn span_hint_validation() {
+    let instructions = vec![
+        OpCode::Begin, OpCode::Read,  OpCode::Eq,   OpCode::Noop,
+        OpCode::Noop,  OpCode::Noop,  OpCode::Noop, OpCode::Noop,
+        OpCode::Push,  OpCode::Read2, OpCode::Noop, OpCode::Noop,
+        OpCode::Noop,  OpCode::Noop,  OpCode::Noop
+    ];
+
+    // valid hints are accepted
+    let mut hints = HashMap::new();
+    hints.insert(1, OpHint::EqStart);
+    hints.insert(8, OpHint::PushValue(3));
+    hints.insert(9, OpHint::PmpathStart(4));
+    assert!(Span::new(instructions.clone(), hints.clone()).is_ok());
+
+    // PUSH operation without a value
+    let mut missing = hints.clone();
+    missing.remove(&8);
+    let result = Span::new(instructions.clone(), missing);
+    assert_eq!(Some(SpanError::MissingPushValue { index: 8 }), result.err());
+
+    // PUSH operation with a hint which is not a value
+    let mut wrong_kind = hints.clone();
+    wrong_kind.insert(8, OpHint::EqStart);
+    let result = Span::new(instructions.clone(), wrong_kind);
+    assert_eq!(Some(SpanError::MissingPushValue { index: 8 }), result.err());
+
+    // value hint attached to an operation other than PUSH
+    let mut unexpected = hints.clone();
+    unexpected.insert(2, OpHint::PushValue(5));
+    let result = Span::new(instructions.clone(), unexpected);
+    assert_eq!(Some(SpanError::UnexpectedHint { index: 2, op: OpCode::Eq }), result.err());
+
+    // hint of READ2 operation attached to READ operation
+    let mut unexpected = hints.clone();
+    unexpected.insert(1, OpHint::PmpathStart(4));
+    let result = Span::new(instructions.clone(), unexpected);
+    assert_eq!(Some(SpanError::UnexpectedHint { index: 1, op: OpCode::Read }), result.err());
+
+    // hint beyond the last instruction
+    let mut out_of_range = hints.clone();
+    out_of_range.insert(15, OpHint::EqStart);
+    let result = Span::new(instructions.clone(), out_of_range);
+    let err = result.err().unwrap();
+    assert_eq!(SpanError::HintIndexOutOfRange { index: 15, length: 15 }, err);
+    assert_eq!("hint out of bounds: step must be smaller than 15 but is 15", err.to_string());
+}
+
+#[test]
+fn span_length_validation() {
+    // one instruction short of or beyond a valid length
+    let err = Span::new(vec![OpCode::Noop; 14], HashMap::new()).err().unwrap();
+    assert_eq!(SpanError::InvalidLength { length: 14 }, err);
+    assert_eq!("invalid number of instructions: expected one less than a multiple of 16, but was 14", err.to_string());
+    let err = Span::new(vec![OpCode::Noop; 16], HashMap::new()).err().unwrap();
+    assert_eq!(SpanError::InvalidLength { length: 16 }, err);
+    assert!(Span::new(vec![OpCode::Noop; 31], HashMap::new()).is_ok());
+
+    // empty span
+    let err = Span::new(vec![], HashMap::new()).err().unwrap();
+    assert_eq!(SpanError::InvalidLength { length: 0 }, err);
+
+    // misaligned PUSH operation
+    let mut instructions = vec![OpCode::Noop; 15];
+    instructions[4] = OpCode::Push;
+    let mut hints = HashMap::new();
+    hints.insert(4, OpHint::PushValue(1));
+    let err = Span::new(instructions, hints).err().unwrap();
+    assert_eq!(SpanError::MisalignedPush { index: 4 }, err);
+    assert_eq!("PUSH is not allowed on step 4, must be on step which is a multiple of 8", err.to_string());
+}
+
+#[test]
+fn padded_span() {
+    // padded span has the same hash as a manually padded one
+    let mut hints = HashMap::new();
+    hints.insert(8, OpHint::PushValue(3));
+    let mut instructions = vec![OpCode::Add; 9];
+    instructions[8] = OpCode::Push;
+    let span = Span::new_padded(instructions.clone(), hints.clone()).unwrap();
+    assert_eq!(15, span.length());
+
+    let mut padded = instructions.clone();
+    padded.resize(15, OpCode::Noop);
+    let expected = Span::new(padded, hints.clone()).unwrap();
+    assert_eq!(expected.hash([0, 0, 0, 0]), span.hash([0, 0, 0, 0]));
+
+    // valid lengths are not padded; off-by-one lengths are padded to the next valid length
+    assert_eq!(15, Span::new_padded(vec![OpCode::Add; 15], HashMap::new()).unwrap().length());
+    assert_eq!(31, Span::new_padded(vec![OpCode::Add; 16], HashMap::new()).unwrap().length());
+    assert_eq!(31, Span::new_padded(vec![OpCode::Add; 14 + 16], HashMap::new()).unwrap().length());
+
+    // empty span is padded to a span of NOOPs
+    let span = Span::new_padded(vec![], HashMap::new()).unwrap();
+    let expected = Span::from_instructions(vec![OpCode::Noop; 15]);
+    assert_eq!(expected.hash([0, 0, 0, 0]), span.hash([0, 0, 0, 0]));
+
+    // hints pointing into the padding are attached to NOOPs
+    let mut hints = HashMap::new();
+    hints.insert(12, OpHint::EqStart);
+    let err = Span::new_padded(vec![OpCode::Add; 9], hints).err().unwrap();
+    assert_eq!(SpanError::UnexpectedHint { index: 12, op: OpCode::Noop }, err);
+
+    // hints pointing past the padded instructions are rejected
+    let mut hints = HashMap::new();
+    hints.insert(15, OpHint::EqStart);
+    let err = Span::new_padded(vec![OpCode::Add; 9], hints).err().unwrap();
+    assert_eq!(SpanError::HintIndexOutOfRange { index: 15, length: 15 }, err);
+}
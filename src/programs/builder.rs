@@ -0,0 +1,338 @@
+use std::collections::HashMap;
+use crate::processor::{ OpCode, OpHint };
+use super::{ Program, ProgramBlock, Span, Group, Switch, Loop, validate_blocks };
+
+// CONSTANTS
+// ================================================================================================
+const PUSH_OP_ALIGNMENT: usize = 8;
+
+// TYPES AND INTERFACES
+// ================================================================================================
+
+/// Builds programs from Rust code; the builder takes care of padding spans with NOOPs, aligning
+/// PUSH operations, and starting branches and loop bodies with the instructions expected by
+/// the VM. Programs produced by the builder have the same hashes as programs produced by the
+/// assembler from equivalent source code.
+///
+/// Errors encountered while the program is being built are reported by `build()`.
+pub struct ProgramBuilder {
+    body        : Vec<Item>,
+    procedures  : HashMap<String, Vec<Item>>,
+    error       : Option<String>,
+}
+
+#[derive(Clone)]
+enum Item {
+    Op(OpCode, OpHint),
+    Group(Vec<Item>),
+    Switch(Vec<Item>, Vec<Item>),
+    Loop(Vec<Item>),
+}
+
+// PROGRAM BUILDER IMPLEMENTATION
+// ================================================================================================
+impl ProgramBuilder {
+
+    /// Returns a new builder for a program which starts with a BEGIN operation.
+    pub fn new() -> ProgramBuilder {
+        return ProgramBuilder { body: Vec::new(), procedures: HashMap::new(), error: None };
+    }
+
+    /// Appends a single operation to the program.
+    pub fn op(&mut self, op_code: OpCode) -> &mut ProgramBuilder {
+        self.body.push(Item::Op(op_code, OpHint::None));
+        return self;
+    }
+
+    /// Appends a sequence of operations to the program.
+    pub fn ops(&mut self, op_codes: &[OpCode]) -> &mut ProgramBuilder {
+        for &op_code in op_codes {
+            self.op(op_code);
+        }
+        return self;
+    }
+
+    /// Appends a single operation together with a hint for the operation.
+    pub fn op_with_hint(&mut self, op_code: OpCode, hint: OpHint) -> &mut ProgramBuilder {
+        self.body.push(Item::Op(op_code, hint));
+        return self;
+    }
+
+    /// Appends a PUSH operation for the specified value; the operation is moved to the next
+    /// step which is a multiple of 8 by inserting NOOPs before it.
+    pub fn push(&mut self, value: u128) -> &mut ProgramBuilder {
+        return self.op_with_hint(OpCode::Push, OpHint::PushValue(value));
+    }
+
+    /// Appends a nested block of instructions to the program.
+    pub fn block<F>(&mut self, body: F) -> &mut ProgramBuilder
+        where F: FnOnce(&mut ProgramBuilder) -> &mut ProgramBuilder
+    {
+        let body = self.build_body(body);
+        self.body.push(Item::Group(body));
+        return self;
+    }
+
+    /// Appends an if-then-else block to the program; the block pops the top item from the stack
+    /// and executes `t_branch` if the item is 1, and `f_branch` if the item is 0. The false
+    /// branch can be left empty.
+    pub fn if_else<T, F>(&mut self, t_branch: T, f_branch: F) -> &mut ProgramBuilder
+        where T: FnOnce(&mut ProgramBuilder) -> &mut ProgramBuilder,
+              F: FnOnce(&mut ProgramBuilder) -> &mut ProgramBuilder
+    {
+        let t_branch = self.build_body(t_branch);
+        let f_branch = self.build_body(f_branch);
+        self.body.push(Item::Switch(t_branch, f_branch));
+        return self;
+    }
+
+    /// Appends a loop to the program; the loop pops the top item from the stack and executes
+    /// the body for as long as the popped item is 1.
+    pub fn while_loop<F>(&mut self, body: F) -> &mut ProgramBuilder
+        where F: FnOnce(&mut ProgramBuilder) -> &mut ProgramBuilder
+    {
+        let body = self.build_body(body);
+        self.body.push(Item::Loop(body));
+        return self;
+    }
+
+    /// Defines a procedure with the specified name; the body of the procedure is inlined into
+    /// the program every time the procedure is executed via `exec()`. Procedures can be defined
+    /// anywhere in the program, but must be defined before they are executed.
+    pub fn proc<F>(&mut self, name: &str, body: F) -> &mut ProgramBuilder
+        where F: FnOnce(&mut ProgramBuilder) -> &mut ProgramBuilder
+    {
+        let body = self.build_body(body);
+        if self.procedures.contains_key(name) {
+            self.set_error(format!("procedure '{}' is already defined", name));
+        }
+        else if body.len() == 0 {
+            self.set_error(format!("procedure '{}' must contain at least one instruction", name));
+        }
+        else {
+            self.procedures.insert(String::from(name), body);
+        }
+        return self;
+    }
+
+    /// Inlines the body of the procedure with the specified name into the program.
+    pub fn exec(&mut self, name: &str) -> &mut ProgramBuilder {
+        match self.procedures.get(name) {
+            Some(body) => {
+                let body = body.clone();
+                self.body.extend(body);
+            },
+            None => self.set_error(format!("procedure '{}' is not defined", name)),
+        }
+        return self;
+    }
+
+    /// Builds the program; returns an error if any of the blocks is empty, if blocks or loops
+    /// are nested deeper than `MAX_CONTEXT_DEPTH` or `MAX_LOOP_DEPTH`, or if any of the hints
+    /// is not valid for the operation it is attached to.
+    pub fn build(&self) -> Result<Program, String> {
+        if let Some(err) = &self.error {
+            return Err(err.clone());
+        }
+
+        let root = build_blocks(&self.body, vec![OpCode::Begin])?;
+        validate_blocks(&root, &[OpCode::Begin], 1, 0)?;
+        return Ok(Program::new(Group::new(root)));
+    }
+
+    // HELPER METHODS
+    // --------------------------------------------------------------------------------------------
+
+    /// Runs `body` against this builder with an empty list of items, and returns the items
+    /// appended by `body`; procedures and errors are shared with the rest of the program.
+    fn build_body<F>(&mut self, body: F) -> Vec<Item>
+        where F: FnOnce(&mut ProgramBuilder) -> &mut ProgramBuilder
+    {
+        let parent = std::mem::replace(&mut self.body, Vec::new());
+        body(self);
+        return std::mem::replace(&mut self.body, parent);
+    }
+
+    /// Records the error unless an error has already been recorded.
+    fn set_error(&mut self, err: String) {
+        if self.error.is_none() {
+            self.error = Some(err);
+        }
+    }
+}
+
+// HELPER FUNCTIONS
+// ================================================================================================
+
+/// Transforms a list of items into a sequence of program blocks; the first span of the sequence
+/// starts with `op_codes`.
+fn build_blocks(items: &[Item], mut op_codes: Vec<OpCode>) -> Result<Vec<ProgramBlock>, String> {
+    if items.len() == 0 {
+        return Err(String::from("a program block must contain at least one instruction"));
+    }
+
+    let mut blocks = Vec::new();
+    let mut op_hints = HashMap::new();
+
+    for item in items {
+        let block = match item {
+            Item::Op(op_code, hint) => {
+                // pad the span with NOOPs to make sure PUSH happens on steps which are multiples of 8
+                if *op_code == OpCode::Push {
+                    let pad_length = (PUSH_OP_ALIGNMENT - op_codes.len() % PUSH_OP_ALIGNMENT) % PUSH_OP_ALIGNMENT;
+                    op_codes.resize(op_codes.len() + pad_length, OpCode::Noop);
+                }
+                if *hint != OpHint::None {
+                    op_hints.insert(op_codes.len(), *hint);
+                }
+                op_codes.push(*op_code);
+                continue;
+            },
+            Item::Group(body) => Group::new_block(build_blocks(body, vec![])?),
+            Item::Switch(t_branch, f_branch) => {
+                let t_branch = build_blocks(t_branch, vec![OpCode::Assert])?;
+                let f_branch = if f_branch.len() == 0 {
+                    vec![build_span(vec![OpCode::Not, OpCode::Assert], HashMap::new())?]
+                }
+                else {
+                    build_blocks(f_branch, vec![OpCode::Not, OpCode::Assert])?
+                };
+                Switch::new_block(t_branch, f_branch)
+            },
+            Item::Loop(body) => Loop::new_block(build_blocks(body, vec![OpCode::Assert])?),
+        };
+
+        // a sequence of blocks must start with a span, and so the first span is added even
+        // if it is empty
+        if op_codes.len() > 0 || blocks.len() == 0 {
+            blocks.push(build_span(op_codes, op_hints)?);
+            op_codes = Vec::new();
+            op_hints = HashMap::new();
+        }
+        blocks.push(block);
+    }
+
+    if op_codes.len() > 0 {
+        blocks.push(build_span(op_codes, op_hints)?);
+    }
+    return Ok(blocks);
+}
+
+/// Builds a Span block from the instructions padded with NOOPs to the nearest valid length.
+fn build_span(op_codes: Vec<OpCode>, op_hints: HashMap<usize, OpHint>) -> Result<ProgramBlock, String> {
+    return match Span::new_padded(op_codes, op_hints) {
+        Ok(span) => Ok(ProgramBlock::Span(span)),
+        Err(err) => Err(err.to_string()),
+    };
+}
+
+// TESTS
+// ================================================================================================
+#[cfg(test)]
+mod tests {
+
+    use crate::{ assembly, OpCode, OpHint, MAX_CONTEXT_DEPTH, MAX_LOOP_DEPTH };
+    use super::ProgramBuilder;
+
+    #[test]
+    fn linear_program() {
+        let expected = assembly::compile("begin push.3 push.5 add read mul push.7 end").unwrap();
+        let program = ProgramBuilder::new()
+            .push(3).push(5).op(OpCode::Add)
+            .ops(&[OpCode::Read, OpCode::Mul])
+            .push(7)
+            .build().unwrap();
+        assert_eq!(expected.hash(), program.hash());
+
+        // hints are attached to the operations they were added with
+        let expected = assembly::compile("begin push.3 read eq end").unwrap();
+        let program = ProgramBuilder::new()
+            .push(3).op(OpCode::Read)
+            .op_with_hint(OpCode::Read, OpHint::EqStart).op(OpCode::Eq)
+            .build().unwrap();
+        assert_eq!(expected.hash(), program.hash());
+    }
+
+    #[test]
+    fn control_flow() {
+        let expected = assembly::compile("
+            begin
+                push.3 read
+                if.true
+                    add
+                    while.true
+                        push.2 mul read
+                    end
+                else
+                    mul
+                end
+                block push.1 end
+                if.true neg end
+            end").unwrap();
+
+        let program = ProgramBuilder::new()
+            .push(3).op(OpCode::Read)
+            .if_else(
+                |t| t.op(OpCode::Add).while_loop(|body| body.push(2).op(OpCode::Mul).op(OpCode::Read)),
+                |f| f.op(OpCode::Mul))
+            .block(|b| b.push(1))
+            .if_else(|t| t.op(OpCode::Neg), |f| f)
+            .build().unwrap();
+        assert_eq!(expected.hash(), program.hash());
+    }
+
+    #[test]
+    fn procedures() {
+        let expected = assembly::compile("
+            proc.foo push.3 add end
+            proc.bar exec.foo if.true mul end end
+            begin exec.bar exec.foo exec.bar end").unwrap();
+
+        let program = ProgramBuilder::new()
+            .proc("foo", |p| p.push(3).op(OpCode::Add))
+            .proc("bar", |p| p.exec("foo").if_else(|t| t.op(OpCode::Mul), |f| f))
+            .exec("bar").exec("foo").exec("bar")
+            .build().unwrap();
+        assert_eq!(expected.hash(), program.hash());
+
+        let err = ProgramBuilder::new().op(OpCode::Add).exec("foo").build().err().unwrap();
+        assert_eq!("procedure 'foo' is not defined", err);
+
+        let err = ProgramBuilder::new()
+            .proc("foo", |p| p.op(OpCode::Add)).proc("foo", |p| p.op(OpCode::Mul))
+            .exec("foo").build().err().unwrap();
+        assert_eq!("procedure 'foo' is already defined", err);
+    }
+
+    #[test]
+    fn build_errors() {
+        let err = ProgramBuilder::new().build().err().unwrap();
+        assert_eq!("a program block must contain at least one instruction", err);
+
+        let err = ProgramBuilder::new().op(OpCode::Read).while_loop(|body| body).build().err().unwrap();
+        assert_eq!("a program block must contain at least one instruction", err);
+
+        let err = ProgramBuilder::new().op_with_hint(OpCode::Add, OpHint::EqStart).build().err().unwrap();
+        assert_eq!("hint on step 1 is not valid for ADD operation", err);
+
+        // blocks nested deeper than the context stack allows are rejected
+        let err = nest_blocks(&mut ProgramBuilder::new(), MAX_CONTEXT_DEPTH).build().err().unwrap();
+        assert_eq!(format!("program blocks are nested deeper than {}", MAX_CONTEXT_DEPTH), err);
+        assert!(nest_blocks(&mut ProgramBuilder::new(), MAX_CONTEXT_DEPTH - 1).build().is_ok());
+
+        // loops nested deeper than the loop stack allows are rejected
+        let err = nest_loops(&mut ProgramBuilder::new(), MAX_LOOP_DEPTH + 1).build().err().unwrap();
+        assert_eq!(format!("loops are nested deeper than {}", MAX_LOOP_DEPTH), err);
+        assert!(nest_loops(&mut ProgramBuilder::new(), MAX_LOOP_DEPTH).build().is_ok());
+    }
+
+    fn nest_blocks(builder: &mut ProgramBuilder, depth: usize) -> &mut ProgramBuilder {
+        if depth == 0 { return builder.op(OpCode::Add); }
+        return builder.block(|b| nest_blocks(b, depth - 1));
+    }
+
+    fn nest_loops(builder: &mut ProgramBuilder, depth: usize) -> &mut ProgramBuilder {
+        if depth == 0 { return builder.op(OpCode::Add); }
+        return builder.while_loop(|body| nest_loops(body, depth - 1));
+    }
+}
@@ -1,6 +1,7 @@
-use crate::{ utils::sponge };
+use crate::{ utils::{ sponge, as_bytes } };
 use super::{
-    ProgramBlock, OpCode, BASE_CYCLE_LENGTH, SPONGE_WIDTH as STATE_WIDTH, HACC_NUM_ROUNDS,
+    ProgramBlock, OpCode, OpHint, BASE_CYCLE_LENGTH, SPONGE_WIDTH as STATE_WIDTH, HACC_NUM_ROUNDS,
+    PROGRAM_DIGEST_SIZE,
 };
 
 // CONSTANTS
@@ -8,9 +9,46 @@ use super::{
 pub const HACC_ROUND_OFFSET: usize = 1;
 pub const NOOP_VALUE: u8 = OpCode::Noop as u8;
 
+const GROUP_SUFFIX: [OpCode; 1] = [OpCode::Noop];
+const GROUP_SUFFIX_OFFSET: usize = BASE_CYCLE_LENGTH - 1;
+
+const LOOP_SUFFIX: [OpCode; 16] = [
+    OpCode::Not,  OpCode::Assert, OpCode::Noop, OpCode::Noop,
+    OpCode::Noop, OpCode::Noop,   OpCode::Noop, OpCode::Noop,
+    OpCode::Noop, OpCode::Noop,   OpCode::Noop, OpCode::Noop,
+    OpCode::Noop, OpCode::Noop,   OpCode::Noop, OpCode::Noop,
+];
+
 // PUBLIC FUNCTIONS
 // ================================================================================================
 
+/// Computes the hash of a program with the specified root block body, without building the
+/// program. The result is equal to `Program::new(Group::new(blocks)).hash()`, but it is derived
+/// directly from the rules which decoder constraints enforce:
+///
+/// 1. Operations of a Span block are absorbed into the sponge one per step; the step index
+///    within the block selects round constants, and PUSH operations absorb their value.
+/// 2. A Span block which follows a control block is preceded by a NOOP absorbed at step 15, so
+///    that the Span starts on a 16-cycle boundary.
+/// 3. A control block is reduced to a pair of hashes (v0, v1) and merged into the first element
+///    of the sponge state via 14 NOOP rounds of the accumulator (starting at round 1):
+///    * Group: (hash of the body, 0);
+///    * Switch: (hash of the true branch, hash of the false branch);
+///    * Loop: (hash of the body, hash of the skip block).
+/// 4. Every sequence ends with a suffix: a NOOP absorbed at step 15 for Group and Switch
+///    branches and for the skip block of a loop; NOT ASSERT and 14 NOOPs absorbed at steps
+///    0 - 15 for loop bodies. The hash of a sequence is the first element of the sponge state.
+/// 5. The root body is hashed as the body of a Group, and the result is merged into an empty
+///    parent via the accumulator; the first 2 elements of the resulting state are the hash.
+pub fn hash_blocks(blocks: &[ProgramBlock]) -> [u8; 32] {
+    let body_hash = hash_sequence(blocks, &GROUP_SUFFIX, GROUP_SUFFIX_OFFSET);
+    let state = hash_acc(0, body_hash, 0);
+
+    let mut hash_bytes = [0u8; 32];
+    hash_bytes.copy_from_slice(as_bytes(&state[..PROGRAM_DIGEST_SIZE]));
+    return hash_bytes;
+}
+
 /// Returns a hash of a sequence of program blocks.
 pub fn hash_seq(blocks: &Vec<ProgramBlock>, suffix: &[u8], suffix_offset: usize) -> u128 {
 
@@ -72,4 +110,49 @@ pub fn hash_acc(parent_hash: u128, v0: u128, v1: u128) -> [u128; STATE_WIDTH] {
         hash_op(&mut state, NOOP_VALUE, 0, i);
     }
     return state;
-}
\ No newline at end of file
+}
+
+// HELPER FUNCTIONS
+// ================================================================================================
+
+/// Hashes a sequence of blocks according to rules 1 - 4 of `hash_blocks()`.
+fn hash_sequence(blocks: &[ProgramBlock], suffix: &[OpCode], suffix_offset: usize) -> u128 {
+    let mut state = [0u128; STATE_WIDTH];
+    for (i, block) in blocks.iter().enumerate() {
+        match block {
+            ProgramBlock::Span(span) => {
+                if i > 0 {
+                    hash_op(&mut state, NOOP_VALUE, 0, BASE_CYCLE_LENGTH - 1);
+                }
+                for step in 0..span.length() {
+                    let (op_code, op_hint) = span.get_op(step);
+                    let op_value = match (op_code, op_hint) {
+                        (OpCode::Push, OpHint::PushValue(value)) => value,
+                        (OpCode::Push, _) => panic!("value for PUSH operation is missing"),
+                        _ => 0,
+                    };
+                    hash_op(&mut state, op_code as u8, op_value, step);
+                }
+            },
+            ProgramBlock::Group(group) => {
+                let v0 = hash_sequence(group.body(), &GROUP_SUFFIX, GROUP_SUFFIX_OFFSET);
+                state = hash_acc(state[0], v0, 0);
+            },
+            ProgramBlock::Switch(switch) => {
+                let v0 = hash_sequence(switch.true_branch(), &GROUP_SUFFIX, GROUP_SUFFIX_OFFSET);
+                let v1 = hash_sequence(switch.false_branch(), &GROUP_SUFFIX, GROUP_SUFFIX_OFFSET);
+                state = hash_acc(state[0], v0, v1);
+            },
+            ProgramBlock::Loop(block) => {
+                let v0 = hash_sequence(block.body(), &LOOP_SUFFIX, 0);
+                let v1 = hash_sequence(block.skip(), &GROUP_SUFFIX, GROUP_SUFFIX_OFFSET);
+                state = hash_acc(state[0], v0, v1);
+            },
+        }
+    }
+
+    for (i, &op_code) in suffix.iter().enumerate() {
+        hash_op(&mut state, op_code as u8, 0, suffix_offset + i);
+    }
+    return state[0];
+}
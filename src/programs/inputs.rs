@@ -1,9 +1,44 @@
-use crate::{ MAX_PUBLIC_INPUTS };
+use std::{ fmt, sync::{ Arc, Mutex } };
+use serde::{ Serialize, Serializer, Deserialize, Deserializer, de, ser };
+use crate::{ math::field, utils::hasher, MAX_PUBLIC_INPUTS };
+
+// CONSTANTS
+// ================================================================================================
+
+/// Number of bytes which can be packed into a single field element.
+const BYTES_PER_ELEMENT: usize = 15;
+
+// PROGRAM INPUTS
+// ================================================================================================
 
 #[derive(Clone, Debug)]
 pub struct ProgramInputs {
-    public: Vec<u128>,
-    secret: [Vec<u128>; 2],
+    public      : Vec<u128>,
+    secret      : [Vec<u128>; 2],
+    provider    : Option<TapeProvider>,
+}
+
+/// Identifies one of the two secret input tapes.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum TapeId { A, B }
+
+/// Read-only view of the stack state right before an operation reads from a secret input tape;
+/// the item at index 0 is the top of the stack.
+pub struct StackView<'a> {
+    values      : &'a [u128],
+}
+
+/// Supplies values for secret input tapes during execution; cloned inputs share the provider,
+/// and so inputs with a provider can be sent to, and shared between, threads.
+#[derive(Clone)]
+pub(crate) struct TapeProvider(Arc<Mutex<dyn FnMut(TapeId, &StackView) -> u128 + Send>>);
+
+/// Builds `ProgramInputs` incrementally; every value is checked to be a valid field element as
+/// it is added, and the number of public inputs is checked against `MAX_PUBLIC_INPUTS`.
+#[derive(Clone, Debug, Default)]
+pub struct ProgramInputsBuilder {
+    public      : Vec<u128>,
+    secret      : [Vec<u128>; 2],
 }
 
 impl ProgramInputs {
@@ -19,16 +54,85 @@ impl ProgramInputs {
             "number of primary secret inputs cannot be smaller than the number of secondary secret inputs");
 
         return ProgramInputs {
-            public  : public.to_vec(),
-            secret  : [secret_a.to_vec(), secret_b.to_vec()]
+            public      : public.to_vec(),
+            secret      : [secret_a.to_vec(), secret_b.to_vec()],
+            provider    : None,
+        };
+    }
+
+    /// Returns `ProgramInputs` initialized with the provided public inputs and secret input
+    /// tapes which are filled lazily by `provider`. The provider is invoked every time an
+    /// operation reads from an empty tape, and receives the tape being read together with the
+    /// stack state right before the operation. For READ2 and READ4 operations, tape A is
+    /// always read before tape B; for `pmpath` instruction, all nodes of the path are read up
+    /// front. Values returned by the provider are recorded into the execution trace exactly as
+    /// values of static tapes are. Executions which share the inputs (e.g. clones of the inputs
+    /// executed on different threads) take turns calling the provider.
+    pub fn with_tape_provider<F>(public: &[u128], provider: F) -> ProgramInputs
+        where F: FnMut(TapeId, &StackView) -> u128 + Send + 'static
+    {
+        assert!(public.len() <= MAX_PUBLIC_INPUTS,
+            "expected no more than {} public inputs, but received {}",
+            MAX_PUBLIC_INPUTS,
+            public.len());
+        let mut inputs = ProgramInputs::from_public(public);
+        inputs.provider = Some(TapeProvider(Arc::new(Mutex::new(provider))));
+        return inputs;
+    }
+
+    /// Returns `ProgramInputs` for verifying that `leaf` is located at the specified `index` of
+    /// a Merkle tree via `mtree.verify` instruction; `path` lists the siblings of the nodes on
+    /// the way from the leaf to the root, starting with the sibling of the leaf. The root of
+    /// the tree (see `compute_merkle_root()`) is placed onto the stack, and the leaf is placed
+    /// onto tapes A and B followed by the inputs for `mtree.verify` instruction. Thus, the
+    /// program `begin dup.2 read.ab mtree.verify.d end`, where d is the depth of the tree,
+    /// leaves the root on the stack.
+    pub fn for_merkle_path(leaf: [u128; 2], path: &[[u128; 2]], index: usize) -> ProgramInputs {
+        let root = compute_merkle_root(leaf, path, index);
+
+        let mut a = vec![leaf[0]];
+        let mut b = vec![leaf[1]];
+        for (i, node) in path.iter().enumerate() {
+            // bits of the index are read via READ2 instruction, and so they are complemented
+            // with ZEROs on tape A
+            a.push(field::ZERO);
+            b.push(((index >> i) & 1) as u128);
+            a.push(node[0]);
+            b.push(node[1]);
+        }
+
+        return ProgramInputs::new(&[root[1], root[0]], &a, &b);
+    }
+
+    /// Reads inputs from a JSON document of the form
+    /// `{ "public": [...], "tape_a": [...], "tape_b": [...] }`, where every value is a field
+    /// element encoded as a decimal or a 0x-prefixed hexadecimal string; omitted lists are empty.
+    /// Values which are not canonical field elements are rejected, and so are documents which
+    /// would be rejected by `ProgramInputs::builder()`.
+    pub fn from_json(source: &str) -> Result<ProgramInputs, String> {
+        return match serde_json::from_str(source) {
+            Ok(inputs) => Ok(inputs),
+            Err(err) => Err(format!("failed to parse program inputs: {}", err)),
         };
     }
 
+    /// Serializes the inputs into a JSON document readable by `from_json()`; field elements are
+    /// encoded as decimal strings. Panics if the inputs have a tape provider.
+    pub fn to_json(&self) -> String {
+        return serde_json::to_string_pretty(self).expect("failed to serialize program inputs");
+    }
+
+    /// Returns a builder for `ProgramInputs` which starts with no inputs.
+    pub fn builder() -> ProgramInputsBuilder {
+        return ProgramInputsBuilder::default();
+    }
+
     /// Returns `ProgramInputs` with public and secret input tapes set to empty vectors.
     pub fn none() -> ProgramInputs {
         return ProgramInputs {
-            public  : Vec::new(),
-            secret  : [Vec::new(), Vec::new()],
+            public      : Vec::new(),
+            secret      : [Vec::new(), Vec::new()],
+            provider    : None,
         };
     }
 
@@ -36,8 +140,31 @@ impl ProgramInputs {
     /// input tapes set to empty vectors.
     pub fn from_public(public: &[u128]) -> ProgramInputs {
         return ProgramInputs {
-            public: public.to_vec(),
-            secret: [vec![], vec![]]
+            public      : public.to_vec(),
+            secret      : [vec![], vec![]],
+            provider    : None,
+        };
+    }
+
+    /// Returns `ProgramInputs` initialized with the provided public inputs preceded by a
+    /// commitment to these inputs; that is, the two elements of the commitment are placed into
+    /// the top two registers of the initial stack state, and public inputs follow. If there are
+    /// more than `MAX_PUBLIC_INPUTS - 2` public inputs, only the commitment is placed onto the
+    /// stack, and the program must get the inputs from the secret tapes. Secret input tapes are
+    /// set to the provided values.
+    pub fn from_public_with_commitment(public: &[u128], secret_a: &[u128], secret_b: &[u128]) -> ProgramInputs {
+        return ProgramInputs::new(&with_commitment(public), secret_a, secret_b);
+    }
+
+    /// Returns `ProgramInputs` consisting of the inputs captured in the witness `record`;
+    /// executing the recorded program against these inputs produces the same execution trace.
+    pub fn from_witness_record(record: &WitnessRecord) -> ProgramInputs {
+        // the record may hold more values for tape B than for tape A (e.g. if tape A values
+        // were placed there by execution hints); this does not affect execution
+        return ProgramInputs {
+            public      : record.public.clone(),
+            secret      : record.secret.clone(),
+            provider    : None,
         };
     }
 
@@ -48,4 +175,433 @@ impl ProgramInputs {
     pub fn get_secret_inputs(&self) -> &[Vec<u128>; 2] {
         return &self.secret;
     }
-}
\ No newline at end of file
+
+    /// Returns a copy of these inputs with public inputs replaced by `public`; secret input
+    /// tapes and the tape provider are retained.
+    pub(crate) fn with_public_inputs(&self, public: &[u128]) -> ProgramInputs {
+        assert!(public.len() <= MAX_PUBLIC_INPUTS,
+            "expected no more than {} public inputs, but received {}",
+            MAX_PUBLIC_INPUTS,
+            public.len());
+        return ProgramInputs { public: public.to_vec(), ..self.clone() };
+    }
+
+    pub(crate) fn get_tape_provider(&self) -> Option<&TapeProvider> {
+        return self.provider.as_ref();
+    }
+
+    /// Returns values of secret input tape A in the order in which they are read.
+    pub fn tape_a(&self) -> &[u128] {
+        return &self.secret[0];
+    }
+
+    /// Returns values of secret input tape B in the order in which they are read.
+    pub fn tape_b(&self) -> &[u128] {
+        return &self.secret[1];
+    }
+}
+
+// SERIALIZATION
+// ================================================================================================
+
+/// Serialized form of `ProgramInputs`; see `ProgramInputs::to_json()`.
+#[derive(Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct InputsDocument {
+    #[serde(default)]
+    public      : Vec<Element>,
+    #[serde(default)]
+    tape_a      : Vec<Element>,
+    #[serde(default)]
+    tape_b      : Vec<Element>,
+}
+
+/// Field element which is serialized as a decimal string, and can be deserialized from either
+/// a decimal or a 0x-prefixed hexadecimal string.
+struct Element(u128);
+
+impl Serialize for ProgramInputs {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        if self.provider.is_some() {
+            return Err(ser::Error::custom("inputs with a tape provider cannot be serialized"));
+        }
+        let to_elements = |values: &[u128]| values.iter().map(|&v| Element(v)).collect();
+        let document = InputsDocument {
+            public      : to_elements(&self.public),
+            tape_a      : to_elements(&self.secret[0]),
+            tape_b      : to_elements(&self.secret[1]),
+        };
+        return document.serialize(serializer);
+    }
+}
+
+impl <'de> Deserialize<'de> for ProgramInputs {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<ProgramInputs, D::Error> {
+        let document = InputsDocument::deserialize(deserializer)?;
+        let public: Vec<u128> = document.public.iter().map(|e| e.0).collect();
+        let inputs = ProgramInputs::builder()
+            .public(&public)
+            .and_then(|builder| builder.extend_a(document.tape_a.iter().map(|e| e.0)))
+            .and_then(|builder| builder.extend_b(document.tape_b.iter().map(|e| e.0)))
+            .and_then(|builder| builder.build())
+            .map_err(de::Error::custom)?;
+        return Ok(inputs);
+    }
+}
+
+impl Serialize for Element {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        return serializer.serialize_str(&self.0.to_string());
+    }
+}
+
+impl <'de> Deserialize<'de> for Element {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Element, D::Error> {
+        let text = String::deserialize(deserializer)?;
+        return parse_element(&text).map(Element).map_err(de::Error::custom);
+    }
+}
+
+// PROGRAM INPUTS BUILDER
+// ================================================================================================
+
+impl ProgramInputsBuilder {
+
+    /// Appends values to the list of public inputs; the first public input is placed at the top
+    /// of the initial stack.
+    pub fn public(mut self, values: &[u128]) -> Result<ProgramInputsBuilder, String> {
+        if self.public.len() + values.len() > MAX_PUBLIC_INPUTS {
+            return Err(format!("expected no more than {} public inputs, but received {}",
+                MAX_PUBLIC_INPUTS, self.public.len() + values.len()));
+        }
+        for &value in values {
+            self.public.push(validate_input(value, "public input", self.public.len())?);
+        }
+        return Ok(self);
+    }
+
+    /// Appends a value to secret input tape A.
+    pub fn push_a(self, value: u128) -> Result<ProgramInputsBuilder, String> {
+        return self.push_secret(0, value);
+    }
+
+    /// Appends a value to secret input tape B.
+    pub fn push_b(self, value: u128) -> Result<ProgramInputsBuilder, String> {
+        return self.push_secret(1, value);
+    }
+
+    /// Appends all values produced by `values` to secret input tape A.
+    pub fn extend_a<I: IntoIterator<Item = u128>>(mut self, values: I) -> Result<ProgramInputsBuilder, String> {
+        for value in values {
+            self = self.push_secret(0, value)?;
+        }
+        return Ok(self);
+    }
+
+    /// Appends all values produced by `values` to secret input tape B.
+    pub fn extend_b<I: IntoIterator<Item = u128>>(mut self, values: I) -> Result<ProgramInputsBuilder, String> {
+        for value in values {
+            self = self.push_secret(1, value)?;
+        }
+        return Ok(self);
+    }
+
+    /// Builds `ProgramInputs` from the values added so far; tape A must hold at least as many
+    /// values as tape B.
+    pub fn build(self) -> Result<ProgramInputs, String> {
+        let [tape_a, tape_b] = self.secret;
+        if tape_a.len() < tape_b.len() {
+            return Err(format!("tape A must hold at least as many values as tape B, but holds {} values while tape B holds {}",
+                tape_a.len(), tape_b.len()));
+        }
+        return Ok(ProgramInputs {
+            public      : self.public,
+            secret      : [tape_a, tape_b],
+            provider    : None,
+        });
+    }
+
+    fn push_secret(mut self, tape: usize, value: u128) -> Result<ProgramInputsBuilder, String> {
+        let name = if tape == 0 { "tape A value" } else { "tape B value" };
+        let value = validate_input(value, name, self.secret[tape].len())?;
+        self.secret[tape].push(value);
+        return Ok(self);
+    }
+}
+
+// TAPE PROVIDER
+// ================================================================================================
+
+impl TapeProvider {
+
+    /// Returns the next value for the specified tape.
+    pub fn read(&self, tape: TapeId, stack: &StackView) -> u128 {
+        let mut provider = self.0.lock().expect("tape provider panicked during an earlier read");
+        return (provider)(tape, stack);
+    }
+}
+
+impl fmt::Debug for TapeProvider {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "TapeProvider")
+    }
+}
+
+impl <'a> StackView<'a> {
+
+    pub(crate) fn new(values: &'a [u128]) -> StackView<'a> {
+        return StackView { values };
+    }
+
+    /// Returns the number of items on the stack.
+    pub fn depth(&self) -> usize {
+        return self.values.len();
+    }
+
+    /// Returns the stack item at the specified position, or None if the stack is not that deep.
+    pub fn get(&self, index: usize) -> Option<u128> {
+        return self.values.get(index).copied();
+    }
+
+    /// Returns all stack items starting with the top of the stack.
+    pub fn values(&self) -> &[u128] {
+        return self.values;
+    }
+}
+
+// WITNESS RECORD
+// ================================================================================================
+
+/// Inputs consumed by a single execution of a program: the public inputs together with the
+/// values actually read from each secret input tape, in the order in which they were read.
+/// The record contains secret inputs, and so callers should encrypt it before storing it.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct WitnessRecord {
+    program_hash    : [u8; 32],
+    public          : Vec<u128>,
+    secret          : [Vec<u128>; 2],
+}
+
+impl WitnessRecord {
+
+    /// Returns a record of executing the program with the specified hash against `inputs`,
+    /// during which `consumed` values were read from tapes A and B.
+    pub(crate) fn new(program_hash: &[u8; 32], inputs: &ProgramInputs, consumed: [Vec<u128>; 2]) -> WitnessRecord {
+        return WitnessRecord {
+            program_hash    : *program_hash,
+            public          : inputs.public.clone(),
+            secret          : consumed,
+        };
+    }
+
+    /// Returns hash of the program which consumed the inputs.
+    pub fn program_hash(&self) -> &[u8; 32] {
+        return &self.program_hash;
+    }
+
+    pub fn public_inputs(&self) -> &[u128] {
+        return &self.public;
+    }
+
+    pub fn secret_inputs(&self) -> &[Vec<u128>; 2] {
+        return &self.secret;
+    }
+
+    /// Serializes the record into a vector of bytes.
+    pub fn to_bytes(&self) -> Vec<u8> {
+        return bincode::serialize(self).expect("failed to serialize witness record");
+    }
+
+    /// Reads a record from bytes produced by `to_bytes()`.
+    pub fn from_bytes(bytes: &[u8]) -> Result<WitnessRecord, String> {
+        return match bincode::deserialize(bytes) {
+            Ok(record) => Ok(record),
+            Err(err) => Err(format!("failed to deserialize witness record: {}", err)),
+        };
+    }
+}
+
+// PUBLIC FUNCTIONS
+// ================================================================================================
+
+/// Packs bytes into field elements, 15 bytes per element. Bytes are packed in little-endian
+/// order: the i-th byte of a 15-byte chunk is multiplied by 256^i; the last element may hold
+/// fewer than 15 bytes. In a program, packed elements can be unpacked back into bytes using
+/// `unpack.n` instruction.
+pub fn pack_bytes(bytes: &[u8]) -> Vec<u128> {
+    return bytes.chunks(BYTES_PER_ELEMENT).map(|chunk| {
+        chunk.iter().rev().fold(0u128, |result, &byte| (result << 8) | byte as u128)
+    }).collect();
+}
+
+/// Computes the root of a Merkle tree in which `leaf` is located at the specified `index`;
+/// `path` lists the siblings of the nodes on the way from the leaf to the root, starting with
+/// the sibling of the leaf. A parent of two nodes is computed as hash(left, right).
+pub fn compute_merkle_root(leaf: [u128; 2], path: &[[u128; 2]], index: usize) -> [u128; 2] {
+    assert!(path.len() > 0, "Merkle path must contain at least one node");
+    assert!(index.checked_shr(path.len() as u32).unwrap_or(0) == 0,
+        "index {} is invalid for a tree of depth {}", index, path.len() + 1);
+
+    let mut node = leaf;
+    for (i, sibling) in path.iter().enumerate() {
+        let digest = if (index >> i) & 1 == 0 {
+            hasher::digest(&[node[0], node[1], sibling[0], sibling[1]])
+        }
+        else {
+            hasher::digest(&[sibling[0], sibling[1], node[0], node[1]])
+        };
+        node = [digest[0], digest[1]];
+    }
+    return node;
+}
+
+/// Computes a 2-element commitment to the provided public inputs. The digest is initialized
+/// with the number of inputs as h = [n, 0], and the inputs are then absorbed two at a time
+/// (padded with a ZERO if their number is odd) as h = hash(h0, h1, x, y). Since the number
+/// of inputs is absorbed, padding cannot make two different lists of inputs collide.
+/// 
+/// A program can recompute the commitment by executing `push.n push.0` followed by
+/// `read read hash.4` for each pair of inputs on tape A; h1 is then at the top of the stack,
+/// and h0 is right below it.
+pub fn commit_public_inputs(public: &[u128]) -> [u128; 2] {
+    let mut digest = vec![public.len() as u128, field::ZERO];
+    for chunk in public.chunks(2) {
+        let y = if chunk.len() == 2 { chunk[1] } else { field::ZERO };
+        digest = hasher::digest(&[digest[0], digest[1], chunk[0], y]);
+    }
+    return [digest[0], digest[1]];
+}
+
+/// Makes sure an input value is a valid field element; `name` and `index` describe the input
+/// in the error message.
+fn validate_input(value: u128, name: &str, index: usize) -> Result<u128, String> {
+    if value >= field::MODULUS {
+        return Err(format!("{} {} is invalid: value {} must be smaller than {}",
+            name, index, value, field::MODULUS));
+    }
+    return Ok(value);
+}
+
+/// Parses a field element from a decimal or a 0x-prefixed hexadecimal string.
+fn parse_element(text: &str) -> Result<u128, String> {
+    let (digits, radix) = match text.strip_prefix("0x") {
+        Some(digits) => (digits, 16),
+        None => (text, 10),
+    };
+    if digits.is_empty() || !digits.chars().all(|c| c.is_digit(radix)) {
+        return Err(format!("\"{}\" is not a decimal or a 0x-prefixed hexadecimal number", text));
+    }
+    return match u128::from_str_radix(digits, radix) {
+        Ok(value) if value < field::MODULUS => Ok(value),
+        _ => Err(format!("value {} is not a valid field element; value must be smaller than {}",
+            text, field::MODULUS)),
+    };
+}
+
+/// Returns a vector consisting of both elements of a commitment to the provided public inputs
+/// followed by the inputs themselves; if the inputs do not fit onto the stack together with the
+/// commitment, the vector consists of the commitment only.
+pub fn with_commitment(public: &[u128]) -> Vec<u128> {
+    let mut result = Vec::with_capacity(public.len() + 2);
+    result.extend_from_slice(&commit_public_inputs(public));
+    if public.len() <= MAX_PUBLIC_INPUTS - 2 {
+        result.extend_from_slice(public);
+    }
+    return result;
+}
+
+// TESTS
+// ================================================================================================
+#[cfg(test)]
+mod tests {
+
+    use crate::{ math::field, MAX_PUBLIC_INPUTS };
+    use super::ProgramInputs;
+
+    #[test]
+    fn build_inputs() {
+        let inputs = ProgramInputs::builder()
+            .public(&[1, 2]).unwrap()
+            .push_a(3).unwrap()
+            .extend_b(vec![4, 5]).unwrap()
+            .extend_a((6..8).map(|i| i as u128)).unwrap()
+            .public(&[9]).unwrap()
+            .build().unwrap();
+
+        let expected = ProgramInputs::new(&[1, 2, 9], &[3, 6, 7], &[4, 5]);
+        assert_eq!(expected.get_public_inputs(), inputs.get_public_inputs());
+        assert_eq!(expected.get_secret_inputs(), inputs.get_secret_inputs());
+        assert_eq!(&[3, 6, 7], inputs.tape_a());
+        assert_eq!(&[4, 5], inputs.tape_b());
+
+        let inputs = ProgramInputs::builder().build().unwrap();
+        assert!(inputs.get_public_inputs().is_empty());
+        assert!(inputs.tape_a().is_empty() && inputs.tape_b().is_empty());
+    }
+
+    #[test]
+    fn build_inputs_errors() {
+        // values must be valid field elements
+        let err = ProgramInputs::builder().public(&[1, field::MODULUS]).unwrap_err();
+        assert_eq!(format!("public input 1 is invalid: value {} must be smaller than {}",
+            field::MODULUS, field::MODULUS), err);
+        let err = ProgramInputs::builder().push_a(1).unwrap().push_a(u128::MAX).unwrap_err();
+        assert_eq!(format!("tape A value 1 is invalid: value {} must be smaller than {}",
+            u128::MAX, field::MODULUS), err);
+        let err = ProgramInputs::builder().extend_b(vec![field::MODULUS - 1, field::MODULUS + 1]).unwrap_err();
+        assert_eq!(format!("tape B value 1 is invalid: value {} must be smaller than {}",
+            field::MODULUS + 1, field::MODULUS), err);
+
+        // the number of public inputs is limited
+        let builder = ProgramInputs::builder().public(&[0; MAX_PUBLIC_INPUTS]).unwrap();
+        let err = builder.public(&[1]).unwrap_err();
+        assert_eq!(format!("expected no more than {} public inputs, but received {}",
+            MAX_PUBLIC_INPUTS, MAX_PUBLIC_INPUTS + 1), err);
+
+        // tape B cannot be longer than tape A
+        let err = ProgramInputs::builder().push_a(1).unwrap().extend_b(vec![2, 3]).unwrap().build().unwrap_err();
+        assert_eq!("tape A must hold at least as many values as tape B, but holds 1 values while tape B holds 2", err);
+    }
+
+    #[test]
+    fn json_round_trip() {
+        let max = field::MODULUS - 1;
+        let inputs = ProgramInputs::new(&[max, 0, 1], &[max - 1, 2, max], &[max, 3]);
+        let json = inputs.to_json();
+        assert!(json.contains(&format!("\"{}\"", max)));
+
+        let parsed = ProgramInputs::from_json(&json).unwrap();
+        assert_eq!(inputs.get_public_inputs(), parsed.get_public_inputs());
+        assert_eq!(inputs.get_secret_inputs(), parsed.get_secret_inputs());
+
+        // values can be hex-encoded, and omitted tapes are empty
+        let source = format!("{{ \"public\": [\"0x{:x}\", \"0x0a\"], \"tape_a\": [\"7\"] }}", max);
+        let parsed = ProgramInputs::from_json(&source).unwrap();
+        assert_eq!(&[max, 10], parsed.get_public_inputs());
+        assert_eq!(&[7], parsed.tape_a());
+        assert!(parsed.tape_b().is_empty());
+    }
+
+    #[test]
+    fn json_errors() {
+        let err = ProgramInputs::from_json(&format!("{{ \"public\": [\"{}\"] }}", field::MODULUS)).unwrap_err();
+        assert!(err.contains(&format!("value {} is not a valid field element", field::MODULUS)), "{}", err);
+        let err = ProgramInputs::from_json(&format!("{{ \"tape_b\": [\"0x{:x}\"] }}", field::MODULUS)).unwrap_err();
+        assert!(err.contains("is not a valid field element"), "{}", err);
+
+        // numbers, signs, and malformed strings are rejected
+        for value in ["5", "\"+5\"", "\"-1\"", "\"0x\"", "\"12a\"", "\"\""].iter() {
+            let err = ProgramInputs::from_json(&format!("{{ \"public\": [{}] }}", value)).unwrap_err();
+            assert!(err.starts_with("failed to parse program inputs"), "{}", err);
+        }
+
+        // documents are validated in the same way as by the builder
+        let err = ProgramInputs::from_json("{ \"tape_a\": [\"1\"], \"tape_b\": [\"2\", \"3\"] }").unwrap_err();
+        assert!(err.contains("tape A must hold at least as many values as tape B"), "{}", err);
+        let public = vec!["\"1\""; MAX_PUBLIC_INPUTS + 1].join(", ");
+        let err = ProgramInputs::from_json(&format!("{{ \"public\": [{}] }}", public)).unwrap_err();
+        assert!(err.contains("expected no more than"), "{}", err);
+        let err = ProgramInputs::from_json("{ \"tape_c\": [] }").unwrap_err();
+        assert!(err.contains("unknown field"), "{}", err);
+    }
+}
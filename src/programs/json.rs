@@ -0,0 +1,427 @@
+//! Portable JSON representation of programs.
+//!
+//! A program document has the following structure:
+//!
+//! ```json
+//! {
+//!     "version": 1,
+//!     "hash": "<hex-encoded program hash; optional on import>",
+//!     "body": [<block>, ...]
+//! }
+//! ```
+//!
+//! where each block is one of:
+//!
+//! - `{ "type": "span", "ops": ["begin", "push", ...], "hints": [<hint>, ...] }`
+//! - `{ "type": "group", "body": [<block>, ...] }`
+//! - `{ "type": "switch", "true_branch": [<block>, ...], "false_branch": [<block>, ...] }`
+//! - `{ "type": "loop", "body": [<block>, ...] }`
+//!
+//! Operations are referred to by their assembly names. A hint is an object with the index of
+//! the operation within its span, the hint type, and, for all hints other than `eq_start`, a
+//! value: `{ "step": 8, "type": "push_value", "value": "42" }`. PUSH values are field elements
+//! and are encoded as decimal strings; all other values are numbers. The hints of a span may be
+//! omitted when the span has none.
+use std::collections::HashMap;
+use serde_json::{ json, Map, Value };
+use crate::{
+    math::field,
+    processor::{ OpCode, OpHint, opcodes::USER_OPS },
+    BASE_CYCLE_LENGTH, PUSH_OP_ALIGNMENT,
+};
+use super::{ Program, ProgramBlock, Span, Group, Switch, Loop, compute_hash };
+
+// CONSTANTS
+// ================================================================================================
+pub const FORMAT_VERSION: u64 = 1;
+
+const HINT_TYPES: [&str; 9] = [
+    "eq_start", "rc_start", "cmp_start", "pmpath_start", "unpack_start", "exp_start", "div_start",
+    "bits_start", "push_value",
+];
+
+// PUBLIC FUNCTIONS
+// ================================================================================================
+
+/// Serializes the `program` into a JSON document.
+pub fn to_json(program: &Program) -> String {
+    let document = json!({
+        "version"   : FORMAT_VERSION,
+        "hash"      : hex::encode(program.hash()),
+        "body"      : blocks_to_json(program.root().body()),
+    });
+    return serde_json::to_string_pretty(&document).expect("failed to serialize program");
+}
+
+/// Reads a program from a JSON document. The document must describe a valid program; if it
+/// contains a program hash, the hash must match the hash computed from the program body. Errors
+/// refer to the invalid part of the document by its JSON path (e.g. `$.body[1].ops[3]`).
+pub fn from_json(source: &str) -> Result<Program, String> {
+    let document: Value = match serde_json::from_str(source) {
+        Ok(document) => document,
+        Err(err) => return Err(format!("failed to parse program document: {}", err)),
+    };
+
+    let document = as_object(&document, "$", &["version", "hash", "body"])?;
+    match document.get("version") {
+        Some(Value::Number(version)) if version.as_u64() == Some(FORMAT_VERSION) => (),
+        Some(version) => return Err(format!("$.version: unsupported format version {}", version)),
+        None => return Err(String::from("$: format version is missing")),
+    }
+
+    let body = parse_blocks(field_of(document, "body", "$")?, "$.body", &[OpCode::Begin])?;
+    let root = Group::new(body);
+
+    if let Some(hash) = document.get("hash") {
+        let hash = match hash {
+            Value::String(hash) => hash,
+            _ => return Err(String::from("$.hash: program hash must be a string")),
+        };
+        if *hash != hex::encode(compute_hash(&root)) {
+            return Err(String::from("$.hash: program hash does not match program body"));
+        }
+    }
+
+    return Ok(Program::new(root));
+}
+
+// SERIALIZATION
+// ================================================================================================
+
+fn blocks_to_json(blocks: &[ProgramBlock]) -> Value {
+    return Value::Array(blocks.iter().map(|block| match block {
+        ProgramBlock::Span(span) => span_to_json(span),
+        ProgramBlock::Group(group) => json!({
+            "type"  : "group",
+            "body"  : blocks_to_json(group.body()),
+        }),
+        ProgramBlock::Switch(switch) => json!({
+            "type"          : "switch",
+            "true_branch"   : blocks_to_json(switch.true_branch()),
+            "false_branch"  : blocks_to_json(switch.false_branch()),
+        }),
+        ProgramBlock::Loop(block) => match block.max_iterations() {
+            Some(n) => json!({
+                "type"          : "loop",
+                "body"          : blocks_to_json(block.body()),
+                "max_iterations": n,
+            }),
+            None => json!({
+                "type"  : "loop",
+                "body"  : blocks_to_json(block.body()),
+            }),
+        },
+    }).collect());
+}
+
+fn span_to_json(span: &Span) -> Value {
+    let mut ops = Vec::with_capacity(span.length());
+    let mut hints = Vec::new();
+    for step in 0..span.length() {
+        let (op_code, op_hint) = span.get_op(step);
+        ops.push(Value::String(op_code.to_string()));
+        let mut hint = match op_hint {
+            OpHint::EqStart             => json!({ "type": "eq_start" }),
+            OpHint::RcStart(value)      => json!({ "type": "rc_start", "value": value }),
+            OpHint::CmpStart(value)     => json!({ "type": "cmp_start", "value": value }),
+            OpHint::PmpathStart(value)  => json!({ "type": "pmpath_start", "value": value }),
+            OpHint::UnpackStart(value)  => json!({ "type": "unpack_start", "value": value }),
+            OpHint::ExpStart(value)     => json!({ "type": "exp_start", "value": value }),
+            OpHint::DivStart            => json!({ "type": "div_start" }),
+            OpHint::BitsStart(value)    => json!({ "type": "bits_start", "value": value }),
+            OpHint::PushValue(value)    => json!({ "type": "push_value", "value": value.to_string() }),
+            OpHint::None                => continue,
+        };
+        hint["step"] = json!(step);
+        hints.push(hint);
+    }
+    return json!({ "type": "span", "ops": ops, "hints": hints });
+}
+
+// PARSING
+// ================================================================================================
+
+/// Parses a list of blocks at the specified `path`; the first block must be a span which starts
+/// with `starts_with` operations.
+fn parse_blocks(value: &Value, path: &str, starts_with: &[OpCode]) -> Result<Vec<ProgramBlock>, String> {
+    let values = match value {
+        Value::Array(values) => values,
+        _ => return Err(format!("{}: expected an array of blocks", path)),
+    };
+    if values.is_empty() {
+        return Err(format!("{}: a sequence of blocks must contain at least one block", path));
+    }
+
+    let mut blocks = Vec::with_capacity(values.len());
+    for (i, value) in values.iter().enumerate() {
+        let block_path = format!("{}[{}]", path, i);
+        let block = parse_block(value, &block_path)?;
+        match (&block, blocks.last()) {
+            (ProgramBlock::Span(span), None) => {
+                if !span.starts_with(starts_with) {
+                    let ops: Vec<String> = starts_with.iter().map(|op| op.to_string()).collect();
+                    return Err(format!("{}: the first block must start with [{}]", block_path, ops.join(", ")));
+                }
+            },
+            (_, None) => return Err(format!("{}: a sequence of blocks must start with a span", block_path)),
+            (ProgramBlock::Span(_), Some(ProgramBlock::Span(_))) => {
+                return Err(format!("{}: a span cannot be followed by another span", block_path));
+            },
+            _ => (),
+        }
+        blocks.push(block);
+    }
+    return Ok(blocks);
+}
+
+fn parse_block(value: &Value, path: &str) -> Result<ProgramBlock, String> {
+    let block_type = match value.get("type") {
+        Some(Value::String(block_type)) => block_type.as_str(),
+        Some(_) => return Err(format!("{}.type: block type must be a string", path)),
+        None => return Err(format!("{}: block type is missing", path)),
+    };
+
+    return match block_type {
+        "span" => {
+            let object = as_object(value, path, &["type", "ops", "hints"])?;
+            Ok(ProgramBlock::Span(parse_span(object, path)?))
+        },
+        "group" => {
+            let object = as_object(value, path, &["type", "body"])?;
+            let body_path = format!("{}.body", path);
+            let body = parse_blocks(field_of(object, "body", path)?, &body_path, &[])?;
+            Ok(Group::new_block(body))
+        },
+        "switch" => {
+            let object = as_object(value, path, &["type", "true_branch", "false_branch"])?;
+            let t_path = format!("{}.true_branch", path);
+            let t_branch = parse_blocks(field_of(object, "true_branch", path)?, &t_path, &[OpCode::Assert])?;
+            let f_path = format!("{}.false_branch", path);
+            let f_branch = parse_blocks(field_of(object, "false_branch", path)?, &f_path, &[OpCode::Not, OpCode::Assert])?;
+            Ok(Switch::new_block(t_branch, f_branch))
+        },
+        "loop" => {
+            let object = as_object(value, path, &["type", "body", "max_iterations"])?;
+            let body_path = format!("{}.body", path);
+            let body = parse_blocks(field_of(object, "body", path)?, &body_path, &[OpCode::Assert])?;
+            match object.get("max_iterations").map(|n| n.as_u64()) {
+                None => Ok(Loop::new_block(body)),
+                Some(Some(n)) => Ok(Loop::new_bounded_block(body, n as usize)),
+                Some(None) => Err(format!("{}.max_iterations: iteration cap must be a non-negative integer", path)),
+            }
+        },
+        _ => Err(format!("{}.type: unknown block type '{}'", path, block_type)),
+    };
+}
+
+fn parse_span(object: &Map<String, Value>, path: &str) -> Result<Span, String> {
+
+    // parse operations
+    let ops = match field_of(object, "ops", path)? {
+        Value::Array(ops) => ops,
+        _ => return Err(format!("{}.ops: expected an array of operation names", path)),
+    };
+    if ops.len() % BASE_CYCLE_LENGTH != BASE_CYCLE_LENGTH - 1 {
+        return Err(format!("{}.ops: invalid number of operations: expected one less than a multiple of {}, but was {}",
+            path, BASE_CYCLE_LENGTH, ops.len()));
+    }
+
+    let mut op_codes = Vec::with_capacity(ops.len());
+    for (i, op) in ops.iter().enumerate() {
+        let op_code = match op {
+            Value::String(name) => match USER_OPS.iter().find(|op_code| op_code.to_string() == *name) {
+                Some(&op_code) => op_code,
+                None => return Err(format!("{}.ops[{}]: unknown operation '{}'", path, i, name)),
+            },
+            _ => return Err(format!("{}.ops[{}]: operation name must be a string", path, i)),
+        };
+        if op_code == OpCode::Push && i % PUSH_OP_ALIGNMENT != 0 {
+            return Err(format!("{}.ops[{}]: PUSH is not allowed on step {}, must be on step which is a multiple of {}",
+                path, i, i, PUSH_OP_ALIGNMENT));
+        }
+        op_codes.push(op_code);
+    }
+
+    // parse hints
+    let mut op_hints = HashMap::new();
+    let hints = match object.get("hints") {
+        Some(Value::Array(hints)) => hints.as_slice(),
+        Some(_) => return Err(format!("{}.hints: expected an array of hints", path)),
+        None => &[],
+    };
+    for (i, hint) in hints.iter().enumerate() {
+        let hint_path = format!("{}.hints[{}]", path, i);
+        let (step, op_hint) = parse_hint(hint, &hint_path)?;
+        if step >= op_codes.len() {
+            return Err(format!("{}.step: hint out of bounds: step must be smaller than {} but is {}",
+                hint_path, op_codes.len(), step));
+        }
+        if op_hints.insert(step, op_hint).is_some() {
+            return Err(format!("{}.step: operation on step {} has more than one hint", hint_path, step));
+        }
+    }
+
+    // make sure operations which require hints have them, and that hints match their operations
+    return match Span::new(op_codes, op_hints) {
+        Ok(span) => Ok(span),
+        Err(err) => Err(format!("{}.ops[{}]: {}", path, err.index(), err)),
+    };
+}
+
+fn parse_hint(value: &Value, path: &str) -> Result<(usize, OpHint), String> {
+    let object = as_object(value, path, &["step", "type", "value"])?;
+
+    let step = match field_of(object, "step", path)?.as_u64() {
+        Some(step) => step as usize,
+        None => return Err(format!("{}.step: step must be a non-negative integer", path)),
+    };
+
+    let hint_type = match field_of(object, "type", path)? {
+        Value::String(hint_type) if HINT_TYPES.contains(&hint_type.as_str()) => hint_type.as_str(),
+        Value::String(hint_type) => return Err(format!("{}.type: unknown hint type '{}'", path, hint_type)),
+        _ => return Err(format!("{}.type: hint type must be a string", path)),
+    };
+
+    // EQ_START and DIV_START hints are the only hints without a value
+    if hint_type == "eq_start" || hint_type == "div_start" {
+        if object.contains_key("value") {
+            return Err(format!("{}.value: {} hint does not take a value", path, hint_type));
+        }
+        let op_hint = if hint_type == "eq_start" { OpHint::EqStart } else { OpHint::DivStart };
+        return Ok((step, op_hint));
+    }
+
+    let value = field_of(object, "value", path)?;
+    if hint_type == "push_value" {
+        let value = match value {
+            Value::String(value) => value.parse::<u128>().ok().filter(|&v| v < field::MODULUS),
+            _ => None,
+        };
+        return match value {
+            Some(value) => Ok((step, OpHint::PushValue(value))),
+            None => Err(format!("{}.value: push value must be a decimal string encoding a field element", path)),
+        };
+    }
+
+    let value = match value.as_u64() {
+        Some(value) if value <= u32::MAX as u64 => value as u32,
+        _ => return Err(format!("{}.value: {} hint value must be a 32-bit unsigned integer", path, hint_type)),
+    };
+    let op_hint = match hint_type {
+        "rc_start"      => OpHint::RcStart(value),
+        "cmp_start"     => OpHint::CmpStart(value),
+        "pmpath_start"  => OpHint::PmpathStart(value),
+        "exp_start"     => OpHint::ExpStart(value),
+        "bits_start"    => OpHint::BitsStart(value),
+        _               => OpHint::UnpackStart(value),
+    };
+    return Ok((step, op_hint));
+}
+
+// HELPER FUNCTIONS
+// ================================================================================================
+
+/// Returns the value as a JSON object, making sure it contains only the `allowed` fields.
+fn as_object<'a>(value: &'a Value, path: &str, allowed: &[&str]) -> Result<&'a Map<String, Value>, String> {
+    let object = match value {
+        Value::Object(object) => object,
+        _ => return Err(format!("{}: expected an object", path)),
+    };
+    if let Some(key) = object.keys().find(|key| !allowed.contains(&key.as_str())) {
+        return Err(format!("{}.{}: unknown field", path, key));
+    }
+    return Ok(object);
+}
+
+fn field_of<'a>(object: &'a Map<String, Value>, name: &str, path: &str) -> Result<&'a Value, String> {
+    return match object.get(name) {
+        Some(value) => Ok(value),
+        None => Err(format!("{}: field '{}' is missing", path, name)),
+    };
+}
+
+// TESTS
+// ================================================================================================
+#[cfg(test)]
+mod tests {
+
+    use crate::{ Program, assembly };
+    use super::{ to_json, from_json };
+
+    const SOURCE: &str = "
+        begin
+            push.3 push.340282366920938463463374557953744961536 eq
+            if.true
+                read read gt.4 rc.8
+            else
+                while.true pmpath.3 unpack.2 end
+            end
+            block push.7 mul end
+        end";
+
+    #[test]
+    fn json_round_trip() {
+        let program = assembly::compile(SOURCE).unwrap();
+        let json = to_json(&program);
+        let imported = from_json(&json).unwrap();
+        assert_eq!(program.hash(), imported.hash());
+        assert_eq!(format!("{:?}", program), format!("{:?}", imported));
+        assert_eq!(json, to_json(&imported));
+    }
+
+    #[test]
+    fn json_hash_stability() {
+        // the hash is recomputed from the body and does not need to be present
+        let program = assembly::compile("begin push.5 add end").unwrap();
+        let document = format!("{{ \"version\": 1, \"body\": [{{ \"type\": \"span\", \"ops\": {}, \
+            \"hints\": [{{ \"step\": 8, \"type\": \"push_value\", \"value\": \"5\" }}] }}] }}", span_ops(&program));
+        let imported = from_json(&document).unwrap();
+        assert_eq!(program.hash(), imported.hash());
+
+        // a hash which does not match the body is rejected
+        let json = to_json(&program).replace(&hex::encode(program.hash()), &hex::encode([0u8; 32]));
+        assert_eq!(Some(String::from("$.hash: program hash does not match program body")), from_json(&json).err());
+    }
+
+    #[test]
+    fn json_malformed_documents() {
+        let program = assembly::compile("begin push.5 add if.true add else mul end end").unwrap();
+        let json = to_json(&program);
+
+        let cases: Vec<(String, &str)> = vec![
+            (String::from("[]"), "$: expected an object"),
+            (json.replace("\"version\": 1", "\"version\": 2"), "$.version: unsupported format version 2"),
+            (json.replace("\"version\": 1", "\"extra\": 1, \"version\": 1"), "$.extra: unknown field"),
+            (json.replacen("\"add\"", "\"addd\"", 1), "$.body[0].ops[9]: unknown operation 'addd'"),
+            (json.replacen("\"noop\",", "", 1), "$.body[0].ops: invalid number of operations"),
+            (json.replacen("\"push_value\"", "\"push\"", 1), "$.body[0].hints[0].type: unknown hint type 'push'"),
+            (json.replacen("\"value\": \"5\"", "\"value\": 5", 1), "$.body[0].hints[0].value: push value must be"),
+            (json.replacen("\"step\": 8", "\"step\": 100", 1), "$.body[0].hints[0].step: hint out of bounds"),
+            (json.replacen("\"step\": 8", "\"step\": 9", 1), "$.body[0].ops[8]: PUSH operation on step 8 is missing a value"),
+            (json.replacen("\"type\": \"switch\"", "\"type\": \"branch\"", 1), "$.body[1].type: unknown block type 'branch'"),
+            (json.replacen("\"assert\"", "\"noop\"", 1), "$.body[1].false_branch[0]: the first block must start with [not, assert]"),
+            (json.replacen("\"begin\"", "\"noop\"", 1), "$.body[0]: the first block must start with [begin]"),
+            (format!("{{ \"version\": 1, \"body\": [{{ \"type\": \"span\", \"ops\": {} }}, \
+                {{ \"type\": \"span\", \"ops\": {} }}] }}", noops(15), noops(15)).replacen("noop", "begin", 1),
+                "$.body[1]: a span cannot be followed by another span"),
+        ];
+
+        for (document, expected) in cases {
+            match from_json(&document) {
+                Ok(_) => panic!("document should have been rejected with '{}'", expected),
+                Err(err) => assert!(err.starts_with(expected), "expected '{}', but was '{}'", expected, err),
+            }
+        }
+    }
+
+    fn noops(n: usize) -> String {
+        return format!("[{}]", vec!["\"noop\""; n].join(", "));
+    }
+
+    fn span_ops(program: &Program) -> String {
+        let json = to_json(program);
+        let start = json.find("\"ops\": [").unwrap() + 7;
+        let end = start + json[start..].find(']').unwrap() + 1;
+        return String::from(&json[start..end]);
+    }
+}
@@ -0,0 +1,127 @@
+use crate::processor::{ OpCode, ExecutionError };
+use super::{ Program, ProgramBlock, Span, Switch };
+
+// PUBLIC FUNCTIONS
+// ================================================================================================
+
+/// Returns paths to all procedures of a `library` program.
+///
+/// A library is a program which starts with a tree of `if.true` blocks; leaves of the tree are
+/// procedures, numbered in the order in which they appear in the program. A procedure is
+/// selected by placing its path (see `selector_inputs()`) at the top of the initial stack; each
+/// `if.true` block consumes one value of the path, and the value `true` selects the true branch.
+pub fn get_procedure_paths(library: &Program) -> Result<Vec<Vec<bool>>, String> {
+    return Ok(get_procedures(library)?.into_iter().map(|(path, _)| path).collect());
+}
+
+/// Returns the path to the procedure with the specified `index` in a `library` program; this
+/// is the same path as the one returned by `get_procedure_paths()` for the procedure.
+pub fn get_proc_path(library: &Program, index: usize) -> Result<Vec<bool>, ExecutionError> {
+    let mut paths = get_procedure_paths(library).map_err(|_| ExecutionError::InvalidLibrary)?;
+    if index >= paths.len() {
+        return Err(ExecutionError::InvalidProcedure { index, count: paths.len() });
+    }
+    return Ok(paths.swap_remove(index));
+}
+
+/// Returns the hash of the procedure with the specified `index` in a `library` program; this is
+/// the hash of the branch which contains the procedure, and it does not depend on the rest of
+/// the library. Procedures are numbered in the same order as in `get_procedure_paths()`.
+pub fn get_procedure_hash(library: &Program, index: usize) -> Result<u128, String> {
+    let procedures = get_procedures(library)?;
+    return match procedures.get(index) {
+        Some(&(_, hash)) => Ok(hash),
+        None => Err(format!("procedure index {} is out of bounds; the library has {} procedures",
+            index, procedures.len())),
+    };
+}
+
+/// Returns public inputs which select a procedure with the specified `path`; the first value of
+/// the path is placed at the top of the stack.
+pub fn selector_inputs(path: &[bool]) -> Vec<u128> {
+    return path.iter().map(|&b| b as u128).collect();
+}
+
+// HELPER FUNCTIONS
+// ================================================================================================
+
+/// Returns paths and hashes of all procedures of a `library` program.
+fn get_procedures(library: &Program) -> Result<Vec<(Vec<bool>, u128)>, String> {
+    let body = library.root().body();
+    let is_dispatch = match body {
+        [ProgramBlock::Span(span), ProgramBlock::Switch(_)] => only_contains(span, &[OpCode::Begin, OpCode::Noop]),
+        _ => false,
+    };
+    if !is_dispatch {
+        return Err(String::from("library must consist of a BEGIN operation followed by an if.true block"));
+    }
+
+    let mut procedures = Vec::new();
+    if let ProgramBlock::Switch(switch) = &body[1] {
+        collect_procedures(switch, &mut Vec::new(), &mut procedures);
+    }
+    return Ok(procedures);
+}
+
+fn collect_procedures(switch: &Switch, prefix: &mut Vec<bool>, procedures: &mut Vec<(Vec<bool>, u128)>) {
+    let branches = [
+        (switch.true_branch(), switch.true_branch_hash(), true),
+        (switch.false_branch(), switch.false_branch_hash(), false),
+    ];
+    for &(branch, hash, value) in branches.iter() {
+        prefix.push(value);
+        match branch {
+            // a branch which only selects between two other branches is an inner node
+            [ProgramBlock::Span(span), ProgramBlock::Switch(inner)]
+                if only_contains(span, &[OpCode::Not, OpCode::Assert, OpCode::Noop]) =>
+            {
+                collect_procedures(inner, prefix, procedures);
+            },
+            _ => procedures.push((prefix.clone(), hash)),
+        }
+        prefix.pop();
+    }
+}
+
+fn only_contains(span: &Span, op_codes: &[OpCode]) -> bool {
+    return (0..span.length()).all(|i| op_codes.contains(&span.get_op(i).0));
+}
+
+// TESTS
+// ================================================================================================
+#[cfg(test)]
+mod tests {
+
+    use crate::assembly;
+    use super::{ get_procedure_paths, selector_inputs };
+
+    #[test]
+    fn procedure_paths() {
+        let library = assembly::compile("
+            begin
+                if.true
+                    if.true add else mul end
+                else
+                    if.true
+                        push.3 add
+                    else
+                        if.true push.4 else push.5 end
+                        mul
+                    end
+                end
+            end").unwrap();
+
+        let paths = get_procedure_paths(&library).unwrap();
+        assert_eq!(vec![
+            vec![true, true],
+            vec![true, false],
+            vec![false, true],
+            vec![false, false],
+        ], paths);
+        assert_eq!(vec![0, 1], selector_inputs(&paths[2]));
+
+        // a library must dispatch right away
+        let program = assembly::compile("begin push.1 if.true add else mul end end").unwrap();
+        assert!(get_procedure_paths(&program).is_err());
+    }
+}
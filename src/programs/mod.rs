@@ -1,27 +1,52 @@
+use std::ops::Range;
+use serde::{ Serialize, Deserialize };
 use crate::{
     math::field,
     processor::{ OpCode, OpHint },
     utils::{ as_bytes },
     SPONGE_WIDTH, PROGRAM_DIGEST_SIZE, BASE_CYCLE_LENGTH, HACC_NUM_ROUNDS,
+    MAX_CONTEXT_DEPTH, MAX_LOOP_DEPTH,
 };
 
 pub mod assembly;
 
 pub mod blocks;
+pub mod stdlib;
 use blocks::{ ProgramBlock, Span, Group, Switch, Loop };
 
 mod inputs;
-pub use inputs::{ ProgramInputs };
+pub use inputs::{ ProgramInputs, ProgramInputsBuilder, WitnessRecord, TapeId, StackView, commit_public_inputs, compute_merkle_root, pack_bytes, with_commitment as public_inputs_with_commitment };
+pub(crate) use inputs::TapeProvider;
 
 mod hashing;
 use hashing::{ hash_op, hash_acc, hash_seq };
+pub use hashing::hash_blocks;
+
+mod stats;
+pub use stats::{ ProgramStats, LoopBounds, analyze };
+
+mod library;
+pub use library::{ get_procedure_paths, get_proc_path, selector_inputs };
+
+mod json;
+
+mod builder;
+pub use builder::ProgramBuilder;
+
+mod optimizer;
 
 #[cfg(test)]
 mod tests;
 
+// CONSTANTS
+// ================================================================================================
+
+/// Version of the binary format produced by `Program::to_bytes()`.
+const PROGRAM_FORMAT_VERSION: u8 = 3;
+
 // TYPES AND INTERFACES
 // ================================================================================================
-#[derive(Clone)]
+#[derive(Clone, Serialize, Deserialize)]
 pub struct Program {
     root    : Group,
     hash    : [u8; 32],
@@ -43,14 +68,59 @@ impl Program {
             _ => panic!("a program must start with a Span block")
         }
 
-        // compute program hash
-        let (v0, v1) = root.get_hash();
-        let hash = hash_acc(field::ZERO, v0, v1);
-        let mut hash_bytes = [0u8; 32];
-        hash_bytes.copy_from_slice(as_bytes(&hash[..PROGRAM_DIGEST_SIZE]));
+        let hash = compute_hash(&root);
+        return Program { root, hash };
+    }
+
+    /// Reads a program from bytes produced by `to_bytes()`. The structure of the program is
+    /// validated, and the program hash is recomputed from the program body and must match the
+    /// hash stored in the bytes.
+    pub fn from_bytes(bytes: &[u8]) -> Result<Program, String> {
+        match bytes.first() {
+            None => return Err(String::from("program bytes are empty")),
+            Some(&PROGRAM_FORMAT_VERSION) => (),
+            Some(&version) => return Err(format!(
+                "program format version {} is not supported; expected version {}", version, PROGRAM_FORMAT_VERSION)),
+        }
+
+        let program: Program = match bincode::deserialize(&bytes[1..]) {
+            Ok(program) => program,
+            Err(err) => return Err(format!("failed to deserialize program: {}", err)),
+        };
 
-        return Program { root, hash: hash_bytes };
+        validate_blocks(program.root.body(), &[OpCode::Begin], 1, 0)?;
+        if compute_hash(&program.root) != program.hash {
+            return Err(String::from("program hash does not match program body"));
+        }
+
+        return Ok(program);
+    }
+
+    /// Serializes the program into a vector of bytes; the first byte is the format version.
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut result = vec![PROGRAM_FORMAT_VERSION];
+        bincode::serialize_into(&mut result, self).expect("failed to serialize program");
+        return result;
+    }
+
+    /// Reads a program from a JSON document produced by `to_json()` or by another toolchain;
+    /// see the `json` module for the description of the format. If the document contains a
+    /// program hash, it must match the hash computed from the program body.
+    pub fn from_json(source: &str) -> Result<Program, String> {
+        return json::from_json(source);
+    }
+
+    /// Serializes the program into a portable JSON document.
+    pub fn to_json(&self) -> String {
+        return json::to_json(self);
+    }
+
+    /// Translates the program into assembly source code; compiling this source code yields a
+    /// program with the same hash.
+    pub fn to_assembly(&self) -> String {
+        return assembly::decompile(self);
     }
+
     /// Returns the root block of the program.
     pub fn root(&self) -> &Group {
         return &self.root;
@@ -60,6 +130,34 @@ impl Program {
     pub fn hash(&self) -> &[u8; 32] {
         return &self.hash;
     }
+
+    /// Returns the hash of the procedure with the specified `index` if this program is a library
+    /// (see `get_procedure_paths()`). The hash is bound into the program hash, and so publishing
+    /// it allows anyone to check that a program contains the procedure without revealing the
+    /// rest of the program.
+    pub fn proc_hash(&self, index: usize) -> Result<u128, String> {
+        return library::get_procedure_hash(self, index);
+    }
+
+    /// Returns a range of execution trace lengths which could result from executing this
+    /// program. If the program has no loops or branches, the range will contain a single value.
+    /// The number of loop iterations is assumed to be within `loop_bounds`; if `loop_bounds`
+    /// is None, the end of the range is usize::MAX.
+    pub fn estimate_trace_length(&self, loop_bounds: Option<&LoopBounds>) -> Range<usize> {
+        return stats::estimate_trace_length(self.root.body(), loop_bounds);
+    }
+
+    /// Returns a program which executes the same instructions as this program in fewer steps;
+    /// nested group blocks are inlined, adjacent spans are merged, and redundant NOOPs are
+    /// removed. The optimized program has a different hash.
+    pub fn optimize(&self) -> Program {
+        return Program::new(Group::new(optimizer::optimize_blocks(self.root.body())));
+    }
+
+    /// Returns stats describing the structure of this program.
+    pub fn stats(&self, loop_bounds: Option<&LoopBounds>) -> ProgramStats {
+        return stats::analyze(self, loop_bounds);
+    }
 }
 
 impl std::fmt::Debug for Program {
@@ -72,4 +170,57 @@ impl std::fmt::Debug for Program {
 
         return Ok(());
     }
-}
\ No newline at end of file
+}
+
+// HELPER FUNCTIONS
+// ================================================================================================
+
+/// Makes sure a deserialized sequence of blocks could have been built via block constructors,
+/// and that executing the blocks does not overflow context or loop stacks; `ctx_depth` and
+/// `loop_depth` are the depths of these stacks while the blocks are executed.
+fn validate_blocks(blocks: &[ProgramBlock], starts_with: &[OpCode], ctx_depth: usize, loop_depth: usize) -> Result<(), String> {
+    if ctx_depth > MAX_CONTEXT_DEPTH {
+        return Err(format!("program blocks are nested deeper than {}", MAX_CONTEXT_DEPTH));
+    }
+    if loop_depth > MAX_LOOP_DEPTH {
+        return Err(format!("loops are nested deeper than {}", MAX_LOOP_DEPTH));
+    }
+
+    match blocks.first() {
+        Some(ProgramBlock::Span(span)) if span.starts_with(starts_with) => (),
+        Some(ProgramBlock::Span(_)) => return Err(String::from("the first block does not start with a valid sequence of instructions")),
+        Some(_) => return Err(String::from("a sequence of blocks must start with a Span block")),
+        None => return Err(String::from("a sequence of blocks must contain at least one block")),
+    }
+
+    for (i, block) in blocks.iter().enumerate() {
+        match block {
+            ProgramBlock::Span(span) => {
+                if i > 0 && blocks[i - 1].is_span() {
+                    return Err(String::from("a Span block cannot be followed by another Span block"));
+                }
+                span.validate()?;
+            },
+            ProgramBlock::Group(block) => {
+                validate_blocks(block.body(), &[], ctx_depth + 1, loop_depth)?;
+            },
+            ProgramBlock::Switch(block) => {
+                validate_blocks(block.true_branch(), &[OpCode::Assert], ctx_depth + 1, loop_depth)?;
+                validate_blocks(block.false_branch(), &[OpCode::Not, OpCode::Assert], ctx_depth + 1, loop_depth)?;
+            },
+            ProgramBlock::Loop(block) => {
+                validate_blocks(block.body(), &[OpCode::Assert], ctx_depth + 1, loop_depth + 1)?;
+                validate_blocks(block.skip(), &[OpCode::Not, OpCode::Assert], ctx_depth + 1, loop_depth)?;
+            },
+        }
+    }
+    return Ok(());
+}
+
+fn compute_hash(root: &Group) -> [u8; 32] {
+    let (v0, v1) = root.get_hash();
+    let hash = hash_acc(field::ZERO, v0, v1);
+    let mut hash_bytes = [0u8; 32];
+    hash_bytes.copy_from_slice(as_bytes(&hash[..PROGRAM_DIGEST_SIZE]));
+    return hash_bytes;
+}
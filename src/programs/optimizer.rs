@@ -0,0 +1,175 @@
+use std::collections::HashMap;
+use crate::{ processor::{ OpCode, OpHint }, BASE_CYCLE_LENGTH };
+use super::{ ProgramBlock, Span, Switch, Loop };
+
+// PUBLIC FUNCTIONS
+// ================================================================================================
+
+/// Returns a sequence of blocks which executes the same instructions as `blocks` in fewer
+/// steps. Bodies of group blocks are inlined into their parents, adjacent spans are merged,
+/// trailing NOOPs beyond the padding required by the 16-cycle alignment are removed, and spans
+/// which contain only NOOPs are removed unless they start the sequence.
+///
+/// Instructions keep their position relative to the nearest 16-step boundary, and so alignment
+/// of PUSH and hashing operations is preserved.
+pub fn optimize_blocks(blocks: &[ProgramBlock]) -> Vec<ProgramBlock> {
+    let mut result: Vec<ProgramBlock> = Vec::with_capacity(blocks.len());
+    for block in flatten(blocks) {
+        let block = match block {
+            ProgramBlock::Span(span) => {
+                let (op_codes, op_hints) = trim_span(&span);
+                match result.last_mut() {
+                    Some(ProgramBlock::Span(prev)) => {
+                        *prev = merge_spans(prev, op_codes, op_hints);
+                        continue;
+                    },
+                    Some(_) if op_codes.len() == 0 => continue,
+                    _ => ProgramBlock::Span(build_span(op_codes, op_hints)),
+                }
+            },
+            ProgramBlock::Group(_) => unreachable!("group blocks are inlined by flatten()"),
+            ProgramBlock::Switch(block) => Switch::new_block(
+                optimize_blocks(block.true_branch()), optimize_blocks(block.false_branch())),
+            ProgramBlock::Loop(block) => match block.max_iterations() {
+                Some(n) => Loop::new_bounded_block(optimize_blocks(block.body()), n),
+                None    => Loop::new_block(optimize_blocks(block.body())),
+            },
+        };
+        result.push(block);
+    }
+    return result;
+}
+
+// HELPER FUNCTIONS
+// ================================================================================================
+
+/// Replaces group blocks with their bodies recursively.
+fn flatten(blocks: &[ProgramBlock]) -> Vec<ProgramBlock> {
+    let mut result = Vec::with_capacity(blocks.len());
+    for block in blocks {
+        match block {
+            ProgramBlock::Group(group) => result.extend(flatten(group.body())),
+            _ => result.push(block.clone()),
+        }
+    }
+    return result;
+}
+
+/// Returns instructions and hints of the span without trailing NOOPs.
+fn trim_span(span: &Span) -> (Vec<OpCode>, HashMap<usize, OpHint>) {
+    let mut length = span.length();
+    while length > 0 && span.get_op(length - 1).0 == OpCode::Noop {
+        length -= 1;
+    }
+
+    let mut op_codes = Vec::with_capacity(length);
+    let mut op_hints = HashMap::new();
+    for i in 0..length {
+        let (op_code, op_hint) = span.get_op(i);
+        if op_hint != OpHint::None {
+            op_hints.insert(i, op_hint);
+        }
+        op_codes.push(op_code);
+    }
+    return (op_codes, op_hints);
+}
+
+/// Appends instructions to the span starting on the first 16-step boundary after the last
+/// instruction of the span which is not a NOOP.
+fn merge_spans(span: &Span, op_codes: Vec<OpCode>, op_hints: HashMap<usize, OpHint>) -> Span {
+    let (mut new_op_codes, mut new_hints) = trim_span(span);
+    if op_codes.len() > 0 {
+        let offset = (new_op_codes.len() + BASE_CYCLE_LENGTH - 1) / BASE_CYCLE_LENGTH * BASE_CYCLE_LENGTH;
+        new_op_codes.resize(offset, OpCode::Noop);
+        new_op_codes.extend(op_codes);
+        for (step, hint) in op_hints {
+            new_hints.insert(step + offset, hint);
+        }
+    }
+    return build_span(new_op_codes, new_hints);
+}
+
+fn build_span(op_codes: Vec<OpCode>, op_hints: HashMap<usize, OpHint>) -> Span {
+    // instructions and hints come from valid spans and keep their alignment
+    return Span::new_padded(op_codes, op_hints).unwrap();
+}
+
+// TESTS
+// ================================================================================================
+#[cfg(test)]
+mod tests {
+
+    use crate::{ assembly, ProgramInputs, run };
+
+    #[test]
+    fn merge_fragmented_spans() {
+        let program = assembly::compile("
+            begin
+                push.3 read
+                block push.5 add block mul end end
+                block dup mul end
+                block push.7 end
+                add
+            end").unwrap();
+        let optimized = program.optimize();
+        let expected = assembly::compile("begin push.3 read push.5 add mul dup mul push.7 add end").unwrap();
+        assert_eq!(expected.root().body().len(), optimized.root().body().len());
+
+        let inputs = ProgramInputs::new(&[], &[2], &[]);
+        let (outputs, steps) = run(&program, &inputs, 1).unwrap();
+        let (optimized_outputs, optimized_steps) = run(&optimized, &inputs, 1).unwrap();
+        assert_eq!(outputs, optimized_outputs);
+        assert_eq!(vec![((2 + 5) * 3) * ((2 + 5) * 3) + 7], outputs);
+        assert!(optimized_steps < steps);
+    }
+
+    #[test]
+    fn preserve_alignment() {
+        // hashing requires 16-step alignment, and so does the align instruction; the second
+        // span must start on a 16-step boundary even though the first span is shorter
+        let program = assembly::compile("
+            begin
+                push.1 push.2 push.3 push.4
+                block hash.2 end
+                block align.5 read end
+                while.true
+                    block push.2 mul end
+                    read
+                end
+            end").unwrap();
+        let optimized = program.optimize();
+
+        let inputs = ProgramInputs::new(&[], &[1, 1, 1, 0], &[]);
+        let (outputs, steps) = run(&program, &inputs, 4).unwrap();
+        let (optimized_outputs, optimized_steps) = run(&optimized, &inputs, 4).unwrap();
+        assert_eq!(outputs, optimized_outputs);
+        assert!(optimized_steps < steps);
+
+        // optimizing an optimized program does not change it
+        assert_eq!(optimized.hash(), optimized.optimize().hash());
+    }
+
+    #[test]
+    fn keep_branches() {
+        let program = assembly::compile("
+            begin
+                read
+                if.true
+                    block push.3 end
+                    block add end
+                else
+                    block push.4 end mul
+                end
+            end").unwrap();
+        let optimized = program.optimize();
+
+        for &(condition, expected) in [(1, 5), (0, 8)].iter() {
+            let inputs = ProgramInputs::new(&[2], &[condition], &[]);
+            let (outputs, steps) = run(&program, &inputs, 1).unwrap();
+            let (optimized_outputs, optimized_steps) = run(&optimized, &inputs, 1).unwrap();
+            assert_eq!(vec![expected], outputs);
+            assert_eq!(outputs, optimized_outputs);
+            assert!(optimized_steps <= steps);
+        }
+    }
+}
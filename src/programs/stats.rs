@@ -0,0 +1,406 @@
+use std::{ cmp, ops::Range };
+use crate::{ MIN_TRACE_LENGTH, HACC_NUM_ROUNDS, MAX_STACK_DEPTH, MAX_CONTEXT_DEPTH, MAX_LOOP_DEPTH };
+use super::{ Program, blocks::{ ProgramBlock, Span, Loop } };
+
+// CONSTANTS
+// ================================================================================================
+
+// BEGIN/LOOP step at the start of a block + NOOP, TEND/FEND, and hash merging NOOPs at the end
+const BLOCK_OVERHEAD: usize = 1 + 1 + 1 + HACC_NUM_ROUNDS;
+
+// TYPES AND INTERFACES
+// ================================================================================================
+
+/// Bounds on the number of iterations of every loop in a program; for loops with a declared
+/// iteration cap (e.g. `while.true.max=N`), the max bound is further limited by the cap.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct LoopBounds {
+    min : usize,
+    max : usize,
+}
+
+/// Summary of program structure together with an estimate of execution trace length.
+#[derive(Clone, Debug, PartialEq)]
+pub struct ProgramStats {
+    pub span_count      : usize,
+    pub group_count     : usize,
+    pub switch_count    : usize,
+    pub loop_count      : usize,
+    pub op_count        : usize,
+    pub trace_length    : Range<usize>,
+    /// Lowest stack depth, relative to the initial depth, at which any operation of the program
+    /// may execute (after taking its operands); None if the stack could shrink without a bound.
+    pub min_stack_delta : Option<isize>,
+    /// Highest stack depth, relative to the initial depth, which the program may reach; None if
+    /// the stack could grow without a bound.
+    pub max_stack_delta : Option<isize>,
+    /// Max number of nested blocks, including the root block.
+    pub ctx_depth       : usize,
+    /// Max number of nested loops.
+    pub loop_depth      : usize,
+}
+
+/// Bounds on changes of stack depth caused by executing a sequence of blocks; None values
+/// indicate that a bound does not exist.
+#[derive(Copy, Clone)]
+struct StackBounds {
+    min_delta   : Option<isize>,
+    max_delta   : Option<isize>,
+    low         : Option<isize>,
+    high        : Option<isize>,
+}
+
+// LOOP BOUNDS IMPLEMENTATION
+// ================================================================================================
+impl LoopBounds {
+
+    /// Returns bounds specifying that each loop is executed at least `min` and at most `max` times.
+    pub fn new(min: usize, max: usize) -> LoopBounds {
+        assert!(min <= max, "min iteration count cannot be greater than max iteration count");
+        return LoopBounds { min, max };
+    }
+
+    pub fn min(&self) -> usize {
+        return self.min;
+    }
+
+    pub fn max(&self) -> usize {
+        return self.max;
+    }
+}
+
+// PROGRAM STATS IMPLEMENTATION
+// ================================================================================================
+impl ProgramStats {
+
+    /// Collects stats for a program with the specified root block body.
+    pub fn new(root: &[ProgramBlock], loop_bounds: Option<&LoopBounds>) -> ProgramStats {
+        let mut stats = ProgramStats {
+            span_count      : 0,
+            group_count     : 1,    // the root is a group block
+            switch_count    : 0,
+            loop_count      : 0,
+            op_count        : 0,
+            trace_length    : 0..0,
+            min_stack_delta : None,
+            max_stack_delta : None,
+            ctx_depth       : 0,
+            loop_depth      : 0,
+        };
+        collect_counts(root, &mut stats);
+
+        stats.trace_length = estimate_trace_length(root, loop_bounds);
+
+        let stack_bounds = get_stack_bounds(root, loop_bounds);
+        stats.min_stack_delta = stack_bounds.low;
+        stats.max_stack_delta = stack_bounds.high;
+
+        let (ctx_depth, loop_depth) = get_nesting_depth(root);
+        stats.ctx_depth = ctx_depth + 1;    // the root is a group block
+        stats.loop_depth = loop_depth;
+        return stats;
+    }
+
+    /// Makes sure that executing the program with `num_inputs` values on the initial stack
+    /// does not exceed stack, context, or loop depth limits of the VM. The check fails if the
+    /// limits are exceeded along any execution path; unbounded stack depths are not treated
+    /// as exceeding the limits.
+    pub fn check_limits(&self, num_inputs: usize) -> Result<(), String> {
+        if let Some(low) = self.min_stack_delta {
+            if (num_inputs as isize) + low < 0 {
+                return Err(format!("stack underflow: the program requires at least {} inputs, but {} were provided",
+                    -low, num_inputs));
+            }
+        }
+        if let Some(high) = self.max_stack_delta {
+            if (num_inputs as isize) + high > MAX_STACK_DEPTH as isize {
+                return Err(format!("stack overflow: stack depth could reach {}, but cannot exceed {}",
+                    num_inputs as isize + high, MAX_STACK_DEPTH));
+            }
+        }
+        if self.ctx_depth > MAX_CONTEXT_DEPTH {
+            return Err(format!("program blocks are nested {} levels deep, but cannot be nested deeper than {}",
+                self.ctx_depth, MAX_CONTEXT_DEPTH));
+        }
+        if self.loop_depth > MAX_LOOP_DEPTH {
+            return Err(format!("loops are nested {} levels deep, but cannot be nested deeper than {}",
+                self.loop_depth, MAX_LOOP_DEPTH));
+        }
+        return Ok(());
+    }
+}
+
+impl std::fmt::Display for ProgramStats {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        writeln!(f, "spans: {}, groups: {}, switches: {}, loops: {}",
+            self.span_count, self.group_count, self.switch_count, self.loop_count)?;
+        writeln!(f, "operations: {}", self.op_count)?;
+        writeln!(f, "nesting depth: {} blocks, {} loops", self.ctx_depth, self.loop_depth)?;
+        match (self.min_stack_delta, self.max_stack_delta) {
+            (Some(low), Some(high)) => writeln!(f, "stack depth change: {} to {}", low, high)?,
+            (Some(low), None)       => writeln!(f, "stack depth change: at least {}", low)?,
+            (None, Some(high))      => writeln!(f, "stack depth change: at most {}", high)?,
+            (None, None)            => writeln!(f, "stack depth change: unbounded")?,
+        }
+        if self.trace_length.end == usize::MAX {
+            write!(f, "trace length: at least {}", self.trace_length.start)
+        }
+        else if self.trace_length.end - self.trace_length.start == 1 {
+            write!(f, "trace length: {}", self.trace_length.start)
+        }
+        else {
+            write!(f, "trace length: {} to {}", self.trace_length.start, self.trace_length.end - 1)
+        }
+    }
+}
+
+// PUBLIC FUNCTIONS
+// ================================================================================================
+
+/// Returns a range of possible execution trace lengths for a program with the specified root
+/// block body. For programs without loops or branches, the range contains exactly one value. For programs
+/// with loops which have no declared iteration caps and no `loop_bounds` specified, the end of the
+/// range is usize::MAX.
+pub fn estimate_trace_length(root: &[ProgramBlock], loop_bounds: Option<&LoopBounds>) -> Range<usize> {
+    // number of steps needed to execute the root block (the root block is closed in the same
+    // way as any other group block, but it is not started by a BEGIN operation)
+    let (min_steps, max_steps) = count_steps(root, loop_bounds);
+    let min_steps = min_steps + BLOCK_OVERHEAD - 1;
+    let max_steps = max_steps.saturating_add(BLOCK_OVERHEAD - 1);
+
+    let start = get_trace_length(min_steps);
+    let end = if max_steps == usize::MAX { usize::MAX } else { get_trace_length(max_steps) + 1 };
+    return start..end;
+}
+
+/// Analyzes the structure of the `program` without executing it; see `ProgramStats` for the
+/// description of the results. The number of iterations of every loop is assumed to be within
+/// `loop_bounds` and within the declared iteration cap of the loop; if a loop has no cap and
+/// `loop_bounds` is None, stack depth bounds affected by the loop are reported as unbounded.
+pub fn analyze(program: &Program, loop_bounds: Option<&LoopBounds>) -> ProgramStats {
+    return ProgramStats::new(program.root().body(), loop_bounds);
+}
+
+// HELPER FUNCTIONS
+// ================================================================================================
+
+/// Returns min and max number of steps needed to execute a sequence of blocks; usize::MAX
+/// indicates that the number of steps is unbounded.
+fn count_steps(blocks: &[ProgramBlock], loop_bounds: Option<&LoopBounds>) -> (usize, usize) {
+    let mut min_steps = 0;
+    let mut max_steps = 0usize;
+
+    for (i, block) in blocks.iter().enumerate() {
+        let (min, max) = match block {
+            ProgramBlock::Span(block) => {
+                // all spans but the first one in a sequence are pre-padded with a NOOP
+                let steps = if i == 0 { block.length() } else { block.length() + 1 };
+                (steps, steps)
+            },
+            ProgramBlock::Group(block) => {
+                let (min, max) = count_steps(block.body(), loop_bounds);
+                (min + BLOCK_OVERHEAD, max.saturating_add(BLOCK_OVERHEAD))
+            },
+            ProgramBlock::Switch(block) => {
+                let (t_min, t_max) = count_steps(block.true_branch(), loop_bounds);
+                let (f_min, f_max) = count_steps(block.false_branch(), loop_bounds);
+                (cmp::min(t_min, f_min) + BLOCK_OVERHEAD, cmp::max(t_max, f_max).saturating_add(BLOCK_OVERHEAD))
+            },
+            ProgramBlock::Loop(block) => count_loop_steps(block, loop_bounds),
+        };
+        min_steps += min;
+        max_steps = max_steps.saturating_add(max);
+    }
+
+    return (min_steps, max_steps);
+}
+
+/// Returns min and max number of steps needed to execute a loop; each iteration executes the
+/// loop body followed by WRAP or BREAK operation, and the loop is exited via the skip block.
+fn count_loop_steps(block: &Loop, loop_bounds: Option<&LoopBounds>) -> (usize, usize) {
+    let (body_min, body_max) = count_steps(block.body(), loop_bounds);
+    let skip_steps = match &block.skip()[0] {
+        ProgramBlock::Span(block) => block.length(),
+        _ => panic!("invalid skip block content: content must be a Span block"),
+    };
+
+    let (min_iterations, max_iterations) = get_iteration_bounds(block, loop_bounds);
+    let max_iterations = max_iterations.unwrap_or(usize::MAX);
+
+    let min = (body_min + 1) * min_iterations + skip_steps + BLOCK_OVERHEAD;
+    let max = body_max.saturating_add(1)
+        .saturating_mul(max_iterations)
+        .saturating_add(skip_steps + BLOCK_OVERHEAD);
+    return (min, max);
+}
+
+/// Returns min and max number of iterations of a loop; the max is None if the loop has no declared
+/// iteration cap and no `loop_bounds` are specified.
+fn get_iteration_bounds(block: &Loop, loop_bounds: Option<&LoopBounds>) -> (usize, Option<usize>) {
+    let (min, max) = match loop_bounds {
+        Some(bounds) => (bounds.min(), Some(bounds.max())),
+        None => (0, None),
+    };
+    return match (block.max_iterations(), max) {
+        (Some(cap), Some(max)) => (cmp::min(min, cap), Some(cmp::min(max, cap))),
+        (Some(cap), None)      => (cmp::min(min, cap), Some(cap)),
+        (None, max)            => (min, max),
+    };
+}
+
+/// Returns the length of an execution trace needed to execute the specified number of steps;
+/// trace length is always a power of 2 and the last step of the trace is always a VOID step.
+fn get_trace_length(num_steps: usize) -> usize {
+    return cmp::max((num_steps + 1).next_power_of_two(), MIN_TRACE_LENGTH);
+}
+
+fn collect_counts(blocks: &[ProgramBlock], stats: &mut ProgramStats) {
+    for block in blocks {
+        match block {
+            ProgramBlock::Span(block) => {
+                stats.span_count += 1;
+                stats.op_count += block.length();
+            },
+            ProgramBlock::Group(block) => {
+                stats.group_count += 1;
+                collect_counts(block.body(), stats);
+            },
+            ProgramBlock::Switch(block) => {
+                stats.switch_count += 1;
+                collect_counts(block.true_branch(), stats);
+                collect_counts(block.false_branch(), stats);
+            },
+            ProgramBlock::Loop(block) => {
+                stats.loop_count += 1;
+                collect_counts(block.body(), stats);
+                collect_counts(block.skip(), stats);
+            },
+        }
+    }
+}
+
+/// Returns max depths of nested blocks and of nested loops in a sequence of blocks.
+fn get_nesting_depth(blocks: &[ProgramBlock]) -> (usize, usize) {
+    let mut result = (0, 0);
+    for block in blocks {
+        let (ctx_depth, loop_depth) = match block {
+            ProgramBlock::Span(_) => continue,
+            ProgramBlock::Group(block) => {
+                let (ctx_depth, loop_depth) = get_nesting_depth(block.body());
+                (ctx_depth + 1, loop_depth)
+            },
+            ProgramBlock::Switch(block) => {
+                let (t_ctx, t_loop) = get_nesting_depth(block.true_branch());
+                let (f_ctx, f_loop) = get_nesting_depth(block.false_branch());
+                (cmp::max(t_ctx, f_ctx) + 1, cmp::max(t_loop, f_loop))
+            },
+            ProgramBlock::Loop(block) => {
+                let (ctx_depth, loop_depth) = get_nesting_depth(block.body());
+                (ctx_depth + 1, loop_depth + 1)
+            },
+        };
+        result = (cmp::max(result.0, ctx_depth), cmp::max(result.1, loop_depth));
+    }
+    return result;
+}
+
+/// Returns bounds on stack depth changes caused by executing a sequence of blocks; the first
+/// operations of branches and loop bodies consume conditions of their blocks, and so control
+/// blocks themselves do not change stack depth.
+fn get_stack_bounds(blocks: &[ProgramBlock], loop_bounds: Option<&LoopBounds>) -> StackBounds {
+    let mut result = StackBounds { min_delta: Some(0), max_delta: Some(0), low: Some(0), high: Some(0) };
+    for block in blocks {
+        let bounds = match block {
+            ProgramBlock::Span(block) => get_span_bounds(block),
+            ProgramBlock::Group(block) => get_stack_bounds(block.body(), loop_bounds),
+            ProgramBlock::Switch(block) => {
+                let t_bounds = get_stack_bounds(block.true_branch(), loop_bounds);
+                let f_bounds = get_stack_bounds(block.false_branch(), loop_bounds);
+                StackBounds {
+                    min_delta   : min(t_bounds.min_delta, f_bounds.min_delta),
+                    max_delta   : max(t_bounds.max_delta, f_bounds.max_delta),
+                    low         : min(t_bounds.low, f_bounds.low),
+                    high        : max(t_bounds.high, f_bounds.high),
+                }
+            },
+            ProgramBlock::Loop(block) => get_loop_bounds(block, loop_bounds),
+        };
+        result = StackBounds {
+            min_delta   : add(result.min_delta, bounds.min_delta),
+            max_delta   : add(result.max_delta, bounds.max_delta),
+            low         : min(result.low, add(result.min_delta, bounds.low)),
+            high        : max(result.high, add(result.max_delta, bounds.high)),
+        };
+    }
+    return result;
+}
+
+fn get_span_bounds(block: &Span) -> StackBounds {
+    let (mut depth, mut low, mut high) = (0, 0, 0);
+    for i in 0..block.length() {
+        let (pops, pushes) = block.get_op(i).0.stack_effect();
+        low = cmp::min(low, depth - pops as isize);
+        depth += pushes as isize - pops as isize;
+        high = cmp::max(high, depth);
+    }
+    return StackBounds { min_delta: Some(depth), max_delta: Some(depth), low: Some(low), high: Some(high) };
+}
+
+/// Returns bounds on stack depth changes caused by executing a loop; a loop which executes its
+/// body k times is equivalent to a sequence of k loop bodies followed by the skip block.
+fn get_loop_bounds(block: &Loop, loop_bounds: Option<&LoopBounds>) -> StackBounds {
+    let body = get_stack_bounds(block.body(), loop_bounds);
+    let skip = get_stack_bounds(block.skip(), loop_bounds);
+    let (min_iterations, max_iterations) = get_iteration_bounds(block, loop_bounds);
+
+    // stack depth changes of all iterations, and stack depth changes after the last iteration
+    let min_delta = lowest_multiple(body.min_delta, min_iterations, max_iterations);
+    let max_delta = highest_multiple(body.max_delta, min_iterations, max_iterations);
+    let mut low = add(min_delta, skip.low);
+    let mut high = add(max_delta, skip.high);
+
+    // stack depth changes within iterations; the i-th iteration starts after i - 1 iterations
+    if max_iterations != Some(0) {
+        let max_preceding = max_iterations.map(|n| n - 1);
+        low = min(low, add(lowest_multiple(body.min_delta, 0, max_preceding), body.low));
+        high = max(high, add(highest_multiple(body.max_delta, 0, max_preceding), body.high));
+    }
+
+    return StackBounds {
+        min_delta   : add(min_delta, skip.min_delta),
+        max_delta   : add(max_delta, skip.max_delta),
+        low, high,
+    };
+}
+
+/// Returns the lowest value of k * delta for k between `min_k` and `max_k`; None values of
+/// `delta` and `max_k` are unbounded.
+fn lowest_multiple(delta: Option<isize>, min_k: usize, max_k: Option<usize>) -> Option<isize> {
+    return match delta {
+        Some(delta) if delta < 0 => max_k.map(|k| k as isize * delta),
+        Some(delta) => Some(min_k as isize * delta),
+        None => None,
+    };
+}
+
+/// Returns the highest value of k * delta for k between `min_k` and `max_k`; None values of
+/// `delta` and `max_k` are unbounded.
+fn highest_multiple(delta: Option<isize>, min_k: usize, max_k: Option<usize>) -> Option<isize> {
+    return match delta {
+        Some(delta) if delta > 0 => max_k.map(|k| k as isize * delta),
+        Some(delta) => Some(min_k as isize * delta),
+        None => None,
+    };
+}
+
+fn add(a: Option<isize>, b: Option<isize>) -> Option<isize> {
+    return Some(a? + b?);
+}
+
+fn min(a: Option<isize>, b: Option<isize>) -> Option<isize> {
+    return Some(cmp::min(a?, b?));
+}
+
+fn max(a: Option<isize>, b: Option<isize>) -> Option<isize> {
+    return Some(cmp::max(a?, b?));
+}
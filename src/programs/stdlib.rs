@@ -0,0 +1,44 @@
+use crate::crypto::ots::{ self, MIN_MESSAGE_BITS, MAX_MESSAGE_BITS };
+
+// ONE-TIME SIGNATURES
+// ================================================================================================
+
+/// Returns assembly source of a routine which verifies a one-time signature produced by
+/// `crypto::ots::SecretKey::sign()` for messages of `message_bits` bits. The returned source
+/// is a sequence of instructions meant to be embedded into a program (it does not contain
+/// `begin` and `end` keywords).
+///
+/// The routine expects the message to be at the top of the stack followed by 2 elements of
+/// the public key. These 3 values are removed from the stack, and the routine fails if the
+/// signature is not valid. Signature digits and chain values are read from input tapes A and
+/// B in the layout produced by `crypto::ots::Signature::to_tapes()`.
+pub fn ots_verify(message_bits: u32) -> String {
+    assert!(message_bits >= MIN_MESSAGE_BITS && message_bits <= MAX_MESSAGE_BITS,
+        "message bits must be between {} and {}, but was {}",
+        MIN_MESSAGE_BITS, MAX_MESSAGE_BITS, message_bits);
+
+    let n = message_bits;
+    let num_chains = (n + ots::checksum_bits(n)) as usize;
+
+    // make sure the message fits into n bits, and initialize the accumulator and the digest
+    // of chain ends: [acc, h0, h1, m, pk0, pk1]
+    let mut source = format!("dup rc.{} assert pad.3\n", n);
+
+    for i in 0..num_chains {
+        // read the next digit and place a copy of it under the digest: [d, 0, h0, h1, d, acc]
+        source.push_str("read swap.2 pick.2 pad.1 swap ");
+
+        // read chain value s, and select either s (for digit 1) or hash(s) (for digit 0) as
+        // the end of the chain; CHOOSE fails here if the digit is not binary
+        source.push_str("read.ab dup.2 hash.2 swap.2 choose.2 ");
+
+        // merge the chain end into the digest, and add the weighted digit to the accumulator
+        source.push_str(&format!("hash.4 swap.2 push.{} mul add\n", ots::digit_weight(n, i)));
+    }
+
+    // the accumulator must be equal to m + n * 2^n, and the digest must be equal to the key
+    let offset = (n as u128) << n;
+    source.push_str(&format!("roll.4 push.{} add assert.eq swap roll.4 assert.eq assert.eq\n", offset));
+
+    return source;
+}
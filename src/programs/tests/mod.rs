@@ -1,6 +1,8 @@
 use crate::utils::{ as_bytes };
-use crate::processor::{ OpCode };
-use super::{ Program, ProgramBlock, Span, Group, Switch, Loop };
+use rand::{ SeedableRng, rngs::StdRng };
+use crate::{ ExecutionError, MAX_STACK_DEPTH, MAX_CONTEXT_DEPTH, MAX_LOOP_DEPTH };
+use crate::processor::{ self, OpCode, reference::build_random_program };
+use super::{ Program, ProgramInputs, ProgramBlock, Span, Group, Switch, Loop, LoopBounds, analyze, assembly, hash_blocks, hash_op, hash_acc };
 
 mod utils;
 use utils::{ traverse, close_block };
@@ -145,6 +147,370 @@ fn simple_loop() {
     assert_eq!(111, step);
 }
 
+// TRACE LENGTH ESTIMATION
+// ================================================================================================
+
+#[test]
+fn estimate_trace_length_without_loops() {
+    // programs without branches or loops: estimate must be exact
+    let sources = [
+        "begin add push.5 mul push.7 end",
+        "begin read read add block push.1 push.2 add end block push.3 push.4 sub end hash.2 end",
+        "begin read read add read eq repeat.5 push.3 add end end",
+    ];
+
+    for source in sources.iter() {
+        let program = assembly::compile(source).unwrap();
+        let estimate = program.estimate_trace_length(None);
+
+        let inputs = ProgramInputs::new(&[1, 2, 3], &[0, 1, 1], &[]);
+        let (trace, _, _) = processor::execute(&program, &inputs).unwrap();
+        let trace_length = trace[0].len();
+        assert_eq!(trace_length..(trace_length + 1), estimate, "{}", source);
+    }
+
+    // programs with branches: estimate must cover both branches of all switch blocks
+    let sources = [
+        "begin push.3 push.5 read if.true add dup mul else mul dup add end end",
+        "begin push.3 push.5 read if.true add dup mul read if.true dup push.6 mul end end rc.16 end",
+    ];
+
+    for source in sources.iter() {
+        let program = assembly::compile(source).unwrap();
+        let estimate = program.estimate_trace_length(None);
+        assert!(estimate.end != usize::MAX);
+
+        for &condition in [0, 1].iter() {
+            let inputs = ProgramInputs::new(&[1, 2, 3], &[condition, condition], &[]);
+            let (trace, _, _) = processor::execute(&program, &inputs).unwrap();
+            assert!(estimate.contains(&trace[0].len()), "{}", source);
+        }
+    }
+}
+
+#[test]
+fn estimate_trace_length_with_loops() {
+    let source = "begin push.3 read while.true push.1 add read end end";
+    let program = assembly::compile(source).unwrap();
+    let loop_bounds = LoopBounds::new(0, 20);
+    let estimate = program.estimate_trace_length(Some(&loop_bounds));
+
+    for num_iterations in 0..21 {
+        let mut tape_a = vec![1; num_iterations + 1];
+        tape_a[num_iterations] = 0;
+
+        let inputs = ProgramInputs::new(&[], &tape_a, &[]);
+        let (trace, _, _) = processor::execute(&program, &inputs).unwrap();
+        assert!(estimate.contains(&trace[0].len()));
+    }
+
+    // with exact loop bounds, estimate contains a single value
+    let loop_bounds = LoopBounds::new(3, 3);
+    let estimate = program.estimate_trace_length(Some(&loop_bounds));
+    let inputs = ProgramInputs::new(&[], &[1, 1, 1, 0], &[]);
+    let (trace, _, _) = processor::execute(&program, &inputs).unwrap();
+    assert_eq!(trace[0].len()..(trace[0].len() + 1), estimate);
+
+    // without loop bounds, the estimate is unbounded
+    let estimate = program.estimate_trace_length(None);
+    assert_eq!(usize::MAX, estimate.end);
+}
+
+#[test]
+fn estimate_trace_length_with_iteration_caps() {
+    let source = "begin push.3 read while.true.max=5 push.1 add read end end";
+    let program = assembly::compile(source).unwrap();
+
+    // the declared cap bounds the estimate, and limits wider loop bounds
+    let estimate = program.estimate_trace_length(None);
+    assert_eq!(program.estimate_trace_length(Some(&LoopBounds::new(0, 5))), estimate);
+    assert_eq!(estimate, program.estimate_trace_length(Some(&LoopBounds::new(0, 20))));
+
+    for num_iterations in 0..6 {
+        let mut tape_a = vec![1; num_iterations + 1];
+        tape_a[num_iterations] = 0;
+
+        let inputs = ProgramInputs::new(&[], &tape_a, &[]);
+        let (trace, _, _) = processor::execute(&program, &inputs).unwrap();
+        assert!(estimate.contains(&trace[0].len()));
+    }
+
+    // executing more iterations than the cap allows fails
+    let inputs = ProgramInputs::new(&[], &[1, 1, 1, 1, 1, 1, 0], &[]);
+    let err = processor::execute(&program, &inputs).err().unwrap();
+    assert!(matches!(err, ExecutionError::IterationCapExceeded { max: 5, .. }));
+
+    // the cap bounds stack depth analysis as well
+    let program = assembly::compile("begin read while.true.max=4 push.1 read end end").unwrap();
+    assert_eq!(Some(5), analyze(&program, None).max_stack_delta);
+
+    // the cap is preserved in all program encodings
+    let program = Program::from_bytes(&program.to_bytes()).unwrap();
+    assert_eq!(Some(5), analyze(&program, None).max_stack_delta);
+    let program = Program::from_json(&program.to_json()).unwrap();
+    assert_eq!(Some(5), analyze(&program, None).max_stack_delta);
+    let program = assembly::compile(&program.to_assembly()).unwrap();
+    assert_eq!(Some(5), analyze(&program, None).max_stack_delta);
+}
+
+#[test]
+fn program_stats() {
+    let source = "begin push.3 read if.true add dup mul else mul end while.true read end end";
+    let program = assembly::compile(source).unwrap();
+    let stats = program.stats(Some(&LoopBounds::new(0, 4)));
+
+    assert_eq!(1, stats.group_count);
+    assert_eq!(1, stats.switch_count);
+    assert_eq!(1, stats.loop_count);
+    assert_eq!(program.estimate_trace_length(Some(&LoopBounds::new(0, 4))), stats.trace_length);
+}
+
+#[test]
+fn stack_depth_analysis() {
+    // DUP4 takes 4 values from a stack which holds one value, READ2 adds 2 values
+    let program = assembly::compile("begin push.1 push.2 add dup.4 read.ab drop end").unwrap();
+    let stats = analyze(&program, None);
+    assert_eq!(Some(-3), stats.min_stack_delta);
+    assert_eq!(Some(7), stats.max_stack_delta);
+
+    // the program needs 3 inputs
+    assert_eq!(Err(String::from("stack underflow: the program requires at least 3 inputs, but 2 were provided")),
+        stats.check_limits(2));
+    assert_eq!(Ok(()), stats.check_limits(3));
+    let tape = [5, 6];
+    let err = crate::run(&program, &ProgramInputs::new(&[1, 2], &tape, &tape), 1).unwrap_err();
+    assert_eq!(ExecutionError::StackUnderflow { step: 19, op: OpCode::Dup4 }, err);
+    assert!(crate::run(&program, &ProgramInputs::new(&[1, 2, 3], &tape, &tape), 1).is_ok());
+
+    // the program fills the stack up to the max depth
+    let program = assembly::compile(&format!("begin repeat.{} pad.2 end end", MAX_STACK_DEPTH / 2)).unwrap();
+    let stats = analyze(&program, None);
+    assert_eq!(Some(MAX_STACK_DEPTH as isize), stats.max_stack_delta);
+    assert_eq!(Ok(()), stats.check_limits(0));
+    assert_eq!(Err(format!("stack overflow: stack depth could reach {}, but cannot exceed {}",
+        MAX_STACK_DEPTH + 1, MAX_STACK_DEPTH)), stats.check_limits(1));
+    assert!(crate::run(&program, &ProgramInputs::none(), 1).is_ok());
+    let err = crate::run(&program, &ProgramInputs::from_public(&[1]), 1).unwrap_err();
+    assert_eq!(Some(OpCode::Pad2), err.op());
+
+    // branches are analyzed separately
+    let program = assembly::compile("begin read if.true pad.2 pad.2 else drop drop end end").unwrap();
+    let stats = analyze(&program, None);
+    assert_eq!(Some(-2), stats.min_stack_delta);
+    assert_eq!(Some(4), stats.max_stack_delta);
+}
+
+#[test]
+fn stack_depth_analysis_with_loops() {
+    // every iteration of the loop grows the stack by one value
+    let program = assembly::compile("begin read while.true push.1 read end end").unwrap();
+    let stats = analyze(&program, None);
+    assert_eq!(Some(0), stats.min_stack_delta);
+    assert_eq!(None, stats.max_stack_delta);
+    assert_eq!(Ok(()), stats.check_limits(0));
+
+    let stats = analyze(&program, Some(&LoopBounds::new(0, 4)));
+    assert_eq!(Some(5), stats.max_stack_delta);
+    let stats = analyze(&program, Some(&LoopBounds::new(0, MAX_STACK_DEPTH)));
+    assert!(stats.check_limits(0).is_err());
+
+    // the loop body does not change stack depth, and so the loop can run any number of times
+    let program = assembly::compile("begin push.1 read while.true dup add read end end").unwrap();
+    let stats = analyze(&program, None);
+    assert_eq!(Some(0), stats.min_stack_delta);
+    assert_eq!(Some(2), stats.max_stack_delta);
+
+    // every iteration of the loop shrinks the stack
+    let program = assembly::compile("begin read while.true drop read end end").unwrap();
+    let stats = analyze(&program, None);
+    assert_eq!(None, stats.min_stack_delta);
+    let stats = analyze(&program, Some(&LoopBounds::new(1, 3)));
+    assert_eq!(Some(-3), stats.min_stack_delta);
+    assert_eq!(Some(1), stats.max_stack_delta);
+}
+
+#[test]
+fn nesting_depth_analysis() {
+    let nest_blocks = |depth: usize| format!("begin {} add {} end", "block ".repeat(depth), "end ".repeat(depth));
+    let nest_loops = |depth: usize| format!("begin {} add {} end", "while.true ".repeat(depth), "end ".repeat(depth));
+
+    // the root block counts against the context depth
+    let stats = assembly::compile(&nest_blocks(MAX_CONTEXT_DEPTH - 1)).unwrap().stats(None);
+    assert_eq!((MAX_CONTEXT_DEPTH, 0), (stats.ctx_depth, stats.loop_depth));
+    assert_eq!(Ok(()), stats.check_limits(2));
+    let stats = assembly::compile(&nest_blocks(MAX_CONTEXT_DEPTH)).unwrap().stats(None);
+    assert_eq!(Err(format!("program blocks are nested {} levels deep, but cannot be nested deeper than {}",
+        MAX_CONTEXT_DEPTH + 1, MAX_CONTEXT_DEPTH)), stats.check_limits(2));
+
+    let stats = assembly::compile(&nest_loops(MAX_LOOP_DEPTH)).unwrap().stats(None);
+    assert_eq!((MAX_LOOP_DEPTH + 1, MAX_LOOP_DEPTH), (stats.ctx_depth, stats.loop_depth));
+    assert_eq!(Ok(()), stats.check_limits(2));
+    let stats = assembly::compile(&nest_loops(MAX_LOOP_DEPTH + 1)).unwrap().stats(None);
+    assert_eq!(Err(format!("loops are nested {} levels deep, but cannot be nested deeper than {}",
+        MAX_LOOP_DEPTH + 1, MAX_LOOP_DEPTH)), stats.check_limits(2));
+}
+
+#[test]
+fn hash_blocks_matches_program_hash() {
+    // programs with nested groups, branches and loops
+    let sources = [
+        "begin push.3 read if.true add dup mul else mul end while.true read end end",
+        "begin read repeat.3 push.2 mul end block push.1 add end while.true dup mul read end end",
+        "begin read if.true read if.true push.7 else push.8 end else noop end end",
+    ];
+    for source in sources.iter() {
+        let program = assembly::compile(source).unwrap();
+        assert_eq!(*program.hash(), hash_blocks(program.root().body()), "hash mismatch for {}", source);
+    }
+
+    // randomly generated programs
+    for seed in 0..16 {
+        let (program, _) = build_random_program(&mut StdRng::seed_from_u64(seed), 1..8, 0.1);
+        assert_eq!(*program.hash(), hash_blocks(program.root().body()));
+    }
+}
+
+// BLOCK HASHES
+// ================================================================================================
+
+#[test]
+fn block_hashes_recombine_into_program_hash() {
+    let program = assembly::compile("
+        begin
+            push.3 read
+            if.true add else mul end
+            push.2
+            block dup mul end
+            while.true read end
+        end").unwrap();
+
+    // recombine the hash of the root body from hashes of its blocks
+    let body = program.root().body();
+    let mut state = [0, 0, 0, 0];
+    for (i, block) in body.iter().enumerate() {
+        match block {
+            ProgramBlock::Span(span) => {
+                if i > 0 { hash_op(&mut state, OpCode::Noop as u8, 0, 15); }
+                state = span.hash(state);
+            },
+            _ => {
+                let (v0, v1) = block.hash();
+                state = hash_acc(state[0], v0, v1);
+            },
+        }
+    }
+    hash_op(&mut state, OpCode::Noop as u8, 0, 15);
+    assert_eq!(program.root().get_hash(), (state[0], 0));
+    assert_eq!(*program.hash(), hash_to_bytes(&hash_acc(0, state[0], 0)));
+
+    // a Span block hashes as a group containing only this span
+    let group = Group::new_block(vec![body[0].clone()]);
+    assert_eq!(group.hash(), body[0].hash());
+}
+
+#[test]
+fn block_hashes_are_bound_by_decoder() {
+    let program = assembly::compile("begin read if.true push.7 add else mul end end").unwrap();
+    let (v0, v1) = program.root().body()[1].hash();
+
+    // when the decoder exits a branch, hashes of both branches are in sponge registers 1 and 2
+    for &condition in [0, 1].iter() {
+        let inputs = ProgramInputs::new(&[2, 3], &[condition], &[]);
+        let (trace, _, _) = processor::execute(&program, &inputs).unwrap();
+        assert!((0..trace[0].len()).any(|i| trace[2][i] == v0 && trace[3][i] == v1));
+    }
+}
+
+#[test]
+fn shared_block_hashes() {
+    let program1 = assembly::compile("begin read if.true push.7 add else mul end end").unwrap();
+    let program2 = assembly::compile("begin push.1 push.2 read if.true push.7 add else mul end dup end").unwrap();
+    assert_ne!(program1.hash(), program2.hash());
+    assert_eq!(program1.root().body()[1].hash(), program2.root().body()[1].hash());
+
+    // procedures shared between libraries have the same hash regardless of their depth; true
+    // and false branches start with different instructions, and so hashes of a procedure in
+    // true and false branches differ
+    let library1 = assembly::compile("
+        begin if.true if.true add else mul end else if.true push.3 add else push.4 mul end end end").unwrap();
+    let library2 = assembly::compile("begin if.true push.3 add else mul end end").unwrap();
+    assert_eq!(library1.proc_hash(2).unwrap(), library2.proc_hash(0).unwrap());
+    assert_eq!(library1.proc_hash(1).unwrap(), library2.proc_hash(1).unwrap());
+    assert_ne!(library1.proc_hash(0).unwrap(), library1.proc_hash(1).unwrap());
+
+    let err = library2.proc_hash(2).unwrap_err();
+    assert_eq!("procedure index 2 is out of bounds; the library has 2 procedures", err);
+    assert!(program2.proc_hash(0).is_err());
+}
+
+// SERIALIZATION
+// ================================================================================================
+
+#[test]
+fn program_bytes_round_trip() {
+    let sources = [
+        "begin push.1 push.2 add end",
+        "begin read block push.1 add end block dup mul end end",
+        "begin read if.true push.7 gt.8 else read rc.16 end read if.true pmpath.3 end end",
+        "begin read while.true push.3 hash.2 read end unpack.2 end",
+    ];
+    for source in sources.iter() {
+        let program = assembly::compile(source).unwrap();
+        let bytes = program.to_bytes();
+        let restored = Program::from_bytes(&bytes).unwrap();
+        assert_eq!(program.hash(), restored.hash());
+        assert_eq!(format!("{:?}", program), format!("{:?}", restored));
+
+        // serialization is deterministic
+        assert_eq!(bytes, restored.to_bytes());
+        assert_eq!(bytes, assembly::compile(source).unwrap().to_bytes());
+    }
+
+    // randomly generated programs
+    for seed in 0..8 {
+        let (program, _) = build_random_program(&mut StdRng::seed_from_u64(seed), 1..8, 0.1);
+        let restored = Program::from_bytes(&program.to_bytes()).unwrap();
+        assert_eq!(program.hash(), restored.hash());
+    }
+}
+
+#[test]
+fn program_bytes_validation() {
+    let bytes = assembly::compile("begin read if.true push.7 else push.8 end end").unwrap().to_bytes();
+
+    assert_eq!(Some(String::from("program bytes are empty")), Program::from_bytes(&[]).err());
+
+    let mut tampered = bytes.clone();
+    tampered[0] = 2;
+    assert_eq!(Some(String::from("program format version 2 is not supported; expected version 3")),
+        Program::from_bytes(&tampered).err());
+
+    let err = Program::from_bytes(&bytes[..bytes.len() / 2]).err().unwrap();
+    assert!(err.starts_with("failed to deserialize program"));
+
+    let mut tampered = bytes.clone();
+    let last = tampered.len() - 1;
+    tampered[last] ^= 1;
+    assert_eq!(Some(String::from("program hash does not match program body")), Program::from_bytes(&tampered).err());
+
+    // blocks nested deeper than the context stack allows are rejected
+    let nested = |depth: usize, head: &str| format!("begin read {} noop {} end", head.repeat(depth), "end ".repeat(depth));
+    assert!(Program::from_bytes(&assembly::compile(&nested(15, "block ")).unwrap().to_bytes()).is_ok());
+    assert_eq!(Some(String::from("program blocks are nested deeper than 16")),
+        Program::from_bytes(&assembly::compile(&nested(16, "block ")).unwrap().to_bytes()).err());
+
+    // loops nested deeper than the loop stack allows are rejected
+    assert!(Program::from_bytes(&assembly::compile(&nested(8, "while.true ")).unwrap().to_bytes()).is_ok());
+    assert_eq!(Some(String::from("loops are nested deeper than 8")),
+        Program::from_bytes(&assembly::compile(&nested(9, "while.true ")).unwrap().to_bytes()).err());
+
+    // operations are encoded by their op codes, and bytes which are not op codes are rejected
+    assert_eq!(vec![OpCode::Swap as u8], bincode::serialize(&OpCode::Swap).unwrap());
+    assert!(bincode::deserialize::<OpCode>(&[0b0_01_00000]).is_err());
+    assert_eq!("0b10000000 is not a valid op code",
+        bincode::deserialize::<OpCode>(&[0b1_00_00000]).err().unwrap().to_string());
+}
+
 // HELPER FUNCTIONS
 // ================================================================================================
 fn build_first_block(op_code: OpCode, length: usize) -> ProgramBlock {
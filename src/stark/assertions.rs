@@ -0,0 +1,99 @@
+use serde::{ Serialize, Deserialize };
+use crate::{ crypto::HashFunction, stark::TraceState, ExecutionError };
+
+// TYPES AND INTERFACES
+// ================================================================================================
+
+/// Step of the execution trace at which an assertion is enforced.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum AssertionStep {
+    First,
+    Last,
+}
+
+/// Asserts that a user stack register holds the specified value at the first or the last step
+/// of the execution trace. Assertions are enforced via boundary constraints, and are bound to
+/// the proof via the proof transcript.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Assertion {
+    pub register    : usize,
+    pub step        : AssertionStep,
+    pub value       : u128,
+}
+
+// ASSERTION IMPLEMENTATION
+// ================================================================================================
+impl Assertion {
+
+    /// Returns an assertion that `register` is equal to `value` at the first step of the trace.
+    pub fn first(register: usize, value: u128) -> Assertion {
+        return Assertion { register, step: AssertionStep::First, value };
+    }
+
+    /// Returns an assertion that `register` is equal to `value` at the last step of the trace.
+    pub fn last(register: usize, value: u128) -> Assertion {
+        return Assertion { register, step: AssertionStep::Last, value };
+    }
+
+    /// Returns true if the assertion holds against the provided trace state.
+    pub fn holds_at(&self, state: &TraceState) -> bool {
+        return state.user_stack()[self.register] == self.value;
+    }
+
+    fn to_bytes(&self) -> [u8; 25] {
+        let mut result = [0u8; 25];
+        result[..8].copy_from_slice(&(self.register as u64).to_le_bytes());
+        result[8] = match self.step { AssertionStep::First => 0, AssertionStep::Last => 1 };
+        result[9..].copy_from_slice(&self.value.to_le_bytes());
+        return result;
+    }
+}
+
+// PUBLIC FUNCTIONS
+// ================================================================================================
+
+/// Makes sure all assertions refer to existing stack registers and that no register is
+/// asserted more than once for the same step.
+pub fn validate_assertions(assertions: &[Assertion], stack_depth: usize) -> Result<(), String> {
+    return match find_invalid_assertion(assertions, stack_depth) {
+        Some(i) => Err(ExecutionError::InvalidAssertion {
+            register    : assertions[i].register,
+            at          : assertions[i].step,
+            stack_depth,
+        }.to_string()),
+        None => Ok(()),
+    };
+}
+
+/// Returns the index of the first assertion which is made against a register beyond
+/// `stack_depth`, or against a register already asserted at the same step.
+pub fn find_invalid_assertion(assertions: &[Assertion], stack_depth: usize) -> Option<usize> {
+    return assertions.iter().enumerate().position(|(i, assertion)| {
+        assertion.register >= stack_depth || assertions[..i].iter()
+            .any(|a| a.register == assertion.register && a.step == assertion.step)
+    });
+}
+
+/// Returns a digest of the provided list of assertions.
+pub fn hash_assertions(assertions: &[Assertion], hash_fn: HashFunction) -> [u8; 32] {
+    let mut data = Vec::with_capacity(assertions.len() * 25);
+    for assertion in assertions.iter() {
+        data.extend_from_slice(&assertion.to_bytes());
+    }
+
+    let mut result = [0u8; 32];
+    hash_fn.digest(&data, &mut result);
+    return result;
+}
+
+/// Returns a seed for drawing constraint coefficients; the seed binds together the root of
+/// the trace Merkle tree and the digest of boundary assertions.
+pub fn get_constraint_seed(trace_root: &[u8; 32], assertion_digest: &[u8; 32], hash_fn: HashFunction) -> [u8; 32] {
+    let mut data = [0u8; 64];
+    data[..32].copy_from_slice(trace_root);
+    data[32..].copy_from_slice(assertion_digest);
+
+    let mut result = [0u8; 32];
+    hash_fn.digest(&data, &mut result);
+    return result;
+}
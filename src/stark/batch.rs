@@ -0,0 +1,151 @@
+use std::ops::Range;
+use serde::{ Serialize, Deserialize };
+use crate::{ math::field, MAX_BATCH_SIZE };
+use super::{ TraceTable, TraceState, TraceLayout };
+
+// TYPES AND INTERFACES
+// ================================================================================================
+
+/// A single program execution within an execution trace, together with public values which
+/// boundary constraints bind to the first and the last steps of the execution.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct TraceSegment {
+    pub first_step  : usize,
+    pub last_step   : usize,
+    pub inputs      : Vec<u128>,
+    pub outputs     : Vec<u128>,
+    pub op_count    : u128,
+    pub final_depth : u128,
+}
+
+/// Length of a single execution within a batch proof, together with values claimed for the
+/// last step of the execution.
+#[derive(Copy, Clone, Debug, Serialize, Deserialize)]
+pub struct SegmentInfo {
+    pub length      : u32,
+    pub op_count    : u32,
+    pub final_depth : u8,
+}
+
+// TRACE SEGMENT IMPLEMENTATION
+// ================================================================================================
+impl TraceSegment {
+
+    /// Returns a segment spanning the specified `steps` of the `trace`; the number of executed
+    /// operations and the final stack depth are read from the last step of the segment.
+    pub fn from_trace(trace: &TraceTable, steps: Range<usize>, inputs: &[u128], outputs: &[u128]) -> TraceSegment {
+        let last_state = trace.get_state(steps.end - 1);
+        return TraceSegment {
+            first_step  : steps.start,
+            last_step   : steps.end - 1,
+            inputs      : inputs.to_vec(),
+            outputs     : outputs.to_vec(),
+            op_count    : last_state.op_counter(),
+            final_depth : last_state.watermark(),
+        };
+    }
+
+    /// Returns the number of steps in the segment.
+    pub fn length(&self) -> usize {
+        return self.last_step - self.first_step + 1;
+    }
+
+    pub fn info(&self) -> SegmentInfo {
+        return SegmentInfo {
+            length      : self.length() as u32,
+            op_count    : self.op_count as u32,
+            final_depth : self.final_depth as u8,
+        };
+    }
+}
+
+// PUBLIC FUNCTIONS
+// ================================================================================================
+
+/// Stacks execution traces of several runs of the same program on top of each other, and
+/// returns the resulting registers together with context and loop depths of the combined trace
+/// and the steps occupied by each run.
+///
+/// Register groups of all traces are widened to the widest trace by adding registers set to 0s,
+/// and each trace is padded to the length of the longest trace by repeating its last state. The
+/// last trace is padded further to make the length of the combined trace a power of 2.
+pub fn stitch_traces(traces: Vec<(Vec<Vec<u128>>, usize, usize)>) -> (Vec<Vec<u128>>, usize, usize, Vec<Range<usize>>) {
+    assert!(traces.len() > 0, "at least one execution trace must be provided");
+    assert!(traces.len() <= MAX_BATCH_SIZE,
+        "cannot combine more than {} execution traces, but received {}", MAX_BATCH_SIZE, traces.len());
+
+    // determine the shape of the combined trace
+    let layouts = traces.iter()
+        .map(|(registers, ctx_depth, loop_depth)| TraceLayout::new(*ctx_depth, *loop_depth,
+            TraceState::compute_stack_depth(registers.len(), *ctx_depth, *loop_depth)))
+        .collect::<Vec<TraceLayout>>();
+    let ctx_depth = layouts.iter().map(|l| l.ctx_depth()).max().unwrap();
+    let loop_depth = layouts.iter().map(|l| l.loop_depth()).max().unwrap();
+    let stack_depth = layouts.iter().map(|l| l.stack_depth()).max().unwrap();
+    let segment_length = traces.iter().map(|(registers, _, _)| registers[0].len()).max().unwrap();
+    let target = TraceLayout::new(ctx_depth, loop_depth, stack_depth);
+
+    // copy all traces into the combined trace one after another
+    let trace_length = (segment_length * traces.len()).next_power_of_two();
+    let mut result = vec![Vec::with_capacity(trace_length); target.width()];
+    let mut steps = Vec::with_capacity(traces.len());
+    for ((registers, _, _), source) in traces.iter().zip(layouts.iter()) {
+        let first_step = result[0].len();
+        for (group, range) in target.groups().iter() {
+            let source_range = source.range(*group);
+            for (i, register) in range.clone().enumerate() {
+                if i < source_range.len() {
+                    let values = &registers[source_range.start + i];
+                    result[register].extend_from_slice(values);
+                    let last_value = *values.last().unwrap();
+                    result[register].resize(first_step + segment_length, last_value);
+                }
+                else {
+                    result[register].resize(first_step + segment_length, field::ZERO);
+                }
+            }
+        }
+        steps.push(first_step..(first_step + segment_length));
+    }
+
+    // extend the last trace to make the length of the combined trace a power of 2
+    for register in result.iter_mut() {
+        let last_value = *register.last().unwrap();
+        register.resize(trace_length, last_value);
+    }
+    steps.last_mut().unwrap().end = trace_length;
+
+    return (result, ctx_depth, loop_depth, steps);
+}
+
+/// Returns barycentric weights of Lagrange basis polynomials defined over `points`.
+pub fn lagrange_weights(points: &[u128]) -> Vec<u128> {
+    let mut result = Vec::with_capacity(points.len());
+    for (i, &xi) in points.iter().enumerate() {
+        let mut denominator = field::ONE;
+        for (j, &xj) in points.iter().enumerate() {
+            if i == j { continue; }
+            denominator = field::mul(denominator, field::sub(xi, xj));
+        }
+        result.push(denominator);
+    }
+
+    // all points are distinct, and so none of the denominators is zero
+    field::inv_many_in_place(&mut result);
+    return result;
+}
+
+/// Evaluates Lagrange basis polynomials defined over `points` at `x`; `weights` must be computed
+/// via `lagrange_weights()`. For a single point, the only basis polynomial is the constant 1.
+pub fn lagrange_basis_at(points: &[u128], weights: &[u128], x: u128) -> Vec<u128> {
+    let mut result = Vec::with_capacity(points.len());
+    for i in 0..points.len() {
+        let mut value = weights[i];
+        for (j, &xj) in points.iter().enumerate() {
+            if i == j { continue; }
+            value = field::mul(value, field::sub(x, xj));
+        }
+        result.push(value);
+    }
+    return result;
+}
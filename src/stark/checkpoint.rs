@@ -0,0 +1,118 @@
+use serde::{ Serialize, Deserialize };
+use crate::crypto::MerkleTree;
+use super::{ TraceTable, TraceSegment, ConstraintPoly, ProofOptions, Assertion };
+
+// CONSTANTS
+// ================================================================================================
+const CHECKPOINT_VERSION: u8 = 5;
+
+// TYPES AND INTERFACES
+// ================================================================================================
+
+/// Last phase of the prover pipeline completed before a checkpoint was taken.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum ProverPhase {
+    TraceExtended,
+    ConstraintsCommitted,
+}
+
+/// Intermediate state of the prover; a checkpoint contains everything needed to complete the
+/// proof, and so proving can be resumed from it in a different process.
+#[derive(Serialize, Deserialize)]
+pub struct ProverCheckpoint {
+    options                 : ProofOptions,
+    segments                : Vec<TraceSegment>,
+    assertions              : Vec<Assertion>,
+    pub(super) zk_seed      : Option<[u8; 32]>,
+    pub(super) trace        : TraceTable,
+    pub(super) commitments  : Option<Commitments>,
+}
+
+/// Commitments to the extended trace and to the constraint polynomial.
+#[derive(Serialize, Deserialize)]
+pub(super) struct Commitments {
+    pub trace_tree      : MerkleTree,
+    pub constraint_poly : ConstraintPoly,
+    pub constraint_tree : MerkleTree,
+    pub mask            : Option<Mask>,
+}
+
+/// Random polynomial which masks the composition polynomial of a zero-knowledge proof, its
+/// evaluations over the LDE domain, and a commitment to these evaluations.
+#[derive(Serialize, Deserialize)]
+pub(super) struct Mask {
+    pub poly            : Vec<u128>,
+    pub evaluations     : Vec<u128>,
+    pub tree            : MerkleTree,
+}
+
+// PROVER CHECKPOINT IMPLEMENTATION
+// ================================================================================================
+impl ProverCheckpoint {
+
+    pub(super) fn new(trace: TraceTable, segments: Vec<TraceSegment>, assertions: &[Assertion], zk_seed: Option<[u8; 32]>, options: &ProofOptions) -> ProverCheckpoint {
+        assert!(trace.is_extended(), "checkpoint can be taken only after the trace is extended");
+        return ProverCheckpoint {
+            options     : options.clone(),
+            segments    : segments,
+            assertions  : assertions.to_vec(),
+            zk_seed     : zk_seed,
+            trace       : trace,
+            commitments : None,
+        };
+    }
+
+    /// Returns the last completed phase of the prover pipeline.
+    pub fn phase(&self) -> ProverPhase {
+        return match self.commitments {
+            None    => ProverPhase::TraceExtended,
+            Some(_) => ProverPhase::ConstraintsCommitted,
+        };
+    }
+
+    /// Returns program executions contained in the execution trace; the checkpoint of a
+    /// single-execution proof contains exactly one segment.
+    pub(super) fn segments(&self) -> &[TraceSegment] {
+        return &self.segments;
+    }
+
+    pub fn assertions(&self) -> &[Assertion] {
+        return &self.assertions;
+    }
+
+    /// Serializes the checkpoint into a vector of bytes; the first byte is the format version.
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut result = vec![CHECKPOINT_VERSION];
+        bincode::serialize_into(&mut result, self).expect("failed to serialize prover checkpoint");
+        return result;
+    }
+
+    /// Reads a checkpoint from the provided bytes, and makes sure it was taken by a prover
+    /// running with the same `options`.
+    pub fn from_bytes(bytes: &[u8], options: &ProofOptions) -> Result<ProverCheckpoint, String> {
+        match bytes.first() {
+            None => return Err(String::from("checkpoint is empty")),
+            Some(&CHECKPOINT_VERSION) => (),
+            Some(&version) => return Err(format!(
+                "checkpoint version {} is not supported; expected version {}", version, CHECKPOINT_VERSION)),
+        }
+
+        let checkpoint: ProverCheckpoint = match bincode::deserialize(&bytes[1..]) {
+            Ok(checkpoint) => checkpoint,
+            Err(err) => return Err(format!("failed to deserialize checkpoint: {}", err)),
+        };
+
+        // options are compared in serialized form since the checkpoint callback is not serialized
+        let expected = bincode::serialize(options).map_err(|err| err.to_string())?;
+        let actual = bincode::serialize(&checkpoint.options).map_err(|err| err.to_string())?;
+        if expected != actual {
+            return Err(String::from("checkpoint was taken with different proof options"));
+        }
+
+        if checkpoint.trace.extension_factor() != options.extension_factor() || !checkpoint.trace.is_extended() {
+            return Err(String::from("checkpoint does not contain an extended execution trace"));
+        }
+
+        return Ok(checkpoint);
+    }
+}
@@ -1,8 +1,10 @@
-use crate::math::{ field, polynom, parallel };
-use crate::stark::{ MAX_CONSTRAINT_DEGREE, utils::CompositionCoefficients };
+use serde::{ Serialize, Deserialize };
+use crate::math::{ field, polynom, parallel, fft::{ self, FftContext } };
+use crate::stark::utils::CompositionCoefficients;
 
 // TYPES AND INTERFACES
 // ================================================================================================
+#[derive(Serialize, Deserialize)]
 pub struct ConstraintPoly {
     poly: Vec<u128>
 }
@@ -10,28 +12,29 @@ pub struct ConstraintPoly {
 // CONSTRAINT POLY IMPLEMENTATION
 // ================================================================================================
 impl ConstraintPoly {
-    pub fn new(poly: Vec<u128>) -> ConstraintPoly {
+    pub fn new(poly: Vec<u128>, expected_degree: usize) -> ConstraintPoly {
 
         assert!(poly.len().is_power_of_two(), "poly length must be a power of two");
-        debug_assert!(get_expected_degree(&poly) == polynom::degree_of(&poly),
+        debug_assert!(expected_degree == polynom::degree_of(&poly),
             "expected polynomial of degree {} but received degree {}",
-            get_expected_degree(&poly),
+            expected_degree,
             polynom::degree_of(&poly));
 
         return ConstraintPoly { poly };
     }
 
     pub fn degree(&self) -> usize {
-        return get_expected_degree(&self.poly);
+        return polynom::degree_of(&self.poly);
     }
 
-    pub fn eval(&self, twiddles: &[u128]) -> Vec<u128> {
-        let domain_size = twiddles.len() * 2;
+    /// Evaluates the polynomial over the largest domain supported by `fft_ctx`.
+    pub fn eval(&self, fft_ctx: &FftContext) -> Vec<u128> {
+        let domain_size = fft_ctx.size();
         assert!(domain_size > self.poly.len(), "domain size must be greater than poly length");
 
         let mut evaluations = vec![field::ZERO; domain_size];
         evaluations[..self.poly.len()].copy_from_slice(&self.poly);
-        polynom::eval_fft_twiddles(&mut evaluations, twiddles, true);
+        fft::evaluate_with_ctx(&mut evaluations, fft_ctx);
 
         return evaluations;
     }
@@ -52,10 +55,3 @@ impl ConstraintPoly {
     }
 
 }
-
-// HELPER FUNCTIONS
-// ================================================================================================
-fn get_expected_degree(poly: &[u128]) -> usize {
-    let trace_length = poly.len() / MAX_CONSTRAINT_DEGREE;
-    return poly.len() - trace_length;
-}
\ No newline at end of file
@@ -1,28 +1,53 @@
-use crate::math::{ field, parallel, fft, polynom };
-use crate::stark::{ TraceTable, TraceState };
+use std::time::{ Duration, Instant };
+#[cfg(feature = "concurrent")]
+use rayon::prelude::*;
+use crate::math::{ field, parallel, fft::{ self, FftContext }, polynom };
+use crate::stark::{ TraceTable, TraceState, TraceSegment, Assertion };
 use crate::utils::{ uninit_vector };
-use super::{ ConstraintEvaluator, ConstraintPoly };
+use super::{ ConstraintEvaluator, ConstraintDegrees, ConstraintPoly };
 
 // TYPES AND INTERFACES
 // ================================================================================================
 pub struct ConstraintTable {
     evaluator       : ConstraintEvaluator,
-    i_evaluations   : Vec<u128>,    // combined evaluations of boundary constraints at the first step
-    f_evaluations   : Vec<u128>,    // combined evaluations of boundary constraints at the last step
+    i_evaluations   : Vec<u128>,    // combined evaluations of boundary constraints at the first steps
+    f_evaluations   : Vec<u128>,    // combined evaluations of boundary constraints at the last steps
     t_evaluations   : Vec<u128>,    // combined evaluations of transition constraints
+    c_evaluations   : Vec<u128>,    // evaluations of individual transition constraints (debug only)
+}
+
+/// A range of consecutive steps of the constraint table; chunks cover disjoint slices of the
+/// table, and so they can be evaluated independently of each other.
+struct TableChunk<'a> {
+    first_step      : usize,
+    i_evaluations   : &'a mut [u128],
+    f_evaluations   : &'a mut [u128],
+    t_evaluations   : &'a mut [u128],
+    c_evaluations   : &'a mut [u128],
 }
 
 // CONSTRAINT TABLE IMPLEMENTATION
 // ================================================================================================
 impl ConstraintTable {
-    pub fn new(trace: &TraceTable, trace_root: &[u8; 32], inputs: &[u128], outputs: &[u128]) -> ConstraintTable {
-        let evaluator = ConstraintEvaluator::from_trace(trace, trace_root, inputs, outputs);
+    pub fn new(trace: &TraceTable, seed: &[u8; 32], segments: &[TraceSegment], assertions: &[Assertion]) -> ConstraintTable {
+        let evaluator = ConstraintEvaluator::from_segments(trace, seed, segments, assertions);
         let evaluation_domain_size = evaluator.domain_size();
+
+        // if we are in debug mode, allocate space to hold individual evaluations of transition
+        // constraints; evaluations for each step are stored next to each other
+        let c_evaluations = if cfg!(debug_assertions) {
+            uninit_vector(evaluation_domain_size * evaluator.transition_constraint_count())
+        }
+        else {
+            Vec::new()
+        };
+
         return ConstraintTable {
             evaluator       : evaluator,
             i_evaluations   : uninit_vector(evaluation_domain_size),
             f_evaluations   : uninit_vector(evaluation_domain_size),
             t_evaluations   : uninit_vector(evaluation_domain_size),
+            c_evaluations   : c_evaluations,
         };
     }
 
@@ -31,7 +56,12 @@ impl ConstraintTable {
         return self.evaluator.constraint_count();
     }
 
-    /// Returns the size of the evaluation domain = trace_length * MAX_CONSTRAINT_DEGREE
+    /// Returns max degrees of decoder and stack transition constraints.
+    pub fn constraint_degrees(&self) -> ConstraintDegrees {
+        return self.evaluator.constraint_degrees();
+    }
+
+    /// Returns the size of the evaluation domain = trace_length * EVALUATION_BLOWUP
     pub fn evaluation_domain_size(&self) -> usize {
         return self.evaluator.domain_size();
     }
@@ -41,21 +71,45 @@ impl ConstraintTable {
         return self.evaluator.trace_length();
     }
 
-    /// Evaluates transition and boundary constraints at the specified step.
-    pub fn evaluate(&mut self, current: &TraceState, next: &TraceState, x: u128, step: usize) {
-        let (init_bound, last_bound) = self.evaluator.evaluate_boundaries(current, x);
-        self.i_evaluations[step] = init_bound;
-        self.f_evaluations[step] = last_bound;
-        self.t_evaluations[step] = self.evaluator.evaluate_transition(current, next, x, step);
+    /// Evaluates transition and boundary constraints over the entire evaluation domain using
+    /// states of the extended `trace` and points of the LDE domain from `fft_ctx`. The domain
+    /// is split into chunks of `chunk_size` steps; if `concurrent` is set, chunks are evaluated
+    /// on all available cores. Returns time spent evaluating each chunk.
+    pub fn evaluate(&mut self, trace: &TraceTable, fft_ctx: &FftContext, chunk_size: usize, concurrent: bool) -> Vec<Duration> {
+        assert!(fft_ctx.size() == trace.domain_size(), "FFT context does not match the LDE domain");
+        let lde_domain = fft_ctx.domain();
+        let evaluator = &self.evaluator;
+
+        // split the table into chunks of consecutive steps
+        let mut c_chunks = self.c_evaluations.chunks_mut(chunk_size * evaluator.transition_constraint_count());
+        let mut chunks: Vec<TableChunk> = self.i_evaluations.chunks_mut(chunk_size)
+            .zip(self.f_evaluations.chunks_mut(chunk_size))
+            .zip(self.t_evaluations.chunks_mut(chunk_size))
+            .enumerate()
+            .map(|(i, ((i_evaluations, f_evaluations), t_evaluations))| TableChunk {
+                first_step      : i * chunk_size,
+                i_evaluations   : i_evaluations,
+                f_evaluations   : f_evaluations,
+                t_evaluations   : t_evaluations,
+                c_evaluations   : c_chunks.next().unwrap_or(&mut []),
+            })
+            .collect();
+
+        #[cfg(feature = "concurrent")]
+        if concurrent {
+            return chunks.par_iter_mut().map(|chunk| chunk.evaluate(evaluator, trace, lde_domain)).collect();
+        }
+
+        #[cfg(not(feature = "concurrent"))]
+        let _ = concurrent;
+        return chunks.iter_mut().map(|chunk| chunk.evaluate(evaluator, trace, lde_domain)).collect();
     }
 
     /// Interpolates all constraint evaluations into polynomials and combines all these 
-    /// polynomials into a single polynomial using pseudo-random linear combination.
-    pub fn combine_polys(mut self) -> ConstraintPoly
+    /// polynomials into a single polynomial using pseudo-random linear combination; inverse
+    /// twiddles for the interpolation are taken from `fft_ctx`.
+    pub fn combine_polys(mut self, fft_ctx: &FftContext) -> ConstraintPoly
     {
-        let combination_root = field::get_root_of_unity(self.evaluation_domain_size());
-        let inv_twiddles = fft::get_inv_twiddles(combination_root, self.evaluation_domain_size());
-     
         #[cfg(debug_assertions)]
         self.validate_transition_degrees();
         
@@ -63,42 +117,50 @@ impl ConstraintTable {
         
         // 1 ----- boundary constraints for the initial step --------------------------------------
         // interpolate initial step boundary constraint combination into a polynomial, divide the 
-        // polynomial by Z(x) = (x - 1), and add it to the result
-        polynom::interpolate_fft_twiddles(&mut self.i_evaluations, &inv_twiddles, true);
-        polynom::syn_div_in_place(&mut self.i_evaluations, field::ONE);
+        // polynomial by Z(x) = (x - 1), and add it to the result; when the trace contains several
+        // program executions, Z(x) has a factor for the first step of every execution
+        fft::interpolate_with_ctx(&mut self.i_evaluations, fft_ctx);
+        for &x_at_first_step in self.evaluator.get_x_at_first_steps() {
+            polynom::syn_div_in_place(&mut self.i_evaluations, x_at_first_step);
+        }
         combined_poly.copy_from_slice(&self.i_evaluations);
 
         // 2 ----- boundary constraints for the final step ----------------------------------------
         // interpolate final step boundary constraint combination into a polynomial, divide the 
-        // polynomial by Z(x) = (x - x_at_last_step), and add it to the result
-        polynom::interpolate_fft_twiddles(&mut self.f_evaluations, &inv_twiddles, true);
-        let x_at_last_step = self.evaluator.get_x_at_last_step();
-        polynom::syn_div_in_place(&mut self.f_evaluations, x_at_last_step);
+        // polynomial by Z(x) = (x - x_at_last_step), and add it to the result; as above, Z(x) has
+        // a factor for the last step of every program execution
+        fft::interpolate_with_ctx(&mut self.f_evaluations, fft_ctx);
+        let x_at_last_steps = self.evaluator.get_x_at_last_steps().to_vec();
+        for &x_at_last_step in x_at_last_steps.iter() {
+            polynom::syn_div_in_place(&mut self.f_evaluations, x_at_last_step);
+        }
         parallel::add_in_place(&mut combined_poly, &self.f_evaluations, 1);
 
         // 3 ----- transition constraints ---------------------------------------------------------
         // interpolate transition constraint combination into a polynomial, divide the polynomial
-        // by Z(x) = (x^steps - 1) / (x - x_at_last_step), and add it to the result
+        // by Z(x) = (x^steps - 1) / (x - x_at_last_step) for all last steps, and add it to the result
         let trace_length = self.trace_length();
-        polynom::interpolate_fft_twiddles(&mut self.t_evaluations, &inv_twiddles, true);
-        polynom::syn_div_expanded_in_place(&mut self.t_evaluations, trace_length, &[x_at_last_step]);
+        fft::interpolate_with_ctx(&mut self.t_evaluations, fft_ctx);
+        polynom::syn_div_expanded_in_place(&mut self.t_evaluations, trace_length, &x_at_last_steps);
         parallel::add_in_place(&mut combined_poly, &self.t_evaluations, 1);
 
-        return ConstraintPoly::new(combined_poly);
+        return ConstraintPoly::new(combined_poly, self.evaluator.combination_degree());
     }
 
     #[cfg(debug_assertions)]
     fn validate_transition_degrees(&self) {
-        let trace_degree = self.evaluator.trace_length() - 1;
+        let trace_degree = self.evaluator.trace_degree();
         let mut expected_degrees = self.evaluator.get_transition_degrees();
         for i in 0..expected_degrees.len() {
             expected_degrees[i] = expected_degrees[i] * trace_degree;
         }
 
         let mut actual_degrees = Vec::new();
-        let transition_evaluations = self.evaluator.get_transition_evaluations();
-        for i in 0..transition_evaluations.len() {
-            let degree = crate::math::polynom::infer_degree(&transition_evaluations[i]);
+        let constraint_count = expected_degrees.len();
+        for i in 0..constraint_count {
+            let evaluations: Vec<u128> = self.c_evaluations.iter()
+                .skip(i).step_by(constraint_count).cloned().collect();
+            let degree = crate::math::polynom::infer_degree(&evaluations);
             actual_degrees.push(degree);
         }
 
@@ -110,4 +172,98 @@ impl ConstraintTable {
             }
         }
     }
-}
\ No newline at end of file
+}
+
+// TABLE CHUNK IMPLEMENTATION
+// ================================================================================================
+impl <'a> TableChunk<'a> {
+
+    /// Evaluates transition and boundary constraints at all steps of this chunk and returns
+    /// time spent on the evaluation.
+    fn evaluate(&mut self, evaluator: &ConstraintEvaluator, trace: &TraceTable, lde_domain: &[u128]) -> Duration {
+        let now = Instant::now();
+
+        // allocate space to hold current and next states for constraint evaluations
+        let mut current = TraceState::new(trace.ctx_depth(), trace.loop_depth(), trace.stack_depth());
+        let mut next = TraceState::new(trace.ctx_depth(), trace.loop_depth(), trace.stack_depth());
+        let mut evaluations = vec![field::ZERO; evaluator.transition_constraint_count()];
+
+        // we don't need to evaluate constraints over the entire extended execution trace; we need
+        // to evaluate them over the domain extended to match max constraint degree - thus, we can
+        // skip most trace states for the purposes of constraint evaluation.
+        let stride = trace.domain_size() / evaluator.domain_size();
+        for i in 0..self.t_evaluations.len() {
+            let step = self.first_step + i;
+            let position = step * stride;
+
+            // copy current and next states from the trace table; next state may wrap around the
+            // execution trace (close to the end of the trace)
+            trace.fill_state(&mut current, position);
+            trace.fill_state(&mut next, (position + trace.extension_factor()) % trace.domain_size());
+
+            // evaluate the constraints
+            let x = lde_domain[position];
+            let (init_bound, last_bound) = evaluator.evaluate_boundaries(&current, x);
+            self.i_evaluations[i] = init_bound;
+            self.f_evaluations[i] = last_bound;
+            self.t_evaluations[i] = evaluator.evaluate_transition_into(&current, &next, x, step, &mut evaluations);
+
+            // when in debug mode, save transition evaluations before they were combined
+            if self.c_evaluations.len() > 0 {
+                self.c_evaluations[(i * evaluations.len())..((i + 1) * evaluations.len())].copy_from_slice(&evaluations);
+            }
+        }
+
+        return now.elapsed();
+    }
+}
+
+// TESTS
+// ================================================================================================
+#[cfg(test)]
+mod tests {
+
+    use crate::{ assembly, processor, run, ProgramInputs, ProofOptions, math::{ field, fft::FftContext } };
+    use crate::stark::{ TraceTable, TraceSegment };
+    use super::ConstraintTable;
+
+    #[test]
+    fn concurrent_evaluation() {
+        let program = assembly::compile("
+            begin
+                push.1 push.2 push.3 push.4
+                hash.2 read
+                while.true
+                    push.2 mul read
+                end
+                read
+                if.true
+                    push.3 add
+                else
+                    push.4 mul
+                end
+            end").unwrap();
+        let inputs = ProgramInputs::new(&[], &[1, 1, 1, 0, 1], &[]);
+        let (outputs, _) = run(&program, &inputs, 2).unwrap();
+        let (registers, ctx_depth, loop_depth) = processor::execute(&program, &inputs).unwrap();
+        let extension_factor = ProofOptions::default().extension_factor();
+
+        // evaluate the constraints sequentially in a single chunk, and then on all available
+        // cores in many small chunks; the resulting tables must be identical
+        let mut tables = Vec::new();
+        for &(concurrent, chunk_size) in [(false, None), (true, Some(24))].iter() {
+            let mut trace = TraceTable::new(registers.clone(), ctx_depth, loop_depth, extension_factor);
+            let segment = TraceSegment::from_trace(&trace, 0..trace.unextended_length(), &[], &outputs);
+            let fft_ctx = FftContext::new(trace.domain_size());
+            trace.extend(&fft_ctx);
+
+            let mut table = ConstraintTable::new(&trace, &[7; 32], &[segment], &[]);
+            let chunk_size = chunk_size.unwrap_or(table.evaluation_domain_size());
+            let times = table.evaluate(&trace, &fft_ctx, chunk_size, concurrent);
+            assert_eq!((table.evaluation_domain_size() + chunk_size - 1) / chunk_size, times.len());
+            tables.push((table.i_evaluations, table.f_evaluations, table.t_evaluations, table.c_evaluations));
+        }
+        assert!(tables[0] == tables[1]);
+        assert!(tables[0].2.iter().any(|&evaluation| evaluation != field::ZERO));
+    }
+}
@@ -454,9 +454,13 @@ mod tests {
             state.push(((UserOps::Noop as u128) >> i) & 1);
         }
 
+        // ld_op bit products for NOOP operation
+        state.extend_from_slice(&[0, 0, 0, 1,  0, 0, 0, 1]);
+
         state.extend_from_slice(ctx_stack);
         state.extend_from_slice(loop_stack);
         state.push(101); // single value for user stack
+        state.extend_from_slice(&[1, 0, 0, 0, 0, 0]);   // stack depth bits
 
         return TraceState::from_vec(ctx_depth, loop_depth, 1, &state);
     }
@@ -27,12 +27,13 @@ mod tests;
 
 // CONSTANTS
 // ================================================================================================
-const NUM_OP_CONSTRAINTS: usize = 15;
+const NUM_OP_CONSTRAINTS: usize = 23;
 const OP_CONSTRAINT_DEGREES: [usize; NUM_OP_CONSTRAINTS] = [
     2, 2, 2, 2, 2, 2, 2, 2, 2, 2,   // all op bits are binary
+    2, 2, 2, 2, 2, 2, 2, 2,         // ld_op bit products are consistent with ld_op bits
     3,                              // op_counter should be incremented for HACC operations
-    8,                              // ld_ops and hd_ops cannot be all 0s
-    8,                              // when cf_ops are not all 0s, ld_ops and hd_ops must be all 1s
+    6,                              // ld_ops and hd_ops cannot be all 0s
+    6,                              // when cf_ops are not all 0s, ld_ops and hd_ops must be all 1s
     6,                              // VOID can be followed only by VOID
     4,                              // operations happen on allowed step multiples
 ];
@@ -56,6 +57,7 @@ pub const NUM_STATIC_DECODER_CONSTRAINTS: usize =
 
 // TYPES AND INTERFACES
 // ================================================================================================
+#[derive(Clone)]
 pub struct Decoder {
     ctx_depth           : usize,
     loop_depth          : usize,
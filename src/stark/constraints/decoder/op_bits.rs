@@ -19,10 +19,9 @@ pub fn enforce_op_bits(result: &mut [u128], current: &TraceState, next: &TraceSt
         i += 1;
     }
 
-    let mut ld_bit_prod = 1;
-    for &op_bit in current.ld_op_bits() {
+    let ld_op_bits = current.ld_op_bits();
+    for &op_bit in ld_op_bits {
         result[i] = is_binary(op_bit);
-        ld_bit_prod = mul(ld_bit_prod, op_bit);
         i += 1;
     }
 
@@ -33,6 +32,25 @@ pub fn enforce_op_bits(result: &mut [u128], current: &TraceState, next: &TraceSt
         i += 1;
     }
 
+    // make sure ld_op bit products are consistent with ld_op bits
+    let ld_op_prods = current.ld_op_prods();
+    for j in 0..4 {
+        let b0 = if j & 1 == 1 { ld_op_bits[0] } else { binary_not(ld_op_bits[0]) };
+        let b1 = if j & 2 == 2 { ld_op_bits[1] } else { binary_not(ld_op_bits[1]) };
+        result[i] = are_equal(ld_op_prods[j], mul(b0, b1));
+        i += 1;
+    }
+
+    for j in 0..4 {
+        let b2 = if j & 1 == 1 { ld_op_bits[2] } else { binary_not(ld_op_bits[2]) };
+        let b3 = if j & 2 == 2 { ld_op_bits[3] } else { binary_not(ld_op_bits[3]) };
+        result[i] = are_equal(ld_op_prods[4 + j], mul(b2, b3));
+        i += 1;
+    }
+
+    // product of all ld_op bits is computed from bit product registers
+    let ld_bit_prod = mul(mul(ld_op_prods[3], ld_op_prods[7]), ld_op_bits[4]);
+
     // when cf_ops = hacc, operation counter should be incremented by 1;
     // otherwise, operation counter should remain the same
     let op_counter = current.op_counter();
@@ -117,6 +135,28 @@ mod tests {
         }
     }
 
+    #[test]
+    fn ld_op_prods_are_consistent() {
+
+        let success_result = vec![0; NUM_OP_CONSTRAINTS];
+
+        // products set from op bits: success
+        let state = new_state(FlowOps::Hacc as u8, UserOps::Add as u8, 1);
+        assert_eq!(success_result, evaluate_state(&state, [0, 0, 0], true));
+
+        // forged product registers
+        for i in 0..8 {
+            let mut state = new_state(FlowOps::Hacc as u8, UserOps::Add as u8, 1);
+            let mut prods = [0; 8];
+            prods.copy_from_slice(state.ld_op_prods());
+            prods[i] = 1 - prods[i];
+            state.set_ld_op_prods(prods);
+
+            let evaluations = evaluate_state(&state, [0, 0, 0], true);
+            assert_ne!(0, evaluations[10 + i]);
+        }
+    }
+
     #[test]
     fn invalid_op_combinations() {
 
@@ -1,10 +1,21 @@
+use std::{ collections::HashMap, sync::Mutex };
 use crate::{
     math::field,
-    utils::uninit_vector,
-    stark::{ StarkProof, TraceTable, TraceState, ConstraintCoefficients },
+    stark::{
+        StarkProof, TraceTable, TraceState, TraceSegment, ConstraintCoefficients, Assertion, AssertionStep,
+        get_constraint_seed, batch::{ lagrange_weights, lagrange_basis_at }, utils,
+    },
     PROGRAM_DIGEST_SIZE,
 };
-use super::{ decoder::Decoder, stack::Stack, super::MAX_CONSTRAINT_DEGREE };
+use super::{ decoder::Decoder, stack::Stack, super::EVALUATION_BLOWUP };
+
+#[cfg(test)]
+thread_local! {
+    /// When set, evaluators built on this thread do not check that transition constraints are
+    /// satisfied by the trace; used to build proofs for invalid traces and to make sure that the
+    /// verifier rejects them.
+    pub(crate) static SKIP_TRANSITION_CHECKS: std::cell::Cell<bool> = std::cell::Cell::new(false);
+}
 
 // TYPES AND INTERFACES
 // ================================================================================================
@@ -18,28 +29,63 @@ pub struct Evaluator {
 
     t_constraint_num: usize,
     t_degree_groups : Vec<(u128, Vec<usize>)>,
-    t_evaluations   : Vec<Vec<u128>>,
 
     b_constraint_num: usize,
     program_hash    : Vec<u128>,
-    op_count        : u128,
-    inputs          : Vec<u128>,
-    outputs         : Vec<u128>,
+    segments        : Vec<TraceSegment>,
+    i_points        : Vec<u128>,        // x coordinates of the first steps of all segments
+    i_weights       : Vec<u128>,
+    f_points        : Vec<u128>,        // x coordinates of the last steps of all segments
+    f_weights       : Vec<u128>,
+    assertions      : Vec<Assertion>,
     b_degree_adj    : u128,
+    trace_degree    : usize,
+
+    #[cfg(test)]
+    skip_checks     : bool,
+}
+
+/// Decoder and stack constraint evaluators depend only on parameters of the execution trace,
+/// and building them involves extending round constants and cycle masks; verifiers of many
+/// proofs with the same parameters can share the evaluators through this cache.
+pub struct EvaluatorCache {
+    decoders        : Mutex<HashMap<(usize, usize, usize, usize), Decoder>>,
+    stacks          : Mutex<HashMap<(usize, usize, usize), Stack>>,
+}
+
+/// Max degrees of decoder and stack transition constraints; both are 0 if constraints were
+/// not evaluated by this process.
+#[derive(Copy, Clone, Debug, Default, PartialEq)]
+pub struct ConstraintDegrees {
+    pub decoder : usize,
+    pub stack   : usize,
 }
 
 // EVALUATOR IMPLEMENTATION
 // ================================================================================================
 impl Evaluator {
 
-    pub fn from_trace(trace: &TraceTable, trace_root: &[u8; 32], inputs: &[u128], outputs: &[u128]) -> Evaluator
+    /// Returns an evaluator for a trace of a single program execution.
+    #[cfg(test)]
+    pub fn from_trace(trace: &TraceTable, seed: &[u8; 32], inputs: &[u128], outputs: &[u128], assertions: &[Assertion]) -> Evaluator
+    {
+        let segment = TraceSegment::from_trace(trace, 0..trace.unextended_length(), inputs, outputs);
+        return Self::from_segments(trace, seed, &[segment], assertions);
+    }
+
+    /// Returns an evaluator for a trace which consists of one or more program executions; boundary
+    /// constraints are enforced at the first and the last steps of every execution, and transition
+    /// constraints are not enforced between executions.
+    pub fn from_segments(trace: &TraceTable, seed: &[u8; 32], segments: &[TraceSegment], assertions: &[Assertion]) -> Evaluator
     {
         let last_state = trace.get_last_state();
         let ctx_depth = trace.ctx_depth();
         let loop_depth = trace.loop_depth();
         let stack_depth = trace.stack_depth();
         let trace_length = trace.unextended_length();
-        let extension_factor = MAX_CONSTRAINT_DEGREE;
+        let trace_degree = trace_length - 1 + trace.blinding_factor();
+        let extension_factor = EVALUATION_BLOWUP;
+        let (i_points, i_weights, f_points, f_weights) = get_boundary_points(segments, trace_length);
 
         // instantiate decoder and stack constraint evaluators 
         let decoder = Decoder::new(trace_length, extension_factor, ctx_depth, loop_depth);
@@ -50,45 +96,46 @@ impl Evaluator {
             decoder.constraint_degrees(), stack.constraint_degrees()
         ].concat();
 
-        // if we are in debug mode, initialize vectors to hold individual evaluations
-        // of transition constraints
         let domain_size = trace_length * extension_factor;
-        let t_evaluations = if cfg!(debug_assertions) {
-            t_constraint_degrees.iter().map(|_| uninit_vector(domain_size)).collect()
-        }
-        else {
-            Vec::new()
-        };
-
         return Evaluator {
             decoder         : decoder,
             stack           : stack,
-            coefficients    : ConstraintCoefficients::new(*trace_root, ctx_depth, loop_depth, stack_depth),
+            coefficients    : ConstraintCoefficients::new(*seed, ctx_depth, loop_depth, stack_depth),
             domain_size     : domain_size,
             extension_factor: extension_factor,
             t_constraint_num: t_constraint_degrees.len(),
-            t_degree_groups : group_transition_constraints(t_constraint_degrees, trace_length),
-            t_evaluations   : t_evaluations,
-            b_constraint_num: get_boundary_constraint_num(&inputs, &outputs),
+            t_degree_groups : group_transition_constraints(t_constraint_degrees, trace_length, trace_degree, segments.len()),
+            b_constraint_num: get_boundary_constraint_num(stack_depth, segments, &assertions),
             program_hash    : last_state.program_hash().to_vec(),
-            op_count        : last_state.op_counter(),
-            inputs          : inputs.to_vec(),
-            outputs         : outputs.to_vec(),
-            b_degree_adj    : get_boundary_constraint_adjustment_degree(trace_length),
+            segments        : segments.to_vec(),
+            i_points        : i_points,
+            i_weights       : i_weights,
+            f_points        : f_points,
+            f_weights       : f_weights,
+            assertions      : assertions.to_vec(),
+            b_degree_adj    : get_boundary_constraint_adjustment_degree(trace_length, trace_degree, segments.len()),
+            trace_degree    : trace_degree,
+            #[cfg(test)]
+            skip_checks     : SKIP_TRANSITION_CHECKS.with(|s| s.get()),
         };
     }
 
-    pub fn from_proof(proof: &StarkProof, program_hash: &[u8; 32], inputs: &[u128], outputs: &[u128]) -> Evaluator
+    /// Returns an evaluator for the trace committed to by the `proof`; `segments` describe program
+    /// executions contained in the trace.
+    pub fn from_proof(proof: &StarkProof, program_hash: &[u8; 32], segments: &[TraceSegment], assertions: &[Assertion], cache: &EvaluatorCache) -> Evaluator
     {
         let ctx_depth = proof.ctx_depth();
         let loop_depth = proof.loop_depth();
         let stack_depth = proof.stack_depth();
         let trace_length = proof.trace_length();
+        let trace_degree = trace_length - 1 + proof.options().blinding_factor();
         let extension_factor = proof.options().extension_factor();
+        let seed = get_constraint_seed(proof.trace_root(), proof.assertion_digest(), proof.options().hash_fn());
+        let (i_points, i_weights, f_points, f_weights) = get_boundary_points(segments, trace_length);
         
         // instantiate decoder and stack constraint evaluators 
-        let decoder = Decoder::new(trace_length, extension_factor, ctx_depth, loop_depth);
-        let stack = Stack::new(trace_length, extension_factor, stack_depth);
+        let decoder = cache.get_decoder(trace_length, extension_factor, ctx_depth, loop_depth);
+        let stack = cache.get_stack(trace_length, extension_factor, stack_depth);
 
         // build a list of transition constraint degrees
         let t_constraint_degrees = [
@@ -98,18 +145,23 @@ impl Evaluator {
         return Evaluator {
             decoder         : decoder,
             stack           : stack,
-            coefficients    : ConstraintCoefficients::new(*proof.trace_root(), ctx_depth, loop_depth, stack_depth),
+            coefficients    : ConstraintCoefficients::new(seed, ctx_depth, loop_depth, stack_depth),
             domain_size     : proof.domain_size(),
             extension_factor: extension_factor,
             t_constraint_num: t_constraint_degrees.len(),
-            t_degree_groups : group_transition_constraints(t_constraint_degrees, trace_length),
-            t_evaluations   : Vec::new(),
-            b_constraint_num: get_boundary_constraint_num(&inputs, &outputs),
+            t_degree_groups : group_transition_constraints(t_constraint_degrees, trace_length, trace_degree, segments.len()),
+            b_constraint_num: get_boundary_constraint_num(stack_depth, segments, &assertions),
             program_hash    : parse_program_hash(program_hash),
-            op_count        : proof.op_count(),
-            inputs          : inputs.to_vec(),
-            outputs         : outputs.to_vec(),
-            b_degree_adj    : get_boundary_constraint_adjustment_degree(trace_length),
+            segments        : segments.to_vec(),
+            i_points        : i_points,
+            i_weights       : i_weights,
+            f_points        : f_points,
+            f_weights       : f_weights,
+            assertions      : assertions.to_vec(),
+            b_degree_adj    : get_boundary_constraint_adjustment_degree(trace_length, trace_degree, segments.len()),
+            trace_degree    : trace_degree,
+            #[cfg(test)]
+            skip_checks     : false,
         };
     }
 
@@ -117,6 +169,19 @@ impl Evaluator {
         return self.t_constraint_num + self.b_constraint_num;
     }
 
+    /// Returns the number of transition constraints.
+    pub fn transition_constraint_count(&self) -> usize {
+        return self.t_constraint_num;
+    }
+
+    /// Returns max degrees of decoder and stack transition constraints.
+    pub fn constraint_degrees(&self) -> ConstraintDegrees {
+        return ConstraintDegrees {
+            decoder : *self.decoder.constraint_degrees().iter().max().unwrap(),
+            stack   : *self.stack.constraint_degrees().iter().max().unwrap(),
+        };
+    }
+
     pub fn domain_size(&self) -> usize {
         return self.domain_size;
     }
@@ -125,9 +190,25 @@ impl Evaluator {
         return self.domain_size / self.extension_factor;
     }
 
-    pub fn get_x_at_last_step(&self) -> u128 {
-        let trace_root = field::get_root_of_unity(self.trace_length());
-        return field::exp(trace_root, (self.trace_length() - 1) as u128);
+    /// Returns the degree of trace polynomials; this is greater than trace length - 1 when
+    /// random values are mixed into trace polynomials.
+    /// Returns the degree of the polynomial into which all constraints are combined.
+    pub fn combination_degree(&self) -> usize {
+        return get_combination_degree(self.trace_length(), self.trace_degree, self.segments.len());
+    }
+
+    pub fn trace_degree(&self) -> usize {
+        return self.trace_degree;
+    }
+
+    /// Returns x coordinates of the first steps of all program executions in the trace.
+    pub fn get_x_at_first_steps(&self) -> &[u128] {
+        return &self.i_points;
+    }
+
+    /// Returns x coordinates of the last steps of all program executions in the trace.
+    pub fn get_x_at_last_steps(&self) -> &[u128] {
+        return &self.f_points;
     }
 
     // CONSTRAINT EVALUATORS
@@ -136,20 +217,24 @@ impl Evaluator {
     /// Computes pseudo-random linear combination of transition constraints D_i at point x as:
     /// cc_{i * 2} * D_i + cc_{i * 2 + 1} * D_i * x^p for all i, where cc_j are the coefficients
     /// used in the linear combination and x^p is a degree adjustment factor (different for each degree).
+    #[cfg(test)]
     pub fn evaluate_transition(&self, current: &TraceState, next: &TraceState, x: u128, step: usize) -> u128 {
-        
-        // evaluate transition constraints
         let mut evaluations = vec![field::ZERO; self.t_constraint_num];
-        self.decoder.evaluate(&current, &next, step, &mut evaluations);
-        self.stack.evaluate(&current, &next, step, &mut evaluations[self.decoder.constraint_count()..]);
+        return self.evaluate_transition_into(current, next, x, step, &mut evaluations);
+    }
+
+    /// Same as evaluate_transition(), but also leaves evaluations of individual transition
+    /// constraints in `evaluations`; the slice must hold exactly one value per constraint.
+    pub fn evaluate_transition_into(&self, current: &TraceState, next: &TraceState, x: u128, step: usize, evaluations: &mut [u128]) -> u128 {
 
-        // when in debug mode, save transition evaluations before they are combined
-        #[cfg(debug_assertions)]
-        self.save_transition_evaluations(&evaluations, step);
+        // evaluate transition constraints
+        evaluations.iter_mut().for_each(|evaluation| *evaluation = field::ZERO);
+        self.decoder.evaluate(&current, &next, step, evaluations);
+        self.stack.evaluate(&current, &next, step, &mut evaluations[self.decoder.constraint_count()..]);
 
         // if the constraints should evaluate to all zeros at this step,
         // make sure they do, and return
-        if self.should_evaluate_to_zero_at(step) {
+        if self.should_evaluate_to_zero_at(step) && !self.skip_transition_checks() {
             let step = step / self.extension_factor;
             for i in 0..evaluations.len() {
                 assert!(evaluations[i] == field::ZERO, "transition constraint at step {} were not satisfied", step);
@@ -177,12 +262,18 @@ impl Evaluator {
     /// Computes pseudo-random linear combination of boundary constraints B_i at point x  separately
     /// for the first and for the last steps of the program; the constraints are computed as:
     /// cc_{i * 2} * B_i + cc_{i * 2 + 1} * B_i * x^p for all i, where cc_j are the coefficients
-    /// used in the linear combination and x^p is a degree adjustment factor.
+    /// used in the linear combination and x^p is a degree adjustment factor. When the trace contains
+    /// several program executions, values expected at the first (or the last) steps of all executions
+    /// are interpolated into a single polynomial.
     pub fn evaluate_boundaries(&self, current: &TraceState, x: u128) -> (u128, u128) {
         
         // compute degree adjustment factor
         let xp = field::exp(x, self.b_degree_adj);
 
+        // compute weights for interpolating values expected at the first and the last steps
+        let i_basis = lagrange_basis_at(&self.i_points, &self.i_weights, x);
+        let f_basis = lagrange_basis_at(&self.f_points, &self.f_weights, x);
+
         // 1 ----- compute combination of boundary constraints for the first step ------------------
         let mut i_result = field::ZERO;
         let mut result_adj = field::ZERO;
@@ -240,14 +331,30 @@ impl Evaluator {
             result_adj = field::add(result_adj, field::mul(loop_stack[i], cc.loop_stack[i * 2 + 1]));
         }
 
-        // make sure stack registers are set to inputs
+        // make sure stack registers are set to inputs, and all other stack registers are 0s
         let user_stack = current.user_stack();
-        for i in 0..self.inputs.len() {
-            let val = field::sub(user_stack[i], self.inputs[i]);
+        for i in 0..current.stack_depth() {
+            let input = interpolate(&i_basis, self.segments.iter()
+                .map(|s| if i < s.inputs.len() { s.inputs[i] } else { field::ZERO }));
+            let val = field::sub(user_stack[i], input);
             i_result = field::add(i_result, field::mul(val, cc.user_stack[i * 2]));
             result_adj = field::add(result_adj, field::mul(val, cc.user_stack[i * 2 + 1]));
         }
 
+        // make sure stack depth is set to the number of inputs; BEGIN reads no items, and so
+        // the depth floor is the depth of the stack
+        let input_count = interpolate(&i_basis, self.segments.iter().map(|s| s.inputs.len() as u128));
+        let val = field::sub(current.depth_floor(), input_count);
+        i_result = field::add(i_result, field::mul(val, cc.watermark[0]));
+        result_adj = field::add(result_adj, field::mul(val, cc.watermark[1]));
+
+        // make sure stack registers satisfy assertions against the first step
+        for assertion in self.assertions.iter().filter(|a| a.step == AssertionStep::First) {
+            let val = field::sub(user_stack[assertion.register], assertion.value);
+            i_result = field::add(i_result, field::mul(val, cc.assertions[assertion.register * 2]));
+            result_adj = field::add(result_adj, field::mul(val, cc.assertions[assertion.register * 2 + 1]));
+        }
+
         // raise the degree of adjusted terms and sum all the terms together
         i_result = field::add(i_result, field::mul(result_adj, xp));
 
@@ -258,7 +365,8 @@ impl Evaluator {
         let cc = &self.coefficients.f_boundary;
         
         // make sure op_counter register is set to the claimed value of operations
-        let val = field::sub(current.op_counter(), self.op_count);
+        let op_count = interpolate(&f_basis, self.segments.iter().map(|s| s.op_count));
+        let val = field::sub(current.op_counter(), op_count);
         f_result = field::add(f_result, field::mul(val, cc.op_counter[0]));
         result_adj = field::add(result_adj, field::mul(val, cc.op_counter[1]));
 
@@ -313,11 +421,26 @@ impl Evaluator {
         }
 
         // make sure user stack registers are set to outputs
-        for i in 0..self.outputs.len() {
-            let val = field::sub(user_stack[i], self.outputs[i]);
+        for i in 0..self.segments[0].outputs.len() {
+            let output = interpolate(&f_basis, self.segments.iter().map(|s| s.outputs[i]));
+            let val = field::sub(user_stack[i], output);
             f_result = field::add(f_result, field::mul(val, cc.user_stack[i * 2]));
             result_adj = field::add(result_adj, field::mul(val, cc.user_stack[i * 2 + 1]));
-        }        
+        }
+
+        // make sure stack depth is set to the claimed final depth; NOOP reads no items, and so
+        // the depth floor is the depth of the stack
+        let final_depth = interpolate(&f_basis, self.segments.iter().map(|s| s.final_depth));
+        let val = field::sub(current.depth_floor(), final_depth);
+        f_result = field::add(f_result, field::mul(val, cc.watermark[0]));
+        result_adj = field::add(result_adj, field::mul(val, cc.watermark[1]));
+
+        // make sure stack registers satisfy assertions against the last step
+        for assertion in self.assertions.iter().filter(|a| a.step == AssertionStep::Last) {
+            let val = field::sub(user_stack[assertion.register], assertion.value);
+            f_result = field::add(f_result, field::mul(val, cc.assertions[assertion.register * 2]));
+            result_adj = field::add(result_adj, field::mul(val, cc.assertions[assertion.register * 2 + 1]));
+        }
 
         // raise the degree of adjusted terms and sum all the terms together
         f_result = field::add(f_result, field::mul(result_adj, xp));
@@ -327,12 +450,22 @@ impl Evaluator {
 
     // HELPER METHODS
     // -------------------------------------------------------------------------------------------
+    #[cfg(test)]
+    fn skip_transition_checks(&self) -> bool {
+        return self.skip_checks;
+    }
+
+    #[cfg(not(test))]
+    fn skip_transition_checks(&self) -> bool {
+        return false;
+    }
+
     fn should_evaluate_to_zero_at(&self, step: usize) -> bool {
         return (step & (self.extension_factor - 1) == 0) // same as: step % extension_factor == 0
-            && (step != self.domain_size - self.extension_factor);
+            && self.segments.iter().all(|s| step != s.last_step * self.extension_factor);
     }
 
-    fn combine_transition_constraints(&self, evaluations: &Vec<u128>, x: u128) -> u128 {
+    fn combine_transition_constraints(&self, evaluations: &[u128], x: u128) -> u128 {
         let cc = &self.coefficients.transition;
         let mut result = field::ZERO;
 
@@ -357,21 +490,6 @@ impl Evaluator {
         return result;
     }
 
-    #[cfg(debug_assertions)]
-    fn save_transition_evaluations(&self, evaluations: &[u128], step: usize) {
-        unsafe {
-            let mutable_self = &mut *(self as *const _ as *mut Evaluator);
-            for i in 0..evaluations.len() {
-                mutable_self.t_evaluations[i][step] = evaluations[i];
-            }
-        }
-    }
-
-    #[cfg(debug_assertions)]
-    pub fn get_transition_evaluations(&self) -> &Vec<Vec<u128>> {
-        return &self.t_evaluations;
-    }
-
     #[cfg(debug_assertions)]
     pub fn get_transition_degrees(&self) -> Vec<usize> {
         return [
@@ -380,9 +498,35 @@ impl Evaluator {
     }
 }
 
+// EVALUATOR CACHE IMPLEMENTATION
+// ================================================================================================
+impl EvaluatorCache {
+
+    pub fn new() -> EvaluatorCache {
+        return EvaluatorCache {
+            decoders    : Mutex::new(HashMap::new()),
+            stacks      : Mutex::new(HashMap::new()),
+        };
+    }
+
+    fn get_decoder(&self, trace_length: usize, extension_factor: usize, ctx_depth: usize, loop_depth: usize) -> Decoder {
+        let mut decoders = self.decoders.lock().unwrap();
+        return decoders.entry((trace_length, extension_factor, ctx_depth, loop_depth))
+            .or_insert_with(|| Decoder::new(trace_length, extension_factor, ctx_depth, loop_depth))
+            .clone();
+    }
+
+    fn get_stack(&self, trace_length: usize, extension_factor: usize, stack_depth: usize) -> Stack {
+        let mut stacks = self.stacks.lock().unwrap();
+        return stacks.entry((trace_length, extension_factor, stack_depth))
+            .or_insert_with(|| Stack::new(trace_length, extension_factor, stack_depth))
+            .clone();
+    }
+}
+
 // HELPER FUNCTIONS
 // ================================================================================================
-fn group_transition_constraints(degrees: Vec<usize>, trace_length: usize) -> Vec<(u128, Vec<usize>)> {
+fn group_transition_constraints(degrees: Vec<usize>, trace_length: usize, trace_degree: usize, num_segments: usize) -> Vec<(u128, Vec<usize>)> {
     let mut groups = [
         Vec::new(), Vec::new(), Vec::new(), Vec::new(), Vec::new(),
         Vec::new(), Vec::new(), Vec::new(), Vec::new(),
@@ -392,12 +536,12 @@ fn group_transition_constraints(degrees: Vec<usize>, trace_length: usize) -> Vec
         groups[degree].push(i);
     }
 
-    let target_degree = get_transition_constraint_target_degree(trace_length);
+    let target_degree = get_transition_constraint_target_degree(trace_length, trace_degree, num_segments);
 
     let mut result = Vec::new();
     for (degree, constraints) in groups.iter().enumerate() {
         if constraints.len() == 0 { continue; }
-        let constraint_degree = (trace_length - 1) * degree;    
+        let constraint_degree = trace_degree * degree;
         let incremental_degree = (target_degree - constraint_degree) as u128;
         result.push((incremental_degree, constraints.clone()));
     }
@@ -405,30 +549,37 @@ fn group_transition_constraints(degrees: Vec<usize>, trace_length: usize) -> Vec
     return result;
 }
 
-fn get_boundary_constraint_adjustment_degree(trace_length: usize) -> u128 {
-    let target_degree = get_boundary_constraint_target_degree(trace_length);
-    let boundary_constraint_degree = trace_length - 1;
+fn get_boundary_constraint_adjustment_degree(trace_length: usize, trace_degree: usize, num_segments: usize) -> u128 {
+    let target_degree = get_boundary_constraint_target_degree(trace_length, trace_degree, num_segments);
+    let boundary_constraint_degree = trace_degree;
     return (target_degree - boundary_constraint_degree) as u128;
 }
 
 /// target degree for boundary constraints is set so that when divided by boundary
-/// constraint divisor (degree 1 polynomial), the degree will be equal to
+/// constraint divisor (one degree 1 factor per segment), the degree will be equal to
 /// deg(combination domain) - deg(trace)
-fn get_boundary_constraint_target_degree(trace_length: usize) -> usize {
-    let combination_degree = (MAX_CONSTRAINT_DEGREE - 1) * trace_length;
-    let divisor_degree = 1;
+fn get_boundary_constraint_target_degree(trace_length: usize, trace_degree: usize, num_segments: usize) -> usize {
+    let combination_degree = get_combination_degree(trace_length, trace_degree, num_segments);
+    let divisor_degree = num_segments;
     return combination_degree + divisor_degree;
 }
 
 /// target degree for transition constraints is set so when divided transition 
-/// constraint divisor (deg(trace) - 1 polynomial), the degree will be equal to
+/// constraint divisor (deg(trace) - num_segments polynomial), the degree will be equal to
 /// deg(combination domain) - deg(trace)
-fn get_transition_constraint_target_degree(trace_length: usize) -> usize {
-    let combination_degree = (MAX_CONSTRAINT_DEGREE - 1) * trace_length;
-    let divisor_degree = trace_length - 1;
+fn get_transition_constraint_target_degree(trace_length: usize, trace_degree: usize, num_segments: usize) -> usize {
+    let combination_degree = get_combination_degree(trace_length, trace_degree, num_segments);
+    let divisor_degree = trace_length - num_segments;
     return combination_degree + divisor_degree;
 }
 
+/// degree of the constraint combination is one greater than the degree of the composition
+/// polynomial because the combination is divided by (x - z) during composition
+fn get_combination_degree(trace_length: usize, trace_degree: usize, num_segments: usize) -> usize {
+    let blinding_factor = trace_degree + 1 - trace_length;
+    return utils::get_composition_degree(trace_length, blinding_factor, num_segments) + 1;
+}
+
 fn parse_program_hash(program_hash: &[u8; 32]) -> Vec<u128> {
     return vec![
         field::from_bytes(&program_hash[..16]),
@@ -436,9 +587,42 @@ fn parse_program_hash(program_hash: &[u8; 32]) -> Vec<u128> {
     ];
 }
 
-fn get_boundary_constraint_num(inputs: &[u128], outputs: &[u128]) -> usize {
+/// Returns x coordinates of the first and the last steps of all `segments` together with
+/// barycentric weights for interpolating values expected at these steps.
+fn get_boundary_points(segments: &[TraceSegment], trace_length: usize) -> (Vec<u128>, Vec<u128>, Vec<u128>, Vec<u128>) {
+    assert!(segments.len() > 0, "execution trace must contain at least one segment");
+    for segment in segments.iter() {
+        assert!(segment.outputs.len() == segments[0].outputs.len(),
+            "all segments must have the same number of outputs");
+    }
+
+    let trace_root = field::get_root_of_unity(trace_length);
+    let i_points = segments.iter()
+        .map(|s| field::exp(trace_root, s.first_step as u128))
+        .collect::<Vec<u128>>();
+    let f_points = segments.iter()
+        .map(|s| field::exp(trace_root, s.last_step as u128))
+        .collect::<Vec<u128>>();
+
+    let i_weights = lagrange_weights(&i_points);
+    let f_weights = lagrange_weights(&f_points);
+    return (i_points, i_weights, f_points, f_weights);
+}
+
+/// Combines values expected at each segment using the provided Lagrange `basis`.
+fn interpolate(basis: &[u128], values: impl Iterator<Item = u128>) -> u128 {
+    let mut result = field::ZERO;
+    for (&b, value) in basis.iter().zip(values) {
+        result = field::add(result, field::mul(b, value));
+    }
+    return result;
+}
+
+fn get_boundary_constraint_num(stack_depth: usize, segments: &[TraceSegment], assertions: &[Assertion]) -> usize {
     return
         PROGRAM_DIGEST_SIZE 
-        + inputs.len() + outputs.len()
+        + stack_depth + segments[0].outputs.len()
+        + assertions.len()
+        + 2 /* for initial and final stack depth */
         + 1 /* for op_count */;
 }
\ No newline at end of file
@@ -7,6 +7,10 @@ mod utils;
 
 pub use decoder::{ NUM_STATIC_DECODER_CONSTRAINTS };
 pub use stack::{ NUM_AUX_CONSTRAINTS as NUM_AUX_STACK_CONSTRAINTS };
-pub use evaluator::{ Evaluator as ConstraintEvaluator};
+pub use evaluator::{ Evaluator as ConstraintEvaluator, ConstraintDegrees, EvaluatorCache };
 pub use constraint_table::{ ConstraintTable };
-pub use constraint_poly::{ ConstraintPoly };
\ No newline at end of file
+pub use constraint_poly::{ ConstraintPoly };
+#[cfg(test)]
+pub use stack::BROKEN_MUL;
+#[cfg(test)]
+pub(crate) use evaluator::SKIP_TRANSITION_CHECKS;
\ No newline at end of file
@@ -4,6 +4,13 @@ use super::{
     enforce_left_shift, enforce_stack_copy,
 };
 
+#[cfg(test)]
+thread_local! {
+    /// When set, constraints of MUL operation are evaluated incorrectly whenever the first operand
+    /// is 0; used to test tools which look for failing programs.
+    pub static BROKEN_MUL: std::cell::Cell<bool> = std::cell::Cell::new(false);
+}
+
 // ARITHMETIC OPERATION
 // ================================================================================================
 
@@ -27,6 +34,8 @@ pub fn enforce_mul(result: &mut [u128], old_stack: &[u128], new_stack: &[u128],
     let x = old_stack[0];
     let y = old_stack[1];
     let op_result = field::mul(x, y);
+    #[cfg(test)]
+    let op_result = if BROKEN_MUL.with(|b| b.get()) && x == field::ZERO { y } else { op_result };
     result.agg_constraint(0, op_flag, are_equal(new_stack[0], op_result));
 
     // ensure that the rest of the stack is shifted by 1 item to the left
@@ -60,6 +69,39 @@ pub fn enforce_neg(result: &mut [u128], old_stack: &[u128], new_stack: &[u128],
     enforce_stack_copy(result, old_stack, new_stack, 1, op_flag);
 }
 
+/// Enforces constraints for EXPACC operation. The constraints are based on the first 5 elements
+/// of the stack; the rest of the stack is unaffected.
+pub fn enforce_expacc(result: &mut [u128], old_stack: &[u128], new_stack: &[u128], op_flag: u128)
+{
+    // layout of first 5 registers:
+    // [exponent bit, base, result, power of two, accumulated exponent]
+    // exponent bit is located in the next state (not current state)
+
+    // the bit was a binary value
+    let bit = new_stack[0];
+    result.agg_constraint(0, op_flag, is_binary(bit));
+
+    // base register was squared
+    let base = old_stack[1];
+    result.agg_constraint(1, op_flag, are_equal(new_stack[1], field::mul(base, base)));
+
+    // result register was multiplied by the base when the bit is 1, and was left unchanged
+    // when the bit is 0: r' = r * (bit * base - bit + 1)
+    let multiplier = field::add(field::sub(field::mul(bit, base), bit), field::ONE);
+    result.agg_constraint(2, op_flag, are_equal(new_stack[2], field::mul(old_stack[2], multiplier)));
+
+    // power of 2 register was updated correctly
+    let power_of_two = old_stack[3];
+    result.agg_constraint(3, op_flag, are_equal(new_stack[3], field::mul(power_of_two, 2)));
+
+    // exponent accumulator was updated correctly
+    let acc = field::add(old_stack[4], field::mul(bit, power_of_two));
+    result.agg_constraint(4, op_flag, are_equal(new_stack[4], acc));
+
+    // ensure nothing changed beyond the 5th item of the stack
+    enforce_stack_copy(result, old_stack, new_stack, 5, op_flag);
+}
+
 // BOOLEAN OPERATION
 // ================================================================================================
 
@@ -116,4 +158,35 @@ pub fn enforce_or(result: &mut [u128], aux: &mut[u128], old_stack: &[u128], new_
     // ensure that both operands are binary values
     aux.agg_constraint(0, op_flag, is_binary(x));
     aux.agg_constraint(1, op_flag, is_binary(y));
-}
\ No newline at end of file
+}
+
+// TESTS
+// ================================================================================================
+#[cfg(test)]
+mod tests {
+
+    use super::{ are_equal, is_binary };
+
+    #[test]
+    fn expacc() {
+        let old_stack = [0, 3, 1, 1, 0, 5, 7, 0];
+        let mut result = vec![0; 8];
+
+        // multiplying the result by the base when the bit is 1 satisfies all constraints
+        let new_stack = [1, 9, 3, 2, 1, 5, 7, 0];
+        super::enforce_expacc(&mut result, &old_stack, &new_stack, 1);
+        assert_eq!(vec![0; 8], result);
+
+        // leaving the result unchanged when the bit is 1 does not
+        let new_stack = [1, 9, 1, 2, 1, 5, 7, 0];
+        super::enforce_expacc(&mut result, &old_stack, &new_stack, 1);
+        assert_eq!(are_equal(1, 3), result[2]);
+
+        // a non-binary bit fails the binary check
+        let new_stack = [2, 9, 5, 2, 2, 5, 7, 0];
+        let mut result = vec![0; 8];
+        super::enforce_expacc(&mut result, &old_stack, &new_stack, 1);
+        assert_eq!(is_binary(2), result[0]);
+        assert_eq!(vec![0; 7], result[1..].to_vec());
+    }
+}
@@ -131,4 +131,51 @@ pub fn enforce_binacc(result: &mut [u128], old_stack: &[u128], new_stack: &[u128
 
     // registers beyond 2nd register remained the same
     enforce_stack_copy(result, old_stack, new_stack, 4, op_flag);
-}
\ No newline at end of file
+}
+
+// TESTS
+// ================================================================================================
+#[cfg(test)]
+mod tests {
+
+    use crate::math::field;
+
+    #[test]
+    fn eq_with_zero() {
+        // this is the layout produced by iszero instruction: 0 is compared with x = 5, and so
+        // the inverse of -5 is the auxiliary value
+        let inv = field::inv(field::neg(5));
+        let old_stack = [inv, 0, 5, 7, 0, 0, 0, 0];
+        let mut result = vec![0; 8];
+        let mut aux = vec![0; 1];
+
+        // setting the result to 0 satisfies all constraints
+        let new_stack = [0, 7, 0, 0, 0, 0, 0, 0];
+        super::enforce_eq(&mut result, &mut aux, &old_stack, &new_stack, 1);
+        assert_eq!(vec![0; 8], result);
+        assert_eq!(vec![0], aux);
+
+        // claiming that 5 is equal to 0 does not
+        let new_stack = [1, 7, 0, 0, 0, 0, 0, 0];
+        super::enforce_eq(&mut result, &mut aux, &old_stack, &new_stack, 1);
+        assert!(result[0] != field::ZERO);
+        assert!(aux[0] != field::ZERO);
+
+        // neither does a cheating inverse combined with the result set to 1
+        let old_stack = [0, 0, 5, 7, 0, 0, 0, 0];
+        let mut result = vec![0; 8];
+        let mut aux = vec![0; 1];
+        super::enforce_eq(&mut result, &mut aux, &old_stack, &new_stack, 1);
+        assert_eq!(vec![0; 8], result);
+        assert_eq!(vec![field::neg(5)], aux);
+
+        // when x = 0, the result must be 1 regardless of the auxiliary value
+        let old_stack = [3, 0, 0, 7, 0, 0, 0, 0];
+        let new_stack = [0, 7, 0, 0, 0, 0, 0, 0];
+        let mut result = vec![0; 8];
+        let mut aux = vec![0; 1];
+        super::enforce_eq(&mut result, &mut aux, &old_stack, &new_stack, 1);
+        assert!(result[0] != field::ZERO);
+        assert_eq!(vec![0], aux);
+    }
+}
@@ -80,4 +80,124 @@ pub fn enforce_cswap2(result: &mut [u128], aux: &mut [u128], old_stack: &[u128],
 
     // make sure the condition was a binary value
     aux.agg_constraint(0, op_flag, is_binary(condition));
-}
\ No newline at end of file
+}
+
+/// Enforces constraints for CSWAP4 operation. These constraints work with top 10 registers of
+/// the stack and enforce that when condition = 1, (v4, v5, v6, v7) move to the top of the stack;
+/// when condition = 0, top 8 elements of the stack remain unchanged.
+pub fn enforce_cswap4(result: &mut [u128], aux: &mut [u128], old_stack: &[u128], new_stack: &[u128], op_flag: u128)
+{
+    // the stack can be narrower than 10 registers only if CSWAP4 is never executed; in this
+    // case, the missing condition register is treated as 0
+    let condition = if old_stack.len() > 8 { old_stack[8] } else { field::ZERO };
+
+    let not_condition = binary_not(condition);
+    for i in 0..4 {
+        let op_result0 = field::add(field::mul(condition, old_stack[i + 4]), field::mul(not_condition, old_stack[i]));
+        let op_result1 = field::add(field::mul(condition, old_stack[i]), field::mul(not_condition, old_stack[i + 4]));
+        result.agg_constraint(i, op_flag, are_equal(new_stack[i], op_result0));
+        result.agg_constraint(i + 4, op_flag, are_equal(new_stack[i + 4], op_result1));
+    }
+
+    // registers beyond the 10th are shifted left by 2 slots
+    enforce_left_shift(result, old_stack, new_stack, 10, 2, op_flag);
+
+    // make sure the condition was a binary value
+    aux.agg_constraint(0, op_flag, is_binary(condition));
+}
+
+// TESTS
+// ================================================================================================
+#[cfg(test)]
+mod tests {
+
+    use crate::math::field;
+    use super::{ are_equal, is_binary };
+
+    #[test]
+    fn choose_non_binary_condition() {
+        // with condition = 2, the next state is consistent with 2 * x - y being selected
+        let old_stack = [4, 5, 2, 7, 0, 0, 0, 0];
+        let new_stack = [3, 7, 0, 0, 0, 0, 0, 0];
+
+        let mut result = vec![0; 8];
+        let mut aux = vec![0; 1];
+        super::enforce_choose(&mut result, &mut aux, &old_stack, &new_stack, 1);
+        assert_eq!(vec![0; 8], result);
+        assert_eq!(vec![is_binary(2)], aux);
+        assert!(aux[0] != field::ZERO);
+
+        // binary conditions satisfy all constraints
+        let old_stack = [4, 5, 1, 7, 0, 0, 0, 0];
+        let new_stack = [4, 7, 0, 0, 0, 0, 0, 0];
+
+        let mut result = vec![0; 8];
+        let mut aux = vec![0; 1];
+        super::enforce_choose(&mut result, &mut aux, &old_stack, &new_stack, 1);
+        assert_eq!(vec![0; 8], result);
+        assert_eq!(vec![0], aux);
+
+        // selecting y when condition = 1 breaks the main constraint
+        let new_stack = [5, 7, 0, 0, 0, 0, 0, 0];
+        let mut result = vec![0; 8];
+        let mut aux = vec![0; 1];
+        super::enforce_choose(&mut result, &mut aux, &old_stack, &new_stack, 1);
+        assert_eq!(are_equal(5, 4), result[0]);
+        assert_eq!(vec![0], aux);
+    }
+
+    #[test]
+    fn choose2_non_binary_condition() {
+        // with condition = 2, the next state is consistent with 2 * x - y being selected
+        let old_stack = [4, 6, 5, 7, 2, 9, 0, 0];
+        let new_stack = [3, 5, 0, 0, 0, 0, 0, 0];
+
+        let mut result = vec![0; 8];
+        let mut aux = vec![0; 1];
+        super::enforce_choose2(&mut result, &mut aux, &old_stack, &new_stack, 1);
+        assert_eq!(vec![0; 8], result);
+        assert_eq!(vec![is_binary(2)], aux);
+        assert!(aux[0] != field::ZERO);
+    }
+
+    #[test]
+    fn cswap2_non_binary_condition() {
+        // with condition = 2, the next state is consistent with 2 * v2 - v0 being swapped in
+        let old_stack = [4, 6, 5, 7, 2, 9, 0, 0];
+        let new_stack = [6, 8, 3, 5, 0, 0, 0, 0];
+
+        let mut result = vec![0; 8];
+        let mut aux = vec![0; 1];
+        super::enforce_cswap2(&mut result, &mut aux, &old_stack, &new_stack, 1);
+        assert_eq!(vec![0; 8], result);
+        assert_eq!(vec![is_binary(2)], aux);
+        assert!(aux[0] != field::ZERO);
+    }
+
+    #[test]
+    fn cswap4() {
+        let old_stack = [1, 2, 3, 4, 5, 6, 7, 8, 1, 9, 10, 0];
+        let mut result = vec![0; 12];
+        let mut aux = vec![0; 1];
+
+        // swapping the words when condition = 1 satisfies all constraints
+        let new_stack = [5, 6, 7, 8, 1, 2, 3, 4, 10, 0, 0, 0];
+        super::enforce_cswap4(&mut result, &mut aux, &old_stack, &new_stack, 1);
+        assert_eq!(vec![0; 12], result);
+        assert_eq!(vec![0], aux);
+
+        // leaving the words in place when condition = 1 does not
+        let new_stack = [1, 2, 3, 4, 5, 6, 7, 8, 10, 0, 0, 0];
+        super::enforce_cswap4(&mut result, &mut aux, &old_stack, &new_stack, 1);
+        assert_eq!(are_equal(1, 5), result[0]);
+        assert_eq!(are_equal(5, 1), result[4]);
+        assert_eq!(vec![0], aux);
+
+        // condition = 2 fails the binary check
+        let old_stack = [1, 2, 3, 4, 5, 6, 7, 8, 2, 9, 10, 0];
+        let mut aux = vec![0; 1];
+        super::enforce_cswap4(&mut vec![0; 12], &mut aux, &old_stack, &new_stack, 1);
+        assert_eq!(vec![is_binary(2)], aux);
+        assert!(aux[0] != field::ZERO);
+    }
+}
@@ -0,0 +1,74 @@
+use crate::{ math::field, processor::OpCode, stark::TraceState };
+use crate::NUM_DEPTH_BITS;
+use super::{ are_equal, is_binary };
+
+// CONSTANTS
+// ================================================================================================
+
+/// Low-degree operations which change depth of the stack.
+const LD_DEPTH_OPS: [OpCode; 21] = [
+    OpCode::Assert, OpCode::AssertEq, OpCode::Eq,     OpCode::Drop,    OpCode::Drop4,
+    OpCode::Choose, OpCode::Choose2,  OpCode::CSwap2, OpCode::CSwap4,  OpCode::Add,
+    OpCode::Mul,    OpCode::And,      OpCode::Or,     OpCode::Read,    OpCode::Read2,
+    OpCode::Read4,  OpCode::Dup,      OpCode::Dup2,   OpCode::Dup4,    OpCode::Dup8,
+    OpCode::Pad2,
+];
+
+/// Max number of positions by which an operation can shift the stack to the right.
+pub const MAX_RIGHT_SHIFT: usize = 8;
+
+/// Number of constraints enforced for the stack depth registers.
+pub const NUM_DEPTH_CONSTRAINTS: usize = 1 + MAX_RIGHT_SHIFT + NUM_DEPTH_BITS;
+
+// CONSTRAINT EVALUATOR
+// ================================================================================================
+
+/// Enforces constraints for the stack depth registers. The first constraint makes sure that the
+/// depth changes by the net stack effect of the executed operation. The next constraints make
+/// sure that operations which shift the stack to the right shift only zeros out of the bottom
+/// registers; together with boundary constraints, this guarantees that all registers at or
+/// beyond the stack depth are zeros. The last constraints make sure that depth registers hold
+/// binary values. Depth registers hold the depth less the number of items read by the executed
+/// operation, and so this keeps the depth at or above the number of read items; thus, an
+/// operation cannot read items below the bottom of the stack, and the depth cannot go below 0
+/// even if it is brought back up before the end of the execution.
+pub fn enforce_depth(result: &mut [u128], current: &TraceState, next: &TraceState)
+{
+    let ld_flags = current.ld_op_flags();
+    let hd_flags = current.hd_op_flags();
+
+    let ops = LD_DEPTH_OPS.iter().map(|&op| (op, ld_flags[op.ld_index()]))
+        .chain(std::iter::once((OpCode::Push, hd_flags[OpCode::Push.hd_index()])));
+
+    // compute expected change in depth, and flags for operations which shift the stack
+    // to the right by at least 1, 2, ..., 8 positions
+    let mut depth_change = field::ZERO;
+    let mut shift_flags = [field::ZERO; MAX_RIGHT_SHIFT];
+    for (op, op_flag) in ops {
+        let delta = op.stack_delta();
+        let change = field::mul(op_flag, delta.abs() as u128);
+        if delta > 0 {
+            depth_change = field::add(depth_change, change);
+            for i in 0..(delta as usize) {
+                shift_flags[i] = field::add(shift_flags[i], op_flag);
+            }
+        }
+        else {
+            depth_change = field::sub(depth_change, change);
+        }
+    }
+
+    result[0] = are_equal(next.watermark(), field::add(current.watermark(), depth_change));
+
+    // values shifted out of the bottom of the stack must be zeros
+    let old_stack = current.user_stack();
+    let stack_depth = current.stack_depth();
+    for i in 0..std::cmp::min(MAX_RIGHT_SHIFT, stack_depth) {
+        result[1 + i] = field::mul(old_stack[stack_depth - 1 - i], shift_flags[i]);
+    }
+
+    // depth registers must contain binary values
+    for (i, &bit) in next.depth_bits().iter().enumerate() {
+        result[1 + MAX_RIGHT_SHIFT + i] = is_binary(bit);
+    }
+}
@@ -20,4 +20,11 @@ pub fn enforce_read(result: &mut [u128], old_stack: &[u128], new_stack: &[u128],
 pub fn enforce_read2(result: &mut [u128], old_stack: &[u128], new_stack: &[u128], op_flag: u128)
 {
     enforce_right_shift(result, old_stack, new_stack, 2, op_flag);
+}
+
+/// Enforces constraints for READ4 operation. No constraints are placed on the first four
+/// elements of the stack; the old stack is shifted right by 4 elements.
+pub fn enforce_read4(result: &mut [u128], old_stack: &[u128], new_stack: &[u128], op_flag: u128)
+{
+    enforce_right_shift(result, old_stack, new_stack, 4, op_flag);
 }
\ No newline at end of file
@@ -35,6 +35,16 @@ pub fn enforce_dup4(result: &mut [u128], old_stack: &[u128], new_stack: &[u128],
     enforce_right_shift(result, old_stack, new_stack, 4, op_flag);
 }
 
+/// Enforces constraints for DUP8 operation. The constraints are based on the first 8 element
+/// of the stack; the old stack is shifted right by 8 element.
+pub fn enforce_dup8(result: &mut [u128], old_stack: &[u128], new_stack: &[u128], op_flag: u128)
+{
+    for i in 0..8 {
+        result.agg_constraint(i, op_flag, are_equal(new_stack[i], old_stack[i]));
+    }
+    enforce_right_shift(result, old_stack, new_stack, 8, op_flag);
+}
+
 /// Enforces constraints for PAD2 operation. The constraints are based on the first 2 element
 /// of the stack; the old stack is shifted right by 2 element.
 pub fn enforce_pad2(result: &mut [u128], old_stack: &[u128], new_stack: &[u128], op_flag: u128)
@@ -11,17 +11,19 @@ use super::utils::{
 };
 
 mod input;
-use input::{ enforce_push, enforce_read, enforce_read2 };
+use input::{ enforce_push, enforce_read, enforce_read2, enforce_read4 };
 
 mod arithmetic;
 use arithmetic::{
-    enforce_add, enforce_mul, enforce_inv, enforce_neg,
+    enforce_add, enforce_mul, enforce_inv, enforce_neg, enforce_expacc,
     enforce_not, enforce_and, enforce_or,
 };
+#[cfg(test)]
+pub use arithmetic::BROKEN_MUL;
 
 mod manipulation;
 use manipulation::{
-    enforce_dup, enforce_dup2, enforce_dup4, enforce_pad2, enforce_drop, enforce_drop4,
+    enforce_dup, enforce_dup2, enforce_dup4, enforce_dup8, enforce_pad2, enforce_drop, enforce_drop4,
     enforce_swap, enforce_swap2, enforce_swap4, enforce_roll4, enforce_roll8,
 };
 
@@ -29,19 +31,26 @@ mod comparison;
 use comparison::{ enforce_assert, enforce_asserteq, enforce_eq, enforce_cmp, enforce_binacc };
 
 mod conditional;
-use conditional::{ enforce_choose, enforce_choose2, enforce_cswap2 };
+use conditional::{ enforce_choose, enforce_choose2, enforce_cswap2, enforce_cswap4 };
 
 mod hash;
 use hash::{ enforce_rescr };
 
+mod depth;
+use depth::{ enforce_depth, NUM_DEPTH_CONSTRAINTS };
+
 // CONSTANTS
 // ================================================================================================
-pub const NUM_AUX_CONSTRAINTS: usize = 2;
-const AUX_CONSTRAINT_DEGREES: [usize; NUM_AUX_CONSTRAINTS] = [7, 7];
-const STACK_TRANSITION_DEGREE: usize = 7; // degree for all stack register transition constraints
+const NUM_OP_AUX_CONSTRAINTS: usize = 2;
+pub const NUM_AUX_CONSTRAINTS: usize = NUM_OP_AUX_CONSTRAINTS + NUM_DEPTH_CONSTRAINTS;
+const AUX_CONSTRAINT_DEGREES: [usize; NUM_AUX_CONSTRAINTS] = [
+    5, 5, 4, 4, 4, 4, 4, 4, 4, 4, 4, 2, 2, 2, 2, 2, 2,
+];
+const STACK_TRANSITION_DEGREE: usize = 6; // degree for all stack register transition constraints
 
 // TYPES AND INTERFACES
 // ================================================================================================
+#[derive(Clone)]
 pub struct Stack {
     trace_length        : usize,
     cycle_length        : usize,
@@ -138,11 +147,13 @@ fn enforce_constraints(current: &TraceState, next: &TraceState, ark: &[u128], re
     // input operations
     enforce_read    (&mut evaluations,      old_stack, new_stack, ld_flags[OpCode::Read.ld_index()]);
     enforce_read2   (&mut evaluations,      old_stack, new_stack, ld_flags[OpCode::Read2.ld_index()]);
+    enforce_read4   (&mut evaluations,      old_stack, new_stack, ld_flags[OpCode::Read4.ld_index()]);
 
     // stack manipulation operations
     enforce_dup     (&mut evaluations,      old_stack, new_stack, ld_flags[OpCode::Dup.ld_index()]);
     enforce_dup2    (&mut evaluations,      old_stack, new_stack, ld_flags[OpCode::Dup2.ld_index()]);
     enforce_dup4    (&mut evaluations,      old_stack, new_stack, ld_flags[OpCode::Dup4.ld_index()]);
+    enforce_dup8    (&mut evaluations,      old_stack, new_stack, ld_flags[OpCode::Dup8.ld_index()]);
     enforce_pad2    (&mut evaluations,      old_stack, new_stack, ld_flags[OpCode::Pad2.ld_index()]);
 
     enforce_drop    (&mut evaluations,      old_stack, new_stack, ld_flags[OpCode::Drop.ld_index()]);
@@ -160,6 +171,7 @@ fn enforce_constraints(current: &TraceState, next: &TraceState, ark: &[u128], re
     enforce_mul     (&mut evaluations,      old_stack, new_stack, ld_flags[OpCode::Mul.ld_index()]);
     enforce_inv     (&mut evaluations,      old_stack, new_stack, ld_flags[OpCode::Inv.ld_index()]);
     enforce_neg     (&mut evaluations,      old_stack, new_stack, ld_flags[OpCode::Neg.ld_index()]);
+    enforce_expacc  (&mut evaluations,      old_stack, new_stack, ld_flags[OpCode::ExpAcc.ld_index()]);
     enforce_not     (&mut evaluations, aux, old_stack, new_stack, ld_flags[OpCode::Not.ld_index()]);
     enforce_and     (&mut evaluations, aux, old_stack, new_stack, ld_flags[OpCode::And.ld_index()]);
     enforce_or      (&mut evaluations, aux, old_stack, new_stack, ld_flags[OpCode::Or.ld_index()]);
@@ -172,6 +184,7 @@ fn enforce_constraints(current: &TraceState, next: &TraceState, ark: &[u128], re
     enforce_choose  (&mut evaluations, aux, old_stack, new_stack, ld_flags[OpCode::Choose.ld_index()]);
     enforce_choose2 (&mut evaluations, aux, old_stack, new_stack, ld_flags[OpCode::Choose2.ld_index()]);
     enforce_cswap2  (&mut evaluations, aux, old_stack, new_stack, ld_flags[OpCode::CSwap2.ld_index()]);
+    enforce_cswap4  (&mut evaluations, aux, old_stack, new_stack, ld_flags[OpCode::CSwap4.ld_index()]);
 
     // 2 ----- enforce constraints for high-degree operations --------------------------------------
     let hd_flags = current.hd_op_flags();
@@ -183,14 +196,16 @@ fn enforce_constraints(current: &TraceState, next: &TraceState, ark: &[u128], re
     // 3 ----- enforce constraints for composite operations ---------------------------------------
 
     // BEGIN and NOOP have "composite" opcodes where all 7 opcode bits are set to either 1s or 0s;
-    // thus, the flags for these operations are computed separately by multiplying all opcodes;
-    // this results in flag degree of 7 for each operation, but since both operations enforce the
-    // same constraints (the stack doesn't change), higher degree terms cancel out, and we
-    // end up with overall constraint degree of (6 + 1 = 7) for both operations.
+    // thus, the flags for these operations are computed separately by multiplying ld_op and
+    // hd_op flags together; this results in flag degree of 5 for each operation, and overall
+    // constraint degree of (5 + 1 = 6) for both operations.
     enforce_stack_copy(&mut evaluations, old_stack, new_stack, 0, current.begin_flag());
     enforce_stack_copy(&mut evaluations, old_stack, new_stack, 0, current.noop_flag());
     
-    // 4 ----- copy evaluations into the result ---------------------------------------------------
+    // 4 ----- enforce constraints for the stack depth registers -----------------------------------
+    enforce_depth(&mut aux[NUM_OP_AUX_CONSTRAINTS..], current, next);
+
+    // 5 ----- copy evaluations into the result ---------------------------------------------------
     result.copy_from_slice(&evaluations[..result.len()]);
 }
 
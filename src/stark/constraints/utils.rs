@@ -1,4 +1,4 @@
-use crate::math::{ field, polynom, fft };
+use crate::math::{ field, fft::{ self, FftContext } };
 use crate::utils::{ filled_vector };
 use crate::{ BASE_CYCLE_LENGTH };
 
@@ -86,12 +86,10 @@ impl EvaluationResult for Vec<u128> {
 // ================================================================================================
 pub fn extend_constants(constants: &[[u128; BASE_CYCLE_LENGTH]], extension_factor: usize) -> (Vec<Vec<u128>>, Vec<Vec<u128>>)
 {
-    let root = field::get_root_of_unity(BASE_CYCLE_LENGTH);
-    let inv_twiddles = fft::get_inv_twiddles(root, BASE_CYCLE_LENGTH);
-
+    // the same context provides twiddles for interpolation over the cycle and for evaluation
+    // over the extended domain
     let domain_size = BASE_CYCLE_LENGTH * extension_factor;
-    let domain_root = field::get_root_of_unity(domain_size);
-    let twiddles = fft::get_twiddles(domain_root, domain_size);
+    let fft_ctx = FftContext::new(domain_size);
 
     let mut polys = Vec::with_capacity(constants.len());
     let mut evaluations = Vec::with_capacity(constants.len());
@@ -100,11 +98,11 @@ pub fn extend_constants(constants: &[[u128; BASE_CYCLE_LENGTH]], extension_facto
         let mut extended_constant = filled_vector(BASE_CYCLE_LENGTH, domain_size, field::ZERO);
         extended_constant.copy_from_slice(constant);
 
-        polynom::interpolate_fft_twiddles(&mut extended_constant, &inv_twiddles, true);
+        fft::interpolate_with_ctx(&mut extended_constant, &fft_ctx);
         polys.push(extended_constant.clone());
 
         unsafe { extended_constant.set_len(extended_constant.capacity()); }
-        polynom::eval_fft_twiddles(&mut extended_constant, &twiddles, true);
+        fft::evaluate_with_ctx(&mut extended_constant, &fft_ctx);
 
         evaluations.push(extended_constant);
     }
@@ -0,0 +1,88 @@
+// TYPES AND INTERFACES
+// ================================================================================================
+
+/// Describes why verification of a proof failed.
+#[derive(Clone, PartialEq)]
+pub enum VerificationError {
+    /// The statement is about a program other than the one expected by the verifier.
+    ProgramHashMismatch { expected: [u8; 32], actual: [u8; 32] },
+    /// Inputs, outputs, or assertions of the statement are malformed, or do not match the
+    /// structure of the proof (e.g. the number of executions).
+    StatementMismatch(String),
+    /// The proof was generated under a version of the proving protocol which is not supported
+    /// by the verifier.
+    VersionMismatch { proof: u32, supported: u32 },
+    /// Parameters of the proof (trace length, stack depth, etc.) are invalid or inconsistent
+    /// with each other.
+    MalformedProof(String),
+    /// Parameters of the proof provide lower security level than required by the verifier.
+    InsufficientSecurity { required: u32, actual: u32 },
+    /// Parameters of the proof (number of queries, hash function, etc.) are not allowed by the
+    /// security policy of the verifier.
+    PolicyViolation(String),
+    /// Proof-of-work attached to the proof is invalid.
+    ProofOfWorkFailed,
+    /// An authentication path of one of the Merkle proofs does not lead to the committed root.
+    MerkleProofFailed(Commitment),
+    /// The low-degree proof does not hold at the specified FRI layer. A proof generated for a
+    /// different program hash, inputs, or outputs than the ones provided to the verifier is
+    /// rejected at depth 0, since evaluations of the constraints do not match the proof.
+    LowDegreeProofFailed { depth: usize, reason: String },
+}
+
+/// Identifies a Merkle commitment included in a proof.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum Commitment {
+    Trace,
+    Constraints,
+    Mask,
+    FriLayer(usize),
+}
+
+// COMMON TRAIT IMPLEMENTATIONS
+// ================================================================================================
+
+impl std::error::Error for VerificationError {}
+
+impl std::fmt::Debug for VerificationError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self)
+    }
+}
+
+impl std::fmt::Display for VerificationError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        return match self {
+            VerificationError::ProgramHashMismatch { expected, actual } =>
+                write!(f, "verification of program hash failed: expected {}, but was {}",
+                    hex::encode(expected), hex::encode(actual)),
+            VerificationError::StatementMismatch(msg) => write!(f, "{}", msg),
+            VerificationError::VersionMismatch { proof, supported } =>
+                write!(f, "verification of protocol version failed: proof was generated with version {}, but only version {} is supported",
+                    proof, supported),
+            VerificationError::MalformedProof(msg) => write!(f, "{}", msg),
+            VerificationError::InsufficientSecurity { required, actual } =>
+                write!(f, "verification of security level failed: proof provides {} bits of security, but {} bits are required",
+                    actual, required),
+            VerificationError::PolicyViolation(msg) =>
+                write!(f, "verification of security policy failed: {}", msg),
+            VerificationError::ProofOfWorkFailed =>
+                write!(f, "seed proof-of-work verification failed"),
+            VerificationError::MerkleProofFailed(commitment) =>
+                write!(f, "verification of {} Merkle proof failed", commitment),
+            VerificationError::LowDegreeProofFailed { reason, .. } =>
+                write!(f, "verification of low-degree proof failed: {}", reason),
+        };
+    }
+}
+
+impl std::fmt::Display for Commitment {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        return match self {
+            Commitment::Trace           => write!(f, "trace"),
+            Commitment::Constraints     => write!(f, "constraint"),
+            Commitment::Mask            => write!(f, "mask"),
+            Commitment::FriLayer(depth) => write!(f, "FRI layer {}", depth),
+        };
+    }
+}
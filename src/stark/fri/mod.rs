@@ -10,8 +10,6 @@ pub use prover::{ reduce, build_proof };
 mod verifier;
 pub use verifier::{ verify };
 
-const MAX_REMAINDER_LENGTH: usize = 256;
-
 // TYPES AND INTERFACES
 // ================================================================================================
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -21,10 +19,12 @@ pub struct FriProof {
     pub rem_values  : Vec<u128>,
 }
 
+/// Queried rows of a FRI layer; each row holds `folding_factor` values, and rows are
+/// concatenated into a single vector.
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct FriLayer {
     pub root    : [u8; 32],
-    pub values  : Vec<[u128; 4]>,
+    pub values  : Vec<u128>,
     pub nodes   : Vec<Vec<[u8; 32]>>,
     pub depth   : u8,
 }
@@ -33,65 +33,64 @@ pub struct FriLayer {
 // ================================================================================================
 #[cfg(test)]
 mod tests {
-    use crate::math::{ field, polynom };
-    use crate::stark::{ ProofOptions, utils::compute_query_positions };
+    use crate::math::{ field, polynom, fft::FftContext };
+    use crate::stark::{ ProofOptions, VerificationError, utils::compute_query_positions };
 
     #[test]
     fn prove_verify() {
         let degree: usize = 63;
         let domain_size: usize = 512;
-        let root = field::get_root_of_unity(domain_size);
-        let domain = field::get_power_series(root, domain_size);
+        let fft_ctx = FftContext::new(domain_size);
         let options = ProofOptions::default();
 
         let evaluations = build_random_poly_evaluations(domain_size, degree);
 
         // generate proof
-        let (fri_trees, fri_values) = super::reduce(&evaluations, &domain, &options);
+        let (fri_trees, fri_values) = super::reduce(&evaluations, &fft_ctx, &options);
         let positions = compute_query_positions(fri_trees[fri_trees.len() - 1].root(), domain_size, &options);
-        let proof = super::build_proof(fri_trees, fri_values, &positions);
+        let proof = super::build_proof(fri_trees, fri_values, &positions, &options);
 
         // verify proof
         let sampled_evaluations = positions.iter().map(|&i| evaluations[i]).collect::<Vec<u128>>();
-        let result = super::verify(&proof, &sampled_evaluations, &positions, degree, &options);
-        assert_eq!(Ok(true), result);
+        let result = super::verify(&proof, &sampled_evaluations, &positions, domain_size, degree, &options);
+        assert_eq!(Ok(()), result);
     }
 
     #[test]
     fn verify_fail() {
         let degree: usize = 63;
         let domain_size: usize = 512;
-        let root = field::get_root_of_unity(domain_size);
-        let domain = field::get_power_series(root, domain_size);
+        let fft_ctx = FftContext::new(domain_size);
         let options = ProofOptions::default();
 
-        // degree too low 1
+        // degree too low 1; the degree is reduced by a full folding factor because the verifier
+        // rounds degree bounds up when folding
         let evaluations = build_random_poly_evaluations(domain_size, degree);
-        let (fri_trees, fri_values) = super::reduce(&evaluations, &domain, &options);
+        let (fri_trees, fri_values) = super::reduce(&evaluations, &fft_ctx, &options);
         let positions = compute_query_positions(fri_trees[fri_trees.len() - 1].root(), domain_size, &options);
-        let proof = super::build_proof(fri_trees, fri_values, &positions);
+        let proof = super::build_proof(fri_trees, fri_values, &positions, &options);
 
         let sampled_evaluations = positions.iter().map(|&i| evaluations[i]).collect::<Vec<u128>>();
-        let result = super::verify(&proof, &sampled_evaluations, &positions, degree - 1, &options);
-        let err_msg = format!("remainder is not a valid degree {} polynomial", 14);
-        assert_eq!(Err(err_msg), result);
+        let result = super::verify(&proof, &sampled_evaluations, &positions, domain_size, degree - options.folding_factor(), &options);
+        let err_msg = format!("verification of low-degree proof failed: remainder is not a valid degree {} polynomial", 14);
+        assert_eq!(Err(err_msg), result.map_err(|err| err.to_string()));
 
         // degree too low 2
         let evaluations = build_random_poly_evaluations(domain_size, degree + 1);
-        let (fri_trees, fri_values) = super::reduce(&evaluations, &domain, &options);
+        let (fri_trees, fri_values) = super::reduce(&evaluations, &fft_ctx, &options);
         let positions = compute_query_positions(fri_trees[fri_trees.len() - 1].root(), domain_size, &options);
-        let proof = super::build_proof(fri_trees, fri_values, &positions);
+        let proof = super::build_proof(fri_trees, fri_values, &positions, &options);
 
         let sampled_evaluations = positions.iter().map(|&i| evaluations[i]).collect::<Vec<u128>>();
-        let result = super::verify(&proof, &sampled_evaluations, &positions, degree, &options);
-        let err_msg = format!("remainder is not a valid degree {} polynomial", 15);
-        assert_eq!(Err(err_msg), result);
+        let result = super::verify(&proof, &sampled_evaluations, &positions, domain_size, degree, &options);
+        let err_msg = format!("verification of low-degree proof failed: remainder is not a valid degree {} polynomial", 15);
+        assert_eq!(Err(err_msg), result.map_err(|err| err.to_string()));
 
         // invalid evaluations
         let sampled_evaluations = sampled_evaluations[1..].to_vec();
-        let result = super::verify(&proof, &sampled_evaluations, &positions, degree, &options);
+        let result = super::verify(&proof, &sampled_evaluations, &positions, domain_size, degree, &options);
         let err_msg = format!("evaluations did not match column value at depth 0");
-        assert_eq!(Err(err_msg), result);
+        assert_eq!(Err(VerificationError::LowDegreeProofFailed { depth: 0, reason: err_msg }), result);
     }
 
     // TODO: add more tests
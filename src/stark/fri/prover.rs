@@ -1,39 +1,46 @@
 use std::mem;
-use crate::math::{ field, quartic };
+use crate::math::{ field, fft::FftContext };
 use crate::crypto::{ MerkleTree };
 use crate::stark::{ ProofOptions };
 
-use super::{ FriProof, FriLayer, utils, MAX_REMAINDER_LENGTH};
+use super::{ FriProof, FriLayer, utils };
 
 // PROVER FUNCTIONS
 // ================================================================================================
 
-pub fn reduce(evaluations: &[u128], domain: &[u128], options: &ProofOptions) -> (Vec<MerkleTree>, Vec<Vec<[u128; 4]>>) {
+/// Builds FRI layers for `evaluations` of a polynomial over the domain of `fft_ctx`; the domain
+/// is shared with other phases of the prover, and so it is not recomputed here.
+pub fn reduce(evaluations: &[u128], fft_ctx: &FftContext, options: &ProofOptions) -> (Vec<MerkleTree>, Vec<Vec<u128>>) {
+    assert!(evaluations.len() == fft_ctx.size(), "number of evaluations must match domain size");
+    let domain = fft_ctx.domain();
     let mut tree_results: Vec<MerkleTree> = Vec::new();
-    let mut value_results: Vec<Vec<[u128; 4]>> = Vec::new();
+    let mut value_results: Vec<Vec<u128>> = Vec::new();
+    let folding_factor = options.folding_factor();
 
-    // transpose evaluations into a matrix with 4 columns and put its rows into a Merkle tree
-    let mut p_values = quartic::transpose(evaluations, 1);
-    let hashed_values = utils::hash_values(&p_values, options.hash_fn());
+    // transpose evaluations into a matrix with folding_factor columns and put its rows into
+    // a Merkle tree
+    let mut p_values = utils::transpose(evaluations, 1, folding_factor);
+    let hashed_values = utils::hash_values(&p_values, folding_factor, options.hash_fn());
     let mut p_tree = MerkleTree::new(hashed_values, options.hash_fn());
 
-    // reduce the degree by 4 at each iteration until the remaining polynomial is small enough
-    while p_tree.leaves().len() * 4 > MAX_REMAINDER_LENGTH {
+    // reduce the degree by folding_factor at each iteration until the remaining polynomial is
+    // small enough
+    while p_values.len() > options.max_remainder_length() {
 
-        // build polynomials from each row of the polynomial value matrix
+        // determine x coordinate of the first value in each row of the polynomial value matrix
         let depth = tree_results.len() as u32;
-        let xs = quartic::transpose(domain, usize::pow(4, depth));
-        let polys = quartic::interpolate_batch(&xs, &p_values);
+        let stride = usize::pow(folding_factor, depth);
+        let xs: Vec<u128> = (0..(p_values.len() / folding_factor)).map(|i| domain[i * stride]).collect();
 
-        // select a pseudo-random x coordinate and evaluate each row polynomial at that x
+        // select a pseudo-random x coordinate and evaluate polynomial of each row at that x
         let special_x = field::prng(*p_tree.root());
-        let column = quartic::evaluate_batch(&polys, special_x);
+        let column = utils::fold_rows(&p_values, &xs, folding_factor, special_x);
 
         // break the column in a polynomial value matrix for the next layer
-        let mut c_values = quartic::transpose(&column, 1);
+        let mut c_values = utils::transpose(&column, 1, folding_factor);
 
         // put the resulting matrix into a Merkle tree
-        let hashed_values = utils::hash_values(&c_values, options.hash_fn());
+        let hashed_values = utils::hash_values(&c_values, folding_factor, options.hash_fn());
         let mut c_tree = MerkleTree::new(hashed_values, options.hash_fn());
 
         // set p_tree = c_tree and p_values = c_values for the next iteration of the loop
@@ -52,23 +59,24 @@ pub fn reduce(evaluations: &[u128], domain: &[u128], options: &ProofOptions) ->
     return (tree_results, value_results);
 }
 
-pub fn build_proof(trees: Vec<MerkleTree>, values: Vec<Vec<[u128; 4]>>, positions: &[usize]) -> FriProof {
+pub fn build_proof(trees: Vec<MerkleTree>, values: Vec<Vec<u128>>, positions: &[usize], options: &ProofOptions) -> FriProof {
+    let folding_factor = options.folding_factor();
     let mut positions = positions.to_vec();
-    let mut domain_size = trees[0].leaves().len() * 4;
+    let mut domain_size = trees[0].leaves().len() * folding_factor;
 
     // for all trees, except the last one, record tree root, authentication paths
     // to row evaluations, and values for row evaluations
     let mut layers = Vec::with_capacity(trees.len());
     for i in 0..(trees.len() - 1) {
         
-        positions = utils::get_augmented_positions(&positions, domain_size);
+        positions = utils::get_augmented_positions(&positions, domain_size, folding_factor);
 
         let tree = &trees[i];
         let proof = tree.prove_batch(&positions);
         
-        let mut queried_values: Vec<[u128; 4]> = Vec::with_capacity(positions.len());
+        let mut queried_values: Vec<u128> = Vec::with_capacity(positions.len() * folding_factor);
         for &position in positions.iter() {
-            queried_values.push(values[i][position]);
+            queried_values.extend_from_slice(&values[i][(position * folding_factor)..((position + 1) * folding_factor)]);
         }
 
         layers.push(FriLayer {
@@ -77,20 +85,20 @@ pub fn build_proof(trees: Vec<MerkleTree>, values: Vec<Vec<[u128; 4]>>, position
             nodes   : proof.nodes,
             depth   : proof.depth
         });
-        domain_size = domain_size / 4;
+        domain_size = domain_size / folding_factor;
     }
 
-    // use the remaining polynomial values directly as proof
+    // use the remaining polynomial values directly as proof; the values are put back into the
+    // order of the evaluation domain
     let last_tree = &trees[trees.len() - 1];
     let last_values = &values[values.len() - 1];
-    let n = last_values.len();
-    let mut remainder = vec![field::ZERO; n * 4];
-    for i in 0..last_values.len() {
-        remainder[i] = last_values[i][0];
-        remainder[i + n] = last_values[i][1];
-        remainder[i + n * 2] = last_values[i][2];
-        remainder[i + n * 3] = last_values[i][3];
+    let n = last_values.len() / folding_factor;
+    let mut remainder = vec![field::ZERO; last_values.len()];
+    for i in 0..n {
+        for j in 0..folding_factor {
+            remainder[i + n * j] = last_values[i * folding_factor + j];
+        }
     }
 
     return FriProof { layers, rem_root: *last_tree.root(), rem_values: remainder };
-}
\ No newline at end of file
+}
@@ -1,8 +1,9 @@
+use crate::math::{ field };
 use crate::crypto::{ HashFunction };
 use crate::utils::{ uninit_vector, as_bytes };
 
-pub fn get_augmented_positions(positions: &[usize], column_length: usize) -> Vec<usize> {
-    let row_length = column_length / 4;
+pub fn get_augmented_positions(positions: &[usize], column_length: usize, folding_factor: usize) -> Vec<usize> {
+    let row_length = column_length / folding_factor;
     let mut result = Vec::new();
     for i in 0..positions.len() {
         let ap = positions[i] % row_length;
@@ -13,10 +14,77 @@ pub fn get_augmented_positions(positions: &[usize], column_length: usize) -> Vec
     return result;
 }
 
-pub fn hash_values(values: &Vec<[u128; 4]>, hash: HashFunction) -> Vec<[u8; 32]> {
-    let mut result: Vec<[u8; 32]> = uninit_vector(values.len());
-    for i in 0..values.len() {
-        hash(as_bytes(&values[i]), &mut result[i]);
+/// Breaks values of the `vector` taken at the specified `stride` into rows of `folding_factor`
+/// values each, such that the i-th row holds values at positions i, i + n, i + 2n etc., where n
+/// is the number of rows; the rows are concatenated into a single vector.
+pub fn transpose(vector: &[u128], stride: usize, folding_factor: usize) -> Vec<u128> {
+    assert!(vector.len() % (folding_factor * stride) == 0,
+        "vector length must be divisible by {}", folding_factor * stride);
+    let row_count = vector.len() / (folding_factor * stride);
+
+    let mut result = uninit_vector(row_count * folding_factor);
+    for i in 0..row_count {
+        for j in 0..folding_factor {
+            result[i * folding_factor + j] = vector[(i + j * row_count) * stride];
+        }
     }
+
     return result;
-}
\ No newline at end of file
+}
+
+/// Interpolates each row of `values` into a polynomial and evaluates it at `x`. The j-th value
+/// of the i-th row is assumed to be the evaluation of the polynomial at xs[i] * r^j, where r is
+/// the root of unity of order `folding_factor`.
+pub fn fold_rows(values: &[u128], xs: &[u128], folding_factor: usize, x: u128) -> Vec<u128> {
+    debug_assert!(values.len() == xs.len() * folding_factor, "number of values must match number of rows");
+    let roots = field::get_power_series(field::get_root_of_unity(folding_factor), folding_factor);
+
+    // since the points of each row form a coset of the group of roots of unity, the polynomial
+    // is evaluated at x via barycentric formula:
+    // p(x) = (x^n - xs[i]^n) / (n * xs[i]^n) * sum(v_j * y_j / (x - y_j)), where y_j = xs[i] * r^j;
+    // all required inversions are batched
+    let row_length = folding_factor + 1;
+    let mut denominators = Vec::with_capacity(xs.len() * row_length);
+    for &xi in xs.iter() {
+        denominators.push(field::exp(xi, folding_factor as u128));
+        for &root in roots.iter() {
+            denominators.push(field::sub(x, field::mul(xi, root)));
+        }
+    }
+    let inverses = field::inv_many(&denominators);
+
+    let x_n = field::exp(x, folding_factor as u128);
+    let n_inv = field::inv(folding_factor as u128);
+
+    let mut result = Vec::with_capacity(xs.len());
+    for (i, &xi) in xs.iter().enumerate() {
+        let row = &values[(i * folding_factor)..((i + 1) * folding_factor)];
+        let offset = i * row_length;
+
+        // if x is one of the points, the value at that point is the evaluation
+        if let Some(j) = (0..folding_factor).find(|&j| denominators[offset + 1 + j] == field::ZERO) {
+            result.push(row[j]);
+            continue;
+        }
+
+        let mut sum = field::ZERO;
+        for j in 0..folding_factor {
+            let y = field::mul(xi, roots[j]);
+            sum = field::add(sum, field::mul(field::mul(row[j], y), inverses[offset + 1 + j]));
+        }
+
+        let scale = field::mul(field::sub(x_n, denominators[offset]), field::mul(n_inv, inverses[offset]));
+        result.push(field::mul(sum, scale));
+    }
+
+    return result;
+}
+
+/// Hashes each row of `folding_factor` values into a single 32-byte digest.
+pub fn hash_values(values: &[u128], folding_factor: usize, hash: HashFunction) -> Vec<[u8; 32]> {
+    let mut result: Vec<[u8; 32]> = uninit_vector(values.len() / folding_factor);
+    for (i, row) in values.chunks(folding_factor).enumerate() {
+        hash.digest(as_bytes(row), &mut result[i]);
+    }
+    return result;
+}
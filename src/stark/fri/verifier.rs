@@ -1,7 +1,7 @@
 use std::mem;
-use crate::math::{ field, polynom, quartic };
+use crate::math::{ field, polynom };
 use crate::crypto::{ MerkleTree, BatchMerkleProof };
-use crate::stark::{ ProofOptions };
+use crate::stark::{ ProofOptions, VerificationError, Commitment };
 
 use super::{ FriProof, FriLayer, utils };
 
@@ -12,23 +12,15 @@ pub fn verify(
     proof       : &FriProof,
     evaluations : &[u128],
     positions   : &[usize],
+    domain_size : usize,
     max_degree  : usize,
-    options     : &ProofOptions) -> Result<bool, String>
+    options     : &ProofOptions) -> Result<(), VerificationError>
 {
-
-    let domain_size = usize::pow(2, proof.layers[0].depth as u32) * 4;
-    let domain_root = field::get_root_of_unity(domain_size);
-
-    // powers of the given root of unity 1, p, p^2, p^3 such that p^4 = 1
-    let quartic_roots = [
-        1u128,
-        field::exp(domain_root, (domain_size / 4) as u128),
-        field::exp(domain_root, (domain_size / 2) as u128),
-        field::exp(domain_root, (domain_size * 3 / 4) as u128),
-    ];
+    let folding_factor = options.folding_factor();
+    verify_structure(proof, domain_size, options)?;
 
     // 1 ----- verify the recursive components of the FRI proof -----------------------------------
-    let mut domain_root = domain_root;
+    let mut domain_root = field::get_root_of_unity(domain_size);
     let mut domain_size = domain_size;
     let mut max_degree_plus_1 = max_degree + 1;
     let mut positions = positions.to_vec();
@@ -36,56 +28,88 @@ pub fn verify(
 
     for (depth, layer) in proof.layers.iter().enumerate() {
 
-        let mut augmented_positions = utils::get_augmented_positions(&positions, domain_size);
-        let column_values = get_column_values(&layer.values, &positions, &augmented_positions, domain_size);
+        let mut augmented_positions = utils::get_augmented_positions(&positions, domain_size, folding_factor);
+        if layer.values.len() != augmented_positions.len() * folding_factor {
+            return Err(VerificationError::LowDegreeProofFailed {
+                depth   : depth,
+                reason  : format!("number of queried values is invalid at depth {}", depth),
+            });
+        }
+
+        let column_values = get_column_values(&layer.values, &positions, &augmented_positions, domain_size, folding_factor);
         if evaluations != column_values {
-            return Err(format!("evaluations did not match column value at depth {}", depth));
+            return Err(VerificationError::LowDegreeProofFailed {
+                depth   : depth,
+                reason  : format!("evaluations did not match column value at depth {}", depth),
+            });
         }
 
         // verify Merkle proof for the layer
         let merkle_proof = build_layer_merkle_proof(&layer, options);
         if !MerkleTree::verify_batch(&layer.root, &augmented_positions, &merkle_proof, options.hash_fn()) {
-            return Err(format!("verification of Merkle proof failed at layer {}", depth));
+            return Err(VerificationError::MerkleProofFailed(Commitment::FriLayer(depth)));
         }
 
-        // build a set of x for each row polynomial
-        let mut xs = Vec::with_capacity(augmented_positions.len());
-        for &i in augmented_positions.iter() {
-            let xe = field::exp(domain_root, i as u128);
-            xs.push([
-                field::mul(quartic_roots[0], xe),
-                field::mul(quartic_roots[1], xe),
-                field::mul(quartic_roots[2], xe),
-                field::mul(quartic_roots[3], xe)
-            ]);
-        }
-
-        // interpolate x and y values into row polynomials
-        let row_polys = quartic::interpolate_batch(&xs, &layer.values);
+        // determine x coordinate of the first value in each queried row
+        let xs: Vec<u128> = augmented_positions.iter().map(|&i| field::exp(domain_root, i as u128)).collect();
 
         // calculate the pseudo-random x coordinate
         let special_x = field::prng(layer.root);
 
-        // check that when the polynomials are evaluated at x, the result is equal to the corresponding column value
-        evaluations = quartic::evaluate_batch(&row_polys, special_x);
+        // evaluate row polynomials at x; the results must be equal to the corresponding column values
+        evaluations = utils::fold_rows(&layer.values, &xs, folding_factor, special_x);
 
         // update variables for the next iteration of the loop
-        domain_root = field::exp(domain_root, 4);
-        max_degree_plus_1 = max_degree_plus_1 / 4;
-        domain_size = domain_size / 4;
+        domain_root = field::exp(domain_root, folding_factor as u128);
+        // each row polynomial has degree < ceil(max_degree_plus_1 / folding_factor), and so
+        // does the folded polynomial
+        max_degree_plus_1 = (max_degree_plus_1 + folding_factor - 1) / folding_factor;
+        domain_size = domain_size / folding_factor;
         mem::swap(&mut positions, &mut augmented_positions);
     }
 
     // 2 ----- verify the remainder of the FRI proof ----------------------------------------------
     
+    let depth = proof.layers.len();
     for (&position, evaluation) in positions.iter().zip(evaluations) {
         if proof.rem_values[position] != evaluation {
-            return Err(String::from("remainder values are inconsistent with values of the last column"));
+            return Err(VerificationError::LowDegreeProofFailed {
+                depth   : depth,
+                reason  : String::from("remainder values are inconsistent with values of the last column"),
+            });
         }
     }
 
     // make sure the remainder values satisfy the degree
-    return verify_remainder(&proof.rem_values, max_degree_plus_1, domain_root, options.extension_factor());
+    return match verify_remainder(&proof.rem_values, max_degree_plus_1, domain_root, options.extension_factor()) {
+        Ok(_) => Ok(()),
+        Err(reason) => Err(VerificationError::LowDegreeProofFailed { depth, reason }),
+    };
+}
+
+/// Makes sure the number of layers and the size of the remainder match the ones which the
+/// prover would produce for the specified domain size and proof options.
+fn verify_structure(proof: &FriProof, domain_size: usize, options: &ProofOptions) -> Result<(), VerificationError> {
+    let folding_factor = options.folding_factor();
+    let mut layer_domain_size = domain_size;
+    let mut num_layers = 0;
+    while layer_domain_size > options.max_remainder_length() {
+        let row_count = layer_domain_size / folding_factor;
+        let is_valid = proof.layers.get(num_layers)
+            .map_or(false, |layer| 1usize.checked_shl(layer.depth as u32) == Some(row_count));
+        if !is_valid {
+            return Err(VerificationError::MalformedProof(format!(
+                "verification of low-degree proof failed: layer at depth {} is missing or invalid", num_layers)));
+        }
+        layer_domain_size = row_count;
+        num_layers += 1;
+    }
+
+    if proof.layers.len() != num_layers || proof.rem_values.len() != layer_domain_size {
+        return Err(VerificationError::MalformedProof(String::from(
+            "verification of low-degree proof failed: number of layers or remainder length is invalid")));
+    }
+    return Ok(());
 }
 
 fn verify_remainder(remainder: &[u128], max_degree_plus_1: usize, domain_root: u128, extension_factor: usize) -> Result<bool, String> {
@@ -101,6 +125,10 @@ fn verify_remainder(remainder: &[u128], max_degree_plus_1: usize, domain_root: u
         }
     }
 
+    if max_degree_plus_1 >= positions.len() {
+        return Err(String::from("remainder does not have enough values to check its degree"));
+    }
+
     // pick a subset of points from the remainder and interpolate them into a polynomial
     let domain = field::get_power_series(domain_root, remainder.len());
     let mut xs = Vec::with_capacity(max_degree_plus_1);
@@ -125,13 +153,13 @@ fn verify_remainder(remainder: &[u128], max_degree_plus_1: usize, domain_root: u
 
 // HELPER FUNCTIONS
 // ================================================================================================
-fn get_column_values(values: &Vec<[u128; 4]>, positions: &[usize], augmented_positions: &[usize], column_length: usize) -> Vec<u128> {
-    let row_length = column_length / 4;
+fn get_column_values(values: &[u128], positions: &[usize], augmented_positions: &[usize], column_length: usize, folding_factor: usize) -> Vec<u128> {
+    let row_length = column_length / folding_factor;
 
     let mut result = Vec::new();
     for position in positions {
         let idx = augmented_positions.iter().position(|&v| v == position % row_length).unwrap();
-        let value = values[idx][position / row_length];
+        let value = values[idx * folding_factor + position / row_length];
         result.push(value);
     }
 
@@ -140,7 +168,7 @@ fn get_column_values(values: &Vec<[u128; 4]>, positions: &[usize], augmented_pos
 
 fn build_layer_merkle_proof(layer: &FriLayer, options: &ProofOptions) -> BatchMerkleProof {
     return BatchMerkleProof {
-        values  : utils::hash_values(&layer.values, options.hash_fn()),
+        values  : utils::hash_values(&layer.values, options.folding_factor(), options.hash_fn()),
         nodes   : layer.nodes.clone(),
         depth   : layer.depth
     };
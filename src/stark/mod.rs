@@ -1,26 +1,54 @@
 mod trace;
 mod constraints;
 mod options;
+mod policy;
 mod prover;
 mod verifier;
 mod proof;
 mod fri;
 mod utils;
+mod assertions;
+mod statement;
+mod checkpoint;
+mod timings;
+mod session;
+mod batch;
+mod errors;
+mod version;
 
-pub use trace::{ TraceTable, TraceState };
+pub use trace::{ TraceTable, TraceState, TraceLayout, RegisterGroup };
 
 pub use constraints::{
     ConstraintEvaluator,
-    ConstraintTable,
+    ConstraintDegrees,
     ConstraintPoly };
+pub(crate) use constraints::EvaluatorCache;
+#[cfg(test)]
+pub use constraints::BROKEN_MUL;
+#[cfg(test)]
+pub(crate) use constraints::SKIP_TRANSITION_CHECKS;
 
 pub use utils::{
     ConstraintCoefficients,
     CompositionCoefficients };
 
-pub use options::ProofOptions;
-pub use proof::{ StarkProof, DeepValues };
-pub use prover::{ prove };
-pub use verifier::{ verify };
+pub use assertions::{ Assertion, AssertionStep, validate_assertions, find_invalid_assertion, hash_assertions, get_constraint_seed };
+pub use statement::{ Statement, link_assertions, check_link };
+pub use options::{ ProofOptions, Minimize, CheckpointFn };
+pub use policy::SecurityPolicy;
+pub use checkpoint::{ ProverCheckpoint, ProverPhase };
+pub use timings::{ ProvingTimings, TraceRegion };
+pub use session::SessionProof;
+pub use batch::{ TraceSegment, SegmentInfo, stitch_traces };
+pub use proof::{ StarkProof, DeepValues, ZkValues, TraceInfo, ProofSizeReport };
+pub use prover::{ prove, prove_batch, resume };
+pub use verifier::{ verify, verify_batch, verify_many, VerificationItem };
+pub use errors::{ VerificationError, Commitment };
+pub use version::{ PROTOCOL_VERSION, air_fingerprint };
+pub(crate) use verifier::check_version;
 
-const MAX_CONSTRAINT_DEGREE : usize = 8;
\ No newline at end of file
+pub(crate) const MAX_CONSTRAINT_DEGREE : usize = 7;
+
+// constraints are evaluated over a domain which is this many times larger than the trace; domain
+// sizes must be powers of 2, and so this is the max constraint degree rounded up to a power of 2
+pub(crate) const EVALUATION_BLOWUP     : usize = MAX_CONSTRAINT_DEGREE.next_power_of_two();
\ No newline at end of file
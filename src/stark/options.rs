@@ -1,25 +1,64 @@
+use std::{ cmp, sync::Arc };
 use serde::{ Serialize, Deserialize };
-use crate::crypto::{ HashFunction, hash };
-use super::MAX_CONSTRAINT_DEGREE;
+use crate::crypto::{ HashFunction };
+use super::{ ProverCheckpoint, EVALUATION_BLOWUP, MAX_CONSTRAINT_DEGREE };
 
 // CONSTANTS
 // ================================================================================================
-const DEFAULT_EXTENSION_FACTOR: u8 = (MAX_CONSTRAINT_DEGREE * 4) as u8;
+const DEFAULT_EXTENSION_FACTOR: u8 = (EVALUATION_BLOWUP * 4) as u8;
 const DEFAULT_NUM_QUERIES     : u8 = 50;
 const DEFAULT_GRINDING_FACTOR : u8 = 20;
+const DEFAULT_FOLDING_FACTOR  : usize = 4;
+const DEFAULT_MAX_REMAINDER   : usize = 256;
+
+const FOLDING_FACTORS         : [usize; 3] = [4, 8, 16];
+const MIN_REMAINDER_LENGTH    : usize = 128;
+const MAX_REMAINDER_LENGTH    : usize = 1024;
+const MAX_BLINDING_FACTOR     : usize = 4096;
+
+// every query reveals evaluations of each trace polynomial at up to 4 points: constraint
+// evaluations are committed in pairs, and each of them depends on the current and the next state
+const ZK_POINTS_PER_QUERY     : usize = 4;
+
+// cost model used by ProofOptions::tune(); constants were calibrated against proofs of small
+// programs (see tests::tuned_cost_model), and are expected to be accurate to within ~25%
+const BYTES_PER_QUERY_LEVEL   : usize = 100;    // proof bytes per query per level of LDE domain
+const BYTES_PER_LEVEL         : usize = 600;    // proof bytes per level of LDE domain
+const NS_PER_DOMAIN_STEP      : usize = 5000;   // prover time per step of LDE domain
+const NS_PER_POW_HASH         : usize = 200;    // prover time per hash during grinding
 
 // TYPES AND INTERFACES
 // ================================================================================================
 
-// TODO: validate field values on de-serialization
+/// Cost to be minimized when tuning proof options.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum Minimize {
+    ProofSize,
+    ProverTime,
+}
+
+/// Callback invoked by the prover with its intermediate state after each completed phase.
+pub type CheckpointFn = Arc<dyn Fn(&ProverCheckpoint) + Send + Sync>;
+
 #[derive(Clone, Serialize, Deserialize)]
 pub struct ProofOptions {
     extension_factor    : u8,   // stored as power of 2
     num_queries         : u8,
     grinding_factor     : u8,
+    min_trace_length    : u8,   // stored as power of 2
+    folding_factor      : u8,   // stored as power of 2
+    max_remainder_length: u8,   // stored as power of 2
+    blinding_factor     : u16,  // 0 when zero-knowledge is disabled
 
     #[serde(with = "hash_fn_serialization")]
     hash_fn: HashFunction,
+
+    // checkpoint callback and randomness are properties of the prover process, and so they
+    // are not serialized
+    #[serde(skip)]
+    checkpoint_fn: Option<CheckpointFn>,
+    #[serde(skip)]
+    seed: Option<[u8; 32]>,
 }
 
 // PROOF OPTIONS IMPLEMENTATION
@@ -45,10 +84,117 @@ impl ProofOptions {
             extension_factor    : extension_factor.trailing_zeros() as u8,
             num_queries         : num_queries as u8,
             grinding_factor     : grinding_factor as u8,
-            hash_fn
+            min_trace_length    : crate::MIN_TRACE_LENGTH.trailing_zeros() as u8,
+            folding_factor      : DEFAULT_FOLDING_FACTOR.trailing_zeros() as u8,
+            max_remainder_length: DEFAULT_MAX_REMAINDER.trailing_zeros() as u8,
+            blinding_factor     : 0,
+            hash_fn,
+            checkpoint_fn       : None,
+            seed                : None,
         };
     }
 
+    /// Returns these options with `callback` invoked by the prover after trace extension and
+    /// after constraint commitment; the callback can persist the checkpoint it receives (e.g.
+    /// via `ProverCheckpoint::to_bytes()`) so that proving can be resumed with `resume()`.
+    pub fn with_checkpoints<F>(mut self, callback: F) -> ProofOptions
+        where F: Fn(&ProverCheckpoint) + Send + Sync + 'static
+    {
+        self.checkpoint_fn = Some(Arc::new(callback));
+        return self;
+    }
+
+    /// Returns these options with execution traces padded to at least `length` steps; this
+    /// can be used to make proofs of all programs shorter than `length` steps equal in size.
+    pub fn with_min_trace_length(mut self, length: usize) -> ProofOptions {
+        assert!(length.is_power_of_two(), "min_trace_length must be a power of 2");
+        assert!(length >= crate::MIN_TRACE_LENGTH,
+            "min_trace_length cannot be smaller than {}", crate::MIN_TRACE_LENGTH);
+        self.min_trace_length = length.trailing_zeros() as u8;
+        return self;
+    }
+
+    /// Returns these options with the prover required to find a proof-of-work nonce which makes
+    /// the seed for query positions start with `bits` zero bits; this adds up to `bits` bits of
+    /// security (see `security_level()`) at the cost of ~2^`bits` hashes for the prover.
+    pub fn with_grinding_bits(mut self, bits: u32) -> ProofOptions {
+        assert!(bits <= 32, "grinding factor cannot be greater than 32");
+        self.grinding_factor = bits as u8;
+        return self;
+    }
+
+    /// Returns these options with `hash_fn` used to commit to the execution trace, constraint
+    /// evaluations, and FRI layers. Blake3 and SHA256 are much faster than Rescue, but Rescue
+    /// commitments are cheaper to verify inside the VM.
+    pub fn with_hash_fn(mut self, hash_fn: HashFunction) -> ProofOptions {
+        self.hash_fn = hash_fn;
+        return self;
+    }
+
+    /// Returns these options with zero-knowledge enabled or disabled. When enabled, the blinding
+    /// factor is set to 4 * `num_queries` + 2, which is enough to hide all trace evaluations
+    /// revealed by the proof: up to 4 per query, and 2 out-of-domain evaluations (see
+    /// `with_blinding_factor()`); thus, this method should be called after the number of queries
+    /// is set.
+    pub fn with_zero_knowledge(mut self, enabled: bool) -> ProofOptions {
+        self.blinding_factor = if enabled { (ZK_POINTS_PER_QUERY * self.num_queries() + 2) as u16 } else { 0 };
+        return self;
+    }
+
+    /// Returns these options with zero-knowledge enabled, and with `count` random values mixed
+    /// into every trace polynomial; evaluations of a trace polynomial at up to `count` points
+    /// outside of the execution trace reveal nothing about the execution. Execution traces are
+    /// padded to leave room for the random values (see `min_trace_length()`).
+    pub fn with_blinding_factor(mut self, count: usize) -> ProofOptions {
+        assert!(count > 0, "blinding factor must be greater than 0");
+        assert!(count <= MAX_BLINDING_FACTOR, "blinding factor cannot be greater than {}", MAX_BLINDING_FACTOR);
+        self.blinding_factor = count as u16;
+        return self;
+    }
+
+    /// Returns these options with all prover-side randomness derived from `seed`, which makes
+    /// proof generation fully deterministic: proving the same program with the same inputs and
+    /// options always produces the same proof. Random values are derived as
+    /// `blake3(seed || tag || index)`, where `tag` is 0 for trace blinding values, 1 for the mask
+    /// polynomial, and 2 and 3 for salts of trace and mask tree leaves. By default, a new random
+    /// seed is drawn from the system for every proof. The seed is not included in proofs.
+    ///
+    /// Values derived from the proof transcript (e.g. query positions) do not depend on the seed;
+    /// currently, only zero-knowledge proofs use prover-side randomness.
+    pub fn with_seed(mut self, seed: [u8; 32]) -> ProofOptions {
+        self.seed = Some(seed);
+        return self;
+    }
+
+    /// Returns these options with the degree of the polynomial reduced by `factor` at each layer
+    /// of the low-degree proof; `factor` must be 4, 8, or 16. Higher factors result in fewer
+    /// layers, and thus, in smaller proofs, but each queried row of a layer holds more values.
+    pub fn with_folding_factor(mut self, factor: usize) -> ProofOptions {
+        assert!(FOLDING_FACTORS.contains(&factor),
+            "folding factor must be one of {:?}, but was {}", FOLDING_FACTORS, factor);
+        if let Err(msg) = check_remainder_length(factor, self.max_remainder_length()) {
+            panic!("{}", msg);
+        }
+        self.folding_factor = factor.trailing_zeros() as u8;
+        return self;
+    }
+
+    /// Returns these options with the reduction of the polynomial in the low-degree proof
+    /// stopped once the polynomial has no more than `length` evaluations; the remaining
+    /// evaluations are included in the proof in full. `length` must be a power of 2 between
+    /// 128 and 1024, and cannot be smaller than the square of the folding factor.
+    pub fn with_max_remainder_length(mut self, length: usize) -> ProofOptions {
+        assert!(length.is_power_of_two(), "max_remainder_length must be a power of 2");
+        assert!(length >= MIN_REMAINDER_LENGTH && length <= MAX_REMAINDER_LENGTH,
+            "max_remainder_length must be between {} and {}, but was {}",
+            MIN_REMAINDER_LENGTH, MAX_REMAINDER_LENGTH, length);
+        if let Err(msg) = check_remainder_length(self.folding_factor(), length) {
+            panic!("{}", msg);
+        }
+        self.max_remainder_length = length.trailing_zeros() as u8;
+        return self;
+    }
+
     pub fn extension_factor(&self) -> usize {
         return 1 << (self.extension_factor as usize)
     }
@@ -61,12 +207,59 @@ impl ProofOptions {
         return self.grinding_factor as u32;
     }
 
+    /// Returns the minimum length of execution traces; shorter traces are padded to this length.
+    ///
+    /// In zero-knowledge mode, every trace polynomial has `blinding_factor()` more coefficients,
+    /// and quotients of transition constraints fit into the composition degree only if the trace
+    /// has at least MAX_CONSTRAINT_DEGREE * `blinding_factor()` + `MAX_BATCH_SIZE` steps; so,
+    /// traces are padded to the next power of 2 of that length. With the default blinding
+    /// factor, this is about 28 * `num_queries()` steps (e.g. 1024 steps for 32 queries).
+    pub fn min_trace_length(&self) -> usize {
+        let min_length = 1 << (self.min_trace_length as usize);
+        if self.blinding_factor == 0 {
+            return min_length;
+        }
+        let zk_length = MAX_CONSTRAINT_DEGREE * self.blinding_factor() + crate::MAX_BATCH_SIZE;
+        return cmp::max(min_length, zk_length.next_power_of_two());
+    }
+
+    /// Returns `true` if proofs generated with these options are zero-knowledge.
+    pub fn zero_knowledge(&self) -> bool {
+        return self.blinding_factor > 0;
+    }
+
+    /// Returns the number of random values mixed into every trace polynomial; 0 when
+    /// zero-knowledge is disabled.
+    pub fn blinding_factor(&self) -> usize {
+        return self.blinding_factor as usize;
+    }
+
+    /// Returns the factor by which the degree of the polynomial is reduced at each layer of the
+    /// low-degree proof.
+    pub fn folding_factor(&self) -> usize {
+        return 1 << (self.folding_factor as usize);
+    }
+
+    /// Returns the maximum number of evaluations of the polynomial remaining after the last
+    /// layer of the low-degree proof.
+    pub fn max_remainder_length(&self) -> usize {
+        return 1 << (self.max_remainder_length as usize);
+    }
+
     pub fn hash_fn(&self) -> HashFunction {
         return self.hash_fn;
     }
 
+    pub fn checkpoint_fn(&self) -> Option<&CheckpointFn> {
+        return self.checkpoint_fn.as_ref();
+    }
+
+    pub(crate) fn seed(&self) -> Option<[u8; 32]> {
+        return self.seed;
+    }
+
     pub fn security_level(&self, optimistic: bool) -> u32 {
-        let one_over_rho = (self.extension_factor() / MAX_CONSTRAINT_DEGREE) as u32;
+        let one_over_rho = (self.extension_factor() / EVALUATION_BLOWUP) as u32;
         let security_factor = 31 - one_over_rho.leading_zeros(); // same as log2(one_over_rho)
         let num_queries = if optimistic == true { self.num_queries } else { self.num_queries / 2 };
 
@@ -77,6 +270,83 @@ impl ProofOptions {
 
         return result;
     }
+
+    /// Returns options which satisfy `target_security` (as reported by `security_level(true)`)
+    /// for a trace of approximately `trace_len_hint` steps while minimizing the specified cost.
+    /// To keep prover time reasonable, grinding factor is never set above the default of 20.
+    pub fn tune(target_security: u32, trace_len_hint: usize, minimize: Minimize) -> ProofOptions {
+        let trace_length = cmp::max(trace_len_hint, crate::MIN_TRACE_LENGTH).next_power_of_two();
+
+        let mut result: Option<(ProofOptions, (usize, usize))> = None;
+        for extension_factor in (4..=8).map(|p| 1usize << p) {
+            for grinding_factor in 0..=(DEFAULT_GRINDING_FACTOR as u32) {
+
+                // find the smallest number of queries which satisfies the security target
+                let options = (1..=128)
+                    .map(|q| ProofOptions::new(extension_factor, q, grinding_factor, HashFunction::Blake3))
+                    .find(|options| options.security_level(true) >= target_security);
+
+                if let Some(options) = options {
+                    let size = options.estimate_proof_size(trace_length);
+                    let time = options.estimate_prover_time(trace_length);
+                    let cost = match minimize {
+                        Minimize::ProofSize  => (size, time),
+                        Minimize::ProverTime => (time, size),
+                    };
+                    if result.as_ref().map_or(true, |(_, best)| cost < *best) {
+                        result = Some((options, cost));
+                    }
+                }
+            }
+        }
+
+        return match result {
+            Some((options, _)) => options,
+            None => panic!("security level of {} bits cannot be achieved", target_security)
+        };
+    }
+
+    /// Makes sure all parameters are within the ranges accepted by the constructor and the
+    /// builder methods; options read from a proof must be validated before they are used.
+    pub(crate) fn validate(&self) -> Result<(), String> {
+        let is_in_range = |value: u8, min: usize, max: usize| {
+            value >= min.trailing_zeros() as u8 && value <= max.trailing_zeros() as u8
+        };
+        if !is_in_range(self.extension_factor, 16, 256) {
+            return Err(format!("extension factor 2^{} is invalid", self.extension_factor));
+        }
+        if self.num_queries == 0 || self.num_queries > 128 {
+            return Err(format!("number of queries {} is invalid", self.num_queries));
+        }
+        if self.grinding_factor > 32 {
+            return Err(format!("grinding factor {} is invalid", self.grinding_factor));
+        }
+        if !is_in_range(self.min_trace_length, crate::MIN_TRACE_LENGTH, 1 << 32) {
+            return Err(format!("min trace length 2^{} is invalid", self.min_trace_length));
+        }
+        if !is_in_range(self.folding_factor, FOLDING_FACTORS[0], FOLDING_FACTORS[2]) {
+            return Err(format!("folding factor 2^{} is invalid", self.folding_factor));
+        }
+        if !is_in_range(self.max_remainder_length, MIN_REMAINDER_LENGTH, MAX_REMAINDER_LENGTH) {
+            return Err(format!("max remainder length 2^{} is invalid", self.max_remainder_length));
+        }
+        if self.blinding_factor() > MAX_BLINDING_FACTOR {
+            return Err(format!("blinding factor {} is invalid", self.blinding_factor));
+        }
+        return check_remainder_length(self.folding_factor(), self.max_remainder_length());
+    }
+
+    /// Returns estimated size (in bytes) of a proof for a trace of `trace_length` steps.
+    pub(crate) fn estimate_proof_size(&self, trace_length: usize) -> usize {
+        let domain_depth = (trace_length * self.extension_factor()).trailing_zeros() as usize;
+        return domain_depth * (self.num_queries() * BYTES_PER_QUERY_LEVEL + BYTES_PER_LEVEL);
+    }
+
+    /// Returns estimated time (in nanoseconds) needed to prove a trace of `trace_length` steps.
+    pub(crate) fn estimate_prover_time(&self, trace_length: usize) -> usize {
+        let domain_size = trace_length * self.extension_factor();
+        return domain_size * NS_PER_DOMAIN_STEP + (1 << self.grinding_factor()) * NS_PER_POW_HASH;
+    }
 }
 
 impl Default for ProofOptions {
@@ -86,26 +356,47 @@ impl Default for ProofOptions {
             extension_factor: DEFAULT_EXTENSION_FACTOR.trailing_zeros() as u8,
             num_queries     : DEFAULT_NUM_QUERIES,
             grinding_factor : DEFAULT_GRINDING_FACTOR,
-            hash_fn         : hash::blake3,
+            min_trace_length: crate::MIN_TRACE_LENGTH.trailing_zeros() as u8,
+            folding_factor  : DEFAULT_FOLDING_FACTOR.trailing_zeros() as u8,
+            max_remainder_length: DEFAULT_MAX_REMAINDER.trailing_zeros() as u8,
+            blinding_factor : 0,
+            hash_fn         : HashFunction::Blake3,
+            checkpoint_fn   : None,
+            seed            : None,
         };
     }
 
 }
 
+// HELPER FUNCTIONS
+// ================================================================================================
+
+/// Makes sure the remainder of the low-degree proof can be committed to: a layer longer than
+/// `max_remainder_length` is folded into at least 2 * `max_remainder_length` / `folding_factor`
+/// values, and these values must fill at least 2 rows of `folding_factor` values each.
+fn check_remainder_length(folding_factor: usize, max_remainder_length: usize) -> Result<(), String> {
+    if max_remainder_length < folding_factor * folding_factor {
+        return Err(format!("max remainder length {} is too small for folding factor {}; it must be at least {}",
+            max_remainder_length, folding_factor, folding_factor * folding_factor));
+    }
+    return Ok(());
+}
+
 // HASH FUNCTION SERIALIZATION / DE-SERIALIZATION
 // ================================================================================================
 mod hash_fn_serialization {
 
-    use serde::{ Serializer, Deserializer, Deserialize, ser, de };
-    use crate::crypto::{ HashFunction, hash };
+    use serde::{ Serializer, Deserializer, Deserialize, de };
+    use crate::crypto::{ HashFunction };
 
     pub fn serialize<S>(hf: &HashFunction, s: S) -> Result<S::Ok, S::Error>
     where
         S: Serializer
     {
-        match *hf as usize {
-            f if f == hash::blake3 as usize => s.serialize_u8(0),
-            _ => Err(ser::Error::custom("unsupported hash function"))?
+        match hf {
+            HashFunction::Blake3 => s.serialize_u8(0),
+            HashFunction::Sha256 => s.serialize_u8(1),
+            HashFunction::Rescue => s.serialize_u8(2),
         }
     }
     
@@ -114,8 +405,10 @@ mod hash_fn_serialization {
         D: Deserializer<'de>
     {
         match Deserialize::deserialize(deserializer)? {
-            0u8 => Ok(hash::blake3),
+            0u8 => Ok(HashFunction::Blake3),
+            1u8 => Ok(HashFunction::Sha256),
+            2u8 => Ok(HashFunction::Rescue),
             _ => Err(de::Error::custom("unsupported hash function"))
         }
     }
-}
\ No newline at end of file
+}
@@ -0,0 +1,106 @@
+use crate::crypto::HashFunction;
+use super::{ ProofOptions, VerificationError };
+
+// TYPES AND INTERFACES
+// ================================================================================================
+
+/// Minimum requirements which parameters of a proof must satisfy before the verifier accepts
+/// the proof. Parameters of a proof are chosen by the prover, and so without a policy a prover
+/// could generate a proof which is cheap to forge (e.g. with very few queries).
+#[derive(Clone, Debug, PartialEq)]
+pub struct SecurityPolicy {
+    min_security        : u32,
+    min_queries         : usize,
+    min_extension_factor: usize,
+    hash_fns            : Vec<HashFunction>,
+}
+
+// SECURITY POLICY IMPLEMENTATION
+// ================================================================================================
+impl SecurityPolicy {
+
+    /// Returns a policy which requires at least `min_security` bits of conjectured security (as
+    /// reported by `ProofOptions::security_level(true)`), and places no other requirements on
+    /// the proof.
+    pub fn new(min_security: u32) -> SecurityPolicy {
+        return SecurityPolicy {
+            min_security        : min_security,
+            min_queries         : 1,
+            min_extension_factor: 16,
+            hash_fns            : vec![HashFunction::Blake3, HashFunction::Sha256, HashFunction::Rescue],
+        };
+    }
+
+    /// Returns this policy with proofs which use fewer than `num_queries` queries rejected.
+    pub fn with_min_queries(mut self, num_queries: usize) -> SecurityPolicy {
+        self.min_queries = num_queries;
+        return self;
+    }
+
+    /// Returns this policy with proofs which use extension factor smaller than `factor` rejected.
+    pub fn with_min_extension_factor(mut self, factor: usize) -> SecurityPolicy {
+        self.min_extension_factor = factor;
+        return self;
+    }
+
+    /// Returns this policy with proofs which commit to their values using hash functions other
+    /// than `hash_fns` rejected.
+    pub fn with_hash_fns(mut self, hash_fns: &[HashFunction]) -> SecurityPolicy {
+        self.hash_fns = hash_fns.to_vec();
+        return self;
+    }
+
+    pub fn min_security(&self) -> u32 {
+        return self.min_security;
+    }
+
+    pub fn min_queries(&self) -> usize {
+        return self.min_queries;
+    }
+
+    pub fn min_extension_factor(&self) -> usize {
+        return self.min_extension_factor;
+    }
+
+    pub fn hash_fns(&self) -> &[HashFunction] {
+        return &self.hash_fns;
+    }
+
+    /// Checks that `options` are valid and satisfy all requirements of this policy.
+    pub(crate) fn check(&self, options: &ProofOptions) -> Result<(), VerificationError> {
+        if let Err(msg) = options.validate() {
+            return Err(VerificationError::MalformedProof(format!(
+                "verification of proof options failed: {}", msg)));
+        }
+
+        let security_level = options.security_level(true);
+        if security_level < self.min_security {
+            return Err(VerificationError::InsufficientSecurity {
+                required: self.min_security, actual: security_level });
+        }
+        if options.num_queries() < self.min_queries {
+            return Err(VerificationError::PolicyViolation(format!(
+                "proof uses {} queries, but at least {} are required",
+                options.num_queries(), self.min_queries)));
+        }
+        if options.extension_factor() < self.min_extension_factor {
+            return Err(VerificationError::PolicyViolation(format!(
+                "proof uses extension factor {}, but at least {} is required",
+                options.extension_factor(), self.min_extension_factor)));
+        }
+        if !self.hash_fns.contains(&options.hash_fn()) {
+            return Err(VerificationError::PolicyViolation(format!(
+                "proof uses {:?} hash function, which is not one of {:?}",
+                options.hash_fn(), self.hash_fns)));
+        }
+        return Ok(());
+    }
+}
+
+impl Default for SecurityPolicy {
+
+    /// Returns a policy which accepts all valid proof parameters.
+    fn default() -> SecurityPolicy {
+        return SecurityPolicy::new(0);
+    }
+}
@@ -1,16 +1,33 @@
+use std::{ fmt, convert::TryFrom };
 use serde::{ Serialize, Deserialize };
 use crate::crypto::{ BatchMerkleProof };
-use crate::stark::{ fri::FriProof, TraceState, ProofOptions };
-use crate::utils::{ uninit_vector, as_bytes };
+use crate::stark::{
+    fri::{ FriProof, FriLayer }, TraceTable, TraceState, TraceLayout, SegmentInfo, ProofOptions, utils,
+    PROTOCOL_VERSION, air_fingerprint };
+use crate::utils::{ uninit_vector, as_bytes, bytes::{ ByteWriter, ByteReader } };
+
+// CONSTANTS
+// ================================================================================================
+
+/// Version of the compact encoding produced by `StarkProof::to_bytes()`.
+pub const PROOF_FORMAT_VERSION: u8 = 2;
+
+const MAX_OPTIONS_BYTES : usize = 64;
+const MAX_DOMAIN_DEPTH  : u8 = 40;
+const MAX_REGISTERS     : usize = 256;
 
 // TYPES AND INTERFACES
 // ================================================================================================
 
-// TODO: custom serialization should reduce size by 5% - 10%
 #[derive(Clone, Serialize, Deserialize)]
 pub struct StarkProof {
+    version             : u32,
+    air_fingerprint     : [u8; 32],
     trace_root          : [u8; 32],
     trace_info          : TraceInfo,
+    assertion_digest    : [u8; 32],
+    outputs             : Vec<u128>,
+    segments            : Vec<SegmentInfo>,
     trace_nodes         : Vec<Vec<[u8; 32]>>,
     trace_evaluations   : Vec<Vec<u128>>,
     constraint_root     : [u8; 32],
@@ -18,6 +35,7 @@ pub struct StarkProof {
     deep_values         : DeepValues,
     degree_proof        : FriProof,
     pow_nonce           : u64,
+    zk_values           : Option<ZkValues>,
     options             : ProofOptions
 }
 
@@ -27,12 +45,46 @@ pub struct DeepValues {
     pub trace_at_z2     : Vec<u128>,
 }
 
+/// Values included only in zero-knowledge proofs: salts of the queried trace states, and a
+/// commitment to the polynomial which masks the composition polynomial together with its
+/// evaluations at the queried positions and at the DEEP point z.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct ZkValues {
+    pub trace_salts     : Vec<[u8; 32]>,
+    pub mask_root       : [u8; 32],
+    pub mask_nodes      : Vec<Vec<[u8; 32]>>,
+    pub mask_evaluations: Vec<u128>,
+    pub mask_salts      : Vec<[u8; 32]>,
+    pub mask_at_z       : u128,
+}
+
+/// Number of bytes taken up by each component of a serialized proof. Sizes of all components
+/// add up to the size of the serialized proof, except for a few bytes of length prefixes.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct ProofSizeReport {
+    pub total               : usize,
+    pub num_queries         : usize,
+    pub trace_length        : usize,
+    pub commitments         : usize,
+    pub trace_evaluations   : usize,
+    pub trace_paths         : usize,
+    pub constraint_evaluations: usize,
+    pub constraint_paths    : usize,
+    pub deep_values         : usize,
+    pub fri_layers          : Vec<usize>,
+    pub fri_remainder       : usize,
+    pub zk_values           : usize,
+    pub metadata            : usize,
+    pub path_bytes_per_query: usize,
+}
+
 #[derive(Clone, Debug, Serialize, Deserialize)]
 pub struct TraceInfo {
     pub domain_depth    : u8,
     pub ctx_depth       : u8,
     pub loop_depth      : u8,
     pub stack_depth     : u8,
+    pub final_depth     : u8,
     pub op_count        : u32,
 }
 
@@ -48,23 +100,23 @@ impl StarkProof {
         deep_values         : DeepValues,
         degree_proof        : FriProof,
         pow_nonce           : u64,
-        op_count            : u128,
-        ctx_depth           : usize,
-        loop_depth          : usize,
-        stack_depth         : usize,
+        trace_info          : TraceInfo,
+        assertion_digest    : &[u8; 32],
+        outputs             : &[u128],
+        segments            : &[SegmentInfo],
+        zk_values           : Option<ZkValues>,
         options             : &ProofOptions ) -> StarkProof
     {
-        let trace_info = TraceInfo {
-            domain_depth        : trace_proof.depth,
-            ctx_depth           : ctx_depth as u8,
-            loop_depth          : loop_depth as u8,
-            stack_depth         : stack_depth as u8,
-            op_count            : op_count as u32,
-        };
+        debug_assert!(trace_info.domain_depth == trace_proof.depth, "trace info does not match trace proof");
 
         return StarkProof {
+            version             : PROTOCOL_VERSION,
+            air_fingerprint     : air_fingerprint(),
             trace_root          : *trace_root,
             trace_info          : trace_info,
+            assertion_digest    : *assertion_digest,
+            outputs             : outputs.to_vec(),
+            segments            : segments.to_vec(),
             trace_nodes         : trace_proof.nodes,
             trace_evaluations   : trace_evaluations,
             constraint_root     : *constraint_root,
@@ -72,18 +124,275 @@ impl StarkProof {
             deep_values         : deep_values,
             degree_proof        : degree_proof,
             pow_nonce           : pow_nonce,
+            zk_values           : zk_values,
             options             : options.clone()
         };
     }
 
+    /// Serializes the proof into a compact encoding; the first byte is the format version, and
+    /// is followed by proof options. Lengths are encoded as varints, and lengths which can be
+    /// inferred from other parts of the proof (e.g. the number of registers in each queried
+    /// trace state) are encoded only once. Authentication paths are written in batched form, and
+    /// so path segments shared by several queries are included only once.
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut writer = ByteWriter::new();
+        writer.write_u8(PROOF_FORMAT_VERSION);
+        writer.write_varint(self.version as u64);
+        writer.write_digest(&self.air_fingerprint);
+        writer.write_bytes(&bincode::serialize(&self.options).expect("failed to serialize proof options"));
+
+        // trace info and public values
+        writer.write_u8(self.trace_info.domain_depth);
+        writer.write_u8(self.trace_info.ctx_depth);
+        writer.write_u8(self.trace_info.loop_depth);
+        writer.write_u8(self.trace_info.stack_depth);
+        writer.write_u8(self.trace_info.final_depth);
+        writer.write_varint(self.trace_info.op_count as u64);
+        writer.write_digest(&self.assertion_digest);
+        writer.write_elements(&self.outputs);
+        writer.write_varint(self.segments.len() as u64);
+        for segment in self.segments.iter() {
+            writer.write_varint(segment.length as u64);
+            writer.write_varint(segment.op_count as u64);
+            writer.write_u8(segment.final_depth);
+        }
+
+        // trace commitment and queried trace states
+        let num_registers = self.deep_values.trace_at_z1.len();
+        writer.write_digest(&self.trace_root);
+        writer.write_varint(num_registers as u64);
+        writer.write_varint(self.trace_evaluations.len() as u64);
+        self.trace_evaluations.iter().for_each(|state| writer.write_element_row(state));
+        write_paths(&mut writer, &self.trace_nodes);
+
+        // constraint commitment and queried constraint evaluations
+        writer.write_digest(&self.constraint_root);
+        writer.write_digests(&self.constraint_proof.values);
+        write_paths(&mut writer, &self.constraint_proof.nodes);
+        writer.write_u8(self.constraint_proof.depth);
+        writer.write_element_row(&self.deep_values.trace_at_z1);
+        writer.write_element_row(&self.deep_values.trace_at_z2);
+
+        // low-degree proof
+        writer.write_varint(self.degree_proof.layers.len() as u64);
+        for layer in self.degree_proof.layers.iter() {
+            writer.write_digest(&layer.root);
+            writer.write_elements(&layer.values);
+            write_paths(&mut writer, &layer.nodes);
+            writer.write_u8(layer.depth);
+        }
+        writer.write_digest(&self.degree_proof.rem_root);
+        writer.write_elements(&self.degree_proof.rem_values);
+        writer.write_varint(self.pow_nonce);
+
+        // zero-knowledge values; salts and mask evaluations are listed for every queried state
+        match &self.zk_values {
+            Some(zk) => {
+                writer.write_u8(1);
+                zk.trace_salts.iter().for_each(|salt| writer.write_digest(salt));
+                writer.write_digest(&zk.mask_root);
+                write_paths(&mut writer, &zk.mask_nodes);
+                writer.write_element_row(&zk.mask_evaluations);
+                zk.mask_salts.iter().for_each(|salt| writer.write_digest(salt));
+                writer.write_element(zk.mask_at_z);
+            },
+            None => writer.write_u8(0),
+        }
+
+        return writer.into_bytes();
+    }
+
+    /// Reads a proof from bytes produced by `to_bytes()`. Proof options are validated, and all
+    /// lengths are checked against limits implied by the options before any values are read.
+    pub fn from_bytes(bytes: &[u8]) -> Result<StarkProof, String> {
+        let mut reader = ByteReader::new(bytes);
+        match reader.read_u8() {
+            Ok(PROOF_FORMAT_VERSION) => (),
+            Ok(version) => return Err(format!(
+                "proof format version {} is not supported; expected version {}", version, PROOF_FORMAT_VERSION)),
+            Err(_) => return Err(String::from("proof bytes are empty")),
+        }
+
+        // protocol version is not checked here, so that the verifier can report a mismatch
+        let version = read_u32(&mut reader)?;
+        let air_fingerprint = reader.read_digest()?;
+
+        let options: ProofOptions = bincode::deserialize(reader.read_bytes(MAX_OPTIONS_BYTES, "option bytes")?)
+            .map_err(|err| format!("failed to deserialize proof options: {}", err))?;
+        options.validate()?;
+        let num_queries = options.num_queries();
+
+        // trace info and public values
+        let trace_info = TraceInfo {
+            domain_depth    : reader.read_u8()?,
+            ctx_depth       : reader.read_u8()?,
+            loop_depth      : reader.read_u8()?,
+            stack_depth     : reader.read_u8()?,
+            final_depth     : reader.read_u8()?,
+            op_count        : read_u32(&mut reader)?,
+        };
+        let min_domain_depth = (options.extension_factor() * crate::MIN_TRACE_LENGTH).trailing_zeros() as u8;
+        if trace_info.domain_depth < min_domain_depth || trace_info.domain_depth > MAX_DOMAIN_DEPTH {
+            return Err(format!("domain depth {} is invalid", trace_info.domain_depth));
+        }
+        let max_depth = trace_info.domain_depth as usize;
+        let trace_length = (1usize << trace_info.domain_depth) / options.extension_factor();
+
+        let assertion_digest = reader.read_digest()?;
+        let outputs = reader.read_elements(crate::MAX_OUTPUTS, "outputs")?;
+        let num_segments = reader.read_len(trace_length, "segments")?;
+        let segments = (0..num_segments).map(|_| Ok(SegmentInfo {
+            length      : read_u32(&mut reader)?,
+            op_count    : read_u32(&mut reader)?,
+            final_depth : reader.read_u8()?,
+        })).collect::<Result<Vec<_>, String>>()?;
+
+        // trace commitment and queried trace states
+        let trace_root = reader.read_digest()?;
+        let num_registers = reader.read_len(MAX_REGISTERS, "registers")?;
+        let num_states = reader.read_len(num_queries, "queried trace states")?;
+        let trace_evaluations = (0..num_states)
+            .map(|_| reader.read_element_row(num_registers))
+            .collect::<Result<Vec<_>, String>>()?;
+        let trace_nodes = read_paths(&mut reader, num_states, max_depth)?;
+
+        // constraint commitment and queried constraint evaluations
+        let constraint_root = reader.read_digest()?;
+        let constraint_values = reader.read_digests(num_queries, "queried constraint evaluations")?;
+        let constraint_nodes = read_paths(&mut reader, constraint_values.len(), max_depth)?;
+        let constraint_proof = BatchMerkleProof {
+            values  : constraint_values,
+            nodes   : constraint_nodes,
+            depth   : read_depth(&mut reader, max_depth)?,
+        };
+        let deep_values = DeepValues {
+            trace_at_z1 : reader.read_element_row(num_registers)?,
+            trace_at_z2 : reader.read_element_row(num_registers)?,
+        };
+
+        // low-degree proof
+        let num_layers = reader.read_len(max_depth, "FRI layers")?;
+        let mut layers = Vec::with_capacity(num_layers);
+        for _ in 0..num_layers {
+            let root = reader.read_digest()?;
+            let values = reader.read_elements(num_queries * options.folding_factor(), "FRI layer values")?;
+            let nodes = read_paths(&mut reader, num_queries, max_depth)?;
+            layers.push(FriLayer { root, values, nodes, depth: read_depth(&mut reader, max_depth)? });
+        }
+        let degree_proof = FriProof {
+            layers      : layers,
+            rem_root    : reader.read_digest()?,
+            rem_values  : reader.read_elements(options.max_remainder_length(), "FRI remainder values")?,
+        };
+        let pow_nonce = reader.read_varint()?;
+
+        // zero-knowledge values
+        let zk_values = match reader.read_u8()? {
+            0 => None,
+            1 => Some(ZkValues {
+                trace_salts     : (0..num_states).map(|_| reader.read_digest()).collect::<Result<_, String>>()?,
+                mask_root       : reader.read_digest()?,
+                mask_nodes      : read_paths(&mut reader, num_states, max_depth)?,
+                mask_evaluations: reader.read_element_row(num_states)?,
+                mask_salts      : (0..num_states).map(|_| reader.read_digest()).collect::<Result<_, String>>()?,
+                mask_at_z       : reader.read_element()?,
+            }),
+            flag => return Err(format!("zero-knowledge flag {} is invalid", flag)),
+        };
+
+        reader.finish()?;
+        return Ok(StarkProof {
+            version, air_fingerprint, trace_root, trace_info, assertion_digest, outputs, segments, trace_nodes, trace_evaluations,
+            constraint_root, constraint_proof, deep_values, degree_proof, pow_nonce, zk_values, options,
+        });
+    }
+
+    /// Returns the version of the proving protocol under which the proof was generated.
+    pub fn version(&self) -> u32 {
+        return self.version;
+    }
+
+    /// Returns the fingerprint of the execution trace layout and constraints for which the
+    /// proof was generated (see `stark::air_fingerprint()`).
+    pub fn air_fingerprint(&self) -> &[u8; 32] {
+        return &self.air_fingerprint;
+    }
+
     pub fn trace_root(&self) -> &[u8; 32] {
         return &self.trace_root;
     }
 
+    /// Returns a digest of boundary assertions which were enforced when the proof was generated.
+    pub fn assertion_digest(&self) -> &[u8; 32] {
+        return &self.assertion_digest;
+    }
+
+    /// Returns outputs of the program execution; the outputs are bound to the proof by boundary
+    /// constraints, and so can be relied upon only after the proof has been verified.
+    pub fn outputs(&self) -> &[u128] {
+        return &self.outputs;
+    }
+
+    #[cfg(test)]
+    pub fn set_pow_nonce(&mut self, nonce: u64) {
+        self.pow_nonce = nonce;
+    }
+
+    #[cfg(test)]
+    pub fn set_outputs(&mut self, outputs: &[u128]) {
+        self.outputs = outputs.to_vec();
+    }
+
+    /// Returns lengths of all program executions attested to by a batch proof, together with
+    /// values claimed for the last step of each execution; empty for proofs of a single execution.
+    pub fn segments(&self) -> &[SegmentInfo] {
+        return &self.segments;
+    }
+
     pub fn options(&self) -> &ProofOptions {
         return &self.options;
     }
 
+    pub fn num_queries(&self) -> usize {
+        return self.options.num_queries();
+    }
+
+    /// Returns the number of layers in the low-degree proof, not counting the remainder.
+    pub fn fri_layer_count(&self) -> usize {
+        return self.degree_proof.layers.len();
+    }
+
+    /// Returns the number of bytes taken up by each component of this proof when serialized.
+    pub fn size_breakdown(&self) -> ProofSizeReport {
+        let fri_layers: Vec<usize> = self.degree_proof.layers.iter().map(serialized_size).collect();
+
+        // authentication paths are batched, and so nodes shared by several queries are counted once
+        let fri_paths: usize = self.degree_proof.layers.iter().map(|layer| serialized_size(&layer.nodes)).sum();
+        let paths = serialized_size(&self.trace_nodes) + serialized_size(&self.constraint_proof.nodes) + fri_paths;
+
+        return ProofSizeReport {
+            total                   : serialized_size(self),
+            num_queries             : self.num_queries(),
+            trace_length            : self.trace_length(),
+            commitments             : serialized_size(&self.trace_root) + serialized_size(&self.constraint_root),
+            trace_evaluations       : serialized_size(&self.trace_evaluations),
+            trace_paths             : serialized_size(&self.trace_nodes),
+            constraint_evaluations  : serialized_size(&self.constraint_proof.values),
+            constraint_paths        : serialized_size(&self.constraint_proof.nodes)
+                + serialized_size(&self.constraint_proof.depth),
+            deep_values             : serialized_size(&self.deep_values),
+            fri_layers              : fri_layers,
+            fri_remainder           : serialized_size(&self.degree_proof.rem_root)
+                + serialized_size(&self.degree_proof.rem_values),
+            zk_values               : serialized_size(&self.zk_values),
+            metadata                : serialized_size(&self.version) + serialized_size(&self.air_fingerprint)
+                + serialized_size(&self.trace_info) + serialized_size(&self.assertion_digest)
+                + serialized_size(&self.outputs) + serialized_size(&self.segments)
+                + serialized_size(&self.pow_nonce) + serialized_size(&self.options),
+            path_bytes_per_query    : paths / self.num_queries(),
+        };
+    }
+
     pub fn domain_size(&self) -> usize {
         return usize::pow(2, self.trace_info.domain_depth as u32);
     }
@@ -93,7 +402,11 @@ impl StarkProof {
         let hash = self.options.hash_fn();
         let mut hashed_states = uninit_vector::<[u8; 32]>(self.trace_evaluations.len());
         for i in 0..self.trace_evaluations.len() {
-            hash(as_bytes(&self.trace_evaluations[i]), &mut hashed_states[i]);
+            match &self.zk_values {
+                Some(zk) => hashed_states[i] = utils::hash_salted(
+                    as_bytes(&self.trace_evaluations[i]), &zk.trace_salts[i], hash),
+                None => hash.digest(as_bytes(&self.trace_evaluations[i]), &mut hashed_states[i]),
+            }
         }
 
         return BatchMerkleProof {
@@ -107,6 +420,38 @@ impl StarkProof {
         return &self.constraint_root;
     }
 
+    /// Returns `true` if the proof was generated in zero-knowledge mode.
+    pub fn is_zero_knowledge(&self) -> bool {
+        return self.zk_values.is_some();
+    }
+
+    pub fn zk_values(&self) -> Option<&ZkValues> {
+        return self.zk_values.as_ref();
+    }
+
+    /// Returns Merkle proof of the queried evaluations of the mask polynomial; can be called
+    /// only for zero-knowledge proofs.
+    pub fn mask_proof(&self) -> BatchMerkleProof {
+        let zk = self.zk_values.as_ref().expect("proof is not zero-knowledge");
+        let hash = self.options.hash_fn();
+        let values = zk.mask_evaluations.iter().zip(zk.mask_salts.iter())
+            .map(|(value, salt)| utils::hash_salted(&value.to_le_bytes(), salt, hash))
+            .collect();
+
+        return BatchMerkleProof {
+            nodes   : zk.mask_nodes.clone(),
+            values  : values,
+            depth   : self.trace_info.domain_depth,
+        };
+    }
+
+    /// Returns the seed from which the DEEP point z and composition coefficients are drawn;
+    /// in zero-knowledge proofs, the seed also binds the commitment to the mask polynomial.
+    pub fn deep_seed(&self) -> [u8; 32] {
+        let mask_root = self.zk_values.as_ref().map(|zk| &zk.mask_root);
+        return utils::get_deep_seed(&self.constraint_root, mask_root, self.options.hash_fn());
+    }
+
     pub fn constraint_proof(&self) -> BatchMerkleProof {
         return self.constraint_proof.clone();
     }
@@ -141,6 +486,16 @@ impl StarkProof {
         return self.trace_info.stack_depth as usize;
     }
 
+    /// Returns names and positions of registers in the execution trace of the proven program.
+    pub fn trace_layout(&self) -> TraceLayout {
+        return TraceLayout::new(self.ctx_depth(), self.loop_depth(), self.stack_depth());
+    }
+
+    /// Returns depth of the user stack at the last step of the execution trace.
+    pub fn final_depth(&self) -> usize {
+        return self.trace_info.final_depth as usize;
+    }
+
     pub fn op_count(&self) -> u128 {
         return self.trace_info.op_count as u128;
     }
@@ -162,4 +517,98 @@ impl StarkProof {
             self.stack_depth(),
             &self.deep_values.trace_at_z2);
     }
-}
\ No newline at end of file
+}
+
+// TRACE INFO IMPLEMENTATION
+// ================================================================================================
+impl TraceInfo {
+
+    /// Describes the shape of the execution `trace` and the values claimed for its last step;
+    /// returns an error if any of these values does not fit into the proof.
+    pub fn new(trace: &TraceTable) -> Result<TraceInfo, String> {
+        let last_state = trace.get_last_state();
+        return Ok(TraceInfo {
+            domain_depth    : narrow(trace.domain_size().trailing_zeros(), "domain depth")?,
+            ctx_depth       : narrow(trace.ctx_depth(), "context depth")?,
+            loop_depth      : narrow(trace.loop_depth(), "loop depth")?,
+            stack_depth     : narrow(trace.stack_depth(), "stack depth")?,
+            final_depth     : narrow(last_state.watermark(), "final stack depth")?,
+            op_count        : narrow(last_state.op_counter(), "operation count")?,
+        });
+    }
+}
+
+// PROOF SIZE REPORT IMPLEMENTATION
+// ================================================================================================
+impl ProofSizeReport {
+
+    /// Returns the sum of sizes of all proof components.
+    pub fn components_total(&self) -> usize {
+        return self.commitments + self.trace_evaluations + self.trace_paths
+            + self.constraint_evaluations + self.constraint_paths + self.deep_values
+            + self.fri_layers.iter().sum::<usize>() + self.fri_remainder
+            + self.zk_values + self.metadata;
+    }
+}
+
+impl fmt::Display for ProofSizeReport {
+
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "Proof size: {} bytes ({} queries, trace length {})", self.total, self.num_queries, self.trace_length)?;
+        writeln!(f, "    commitments:            {} bytes", self.commitments)?;
+        writeln!(f, "    trace evaluations:      {} bytes", self.trace_evaluations)?;
+        writeln!(f, "    trace paths:            {} bytes", self.trace_paths)?;
+        writeln!(f, "    constraint evaluations: {} bytes", self.constraint_evaluations)?;
+        writeln!(f, "    constraint paths:       {} bytes", self.constraint_paths)?;
+        writeln!(f, "    DEEP values:            {} bytes", self.deep_values)?;
+        for (i, size) in self.fri_layers.iter().enumerate() {
+            writeln!(f, "    FRI layer {:<2}:           {} bytes", i, size)?;
+        }
+        writeln!(f, "    FRI remainder:          {} bytes", self.fri_remainder)?;
+        if self.zk_values > 1 {
+            writeln!(f, "    zero-knowledge values:  {} bytes", self.zk_values)?;
+        }
+        writeln!(f, "    metadata:               {} bytes", self.metadata)?;
+        write!(f,   "Authentication paths per query: {} bytes", self.path_bytes_per_query)?;
+        return Ok(());
+    }
+}
+
+// HELPER FUNCTIONS
+// ================================================================================================
+
+/// Writes authentication paths of a batch Merkle proof; each path holds only the nodes which
+/// are not shared with paths written before it.
+fn write_paths(writer: &mut ByteWriter, paths: &[Vec<[u8; 32]>]) {
+    writer.write_varint(paths.len() as u64);
+    paths.iter().for_each(|path| writer.write_digests(path));
+}
+
+fn narrow<T: TryFrom<U>, U: Copy + fmt::Display>(value: U, name: &str) -> Result<T, String> {
+    return T::try_from(value).map_err(|_| format!("{} {} is too large to be included in a proof", name, value));
+}
+
+fn read_paths(reader: &mut ByteReader, max_paths: usize, max_depth: usize) -> Result<Vec<Vec<[u8; 32]>>, String> {
+    let num_paths = reader.read_len(max_paths, "authentication paths")?;
+    return (0..num_paths).map(|_| reader.read_digests(max_depth, "path nodes")).collect();
+}
+
+fn read_depth(reader: &mut ByteReader, max_depth: usize) -> Result<u8, String> {
+    let depth = reader.read_u8()?;
+    if depth as usize > max_depth {
+        return Err(format!("tree depth {} exceeds domain depth {}", depth, max_depth));
+    }
+    return Ok(depth);
+}
+
+fn read_u32(reader: &mut ByteReader) -> Result<u32, String> {
+    let value = reader.read_varint()?;
+    if value > u32::MAX as u64 {
+        return Err(format!("value {} does not fit into 32 bits", value));
+    }
+    return Ok(value as u32);
+}
+
+fn serialized_size<T: Serialize>(value: &T) -> usize {
+    return bincode::serialized_size(value).expect("failed to compute serialized size") as usize;
+}
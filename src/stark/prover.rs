@@ -1,104 +1,120 @@
 use std::time::Instant;
 use log::debug;
 use crate::{
-    math::{ field, polynom, fft },
+    math::{ field, polynom, fft::{ self, FftContext }, parallel },
     crypto::MerkleTree,
+    utils::{ num_threads, CONCURRENT },
 };
 use super::{
-    ProofOptions, StarkProof, CompositionCoefficients, DeepValues, fri, utils,
-    trace::{ TraceTable, TraceState },
+    ProofOptions, StarkProof, TraceInfo, CompositionCoefficients, DeepValues, ZkValues, Assertion, fri, utils,
+    hash_assertions, get_constraint_seed,
+    checkpoint::{ ProverCheckpoint, ProverPhase, Commitments, Mask },
+    timings::{ ProvingTimings, REGION_LENGTH },
+    trace::TraceTable,
+    batch::TraceSegment,
     constraints::{ ConstraintTable, ConstraintPoly },
-    MAX_CONSTRAINT_DEGREE,
+    EVALUATION_BLOWUP,
 };
 
-// PROVER FUNCTION
+// PROVER FUNCTIONS
 // ================================================================================================
 
-pub fn prove(trace: &mut TraceTable, inputs: &[u128], outputs: &[u128], options: &ProofOptions) -> StarkProof {
+/// Generates a proof of the execution `trace`, and returns it together with time spent in each
+/// phase of proof generation. Returns an error if the trace is too large to be described by
+/// the proof.
+pub fn prove(trace: TraceTable, inputs: &[u128], outputs: &[u128], assertions: &[Assertion], options: &ProofOptions) -> Result<(StarkProof, ProvingTimings), String> {
+    let segment = TraceSegment::from_trace(&trace, 0..trace.unextended_length(), inputs, outputs);
+    return prove_segments(trace, vec![segment], assertions, options);
+}
+
+/// Generates a single proof of several executions of the same program; the `trace` must consist
+/// of execution traces of all runs stacked on top of each other as described by `segments`.
+pub fn prove_batch(trace: TraceTable, segments: Vec<TraceSegment>, options: &ProofOptions) -> Result<(StarkProof, ProvingTimings), String> {
+    return prove_segments(trace, segments, &[], options);
+}
+
+fn prove_segments(mut trace: TraceTable, segments: Vec<TraceSegment>, assertions: &[Assertion], options: &ProofOptions) -> Result<(StarkProof, ProvingTimings), String> {
+    let mut timings = ProvingTimings::default();
+    let trace_info = TraceInfo::new(&trace)?;
+
     // 1 ----- extend execution trace -------------------------------------------------------------
     let now = Instant::now();
 
-    // build LDE domain and LDE twiddles (for FFT evaluation over LDE domain)
-    let lde_root = field::get_root_of_unity(trace.domain_size());
-    let lde_domain = field::get_power_series(lde_root, trace.domain_size());
-    let lde_twiddles = twiddles_from_domain(&lde_domain);
-
-    // extend the execution trace registers to LDE domain
-    trace.extend(&lde_twiddles);
-    debug!("Extended execution trace from {} to {} steps in {} ms",
+    // build LDE domain together with twiddles for FFTs over it; the context is shared by all
+    // phases of the prover, including FFTs over smaller domains
+    let fft_ctx = FftContext::new(trace.domain_size());
+
+    // extend the execution trace registers to LDE domain; in zero-knowledge mode, random values
+    // are mixed into trace polynomials before the extension
+    let zk_seed = if options.zero_knowledge() { Some(utils::get_prover_seed(options.seed())) } else { None };
+    match zk_seed {
+        Some(seed) => {
+            let blinding = utils::get_trace_blinding(&seed, trace.register_count(), options.blinding_factor());
+            trace.extend_blinded(&fft_ctx, blinding);
+        },
+        None => trace.extend(&fft_ctx),
+    }
+    timings.trace_extension = now.elapsed();
+    debug!("Extended execution trace from {} to {} steps in {} ms using {} threads",
         trace.unextended_length(),
         trace.domain_size(), 
-        now.elapsed().as_millis());
+        now.elapsed().as_millis(),
+        num_threads());
 
-    // 2 ----- build Merkle tree from the extended execution trace ------------------------------------
-    let now = Instant::now();
-    let trace_tree = trace.build_merkle_tree(options.hash_fn());
-    debug!("Built trace Merkle tree in {} ms", 
-        now.elapsed().as_millis());
+    let checkpoint = ProverCheckpoint::new(trace, segments, assertions, zk_seed, options);
+    save_checkpoint(&checkpoint, options);
 
-    // 3 ----- evaluate constraints ---------------------------------------------------------------
-    let now = Instant::now();
-    
-    // initialize constraint evaluation table
-    let mut constraints = ConstraintTable::new(&trace, trace_tree.root(), inputs, outputs);
-    
-    // allocate space to hold current and next states for constraint evaluations
-    let mut current = TraceState::new(trace.ctx_depth(), trace.loop_depth(), trace.stack_depth());
-    let mut next = TraceState::new(trace.ctx_depth(), trace.loop_depth(), trace.stack_depth());
-
-    // we don't need to evaluate constraints over the entire extended execution trace; we need
-    // to evaluate them over the domain extended to match max constraint degree - thus, we can
-    // skip most trace states for the purposes of constraint evaluation.
-    let stride = trace.extension_factor() / MAX_CONSTRAINT_DEGREE;
-    for i in (0..trace.domain_size()).step_by(stride) {
-        // TODO: this loop should be parallelized and also potentially optimized to avoid copying
-        // next state from the trace table twice
-
-        // copy current and next states from the trace table; next state may wrap around the
-        // execution trace (close to the end of the trace)
-        trace.fill_state(&mut current, i);
-        trace.fill_state(&mut next, (i + trace.extension_factor()) % trace.domain_size());
-
-        // evaluate the constraints
-        constraints.evaluate(&current, &next, lde_domain[i], i / stride);
-    }
+    let proof = prove_from(checkpoint, trace_info, &fft_ctx, options, &mut timings);
+    return Ok((proof, timings));
+}
 
-    debug!("Evaluated {} constraints over domain of {} elements in {} ms",
-        constraints.constraint_count(),
-        constraints.evaluation_domain_size(),
-        now.elapsed().as_millis());
+/// Resumes proving from a checkpoint serialized via `ProverCheckpoint::to_bytes()`; the
+/// resulting proof is identical to the proof which would have been produced by an
+/// uninterrupted prover. `options` must be the same as the options of the interrupted prover.
+pub fn resume(checkpoint: &[u8], options: &ProofOptions) -> Result<StarkProof, String> {
+    let checkpoint = ProverCheckpoint::from_bytes(checkpoint, options)?;
+    let trace_info = TraceInfo::new(&checkpoint.trace)?;
+    debug!("Resuming proof generation after {:?} phase", checkpoint.phase());
+
+    let fft_ctx = FftContext::new(checkpoint.trace.domain_size());
+    let mut timings = ProvingTimings::default();
+    return Ok(prove_from(checkpoint, trace_info, &fft_ctx, options, &mut timings));
+}
 
-    // 4 ----- convert constraint evaluations into a polynomial -----------------------------------
-    let now = Instant::now();
-    let constraint_poly = constraints.combine_polys();
-    debug!("Converted constraint evaluations into a single polynomial of degree {} in {} ms",
-        constraint_poly.degree(),
-        now.elapsed().as_millis());
+/// Completes proof generation starting from the phase following the last completed phase
+/// of the `checkpoint`.
+fn prove_from(mut checkpoint: ProverCheckpoint, trace_info: TraceInfo, fft_ctx: &FftContext, options: &ProofOptions, timings: &mut ProvingTimings) -> StarkProof {
+    if checkpoint.phase() == ProverPhase::TraceExtended {
+        checkpoint.commitments = Some(commit_constraints(&checkpoint, fft_ctx, options, timings));
+        save_checkpoint(&checkpoint, options);
+    }
 
-    // 5 ----- build Merkle tree from constraint polynomial evaluations ---------------------------
-    let now = Instant::now();
-    
-    // evaluate constraint polynomial over the evaluation domain
-    let constraint_evaluations = constraint_poly.eval(&lde_twiddles);
+    let assertion_digest = hash_assertions(checkpoint.assertions(), options.hash_fn());
 
-    // put evaluations into a Merkle tree; 4 evaluations per leaf
-    let constraint_evaluations = evaluations_to_leaves(constraint_evaluations);
-    let constraint_tree = MerkleTree::new(constraint_evaluations, options.hash_fn());
-    debug!("Evaluated constraint polynomial and built constraint Merkle tree in {} ms",
-        now.elapsed().as_millis());
+    // a proof of a single execution carries its outputs, while a batch proof carries the shape of
+    // every execution; outputs of a batch are provided by the verifier
+    let num_segments = checkpoint.segments().len();
+    let (outputs, segments) = match checkpoint.segments() {
+        [segment] => (segment.outputs.clone(), Vec::new()),
+        segments  => (Vec::new(), segments.iter().map(|s| s.info()).collect()),
+    };
+    let ProverCheckpoint { trace, commitments, zk_seed, .. } = checkpoint;
+    let Commitments { trace_tree, constraint_poly, constraint_tree, mask } = commitments.unwrap();
 
     // 6 ----- build and evaluate deep composition polynomial -------------------------------------
     let now = Instant::now();
 
-    // combine trace and constraint polynomials into the final deep composition polynomial
-    let seed = constraint_tree.root();
-    let (composition_poly, deep_values) = build_composition_poly(&trace, constraint_poly, seed);
+    // combine trace and constraint polynomials (and the mask polynomial, if there is one) into
+    // the final deep composition polynomial
+    let seed = utils::get_deep_seed(constraint_tree.root(), mask.as_ref().map(|m| m.tree.root()), options.hash_fn());
+    let (composition_poly, deep_values, mask_at_z) = build_composition_poly(&trace, num_segments, constraint_poly, mask.as_ref(), &seed);
 
     // evaluate the composition polynomial over LDE domain
     let mut composed_evaluations = composition_poly;
-    debug_assert!(composed_evaluations.capacity() == lde_domain.len(), "invalid composition polynomial capacity");
+    debug_assert!(composed_evaluations.capacity() == fft_ctx.size(), "invalid composition polynomial capacity");
     unsafe { composed_evaluations.set_len(composed_evaluations.capacity()); }
-    polynom::eval_fft_twiddles(&mut composed_evaluations, &lde_twiddles, true);
+    fft::evaluate_with_ctx(&mut composed_evaluations, fft_ctx);
+    timings.composition = now.elapsed();
 
     debug!("Built composition polynomial and evaluated it over domain of {} elements in {} ms",
         composed_evaluations.len(),
@@ -106,9 +122,11 @@ pub fn prove(trace: &mut TraceTable, inputs: &[u128], outputs: &[u128], options:
 
     // 7 ----- compute FRI layers for the composition polynomial ----------------------------------
     let now = Instant::now();
-    let composition_degree = utils::get_composition_degree(trace.unextended_length());
+    let composition_degree = utils::get_composition_degree(
+        trace.unextended_length(), trace.blinding_factor(), num_segments);
     debug_assert!(composition_degree == polynom::infer_degree(&composed_evaluations));
-    let (fri_trees, fri_values) = fri::reduce(&composed_evaluations, &lde_domain, options);
+    let (fri_trees, fri_values) = fri::reduce(&composed_evaluations, fft_ctx, options);
+    timings.fri_layers = now.elapsed();
     debug!("Computed {} FRI layers from composition polynomial evaluations in {} ms",
     fri_trees.len(),
         now.elapsed().as_millis());
@@ -124,13 +142,14 @@ pub fn prove(trace: &mut TraceTable, inputs: &[u128], outputs: &[u128], options:
 
     // derive a seed from the combined roots
     let mut seed = [0u8; 32];
-    options.hash_fn()(&fri_roots, &mut seed);
+    options.hash_fn().digest(&fri_roots, &mut seed);
 
     // apply proof-of-work to get a new seed
     let (seed, pow_nonce) = utils::find_pow_nonce(seed, &options);
 
     // generate pseudo-random query positions
-    let positions = utils::compute_query_positions(&seed, lde_domain.len(), options);
+    let positions = utils::compute_query_positions(&seed, fft_ctx.size(), options);
+    timings.query_positions = now.elapsed();
     debug!("Determined {} query positions from seed {} in {} ms",
         positions.len(),
         hex::encode(seed),
@@ -140,7 +159,7 @@ pub fn prove(trace: &mut TraceTable, inputs: &[u128], outputs: &[u128], options:
     let now = Instant::now();
 
     // generate FRI proof
-    let fri_proof = fri::build_proof(fri_trees, fri_values, &positions);
+    let fri_proof = fri::build_proof(fri_trees, fri_values, &positions, options);
 
     // built a list of trace evaluations at queried positions
     let trace_evaluations = trace.get_register_values_at(&positions);
@@ -148,6 +167,22 @@ pub fn prove(trace: &mut TraceTable, inputs: &[u128], outputs: &[u128], options:
     // build a list of constraint positions
     let constraint_positions = utils::map_trace_to_constraint_positions(&positions);
 
+    // in zero-knowledge mode, include salts of the queried trace states, and openings of the mask
+    let zk_values = match (zk_seed, mask) {
+        (Some(seed), Some(mask)) => {
+            let mask_proof = mask.tree.prove_batch(&positions);
+            Some(ZkValues {
+                trace_salts     : positions.iter().map(|&p| utils::get_leaf_salt(&seed, utils::TRACE_SALTS, p)).collect(),
+                mask_root       : *mask.tree.root(),
+                mask_nodes      : mask_proof.nodes,
+                mask_evaluations: positions.iter().map(|&p| mask.evaluations[p]).collect(),
+                mask_salts      : positions.iter().map(|&p| utils::get_leaf_salt(&seed, utils::MASK_SALTS, p)).collect(),
+                mask_at_z       : mask_at_z,
+            })
+        },
+        _ => None,
+    };
+
     // build the proof object
     let proof = StarkProof::new(
         trace_tree.root(),
@@ -158,22 +193,98 @@ pub fn prove(trace: &mut TraceTable, inputs: &[u128], outputs: &[u128], options:
         deep_values,
         fri_proof,
         pow_nonce,
-        trace.get_last_state().op_counter(),
-        trace.ctx_depth(),
-        trace.loop_depth(),
-        trace.stack_depth(),
+        trace_info,
+        &assertion_digest,
+        &outputs,
+        &segments,
+        zk_values,
         &options);
 
+    timings.proof_building = now.elapsed();
     debug!("Built proof object in {} ms", now.elapsed().as_millis());
     return proof;
 }
 
+/// Commits to the extended execution trace, evaluates constraints over it, and commits to the
+/// resulting constraint polynomial.
+fn commit_constraints(checkpoint: &ProverCheckpoint, fft_ctx: &FftContext, options: &ProofOptions, timings: &mut ProvingTimings) -> Commitments {
+    let trace = &checkpoint.trace;
+
+    // 2 ----- build Merkle tree from the extended execution trace ------------------------------------
+    let now = Instant::now();
+    let trace_tree = trace.build_merkle_tree(fft_ctx, options.hash_fn(), checkpoint.zk_seed.as_ref());
+    timings.trace_commitment = now.elapsed();
+    debug!("Built trace Merkle tree in {} ms using {} threads", 
+        now.elapsed().as_millis(),
+        num_threads());
+
+    // 3 ----- evaluate constraints ---------------------------------------------------------------
+    let now = Instant::now();
+    
+    // draw constraint coefficients from a seed which binds the trace to boundary assertions
+    let assertion_digest = hash_assertions(checkpoint.assertions(), options.hash_fn());
+    let seed = get_constraint_seed(trace_tree.root(), &assertion_digest, options.hash_fn());
+
+    // initialize constraint evaluation table
+    let mut constraints = ConstraintTable::new(&trace, &seed, checkpoint.segments(), checkpoint.assertions());
+
+    // evaluate the constraints in chunks aligned with regions of the execution trace, and
+    // attribute evaluation time of each chunk to its region
+    let region_size = REGION_LENGTH * EVALUATION_BLOWUP;
+    for time in constraints.evaluate(&trace, fft_ctx, region_size, CONCURRENT) {
+        timings.add_region(time);
+    }
+    timings.set_region_ops(trace);
+    timings.constraint_evaluation = now.elapsed();
+
+    debug!("Evaluated {} constraints over domain of {} elements in {} ms using {} threads",
+        constraints.constraint_count(),
+        constraints.evaluation_domain_size(),
+        now.elapsed().as_millis(),
+        num_threads());
+
+    timings.constraint_degrees = constraints.constraint_degrees();
+    debug!("Max transition constraint degrees: decoder {}, stack {}",
+        timings.constraint_degrees.decoder,
+        timings.constraint_degrees.stack);
+
+    // 4 ----- convert constraint evaluations into a polynomial -----------------------------------
+    let now = Instant::now();
+    let constraint_poly = constraints.combine_polys(fft_ctx);
+    timings.constraint_commitment = now.elapsed();
+    debug!("Converted constraint evaluations into a single polynomial of degree {} in {} ms",
+        constraint_poly.degree(),
+        now.elapsed().as_millis());
+
+    // 5 ----- build Merkle tree from constraint polynomial evaluations ---------------------------
+    let now = Instant::now();
+    
+    // evaluate constraint polynomial over the evaluation domain
+    let constraint_evaluations = constraint_poly.eval(fft_ctx);
+
+    // put evaluations into a Merkle tree; 4 evaluations per leaf
+    let constraint_evaluations = evaluations_to_leaves(constraint_evaluations);
+    let constraint_tree = MerkleTree::new(constraint_evaluations, options.hash_fn());
+    timings.constraint_commitment += now.elapsed();
+    debug!("Evaluated constraint polynomial and built constraint Merkle tree in {} ms",
+        now.elapsed().as_millis());
+
+    // in zero-knowledge mode, commit to a random polynomial which masks the composition polynomial
+    let mask = checkpoint.zk_seed.map(|seed| build_mask(&seed, trace, checkpoint.segments().len(), fft_ctx, options));
+
+    return Commitments { trace_tree, constraint_poly, constraint_tree, mask };
+}
+
 // HELPER FUNCTIONS
 // ================================================================================================
-fn twiddles_from_domain(domain: &[u128]) -> Vec<u128> {
-    let mut twiddles = domain[..(domain.len() / 2)].to_vec();
-    fft::permute(&mut twiddles);
-    return twiddles;
+fn save_checkpoint(checkpoint: &ProverCheckpoint, options: &ProofOptions) {
+    if let Some(callback) = options.checkpoint_fn() {
+        let now = Instant::now();
+        callback(checkpoint);
+        debug!("Saved checkpoint after {:?} phase in {} ms",
+            checkpoint.phase(),
+            now.elapsed().as_millis());
+    }
 }
 
 /// Re-interpret vector of 16-byte values as a vector of 32-byte arrays
@@ -186,16 +297,48 @@ fn evaluations_to_leaves(evaluations: Vec<u128>) -> Vec<[u8; 32]> {
     return unsafe { Vec::from_raw_parts(p as *mut [u8; 32], len, cap) };
 }
 
-fn build_composition_poly(trace: &TraceTable, constraint_poly: ConstraintPoly, seed: &[u8; 32]) -> (Vec<u128>, DeepValues) {
+fn build_composition_poly(trace: &TraceTable, num_segments: usize, constraint_poly: ConstraintPoly, mask: Option<&Mask>, seed: &[u8; 32]) -> (Vec<u128>, DeepValues, u128) {
     // pseudo-randomly selection deep point z and coefficients for the composition
     let z = field::prng(*seed);
     let coefficients = CompositionCoefficients::new(*seed);
 
     // divide out deep point from trace polynomials and merge them into a single polynomial
-    let (mut result, s1, s2) = trace.get_composition_poly(z, &coefficients);
+    let (mut result, s1, s2) = trace.get_composition_poly(z, &coefficients, num_segments);
 
     // divide out deep point from constraint polynomial and merge it into the result
     constraint_poly.merge_into(&mut result, z, &coefficients);
 
-    return (result, DeepValues { trace_at_z1: s1, trace_at_z2: s2 });
-}
\ No newline at end of file
+    // divide out deep point from the mask polynomial and merge it into the result
+    let mut mask_at_z = field::ZERO;
+    if let Some(mask) = mask {
+        mask_at_z = polynom::eval(&mask.poly, z);
+        let mut mask_poly = mask.poly.clone();
+        mask_poly[0] = field::sub(mask_poly[0], mask_at_z);
+        polynom::syn_div_in_place(&mut mask_poly, z);
+        parallel::mul_acc(&mut result[..mask_poly.len()], &mask_poly, coefficients.mask, 1);
+    }
+
+    return (result, DeepValues { trace_at_z1: s1, trace_at_z2: s2 }, mask_at_z);
+}
+
+/// Builds a random polynomial with degree one greater than the degree of the composition
+/// polynomial, and commits to its evaluations over the LDE domain; every evaluation is hashed
+/// together with a salt.
+fn build_mask(seed: &[u8; 32], trace: &TraceTable, num_segments: usize, fft_ctx: &FftContext, options: &ProofOptions) -> Mask {
+    let composition_degree = utils::get_composition_degree(
+        trace.unextended_length(), trace.blinding_factor(), num_segments);
+    let poly = utils::get_mask_coefficients(seed, composition_degree + 2);
+
+    let mut evaluations = vec![field::ZERO; fft_ctx.size()];
+    evaluations[..poly.len()].copy_from_slice(&poly);
+    fft::evaluate_with_ctx(&mut evaluations, fft_ctx);
+
+    let leaves = evaluations.iter().enumerate()
+        .map(|(i, value)| {
+            let salt = utils::get_leaf_salt(seed, utils::MASK_SALTS, i);
+            utils::hash_salted(&value.to_le_bytes(), &salt, options.hash_fn())
+        })
+        .collect();
+    let tree = MerkleTree::new(leaves, options.hash_fn());
+    return Mask { poly, evaluations, tree };
+}
@@ -0,0 +1,71 @@
+use serde::{ Serialize, Deserialize };
+use super::StarkProof;
+
+// TYPES AND INTERFACES
+// ================================================================================================
+
+/// Proofs of a sequence of calls to procedures of a single library program. Each call is proven
+/// separately; paths of the called procedures are stored only once for all calls to the same
+/// procedure.
+#[derive(Clone, Serialize, Deserialize)]
+pub struct SessionProof {
+    proofs  : Vec<StarkProof>,
+    paths   : Vec<Vec<bool>>,
+    calls   : Vec<usize>,       // index of the procedure path for each call
+}
+
+// SESSION PROOF IMPLEMENTATION
+// ================================================================================================
+impl SessionProof {
+
+    /// Returns an empty session proof.
+    pub fn new() -> SessionProof {
+        return SessionProof { proofs: Vec::new(), paths: Vec::new(), calls: Vec::new() };
+    }
+
+    /// Appends a `proof` of a call to the procedure with the specified `path`.
+    pub fn push(&mut self, path: &[bool], proof: StarkProof) {
+        let index = match self.paths.iter().position(|p| p == path) {
+            Some(index) => index,
+            None => {
+                self.paths.push(path.to_vec());
+                self.paths.len() - 1
+            }
+        };
+        self.calls.push(index);
+        self.proofs.push(proof);
+    }
+
+    /// Returns the number of calls proven in this session.
+    pub fn num_calls(&self) -> usize {
+        return self.calls.len();
+    }
+
+    /// Returns the proof of the call at the specified index.
+    pub fn proof(&self, call: usize) -> &StarkProof {
+        return &self.proofs[call];
+    }
+
+    /// Returns the path of the procedure invoked by the call at the specified index.
+    pub fn procedure_path(&self, call: usize) -> &[bool] {
+        return &self.paths[self.calls[call]];
+    }
+
+    /// Returns all distinct procedure paths invoked in this session.
+    pub fn paths(&self) -> &[Vec<bool>] {
+        return &self.paths;
+    }
+
+    /// Makes sure every call refers to a valid procedure path.
+    pub fn validate(&self) -> Result<(), String> {
+        if self.calls.len() != self.proofs.len() {
+            return Err(format!("session proof contains {} calls, but {} proofs",
+                self.calls.len(), self.proofs.len()));
+        }
+        if let Some(&index) = self.calls.iter().find(|&&index| index >= self.paths.len()) {
+            return Err(format!("session proof refers to procedure path {}, but contains only {} paths",
+                index, self.paths.len()));
+        }
+        return Ok(());
+    }
+}
@@ -0,0 +1,82 @@
+use super::{ Assertion, AssertionStep };
+
+// TYPES AND INTERFACES
+// ================================================================================================
+
+/// Public statement about a program execution: executing a program with `program_hash` with
+/// `inputs` and some secret inputs results in `outputs`, and all `assertions` hold. The proof of
+/// the statement must have at least `stack_depth` user stack registers, and its execution trace
+/// must be padded to at least `min_trace_length` steps.
+///
+/// If the program is a library, `proc_path` is the path to the called procedure (see
+/// `set_proc_path()`); otherwise, it is empty.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Statement {
+    pub program_hash    : [u8; 32],
+    pub inputs          : Vec<u128>,
+    pub outputs         : Vec<u128>,
+    pub assertions      : Vec<Assertion>,
+    pub stack_depth     : usize,
+    pub min_trace_length: usize,
+    pub proc_path       : Vec<bool>,
+}
+
+// STATEMENT IMPLEMENTATION
+// ================================================================================================
+impl Statement {
+
+    /// Returns a new statement with an empty list of assertions; the minimum stack depth is set
+    /// to the number of inputs, and no padding beyond the minimum trace length is required.
+    pub fn new(program_hash: &[u8; 32], inputs: &[u128], outputs: &[u128]) -> Statement {
+        return Statement {
+            program_hash    : *program_hash,
+            inputs          : inputs.to_vec(),
+            outputs         : outputs.to_vec(),
+            assertions      : Vec::new(),
+            stack_depth     : inputs.len(),
+            min_trace_length: crate::MIN_TRACE_LENGTH,
+            proc_path       : Vec::new(),
+        };
+    }
+
+    /// Binds the statement to a call of the procedure with the specified `path` in a library
+    /// program. The procedure selector is placed at the top of the stack above the inputs, and
+    /// so the minimum stack depth grows by the length of the path.
+    pub fn set_proc_path(&mut self, path: &[bool]) {
+        self.stack_depth = self.stack_depth.saturating_sub(self.proc_path.len()) + path.len();
+        self.proc_path = path.to_vec();
+    }
+
+    /// Returns public inputs of the statement as they appear on the initial stack: the selector
+    /// of the called procedure (if any) followed by the inputs.
+    pub fn stack_inputs(&self) -> Vec<u128> {
+        return self.proc_path.iter().map(|&b| b as u128).chain(self.inputs.iter().copied()).collect();
+    }
+}
+
+// PUBLIC FUNCTIONS
+// ================================================================================================
+
+/// Appends assertions to the `next` statement requiring that the first state of its user stack
+/// starts with the outputs of the `prev` statement. The assertions are bound to the proof of the
+/// next statement via the proof transcript.
+pub fn link_assertions(prev: &Statement, next: &mut Statement) {
+    for (i, &value) in prev.outputs.iter().enumerate() {
+        next.assertions.push(Assertion::first(i, value));
+    }
+}
+
+/// Makes sure the `next` statement contains all assertions needed to link it to the `prev`
+/// statement.
+pub fn check_link(prev: &Statement, next: &Statement) -> Result<(), String> {
+    for (i, &value) in prev.outputs.iter().enumerate() {
+        let linked = next.assertions.iter()
+            .any(|a| a.register == i && a.step == AssertionStep::First && a.value == value);
+        if !linked {
+            return Err(format!(
+                "verification of segment link failed: register {} at the first step is not bound to output {} of the previous segment",
+                i, value));
+        }
+    }
+    return Ok(());
+}
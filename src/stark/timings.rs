@@ -0,0 +1,173 @@
+use std::{ fmt, ops::Range, time::Duration };
+use crate::{ processor::OpCode, BASE_CYCLE_LENGTH };
+use super::{ TraceTable, TraceState, ConstraintDegrees };
+
+// CONSTANTS
+// ================================================================================================
+
+/// Number of trace steps in a region to which constraint evaluation time is attributed.
+pub const REGION_LENGTH: usize = BASE_CYCLE_LENGTH;
+
+// TYPES AND INTERFACES
+// ================================================================================================
+
+/// Time spent by the prover in each phase of proof generation; constraint evaluation time is
+/// also broken down by regions of the execution trace. Max degrees of transition constraints
+/// observed during constraint evaluation are reported together with the timings.
+#[derive(Clone, Debug, Default)]
+pub struct ProvingTimings {
+    pub trace_extension         : Duration,
+    pub trace_commitment        : Duration,
+    pub constraint_evaluation   : Duration,
+    pub constraint_commitment   : Duration,
+    pub composition             : Duration,
+    pub fri_layers              : Duration,
+    pub query_positions         : Duration,
+    pub proof_building          : Duration,
+    pub constraint_degrees      : ConstraintDegrees,
+    regions                     : Vec<Duration>,
+    region_ops                  : Vec<OpCode>,
+}
+
+/// A range of trace steps executing mostly the same operation, and the time spent evaluating
+/// constraints over this range.
+#[derive(Clone, Debug, PartialEq)]
+pub struct TraceRegion {
+    pub steps       : Range<usize>,
+    pub time        : Duration,
+    pub dominant_op : OpCode,
+}
+
+// PROVING TIMINGS IMPLEMENTATION
+// ================================================================================================
+impl ProvingTimings {
+
+    /// Returns total time spent generating the proof.
+    pub fn total(&self) -> Duration {
+        return self.trace_extension + self.trace_commitment + self.constraint_evaluation
+            + self.constraint_commitment + self.composition + self.fri_layers
+            + self.query_positions + self.proof_building;
+    }
+
+    /// Returns regions of the execution trace ordered by step together with the time spent
+    /// evaluating constraints over them. Adjacent regions with the same dominant operation are
+    /// merged into a single region. Returns an empty list if constraints were evaluated by a
+    /// different process (i.e. proving was resumed after constraint commitment).
+    ///
+    /// All transition constraints are evaluated at every step of the trace regardless of the
+    /// operation executed at the step, and so the cost of evaluating constraints over a region
+    /// does not depend on the operations in it; differences in region times come from scheduling
+    /// and measurement noise, and cannot be used to find operations which make proving expensive.
+    pub fn regions(&self) -> Vec<TraceRegion> {
+        let mut result: Vec<TraceRegion> = Vec::new();
+        for (i, (&time, &op)) in self.regions.iter().zip(self.region_ops.iter()).enumerate() {
+            let steps = (i * REGION_LENGTH)..((i + 1) * REGION_LENGTH);
+            match result.last_mut() {
+                Some(region) if region.dominant_op == op => {
+                    region.steps.end = steps.end;
+                    region.time += time;
+                },
+                _ => result.push(TraceRegion { steps, time, dominant_op: op }),
+            }
+        }
+        return result;
+    }
+
+    // CRATE METHODS
+    // --------------------------------------------------------------------------------------------
+
+    /// Records time spent evaluating constraints over the next region of the execution trace.
+    pub(crate) fn add_region(&mut self, time: Duration) {
+        self.regions.push(time);
+    }
+
+    /// Determines the most frequently executed operation in each region of the `trace`.
+    pub(crate) fn set_region_ops(&mut self, trace: &TraceTable) {
+        let mut state = TraceState::new(trace.ctx_depth(), trace.loop_depth(), trace.stack_depth());
+        let num_regions = trace.unextended_length() / REGION_LENGTH;
+
+        self.region_ops = Vec::with_capacity(num_regions);
+        for region in 0..num_regions {
+            let mut counts = [0usize; 128];
+            for step in (region * REGION_LENGTH)..((region + 1) * REGION_LENGTH) {
+                // in an extended trace, the original trace steps are spaced by extension factor
+                trace.fill_state(&mut state, step * trace.extension_factor());
+                counts[state.op_code() as usize & 0x7F] += 1;
+            }
+
+            // ties are resolved in favor of the smaller op code
+            let mut dominant = 0;
+            for code in 1..counts.len() {
+                if counts[code] > counts[dominant] { dominant = code; }
+            }
+            self.region_ops.push(OpCode::from_code(dominant as u8).unwrap_or(OpCode::Noop));
+        }
+    }
+}
+
+impl fmt::Display for ProvingTimings {
+
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "Proof generation time: {} ms", self.total().as_millis())?;
+        writeln!(f, "    trace extension:       {} ms", self.trace_extension.as_millis())?;
+        writeln!(f, "    trace commitment:      {} ms", self.trace_commitment.as_millis())?;
+        writeln!(f, "    constraint evaluation: {} ms", self.constraint_evaluation.as_millis())?;
+        writeln!(f, "    constraint commitment: {} ms", self.constraint_commitment.as_millis())?;
+        writeln!(f, "    composition:           {} ms", self.composition.as_millis())?;
+        writeln!(f, "    FRI layers:            {} ms", self.fri_layers.as_millis())?;
+        writeln!(f, "    query positions:       {} ms", self.query_positions.as_millis())?;
+        write!(f,   "    proof building:        {} ms", self.proof_building.as_millis())?;
+
+        let regions = self.regions();
+        if !regions.is_empty() {
+            write!(f, "\nConstraint evaluation by trace region:")?;
+            for region in regions.iter() {
+                write!(f, "\n    steps {:>6}..{:<6} {:>6} us  {}",
+                    region.steps.start, region.steps.end,
+                    region.time.as_micros(), region.dominant_op)?;
+            }
+        }
+
+        return Ok(());
+    }
+}
+
+// TESTS
+// ================================================================================================
+#[cfg(test)]
+mod tests {
+
+    use crate::{ Program, ProgramInputs, ProofOptions, OpCode, blocks::{ ProgramBlock, Span, Group } };
+    use super::REGION_LENGTH;
+
+    #[test]
+    fn hash_region_attribution() {
+        // first half of the program hashes the top of the stack, second half does nothing
+        let mut instructions = vec![OpCode::Begin];
+        instructions.resize(64, OpCode::RescR);
+        instructions.resize(127, OpCode::Noop);
+        let root = vec![ProgramBlock::Span(Span::from_instructions(instructions))];
+        let program = Program::new(Group::new(root));
+
+        let inputs = ProgramInputs::from_public(&[1, 2, 3, 4, 5, 6]);
+        let (_, _, timings) = crate::execute_with_timings(&program, &inputs, 1, &ProofOptions::default()).unwrap();
+
+        // the hash region is reported as a single region dominated by RESCR operations, and the
+        // rest of the trace is covered by regions dominated by NOOPs; evaluation time is not
+        // compared between regions because it does not depend on the executed operations
+        let regions = timings.regions();
+        assert_eq!(OpCode::RescR, regions[0].dominant_op);
+        assert_eq!(0..(4 * REGION_LENGTH), regions[0].steps);
+        assert!(regions[1..].iter().all(|r| r.dominant_op == OpCode::Noop));
+        for pair in regions.windows(2) {
+            assert_eq!(pair[0].steps.end, pair[1].steps.start, "regions are not contiguous");
+        }
+
+        let region_time = regions.iter().map(|r| r.time).sum();
+        assert!(timings.constraint_evaluation >= region_time);
+        assert!(timings.total() >= timings.constraint_evaluation);
+
+        let printed = format!("{}", timings);
+        assert!(printed.contains("steps      0..64     "), "{}", printed);
+    }
+}
\ No newline at end of file
@@ -1,5 +1,12 @@
 mod trace_state;
 mod trace_table;
+mod trace_layout;
+
+#[cfg(test)]
+mod trace_diff;
 
 pub use trace_state::TraceState;
-pub use trace_table::TraceTable;
\ No newline at end of file
+pub use trace_table::TraceTable;
+pub use trace_layout::{ TraceLayout, RegisterGroup };
+#[cfg(test)]
+pub use trace_diff::{ TraceDiff, TraceShape };
\ No newline at end of file
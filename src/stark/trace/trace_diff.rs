@@ -0,0 +1,224 @@
+use std::fmt;
+use crate::utils::fmt::{ fmt_element, display_radix };
+use super::TraceLayout;
+
+// CONSTANTS
+// ================================================================================================
+const MAX_REPORTED_CELLS: usize = 16;
+const CONTEXT_ROWS: usize = 3;
+
+// TYPES AND INTERFACES
+// ================================================================================================
+
+/// Dimensions of a trace table: context, loop, and user stack depths, and trace length.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct TraceShape {
+    pub ctx_depth   : usize,
+    pub loop_depth  : usize,
+    pub stack_depth : usize,
+    pub length      : usize,
+}
+
+/// Result of comparing two trace tables register by register.
+pub struct TraceDiff {
+    shapes          : [TraceShape; 2],
+    register_names  : Vec<String>,
+    first_cells     : Vec<(usize, usize)>,
+    mismatch_counts : Vec<usize>,
+    context         : Vec<(usize, Vec<u128>, Vec<u128>)>,
+}
+
+// TRACE DIFF IMPLEMENTATION
+// ================================================================================================
+impl TraceDiff {
+
+    pub(super) fn new(a: &[Vec<u128>], a_shape: TraceShape, b: &[Vec<u128>], b_shape: TraceShape) -> TraceDiff {
+        let register_names = TraceLayout::new(a_shape.ctx_depth, a_shape.loop_depth, a_shape.stack_depth).register_names();
+        let mut diff = TraceDiff {
+            shapes          : [a_shape, b_shape],
+            register_names  : register_names,
+            first_cells     : Vec::new(),
+            mismatch_counts : Vec::new(),
+            context         : Vec::new(),
+        };
+
+        // traces of different shapes cannot be compared cell by cell
+        if a_shape != b_shape || a.len() != b.len() {
+            return diff;
+        }
+
+        // count mismatches in each register; cells are visited step by step so that
+        // the first reported cells are the earliest differences in the trace
+        let length = a_shape.length;
+        diff.mismatch_counts = vec![0; a.len()];
+        for step in 0..length {
+            for register in 0..a.len() {
+                if a[register][step] != b[register][step] {
+                    diff.mismatch_counts[register] += 1;
+                    if diff.first_cells.len() < MAX_REPORTED_CELLS {
+                        diff.first_cells.push((register, step));
+                    }
+                }
+            }
+        }
+
+        // save rows around the first difference so that they can be printed later
+        if let Some(&(_, step)) = diff.first_cells.first() {
+            let start = step.saturating_sub(CONTEXT_ROWS);
+            let end = usize::min(step + CONTEXT_ROWS + 1, length);
+            for i in start..end {
+                let row_a = a.iter().map(|r| r[i]).collect();
+                let row_b = b.iter().map(|r| r[i]).collect();
+                diff.context.push((i, row_a, row_b));
+            }
+        }
+
+        return diff;
+    }
+
+    // PUBLIC ACCESSORS
+    // --------------------------------------------------------------------------------------------
+
+    /// Returns true if the traces have the same shape and all their cells are equal.
+    pub fn is_empty(&self) -> bool {
+        return !self.is_shape_mismatch() && self.first_cells.is_empty();
+    }
+
+    /// Returns true if the traces could not be compared because their shapes differ.
+    pub fn is_shape_mismatch(&self) -> bool {
+        return self.shapes[0] != self.shapes[1];
+    }
+
+    /// Returns shapes of the compared traces.
+    pub fn shapes(&self) -> &[TraceShape; 2] {
+        return &self.shapes;
+    }
+
+    /// Returns (register, step) pairs of the first differing cells ordered by step; at most
+    /// 16 cells are reported.
+    pub fn first_cells(&self) -> &[(usize, usize)] {
+        return &self.first_cells;
+    }
+
+    /// Returns the number of differing cells in each register.
+    pub fn mismatch_counts(&self) -> &[usize] {
+        return &self.mismatch_counts;
+    }
+
+    /// Returns the name of the register at the specified `index`.
+    pub fn register_name(&self, index: usize) -> &str {
+        return &self.register_names[index];
+    }
+
+    /// Returns a single line description of the diff suitable for assertion messages.
+    pub fn summary(&self) -> String {
+        if self.is_shape_mismatch() {
+            return format!("trace shapes differ: {:?} vs. {:?}", self.shapes[0], self.shapes[1]);
+        }
+
+        match self.first_cells.first() {
+            None => return String::from("traces are identical"),
+            Some(&(register, step)) => {
+                let num_cells: usize = self.mismatch_counts.iter().sum();
+                let num_registers = self.mismatch_counts.iter().filter(|&&c| c > 0).count();
+                return format!("traces differ in {} cells across {} registers; first difference is in {} at step {}",
+                    num_cells, num_registers, self.register_names[register], step);
+            }
+        }
+    }
+}
+
+impl fmt::Display for TraceDiff {
+    /// Prints the summary followed by rows around the first difference; only registers which
+    /// differ somewhere in the trace are printed, and differing cells are marked with `*`.
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "{}", self.summary())?;
+
+        let registers: Vec<usize> = (0..self.mismatch_counts.len())
+            .filter(|&i| self.mismatch_counts[i] > 0)
+            .collect();
+
+        for &register in registers.iter() {
+            writeln!(f, "  {:<14} {} mismatches", self.register_names[register], self.mismatch_counts[register])?;
+        }
+
+        let radix = display_radix();
+        for (step, row_a, row_b) in self.context.iter() {
+            write!(f, "  [{:>5}]", step)?;
+            for &register in registers.iter() {
+                let marker = if row_a[register] != row_b[register] { '*' } else { ' ' };
+                write!(f, " {}={}/{}{}", self.register_names[register],
+                    fmt_element(row_a[register], radix), fmt_element(row_b[register], radix), marker)?;
+            }
+            writeln!(f)?;
+        }
+
+        return Ok(());
+    }
+}
+
+// TESTS
+// ================================================================================================
+#[cfg(test)]
+mod tests {
+
+    use crate::{ ProgramInputs, assembly, processor, stark::TraceTable, NUM_DEPTH_BITS };
+
+    #[test]
+    fn identical_traces() {
+        let a = build_trace_table("begin push.1 push.2 add end", |_| {});
+        let b = build_trace_table("begin push.1 push.2 add end", |_| {});
+
+        let diff = a.diff(&b);
+        assert!(diff.is_empty(), "{}", diff.summary());
+        assert!(diff.first_cells().is_empty());
+        assert!(diff.mismatch_counts().iter().all(|&c| c == 0));
+        assert_eq!("traces are identical", diff.summary());
+    }
+
+    #[test]
+    fn single_cell_difference() {
+        let a = build_trace_table("begin push.1 push.2 add end", |_| {});
+        let b = build_trace_table("begin push.1 push.2 add end", |registers| {
+            let register = registers.len() - NUM_DEPTH_BITS - 1;
+            registers[register][9] = 42;
+        });
+
+        let diff = a.diff(&b);
+        assert!(!diff.is_empty());
+        assert!(!diff.is_shape_mismatch());
+
+        let register = a.register_count() - NUM_DEPTH_BITS - 1;
+        assert_eq!(&[(register, 9)], diff.first_cells());
+        assert_eq!(1, diff.mismatch_counts()[register]);
+        assert_eq!(1, diff.mismatch_counts().iter().sum::<usize>());
+        assert_eq!("stack[1]", diff.register_name(register));
+        assert!(diff.summary().ends_with("first difference is in stack[1] at step 9"));
+
+        let printed = format!("{}", diff);
+        assert!(printed.contains("[    9] stack[1]=0/42*"), "{}", printed);
+        assert!(printed.contains("[    6]") && printed.contains("[   12]"), "{}", printed);
+    }
+
+    #[test]
+    fn shape_mismatch() {
+        let a = build_trace_table("begin push.1 push.2 add end", |_| {});
+        let b = build_trace_table("begin push.1 push.2 push.3 add add end", |_| {});
+
+        let diff = a.diff(&b);
+        assert!(!diff.is_empty());
+        assert!(diff.is_shape_mismatch());
+        assert!(diff.first_cells().is_empty());
+        assert_eq!(2, diff.shapes()[0].stack_depth);
+        assert_eq!(3, diff.shapes()[1].stack_depth);
+        assert!(diff.summary().starts_with("trace shapes differ"));
+    }
+
+    fn build_trace_table(source: &str, alter: fn(&mut Vec<Vec<u128>>)) -> TraceTable {
+        let program = assembly::compile(source).unwrap();
+        let inputs = ProgramInputs::none();
+        let (mut registers, ctx_depth, loop_depth) = processor::execute(&program, &inputs).unwrap();
+        alter(&mut registers);
+        return TraceTable::new(registers, ctx_depth, loop_depth, 32);
+    }
+}
@@ -0,0 +1,206 @@
+use std::{ fmt, ops::Range };
+use serde::{ Serialize, Deserialize };
+use crate::{
+    OP_COUNTER_IDX, SPONGE_RANGE,
+    CF_OP_BITS_RANGE, LD_OP_BITS_RANGE, HD_OP_BITS_RANGE, LD_OP_PRODS_RANGE, NUM_DEPTH_BITS,
+};
+
+// TYPES AND INTERFACES
+// ================================================================================================
+
+/// Groups of registers in an execution trace; groups are listed in the order in which they
+/// appear in the trace.
+#[derive(Copy, Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub enum RegisterGroup {
+    OpCounter,
+    Sponge,
+    CfOpBits,
+    LdOpBits,
+    HdOpBits,
+    LdOpProds,
+    Ctx,
+    Loop,
+    Stack,
+    StackDepth,
+}
+
+/// Describes names and positions of all registers in an execution trace with a given context,
+/// loop, and user stack depth.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct TraceLayout {
+    ctx_depth   : usize,
+    loop_depth  : usize,
+    stack_depth : usize,
+    groups      : Vec<(RegisterGroup, Range<usize>)>,
+}
+
+// REGISTER GROUP IMPLEMENTATION
+// ================================================================================================
+impl RegisterGroup {
+
+    /// Returns the name of the group; registers in the group are named `name[i]`, except for
+    /// groups which always consist of a single register.
+    pub fn name(&self) -> &'static str {
+        return match self {
+            RegisterGroup::OpCounter    => "op_counter",
+            RegisterGroup::Sponge       => "sponge",
+            RegisterGroup::CfOpBits     => "cf_bits",
+            RegisterGroup::LdOpBits     => "ld_bits",
+            RegisterGroup::HdOpBits     => "hd_bits",
+            RegisterGroup::LdOpProds    => "ld_prods",
+            RegisterGroup::Ctx          => "ctx",
+            RegisterGroup::Loop         => "loop",
+            RegisterGroup::Stack        => "stack",
+            RegisterGroup::StackDepth   => "depth",
+        };
+    }
+
+    fn is_single(&self) -> bool {
+        return *self == RegisterGroup::OpCounter;
+    }
+}
+
+// TRACE LAYOUT IMPLEMENTATION
+// ================================================================================================
+impl TraceLayout {
+
+    pub fn new(ctx_depth: usize, loop_depth: usize, stack_depth: usize) -> TraceLayout {
+        let ctx_end = LD_OP_PRODS_RANGE.end + ctx_depth;
+        let loop_end = ctx_end + loop_depth;
+        let stack_end = loop_end + stack_depth;
+
+        let groups = vec![
+            (RegisterGroup::OpCounter,  OP_COUNTER_IDX..(OP_COUNTER_IDX + 1)),
+            (RegisterGroup::Sponge,     SPONGE_RANGE),
+            (RegisterGroup::CfOpBits,   CF_OP_BITS_RANGE),
+            (RegisterGroup::LdOpBits,   LD_OP_BITS_RANGE),
+            (RegisterGroup::HdOpBits,   HD_OP_BITS_RANGE),
+            (RegisterGroup::LdOpProds,  LD_OP_PRODS_RANGE),
+            (RegisterGroup::Ctx,        LD_OP_PRODS_RANGE.end..ctx_end),
+            (RegisterGroup::Loop,       ctx_end..loop_end),
+            (RegisterGroup::Stack,      loop_end..stack_end),
+            (RegisterGroup::StackDepth, stack_end..(stack_end + NUM_DEPTH_BITS)),
+        ];
+
+        return TraceLayout { ctx_depth, loop_depth, stack_depth, groups };
+    }
+
+    pub fn ctx_depth(&self) -> usize {
+        return self.ctx_depth;
+    }
+
+    pub fn loop_depth(&self) -> usize {
+        return self.loop_depth;
+    }
+
+    pub fn stack_depth(&self) -> usize {
+        return self.stack_depth;
+    }
+
+    /// Returns the total number of registers in the trace.
+    pub fn width(&self) -> usize {
+        return self.groups.last().unwrap().1.end;
+    }
+
+    /// Returns all register groups together with their index ranges.
+    pub fn groups(&self) -> &[(RegisterGroup, Range<usize>)] {
+        return &self.groups;
+    }
+
+    /// Returns the range of register indexes occupied by the `group`.
+    pub fn range(&self, group: RegisterGroup) -> Range<usize> {
+        let (_, range) = self.groups.iter().find(|(g, _)| *g == group).unwrap();
+        return range.clone();
+    }
+
+    /// Returns the group of the register at the specified `index`.
+    pub fn group_of(&self, index: usize) -> RegisterGroup {
+        assert!(index < self.width(), "register index {} is out of bounds", index);
+        let (group, _) = self.groups.iter().find(|(_, range)| range.contains(&index)).unwrap();
+        return *group;
+    }
+
+    /// Returns the name of the register at the specified `index` (e.g. `stack[3]`).
+    pub fn register_name(&self, index: usize) -> String {
+        let group = self.group_of(index);
+        if group.is_single() {
+            return String::from(group.name());
+        }
+        return format!("{}[{}]", group.name(), index - self.range(group).start);
+    }
+
+    /// Returns names of all registers in the trace ordered by register index.
+    pub fn register_names(&self) -> Vec<String> {
+        return (0..self.width()).map(|i| self.register_name(i)).collect();
+    }
+}
+
+impl fmt::Display for TraceLayout {
+    /// Prints index ranges of all non-empty register groups (e.g. `sponge: 1..5`).
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let groups: Vec<String> = self.groups.iter()
+            .filter(|(_, range)| range.len() > 0)
+            .map(|(group, range)| format!("{}: {}..{}", group.name(), range.start, range.end))
+            .collect();
+        return write!(f, "{}", groups.join(", "));
+    }
+}
+
+// TESTS
+// ================================================================================================
+#[cfg(test)]
+mod tests {
+
+    use crate::{ ProgramInputs, ProofOptions, assembly, processor, stark::TraceTable };
+    use crate::{ SPONGE_RANGE, CF_OP_BITS_RANGE, LD_OP_BITS_RANGE, HD_OP_BITS_RANGE, LD_OP_PRODS_RANGE };
+    use super::{ TraceLayout, RegisterGroup };
+
+    #[test]
+    fn layout_matches_trace() {
+        let program = assembly::compile("begin push.1 push.2 add push.1 if.true push.4 else push.5 end end").unwrap();
+        let (registers, ctx_depth, loop_depth) = processor::execute(&program, &ProgramInputs::none()).unwrap();
+        let trace = TraceTable::new(registers, ctx_depth, loop_depth, ProofOptions::default().extension_factor());
+
+        let layout = trace.layout();
+        assert_eq!(trace.register_count(), layout.width());
+        assert_eq!(trace.stack_depth(), layout.range(RegisterGroup::Stack).len());
+        assert_eq!(trace.ctx_depth(), layout.range(RegisterGroup::Ctx).len());
+        assert_eq!(trace.loop_depth(), layout.range(RegisterGroup::Loop).len());
+
+        // groups are contiguous and cover all registers
+        let mut end = 0;
+        for (_, range) in layout.groups().iter() {
+            assert_eq!(end, range.start);
+            end = range.end;
+        }
+        assert_eq!(layout.width(), end);
+    }
+
+    #[test]
+    fn default_ranges() {
+        let layout = TraceLayout::new(1, 1, 8);
+        assert_eq!(0..1, layout.range(RegisterGroup::OpCounter));
+        assert_eq!(SPONGE_RANGE, layout.range(RegisterGroup::Sponge));
+        assert_eq!(CF_OP_BITS_RANGE, layout.range(RegisterGroup::CfOpBits));
+        assert_eq!(LD_OP_BITS_RANGE, layout.range(RegisterGroup::LdOpBits));
+        assert_eq!(HD_OP_BITS_RANGE, layout.range(RegisterGroup::HdOpBits));
+        assert_eq!(LD_OP_PRODS_RANGE, layout.range(RegisterGroup::LdOpProds));
+        assert_eq!(23..24, layout.range(RegisterGroup::Ctx));
+        assert_eq!(24..25, layout.range(RegisterGroup::Loop));
+        assert_eq!(25..33, layout.range(RegisterGroup::Stack));
+        assert_eq!(33..39, layout.range(RegisterGroup::StackDepth));
+        assert_eq!(39, layout.width());
+
+        assert_eq!("op_counter", layout.register_name(0));
+        assert_eq!("ld_bits[2]", layout.register_name(10));
+        assert_eq!("stack[0]", layout.register_name(25));
+        assert_eq!("depth[0]", layout.register_name(33));
+        assert_eq!(RegisterGroup::HdOpBits, layout.group_of(14));
+
+        assert!(layout.to_string().starts_with("op_counter: 0..1, sponge: 1..5, cf_bits: 5..8"));
+        assert!(layout.to_string().ends_with("stack: 25..33, depth: 33..39"));
+
+        let bytes = bincode::serialize(&layout).unwrap();
+        assert_eq!(layout, bincode::deserialize::<TraceLayout>(&bytes).unwrap());
+    }
+}
@@ -1,19 +1,22 @@
 use std::{ fmt, cmp };
 use crate::{
     math::field,
+    utils::fmt::{ fmt_elements, display_radix },
     OpCode,
+    processor::opcodes::USER_OPS,
     PROGRAM_DIGEST_SIZE,
     MIN_STACK_DEPTH, MIN_CONTEXT_DEPTH, MIN_LOOP_DEPTH,
     OP_COUNTER_IDX, SPONGE_WIDTH, SPONGE_RANGE,
     NUM_CF_OPS, NUM_LD_OPS, NUM_HD_OPS,
-    NUM_CF_OP_BITS, NUM_LD_OP_BITS, NUM_HD_OP_BITS,
-    CF_OP_BITS_RANGE, LD_OP_BITS_RANGE, HD_OP_BITS_RANGE,
+    NUM_CF_OP_BITS, NUM_LD_OP_BITS, NUM_HD_OP_BITS, NUM_LD_OP_PRODS,
+    CF_OP_BITS_RANGE, LD_OP_BITS_RANGE, HD_OP_BITS_RANGE, LD_OP_PRODS_RANGE,
+    NUM_DEPTH_BITS,
 };
 
 // CONSTANTS
 // ================================================================================================
 const NUM_OP_BITS: usize = NUM_CF_OP_BITS + NUM_LD_OP_BITS + NUM_HD_OP_BITS;
-const NUM_STATIC_DECODER_REGISTERS: usize = 1 + SPONGE_WIDTH + NUM_OP_BITS; // 1 is for op_counter
+const NUM_STATIC_DECODER_REGISTERS: usize = 1 + SPONGE_WIDTH + NUM_OP_BITS + NUM_LD_OP_PRODS; // 1 is for op_counter
 
 // TYPES AND INTERFACES
 // ================================================================================================
@@ -24,9 +27,11 @@ pub struct TraceState {
     cf_op_bits  : [u128; NUM_CF_OP_BITS],
     ld_op_bits  : [u128; NUM_LD_OP_BITS],
     hd_op_bits  : [u128; NUM_HD_OP_BITS],
+    ld_op_prods : [u128; NUM_LD_OP_PRODS],
     ctx_stack   : Vec<u128>,
     loop_stack  : Vec<u128>,
     user_stack  : Vec<u128>,
+    depth_bits  : [u128; NUM_DEPTH_BITS],
 
     ctx_depth   : usize,
     loop_depth  : usize,
@@ -55,9 +60,11 @@ impl TraceState {
             cf_op_bits  : [0; NUM_CF_OP_BITS],
             ld_op_bits  : [0; NUM_LD_OP_BITS],
             hd_op_bits  : [0; NUM_HD_OP_BITS],
+            ld_op_prods : [0; NUM_LD_OP_PRODS],
             ctx_stack   : vec![0; cmp::max(ctx_depth, MIN_CONTEXT_DEPTH)],
             loop_stack  : vec![0; cmp::max(loop_depth, MIN_LOOP_DEPTH)],
             user_stack  : vec![0; cmp::max(stack_depth, MIN_STACK_DEPTH)],
+            depth_bits  : [0; NUM_DEPTH_BITS],
             ctx_depth   : ctx_depth,
             loop_depth  : loop_depth,
             stack_depth : stack_depth,
@@ -86,21 +93,28 @@ impl TraceState {
         let mut hd_op_bits = [0; NUM_HD_OP_BITS];
         hd_op_bits.copy_from_slice(&state[HD_OP_BITS_RANGE]);
 
+        let mut ld_op_prods = [0; NUM_LD_OP_PRODS];
+        ld_op_prods.copy_from_slice(&state[LD_OP_PRODS_RANGE]);
+
         let mut ctx_stack = vec![0; cmp::max(ctx_depth, MIN_CONTEXT_DEPTH)];
-        let ctx_stack_end = HD_OP_BITS_RANGE.end + ctx_depth;
-        ctx_stack[..ctx_depth].copy_from_slice(&state[HD_OP_BITS_RANGE.end..ctx_stack_end]);
+        let ctx_stack_end = LD_OP_PRODS_RANGE.end + ctx_depth;
+        ctx_stack[..ctx_depth].copy_from_slice(&state[LD_OP_PRODS_RANGE.end..ctx_stack_end]);
 
         let mut loop_stack = vec![0; cmp::max(loop_depth, MIN_LOOP_DEPTH)];
         let loop_stack_end = ctx_stack_end + loop_depth;
         loop_stack[..loop_depth].copy_from_slice(&state[ctx_stack_end..loop_stack_end]);
 
         let mut user_stack = vec![0; cmp::max(stack_depth, MIN_STACK_DEPTH)];
-        user_stack[..stack_depth].copy_from_slice(&state[loop_stack_end..]);
+        let user_stack_end = loop_stack_end + stack_depth;
+        user_stack[..stack_depth].copy_from_slice(&state[loop_stack_end..user_stack_end]);
+
+        let mut depth_bits = [0; NUM_DEPTH_BITS];
+        depth_bits.copy_from_slice(&state[user_stack_end..(user_stack_end + NUM_DEPTH_BITS)]);
 
         return TraceState {
             op_counter, sponge,
-            cf_op_bits, ld_op_bits, hd_op_bits,
-            ctx_stack, loop_stack, user_stack,
+            cf_op_bits, ld_op_bits, hd_op_bits, ld_op_prods,
+            ctx_stack, loop_stack, user_stack, depth_bits,
             ctx_depth, loop_depth, stack_depth,
             cf_op_flags : [0; NUM_CF_OPS],
             ld_op_flags : [0; NUM_LD_OPS],
@@ -117,10 +131,16 @@ impl TraceState {
         return NUM_STATIC_DECODER_REGISTERS + ctx_depth + loop_depth;
     }
 
+    /// Returns the number of user stack registers in an execution trace with the specified
+    /// number of registers; the trace also contains registers for bits of the stack depth.
+    pub fn compute_stack_depth(num_registers: usize, ctx_depth: usize, loop_depth: usize) -> usize {
+        return num_registers - Self::compute_decoder_width(ctx_depth, loop_depth) - NUM_DEPTH_BITS;
+    }
+
     // PUBLIC ACCESSORS
     // --------------------------------------------------------------------------------------------
     pub fn width(&self) -> usize {
-        return HD_OP_BITS_RANGE.end + self.ctx_depth + self.loop_depth + self.stack_depth;
+        return LD_OP_PRODS_RANGE.end + self.ctx_depth + self.loop_depth + self.stack_depth + NUM_DEPTH_BITS;
     }
 
     pub fn stack_depth(&self) -> usize {
@@ -162,6 +182,10 @@ impl TraceState {
         return &self.hd_op_bits;
     }
 
+    pub fn ld_op_prods(&self) -> &[u128] {
+        return &self.ld_op_prods;
+    }
+
     pub fn op_code(&self) -> u128 {
         let mut result = self.ld_op_bits[0];
         result = field::add(result, field::mul(self.ld_op_bits[1], 2));
@@ -177,6 +201,29 @@ impl TraceState {
         self.cf_op_bits.copy_from_slice(&bits[..3]);
         self.ld_op_bits.copy_from_slice(&bits[3..8]);
         self.hd_op_bits.copy_from_slice(&bits[8..]);
+
+        // set bit products to the values consistent with the bits
+        let not_0 = binary_not(self.ld_op_bits[0]);
+        let not_1 = binary_not(self.ld_op_bits[1]);
+        let not_2 = binary_not(self.ld_op_bits[2]);
+        let not_3 = binary_not(self.ld_op_bits[3]);
+        self.ld_op_prods = [
+            field::mul(not_0, not_1),
+            field::mul(self.ld_op_bits[0], not_1),
+            field::mul(not_0, self.ld_op_bits[1]),
+            field::mul(self.ld_op_bits[0], self.ld_op_bits[1]),
+            field::mul(not_2, not_3),
+            field::mul(self.ld_op_bits[2], not_3),
+            field::mul(not_2, self.ld_op_bits[3]),
+            field::mul(self.ld_op_bits[2], self.ld_op_bits[3]),
+        ];
+        self.op_flags_set = false;
+    }
+
+    #[cfg(test)]
+    pub fn set_ld_op_prods(&mut self, prods: [u128; NUM_LD_OP_PRODS]) {
+        self.ld_op_prods = prods;
+        self.op_flags_set = false;
     }
 
     // OP FLAGS
@@ -233,6 +280,53 @@ impl TraceState {
         return &self.user_stack;
     }
 
+    /// Returns logical depth of the user stack; all user stack registers at or beyond this
+    /// depth are guaranteed to be zeros.
+    pub fn watermark(&self) -> u128 {
+        return field::add(self.depth_floor(), self.read_depth());
+    }
+
+    /// Returns the number of user stack items below the items read by the current operation;
+    /// depth registers hold this value in binary form, and so it can never be negative.
+    pub fn depth_floor(&self) -> u128 {
+        let mut result = field::ZERO;
+        for i in (0..NUM_DEPTH_BITS).rev() {
+            result = field::add(field::mul(result, 2), self.depth_bits[i]);
+        }
+        return result;
+    }
+
+    /// Returns the number of items the current operation reads from the top of the user stack.
+    pub fn read_depth(&self) -> u128 {
+        let ld_flags = self.ld_op_flags();
+        let hd_flags = self.hd_op_flags();
+
+        let mut result = field::ZERO;
+        for &op in USER_OPS.iter() {
+            let (pops, _) = op.stack_effect();
+            if pops == 0 { continue; }
+            let op_flag = match op {
+                OpCode::Push | OpCode::Cmp | OpCode::RescR => hd_flags[op.hd_index()],
+                _ => ld_flags[op.ld_index()],
+            };
+            result = field::add(result, field::mul(op_flag, pops as u128));
+        }
+        return result;
+    }
+
+    /// Returns binary decomposition of the depth floor of the user stack; the least
+    /// significant bit comes first.
+    pub fn depth_bits(&self) -> &[u128] {
+        return &self.depth_bits;
+    }
+
+    #[cfg(test)]
+    pub fn set_depth_floor(&mut self, value: u128) {
+        for i in 0..NUM_DEPTH_BITS {
+            self.depth_bits[i] = (value >> i) & 1;
+        }
+    }
+
     // RAW STATE
     // --------------------------------------------------------------------------------------------
     pub fn to_vec(&self) -> Vec<u128> {
@@ -242,9 +336,11 @@ impl TraceState {
         result.extend_from_slice(&self.cf_op_bits);
         result.extend_from_slice(&self.ld_op_bits);
         result.extend_from_slice(&self.hd_op_bits);
+        result.extend_from_slice(&self.ld_op_prods);
         result.extend_from_slice(&self.ctx_stack[..self.ctx_depth]);
         result.extend_from_slice(&self.loop_stack[..self.loop_depth]);
         result.extend_from_slice(&self.user_stack[..self.stack_depth]);
+        result.extend_from_slice(&self.depth_bits);
         return result;
     }
 
@@ -256,8 +352,9 @@ impl TraceState {
         for (i, j) in CF_OP_BITS_RANGE.enumerate() { self.cf_op_bits[i] = trace[j][step]; }
         for (i, j) in LD_OP_BITS_RANGE.enumerate() { self.ld_op_bits[i] = trace[j][step]; }
         for (i, j) in HD_OP_BITS_RANGE.enumerate() { self.hd_op_bits[i] = trace[j][step]; }
+        for (i, j) in LD_OP_PRODS_RANGE.enumerate(){ self.ld_op_prods[i] = trace[j][step]; }
 
-        let ctx_stack_start = HD_OP_BITS_RANGE.end;
+        let ctx_stack_start = LD_OP_PRODS_RANGE.end;
         let ctx_stack_end = ctx_stack_start + self.ctx_depth;
         for (i, j) in (ctx_stack_start..ctx_stack_end).enumerate() {
             self.ctx_stack[i] = trace[j][step];
@@ -272,7 +369,10 @@ impl TraceState {
         for (i, j) in (loop_stack_end..user_stack_end).enumerate() {
             self.user_stack[i] = trace[j][step];
         }
-        
+        for (i, j) in (user_stack_end..(user_stack_end + NUM_DEPTH_BITS)).enumerate() {
+            self.depth_bits[i] = trace[j][step];
+        }
+
         self.op_flags_set = false;
     }
 
@@ -293,28 +393,14 @@ impl TraceState {
         for i in 0..4 { self.cf_op_flags[i] = field::mul(self.cf_op_flags[i], not_2); }
         for i in 4..8 { self.cf_op_flags[i] = field::mul(self.cf_op_flags[i], self.cf_op_bits[2]); }
 
-        // set low-degree operation flags
-        let not_0 = binary_not(self.ld_op_bits[0]);
-        let not_1 = binary_not(self.ld_op_bits[1]);
-        self.ld_op_flags[0] = field::mul(not_0, not_1);
-        self.ld_op_flags[1] = field::mul(self.ld_op_bits[0], not_1);
-        self.ld_op_flags[2] = field::mul(not_0, self.cf_op_bits[1]);
-        self.ld_op_flags[3] = field::mul(self.ld_op_bits[0], self.ld_op_bits[1]);
-        self.ld_op_flags.copy_within(0..4, 4);
-
-        let not_2 = binary_not(self.ld_op_bits[2]);
-        for i in 0..4 { self.ld_op_flags[i] = field::mul(self.ld_op_flags[i], not_2); }
-        for i in 4..8 { self.ld_op_flags[i] = field::mul(self.ld_op_flags[i], self.ld_op_bits[2]); }
-        self.ld_op_flags.copy_within(0..8, 8);
-
-        let not_3 = binary_not(self.ld_op_bits[3]);
-        for i in 0..8  { self.ld_op_flags[i] = field::mul(self.ld_op_flags[i], not_3); }
-        for i in 8..16 { self.ld_op_flags[i] = field::mul(self.ld_op_flags[i], self.ld_op_bits[3]); }
-        self.ld_op_flags.copy_within(0..16, 16);
-
+        // set low-degree operation flags; products of bit pairs (0, 1) and (2, 3) are read from
+        // dedicated registers, and thus, each flag is a product of just 3 values
         let not_4 = binary_not(self.ld_op_bits[4]);
-        for i in 0..16  { self.ld_op_flags[i] = field::mul(self.ld_op_flags[i], not_4); }
-        for i in 16..32 { self.ld_op_flags[i] = field::mul(self.ld_op_flags[i], self.ld_op_bits[4]); }
+        for i in 0..NUM_LD_OPS {
+            let prod = field::mul(self.ld_op_prods[i & 3], self.ld_op_prods[4 + ((i >> 2) & 3)]);
+            let bit_4 = if i < 16 { not_4 } else { self.ld_op_bits[4] };
+            self.ld_op_flags[i] = field::mul(prod, bit_4);
+        }
 
         // set high-degree operation flags
         let not_0 = binary_not(self.hd_op_bits[0]);
@@ -352,30 +438,34 @@ impl TraceState {
 
 impl fmt::Debug for TraceState {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        write!(f, "[{:>4}] {:>32X?} {:?} {:?} {:?} {:>32X?} {:>32X?} {:?}",
+        write!(f, "[{:>4}] {:>32X?} {:?} {:?} {:?} {:?} {:>32X?} {:>32X?} {:?} {}",
             self.op_counter,
             self.sponge, 
             self.cf_op_bits,
             self.ld_op_bits,
             self.hd_op_bits,
+            self.ld_op_prods,
             self.ctx_stack,
             self.loop_stack,
-            self.user_stack
+            self.user_stack,
+            self.watermark()
         )
     }
 }
 
 impl fmt::Display for TraceState {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        write!(f, "[{:>4}] {:>16X?} {:?} {:?} {:?} {:>16X?} {:>16X?} {:?}",
+        write!(f, "[{:>4}] {:>16X?} {:?} {:?} {:?} {:?} {:>16X?} {:>16X?} {} {}",
             self.op_counter,
             self.sponge.iter().map(|x| x >> 64).collect::<Vec<u128>>(),
             self.cf_op_bits,
             self.ld_op_bits,
             self.hd_op_bits,
+            self.ld_op_prods,
             self.ctx_stack.iter().map(|x| x >> 64).collect::<Vec<u128>>(),
             self.loop_stack.iter().map(|x| x >> 64).collect::<Vec<u128>>(),
-            &self.user_stack[..self.stack_depth]
+            fmt_elements(&self.user_stack[..self.stack_depth], display_radix()),
+            self.watermark()
         )
     }
 }
@@ -399,7 +489,8 @@ mod tests {
 
         // empty context and loop stacks
         let state = TraceState::from_vec(0, 0, 2, &vec![
-            101,  1, 2, 3, 4,  5, 6, 7,  8, 9, 10, 11, 12,  13, 14,  15, 16
+            101,  1, 2, 3, 4,  5, 6, 7,  8, 9, 10, 11, 12,  13, 14,  15, 16, 17, 18,  19, 20, 21, 22,
+            23, 24,  0, 1, 0, 0, 0, 0
         ]);
 
         assert_eq!(101, state.op_counter());
@@ -407,18 +498,22 @@ mod tests {
         assert_eq!([5, 6, 7], state.cf_op_bits());
         assert_eq!([8, 9, 10, 11, 12], state.ld_op_bits());
         assert_eq!([13, 14], state.hd_op_bits());
+        assert_eq!([15, 16, 17, 18, 19, 20, 21, 22], state.ld_op_prods());
         assert_eq!([0], state.ctx_stack());
         assert_eq!([0], state.loop_stack());
-        assert_eq!([15, 16, 0, 0, 0, 0, 0, 0], state.user_stack());
-        assert_eq!(17, state.width());
+        assert_eq!([23, 24, 0, 0, 0, 0, 0, 0], state.user_stack());
+        assert_eq!(2, state.depth_floor());
+        assert_eq!(31, state.width());
         assert_eq!(2, state.stack_depth());
         assert_eq!(vec![
-            101, 1, 2, 3, 4, 5, 6, 7, 8, 9, 10, 11, 12, 13, 14, 15, 16
+            101, 1, 2, 3, 4, 5, 6, 7, 8, 9, 10, 11, 12, 13, 14, 15, 16, 17, 18, 19, 20, 21, 22, 23, 24,
+            0, 1, 0, 0, 0, 0
         ], state.to_vec());
 
         // 1 item on context stack, empty loop stack
         let state = TraceState::from_vec(1, 0, 2, &vec![
-            101,  1, 2, 3, 4,  5, 6, 7,  8, 9, 10, 11, 12,  13, 14,  15,  16, 17
+            101,  1, 2, 3, 4,  5, 6, 7,  8, 9, 10, 11, 12,  13, 14,  15, 16, 17, 18,  19, 20, 21, 22,
+            23,  24, 25,  1, 0, 0, 0, 0, 0
         ]);
 
         assert_eq!(101, state.op_counter());
@@ -426,19 +521,22 @@ mod tests {
         assert_eq!([5, 6, 7], state.cf_op_bits());
         assert_eq!([8, 9, 10, 11, 12], state.ld_op_bits());
         assert_eq!([13, 14], state.hd_op_bits());
-        assert_eq!([15], state.ctx_stack());
+        assert_eq!([15, 16, 17, 18, 19, 20, 21, 22], state.ld_op_prods());
+        assert_eq!([23], state.ctx_stack());
         assert_eq!([0], state.loop_stack());
-        assert_eq!([16, 17, 0, 0, 0, 0, 0, 0], state.user_stack());
-        assert_eq!(18, state.width());
+        assert_eq!([24, 25, 0, 0, 0, 0, 0, 0], state.user_stack());
+        assert_eq!(1, state.depth_floor());
+        assert_eq!(32, state.width());
         assert_eq!(2, state.stack_depth());
         assert_eq!(vec![
-            101, 1, 2, 3, 4, 5, 6, 7, 8, 9, 10, 11, 12, 13, 14, 15, 16, 17
+            101, 1, 2, 3, 4, 5, 6, 7, 8, 9, 10, 11, 12, 13, 14, 15, 16, 17, 18, 19, 20, 21, 22, 23,
+            24, 25, 1, 0, 0, 0, 0, 0
         ], state.to_vec());
 
         // non-empty loop stack
         let state = TraceState::from_vec(2, 1, 9, &vec![
-            101,  1, 2, 3, 4,  5, 6, 7,  8, 9, 10, 11, 12,  13, 14,  15, 16,  17,
-            18, 19, 20, 21, 22, 23, 24, 25, 26,
+            101,  1, 2, 3, 4,  5, 6, 7,  8, 9, 10, 11, 12,  13, 14,  15, 16, 17, 18,  19, 20, 21, 22,
+            23, 24,  25,  26, 27, 28, 29, 30, 31, 32, 33, 34,  1, 1, 1, 0, 0, 0,
         ]);
 
         assert_eq!(101, state.op_counter());
@@ -446,21 +544,24 @@ mod tests {
         assert_eq!([5, 6, 7], state.cf_op_bits());
         assert_eq!([8, 9, 10, 11, 12], state.ld_op_bits());
         assert_eq!([13, 14], state.hd_op_bits());
-        assert_eq!([15, 16], state.ctx_stack());
-        assert_eq!([17], state.loop_stack());
-        assert_eq!([18, 19, 20, 21, 22, 23, 24, 25, 26], state.user_stack());
-        assert_eq!(27, state.width());
+        assert_eq!([15, 16, 17, 18, 19, 20, 21, 22], state.ld_op_prods());
+        assert_eq!([23, 24], state.ctx_stack());
+        assert_eq!([25], state.loop_stack());
+        assert_eq!([26, 27, 28, 29, 30, 31, 32, 33, 34], state.user_stack());
+        assert_eq!(7, state.depth_floor());
+        assert_eq!(41, state.width());
         assert_eq!(9, state.stack_depth());
         assert_eq!(vec![
-            101, 1, 2, 3, 4, 5, 6, 7, 8, 9, 10, 11, 12, 13, 14, 15, 16, 17,
-            18, 19, 20, 21, 22, 23, 24, 25, 26,
+            101, 1, 2, 3, 4, 5, 6, 7, 8, 9, 10, 11, 12, 13, 14, 15, 16, 17, 18, 19, 20, 21, 22,
+            23, 24, 25, 26, 27, 28, 29, 30, 31, 32, 33, 34, 1, 1, 1, 0, 0, 0,
         ], state.to_vec());
     }
 
     #[test]
     fn update_from_trace() {
         let data = vec![
-            101,  1, 2, 3, 4,  5, 6, 7,  8, 9, 10, 11, 12,  13, 14,  15, 16,  17,  18, 19, 20
+            101,  1, 2, 3, 4,  5, 6, 7,  8, 9, 10, 11, 12,  13, 14,  15, 16, 17, 18,  19, 20, 21, 22,
+            23, 24,  25,  26, 27, 28,  1, 1, 0, 0, 0, 0
         ];
         let mut trace = Vec::with_capacity(data.len());
         for i in 0..data.len() {
@@ -476,10 +577,12 @@ mod tests {
         assert_eq!([0, 0, 0], state.cf_op_bits());
         assert_eq!([0, 0, 0, 0, 0], state.ld_op_bits());
         assert_eq!([0, 0], state.hd_op_bits());
+        assert_eq!([0, 0, 0, 0, 0, 0, 0, 0], state.ld_op_prods());
         assert_eq!([0, 0], state.ctx_stack());
         assert_eq!([0], state.loop_stack());
         assert_eq!([0, 0, 0, 0, 0, 0, 0, 0], state.user_stack());
-        assert_eq!(21, state.width());
+        assert_eq!(0, state.depth_floor());
+        assert_eq!(35, state.width());
         assert_eq!(3, state.stack_depth());
 
         // second row
@@ -490,10 +593,12 @@ mod tests {
         assert_eq!([5, 6, 7], state.cf_op_bits());
         assert_eq!([8, 9, 10, 11, 12], state.ld_op_bits());
         assert_eq!([13, 14], state.hd_op_bits());
-        assert_eq!([15, 16], state.ctx_stack());
-        assert_eq!([17], state.loop_stack());
-        assert_eq!([18, 19, 20, 0, 0, 0, 0, 0], state.user_stack());
-        assert_eq!(21, state.width());
+        assert_eq!([15, 16, 17, 18, 19, 20, 21, 22], state.ld_op_prods());
+        assert_eq!([23, 24], state.ctx_stack());
+        assert_eq!([25], state.loop_stack());
+        assert_eq!([26, 27, 28, 0, 0, 0, 0, 0], state.user_stack());
+        assert_eq!(3, state.depth_floor());
+        assert_eq!(35, state.width());
         assert_eq!(3, state.stack_depth());
     }
 
@@ -502,7 +607,7 @@ mod tests {
 
         // all zeros
         let state = TraceState::from_vec(1, 0, 2, &vec![
-            101,  1, 2, 3, 4,  0, 0, 0,  0, 0, 0, 0, 0,  0, 0,  15, 16, 17
+            101,  1, 2, 3, 4,  0, 0, 0,  0, 0, 0, 0, 0,  0, 0,  1, 0, 0, 0,  1, 0, 0, 0,  15, 16, 17,  0, 1, 0, 0, 0, 0
         ]);
 
         assert_eq!([1, 0, 0, 0, 0, 0, 0, 0], state.cf_op_flags());
@@ -516,7 +621,7 @@ mod tests {
 
         // all ones
         let state = TraceState::from_vec(1, 0, 2, &vec![
-            101,  1, 2, 3, 4,  1, 1, 1,  1, 1, 1, 1, 1,  1, 1,  15, 16, 17
+            101,  1, 2, 3, 4,  1, 1, 1,  1, 1, 1, 1, 1,  1, 1,  0, 0, 0, 1,  0, 0, 0, 1,  15, 16, 17,  0, 1, 0, 0, 0, 0
         ]);
 
         assert_eq!([0, 0, 0, 0, 0, 0, 0, 1], state.cf_op_flags());
@@ -530,7 +635,7 @@ mod tests {
 
         // mixed 1
         let state = TraceState::from_vec(1, 0, 2, &vec![
-            101,  1, 2, 3, 4,  1, 0, 0,  1, 0, 0, 0, 0,  1, 0,  15, 16, 17
+            101,  1, 2, 3, 4,  1, 0, 0,  1, 0, 0, 0, 0,  1, 0,  0, 1, 0, 0,  1, 0, 0, 0,  15, 16, 17,  0, 1, 0, 0, 0, 0
         ]);
 
         assert_eq!([0, 1, 0, 0, 0, 0, 0, 0], state.cf_op_flags());
@@ -544,7 +649,7 @@ mod tests {
 
         // mixed 2
         let state = TraceState::from_vec(1, 0, 2, &vec![
-            101, 1, 2, 3, 4, 1, 1, 0, 1, 1, 0, 0, 0, 0, 1, 15, 16, 17
+            101, 1, 2, 3, 4, 1, 1, 0, 1, 1, 0, 0, 0, 0, 1, 0, 0, 0, 1, 1, 0, 0, 0, 15, 16, 17, 0, 1, 0, 0, 0, 0
         ]);
 
         assert_eq!([0, 0, 0, 1, 0, 0, 0, 0], state.cf_op_flags());
@@ -555,25 +660,47 @@ mod tests {
         assert_eq!([0, 0, 1, 0], state.hd_op_flags());
     }
 
+    #[test]
+    fn ld_op_flags_ignore_cf_op_bits() {
+
+        // op code 2 sets only ld_op bit 1, while cf_op bit 1 is left at 0
+        let mut state = TraceState::new(1, 0, 2);
+        state.set_op_bits([1, 0, 0,  0, 1, 0, 0, 0,  0, 0]);
+
+        assert_eq!([
+            0, 0, 1, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0,
+            0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0,
+        ], state.ld_op_flags());
+
+        // op code 32 sets no ld_op bits, while cf_op bit 1 is set to 1
+        let mut state = TraceState::new(1, 0, 2);
+        state.set_op_bits([0, 1, 0,  0, 0, 0, 0, 0,  1, 0]);
+
+        assert_eq!([
+            1, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0,
+            0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0,
+        ], state.ld_op_flags());
+    }
+
     #[test]
     fn op_code() {
         let state = TraceState::from_vec(1, 0, 2, &vec![
-            101,  1, 2, 3, 4,  1, 1, 1,  0, 0, 0, 0, 0,  0, 0,  15, 16, 17
+            101,  1, 2, 3, 4,  1, 1, 1,  0, 0, 0, 0, 0,  0, 0,  1, 0, 0, 0,  1, 0, 0, 0,  15, 16, 17,  0, 1, 0, 0, 0, 0
         ]);
         assert_eq!(0, state.op_code());
 
         let state = TraceState::from_vec(1, 0, 2, &vec![
-            101,  1, 2, 3, 4,  1, 1, 1,  1, 1, 1, 1, 1,  1, 1,  15, 16, 17
+            101,  1, 2, 3, 4,  1, 1, 1,  1, 1, 1, 1, 1,  1, 1,  0, 0, 0, 1,  0, 0, 0, 1,  15, 16, 17,  0, 1, 0, 0, 0, 0
         ]);
         assert_eq!(127, state.op_code());
 
         let state = TraceState::from_vec(1, 0, 2, &vec![
-            101,  1, 2, 3, 4,  1, 1, 1,  1, 1, 1, 1, 1,  1, 0,  15, 16, 17
+            101,  1, 2, 3, 4,  1, 1, 1,  1, 1, 1, 1, 1,  1, 0,  0, 0, 0, 1,  0, 0, 0, 1,  15, 16, 17,  0, 1, 0, 0, 0, 0
         ]);
         assert_eq!(63, state.op_code());
 
         let state = TraceState::from_vec(1, 0, 2, &vec![
-            101,  1, 2, 3, 4,  1, 1, 1,  1, 0, 0, 0, 0,  1, 1,  15, 16, 17
+            101,  1, 2, 3, 4,  1, 1, 1,  1, 0, 0, 0, 0,  1, 1,  0, 1, 0, 0,  1, 0, 0, 0,  15, 16, 17,  0, 1, 0, 0, 0, 0
         ]);
         assert_eq!(97, state.op_code());
     }
@@ -1,13 +1,19 @@
-use crate::math::{ field, fft, polynom, parallel };
+use serde::{ Serialize, Deserialize };
+#[cfg(feature = "concurrent")]
+use rayon::prelude::*;
+use crate::math::{ field, fft::{ self, FftContext }, polynom, parallel };
 use crate::crypto::{ MerkleTree, HashFunction };
-use crate::stark::{ CompositionCoefficients, utils };
-use crate::utils::{ uninit_vector, filled_vector, as_bytes };
-use super::{ TraceState };
+use crate::stark::{ CompositionCoefficients, EVALUATION_BLOWUP, utils };
+use crate::utils::{ filled_vector, as_bytes, CONCURRENT };
+use super::{ TraceState, TraceLayout };
+#[cfg(test)]
+use super::{ TraceDiff, TraceShape };
 
 // TYPES AND INTERFACES
 // ================================================================================================
+#[derive(Serialize, Deserialize)]
 pub struct TraceTable {
-    registers       : Vec<Vec<u128>>,
+    registers       : Vec<Vec<u128>>,   // once extended, only states over constraint evaluation domain
     polys           : Vec<Vec<u128>>,
     ctx_depth       : usize,
     loop_depth      : usize,
@@ -37,8 +43,8 @@ impl TraceTable {
 
         // compute stack depth
         let decoder_width = TraceState::compute_decoder_width(ctx_depth, loop_depth);
-        assert!(registers.len() > decoder_width, "user stack must consist of at least one register");
-        let stack_depth = registers.len() - decoder_width;
+        assert!(registers.len() > decoder_width + 1, "user stack must consist of at least one register");
+        let stack_depth = TraceState::compute_stack_depth(registers.len(), ctx_depth, loop_depth);
 
         // validate register traces
         assert!(registers.len() < crate::MAX_REGISTER_COUNT,
@@ -75,9 +81,20 @@ impl TraceTable {
         return self.get_state(last_step);
     }
 
-    /// Copies trace table state at the specified `step` to the passed in `state` object.
+    /// Copies trace table state at the specified `step` to the passed in `state` object. Once
+    /// the table is extended, only states at steps which are multiples of extension_factor / 8
+    /// (i.e. states over the constraint evaluation domain) can be copied.
     pub fn fill_state(&self, state: &mut TraceState, step: usize) {
-        state.update_from_trace(&self.registers, step);
+        state.update_from_trace(&self.registers, self.row_index(step));
+    }
+
+    /// Maps a `step` of the trace table to the index of its state in register traces; for an
+    /// extended table, only steps over the constraint evaluation domain can be mapped.
+    fn row_index(&self, step: usize) -> usize {
+        if !self.is_extended() { return step; }
+        let stride = self.extension_factor / EVALUATION_BLOWUP;
+        assert!(step % stride == 0, "state at step {} of the extended trace is not kept in the table", step);
+        return step / stride;
     }
 
     /// Returns the number of states in the un-extended trace table.
@@ -115,6 +132,30 @@ impl TraceTable {
         return self.stack_depth;
     }
 
+    /// Returns names and positions of registers in the trace table.
+    pub fn layout(&self) -> TraceLayout {
+        return TraceLayout::new(self.ctx_depth, self.loop_depth, self.stack_depth);
+    }
+
+    /// Returns dimensions of the trace table.
+    #[cfg(test)]
+    pub fn shape(&self) -> TraceShape {
+        return TraceShape {
+            ctx_depth   : self.ctx_depth,
+            loop_depth  : self.loop_depth,
+            stack_depth : self.stack_depth,
+            length      : self.registers[0].len(),
+        };
+    }
+
+    /// Compares this trace table to the `other` trace table register by register. Tables of
+    /// different shapes (including extended vs. un-extended tables) are reported as a shape
+    /// mismatch without comparing individual cells.
+    #[cfg(test)]
+    pub fn diff(&self, other: &TraceTable) -> TraceDiff {
+        return TraceDiff::new(&self.registers, self.shape(), &other.registers, other.shape());
+    }
+
     /// Returns polynomial of the register at the specified `index`; can be called only
     /// after the trace table has been extended.
     #[cfg(test)]
@@ -123,65 +164,194 @@ impl TraceTable {
         return &self.polys[index];
     }
 
-    /// Returns values of all registers at the specified `positions`.
+    /// Returns values of all registers at the specified `positions` of the extended trace; values
+    /// of states which are not kept in the table are computed from trace polynomials.
     pub fn get_register_values_at(&self, positions: &[usize]) -> Vec<Vec<u128>> {
+        assert!(self.is_extended(), "trace table has not been extended yet");
+        let stride = self.extension_factor / EVALUATION_BLOWUP;
+        let root = field::get_root_of_unity(self.domain_size());
+
         let mut result = Vec::with_capacity(positions.len());
         for &i in positions.iter() {
-            let row = self.registers.iter().map(|r| r[i]).collect();
+            let row = if i % stride == 0 {
+                self.registers.iter().map(|r| r[i / stride]).collect()
+            }
+            else {
+                self.eval_polys_at(field::exp(root, i as u128))
+            };
             result.push(row);
         }
         return result;
     }
 
+    /// Returns the number of steps which follow the last state of the program execution; these
+    /// steps repeat the last state, and pad the trace to a power of 2 or to the minimum length
+    /// required by proof options.
+    pub fn padding_length(&self) -> usize {
+        let stride = if self.is_extended() { EVALUATION_BLOWUP } else { 1 };
+        let last_step = self.trace_length - 1;
+        let repeats_last_state = |step: usize| self.registers.iter()
+            .all(|register| register[step * stride] == register[last_step * stride]);
+
+        let mut step = last_step;
+        while step > 0 && repeats_last_state(step - 1) {
+            step -= 1;
+        }
+        return last_step - step;
+    }
+
     /// Returns `true` if the trace table has been extended.
     pub fn is_extended(&self) -> bool {
         return self.registers[0].len() > self.trace_length;
     }
 
     /// Extends all registers of the trace table by the `extension_factor` specified during
-    /// trace table construction. A trace table can be extended only once.
-    pub fn extend(&mut self, twiddles: &[u128]) {
+    /// trace table construction using twiddles from `fft_ctx`; the context must support FFTs
+    /// over the extended domain. A trace table can be extended only once.
+    ///
+    /// To limit memory usage, only states over the constraint evaluation domain (every
+    /// extension_factor / 8 state of the extended trace) are kept in the table after the
+    /// extension; all other states are re-computed from trace polynomials when needed.
+    pub fn extend(&mut self, fft_ctx: &FftContext) {
+        self.extend_blinded(fft_ctx, Vec::new());
+    }
+
+    /// Extends all registers of the trace table in the same way as `extend()`, but before the
+    /// extension, polynomial T(x) of register i is replaced with T(x) + (x^n - 1) * R_i(x), where
+    /// n is the length of the trace, and coefficients of R_i(x) are `blinding[i]`. The new
+    /// polynomial has the same values over the execution trace, but its degree is increased by
+    /// the number of coefficients in R_i(x), and its values outside of the trace are random.
+    pub fn extend_blinded(&mut self, fft_ctx: &FftContext, blinding: Vec<Vec<u128>>) {
+        self.extend_registers(fft_ctx, blinding, CONCURRENT);
+    }
+
+    /// Extends all registers of the trace table either sequentially, or, if `concurrent` is
+    /// set, on all available cores; both ways produce the same extended trace.
+    fn extend_registers(&mut self, fft_ctx: &FftContext, blinding: Vec<Vec<u128>>, concurrent: bool) {
         assert!(!self.is_extended(), "trace table has already been extended");
-        assert!(twiddles.len() * 2 == self.domain_size(), "invalid number of twiddles");
+        assert!(fft_ctx.size() >= self.domain_size(), "FFT context does not support the extended domain");
+        assert!(blinding.is_empty() || blinding.len() == self.register_count(),
+            "expected blinding coefficients for {} registers, but received {}",
+            self.register_count(), blinding.len());
 
-        // build inverse twiddles needed for FFT interpolation
-        let root = field::get_root_of_unity(self.unextended_length());
-        let inv_twiddles = fft::get_inv_twiddles(root, self.unextended_length());
-        
         // move register traces into polys
         std::mem::swap(&mut self.registers, &mut self.polys);
 
-        // extend all registers
-        let domain_size = self.domain_size();
-        for poly in self.polys.iter_mut() {
-
-            // interpolate register trace into a polynomial
-            polynom::interpolate_fft_twiddles(poly, &inv_twiddles, true);
-            
-            // allocate space to hold extended evaluations and copy the polynomial into it
-            let mut register = vec![field::ZERO; domain_size];
-            register[..poly.len()].copy_from_slice(&poly);
-            
-            // evaluate the polynomial over extended domain
-            polynom::eval_fft_twiddles(&mut register, &twiddles, true);
-            self.registers.push(register);
+        // extend all registers over the constraint evaluation domain; registers are independent
+        // of each other, and so they can be extended in parallel
+        let trace_length = self.unextended_length();
+        let evaluation_size = trace_length * EVALUATION_BLOWUP;
+        let extend = |(i, poly): (usize, &mut Vec<u128>)| {
+            extend_register(poly, blinding.get(i), fft_ctx, trace_length, evaluation_size)
+        };
+
+        #[cfg(feature = "concurrent")]
+        if concurrent {
+            self.registers = self.polys.par_iter_mut().enumerate().map(extend).collect();
+            return;
         }
+
+        #[cfg(not(feature = "concurrent"))]
+        let _ = concurrent;
+        self.registers = self.polys.iter_mut().enumerate().map(extend).collect();
+    }
+
+    /// Returns the number of random coefficients mixed into every register polynomial; this is
+    /// always 0 for trace tables which have not been extended yet.
+    pub fn blinding_factor(&self) -> usize {
+        if !self.is_extended() { return 0; }
+        return self.polys[0].len() - self.trace_length;
+    }
+
+    /// Returns an estimate of the number of bytes needed to extend and commit to an execution
+    /// trace of `registers` registers and `steps` steps (after padding) with the specified
+    /// `blowup` (extension) factor. The estimate covers trace polynomials, states kept in the
+    /// extended table, states of a single coset computed during hashing, and the Merkle tree
+    /// of the extended trace; it does not include memory needed for constraint evaluation or
+    /// for FRI layers.
+    pub fn memory_estimate(registers: usize, steps: usize, blowup: usize) -> usize {
+        let element_size = std::mem::size_of::<u128>();
+        let polys = registers * steps * element_size;
+        let states = registers * steps * EVALUATION_BLOWUP.min(blowup) * element_size;
+        let coset = registers * steps * element_size;
+        let merkle_tree = 2 * steps * blowup * 32;
+        return polys + states + coset + merkle_tree;
     }
 
-    /// Puts the trace table into a Merkle tree such that each state of the table becomes
-    /// a distinct leaf in the tree; all registers at a given step are hashed together to
-    /// form a single leaf value.
-    pub fn build_merkle_tree(&self, hash: HashFunction) -> MerkleTree {
-        let mut trace_state = vec![field::ZERO; self.register_count()];
-        let mut hashed_states = uninit_vector::<[u8; 32]>(self.domain_size());
-        // TODO: this loop should be parallelized
-        for i in 0..self.domain_size() {
-            for j in 0..trace_state.len() {
-                trace_state[j] = self.registers[j][i];
+    /// Puts the extended trace table into a Merkle tree such that each state of the table becomes
+    /// a distinct leaf in the tree; all registers at a given step are hashed together to form a
+    /// single leaf value. When `salt_seed` is provided, every state is hashed together with a
+    /// pseudo-random salt derived from the seed. `fft_ctx` must be built for the extended domain.
+    pub fn build_merkle_tree(&self, fft_ctx: &FftContext, hash: HashFunction, salt_seed: Option<&[u8; 32]>) -> MerkleTree {
+        return MerkleTree::new(self.hash_states(fft_ctx, hash, salt_seed, CONCURRENT), hash);
+    }
+
+    /// Hashes all states of the extended trace table either sequentially, or, if `concurrent`
+    /// is set, on all available cores; both ways produce the same hashes. The extended domain
+    /// consists of extension_factor cosets of the trace domain, and states are computed and
+    /// hashed one coset at a time, so that only one coset of states is held in memory.
+    fn hash_states(&self, fft_ctx: &FftContext, hash: HashFunction, salt_seed: Option<&[u8; 32]>, concurrent: bool) -> Vec<[u8; 32]> {
+        assert!(self.is_extended(), "trace table has not been extended yet");
+        assert!(fft_ctx.size() == self.domain_size(), "FFT context does not match the extended domain");
+
+        let mut hashed_states = vec![[0u8; 32]; self.domain_size()];
+        let mut coset = vec![vec![field::ZERO; self.trace_length]; self.register_count()];
+        for j in 0..self.extension_factor {
+            self.fill_coset(&mut coset, j, fft_ctx, concurrent);
+
+            // i-th state of the coset is the state at step i * extension_factor + j
+            let hash_state = |trace_state: &mut Vec<u128>, i: usize| {
+                for (value, register) in trace_state.iter_mut().zip(coset.iter()) {
+                    *value = register[i];
+                }
+                let step = i * self.extension_factor + j;
+                return match salt_seed {
+                    Some(seed) => {
+                        let salt = utils::get_leaf_salt(seed, utils::TRACE_SALTS, step);
+                        utils::hash_salted(as_bytes(trace_state), &salt, hash)
+                    },
+                    None => {
+                        let mut result = [0u8; 32];
+                        hash.digest(as_bytes(trace_state), &mut result);
+                        result
+                    },
+                };
+            };
+
+            let hashes = map_states(self.trace_length, self.register_count(), hash_state, concurrent);
+            for (i, state_hash) in hashes.into_iter().enumerate() {
+                hashed_states[i * self.extension_factor + j] = state_hash;
+            }
+        }
+        return hashed_states;
+    }
+
+    /// Fills `coset` with states of the extended trace at steps i * extension_factor + j for
+    /// all i; states which are kept in the table are copied, and all other states are computed
+    /// from trace polynomials.
+    fn fill_coset(&self, coset: &mut [Vec<u128>], j: usize, fft_ctx: &FftContext, concurrent: bool) {
+        let stride = self.extension_factor / EVALUATION_BLOWUP;
+        let offset = fft_ctx.domain()[j];
+        let fill = |(values, (register, poly)): (&mut Vec<u128>, (&Vec<u128>, &Vec<u128>))| {
+            if j % stride == 0 {
+                for (i, value) in values.iter_mut().enumerate() {
+                    *value = register[i * EVALUATION_BLOWUP + j / stride];
+                }
             }
-            hash(as_bytes(&trace_state), &mut hashed_states[i]);
+            else {
+                fft::evaluate_on_coset(poly, offset, values, fft_ctx);
+            }
+        };
+
+        #[cfg(feature = "concurrent")]
+        if concurrent {
+            coset.par_iter_mut().zip(self.registers.par_iter().zip(self.polys.par_iter())).for_each(fill);
+            return;
         }
-        return MerkleTree::new(hashed_states, hash);
+
+        #[cfg(not(feature = "concurrent"))]
+        let _ = concurrent;
+        coset.iter_mut().zip(self.registers.iter().zip(self.polys.iter())).for_each(fill);
     }
 
     /// Evaluates trace polynomials at the specified point `z`; can be called only after
@@ -202,8 +372,9 @@ impl TraceTable {
     /// 2. Then, polynomials T1_i(x) = (T_i(x) - T_i(z)) / (x - z) and 
     /// T2_i(x) = (T_i(x) - T_i(z * g)) / (x - z * g) are computed for all i and combined
     /// together into a single polynomial using a pseudo-random linear combination;
-    /// 3. Then the degree of the polynomial is adjusted to match the specified degree
-    pub fn get_composition_poly(&self, z: u128, cc: &CompositionCoefficients) -> (Vec<u128>, Vec<u128>, Vec<u128>) {
+    /// 3. Then the degree of the polynomial is adjusted to match the composition degree for a
+    /// trace which contains `num_segments` program executions
+    pub fn get_composition_poly(&self, z: u128, cc: &CompositionCoefficients, num_segments: usize) -> (Vec<u128>, Vec<u128>, Vec<u128>) {
 
         let trace_length = self.unextended_length();
         assert!(self.is_extended(), "trace table has not been extended yet");
@@ -215,8 +386,9 @@ impl TraceTable {
         let trace_state1 = self.eval_polys_at(z);
         let trace_state2 = self.eval_polys_at(next_z);
 
-        let mut t1_composition = vec![field::ZERO; trace_length];
-        let mut t2_composition = vec![field::ZERO; trace_length];
+        let poly_length = self.polys[0].len();
+        let mut t1_composition = vec![field::ZERO; poly_length];
+        let mut t2_composition = vec![field::ZERO; poly_length];
 
         // combine trace polynomials into 2 composition polynomials T1(x) and T2(x)
         for i in 0..self.polys.len() {
@@ -241,18 +413,19 @@ impl TraceTable {
 
         // adjust the degree of the polynomial to match the degree parameter by computing
         // C(x) = T(x) * k_1 + T(x) * x^incremental_degree * k_2
-        let poly_size = utils::get_composition_degree(trace_length).next_power_of_two();
+        let blinding_factor = self.blinding_factor();
+        let poly_size = utils::get_composition_degree(trace_length, blinding_factor, num_segments).next_power_of_two();
         let mut composition_poly = filled_vector(poly_size, self.domain_size(), field::ZERO);
-        let incremental_degree = utils::get_incremental_trace_degree(trace_length);
+        let incremental_degree = utils::get_incremental_trace_degree(trace_length, blinding_factor, num_segments);
         // this is equivalent to T(x) * k_1
         parallel::mul_acc(
-            &mut composition_poly[..trace_length],
+            &mut composition_poly[..poly_length],
             &t1_composition,
             cc.t1_degree,
             1);
         // this is equivalent to T(x) * x^incremental_degree * k_2
         parallel::mul_acc(
-            &mut composition_poly[incremental_degree..(incremental_degree + trace_length)],
+            &mut composition_poly[incremental_degree..(incremental_degree + poly_length)],
             &t1_composition,
             cc.t2_degree,
             1);
@@ -261,16 +434,63 @@ impl TraceTable {
     }
 }
 
+// HELPER FUNCTIONS
+// ================================================================================================
+
+/// Hashes `count` states using `hash_state` either sequentially, or, if `concurrent` is set,
+/// on all available cores; `hash_state` receives a buffer of `width` elements for the state.
+fn map_states<F>(count: usize, width: usize, hash_state: F, concurrent: bool) -> Vec<[u8; 32]>
+    where F: Fn(&mut Vec<u128>, usize) -> [u8; 32] + Send + Sync
+{
+    #[cfg(feature = "concurrent")]
+    if concurrent {
+        return (0..count).into_par_iter()
+            .map_init(|| vec![field::ZERO; width], hash_state)
+            .collect();
+    }
+
+    #[cfg(not(feature = "concurrent"))]
+    let _ = concurrent;
+    let mut trace_state = vec![field::ZERO; width];
+    return (0..count).map(|i| hash_state(&mut trace_state, i)).collect();
+}
+
+/// Interpolates register trace `poly` into a polynomial in place, adds (x^n - 1) * R(x) to it if
+/// `blinding` coefficients of R(x) are provided, and evaluates the polynomial over the extended
+/// domain.
+fn extend_register(poly: &mut Vec<u128>, blinding: Option<&Vec<u128>>, fft_ctx: &FftContext, trace_length: usize, domain_size: usize) -> Vec<u128> {
+
+    // interpolate register trace into a polynomial
+    fft::interpolate_with_ctx(poly, fft_ctx);
+
+    // add (x^n - 1) * R(x) to the polynomial
+    if let Some(coefficients) = blinding {
+        assert!(coefficients.len() < trace_length, "too many blinding coefficients");
+        poly.resize(trace_length + coefficients.len(), field::ZERO);
+        for (j, &c) in coefficients.iter().enumerate() {
+            poly[j] = field::sub(poly[j], c);
+            poly[trace_length + j] = field::add(poly[trace_length + j], c);
+        }
+    }
+
+    // allocate space to hold extended evaluations and copy the polynomial into it
+    let mut register = vec![field::ZERO; domain_size];
+    register[..poly.len()].copy_from_slice(&poly);
+
+    // evaluate the polynomial over extended domain
+    fft::evaluate_with_ctx(&mut register, fft_ctx);
+    return register;
+}
+
 // TESTS
 // ================================================================================================
 
 #[cfg(test)]
 mod tests {
 
-    use std::collections::HashMap;
     use crate::{
-        math::{ field, polynom, parallel, fft },
-        crypto::hash::blake3,
+        math::{ field, polynom, parallel, fft::FftContext },
+        crypto::{ HashFunction, build_nodes },
         programs::{ Program, ProgramInputs, blocks::{ ProgramBlock, Span, Group } },
         processor::{ execute, OpCode },
         stark::{ TraceTable, CompositionCoefficients, utils::get_composition_degree }
@@ -281,8 +501,7 @@ mod tests {
     #[test]
     fn eval_polys_at() {
         let mut trace = build_trace_table();
-        let lde_root = field::get_root_of_unity(trace.domain_size());
-        trace.extend(&fft::get_twiddles(lde_root, trace.domain_size()));
+        trace.extend(&FftContext::new(trace.domain_size()));
 
         let g = field::get_root_of_unity(trace.unextended_length());
 
@@ -295,23 +514,56 @@ mod tests {
         assert_eq!(v2, s2.to_vec());
     }
 
+    #[test]
+    fn get_register_values_at() {
+        let mut trace = build_trace_table();
+        trace.extend(&FftContext::new(trace.domain_size()));
+
+        // only every 4th state is kept in the table, the rest is computed from polynomials
+        let g = field::get_root_of_unity(trace.domain_size());
+        let positions = [0, 3, 4, 33, 1000];
+        let values = trace.get_register_values_at(&positions);
+        for (&position, row) in positions.iter().zip(values.iter()) {
+            assert_eq!(&trace.eval_polys_at(field::exp(g, position as u128)), row);
+        }
+    }
+
+    #[test]
+    fn concurrent_commitment() {
+        // with `concurrent` feature, the trace is extended and hashed on all available cores;
+        // the result must be the same as for sequential extension and hashing
+        let fft_ctx = FftContext::new(build_trace_table().domain_size());
+        let seed = [3u8; 32];
+
+        let mut roots = Vec::new();
+        for &concurrent in [false, true].iter() {
+            let mut trace = build_trace_table();
+            let blinding = (0..trace.register_count()).map(|i| vec![i as u128 + 1; 4]).collect();
+            trace.extend_registers(&fft_ctx, blinding, concurrent);
+            let leaves = trace.hash_states(&fft_ctx, HashFunction::Blake3, Some(&seed), concurrent);
+            let nodes = build_nodes(&leaves, HashFunction::Blake3, concurrent);
+            roots.push((trace.registers, leaves, nodes[1]));
+        }
+        assert!(roots[0] == roots[1]);
+    }
+
     #[test]
     fn get_composition_poly() {
 
         let mut trace = build_trace_table();
-        let lde_root = field::get_root_of_unity(trace.domain_size());
-        trace.extend(&fft::get_twiddles(lde_root, trace.domain_size()));
+        let fft_ctx = FftContext::new(trace.domain_size());
+        trace.extend(&fft_ctx);
 
         // compute trace composition polynomial
-        let t_tree = trace.build_merkle_tree(blake3);
+        let t_tree = trace.build_merkle_tree(&fft_ctx, HashFunction::Blake3, None);
         let z = field::prng(*t_tree.root());
         let cc = CompositionCoefficients::new(*t_tree.root());
-        let target_degree =  get_composition_degree(trace.unextended_length());
+        let target_degree =  get_composition_degree(trace.unextended_length(), 0, 1);
 
         let g = field::get_root_of_unity(trace.unextended_length());
         let zg = field::mul(z, g);
 
-        let (composition_poly, ..) = trace.get_composition_poly(z, &cc);
+        let (composition_poly, ..) = trace.get_composition_poly(z, &cc, 1);
         let mut actual_evaluations = composition_poly.clone();
         polynom::eval_fft(&mut actual_evaluations, true);
         assert_eq!(target_degree, polynom::infer_degree(&actual_evaluations));
@@ -370,10 +622,10 @@ mod tests {
             OpCode::Add,   OpCode::Noop, OpCode::Noop,
         ];
         let program = Program::new(Group::new(vec![
-            ProgramBlock::Span(Span::new(instructions, HashMap::new()))
+            ProgramBlock::Span(Span::from_instructions(instructions))
         ]));
         let inputs = ProgramInputs::from_public(&[1, 0]);
-        let (trace, ctx_depth, loop_depth) = execute(&program, &inputs);
+        let (trace, ctx_depth, loop_depth) = execute(&program, &inputs).unwrap();
         return TraceTable::new(trace, ctx_depth, loop_depth, EXT_FACTOR);
     }
 }
\ No newline at end of file
@@ -0,0 +1,79 @@
+use rand::prelude::*;
+use crate::{ math::field, crypto::HashFunction };
+
+// CONSTANTS
+// ================================================================================================
+const TRACE_TAG : u8 = 0;
+const MASK_TAG  : u8 = 1;
+const SALT_TAG  : u8 = 2;
+
+/// Identifiers of salted Merkle trees.
+pub const TRACE_SALTS: u8 = 0;
+pub const MASK_SALTS : u8 = 1;
+
+// PUBLIC FUNCTIONS
+// ================================================================================================
+
+/// Returns a seed from which all prover-side randomness is derived; the seed specified in proof
+/// options is used when present.
+pub fn get_prover_seed(seed: Option<[u8; 32]>) -> [u8; 32] {
+    return match seed {
+        Some(seed) => seed,
+        None => rand::thread_rng().gen(),
+    };
+}
+
+/// Returns `count` random coefficients for each of `num_registers` trace polynomials.
+pub fn get_trace_blinding(seed: &[u8; 32], num_registers: usize, count: usize) -> Vec<Vec<u128>> {
+    return (0..num_registers)
+        .map(|i| field::prng_vector(derive_seed(seed, TRACE_TAG, i as u64), count))
+        .collect();
+}
+
+/// Returns `length` random coefficients of the polynomial which masks the composition polynomial.
+pub fn get_mask_coefficients(seed: &[u8; 32], length: usize) -> Vec<u128> {
+    return field::prng_vector(derive_seed(seed, MASK_TAG, 0), length);
+}
+
+/// Returns the salt for a leaf at the specified `index` of a Merkle tree; `tree` distinguishes
+/// trees committed to by the same proof.
+pub fn get_leaf_salt(seed: &[u8; 32], tree: u8, index: usize) -> [u8; 32] {
+    return derive_seed(seed, SALT_TAG + tree, index as u64);
+}
+
+/// Hashes `values` together with the `salt` into a single Merkle tree leaf.
+pub fn hash_salted(values: &[u8], salt: &[u8; 32], hash: HashFunction) -> [u8; 32] {
+    let mut data = Vec::with_capacity(values.len() + salt.len());
+    data.extend_from_slice(values);
+    data.extend_from_slice(salt);
+    let mut result = [0u8; 32];
+    hash.digest(&data, &mut result);
+    return result;
+}
+
+/// Returns the seed for the DEEP point z and composition coefficients; without a mask, this is
+/// the root of the constraint tree.
+pub fn get_deep_seed(constraint_root: &[u8; 32], mask_root: Option<&[u8; 32]>, hash: HashFunction) -> [u8; 32] {
+    return match mask_root {
+        Some(mask_root) => {
+            let mut result = [0u8; 32];
+            hash.digest(&[&constraint_root[..], &mask_root[..]].concat(), &mut result);
+            result
+        },
+        None => *constraint_root,
+    };
+}
+
+// HELPER FUNCTIONS
+// ================================================================================================
+
+/// PRF from which all prover-side randomness is derived: blake3(seed || tag || index), where
+/// `index` is encoded as 8 little-endian bytes. Field elements are expanded from the result
+/// using `field::prng_vector()`.
+fn derive_seed(seed: &[u8; 32], tag: u8, index: u64) -> [u8; 32] {
+    let mut data = [0u8; 41];
+    data[..32].copy_from_slice(seed);
+    data[32] = tag;
+    data[33..].copy_from_slice(&index.to_le_bytes());
+    return *blake3::hash(&data).as_bytes();
+}
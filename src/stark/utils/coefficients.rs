@@ -2,7 +2,7 @@ use std::{ ops::Range };
 use crate::{
     math::field,
     utils::RangeSlider,
-    MAX_REGISTER_COUNT, MAX_PUBLIC_INPUTS,
+    MAX_REGISTER_COUNT,
     SPONGE_WIDTH,
     MAX_CONTEXT_DEPTH, MAX_LOOP_DEPTH, MAX_STACK_DEPTH,
     MIN_CONTEXT_DEPTH, MIN_LOOP_DEPTH, MIN_STACK_DEPTH,
@@ -13,14 +13,16 @@ use crate::{
 // CONSTANTS
 // ================================================================================================
 const NUM_OP_BITS: usize = NUM_CF_OP_BITS + NUM_LD_OP_BITS + NUM_HD_OP_BITS;
-const MAX_USER_STACK_IO_CONSTRAINTS: usize = MAX_PUBLIC_INPUTS; // same as MAX_OUTPUTS
+const MAX_USER_STACK_IO_CONSTRAINTS: usize = MAX_STACK_DEPTH;   // inputs and 0s, or outputs
 const NUM_BOUNDARY_CONSTRAINTS: usize =
     1   // for op_counter
     + SPONGE_WIDTH
     + NUM_OP_BITS
     + MAX_CONTEXT_DEPTH
     + MAX_LOOP_DEPTH
-    + MAX_USER_STACK_IO_CONSTRAINTS;
+    + MAX_USER_STACK_IO_CONSTRAINTS
+    + 1                 // for stack depth
+    + MAX_STACK_DEPTH;  // for assertions
 
 const NUM_TRANSITION_CONSTRAINTS: usize =
     0 
@@ -47,6 +49,8 @@ pub struct BoundaryCoefficients {
     pub ctx_stack   : [u128; MAX_CONTEXT_DEPTH * 2],
     pub loop_stack  : [u128; MAX_LOOP_DEPTH * 2],
     pub user_stack  : [u128; MAX_USER_STACK_IO_CONSTRAINTS * 2],
+    pub watermark   : [u128; 2],
+    pub assertions  : [u128; MAX_STACK_DEPTH * 2],
 }
 
 pub struct CompositionCoefficients {
@@ -55,6 +59,7 @@ pub struct CompositionCoefficients {
     pub t1_degree   : u128,
     pub t2_degree   : u128,
     pub constraints : u128,
+    pub mask        : u128,     // used only by zero-knowledge proofs
 }
 
 // IMPLEMENTATIONS
@@ -79,7 +84,7 @@ impl ConstraintCoefficients {
 impl CompositionCoefficients {
     pub fn new(seed: [u8; 32]) -> CompositionCoefficients {
         // generate a pseudo-random list of coefficients
-        let coefficients = field::prng_vector(seed, 1 + 4 * MAX_REGISTER_COUNT + 3);
+        let coefficients = field::prng_vector(seed, 1 + 4 * MAX_REGISTER_COUNT + 4);
 
         // skip the first value because it is used up by deep point z
         let start_index = 1;
@@ -98,8 +103,9 @@ impl CompositionCoefficients {
         let t1_degree = coefficients[index];
         let t2_degree = coefficients[index + 1];
         let constraints = coefficients[index + 2];
+        let mask = coefficients[index + 3];
 
-        return CompositionCoefficients { trace1, trace2, t1_degree, t2_degree, constraints };
+        return CompositionCoefficients { trace1, trace2, t1_degree, t2_degree, constraints, mask };
     }
 }
 
@@ -114,6 +120,8 @@ fn build_boundary_coefficients(coefficients: &[u128]) -> (BoundaryCoefficients,
         ctx_stack   : [0; MAX_CONTEXT_DEPTH * 2],
         loop_stack  : [0; MAX_LOOP_DEPTH * 2],
         user_stack  : [0; MAX_USER_STACK_IO_CONSTRAINTS * 2],
+        watermark   : [0; 2],
+        assertions  : [0; MAX_STACK_DEPTH * 2],
     };
 
     let mut range: Range<usize> = Range { start: 0, end: 2 };
@@ -134,6 +142,12 @@ fn build_boundary_coefficients(coefficients: &[u128]) -> (BoundaryCoefficients,
     range = range.slide(MAX_USER_STACK_IO_CONSTRAINTS * 2);
     result.user_stack.copy_from_slice(&coefficients[range.clone()]);
 
+    range = range.slide(2);
+    result.watermark.copy_from_slice(&coefficients[range.clone()]);
+
+    range = range.slide(MAX_STACK_DEPTH * 2);
+    result.assertions.copy_from_slice(&coefficients[range.clone()]);
+
     return (result, range.end);
 }
 
@@ -10,16 +10,31 @@ pub use coefficients::{ ConstraintCoefficients, CompositionCoefficients };
 mod proof_of_work;
 pub use proof_of_work::{ find_pow_nonce, verify_pow_nonce };
 
-pub fn get_composition_degree(trace_length: usize) -> usize {
-    return (MAX_CONSTRAINT_DEGREE - 1) * trace_length - 1;
+mod blinding;
+pub use blinding::{
+    get_prover_seed, get_trace_blinding, get_mask_coefficients, get_leaf_salt, hash_salted, get_deep_seed,
+    TRACE_SALTS, MASK_SALTS };
+
+/// Returns the degree of the composition polynomial for a trace of `trace_length` steps which
+/// contains `num_segments` program executions. Quotients of the highest-degree constraints fit
+/// into (MAX_CONSTRAINT_DEGREE - 1) * trace_length only when trace polynomials are not blinded
+/// and there are fewer than MAX_CONSTRAINT_DEGREE executions in the trace; otherwise, the degree
+/// is raised by one more trace_length.
+pub fn get_composition_degree(trace_length: usize, blinding_factor: usize, num_segments: usize) -> usize {
+    let slack = MAX_CONSTRAINT_DEGREE * blinding_factor + num_segments;
+    let factor = if slack < MAX_CONSTRAINT_DEGREE { MAX_CONSTRAINT_DEGREE - 1 } else { MAX_CONSTRAINT_DEGREE };
+    return factor * trace_length - 1;
 }
 
 // PUBLIC FUNCTIONS
 // ================================================================================================
 
-pub fn get_incremental_trace_degree(trace_length: usize) -> usize {
-    let composition_degree = get_composition_degree(trace_length);
-    return composition_degree - (trace_length - 2);
+/// Returns the degree by which the composition of trace polynomials must be raised to match
+/// the composition degree; each trace polynomial has `blinding_factor` random coefficients in
+/// addition to the `trace_length` coefficients interpolated from the trace.
+pub fn get_incremental_trace_degree(trace_length: usize, blinding_factor: usize, num_segments: usize) -> usize {
+    let composition_degree = get_composition_degree(trace_length, blinding_factor, num_segments);
+    return composition_degree - (trace_length + blinding_factor - 2);
 }
 
 pub fn compute_query_positions(seed: &[u8; 32], domain_size: usize, options: &ProofOptions) -> Vec<usize> {
@@ -1,18 +1,175 @@
+use crossbeam_utils::thread;
 use crate::{
     math::field,
     crypto::{ MerkleTree },
-    MIN_TRACE_LENGTH
+    MIN_TRACE_LENGTH, MIN_STACK_DEPTH, MAX_STACK_DEPTH, MAX_BATCH_SIZE, BASE_CYCLE_LENGTH
 };
-use super::{ StarkProof, TraceState, ConstraintEvaluator, CompositionCoefficients, fri, utils };
+use super::{
+    StarkProof, TraceState, TraceSegment, ConstraintEvaluator, EvaluatorCache, CompositionCoefficients, Assertion, fri, utils,
+    validate_assertions, hash_assertions, VerificationError, Commitment, PROTOCOL_VERSION, air_fingerprint,
+};
+
+// TYPES AND INTERFACES
+// ================================================================================================
+
+/// A proof together with the statement it is expected to attest to: executing a program with
+/// `program_hash` with `public_inputs` and some secret inputs produces `outputs`.
+#[derive(Copy, Clone)]
+pub struct VerificationItem<'a> {
+    pub program_hash    : &'a [u8; 32],
+    pub public_inputs   : &'a [u128],
+    pub outputs         : &'a [u128],
+    pub proof           : &'a StarkProof,
+}
+
+// VERIFIER FUNCTIONS
+// ================================================================================================
+
+pub fn verify(program_hash: &[u8; 32], inputs: &[u128], outputs: &[u128], assertions: &[Assertion], proof: &StarkProof) -> Result<(), VerificationError>
+{
+    return verify_with_cache(program_hash, inputs, outputs, assertions, proof, &EvaluatorCache::new());
+}
+
+/// Verifies every item in the same way as `verify()` does, and returns the result for each
+/// item. Items are verified in parallel, and constraint evaluators are shared between proofs
+/// of executions with the same trace parameters.
+pub fn verify_many(items: &[VerificationItem]) -> Vec<Result<(), VerificationError>>
+{
+    let cache = EvaluatorCache::new();
+    let num_threads = std::thread::available_parallelism().map(|n| n.get()).unwrap_or(1);
+    let chunk_size = std::cmp::max(1, (items.len() + num_threads - 1) / num_threads);
+
+    return thread::scope(|s| {
+        let cache = &cache;
+        let handles: Vec<_> = items.chunks(chunk_size)
+            .map(|chunk| s.spawn(move |_| chunk.iter()
+                .map(|item| verify_with_cache(item.program_hash, item.public_inputs, item.outputs, &[], item.proof, cache))
+                .collect::<Vec<_>>()))
+            .collect();
+        handles.into_iter().flat_map(|handle| handle.join().unwrap()).collect()
+    }).unwrap();
+}
+
+fn verify_with_cache(program_hash: &[u8; 32], inputs: &[u128], outputs: &[u128], assertions: &[Assertion], proof: &StarkProof, cache: &EvaluatorCache) -> Result<(), VerificationError>
+{
+    check_version(proof)?;
+    if !proof.segments().is_empty() {
+        return Err(VerificationError::StatementMismatch(String::from(
+            "verification of execution count failed: proof attests to a batch of executions")));
+    }
+
+    // a single program execution spans the entire execution trace
+    let segment = TraceSegment {
+        first_step  : 0,
+        last_step   : proof.trace_length() - 1,
+        inputs      : inputs.to_vec(),
+        outputs     : outputs.to_vec(),
+        op_count    : proof.op_count(),
+        final_depth : proof.final_depth() as u128,
+    };
+    return verify_segments(program_hash, &[segment], assertions, proof, cache);
+}
+
+/// Verifies a proof of several executions of the same program; `statement` contains public
+/// inputs and outputs of every execution in the order in which the executions were proven.
+pub fn verify_batch(program_hash: &[u8; 32], statement: &[(Vec<u128>, Vec<u128>)], proof: &StarkProof) -> Result<(), VerificationError>
+{
+    check_version(proof)?;
+
+    // a batch of a single execution is proven in the same way as a single execution
+    let infos = proof.segments();
+    if infos.is_empty() && statement.len() == 1 {
+        let (inputs, outputs) = &statement[0];
+        return verify(program_hash, inputs, outputs, &[], proof);
+    }
+    else if infos.is_empty() {
+        return Err(VerificationError::StatementMismatch(String::from(
+            "verification of execution count failed: proof attests to a single execution")));
+    }
+
+    if infos.len() > MAX_BATCH_SIZE || infos.len() != statement.len() {
+        return Err(VerificationError::StatementMismatch(format!(
+            "verification of execution count failed: proof attests to {} executions, but {} were provided",
+            infos.len(), statement.len())));
+    }
+
+    let num_outputs = statement[0].1.len();
+    if statement.iter().any(|(_, outputs)| outputs.len() != num_outputs) {
+        return Err(VerificationError::StatementMismatch(String::from(
+            "verification of outputs failed: all executions must have the same number of outputs")));
+    }
 
-// VERIFIER FUNCTION
+    // executions must be aligned on cycle boundaries and must cover the entire execution trace
+    let mut segments = Vec::with_capacity(infos.len());
+    let mut first_step = 0;
+    for (info, (inputs, outputs)) in infos.iter().zip(statement.iter()) {
+        let length = info.length as usize;
+        if length < MIN_TRACE_LENGTH || length % BASE_CYCLE_LENGTH != 0 {
+            return Err(VerificationError::MalformedProof(format!(
+                "verification of execution length failed: execution of {} steps is invalid", length)));
+        }
+
+        segments.push(TraceSegment {
+            first_step  : first_step,
+            last_step   : first_step + length - 1,
+            inputs      : inputs.clone(),
+            outputs     : outputs.clone(),
+            op_count    : info.op_count as u128,
+            final_depth : info.final_depth as u128,
+        });
+        first_step += length;
+    }
+
+    if first_step != proof.trace_length() {
+        return Err(VerificationError::MalformedProof(format!(
+            "verification of execution length failed: executions span {} steps, but the trace has {} steps",
+            first_step, proof.trace_length())));
+    }
+
+    return verify_segments(program_hash, &segments, &[], proof, &EvaluatorCache::new());
+}
+
+// HELPER FUNCTIONS
 // ================================================================================================
+/// Makes sure the proof was generated under the same protocol version and for the same AIR
+/// layout as the ones used by this verifier; this is checked before any other part of the proof.
+pub fn check_version(proof: &StarkProof) -> Result<(), VerificationError> {
+    if proof.version() != PROTOCOL_VERSION {
+        return Err(VerificationError::VersionMismatch { proof: proof.version(), supported: PROTOCOL_VERSION });
+    }
+    if proof.air_fingerprint() != &air_fingerprint() {
+        return Err(VerificationError::MalformedProof(String::from(
+            "verification of AIR fingerprint failed: proof was generated for a different execution trace layout")));
+    }
+    return Ok(());
+}
 
-pub fn verify(program_hash: &[u8; 32], inputs: &[u128], outputs: &[u128], proof: &StarkProof) -> Result<bool, String>
+fn verify_segments(program_hash: &[u8; 32], segments: &[TraceSegment], assertions: &[Assertion], proof: &StarkProof, cache: &EvaluatorCache) -> Result<(), VerificationError>
 {
+    // options come from the prover, and so they must be validated before they are used
     let options = proof.options();
+    if let Err(msg) = options.validate() {
+        return Err(VerificationError::MalformedProof(format!(
+            "verification of proof options failed: {}", msg)));
+    }
     let hash_fn = options.hash_fn();
 
+    // zero-knowledge proofs carry a salt and a mask evaluation for every queried trace state
+    match proof.zk_values() {
+        Some(zk) if options.zero_knowledge() => {
+            let num_queries = proof.trace_evaluations().len();
+            if zk.trace_salts.len() != num_queries || zk.mask_salts.len() != num_queries
+                || zk.mask_evaluations.len() != num_queries
+            {
+                return Err(VerificationError::MalformedProof(String::from(
+                    "verification of zero-knowledge values failed: number of values does not match number of queries")));
+            }
+        },
+        None if !options.zero_knowledge() => (),
+        _ => return Err(VerificationError::MalformedProof(String::from(
+            "verification of zero-knowledge values failed: values are inconsistent with proof options"))),
+    }
+
     // 1 ----- Verify proof of work and determine query positions ---------------------------------
     let degree_proof = proof.degree_proof();
     let mut fri_roots: Vec<u8> = Vec::new();
@@ -22,70 +179,121 @@ pub fn verify(program_hash: &[u8; 32], inputs: &[u128], outputs: &[u128], proof:
     degree_proof.rem_root.iter().for_each(|&v| fri_roots.push(v));
 
     let mut seed = [0u8; 32];
-    hash_fn(&fri_roots, &mut seed);
+    hash_fn.digest(&fri_roots, &mut seed);
     let seed = match utils::verify_pow_nonce(seed, proof.pow_nonce(), &options) {
         Ok(seed) => seed,
-        Err(msg) => return Err(msg)
+        Err(_) => return Err(VerificationError::ProofOfWorkFailed)
     };
 
     let t_positions = utils::compute_query_positions(&seed, proof.domain_size(), options);
     let c_positions = utils::map_trace_to_constraint_positions(&t_positions);
 
-    // 2 ----- Verify number of operations in the program -----------------------------------------
-    if proof.op_count() < MIN_TRACE_LENGTH as u128 {
-        return Err(String::from("Verification of minimum operation count failed"));
+    // 2 ----- Verify number of operations and boundary assertions --------------------------------
+    // the trace must be padded to the minimum length the proof was generated for
+    if options.min_trace_length() < MIN_TRACE_LENGTH || proof.trace_length() < options.min_trace_length() {
+        return Err(VerificationError::MalformedProof(format!(
+            "verification of trace length failed: trace of {} steps is shorter than {} steps",
+            proof.trace_length(), options.min_trace_length())));
+    }
+
+    for segment in segments.iter() {
+        if segment.op_count < MIN_TRACE_LENGTH as u128 {
+            return Err(VerificationError::MalformedProof(String::from(
+                "Verification of minimum operation count failed")));
+        }
+
+        // user stack must be wide enough to hold all public inputs; beyond that, any width is fine
+        if proof.stack_depth() < segment.inputs.len() || proof.stack_depth() > MAX_STACK_DEPTH {
+            return Err(VerificationError::MalformedProof(format!(
+                "verification of stack depth failed: stack depth {} is invalid for {} inputs",
+                proof.stack_depth(), segment.inputs.len())));
+        }
+
+        // outputs beyond the minimum stack depth must be backed by user stack registers
+        if segment.outputs.len() > std::cmp::max(proof.stack_depth(), MIN_STACK_DEPTH) {
+            return Err(VerificationError::StatementMismatch(format!(
+                "verification of outputs failed: {} outputs exceed stack depth {}",
+                segment.outputs.len(), proof.stack_depth())));
+        }
+
+        if segment.final_depth > proof.stack_depth() as u128 {
+            return Err(VerificationError::MalformedProof(format!(
+                "verification of stack depth failed: final depth {} exceeds stack depth {}",
+                segment.final_depth, proof.stack_depth())));
+        }
+    }
+
+    // make sure the proof was generated against the same set of boundary assertions
+    if let Err(msg) = validate_assertions(assertions, proof.stack_depth()) {
+        return Err(VerificationError::StatementMismatch(format!(
+            "verification of boundary assertions failed: {}", msg)));
+    }
+
+    if hash_assertions(assertions, hash_fn) != *proof.assertion_digest() {
+        return Err(VerificationError::StatementMismatch(String::from(
+            "verification of boundary assertions failed: assertions do not match the proof")));
     }
 
     // 3 ----- Verify trace and constraint Merkle proofs ------------------------------------------
     if !MerkleTree::verify_batch(proof.trace_root(), &t_positions, &proof.trace_proof(), hash_fn) {
-        return Err(String::from("verification of trace Merkle proof failed"));
+        return Err(VerificationError::MerkleProofFailed(Commitment::Trace));
     }
 
     if !MerkleTree::verify_batch(proof.constraint_root(), &c_positions, &proof.constraint_proof(), hash_fn) {
-        return Err(String::from("verification of constraint Merkle proof failed"));
+        return Err(VerificationError::MerkleProofFailed(Commitment::Constraints));
+    }
+
+    if let Some(zk) = proof.zk_values() {
+        if !MerkleTree::verify_batch(&zk.mask_root, &t_positions, &proof.mask_proof(), hash_fn) {
+            return Err(VerificationError::MerkleProofFailed(Commitment::Mask));
+        }
     }
 
     // 4 ----- Compute constraint evaluations at DEEP point z -------------------------------------
-    // derive DEEP point z from the root of the constraint tree
-    let z = field::prng(*proof.constraint_root());
+    // derive DEEP point z from the root of the constraint tree (and the root of the mask tree
+    // for zero-knowledge proofs)
+    let deep_seed = proof.deep_seed();
+    let z = field::prng(deep_seed);
 
     // evaluate constraints at z
     let constraint_evaluation_at_z = evaluate_constraints(
-        ConstraintEvaluator::from_proof(proof, program_hash, inputs, outputs),
+        ConstraintEvaluator::from_proof(proof, program_hash, segments, assertions, cache),
         proof.get_state_at_z1(),
         proof.get_state_at_z2(),
         z
     );
 
     // 5 ----- Compute composition polynomial evaluations -----------------------------------------
-    // derive coefficient for linear combination from the root of constraint tree
-    let coefficients = CompositionCoefficients::new(*proof.constraint_root());
+    // derive coefficient for linear combination from the same seed as z
+    let coefficients = CompositionCoefficients::new(deep_seed);
 
     // compute composition values separately for trace and constraints, and then add them together
-    let t_composition = compose_registers(&proof, &t_positions, z, &coefficients);
+    let t_composition = compose_registers(&proof, segments.len(), &t_positions, z, &coefficients);
     let c_composition = compose_constraints(&proof, &t_positions, &c_positions, z, constraint_evaluation_at_z, &coefficients);
-    let evaluations = t_composition.iter().zip(c_composition).map(|(&t, c)| field::add(t, c)).collect::<Vec<u128>>();
+    let mut evaluations = t_composition.iter().zip(c_composition).map(|(&t, c)| field::add(t, c)).collect::<Vec<u128>>();
+    if proof.is_zero_knowledge() {
+        let m_composition = compose_mask(&proof, &t_positions, z, &coefficients);
+        evaluations.iter_mut().zip(m_composition).for_each(|(e, m)| *e = field::add(*e, m));
+    }
     
     // 6 ----- Verify low-degree proof -------------------------------------------------------------
-    let max_degree = utils::get_composition_degree(proof.trace_length());
-    return match fri::verify(&degree_proof, &evaluations, &t_positions, max_degree, options) {
-        Ok(result) => Ok(result),
-        Err(msg) => Err(format!("verification of low-degree proof failed: {}", msg))
-    }
+    let max_degree = utils::get_composition_degree(
+        proof.trace_length(), options.blinding_factor(), segments.len());
+    return fri::verify(&degree_proof, &evaluations, &t_positions, proof.domain_size(), max_degree, options);
 }
 
-// HELPER FUNCTIONS
-// ================================================================================================
 fn evaluate_constraints(evaluator: ConstraintEvaluator, state1: TraceState, state2: TraceState, x: u128) -> u128 {
     let (i_value, f_value) = evaluator.evaluate_boundaries(&state1, x);
     let t_value = evaluator.evaluate_transition_at(&state1, &state2, x);
 
-    // Z(x) = x - 1
-    let z = field::sub(x, field::ONE);
+    // Z(x) = x - 1; for several program executions, Z(x) = (x - x_at_first_step) for all first steps
+    let z = evaluator.get_x_at_first_steps().iter()
+        .fold(field::ONE, |z, &x_at_first_step| field::mul(z, field::sub(x, x_at_first_step)));
     let mut result = field::div(i_value, z);
 
-    // Z(x) = x - x_at_last_step
-    let z = field::sub(x, evaluator.get_x_at_last_step());
+    // Z(x) = x - x_at_last_step; for several program executions, there is a factor for each last step
+    let z = evaluator.get_x_at_last_steps().iter()
+        .fold(field::ONE, |z, &x_at_last_step| field::mul(z, field::sub(x, x_at_last_step)));
     result = field::add(result, field::div(f_value, z));
 
     // Z(x) = (x^steps - 1) / (x - x_at_last_step)
@@ -95,7 +303,7 @@ fn evaluate_constraints(evaluator: ConstraintEvaluator, state1: TraceState, stat
     return result;
 }
 
-fn compose_registers(proof: &StarkProof, positions: &[usize], z: u128, cc: &CompositionCoefficients) -> Vec<u128>
+fn compose_registers(proof: &StarkProof, num_segments: usize, positions: &[usize], z: u128, cc: &CompositionCoefficients) -> Vec<u128>
 {    
     let lde_root = field::get_root_of_unity(proof.domain_size());
     let trace_root = field::get_root_of_unity(proof.trace_length());
@@ -105,7 +313,8 @@ fn compose_registers(proof: &StarkProof, positions: &[usize], z: u128, cc: &Comp
     let trace_at_z2 = proof.get_state_at_z2().to_vec();
     let evaluations = proof.trace_evaluations();
 
-    let incremental_degree = utils::get_incremental_trace_degree(proof.trace_length()) as u128;
+    let incremental_degree = utils::get_incremental_trace_degree(
+        proof.trace_length(), proof.options().blinding_factor(), num_segments) as u128;
 
     let mut result = Vec::with_capacity(evaluations.len());
     for (registers, &position) in evaluations.into_iter().zip(positions) {
@@ -160,4 +369,22 @@ fn compose_constraints(proof: &StarkProof, t_positions: &[usize], c_positions: &
     }
 
     return result;
-}
\ No newline at end of file
+}
+
+fn compose_mask(proof: &StarkProof, positions: &[usize], z: u128, cc: &CompositionCoefficients) -> Vec<u128> {
+    let zk = proof.zk_values().unwrap();
+    let lde_root = field::get_root_of_unity(proof.domain_size());
+
+    // divide out deep point from the evaluations
+    let mut result = Vec::with_capacity(positions.len());
+    for (&evaluation, &position) in zk.mask_evaluations.iter().zip(positions) {
+        let x = field::exp(lde_root, position as u128);
+
+        // compute M(x) = (P(x) - P(z)) / (x - z)
+        let composition = field::div(field::sub(evaluation, zk.mask_at_z), field::sub(x, z));
+        // multiply by pseudo-random coefficient for linear combination
+        result.push(field::mul(composition, cc.mask));
+    }
+
+    return result;
+}
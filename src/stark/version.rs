@@ -0,0 +1,55 @@
+use super::{ MAX_CONSTRAINT_DEGREE, constraints::{ NUM_STATIC_DECODER_CONSTRAINTS, NUM_AUX_STACK_CONSTRAINTS } };
+
+// CONSTANTS
+// ================================================================================================
+
+/// Version of the proving protocol. Proofs generated under one version of the protocol cannot
+/// be verified under another, and so this must be incremented whenever the layout of the
+/// execution trace, the constraints, or the way the prover commits to values change. The test
+/// suite records the AIR fingerprint (see `air_fingerprint()`) of every version, and fails if
+/// layout constants change without a version bump.
+pub const PROTOCOL_VERSION: u32 = 4;
+
+// PUBLIC FUNCTIONS
+// ================================================================================================
+
+/// Returns a digest of the constants which define the layout of the execution trace and the
+/// structure of the constraints (register counts and ranges, cycle lengths, hash parameters,
+/// constraint counts, etc.). Every proof carries the fingerprint of the prover which generated
+/// it, and proofs with a fingerprint different from the one of the verifier are rejected.
+pub fn air_fingerprint() -> [u8; 32] {
+    let constants = [
+        // execution trace
+        crate::MIN_TRACE_LENGTH, crate::MAX_REGISTER_COUNT, crate::MIN_EXTENSION_FACTOR,
+        crate::BASE_CYCLE_LENGTH, crate::PUSH_OP_ALIGNMENT,
+
+        // stacks
+        crate::MIN_STACK_DEPTH, crate::MAX_STACK_DEPTH, crate::MIN_CONTEXT_DEPTH,
+        crate::MAX_CONTEXT_DEPTH, crate::MIN_LOOP_DEPTH, crate::MAX_LOOP_DEPTH,
+        crate::MAX_PUBLIC_INPUTS, crate::MAX_OUTPUTS, crate::MAX_BATCH_SIZE, crate::NUM_DEPTH_BITS,
+
+        // hash functions
+        crate::HASH_STATE_RATE, crate::HASH_STATE_CAPACITY, crate::HASH_NUM_ROUNDS,
+        crate::HASH_DIGEST_SIZE, crate::SPONGE_WIDTH, crate::PROGRAM_DIGEST_SIZE,
+        crate::HACC_NUM_ROUNDS,
+
+        // decoder layout
+        crate::NUM_CF_OP_BITS, crate::NUM_LD_OP_BITS, crate::NUM_HD_OP_BITS,
+        crate::NUM_CF_OPS, crate::NUM_LD_OPS, crate::NUM_HD_OPS, crate::NUM_LD_OP_PRODS,
+        crate::OP_COUNTER_IDX,
+        crate::SPONGE_RANGE.start, crate::SPONGE_RANGE.end,
+        crate::CF_OP_BITS_RANGE.start, crate::CF_OP_BITS_RANGE.end,
+        crate::LD_OP_BITS_RANGE.start, crate::LD_OP_BITS_RANGE.end,
+        crate::HD_OP_BITS_RANGE.start, crate::HD_OP_BITS_RANGE.end,
+        crate::LD_OP_PRODS_RANGE.start, crate::LD_OP_PRODS_RANGE.end,
+
+        // constraints
+        MAX_CONSTRAINT_DEGREE, NUM_STATIC_DECODER_CONSTRAINTS, NUM_AUX_STACK_CONSTRAINTS,
+    ];
+
+    let mut data = Vec::with_capacity(constants.len() * 8);
+    for &value in constants.iter() {
+        data.extend_from_slice(&(value as u64).to_le_bytes());
+    }
+    return *blake3::hash(&data).as_bytes();
+}
@@ -0,0 +1,121 @@
+use std::{ cell::RefCell, collections::{ HashMap, HashSet }, sync::Mutex };
+use crate::processor::{ OpCode, opcodes::USER_OPS };
+
+// CONSTANTS
+// ================================================================================================
+
+/// All control flow paths which can be taken by the VM.
+const FLOW_PATHS: [FlowPath; 6] = [
+    FlowPath::TrueBranch, FlowPath::FalseBranch,
+    FlowPath::LoopEnter,  FlowPath::LoopWrap,   FlowPath::LoopExit,  FlowPath::LoopSkip,
+];
+
+// TYPES AND INTERFACES
+// ================================================================================================
+
+/// Control flow path taken while executing Switch and Loop blocks.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub enum FlowPath {
+    TrueBranch,
+    FalseBranch,
+    LoopEnter,
+    LoopWrap,
+    LoopExit,
+    LoopSkip,
+}
+
+/// A single item tracked by the coverage collector.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+enum CoveragePoint {
+    Op(u8),
+    Flow(FlowPath),
+}
+
+/// Lists of operations and control flow paths for which proofs were and were not verified.
+#[derive(Clone, Debug)]
+pub struct CoverageReport {
+    pub covered     : Vec<String>,
+    pub uncovered   : Vec<String>,
+}
+
+// COLLECTOR STATE
+// ================================================================================================
+
+thread_local! {
+    // points hit during the most recent execution on the current thread
+    static EXECUTED: RefCell<HashSet<CoveragePoint>> = RefCell::new(HashSet::new());
+}
+
+// points hit during executions for which proofs were generated, keyed by program hash
+static PROVEN: Mutex<Option<HashMap<[u8; 32], HashSet<CoveragePoint>>>> = Mutex::new(None);
+
+// points hit during executions for which proofs were successfully verified
+static VERIFIED: Mutex<Option<HashSet<CoveragePoint>>> = Mutex::new(None);
+
+// TRACER FUNCTIONS
+// ================================================================================================
+
+/// Clears the set of points hit on the current thread; called when program execution starts.
+pub fn start_execution() {
+    EXECUTED.with(|executed| executed.borrow_mut().clear());
+}
+
+/// Records execution of the specified user operation.
+pub fn record_op(op_code: OpCode) {
+    EXECUTED.with(|executed| executed.borrow_mut().insert(CoveragePoint::Op(op_code as u8)));
+}
+
+/// Records that the specified control flow path was taken.
+pub fn record_flow(path: FlowPath) {
+    EXECUTED.with(|executed| executed.borrow_mut().insert(CoveragePoint::Flow(path)));
+}
+
+/// Marks points hit during the most recent execution on the current thread as proven for
+/// a program with the specified hash.
+pub fn record_proof(program_hash: &[u8; 32]) {
+    let points = EXECUTED.with(|executed| executed.replace(HashSet::new()));
+    let mut proven = PROVEN.lock().unwrap();
+    proven.get_or_insert_with(HashMap::new)
+        .entry(*program_hash)
+        .or_insert_with(HashSet::new)
+        .extend(points);
+}
+
+/// Marks all points proven for a program with the specified hash as covered.
+pub fn record_verification(program_hash: &[u8; 32]) {
+    let points = match PROVEN.lock().unwrap().as_mut() {
+        Some(proven) => proven.remove(program_hash).unwrap_or_default(),
+        None => return,
+    };
+    VERIFIED.lock().unwrap().get_or_insert_with(HashSet::new).extend(points);
+}
+
+// REPORTING
+// ================================================================================================
+
+/// Returns a report listing operations and control flow paths which were and were not covered
+/// by verified proofs.
+pub fn report() -> CoverageReport {
+    let verified = VERIFIED.lock().unwrap().clone().unwrap_or_default();
+
+    let points = USER_OPS.iter().map(|&op| (CoveragePoint::Op(op as u8), op.to_string()))
+        .chain(FLOW_PATHS.iter().map(|&path| (CoveragePoint::Flow(path), format!("{:?}", path))));
+
+    let mut result = CoverageReport { covered: Vec::new(), uncovered: Vec::new() };
+    for (point, name) in points {
+        if verified.contains(&point) {
+            result.covered.push(name);
+        }
+        else {
+            result.uncovered.push(name);
+        }
+    }
+    return result;
+}
+
+impl std::fmt::Display for CoverageReport {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        writeln!(f, "covered ({}): {}", self.covered.len(), self.covered.join(", "))?;
+        write!(f, "uncovered ({}): {}", self.uncovered.len(), self.uncovered.join(", "))
+    }
+}
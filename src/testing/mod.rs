@@ -0,0 +1,8 @@
+//! Utilities for testing the VM itself; available in tests of this crate and when the `coverage`
+//! feature is enabled.
+
+#[cfg(feature = "coverage")]
+pub mod coverage;
+
+mod shrink;
+pub use shrink::shrink;
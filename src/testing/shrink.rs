@@ -0,0 +1,315 @@
+use std::collections::HashMap;
+use crate::{
+    Program, ProgramInputs, OpCode, OpHint,
+    blocks::{ ProgramBlock, Span, Group, Switch, Loop },
+};
+
+// CONSTANTS
+// ================================================================================================
+
+/// Number of operations removed from a span at once; removing a multiple of 16 operations at
+/// a position which is a multiple of 8 keeps span length and PUSH alignment valid.
+const REMOVED_OPS_COUNT: usize = 16;
+const REMOVED_OPS_ALIGNMENT: usize = 8;
+
+// TYPES AND INTERFACES
+// ================================================================================================
+
+/// A single simplification of a failing case; sites of each kind are numbered in the order in
+/// which they are visited by a depth-first traversal of the program.
+#[derive(Copy, Clone, Debug, PartialEq)]
+enum Edit {
+    DropBlock(usize),
+    DropOps(usize),
+    TruncateTape(usize, usize),
+    ReplaceOp(usize),
+    SetPushValue(usize, u128),
+}
+
+/// Applies a single edit to program blocks, and counts visited sites along the way.
+struct Rewriter {
+    edit        : Edit,
+    counts      : [usize; 4],
+    push_values : Vec<u128>,
+}
+
+// PUBLIC FUNCTIONS
+// ================================================================================================
+
+/// Repeatedly simplifies a failing `program` and its `inputs` while the `predicate` still holds,
+/// and returns the simplest case found.
+///
+/// Simplifications are tried from the coarsest to the finest: dropping blocks, dropping groups of
+/// operations from spans, truncating input tapes, replacing operations with NOOPs, and shrinking
+/// PUSH values toward 0 and 1. Every candidate is a valid program, and candidates are always
+/// tried in the same order, so the result depends only on the original case and the predicate.
+pub fn shrink<P>(program: &Program, inputs: &ProgramInputs, predicate: P) -> (Program, ProgramInputs)
+    where P: Fn(&Program, &ProgramInputs) -> bool
+{
+    assert!(predicate(program, inputs), "predicate does not hold for the original program");
+
+    let mut program = program.clone();
+    let mut inputs = inputs.clone();
+    'outer: loop {
+        for edit in list_edits(&program, &inputs) {
+            let (candidate, candidate_inputs) = apply_edit(&program, &inputs, edit);
+            if predicate(&candidate, &candidate_inputs) {
+                program = candidate;
+                inputs = candidate_inputs;
+                continue 'outer;
+            }
+        }
+        return (program, inputs);
+    }
+}
+
+// HELPER FUNCTIONS
+// ================================================================================================
+
+/// Returns all edits applicable to the program and inputs, ordered from the coarsest.
+fn list_edits(program: &Program, inputs: &ProgramInputs) -> Vec<Edit> {
+    let mut counter = Rewriter::counter();
+    counter.rewrite_blocks(program.root().body());
+    let [num_blocks, num_op_groups, num_ops, _] = counter.counts;
+
+    let mut edits = Vec::new();
+    edits.extend((0..num_blocks).map(|i| Edit::DropBlock(i)));
+    edits.extend((0..num_op_groups).rev().map(|i| Edit::DropOps(i)));
+
+    // the primary tape must be at least as long as the secondary tape
+    let [tape_a, tape_b] = inputs.get_secret_inputs();
+    for &(tape, length, min_length) in [(0, tape_a.len(), tape_b.len()), (1, tape_b.len(), 0)].iter() {
+        let mut lengths = vec![min_length, (length + min_length) / 2, length.saturating_sub(1)];
+        lengths.dedup();
+        edits.extend(lengths.into_iter()
+            .filter(|&l| l >= min_length && l < length)
+            .map(|l| Edit::TruncateTape(tape, l)));
+    }
+
+    edits.extend((0..num_ops).map(|i| Edit::ReplaceOp(i)));
+
+    for (i, &value) in counter.push_values.iter().enumerate() {
+        let mut values = vec![0, 1, value / 2];
+        values.dedup();
+        edits.extend(values.into_iter().filter(|&v| v < value).map(|v| Edit::SetPushValue(i, v)));
+    }
+
+    return edits;
+}
+
+fn apply_edit(program: &Program, inputs: &ProgramInputs, edit: Edit) -> (Program, ProgramInputs) {
+    if let Edit::TruncateTape(tape, length) = edit {
+        let mut tapes = inputs.get_secret_inputs().clone();
+        tapes[tape].truncate(length);
+        let inputs = ProgramInputs::new(inputs.get_public_inputs(), &tapes[0], &tapes[1]);
+        return (program.clone(), inputs);
+    }
+
+    let mut rewriter = Rewriter { edit, counts: [0; 4], push_values: Vec::new() };
+    let body = rewriter.rewrite_blocks(program.root().body());
+    return (Program::new(Group::new(body)), inputs.clone());
+}
+
+// REWRITER IMPLEMENTATION
+// ================================================================================================
+impl Rewriter {
+
+    /// Returns a rewriter which does not change anything and only counts sites.
+    fn counter() -> Rewriter {
+        return Rewriter { edit: Edit::TruncateTape(0, 0), counts: [0; 4], push_values: Vec::new() };
+    }
+
+    /// Returns true if the next site of the specified kind is the target of the edit.
+    fn hit(&mut self, kind: usize) -> bool {
+        let index = self.counts[kind];
+        self.counts[kind] += 1;
+        return match self.edit {
+            Edit::DropBlock(i)          => kind == 0 && i == index,
+            Edit::DropOps(i)            => kind == 1 && i == index,
+            Edit::ReplaceOp(i)          => kind == 2 && i == index,
+            Edit::SetPushValue(i, _)    => kind == 3 && i == index,
+            Edit::TruncateTape(_, _)    => false,
+        };
+    }
+
+    fn rewrite_blocks(&mut self, blocks: &[ProgramBlock]) -> Vec<ProgramBlock> {
+        let mut result: Vec<ProgramBlock> = Vec::with_capacity(blocks.len());
+        for (i, block) in blocks.iter().enumerate() {
+            // the first block of a sequence must be a span, and so it is never dropped
+            if i > 0 && self.hit(0) {
+                continue;
+            }
+
+            let block = match block {
+                ProgramBlock::Span(span) => ProgramBlock::Span(self.rewrite_span(span, i == 0)),
+                ProgramBlock::Group(group) => Group::new_block(self.rewrite_blocks(group.body())),
+                ProgramBlock::Switch(switch) => {
+                    let true_branch = self.rewrite_blocks(switch.true_branch());
+                    let false_branch = self.rewrite_blocks(switch.false_branch());
+                    Switch::new_block(true_branch, false_branch)
+                },
+                ProgramBlock::Loop(block) => match block.max_iterations() {
+                    Some(n) => Loop::new_bounded_block(self.rewrite_blocks(block.body()), n),
+                    None    => Loop::new_block(self.rewrite_blocks(block.body())),
+                },
+            };
+
+            // a span cannot follow another span; this happens when a block between them is dropped
+            match (result.last(), &block) {
+                (Some(ProgramBlock::Span(prev)), ProgramBlock::Span(span)) => {
+                    let merged = Span::merge(prev, span);
+                    *result.last_mut().unwrap() = ProgramBlock::Span(merged);
+                },
+                _ => result.push(block),
+            }
+        }
+        return result;
+    }
+
+    fn rewrite_span(&mut self, span: &Span, is_first: bool) -> Span {
+        let mut op_codes = Vec::with_capacity(span.length());
+        let mut hints = HashMap::new();
+        for i in 0..span.length() {
+            let (op_code, op_hint) = span.get_op(i);
+            op_codes.push(op_code);
+            if op_hint.kind().is_some() {
+                hints.insert(i, op_hint);
+            }
+        }
+
+        // operations which start a program or a branch must stay in place
+        let prefix_length = if !is_first { 0 } else if op_codes[0] == OpCode::Not { 2 } else { 1 };
+
+        for i in prefix_length..op_codes.len() {
+            if op_codes[i] == OpCode::Noop { continue; }
+            if self.hit(2) {
+                op_codes[i] = OpCode::Noop;
+                hints.remove(&i);
+            }
+            else if let Some(&OpHint::PushValue(value)) = hints.get(&i) {
+                self.push_values.push(value);
+                if self.hit(3) {
+                    if let Edit::SetPushValue(_, new_value) = self.edit {
+                        hints.insert(i, OpHint::PushValue(new_value));
+                    }
+                }
+            }
+        }
+
+        let mut start = REMOVED_OPS_ALIGNMENT;
+        while start + REMOVED_OPS_COUNT <= op_codes.len() {
+            if self.hit(1) {
+                let end = start + REMOVED_OPS_COUNT;
+                op_codes.drain(start..end);
+                hints = hints.into_iter()
+                    .filter(|(step, _)| *step < start || *step >= end)
+                    .map(|(step, hint)| (if step >= end { step - REMOVED_OPS_COUNT } else { step }, hint))
+                    .collect();
+                break;
+            }
+            start += REMOVED_OPS_ALIGNMENT;
+        }
+
+        return Span::new(op_codes, hints).unwrap();
+    }
+}
+
+// TESTS
+// ================================================================================================
+#[cfg(test)]
+mod tests {
+
+    use std::panic;
+    use rand::{ SeedableRng, rngs::StdRng };
+    use crate::{ Program, ProgramInputs, OpCode, assembly, processor };
+    use crate::blocks::ProgramBlock;
+    use crate::processor::reference::build_random_program;
+    use crate::stark::{ TraceTable, ConstraintEvaluator, BROKEN_MUL, EVALUATION_BLOWUP };
+    use super::shrink;
+
+    #[test]
+    fn shrink_random_program() {
+        BROKEN_MUL.with(|b| b.set(true));
+
+        // find a random program of 207 operations which fails because of the broken constraint
+        let (program, inputs) = (0..1000)
+            .map(|seed| build_random_program(&mut StdRng::seed_from_u64(seed), 13..14, 0.0))
+            .find(|(program, inputs)| violates_constraints(program, inputs))
+            .expect("none of the first 1000 random programs violates the broken constraint");
+        assert!(count_ops(&program) > 150);
+
+        let (shrunk, shrunk_inputs) = shrink(&program, &inputs, violates_constraints);
+        assert!(count_ops(&shrunk) < 20, "shrunk program is too long: {:?}", shrunk);
+        assert!(violates_constraints(&shrunk, &shrunk_inputs));
+
+        // shrinking is deterministic
+        let (shrunk2, shrunk_inputs2) = shrink(&program, &inputs, violates_constraints);
+        assert_eq!(shrunk.hash(), shrunk2.hash());
+        assert_eq!(format!("{:?}", shrunk_inputs), format!("{:?}", shrunk_inputs2));
+
+        // the shrunk program is valid with correct constraints
+        BROKEN_MUL.with(|b| b.set(false));
+        assert!(!violates_constraints(&shrunk, &shrunk_inputs));
+    }
+
+    #[test]
+    fn shrink_program_with_branches() {
+        BROKEN_MUL.with(|b| b.set(true));
+
+        let program = assembly::compile("
+            begin
+                push.3 push.4 add read
+                if.true
+                    push.7 dup mul
+                else
+                    push.5 push.0 mul
+                end
+                push.2 push.2 add
+                repeat.4 push.3 mul end
+            end").unwrap();
+        let inputs = ProgramInputs::new(&[1], &[0, 1, 1], &[]);
+        assert!(violates_constraints(&program, &inputs));
+
+        let (shrunk, shrunk_inputs) = shrink(&program, &inputs, violates_constraints);
+        assert!(count_ops(&shrunk) < count_ops(&program) / 2, "program was not shrunk: {:?}", shrunk);
+        assert!(violates_constraints(&shrunk, &shrunk_inputs));
+        assert_eq!(1, shrunk_inputs.get_secret_inputs()[0].len());
+
+        BROKEN_MUL.with(|b| b.set(false));
+    }
+
+    // HELPER FUNCTIONS
+    // --------------------------------------------------------------------------------------------
+
+    /// Returns true if the program executes without errors, but its execution trace does not
+    /// satisfy transition constraints.
+    fn violates_constraints(program: &Program, inputs: &ProgramInputs) -> bool {
+        let (registers, ctx_depth, loop_depth) = match processor::execute(program, inputs) {
+            Ok(trace) => trace,
+            Err(_) => return false,
+        };
+
+        let trace = TraceTable::new(registers, ctx_depth, loop_depth, crate::MIN_EXTENSION_FACTOR);
+        let evaluator = ConstraintEvaluator::from_trace(&trace, &[0; 32], inputs.get_public_inputs(), &[], &[]);
+        let result = panic::catch_unwind(panic::AssertUnwindSafe(|| {
+            for step in 0..(trace.unextended_length() - 1) {
+                let (current, next) = (trace.get_state(step), trace.get_state(step + 1));
+                evaluator.evaluate_transition(&current, &next, 0, step * EVALUATION_BLOWUP);
+            }
+        }));
+        return result.is_err();
+    }
+
+    fn count_ops(program: &Program) -> usize {
+        return count_block_ops(program.root().body());
+    }
+
+    fn count_block_ops(blocks: &[ProgramBlock]) -> usize {
+        return blocks.iter().map(|block| match block {
+            ProgramBlock::Span(span) => (0..span.length()).filter(|&i| span.get_op(i).0 != OpCode::Noop).count(),
+            ProgramBlock::Group(group) => count_block_ops(group.body()),
+            ProgramBlock::Switch(switch) => count_block_ops(switch.true_branch()) + count_block_ops(switch.false_branch()),
+            ProgramBlock::Loop(block) => count_block_ops(block.body()),
+        }).sum();
+    }
+}
@@ -0,0 +1,113 @@
+use crate::{ ProofOptions, ProgramInputs, Program, assembly };
+use super::super::{ execute, execute_batch, verify, verify_batch };
+
+#[test]
+fn batch_of_executions() {
+    let program = build_program();
+    let options = ProofOptions::default();
+    let inputs = build_inputs();
+
+    let (outputs, proof) = execute_batch(&program, &inputs, 1, &options).unwrap();
+    for (i, outputs) in outputs.iter().enumerate() {
+        assert_eq!(vec![(i as u128 + 3) << i], *outputs);
+    }
+
+    let statement = build_statement(&inputs, &outputs);
+    assert_eq!(Ok(()), verify_batch(program.hash(), &statement, &proof));
+
+    // a batch proof cannot be verified as a proof of a single execution
+    let result = verify(program.hash(), &statement[0].0, &statement[0].1, &proof);
+    assert_eq!(Err(String::from("verification of execution count failed: proof attests to a batch of executions")), result.map_err(|err| err.to_string()));
+
+    // all executions of the batch must be verified together
+    let result = verify_batch(program.hash(), &statement[..7], &proof);
+    assert_eq!(Err(String::from("verification of execution count failed: proof attests to 8 executions, but 7 were provided")), result.map_err(|err| err.to_string()));
+}
+
+#[test]
+fn batch_with_altered_statement() {
+    let program = build_program();
+    let options = ProofOptions::default();
+    let inputs = build_inputs();
+
+    let (outputs, proof) = execute_batch(&program, &inputs, 1, &options).unwrap();
+    let statement = build_statement(&inputs, &outputs);
+
+    for i in 0..statement.len() {
+        let mut altered = statement.clone();
+        altered[i].1[0] += 1;
+        let result = verify_batch(program.hash(), &altered, &proof);
+        assert!(result.is_err(), "altered outputs of execution {} were accepted", i);
+
+        let mut altered = statement.clone();
+        altered[i].0[0] += 1;
+        let result = verify_batch(program.hash(), &altered, &proof);
+        assert!(result.is_err(), "altered inputs of execution {} were accepted", i);
+    }
+
+    // swapping two executions changes the statement as well
+    let mut altered = statement.clone();
+    altered.swap(0, 1);
+    assert!(verify_batch(program.hash(), &altered, &proof).is_err());
+}
+
+#[test]
+fn batch_of_single_execution() {
+    let program = build_program();
+    let options = ProofOptions::default();
+    let inputs = build_inputs();
+
+    let (outputs, proof) = execute_batch(&program, &inputs[3..4], 1, &options).unwrap();
+    let statement = build_statement(&inputs[3..4], &outputs);
+    assert_eq!(Ok(()), verify_batch(program.hash(), &statement, &proof));
+    assert_eq!(Ok(()), verify(program.hash(), &statement[0].0, &statement[0].1, &proof));
+}
+
+#[test]
+fn batch_proof_size() {
+    let program = build_program();
+    let options = ProofOptions::default();
+    let inputs = build_inputs();
+
+    let (_, proof) = execute_batch(&program, &inputs, 1, &options).unwrap();
+    let batch_size = bincode::serialize(&proof).unwrap().len();
+
+    let mut separate_size = 0;
+    for inputs in inputs.iter() {
+        let (_, proof) = execute(&program, inputs, 1, &options).unwrap();
+        separate_size += bincode::serialize(&proof).unwrap().len();
+    }
+
+    assert!(batch_size * 4 < separate_size,
+        "batch proof of {} bytes is not much smaller than separate proofs of {} bytes", batch_size, separate_size);
+}
+
+// HELPER FUNCTIONS
+// ================================================================================================
+
+/// Doubles the public input once for every 1 on the secret tape.
+fn build_program() -> Program {
+    return assembly::compile("
+        begin
+            read
+            while.true
+                push.2 mul read
+            end
+        end").unwrap();
+}
+
+/// Builds 8 sets of inputs with execution `i` running `i` loop iterations; execution traces of
+/// the batch are therefore of different lengths.
+fn build_inputs() -> Vec<ProgramInputs> {
+    return (0..8).map(|i| {
+        let mut tape = vec![1; i];
+        tape.push(0);
+        ProgramInputs::new(&[i as u128 + 3], &tape, &[])
+    }).collect();
+}
+
+fn build_statement(inputs: &[ProgramInputs], outputs: &[Vec<u128>]) -> Vec<(Vec<u128>, Vec<u128>)> {
+    return inputs.iter().zip(outputs.iter())
+        .map(|(inputs, outputs)| (inputs.get_public_inputs().to_vec(), outputs.clone()))
+        .collect();
+}
@@ -1,38 +1,165 @@
-/*
-TODO: re-enable
-use crate::{ ProofOptions, opcodes::f128 as opcodes, ExecutionGraph };
-use super::{ Program, ProgramInputs, super::{ execute, verify, } };
+use crate::{ ProofOptions, ExecutionError, OpCode, assembly, processor, math::field };
+use super::{ ProgramInputs, super::{ execute, verify, } };
 
 #[test]
 fn simple_branching() {
-    // build program execution graph
-    let mut exe_graph = ExecutionGraph::new(vec![
-        opcodes::BEGIN, opcodes::PUSH, 3, opcodes::PUSH, 5, opcodes::READ
-    ]);
-    let true_branch = ExecutionGraph::new(vec![
-        opcodes::ASSERT, opcodes::ADD
-    ]);
-    let false_branch = ExecutionGraph::new(vec![
-        opcodes::NOT, opcodes::ASSERT, opcodes::MUL
-    ]);
-    exe_graph.set_next(true_branch, false_branch);
+    let program = assembly::compile("
+        begin
+            push.3 push.5 read
+            if.true
+                add
+            else
+                mul
+            end
+        end").unwrap();
 
     let options = ProofOptions::default();
-    let program = Program::new(exe_graph, options.hash_fn());
     let num_outputs = 1;
 
     // test true branch
     let inputs = ProgramInputs::new(&[], &[1], &[]);
-    let (outputs, proof) = execute(&program, &inputs, num_outputs, &options);
+    let (outputs, proof) = execute(&program, &inputs, num_outputs, &options).unwrap();
     assert_eq!(outputs, [8]);
     let result = verify(program.hash(), inputs.get_public_inputs(), &outputs, &proof);
-    assert_eq!(Ok(true), result);
+    assert_eq!(Ok(()), result);
 
     // test false branch
     let inputs = ProgramInputs::new(&[], &[0], &[]);
-    let (outputs, proof) = execute(&program, &inputs, num_outputs, &options);
+    let (outputs, proof) = execute(&program, &inputs, num_outputs, &options).unwrap();
     assert_eq!(outputs, [15]);
     let result = verify(program.hash(), inputs.get_public_inputs(), &outputs, &proof);
-    assert_eq!(Ok(true), result);
+    assert_eq!(Ok(()), result);
+}
+
+#[test]
+fn simple_loop() {
+    let program = assembly::compile("
+        begin
+            push.3 read
+            while.true
+                push.2 mul read
+            end
+        end").unwrap();
+
+    let options = ProofOptions::default();
+    let num_outputs = 1;
+
+    // test loop with multiple iterations
+    let inputs = ProgramInputs::new(&[], &[1, 1, 1, 0], &[]);
+    let (outputs, proof) = execute(&program, &inputs, num_outputs, &options).unwrap();
+    assert_eq!(outputs, [24]);
+    let result = verify(program.hash(), inputs.get_public_inputs(), &outputs, &proof);
+    assert_eq!(Ok(()), result);
+
+    // test loop which is skipped
+    let inputs = ProgramInputs::new(&[], &[0], &[]);
+    let (outputs, proof) = execute(&program, &inputs, num_outputs, &options).unwrap();
+    assert_eq!(outputs, [3]);
+    let result = verify(program.hash(), inputs.get_public_inputs(), &outputs, &proof);
+    assert_eq!(Ok(()), result);
+}
+
+#[test]
+fn skipped_loop_length() {
+    let short_loop = assembly::compile("
+        begin push.3 read while.true push.2 mul read end end").unwrap();
+    let long_loop = assembly::compile("
+        begin push.3 read while.true repeat.20 push.2 mul end read end end").unwrap();
+
+    // a loop which is skipped takes the same number of steps regardless of the size of its body
+    let inputs = ProgramInputs::new(&[], &[0], &[]);
+    let (short_trace, _, _) = processor::execute(&short_loop, &inputs).unwrap();
+    let (long_trace, _, _) = processor::execute(&long_loop, &inputs).unwrap();
+    assert_eq!(short_trace[0].last(), long_trace[0].last());
+
+    // the skipped and the executed loop are proven against the same program hash
+    let options = ProofOptions::default();
+    for (tape, expected) in [(vec![0], 3), (vec![1, 1, 0], 3 << 40)].iter() {
+        let inputs = ProgramInputs::new(&[], tape, &[]);
+        let (outputs, proof) = execute(&long_loop, &inputs, 1, &options).unwrap();
+        assert_eq!(outputs, [*expected]);
+        let result = verify(long_loop.hash(), inputs.get_public_inputs(), &outputs, &proof);
+        assert_eq!(Ok(()), result);
+    }
+}
+
+#[test]
+fn bounded_loop() {
+    let program = assembly::compile("
+        begin
+            push.3 read
+            while.true.max=10
+                push.2 mul read
+            end
+        end").unwrap();
+
+    // the annotation does not change the program
+    let unbounded = assembly::compile("
+        begin push.3 read while.true push.2 mul read end end").unwrap();
+    assert_eq!(unbounded.hash(), program.hash());
+
+    // loops which run 0, 1, and 10 times
+    let options = ProofOptions::default();
+    for &n in [0, 1, 10].iter() {
+        let mut tape = vec![1; n];
+        tape.push(0);
+        let inputs = ProgramInputs::new(&[], &tape, &[]);
+        let (outputs, proof) = execute(&program, &inputs, 1, &options).unwrap();
+        assert_eq!(outputs, [3 << n]);
+        let result = verify(program.hash(), inputs.get_public_inputs(), &outputs, &proof);
+        assert_eq!(Ok(()), result);
+    }
+
+    // a non-binary condition is reported as an error
+    let inputs = ProgramInputs::new(&[], &[1, 2], &[]);
+    let err = execute(&program, &inputs, 1, &options).err().unwrap();
+    assert_eq!(ExecutionError::NonBinaryCondition { step: 31, value: 2 }, err);
+
+    // loops which could exceed the step budget are rejected
+    let err = assembly::compile("begin read while.true.max=100000 push.2 mul read end end").unwrap_err();
+    assert_eq!(format!("loop while.true.max=100000 may take up to 1600000 steps, which exceeds the budget of {} steps",
+        assembly::MAX_LOOP_STEPS), *err.message());
+
+    let err = assembly::compile("begin read while.true.max=0 read end end").unwrap_err();
+    assert_eq!("invalid block head 'while.true.max=0'", err.message());
+}
+
+#[test]
+fn switch_block() {
+    let program = assembly::compile("
+        begin
+            push.3 read
+            switch
+            case.0
+                push.1 add
+            case.1
+                push.2 mul
+            case.2
+                dup mul
+            case.3
+                read if.true neg end
+            end
+        end").unwrap();
+
+    // every case is proven against the same program hash
+    let options = ProofOptions::default();
+    let cases = [(vec![0], 4), (vec![1], 6), (vec![2], 9), (vec![3, 1], field::neg(3))];
+    for (tape, expected) in cases.iter() {
+        let inputs = ProgramInputs::new(&[], tape, &[]);
+        let (outputs, proof) = execute(&program, &inputs, 1, &options).unwrap();
+        assert_eq!(outputs, [*expected]);
+        let result = verify(program.hash(), inputs.get_public_inputs(), &outputs, &proof);
+        assert_eq!(Ok(()), result);
+    }
+
+    // a selector which does not fit into the number of cases fails the execution
+    let inputs = ProgramInputs::new(&[], &[4], &[]);
+    let err = execute(&program, &inputs, 1, &options).err().unwrap();
+    assert_eq!(Some(OpCode::Read), err.op());
+
+    // so does a selector which fits into the same number of bits as the last case
+    let program = assembly::compile("begin read switch case.0 noop case.1 noop case.2 noop end end").unwrap();
+    let inputs = ProgramInputs::new(&[], &[3], &[]);
+    let err = execute(&program, &inputs, 1, &options).err().unwrap();
+    assert_eq!(Some(OpCode::Assert), err.op());
 }
-*/
\ No newline at end of file
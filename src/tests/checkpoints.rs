@@ -0,0 +1,108 @@
+use std::{ panic, sync::{ Arc, Mutex } };
+use crate::{ ProofOptions, ProgramInputs, ProverCheckpoint, ProverPhase, StarkProof, Assertion, assembly };
+use super::super::{ execute_with_assertions, verify_with_assertions, resume };
+
+#[test]
+fn resume_after_trace_extension() {
+    let (expected, checkpoint) = prove_interrupted(ProverPhase::TraceExtended);
+    let proof = resume(&checkpoint, &ProofOptions::default()).unwrap();
+    assert_eq!(expected, bincode::serialize(&proof).unwrap());
+    verify_proof(&proof);
+}
+
+#[test]
+fn resume_after_constraint_commitment() {
+    let (expected, checkpoint) = prove_interrupted(ProverPhase::ConstraintsCommitted);
+    let proof = resume(&checkpoint, &ProofOptions::default()).unwrap();
+    assert_eq!(expected, bincode::serialize(&proof).unwrap());
+    verify_proof(&proof);
+}
+
+#[test]
+fn resume_with_checkpoints() {
+    // a resumed prover takes checkpoints for the phases it completes
+    let (expected, checkpoint) = prove_interrupted(ProverPhase::TraceExtended);
+    let (options, checkpoints) = recording_options(None);
+    let proof = resume(&checkpoint, &options).unwrap();
+    assert_eq!(expected, bincode::serialize(&proof).unwrap());
+
+    let checkpoints = checkpoints.lock().unwrap();
+    assert_eq!(1, checkpoints.len());
+    let checkpoint = ProverCheckpoint::from_bytes(&checkpoints[0], &options).unwrap();
+    assert_eq!(ProverPhase::ConstraintsCommitted, checkpoint.phase());
+}
+
+#[test]
+fn resume_with_mismatched_options() {
+    let (_, checkpoint) = prove_interrupted(ProverPhase::TraceExtended);
+    let options = ProofOptions::new(32, 40, 20, crate::crypto::HashFunction::Blake3);
+    let result = resume(&checkpoint, &options);
+    assert_eq!(Err(String::from("checkpoint was taken with different proof options")), result.map(|_| ()));
+}
+
+#[test]
+fn resume_with_mismatched_version() {
+    let (_, mut checkpoint) = prove_interrupted(ProverPhase::ConstraintsCommitted);
+    checkpoint[0] += 1;
+    let result = resume(&checkpoint, &ProofOptions::default());
+    assert_eq!(Err(String::from("checkpoint version 6 is not supported; expected version 5")), result.map(|_| ()));
+
+    let result = resume(&[], &ProofOptions::default());
+    assert_eq!(Err(String::from("checkpoint is empty")), result.map(|_| ()));
+}
+
+#[test]
+fn resume_with_old_version() {
+    // checkpoints of version 4 keep all states of the extended trace, and reading them as
+    // checkpoints of the current version would misplace trace states
+    let (_, mut checkpoint) = prove_interrupted(ProverPhase::TraceExtended);
+    checkpoint[0] = 4;
+    let result = resume(&checkpoint, &ProofOptions::default());
+    assert_eq!(Err(String::from("checkpoint version 4 is not supported; expected version 5")), result.map(|_| ()));
+}
+
+// HELPER FUNCTIONS
+// ================================================================================================
+const SOURCE: &str = "begin push.3 push.4 mul push.5 add hash.2 dup add end";
+
+/// Generates a proof without interruptions and serializes it, and then starts generating the
+/// same proof again, but aborts the prover right after the checkpoint for the `phase` is taken.
+fn prove_interrupted(phase: ProverPhase) -> (Vec<u8>, Vec<u8>) {
+    let program = assembly::compile(SOURCE).unwrap();
+    let inputs = ProgramInputs::from_public(&[1, 2]);
+    let assertions = [Assertion::first(1, 2)];
+
+    let (_, proof) = execute_with_assertions(&program, &inputs, 2, &assertions, &ProofOptions::default()).unwrap();
+    let expected = bincode::serialize(&proof).unwrap();
+
+    let (options, checkpoints) = recording_options(Some(phase));
+    let result = panic::catch_unwind(panic::AssertUnwindSafe(|| {
+        execute_with_assertions(&program, &inputs, 2, &assertions, &options).unwrap()
+    }));
+    assert!(result.is_err(), "prover was not interrupted");
+
+    let checkpoint = checkpoints.lock().unwrap().pop().unwrap();
+    return (expected, checkpoint);
+}
+
+/// Returns options which record all checkpoints; if `abort_after` is set, the prover panics
+/// right after the checkpoint for the specified phase is recorded.
+fn recording_options(abort_after: Option<ProverPhase>) -> (ProofOptions, Arc<Mutex<Vec<Vec<u8>>>>) {
+    let checkpoints = Arc::new(Mutex::new(Vec::new()));
+    let recorded = checkpoints.clone();
+    let options = ProofOptions::default().with_checkpoints(move |checkpoint| {
+        recorded.lock().unwrap().push(checkpoint.to_bytes());
+        if abort_after == Some(checkpoint.phase()) {
+            panic!("prover interrupted after {:?} phase", checkpoint.phase());
+        }
+    });
+    return (options, checkpoints);
+}
+
+fn verify_proof(proof: &StarkProof) {
+    let program = assembly::compile(SOURCE).unwrap();
+    let outputs = proof.outputs().to_vec();
+    let assertions = [Assertion::first(1, 2)];
+    let result = verify_with_assertions(program.hash(), &[1, 2], &outputs, &assertions, proof);
+    assert_eq!(Ok(()), result);
+}
@@ -1,4 +1,4 @@
-use crate::{ ProofOptions, math::field };
+use crate::{ ProofOptions, ExecutionError, assembly, math::field };
 use super::{
     build_program, OpCode,
     super::{ execute, verify, ProgramInputs }
@@ -20,11 +20,125 @@ fn eq_operations() {
 
     let expected_result = vec![1, 0, 3];
 
-    let (outputs, proof) = execute(&program, &inputs, num_outputs, &options);
+    let (outputs, proof) = execute(&program, &inputs, num_outputs, &options).unwrap();
     assert_eq!(expected_result, outputs);
 
     let result = verify(program.hash(), inputs.get_public_inputs(), &outputs, &proof);
-    assert_eq!(Ok(true), result);
+    assert_eq!(Ok(()), result);
+}
+
+#[test]
+fn eq_instruction() {
+    // the inverse of the difference is computed by the processor, and so no secret inputs
+    // need to be supplied
+    let program = assembly::compile("begin eq end").unwrap();
+    let options = ProofOptions::default();
+
+    let cases = [(5, 5, 1), (5, 7, 0), (0, 0, 1), (0, field::M - 1, 0)];
+    for &(x, y, expected) in cases.iter() {
+        let inputs = ProgramInputs::from_public(&[x, y, 9]);
+        let (outputs, proof) = execute(&program, &inputs, 2, &options).unwrap();
+        assert_eq!(vec![expected, 9], outputs, "eq failed for {} and {}", x, y);
+
+        let result = verify(program.hash(), inputs.get_public_inputs(), &outputs, &proof);
+        assert_eq!(Ok(()), result);
+    }
+}
+
+#[test]
+fn iszero_instruction() {
+    let program = assembly::compile("begin iszero end").unwrap();
+    let options = ProofOptions::default();
+
+    for &(x, expected) in [(0, 1), (1, 0), (field::M - 1, 0)].iter() {
+        let inputs = ProgramInputs::from_public(&[x, 9]);
+        let (outputs, proof) = execute(&program, &inputs, 2, &options).unwrap();
+        assert_eq!(vec![expected, 9], outputs, "iszero failed for {}", x);
+
+        let result = verify(program.hash(), inputs.get_public_inputs(), &outputs, &proof);
+        assert_eq!(Ok(()), result);
+    }
+}
+
+#[test]
+fn neq_instruction() {
+    let program = assembly::compile("begin neq end").unwrap();
+    assert_eq!(program.hash(), assembly::compile("begin ne end").unwrap().hash());
+    let options = ProofOptions::default();
+
+    for &(x, y, expected) in [(5, 5, 0), (5, 7, 1)].iter() {
+        let inputs = ProgramInputs::from_public(&[x, y]);
+        let (outputs, proof) = execute(&program, &inputs, 1, &options).unwrap();
+        assert_eq!(vec![expected], outputs, "neq failed for {} and {}", x, y);
+        assert_eq!(Ok(()), verify(program.hash(), inputs.get_public_inputs(), &outputs, &proof));
+    }
+}
+
+#[test]
+fn asserteq_instruction() {
+    let program = assembly::compile("begin assert.eq end").unwrap();
+    let options = ProofOptions::default();
+
+    // equal values are removed from the stack
+    let inputs = ProgramInputs::from_public(&[7, 7, 9]);
+    let (outputs, proof) = execute(&program, &inputs, 1, &options).unwrap();
+    assert_eq!(vec![9], outputs);
+    assert_eq!(Ok(()), verify(program.hash(), inputs.get_public_inputs(), &outputs, &proof));
+
+    // different values fail execution, and the error describes both values
+    let inputs = ProgramInputs::from_public(&[7, 8, 9]);
+    let err = crate::run(&program, &inputs, 1).unwrap_err();
+    assert_eq!(ExecutionError::UnequalValues { step: 2, op: OpCode::AssertEq, values: [7, 8] }, err);
+    assert_eq!("ASSERTEQ failed at step 2: 7 is not equal to 8", err.to_string());
+}
+
+#[test]
+fn assert_instructions() {
+    let options = ProofOptions::default();
+
+    // a program with several passing assertions can be proven and verified
+    let program = assembly::compile("begin assert assert.z assert.eq assert.z push.1 assert end").unwrap();
+    let inputs = ProgramInputs::from_public(&[1, 0, 5, 5, 0, 9]);
+    let (outputs, proof) = execute(&program, &inputs, 1, &options).unwrap();
+    assert_eq!(vec![9], outputs);
+    assert_eq!(Ok(()), verify(program.hash(), inputs.get_public_inputs(), &outputs, &proof));
+
+    // failed assert reports the offending value
+    let program = assembly::compile("begin assert end").unwrap();
+    let err = crate::run(&program, &ProgramInputs::from_public(&[0, 9]), 1).unwrap_err();
+    assert_eq!(ExecutionError::FailedAssertion { step: 2, op: OpCode::Assert, value: 0 }, err);
+    assert_eq!("ASSERT failed at step 2: expected 1 but got 0", err.to_string());
+
+    // failed assert.z reports the offending value as well
+    let program = assembly::compile("begin assert.z end").unwrap();
+    let (outputs, _) = crate::run(&program, &ProgramInputs::from_public(&[0, 9]), 1).unwrap();
+    assert_eq!(vec![9], outputs);
+
+    let err = crate::run(&program, &ProgramInputs::from_public(&[1, 9]), 1).unwrap_err();
+    assert_eq!(ExecutionError::UnequalValues { step: 4, op: OpCode::AssertEq, values: [0, 1] }, err);
+}
+
+#[test]
+fn lt_gt_instructions() {
+    let options = ProofOptions::default();
+    let p = field::M;
+    let cases = [(0, 0), (0, 1), (1, 0), (1, 1), (p - 1, 0), (0, p - 1), (p - 1, p - 2), (p - 1, p - 1)];
+
+    for &(op, n) in [("lt", 128), ("gt", 128)].iter() {
+        let program = assembly::compile(&format!("begin {}.{} end", op, n)).unwrap();
+        for &(x, y) in cases.iter() {
+            let expected = if op == "lt" { (x < y) as u128 } else { (x > y) as u128 };
+
+            let inputs = ProgramInputs::from_public(&[x, y]);
+            let (outputs, proof) = execute(&program, &inputs, 1, &options).unwrap();
+            assert_eq!(vec![expected], outputs, "{} failed for {} and {}", op, x, y);
+            assert_eq!(Ok(()), verify(program.hash(), inputs.get_public_inputs(), &outputs, &proof));
+        }
+    }
+
+    // values which do not fit into n bits are rejected
+    let program = assembly::compile("begin lt.8 end").unwrap();
+    assert!(crate::run(&program, &ProgramInputs::from_public(&[256, 1]), 1).is_err());
 }
 
 #[test]
@@ -58,12 +172,12 @@ fn cmp_operation() {
     let expected_result = vec![gt, lt, b, a];
 
     // execute the program and make sure results are correct
-    let (outputs, proof) = execute(&program, &inputs, num_outputs, &options);
+    let (outputs, proof) = execute(&program, &inputs, num_outputs, &options).unwrap();
     assert_eq!(expected_result, outputs);
 
     // verify execution proof
     let result = verify(program.hash(), inputs.get_public_inputs(), &outputs, &proof);
-    assert_eq!(Ok(true), result);
+    assert_eq!(Ok(()), result);
 }
 
 #[test]
@@ -96,12 +210,12 @@ fn binacc_operation() {
     let expected_result = vec![a, a];
 
     // execute the program and make sure results are correct
-    let (outputs, proof) = execute(&program, &inputs, num_outputs, &options);
+    let (outputs, proof) = execute(&program, &inputs, num_outputs, &options).unwrap();
     assert_eq!(expected_result, outputs);
 
     // verify execution proof
     let result = verify(program.hash(), inputs.get_public_inputs(), &outputs, &proof);
-    assert_eq!(Ok(true), result);
+    assert_eq!(Ok(()), result);
 }
 
 // HELPER FUNCTIONS
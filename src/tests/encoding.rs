@@ -0,0 +1,58 @@
+use rand::prelude::*;
+use crate::{ ProofOptions, ProgramInputs, StarkProof, HashFunction, assembly };
+use super::super::{ execute, verify };
+
+#[test]
+fn compact_proof_encoding() {
+    let program = assembly::compile("begin push.0 push.1 while.true push.1 add dup push.30 eq not end end").unwrap();
+    let inputs = ProgramInputs::none();
+
+    let zk_options = ProofOptions::new(32, 8, 0, HashFunction::Blake3).with_zero_knowledge(true);
+    for options in [ProofOptions::default(), zk_options].iter() {
+        let (outputs, proof) = execute(&program, &inputs, 1, options).unwrap();
+        let bytes = proof.to_bytes();
+
+        // decoded proof is identical to the original one, and still verifies
+        let decoded = StarkProof::from_bytes(&bytes).unwrap();
+        assert_eq!(bincode::serialize(&proof).unwrap(), bincode::serialize(&decoded).unwrap());
+        assert_eq!(bytes, decoded.to_bytes());
+        assert_eq!(Ok(()), verify(program.hash(), &[], &outputs, &decoded));
+
+        // compact encoding is smaller than bincode encoding
+        let bincode_size = bincode::serialize(&proof).unwrap().len();
+        assert!(bytes.len() < bincode_size, "compact: {}, bincode: {}", bytes.len(), bincode_size);
+    }
+}
+
+#[test]
+fn malformed_proof_bytes() {
+    let program = assembly::compile("begin push.3 push.5 read add mul end").unwrap();
+    let inputs = ProgramInputs::new(&[], &[2], &[]);
+    let (_, proof) = execute(&program, &inputs, 1, &ProofOptions::default()).unwrap();
+    let bytes = proof.to_bytes();
+
+    assert_eq!(Err(String::from("proof bytes are empty")), StarkProof::from_bytes(&[]).map(|_| ()));
+    let mut unknown_version = bytes.clone();
+    unknown_version[0] = 3;
+    assert_eq!(Err(String::from("proof format version 3 is not supported; expected version 2")),
+        StarkProof::from_bytes(&unknown_version).map(|_| ()));
+    let mut trailing = bytes.clone();
+    trailing.push(0);
+    assert!(StarkProof::from_bytes(&trailing).is_err());
+
+    // every truncation of the proof is rejected without panicking
+    let mut rng = thread_rng();
+    let mut lengths: Vec<usize> = (0..200).collect();
+    lengths.extend((0..200).map(|_| rng.gen_range(0, bytes.len())));
+    for &length in lengths.iter() {
+        assert!(StarkProof::from_bytes(&bytes[..length]).is_err(), "truncated to {} bytes", length);
+    }
+
+    // corrupted proofs may or may not decode, but decoding never panics
+    for _ in 0..200 {
+        let mut corrupted = bytes.clone();
+        let position = rng.gen_range(0, bytes.len());
+        corrupted[position] ^= rng.gen_range(1, 256) as u8;
+        let _ = StarkProof::from_bytes(&corrupted);
+    }
+}
@@ -0,0 +1,161 @@
+use crate::{ ProofOptions, Program, OpHint, assembly, math::field, blocks::{ ProgramBlock, Span, Group } };
+use super::super::{ execute, verify, run, ProgramInputs };
+
+#[test]
+fn u32_add() {
+    let cases: [(u32, u32); 5] = [(0, 0), (1, 2), (0xFFFF_FFFF, 1), (0xFFFF_FFFF, 0xFFFF_FFFF), (0x8000_0000, 0x8000_0000)];
+    for &(a, b) in cases.iter() {
+        let (sum, carry) = a.overflowing_add(b);
+        check_u32_op("u32.add", a, b, &[carry as u128, sum as u128]);
+    }
+}
+
+#[test]
+fn u32_sub() {
+    let cases: [(u32, u32); 6] = [(0, 0), (5, 3), (0, 1), (3, 5), (0xFFFF_FFFF, 0), (0, 0xFFFF_FFFF)];
+    for &(a, b) in cases.iter() {
+        let (diff, borrow) = a.overflowing_sub(b);
+        check_u32_op("u32.sub", a, b, &[borrow as u128, diff as u128]);
+    }
+}
+
+#[test]
+fn u32_mul() {
+    let cases: [(u32, u32); 5] = [(0, 0xFFFF_FFFF), (3, 5), (0x1_0000, 0x1_0000), (0xFFFF_FFFF, 0xFFFF_FFFF), (0xFFFF_FFFF, 2)];
+    for &(a, b) in cases.iter() {
+        let product = (a as u64) * (b as u64);
+        check_u32_op("u32.mul", a, b, &[(product >> 32) as u128, product as u32 as u128]);
+    }
+}
+
+#[test]
+fn u32_divmod() {
+    let cases: [(u32, u32); 6] = [(0, 1), (42, 1), (42, 7), (43, 7), (5, 9), (u32::MAX, 0x1_0001)];
+    for &(a, b) in cases.iter() {
+        check_u32_op("u32.divmod", a, b, &[(a % b) as u128, (a / b) as u128]);
+    }
+
+    // division by zero is an execution error
+    let program = assembly::compile("begin u32.divmod end").unwrap();
+    assert!(run(&program, &ProgramInputs::from_public(&[0, 5]), 2).is_err());
+}
+
+#[test]
+fn u32_divmod_dishonest_prover() {
+    // remove the hint from the program so that the quotient and the remainder are read from
+    // the secret inputs instead of being computed by the processor
+    let program = assembly::compile("begin u32.divmod end").unwrap();
+    let body = program.root().body().iter().map(|block| match block {
+        ProgramBlock::Span(span) => {
+            let op_codes = (0..span.length()).map(|i| span.get_op(i).0).collect();
+            let op_hints = (0..span.length())
+                .map(|i| (i, span.get_op(i).1))
+                .filter(|&(_, hint)| hint != OpHint::None && hint != OpHint::DivStart)
+                .collect();
+            ProgramBlock::Span(Span::new(op_codes, op_hints).unwrap())
+        },
+        block => block.clone(),
+    }).collect();
+    let program = Program::new(Group::new(body));
+
+    // honest quotient and remainder are accepted
+    let inputs = ProgramInputs::new(&[7, 43], &[6], &[1]);
+    assert_eq!(vec![1, 6], run(&program, &inputs, 2).unwrap().0);
+
+    // remainder which is not smaller than the divisor: 42 = 5 * 7 + 7
+    let inputs = ProgramInputs::new(&[7, 42], &[5], &[7]);
+    assert!(run(&program, &inputs, 2).is_err());
+
+    // quotient which does not fit into 32 bits: 5 = (5 / 2) * 2 + 0 in the field
+    let q = field::div(5, 2);
+    let inputs = ProgramInputs::new(&[2, 5], &[q], &[0]);
+    assert!(run(&program, &inputs, 2).is_err());
+}
+
+#[test]
+fn u32_bitwise() {
+    let mut cases: Vec<(u32, u32)> = vec![
+        (0, 0), (0, u32::MAX), (u32::MAX, 0), (u32::MAX, u32::MAX), (0xAAAA_AAAA, 0x5555_5555),
+    ];
+    for _ in 0..2 {
+        cases.push((field::rand() as u32, field::rand() as u32));
+    }
+
+    for &(a, b) in cases.iter() {
+        check_u32_op("u32.and", a, b, &[(a & b) as u128]);
+        check_u32_op("u32.or",  a, b, &[(a | b) as u128]);
+        check_u32_op("u32.xor", a, b, &[(a ^ b) as u128]);
+    }
+}
+
+#[test]
+fn u32_shift_rotate() {
+    let values: [u32; 4] = [0, 1, u32::MAX, 0x8000_0001];
+    for &a in values.iter().chain([field::rand() as u32].iter()) {
+        for s in 0..32 {
+            check_u32_shift("shl", a, s, a << s);
+            check_u32_shift("shr", a, s, a >> s);
+            check_u32_shift("rotl", a, s, a.rotate_left(s));
+            check_u32_shift("rotr", a, s, a.rotate_right(s));
+        }
+    }
+}
+
+#[test]
+fn u32_sha256_sigma() {
+    // σ0(x) = (x >>> 7) ^ (x >>> 18) ^ (x >> 3) from the SHA-256 message schedule
+    let options = ProofOptions::default();
+    let program = assembly::compile("begin
+        dup dup u32.rotr.7 swap u32.rotr.18 u32.xor swap u32.shr.3 u32.xor end").unwrap();
+
+    let x = field::rand() as u32;
+    let expected = x.rotate_right(7) ^ x.rotate_right(18) ^ (x >> 3);
+    let inputs = ProgramInputs::from_public(&[x as u128]);
+    let (outputs, proof) = execute(&program, &inputs, 1, &options).unwrap();
+    assert_eq!(vec![expected as u128], outputs);
+    assert_eq!(Ok(()), verify(program.hash(), inputs.get_public_inputs(), &outputs, &proof));
+}
+
+#[test]
+fn u32_invalid_operands() {
+    for &op in ["u32.add", "u32.sub", "u32.mul", "u32.divmod", "u32.and", "u32.or", "u32.xor"].iter() {
+        let program = assembly::compile(&format!("begin {} end", op)).unwrap();
+        assert!(run(&program, &ProgramInputs::from_public(&[1 << 32, 1]), 2).is_err());
+        assert!(run(&program, &ProgramInputs::from_public(&[1, 1 << 32]), 2).is_err());
+        assert!(run(&program, &ProgramInputs::from_public(&[1, 1]), 2).is_ok());
+    }
+
+    for &op in ["u32.shl.0", "u32.shr.0", "u32.shr.5", "u32.rotl.13", "u32.rotr.31"].iter() {
+        let program = assembly::compile(&format!("begin {} end", op)).unwrap();
+        assert!(run(&program, &ProgramInputs::from_public(&[1 << 32]), 1).is_err());
+        assert!(run(&program, &ProgramInputs::from_public(&[field::M - 1]), 1).is_err());
+        assert!(run(&program, &ProgramInputs::from_public(&[1]), 1).is_ok());
+    }
+
+    assert!(assembly::compile("begin u32.shl.32 end").is_err());
+    assert!(assembly::compile("begin u32.rotl end").is_err());
+    assert!(assembly::compile("begin u32.add.1 end").is_err());
+}
+
+// HELPER FUNCTIONS
+// ================================================================================================
+
+/// Executes a u32 operation with `a` as the first and `b` as the second operand, and checks
+/// both the result and the proof of the execution.
+fn check_u32_op(op: &str, a: u32, b: u32, expected: &[u128]) {
+    let options = ProofOptions::default();
+    let program = assembly::compile(&format!("begin {} end", op)).unwrap();
+
+    let inputs = ProgramInputs::from_public(&[b as u128, a as u128]);
+    let (outputs, proof) = execute(&program, &inputs, expected.len(), &options).unwrap();
+    assert_eq!(expected.to_vec(), outputs, "{} failed for {} and {}", op, a, b);
+    assert_eq!(Ok(()), verify(program.hash(), inputs.get_public_inputs(), &outputs, &proof));
+}
+
+/// Executes a u32 shift or rotation of `a` by `s` bits without generating a proof.
+fn check_u32_shift(op: &str, a: u32, s: u32, expected: u32) {
+    let program = assembly::compile(&format!("begin u32.{}.{} end", op, s)).unwrap();
+    let inputs = ProgramInputs::from_public(&[a as u128]);
+    let (outputs, _) = run(&program, &inputs, 1).unwrap();
+    assert_eq!(vec![expected as u128], outputs, "u32.{}.{} failed for {}", op, s, a);
+}
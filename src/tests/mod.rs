@@ -1,12 +1,22 @@
 use std::collections::HashMap;
 use crate::{
-    ProofOptions, Program, ProgramInputs, OpCode, OpHint,
+    ProofOptions, SecurityPolicy, VerificationItem, Minimize, Program, ProgramInputs, OpCode, OpHint, Assertion, AssertionStep, Statement, ExecutionError, VerificationError, assembly,
     blocks::{ ProgramBlock, Span, Group },
-    math::field, utils::hasher
+    math::field, utils::hasher, crypto::HashFunction, processor, stark, NUM_DEPTH_BITS,
+    stark::{ TraceTable, ConstraintEvaluator, ConstraintDegrees, MAX_CONSTRAINT_DEGREE },
 };
 
+mod batch;
 mod branches;
+mod checkpoints;
 mod comparisons;
+mod encoding;
+mod integers;
+mod padding;
+mod session;
+mod signatures;
+mod tapes;
+mod witness;
 
 #[test]
 fn execute_verify() {
@@ -21,11 +31,93 @@ fn execute_verify() {
     let inputs = ProgramInputs::from_public(&[1, 0]);
     let num_outputs = 1;
 
-    let (outputs, proof) = super::execute(&program, &inputs, num_outputs, &options);
+    let (outputs, proof) = super::execute(&program, &inputs, num_outputs, &options).unwrap();
     assert_eq!(outputs, [3]);
 
     let result = super::verify(program.hash(), inputs.get_public_inputs(), &outputs, &proof);
-    assert_eq!(Ok(true), result);
+    assert_eq!(Ok(()), result);
+}
+
+#[test]
+fn run_matches_execute() {
+    let source = "
+    begin
+        repeat.15
+            swap dup.2 drop add
+        end
+    end";
+    let program = assembly::compile(source).unwrap();
+
+    let options = ProofOptions::default();
+    let inputs = ProgramInputs::from_public(&[1, 0]);
+
+    let (outputs, cycles) = super::run(&program, &inputs, 1).unwrap();
+    assert_eq!(vec![987], outputs);
+
+    let (expected, proof) = super::execute(&program, &inputs, 1, &options).unwrap();
+    assert_eq!(expected, outputs);
+    assert!(cycles >= 60 && cycles <= proof.trace_length());
+
+    // errors are reported in the same way as by execute()
+    let program = assembly::compile("begin push.2 assert end").unwrap();
+    let expected = ExecutionError::FailedAssertion { step: 10, op: OpCode::Assert, value: 2 };
+    assert_eq!(Err(expected), super::run(&program, &inputs, 1));
+    assert!(super::execute(&program, &inputs, 1, &options).err() == Some(expected));
+}
+
+#[test]
+fn run_counts_all_steps() {
+    let program = assembly::compile("
+    begin
+        push.1 if.true add else mul end
+        push.0 if.true add else mul end
+    end").unwrap();
+    let inputs = ProgramInputs::from_public(&[2, 3, 4]);
+
+    // flow control operations (e.g. TEND, FEND) count as steps, but padding of the trace does not
+    let (outputs, steps) = super::run(&program, &inputs, 1).unwrap();
+    assert_eq!(vec![20], outputs);
+    assert_eq!(111, steps);
+
+    let (_, proof) = super::execute(&program, &inputs, 1, &ProofOptions::default()).unwrap();
+    assert_eq!(128, proof.trace_length());
+}
+
+#[test]
+fn execute_verify_many_outputs() {
+    let source = format!("begin {} end", (3..17).map(|i| format!("push.{}", i)).collect::<Vec<_>>().join(" "));
+    let program = assembly::compile(&source).unwrap();
+
+    let options = ProofOptions::default();
+    let inputs = ProgramInputs::from_public(&[2, 1]);
+    let num_outputs = 16;
+
+    let (outputs, proof) = super::execute(&program, &inputs, num_outputs, &options).unwrap();
+    assert_eq!((1..17).rev().collect::<Vec<u128>>(), outputs);
+
+    let result = super::verify(program.hash(), inputs.get_public_inputs(), &outputs, &proof);
+    assert_eq!(Ok(()), result);
+
+    // all outputs are bound to the proof
+    let result = super::verify(program.hash(), inputs.get_public_inputs(), &outputs[..8], &proof);
+    assert!(result.is_err());
+
+    // outputs cannot exceed the stack depth of the proof
+    let result = super::verify(program.hash(), inputs.get_public_inputs(), &[0; 17], &proof);
+    let err_msg = format!("verification of outputs failed: 17 outputs exceed stack depth 16");
+    assert_eq!(Err(VerificationError::StatementMismatch(err_msg)), result);
+
+    // outputs beyond the depth of the stack are zeros
+    let (outputs, proof) = super::execute(&program, &inputs, super::MAX_OUTPUTS, &options).unwrap();
+    assert_eq!(super::MAX_OUTPUTS, proof.stack_depth());
+    assert!(outputs[16..].iter().all(|&v| v == 0));
+    let result = super::verify(program.hash(), inputs.get_public_inputs(), &outputs, &proof);
+    assert_eq!(Ok(()), result);
+
+    let mut outputs2 = outputs.clone();
+    outputs2[31] = 1;
+    let result = super::verify(program.hash(), inputs.get_public_inputs(), &outputs2, &proof);
+    assert!(result.is_err());
 }
 
 #[test]
@@ -41,25 +133,321 @@ fn execute_verify_fail() {
     let inputs = ProgramInputs::from_public(&[1, 0]);
     let num_outputs = 1;
 
-    let (outputs, proof) = super::execute(&program, &inputs, num_outputs, &options);
+    let (outputs, proof) = super::execute(&program, &inputs, num_outputs, &options).unwrap();
     assert_eq!(outputs, [3]);
 
     // wrong inputs
+    let expected = VerificationError::LowDegreeProofFailed {
+        depth   : 0,
+        reason  : String::from("evaluations did not match column value at depth 0"),
+    };
     let result = super::verify(program.hash(), &[1, 1], &outputs, &proof);
-    let err_msg = format!("verification of low-degree proof failed: evaluations did not match column value at depth 0");
-    assert_eq!(Err(err_msg), result);
+    assert_eq!(Err(expected.clone()), result);
 
     // wrong outputs
     let result = super::verify(program.hash(), inputs.get_public_inputs(), &[5], &proof);
     let err_msg = format!("verification of low-degree proof failed: evaluations did not match column value at depth 0");
-    assert_eq!(Err(err_msg), result);
+    assert_eq!(Err(err_msg), result.map_err(|err| err.to_string()));
 
     // wrong program hash
     let mut program_hash2 = program.hash().clone();
     program_hash2[0] = 1;
     let result = super::verify(&program_hash2, inputs.get_public_inputs(), &outputs, &proof);
-    let err_msg = format!("verification of low-degree proof failed: evaluations did not match column value at depth 0");
-    assert_eq!(Err(err_msg), result);
+    assert_eq!(Err(expected), result);
+}
+
+#[test]
+fn execute_verify_assertions() {
+    let program = build_program(vec![
+        OpCode::Begin, OpCode::Swap, OpCode::Dup2, OpCode::Drop,
+        OpCode::Add,   OpCode::Swap, OpCode::Dup2, OpCode::Drop,
+        OpCode::Add,   OpCode::Swap, OpCode::Dup2, OpCode::Drop,
+        OpCode::Add,   OpCode::Noop, OpCode::Noop,
+    ], &[]);
+
+    let options = ProofOptions::default();
+    let inputs = ProgramInputs::from_public(&[1, 0]);
+    let num_outputs = 1;
+    let assertions = [Assertion::first(1, 0), Assertion::last(1, 2), Assertion::last(3, 0)];
+
+    let (outputs, proof) = super::execute_with_assertions(&program, &inputs, num_outputs, &assertions, &options).unwrap();
+    assert_eq!(outputs, [3]);
+
+    let result = super::verify_with_assertions(program.hash(), inputs.get_public_inputs(), &outputs, &assertions, &proof);
+    assert_eq!(Ok(()), result);
+
+    // wrong value in the final-step assertion
+    let assertions2 = [Assertion::first(1, 0), Assertion::last(1, 2), Assertion::last(3, 1)];
+    let result = super::verify_with_assertions(program.hash(), inputs.get_public_inputs(), &outputs, &assertions2, &proof);
+    let err_msg = format!("verification of boundary assertions failed: assertions do not match the proof");
+    assert_eq!(Err(err_msg), result.map_err(|err| err.to_string()));
+
+    // assertions omitted
+    let result = super::verify(program.hash(), inputs.get_public_inputs(), &outputs, &proof);
+    let err_msg = format!("verification of boundary assertions failed: assertions do not match the proof");
+    assert_eq!(Err(err_msg), result.map_err(|err| err.to_string()));
+}
+
+#[test]
+fn verify_false_assertion() {
+    let program = build_program(vec![
+        OpCode::Begin, OpCode::Swap, OpCode::Dup2, OpCode::Drop,
+        OpCode::Add,   OpCode::Swap, OpCode::Dup2, OpCode::Drop,
+        OpCode::Add,   OpCode::Swap, OpCode::Dup2, OpCode::Drop,
+        OpCode::Add,   OpCode::Noop, OpCode::Noop,
+    ], &[]);
+
+    let options = ProofOptions::default();
+    let inputs = ProgramInputs::from_public(&[1, 0]);
+
+    // build a proof directly from the trace so that the prover does not check assertions;
+    // register 1 ends as 2, and so the boundary constraint for the assertion is not satisfied
+    let assertions = [Assertion::last(1, 5)];
+    let (trace, ctx_depth, loop_depth) = processor::execute(&program, &inputs).unwrap();
+    let trace = TraceTable::new(trace, ctx_depth, loop_depth, options.extension_factor());
+    let (proof, _) = stark::prove(trace, inputs.get_public_inputs(), &[3], &assertions, &options).unwrap();
+
+    let result = super::verify_with_assertions(program.hash(), inputs.get_public_inputs(), &[3], &assertions, &proof);
+    assert!(matches!(result, Err(VerificationError::LowDegreeProofFailed { depth: 0, .. })));
+}
+
+#[test]
+fn prove_oversized_op_count() {
+    let program = build_program(vec![
+        OpCode::Begin, OpCode::Swap, OpCode::Dup2, OpCode::Drop,
+        OpCode::Add,   OpCode::Swap, OpCode::Dup2, OpCode::Drop,
+        OpCode::Add,   OpCode::Swap, OpCode::Dup2, OpCode::Drop,
+        OpCode::Add,   OpCode::Noop, OpCode::Noop,
+    ], &[]);
+
+    let options = ProofOptions::default();
+    let inputs = ProgramInputs::from_public(&[1, 0]);
+
+    // the operation counter at the last step does not fit into 32 bits, and so the prover
+    // refuses to build a proof instead of truncating it
+    let (mut trace, ctx_depth, loop_depth) = processor::execute(&program, &inputs).unwrap();
+    let last_step = trace[0].len() - 1;
+    trace[super::OP_COUNTER_IDX][last_step] = 1 << 32;
+    let trace = TraceTable::new(trace, ctx_depth, loop_depth, options.extension_factor());
+    let result = stark::prove(trace, inputs.get_public_inputs(), &[3], &[], &options);
+    let err_msg = format!("operation count {} is too large to be included in a proof", 1u128 << 32);
+    assert_eq!(Some(err_msg), result.err());
+}
+
+#[test]
+fn execute_assertions_fail() {
+    let program = build_program(vec![
+        OpCode::Begin, OpCode::Swap, OpCode::Dup2, OpCode::Drop,
+        OpCode::Add,   OpCode::Swap, OpCode::Dup2, OpCode::Drop,
+        OpCode::Add,   OpCode::Swap, OpCode::Dup2, OpCode::Drop,
+        OpCode::Add,   OpCode::Noop, OpCode::Noop,
+    ], &[]);
+
+    let options = ProofOptions::default();
+    let inputs = ProgramInputs::from_public(&[1, 0]);
+    let assertions = [Assertion::last(3, 1)];
+    let result = super::execute_with_assertions(&program, &inputs, 1, &assertions, &options);
+    let expected = ExecutionError::UnsatisfiedAssertion { register: 3, at: AssertionStep::Last, expected: 1, actual: 0 };
+    assert_eq!(Some(expected), result.err());
+
+    // assertions against registers beyond the stack depth, or against the same register twice,
+    // are rejected
+    let assertions = [Assertion::last(20, 1)];
+    let result = super::execute_with_assertions(&program, &inputs, 1, &assertions, &options);
+    let expected = ExecutionError::InvalidAssertion { register: 20, at: AssertionStep::Last, stack_depth: 4 };
+    assert_eq!(Some(expected), result.err());
+
+    let assertions = [Assertion::last(1, 2), Assertion::last(1, 2)];
+    let result = super::execute_with_assertions(&program, &inputs, 1, &assertions, &options);
+    let expected = ExecutionError::InvalidAssertion { register: 1, at: AssertionStep::Last, stack_depth: 4 };
+    assert_eq!(Some(expected), result.err());
+    assert_eq!("register 1 is asserted more than once at Last step", expected.to_string());
+}
+
+#[test]
+fn verify_extract_outputs() {
+    let program = build_program(vec![
+        OpCode::Begin, OpCode::Swap, OpCode::Dup2, OpCode::Drop,
+        OpCode::Add,   OpCode::Swap, OpCode::Dup2, OpCode::Drop,
+        OpCode::Add,   OpCode::Swap, OpCode::Dup2, OpCode::Drop,
+        OpCode::Add,   OpCode::Noop, OpCode::Noop,
+    ], &[]);
+
+    let options = ProofOptions::default();
+    let inputs = ProgramInputs::from_public(&[1, 0]);
+    let (outputs, mut proof) = super::execute(&program, &inputs, 2, &options).unwrap();
+    assert_eq!(outputs, [3, 2]);
+
+    let result = super::verify_and_extract(program.hash(), inputs.get_public_inputs(), 2, &proof);
+    assert_eq!(Ok(outputs.clone()), result);
+
+    let result = super::verify_and_extract(program.hash(), inputs.get_public_inputs(), 1, &proof);
+    assert_eq!(Ok(vec![3]), result);
+
+    let result = super::verify_and_extract(program.hash(), inputs.get_public_inputs(), 3, &proof);
+    let err_msg = format!("proof contains 2 outputs, but 3 were requested");
+    assert_eq!(Err(err_msg), result.map_err(|err| err.to_string()));
+
+    // tampered outputs should not be returned
+    proof.set_outputs(&[5, 2]);
+    let result = super::verify_and_extract(program.hash(), inputs.get_public_inputs(), 2, &proof);
+    assert!(result.is_err());
+}
+
+#[test]
+fn linked_segments() {
+    let program = build_program(vec![
+        OpCode::Begin, OpCode::Swap, OpCode::Dup2, OpCode::Drop,
+        OpCode::Add,   OpCode::Swap, OpCode::Dup2, OpCode::Drop,
+        OpCode::Add,   OpCode::Swap, OpCode::Dup2, OpCode::Drop,
+        OpCode::Add,   OpCode::Noop, OpCode::Noop,
+    ], &[]);
+    let options = ProofOptions::default();
+
+    // first half of the computation
+    let inputs = ProgramInputs::from_public(&[1, 0]);
+    let (outputs, prev_proof) = super::execute(&program, &inputs, 2, &options).unwrap();
+    assert_eq!(outputs, [3, 2]);
+    let prev = Statement::new(program.hash(), inputs.get_public_inputs(), &outputs);
+
+    // second half of the computation starts where the first half left off
+    let inputs = ProgramInputs::from_public(&prev.outputs);
+    let mut next = Statement::new(program.hash(), inputs.get_public_inputs(), &[13, 8]);
+    super::link_assertions(&prev, &mut next);
+    let (outputs, next_proof) = super::execute_with_assertions(&program, &inputs, 2, &next.assertions, &options).unwrap();
+    assert_eq!(outputs, next.outputs);
+
+    let result = super::verify_linked(&prev, &prev_proof, &next, &next_proof);
+    assert_eq!(Ok(()), result);
+
+    // altering the handoff value should break the link
+    let mut next2 = next.clone();
+    next2.assertions[0].value = 4;
+    let result = super::verify_linked(&prev, &prev_proof, &next2, &next_proof);
+    let err_msg = format!("verification of segment link failed: register 0 at the first step is not bound to output 3 of the previous segment");
+    assert_eq!(Err(err_msg), result.map_err(|err| err.to_string()));
+}
+
+#[test]
+fn public_input_commitment() {
+    // re-hash public inputs read from tape A, make sure the result matches the commitment,
+    // and check that the third public input (the fee) is equal to 5
+    let program = assembly::compile("
+        begin
+            push.4 push.0
+            read read hash.4
+            read dup push.5 assert.eq read hash.4
+            roll.4 assert.eq assert.eq
+        end").unwrap();
+
+    let public_inputs = [3, 8, 5, 9];
+    let options = ProofOptions::default();
+    let inputs = ProgramInputs::from_public_with_commitment(&public_inputs, &public_inputs, &[]);
+    assert_eq!(super::commit_public_inputs(&public_inputs), inputs.get_public_inputs()[..2]);
+
+    let (outputs, proof) = super::execute(&program, &inputs, 1, &options).unwrap();
+    assert_eq!(outputs, [3]);
+
+    let result = super::verify_with_commitment(program.hash(), &public_inputs, &outputs, &proof);
+    assert_eq!(Ok(()), result);
+
+    // verification against different public inputs should fail
+    let result = super::verify_with_commitment(program.hash(), &[3, 8, 6, 9], &outputs, &proof);
+    assert!(result.is_err());
+}
+
+#[test]
+fn public_input_commitment_padding() {
+    // a commitment to [x] must not be usable as a commitment to [x, 0]
+    let program = assembly::compile("begin push.1 push.0 read read hash.4 roll.4 assert.eq assert.eq end").unwrap();
+    assert_ne!(super::commit_public_inputs(&[7]), super::commit_public_inputs(&[7, 0]));
+
+    let options = ProofOptions::default();
+    let inputs = ProgramInputs::from_public_with_commitment(&[7], &[7, 0], &[]);
+    let (outputs, proof) = super::execute(&program, &inputs, 1, &options).unwrap();
+    assert_eq!(outputs, [7]);
+
+    let result = super::verify_with_commitment(program.hash(), &[7], &outputs, &proof);
+    assert_eq!(Ok(()), result);
+
+    let result = super::verify_with_commitment(program.hash(), &[7, 0], &outputs, &proof);
+    assert!(matches!(result, Err(VerificationError::LowDegreeProofFailed { depth: 0, .. })));
+}
+
+#[test]
+#[should_panic(expected = "ASSERTEQ failed")]
+fn public_input_commitment_mismatch() {
+    let program = assembly::compile("
+        begin
+            push.4 push.0
+            read read hash.4
+            read dup push.5 assert.eq read hash.4
+            roll.4 assert.eq assert.eq
+        end").unwrap();
+
+    // the copy of public inputs on the tape is altered
+    let public_inputs = [3, 8, 5, 9];
+    let options = ProofOptions::default();
+    let inputs = ProgramInputs::from_public_with_commitment(&public_inputs, &[3, 7, 5, 9], &[]);
+    super::execute(&program, &inputs, 1, &options).unwrap();
+}
+
+#[test]
+fn committed_public_inputs() {
+    // re-hash 64 public inputs read from tape A, make sure the result matches the commitment
+    // at the top of the stack, and return the commitment
+    let source = format!("begin push.64 push.0 {} dup.4 roll.4 assert.eq assert.eq end", "read read hash.4 ".repeat(32));
+    let program = assembly::compile(&source).unwrap();
+
+    // the inputs do not fit onto the stack together with the commitment, and so only the
+    // commitment is placed onto the stack
+    let public_inputs: Vec<u128> = (0..64).map(|i| i * 31 + 7).collect();
+    let options = ProofOptions::default();
+    let inputs = ProgramInputs::from_public_with_commitment(&public_inputs, &public_inputs, &[]);
+    let commitment = super::commit_public_inputs(&public_inputs);
+    assert_eq!(&commitment, inputs.get_public_inputs());
+
+    let (outputs, proof) = super::execute(&program, &inputs, 2, &options).unwrap();
+    assert_eq!(vec![commitment[1], commitment[0]], outputs);
+
+    let result = super::verify_with_commitment(program.hash(), &public_inputs, &outputs, &proof);
+    assert_eq!(Ok(()), result);
+
+    // verification against different public inputs should fail
+    let mut public_inputs2 = public_inputs.clone();
+    public_inputs2[63] = 1;
+    let result = super::verify_with_commitment(program.hash(), &public_inputs2, &outputs, &proof);
+    assert!(result.is_err());
+
+    // the inputs are placed onto the stack only while they fit there with the commitment
+    assert_eq!(8, ProgramInputs::from_public_with_commitment(&[1; 6], &[], &[]).get_public_inputs().len());
+    assert_eq!(2, ProgramInputs::from_public_with_commitment(&[1; 7], &[], &[]).get_public_inputs().len());
+}
+
+#[test]
+fn packed_bytes() {
+    let message: Vec<u8> = (0..64).map(|i| (i * 37 + 11) as u8).collect();
+    let packed = super::pack_bytes(&message);
+    assert_eq!(5, packed.len());
+
+    // unpack each element into bytes and hash the bytes; the first four elements contain 15
+    // bytes each, and so they are padded with a ZERO to make the number of bytes even
+    let program = assembly::compile("
+        begin
+            read pad.1 swap unpack.15 hash.4 hash.4 hash.4 hash.4 hash.4 hash.4 hash.4
+            read pad.1 swap unpack.15 hash.4 hash.4 hash.4 hash.4 hash.4 hash.4 hash.4 hash.4
+            read pad.1 swap unpack.15 hash.4 hash.4 hash.4 hash.4 hash.4 hash.4 hash.4 hash.4
+            read pad.1 swap unpack.15 hash.4 hash.4 hash.4 hash.4 hash.4 hash.4 hash.4 hash.4
+            read unpack.4 hash.4 hash.4
+        end").unwrap();
+
+    let options = ProofOptions::default();
+    let inputs = ProgramInputs::new(&[], &packed, &[]);
+    let (outputs, proof) = super::execute(&program, &inputs, 2, &options).unwrap();
+    assert_eq!(hash_bytes(&message), outputs);
+
+    let result = super::verify(program.hash(), inputs.get_public_inputs(), &outputs, &proof);
+    assert_eq!(Ok(()), result);
 }
 
 #[test]
@@ -79,11 +467,105 @@ fn stack_manipulation() {
     let inputs = ProgramInputs::from_public(&[7, 6, 5, 4, 3, 2, 1, 0]);
     let num_outputs = 8;
 
-    let (outputs, proof) = super::execute(&program, &inputs, num_outputs, &options);
+    let (outputs, proof) = super::execute(&program, &inputs, num_outputs, &options).unwrap();
     assert_eq!(outputs, [46, 19, 4, 11, 0, 11, 0, 6]);
 
     let result = super::verify(program.hash(), inputs.get_public_inputs(), &outputs, &proof);
-    assert_eq!(Ok(true), result);
+    assert_eq!(Ok(()), result);
+}
+
+#[test]
+fn dup8_operation() {
+    let program = assembly::compile("begin read.4 read.4 dup.8 pad.8 end").unwrap();
+    let options = ProofOptions::default();
+    let inputs = ProgramInputs::new(&[], &[1, 2, 3, 4], &[5, 6, 7, 8]);
+    let num_outputs = 24;
+
+    let (outputs, proof) = super::execute(&program, &inputs, num_outputs, &options).unwrap();
+    let word = [8, 4, 7, 3, 6, 2, 5, 1];
+    assert_eq!(outputs, [&[0; 8][..], &word, &word].concat());
+
+    let result = super::verify(program.hash(), inputs.get_public_inputs(), &outputs, &proof);
+    assert_eq!(Ok(()), result);
+
+    // pushing 8 values onto a 26-deep stack fails instead of truncating the stack; pad.8 is
+    // PAD2 PAD2 DUP4, and so it fails on DUP4
+    for &(instruction, op) in [("dup.8", OpCode::Dup8), ("pad.8", OpCode::Dup4)].iter() {
+        let program = assembly::compile(&format!("begin repeat.13 pad.2 end {} end", instruction)).unwrap();
+        let err = super::run(&program, &ProgramInputs::none(), 1).unwrap_err();
+        assert_eq!(Some(op), err.op(), "{}", instruction);
+        assert!(matches!(err, ExecutionError::StackOverflow { .. }), "{}", instruction);
+    }
+}
+
+#[test]
+fn drop_below_empty_stack() {
+    // drop.8 is DROP4 DROP4; the second DROP4 removes more values than the stack holds
+    let program = assembly::compile("begin drop.8 end").unwrap();
+    let inputs = ProgramInputs::from_public(&[1, 2, 3, 4, 5, 6]);
+    let expected = ExecutionError::StackUnderflow { step: 3, op: OpCode::Drop4 };
+    assert_eq!(Err(expected), super::run(&program, &inputs, 1));
+
+    let inputs = ProgramInputs::from_public(&[1, 2, 3, 4, 5, 6, 7, 8]);
+    assert!(super::run(&program, &inputs, 1).is_ok());
+}
+
+#[test]
+fn stack_movement() {
+    // fill the stack with 16 distinct values
+    let prefix = "read.4 read.4 read.4 read.4";
+    let inputs = ProgramInputs::new(&[], &[1, 2, 3, 4, 5, 6, 7, 8], &[9, 10, 11, 12, 13, 14, 15, 16]);
+    let program = assembly::compile(&format!("begin {} end", prefix)).unwrap();
+    let (stack, _) = super::run(&program, &inputs, 16).unwrap();
+
+    for n in 2..=8 {
+        let program = assembly::compile(&format!("begin {} movup.{} end", prefix, n)).unwrap();
+        let mut expected = stack.clone();
+        let item = expected.remove(n - 1);
+        expected.insert(0, item);
+        assert_eq!(expected, super::run(&program, &inputs, 16).unwrap().0, "movup.{}", n);
+
+        let program = assembly::compile(&format!("begin {} movdn.{} end", prefix, n)).unwrap();
+        let mut expected = stack.clone();
+        let item = expected.remove(0);
+        expected.insert(n - 1, item);
+        assert_eq!(expected, super::run(&program, &inputs, 16).unwrap().0, "movdn.{}", n);
+    }
+
+    let program = assembly::compile(&format!("begin {} movup.7 movdn.5 end", prefix)).unwrap();
+    let options = ProofOptions::default();
+    let (outputs, proof) = super::execute(&program, &inputs, 16, &options).unwrap();
+    assert_eq!(super::run(&program, &inputs, 16).unwrap().0, outputs);
+
+    let result = super::verify(program.hash(), inputs.get_public_inputs(), &outputs, &proof);
+    assert_eq!(Ok(()), result);
+
+    // items deeper than 8 cannot be moved
+    assert!(assembly::compile("begin movup.9 end").is_err());
+    assert!(assembly::compile("begin movdn.16 end").is_err());
+}
+
+#[test]
+fn read4_operation() {
+    let program = assembly::compile("begin read.4 read.ab add end").unwrap();
+    let options = ProofOptions::default();
+
+    // tapes are consumed exactly
+    let inputs = ProgramInputs::new(&[], &[1, 2, 3], &[4, 5, 6]);
+    let (outputs, proof) = super::execute(&program, &inputs, 5, &options).unwrap();
+    assert_eq!(outputs, [9, 5, 2, 4, 1]);
+
+    let result = super::verify(program.hash(), inputs.get_public_inputs(), &outputs, &proof);
+    assert_eq!(Ok(()), result);
+
+    // READ4 needs two values on each of the tapes
+    let inputs = ProgramInputs::new(&[], &[1, 2, 3], &[4]);
+    let err = super::execute(&program, &inputs, 1, &options).err().unwrap();
+    assert_eq!(ExecutionError::EmptyTape { step: 2, op: OpCode::Read4, tape: 'B' }, err);
+
+    let inputs = ProgramInputs::new(&[], &[1], &[4]);
+    let err = super::execute(&program, &inputs, 1, &options).err().unwrap();
+    assert_eq!(ExecutionError::EmptyTape { step: 2, op: OpCode::Read4, tape: 'A' }, err);
 }
 
 #[test]
@@ -100,11 +582,11 @@ fn conditional_operations() {
     let inputs = ProgramInputs::from_public(&[3, 4, 1, 5, 0, 6, 7, 8]);
     let num_outputs = 8;
 
-    let (outputs, proof) = super::execute(&program, &inputs, num_outputs, &options);
+    let (outputs, proof) = super::execute(&program, &inputs, num_outputs, &options).unwrap();
     assert_eq!(outputs, [5, 6, 7, 8, 0, 0, 0, 0]);
 
     let result = super::verify(program.hash(), inputs.get_public_inputs(), &outputs, &proof);
-    assert_eq!(Ok(true), result);
+    assert_eq!(Ok(()), result);
 
     // CHOOSE2
     let program = build_program(vec![
@@ -122,11 +604,11 @@ fn conditional_operations() {
     let inputs = ProgramInputs::from_public(&[5, 6, 1, 0, 7, 8, 0, 0]);
     let num_outputs = 8;
 
-    let (outputs, proof) = super::execute(&program, &inputs, num_outputs, &options);
+    let (outputs, proof) = super::execute(&program, &inputs, num_outputs, &options).unwrap();
     assert_eq!(outputs, [7, 8, 0, 0, 0, 0, 0, 0]);
 
     let result = super::verify(program.hash(), inputs.get_public_inputs(), &outputs, &proof);
-    assert_eq!(Ok(true), result);
+    assert_eq!(Ok(()), result);
 
     // CSWAP2
     let program = build_program(vec![
@@ -140,16 +622,57 @@ fn conditional_operations() {
     let inputs = ProgramInputs::from_public(&[3, 4, 1, 2, 1, 0, 5, 6]);
     let num_outputs = 8;
 
-    let (outputs, proof) = super::execute(&program, &inputs, num_outputs, &options);
+    let (outputs, proof) = super::execute(&program, &inputs, num_outputs, &options).unwrap();
     assert_eq!(outputs, [3, 4, 5, 6, 1, 2, 0, 0]);
 
     let result = super::verify(program.hash(), inputs.get_public_inputs(), &outputs, &proof);
-    assert_eq!(Ok(true), result);
+    assert_eq!(Ok(()), result);
+
+    // CSWAP4
+    let program = assembly::compile("begin read.4 read.4 cswap.4 end").unwrap();
+    let num_outputs = 8;
+
+    let inputs = ProgramInputs::new(&[1, 0], &[1, 2, 3, 4], &[5, 6, 7, 8]);
+    let (outputs, proof) = super::execute(&program, &inputs, num_outputs, &options).unwrap();
+    assert_eq!(outputs, [6, 2, 5, 1, 8, 4, 7, 3]);
+
+    let result = super::verify(program.hash(), inputs.get_public_inputs(), &outputs, &proof);
+    assert_eq!(Ok(()), result);
+
+    let inputs = ProgramInputs::new(&[0, 0], &[1, 2, 3, 4], &[5, 6, 7, 8]);
+    let (outputs, proof) = super::execute(&program, &inputs, num_outputs, &options).unwrap();
+    assert_eq!(outputs, [8, 4, 7, 3, 6, 2, 5, 1]);
+
+    let result = super::verify(program.hash(), inputs.get_public_inputs(), &outputs, &proof);
+    assert_eq!(Ok(()), result);
+}
+
+#[test]
+fn merkle_path_with_cswap() {
+    // a Merkle path verified with cswap.2 and hash.4 yields the same root as smpath
+    let smpath = assembly::compile("begin read.ab smpath.3 end").unwrap();
+    let cswap = assembly::compile("begin read.ab
+        read.ab swap.2 read.ab cswap.2 hash.4
+        read.ab swap.2 read.ab cswap.2 hash.4 end").unwrap();
+
+    let options = ProofOptions::default();
+    let inputs = ProgramInputs::new(&[], &[1, 0, 3, 0, 5], &[2, 1, 4, 0, 6]);
+    let (expected, _) = super::execute(&smpath, &inputs, 2, &options).unwrap();
+
+    let (outputs, proof) = super::execute(&cswap, &inputs, 2, &options).unwrap();
+    assert_eq!(expected, outputs);
+
+    let result = super::verify(cswap.hash(), inputs.get_public_inputs(), &outputs, &proof);
+    assert_eq!(Ok(()), result);
+
+    // index bits of the path must be binary
+    let inputs = ProgramInputs::new(&[], &[1, 0, 3, 0, 5], &[2, 2, 4, 0, 6]);
+    let err = super::execute(&cswap, &inputs, 2, &options).err().unwrap();
+    assert_eq!(OpCode::CSwap2, err.op().unwrap());
 }
 
 #[test]
-#[should_panic(expected = "CHOOSE on a non-binary condition at step 2")]
-fn selection_operations_panic() {
+fn selection_operations_fail() {
     let program = build_program(vec![
         OpCode::Begin, OpCode::Choose, OpCode::Choose, OpCode::Noop,
         OpCode::Noop,  OpCode::Noop,   OpCode::Noop,   OpCode::Noop,
@@ -161,7 +684,127 @@ fn selection_operations_panic() {
     let inputs = ProgramInputs::from_public(&[3, 4, 2, 5, 0, 6, 7, 8]);
     let num_outputs = 8;
 
-    super::execute(&program, &inputs, num_outputs, &options);
+    let result = super::execute(&program, &inputs, num_outputs, &options);
+    let expected = ExecutionError::NonBinaryValue { step: 2, op: OpCode::Choose, value: 2 };
+    assert_eq!(Some(expected), result.err());
+}
+
+#[test]
+fn execution_errors() {
+    let options = ProofOptions::default();
+    let nested_blocks = format!("begin noop {} {} end", "block noop ".repeat(20), "end ".repeat(20));
+    let cases = [
+        ("begin push.2 assert end", vec![], Some(OpCode::Assert)),
+        ("begin read read add end", vec![1], Some(OpCode::Read)),
+        ("begin drop end", vec![], Some(OpCode::Drop)),
+        ("begin read read read drop.5 end", vec![1, 2, 3], Some(OpCode::Drop4)),
+        ("begin push.0 push.0 push.0 push.0 dup.4 dup.4 dup.4 dup.4 dup.4 dup.4 dup.4 dup.4 end", vec![], Some(OpCode::Dup4)),
+        ("begin push.2 if.true add else drop end end", vec![], None),
+        ("begin push.3 while.true push.0 end end", vec![], None),
+        (&nested_blocks[..], vec![], None),
+    ];
+
+    for (source, tape, op) in cases.iter() {
+        let program = assembly::compile(source).unwrap();
+        let inputs = ProgramInputs::new(&[], tape, &[]);
+        let err = super::execute(&program, &inputs, 1, &options).err().expect(source);
+        assert_eq!(*op, err.op(), "{}: {}", source, err);
+    }
+}
+
+#[test]
+fn execution_errors_outside_of_operations() {
+    let options = ProofOptions::default();
+    let program = assembly::compile("begin push.1 block push.2 drop end while.true push.0 end end").unwrap();
+    let inputs = ProgramInputs::none();
+
+    // too many outputs
+    let expected = ExecutionError::TooManyOutputs { requested: super::MAX_OUTPUTS + 1, max: super::MAX_OUTPUTS };
+    assert_eq!(Some(expected), super::execute(&program, &inputs, super::MAX_OUTPUTS + 1, &options).err());
+    assert_eq!(Some(expected), super::run(&program, &inputs, super::MAX_OUTPUTS + 1).err());
+    assert_eq!(None, expected.step());
+
+    // too few operations
+    let last_state = stark::TraceState::new(1, 0, 8);
+    let expected = ExecutionError::TooFewOperations { executed: 0, min: super::MIN_TRACE_LENGTH };
+    assert_eq!(Err(expected), super::check_last_state(&program, &last_state));
+
+    // too many operations to be described by a proof
+    let mut last_state = stark::TraceState::new(1, 0, 8);
+    last_state.set_op_counter(1 << 32);
+    let expected = ExecutionError::TooManyOperations { executed: 1 << 32, max: u32::MAX as u128 };
+    assert_eq!(Err(expected), super::check_last_state(&program, &last_state));
+
+    // program hash does not match the program
+    let mut json = serde_json::to_value(&program).unwrap();
+    json["hash"][0] = serde_json::json!(program.hash()[0] ^ 1);
+    let tampered: Program = serde_json::from_value(json).unwrap();
+    let expected = ExecutionError::ProgramHashMismatch { expected: *tampered.hash(), actual: *program.hash() };
+    assert_eq!(Some(expected), super::execute(&tampered, &inputs, 1, &options).err());
+
+    // a sequence of blocks which does not start with a Span block
+    let mut json = serde_json::to_value(&program).unwrap();
+    let body = json["root"]["body"][1]["Group"]["body"].take();
+    json["root"]["body"][1]["Group"]["body"] = serde_json::json!([{ "Group": { "body": body } }]);
+    let malformed: Program = serde_json::from_value(json).unwrap();
+    let err = super::execute(&malformed, &inputs, 1, &options).err().unwrap();
+    assert!(matches!(err, ExecutionError::MalformedBlock { .. }), "{}", err);
+
+    // a loop skip block which does not start with a Span block
+    let mut json = serde_json::to_value(&program).unwrap();
+    let skip = json["root"]["body"][2]["Loop"]["skip"].take();
+    json["root"]["body"][2]["Loop"]["skip"] = serde_json::json!([{ "Group": { "body": skip } }]);
+    let malformed: Program = serde_json::from_value(json).unwrap();
+    let err = super::execute(&malformed, &inputs, 1, &options).err().unwrap();
+    assert!(matches!(err, ExecutionError::MalformedBlock { .. }), "{}", err);
+
+    // loops nested deeper than MAX_LOOP_DEPTH
+    let source = format!("begin push.1 {} end", "while.true push.1 ".repeat(super::MAX_LOOP_DEPTH + 1) + &"end ".repeat(super::MAX_LOOP_DEPTH + 1));
+    let program = assembly::compile(&source).unwrap();
+    let err = super::execute(&program, &inputs, 1, &options).err().unwrap();
+    assert!(matches!(err, ExecutionError::LoopOverflow { .. }), "{}", err);
+}
+
+#[test]
+fn choose_on_doctored_condition() {
+    let program = assembly::compile("begin read push.5 push.4 choose end").unwrap();
+    let options = ProofOptions::default();
+    let inputs = ProgramInputs::new(&[], &[1], &[]);
+
+    // replace the condition read from the tape with 2, and make CHOOSE output 2 * 4 - 5 = 3;
+    // this satisfies the selection constraint, but not the constraint for a binary condition
+    let (mut trace, ctx_depth, loop_depth) = processor::execute(&program, &inputs).unwrap();
+    let stack_depth = stark::TraceState::compute_stack_depth(trace.len(), ctx_depth, loop_depth);
+    let stack = stark::TraceLayout::new(ctx_depth, loop_depth, stack_depth).range(stark::RegisterGroup::Stack);
+    let choose_step = (0..trace[0].len())
+        .rev()
+        .find(|&i| trace[stack.start][i] == 4 && trace[stack.start + 1][i] == 5)
+        .unwrap();
+
+    // the stack is empty before READ, and so until CHOOSE, the condition is the deepest item
+    // of the stack; only the register which holds the condition is changed at every step
+    let mut state = stark::TraceState::new(ctx_depth, loop_depth, stack_depth);
+    for step in 0..=choose_step {
+        state.update_from_trace(&trace, step);
+        let depth = state.watermark() as usize;
+        if depth > 0 {
+            let register = stack.start + depth - 1;
+            assert_eq!(1, trace[register][step]);
+            trace[register][step] = 2;
+        }
+    }
+    for step in (choose_step + 1)..trace[0].len() {
+        trace[stack.start][step] = 3;
+    }
+
+    // the prover refuses to prove such a trace, and so its checks are skipped to build a proof
+    let trace = TraceTable::new(trace, ctx_depth, loop_depth, options.extension_factor());
+    stark::SKIP_TRANSITION_CHECKS.with(|s| s.set(true));
+    let (proof, _) = stark::prove(trace, &[], &[3], &[], &options).unwrap();
+    stark::SKIP_TRANSITION_CHECKS.with(|s| s.set(false));
+
+    let result = super::verify(program.hash(), &[], &[3], &proof);
+    assert!(matches!(result, Err(VerificationError::LowDegreeProofFailed { depth: 0, .. })));
 }
 
 #[test]
@@ -179,11 +822,11 @@ fn math_operations() {
 
     let expected_result = vec![field::ONE, field::neg(field::inv(65))];
 
-    let (outputs, proof) = super::execute(&program, &inputs, num_outputs, &options);
+    let (outputs, proof) = super::execute(&program, &inputs, num_outputs, &options).unwrap();
     assert_eq!(expected_result, outputs);
 
     let result = super::verify(program.hash(), inputs.get_public_inputs(), &outputs, &proof);
-    assert_eq!(Ok(true), result);
+    assert_eq!(Ok(()), result);
 }
 
 #[test]
@@ -201,11 +844,185 @@ fn bool_operations() {
 
     let expected_result = vec![field::ONE];
 
-    let (outputs, proof) = super::execute(&program, &inputs, num_outputs, &options);
+    let (outputs, proof) = super::execute(&program, &inputs, num_outputs, &options).unwrap();
     assert_eq!(expected_result, outputs);
 
     let result = super::verify(program.hash(), inputs.get_public_inputs(), &outputs, &proof);
-    assert_eq!(Ok(true), result);
+    assert_eq!(Ok(()), result);
+}
+
+#[test]
+fn div_instruction() {
+    let options = ProofOptions::default();
+    let program = assembly::compile("begin div end").unwrap();
+
+    let (x, y) = (field::rand(), field::rand());
+    let inputs = ProgramInputs::from_public(&[y, x]);
+    let (outputs, proof) = super::execute(&program, &inputs, 1, &options).unwrap();
+    assert_eq!(vec![field::div(x, y)], outputs);
+    assert_eq!(Ok(()), super::verify(program.hash(), inputs.get_public_inputs(), &outputs, &proof));
+
+    // division by zero is an execution error
+    let result = crate::run(&program, &ProgramInputs::from_public(&[0, x]), 1);
+    assert!(matches!(result, Err(ExecutionError::InverseOfZero { .. })));
+}
+
+#[test]
+fn bits_instruction() {
+    let options = ProofOptions::default();
+    for &n in [1, 8, 30].iter() {
+        let program = assembly::compile(&format!("begin bits.{} end", n)).unwrap();
+        for &x in [0, 1, (1 << n) - 1, field::rand() % (1 << n)].iter() {
+            let expected: Vec<u128> = (0..n).map(|i| (x >> i) & 1).collect();
+            let inputs = ProgramInputs::from_public(&[x]);
+            let (outputs, proof) = super::execute(&program, &inputs, n, &options).unwrap();
+            assert_eq!(expected, outputs, "bits.{} failed for {}", n, x);
+            assert_eq!(Ok(()), super::verify(program.hash(), inputs.get_public_inputs(), &outputs, &proof));
+        }
+
+        // values which do not fit into n bits are rejected
+        assert!(crate::run(&program, &ProgramInputs::from_public(&[1 << n]), n).is_err());
+        assert!(crate::run(&program, &ProgramInputs::from_public(&[field::M - 1]), n).is_err());
+    }
+
+    assert!(assembly::compile("begin bits.31 end").is_err());
+}
+
+#[test]
+fn select_instruction() {
+    let options = ProofOptions::default();
+    for &n in [2, 4].iter() {
+        let program = assembly::compile(&format!("begin select.{} end", n)).unwrap();
+        let items: Vec<u128> = (0..n).map(|_| field::rand()).collect();
+        for k in 0..n {
+            let mut inputs = vec![k as u128];
+            inputs.extend_from_slice(&items);
+            let mut expected = vec![items[k]];
+            expected.extend_from_slice(&items);
+
+            let inputs = ProgramInputs::from_public(&inputs);
+            let (outputs, proof) = super::execute(&program, &inputs, n + 1, &options).unwrap();
+            assert_eq!(expected, outputs, "select.{} failed for index {}", n, k);
+            assert_eq!(Ok(()), super::verify(program.hash(), inputs.get_public_inputs(), &outputs, &proof));
+        }
+
+        // indexes outside of the window are rejected
+        let mut inputs = vec![n as u128];
+        inputs.extend_from_slice(&items);
+        assert!(crate::run(&program, &ProgramInputs::from_public(&inputs), n + 1).is_err());
+    }
+
+    assert!(assembly::compile("begin select.8 end").is_err());
+}
+
+#[test]
+fn exp_instruction() {
+    let options = ProofOptions::default();
+    let program = assembly::compile("begin exp end").unwrap();
+
+    let base = field::rand();
+    let cases = [
+        (base, 0), (base, 1), (base, 2), (base, field::rand()), (base, field::M - 1),
+        (0, 5), (field::rand(), field::M - 1),
+    ];
+    for &(x, e) in cases.iter() {
+        let inputs = ProgramInputs::from_public(&[e, x]);
+        let (outputs, proof) = super::execute(&program, &inputs, 1, &options).unwrap();
+        assert_eq!(vec![field::exp(x, e)], outputs, "exp failed for {} and {}", x, e);
+        assert_eq!(Ok(()), super::verify(program.hash(), inputs.get_public_inputs(), &outputs, &proof));
+    }
+
+    // exponents which do not fit into n bits are rejected
+    let program = assembly::compile("begin exp.8 end").unwrap();
+    assert_eq!(vec![field::exp(3, 255)], crate::run(&program, &ProgramInputs::from_public(&[255, 3]), 1).unwrap().0);
+    assert!(crate::run(&program, &ProgramInputs::from_public(&[256, 3]), 1).is_err());
+}
+
+#[test]
+fn immediate_arithmetic() {
+    let options = ProofOptions::default();
+    let cases = [
+        ("add.5", 7, 12), ("sub.3", 7, 4), ("sub.8", 7, field::neg(1)), ("mul.3", 7, 21), ("mul.0x10", 7, 112),
+    ];
+    for &(op, x, expected) in cases.iter() {
+        let program = assembly::compile(&format!("begin {} end", op)).unwrap();
+        let inputs = ProgramInputs::from_public(&[x]);
+        let (outputs, proof) = super::execute(&program, &inputs, 1, &options).unwrap();
+        assert_eq!(vec![expected], outputs, "{} failed for {}", op, x);
+        assert_eq!(Ok(()), super::verify(program.hash(), inputs.get_public_inputs(), &outputs, &proof));
+    }
+}
+
+#[test]
+fn swapw_instruction() {
+    let program = assembly::compile("begin swapw end").unwrap();
+    assert_eq!(program.hash(), assembly::compile("begin swap.4 end").unwrap().hash());
+
+    // only the top two words are swapped; the rest of the stack is left untouched
+    let options = ProofOptions::default();
+    let values: Vec<u128> = (1..17).collect();
+    let inputs = ProgramInputs::from_public(&values);
+    let (outputs, proof) = super::execute(&program, &inputs, 16, &options).unwrap();
+    assert_eq!(vec![5, 6, 7, 8, 1, 2, 3, 4, 9, 10, 11, 12, 13, 14, 15, 16], outputs);
+    assert_eq!(Ok(()), super::verify(program.hash(), inputs.get_public_inputs(), &outputs, &proof));
+}
+
+#[test]
+fn hash_2to1_instruction() {
+    let left = hasher::digest(&[field::rand(), field::rand()]);
+    let right = hasher::digest(&[field::rand(), field::rand()]);
+    let mut expected_hash = hasher::digest(&[left[0], left[1], right[0], right[1]]);
+    expected_hash.reverse();
+
+    // digests are placed onto the stack in reverse order, with the left child pushed first
+    let program = assembly::compile("begin noop hash.2to1 end").unwrap();
+    let options = ProofOptions::default();
+    let inputs = ProgramInputs::from_public(&[right[1], right[0], left[1], left[0]]);
+    let (outputs, proof) = super::execute(&program, &inputs, 2, &options).unwrap();
+    assert_eq!(expected_hash, outputs);
+    assert_eq!(Ok(()), super::verify(program.hash(), inputs.get_public_inputs(), &outputs, &proof));
+
+    // the instruction is the same as hash.4
+    assert_eq!(program.hash(), assembly::compile("begin noop hash.4 end").unwrap().hash());
+}
+
+#[test]
+fn align_instruction() {
+    // push and hash instructions placed after aligned instructions get padded correctly
+    let program = assembly::compile("begin align.3 push.7 align.13 read hash.2 end").unwrap();
+    let mut expected_hash = hasher::digest(&[7, 5]);
+    expected_hash.reverse();
+
+    let options = ProofOptions::default();
+    let inputs = ProgramInputs::new(&[], &[5], &[]);
+    let (outputs, proof) = super::execute(&program, &inputs, 2, &options).unwrap();
+    assert_eq!(expected_hash, outputs);
+    assert_eq!(Ok(()), super::verify(program.hash(), inputs.get_public_inputs(), &outputs, &proof));
+
+    // alignment results in the same program as hand-counted NOOPs
+    let source = "begin noop noop push.7 noop noop noop noop read hash.2 end";
+    assert_eq!(program.hash(), assembly::compile(source).unwrap().hash());
+}
+
+#[test]
+fn mtree_verify_instruction() {
+    let options = ProofOptions::default();
+    for &(depth, index) in [(4, 5), (16, 0x5a3c)].iter() {
+        let program = assembly::compile(&format!("begin dup.2 read.ab mtree.verify.{} end", depth)).unwrap();
+        let (mut a, b, root) = build_merkle_path(depth, index);
+
+        // the expected root is passed in via public inputs and is left on the stack
+        let inputs = ProgramInputs::new(&[root[1], root[0]], &a, &b);
+        let (outputs, proof) = super::execute(&program, &inputs, 2, &options).unwrap();
+        assert_eq!(vec![root[1], root[0]], outputs);
+        assert_eq!(Ok(()), super::verify(program.hash(), inputs.get_public_inputs(), &outputs, &proof));
+
+        // a path with a flipped sibling does not resolve to the root
+        a[4] = field::add(a[4], field::ONE);
+        let inputs = ProgramInputs::new(&[root[1], root[0]], &a, &b);
+        let err = super::execute(&program, &inputs, 2, &options).err().unwrap();
+        assert_eq!(OpCode::AssertEq, err.op().unwrap());
+    }
 }
 
 #[test]
@@ -230,11 +1047,11 @@ fn hash_operations() {
     let inputs = ProgramInputs::from_public(&[0, 0, 4, 3, 2, 1]);
     let num_outputs = 2;
 
-    let (outputs, proof) = super::execute(&program, &inputs, num_outputs, &options);
+    let (outputs, proof) = super::execute(&program, &inputs, num_outputs, &options).unwrap();
     assert_eq!(expected_hash, outputs);
 
     let result = super::verify(program.hash(), inputs.get_public_inputs(), &outputs, &proof);
-    assert_eq!(Ok(true), result);
+    assert_eq!(Ok(()), result);
 
     // double hash
     let program = build_program(vec![
@@ -261,11 +1078,47 @@ fn hash_operations() {
     let inputs = ProgramInputs::from_public(&[0, 0, 4, 3, 2, 1]);
     let num_outputs = 2;
 
-    let (outputs, proof) = super::execute(&program, &inputs, num_outputs, &options);
+    let (outputs, proof) = super::execute(&program, &inputs, num_outputs, &options).unwrap();
     assert_eq!(expected_hash, outputs);
 
     let result = super::verify(program.hash(), inputs.get_public_inputs(), &outputs, &proof);
-    assert_eq!(Ok(true), result);
+    assert_eq!(Ok(()), result);
+}
+
+#[test]
+fn interleaved_hashing() {
+    // hash state lives at the top of the stack, so a hash computation can be suspended in the
+    // middle of a permutation and resumed later: the first 5 rounds are applied to state A,
+    // state B is read from the tape and fully permuted, the digest of B is rolled under the
+    // 6 elements of state A, and the last 5 rounds are applied to state A on steps with the
+    // same round constants as if A was hashed without interruption
+    let mut instructions = vec![OpCode::Begin];
+    instructions.resize(16, OpCode::Noop);
+    instructions.resize(21, OpCode::RescR);     // rounds 0 - 4 of A
+    instructions.resize(27, OpCode::Read);
+    instructions.resize(32, OpCode::Noop);
+    instructions.resize(42, OpCode::RescR);     // rounds 0 - 9 of B
+    instructions.push(OpCode::Drop4);
+    instructions.resize(49, OpCode::Roll8);
+    instructions.resize(53, OpCode::Noop);
+    instructions.resize(58, OpCode::RescR);     // rounds 5 - 9 of A
+    instructions.push(OpCode::Drop4);
+    instructions.resize(63, OpCode::Noop);
+    let program = build_program(instructions, &[]);
+
+    let options = ProofOptions::default();
+    let inputs = ProgramInputs::new(&[1, 2, 3, 4, 5, 6], &[7, 8, 9, 10, 11, 12], &[]);
+    let (outputs, proof) = super::execute(&program, &inputs, 4, &options).unwrap();
+
+    // hash both states sequentially
+    let mut state_a = [1, 2, 3, 4, 5, 6];
+    let mut state_b = [12, 11, 10, 9, 8, 7];
+    for i in 0..10 { hasher::apply_round(&mut state_a, i); }
+    for i in 0..10 { hasher::apply_round(&mut state_b, i); }
+    assert_eq!(vec![state_a[4], state_a[5], state_b[4], state_b[5]], outputs);
+
+    let result = super::verify(program.hash(), inputs.get_public_inputs(), &outputs, &proof);
+    assert_eq!(Ok(()), result);
 }
 
 #[test]
@@ -281,11 +1134,11 @@ fn read_operations() {
     let inputs = ProgramInputs::new(&[1], &[2, 3], &[4]);
     let num_outputs = 5;
 
-    let (outputs, proof) = super::execute(&program, &inputs, num_outputs, &options);
+    let (outputs, proof) = super::execute(&program, &inputs, num_outputs, &options).unwrap();
     assert_eq!(vec![5, 4, 3, 2, 1], outputs);
 
     let result = super::verify(program.hash(), inputs.get_public_inputs(), &outputs, &proof);
-    assert_eq!(Ok(true), result);
+    assert_eq!(Ok(()), result);
 }
 
 #[test]
@@ -303,34 +1156,633 @@ fn assert_operations() {
 
     let expected_result = vec![0, 0];
 
-    let (outputs, proof) = super::execute(&program, &inputs, num_outputs, &options);
+    let (outputs, proof) = super::execute(&program, &inputs, num_outputs, &options).unwrap();
     assert_eq!(expected_result, outputs);
 
     let result = super::verify(program.hash(), inputs.get_public_inputs(), &outputs, &proof);
-    assert_eq!(Ok(true), result);
+    assert_eq!(Ok(()), result);
+}
+
+#[test]
+fn constraint_degrees() {
+    let program = build_program(vec![
+        OpCode::Begin, OpCode::Swap, OpCode::Dup2, OpCode::Drop,
+        OpCode::Add,   OpCode::Swap, OpCode::Dup2, OpCode::Drop,
+        OpCode::Add,   OpCode::Swap, OpCode::Dup2, OpCode::Drop,
+        OpCode::Add,   OpCode::Noop, OpCode::Noop,
+    ], &[]);
+
+    let options = ProofOptions::default();
+    let inputs = ProgramInputs::from_public(&[1, 0]);
+
+    let (trace, ctx_depth, loop_depth) = processor::execute(&program, &inputs).unwrap();
+    let trace = TraceTable::new(trace, ctx_depth, loop_depth, options.extension_factor());
+    let evaluator = ConstraintEvaluator::from_trace(&trace, &[0; 32], &[1, 0], &[3], &[]);
+
+    // with ld_op bit products stored in dedicated registers, max degree of decoder constraints
+    // goes down from 8 to 7, and max degree of stack constraints goes down from 7 to 6
+    let degrees = evaluator.constraint_degrees();
+    assert_eq!(ConstraintDegrees { decoder: 7, stack: 6 }, degrees);
+    assert_eq!(MAX_CONSTRAINT_DEGREE, degrees.decoder.max(degrees.stack));
+
+    // constraints are combined into a polynomial of degree (MAX_CONSTRAINT_DEGREE - 1) * n, and
+    // so the composition polynomial has degree 6n - 1 rather than 7n - 1
+    assert_eq!((MAX_CONSTRAINT_DEGREE - 1) * trace.unextended_length(), evaluator.combination_degree());
+
+    // the prover reports the same degrees
+    let (_, _, timings) = super::execute_with_timings(&program, &inputs, 1, &options).unwrap();
+    assert_eq!(degrees, timings.constraint_degrees);
+}
+
+#[test]
+fn stack_width() {
+    // both programs compute the same sum using the same number of operations, but the first one
+    // never grows the stack beyond 2 items, while the second one grows it to 11 items
+    let shallow = assembly::compile("begin
+        push.1 add push.2 add push.3 add push.4 add push.5 add
+        push.6 add push.7 add push.8 add push.9 add push.10 add end").unwrap();
+    let deep = assembly::compile("begin
+        push.1 push.2 push.3 push.4 push.5 push.6 push.7 push.8 push.9 push.10
+        add add add add add add add add add add end").unwrap();
+
+    let options = ProofOptions::default();
+    let inputs = ProgramInputs::from_public(&[5]);
+
+    let (outputs, shallow_proof) = super::execute(&shallow, &inputs, 1, &options).unwrap();
+    assert_eq!(outputs, [60]);
+    assert_eq!(2, shallow_proof.stack_depth());
+    let result = super::verify(shallow.hash(), inputs.get_public_inputs(), &outputs, &shallow_proof);
+    assert_eq!(Ok(()), result);
+
+    let (outputs, deep_proof) = super::execute(&deep, &inputs, 1, &options).unwrap();
+    assert_eq!(outputs, [60]);
+    assert_eq!(11, deep_proof.stack_depth());
+    let result = super::verify(deep.hash(), inputs.get_public_inputs(), &outputs, &deep_proof);
+    assert_eq!(Ok(()), result);
+
+    // proof of the shallow program should be smaller
+    let shallow_size = bincode::serialize(&shallow_proof).unwrap().len();
+    let deep_size = bincode::serialize(&deep_proof).unwrap().len();
+    assert!(shallow_size < deep_size, "expected {} to be less than {}", shallow_size, deep_size);
+
+    // statement may require a wider stack than the proof provides
+    let mut statement = Statement::new(shallow.hash(), inputs.get_public_inputs(), &outputs);
+    assert_eq!(Ok(()), super::verify_statement(&statement, &shallow_proof));
+    statement.stack_depth = 4;
+    let err_msg = format!("verification of stack depth failed: expected at least 4 registers, but was 2");
+    assert_eq!(Err(err_msg), super::verify_statement(&statement, &shallow_proof).map_err(|err| err.to_string()));
+}
+
+#[test]
+#[should_panic(expected = "transition constraint at step")]
+fn stack_width_too_small() {
+    let program = assembly::compile("begin
+        push.1 push.2 push.3 push.4 push.5 push.6 push.7 push.8 push.9 push.10
+        add add add add add add add add add add end").unwrap();
+
+    let options = ProofOptions::default();
+    let inputs = ProgramInputs::from_public(&[5]);
+
+    // drop the bottom stack register; the input value shifted into it can no longer be
+    // shifted back, and so constraints of the first ADD operation are not satisfied
+    let (mut trace, ctx_depth, loop_depth) = processor::execute(&program, &inputs).unwrap();
+    trace.remove(trace.len() - NUM_DEPTH_BITS - 1);
+    let trace = TraceTable::new(trace, ctx_depth, loop_depth, options.extension_factor());
+    assert_eq!(10, trace.stack_depth());
+    stark::prove(trace, inputs.get_public_inputs(), &[60], &[], &options).unwrap();
+}
+
+#[test]
+fn stack_underflow() {
+    let program = assembly::compile("begin drop end").unwrap();
+    let options = ProofOptions::default();
+    let inputs = ProgramInputs::from_public(&[0]);
+
+    // build a trace for a program with a single input, and then lower the depth by one so
+    // that the trace claims the stack was empty; DROP then takes the depth below zero, and such
+    // depth cannot be claimed for the last step of the trace
+    let result = prove_with_lowered_depth(&program, &inputs, &options);
+    let err_msg = format!("final stack depth {} is too large to be included in a proof", field::sub(0, 1));
+    assert_eq!(Some(err_msg), result.err());
+}
+
+#[test]
+fn stack_underflow_recovered() {
+    let program = assembly::compile("begin drop push.0 end").unwrap();
+    let options = ProofOptions::default();
+    let inputs = ProgramInputs::from_public(&[0]);
+
+    // with the depth lowered by one, DROP takes the depth to -1 and PUSH brings it back to 0;
+    // boundary constraints and the depth change constraint are satisfied, and so the proof
+    // can be rejected only because the depth went below zero in the middle of the execution
+    let proof = prove_with_lowered_depth(&program, &inputs, &options).unwrap();
+
+    let result = super::verify(program.hash(), &[], &[], &proof);
+    assert!(matches!(result, Err(VerificationError::LowDegreeProofFailed { depth: 0, .. })));
+}
+
+#[test]
+fn read_below_stack_depth() {
+    let program = assembly::compile("begin dup.4 end").unwrap();
+    let options = ProofOptions::default();
+
+    // the processor refuses to duplicate 4 items when the stack holds only 2
+    let inputs = ProgramInputs::from_public(&[1, 2]);
+    let expected = ExecutionError::StackUnderflow { step: 2, op: OpCode::Dup4 };
+    assert_eq!(Some(expected), processor::execute(&program, &inputs).err());
+
+    // build a trace with 2 extra zero inputs, and then lower the depth by 2 so that the trace
+    // claims the stack held only 2 items; stack registers are consistent with such a stack, and
+    // so the proof can be rejected only because DUP4 reads 2 items below the bottom of the stack
+    let inputs = ProgramInputs::from_public(&[1, 2, 0, 0]);
+    let (mut trace, ctx_depth, loop_depth) = processor::execute(&program, &inputs).unwrap();
+    let depth_start = trace.len() - NUM_DEPTH_BITS;
+    for step in 0..trace[0].len() {
+        let floor = (0..NUM_DEPTH_BITS).fold(0, |floor, i| floor | (trace[depth_start + i][step] << i));
+        let floor = field::sub(floor, 2);
+        for i in 0..NUM_DEPTH_BITS {
+            trace[depth_start + i][step] = if floor < 64 { (floor >> i) & 1 } else if i == 0 { floor } else { 0 };
+        }
+    }
+    let trace = TraceTable::new(trace, ctx_depth, loop_depth, options.extension_factor());
+    assert_eq!(2, trace.get_state(0).watermark());
+    assert_eq!(field::sub(0, 2), trace.get_state(1).depth_floor());
+    assert_eq!(6, trace.get_last_state().watermark());
+
+    stark::SKIP_TRANSITION_CHECKS.with(|s| s.set(true));
+    let (proof, _) = stark::prove(trace, &[1, 2], &[1, 2, 0, 0, 1, 2], &[], &options).unwrap();
+    stark::SKIP_TRANSITION_CHECKS.with(|s| s.set(false));
+
+    let result = super::verify(program.hash(), &[1, 2], &[1, 2, 0, 0, 1, 2], &proof);
+    assert!(matches!(result, Err(VerificationError::LowDegreeProofFailed { depth: 0, .. })));
+}
+
+#[test]
+#[should_panic(expected = "transition constraint at step")]
+fn stack_shift_below_watermark() {
+    let program = assembly::compile("begin push.3 push.4 add add end").unwrap();
+    let options = ProofOptions::default();
+    let inputs = ProgramInputs::from_public(&[1, 2]);
+
+    // put a non-zero value below the watermark and carry it along as the first PUSH shifts
+    // the stack right; the second PUSH would shift the value out of the stack, and so the
+    // constraints must not be satisfied
+    let (mut trace, ctx_depth, loop_depth) = processor::execute(&program, &inputs).unwrap();
+    let stack_start = trace.len() - NUM_DEPTH_BITS - 4;
+    trace[stack_start + 2][0] = 7;
+    trace[stack_start + 3][1] = 7;
+    let trace = TraceTable::new(trace, ctx_depth, loop_depth, options.extension_factor());
+    assert_eq!(4, trace.stack_depth());
+    stark::prove(trace, inputs.get_public_inputs(), &[10], &[], &options).unwrap();
+}
+
+#[test]
+fn tuned_options() {
+    for &target in [80, 100, 128].iter() {
+        for &minimize in [Minimize::ProofSize, Minimize::ProverTime].iter() {
+            let options = ProofOptions::tune(target, 1024, minimize);
+            assert!(options.security_level(true) >= target,
+                "{:?} options do not meet {} bit security target", minimize, target);
+        }
+    }
+
+    let program = assembly::compile("begin
+        push.1 push.2 push.3 push.4 push.5 push.6 push.7 push.8 push.9 push.10
+        add add add add add add add add add add end").unwrap();
+    let inputs = ProgramInputs::from_public(&[5]);
+    let trace_length = program.estimate_trace_length(None).start;
+
+    let mut sizes = Vec::new();
+    for &minimize in [Minimize::ProofSize, Minimize::ProverTime].iter() {
+        let options = ProofOptions::tune(100, trace_length, minimize);
+        let (outputs, proof) = super::execute(&program, &inputs, 1, &options).unwrap();
+        let result = super::verify(program.hash(), inputs.get_public_inputs(), &outputs, &proof);
+        assert_eq!(Ok(()), result);
+        sizes.push(bincode::serialize(&proof).unwrap().len());
+    }
+    assert!(sizes[0] < sizes[1], "expected {} to be less than {}", sizes[0], sizes[1]);
+}
+
+#[test]
+fn tuned_cost_model() {
+    let program = assembly::compile("begin
+        push.1 push.2 push.3 push.4 push.5 push.6 push.7 push.8 push.9 push.10
+        add add add add add add add add add add end").unwrap();
+    let inputs = ProgramInputs::from_public(&[5]);
+
+    // estimated proof sizes should be within 25% of actual proof sizes
+    for &(extension_factor, num_queries) in [(16, 10), (32, 40), (64, 80)].iter() {
+        let options = ProofOptions::new(extension_factor, num_queries, 0, HashFunction::Blake3);
+        let (_, proof) = super::execute(&program, &inputs, 1, &options).unwrap();
+        let actual = bincode::serialize(&proof).unwrap().len();
+        let estimate = options.estimate_proof_size(proof.domain_size() / extension_factor);
+        assert!(estimate * 4 > actual * 3 && estimate * 4 < actual * 5,
+            "estimated proof size {} is too far from actual size {}", estimate, actual);
+    }
+}
+
+#[test]
+fn grinding_nonce() {
+    let program = assembly::compile("begin push.3 mul end").unwrap();
+    let inputs = ProgramInputs::from_public(&[5]);
+
+    // grinding bits are counted towards security level
+    let options = ProofOptions::default().with_grinding_bits(0);
+    assert_eq!(options.security_level(true) + 16, options.clone().with_grinding_bits(16).security_level(true));
+
+    let options = options.with_grinding_bits(16);
+    let (outputs, mut proof) = super::execute(&program, &inputs, 1, &options).unwrap();
+    assert_eq!(16, proof.options().grinding_factor());
+    assert_eq!(Ok(()), super::verify(program.hash(), inputs.get_public_inputs(), &outputs, &proof));
+
+    // a proof with a tampered nonce fails verification
+    proof.set_pow_nonce(proof.pow_nonce() ^ 1);
+    let result = super::verify(program.hash(), inputs.get_public_inputs(), &outputs, &proof);
+    assert_eq!(Err(VerificationError::ProofOfWorkFailed), result);
+}
+
+#[test]
+fn folding_factors() {
+    // a loop which runs for about 1000 steps
+    let program = assembly::compile("begin push.0 push.1 while.true push.1 add dup push.30 eq not end end").unwrap();
+    let inputs = ProgramInputs::none();
+
+    let mut layers = Vec::new();
+    let mut sizes = Vec::new();
+    for &folding_factor in [4, 8, 16].iter() {
+        let options = ProofOptions::default().with_folding_factor(folding_factor);
+        let (outputs, proof) = super::execute(&program, &inputs, 1, &options).unwrap();
+        assert_eq!(folding_factor, proof.options().folding_factor());
+        assert!(proof.degree_proof().rem_values.len() <= options.max_remainder_length());
+        assert_eq!(Ok(()), super::verify(program.hash(), &[], &outputs, &proof));
+        layers.push(proof.degree_proof().layers.len());
+        sizes.push(bincode::serialize(&proof).unwrap().len());
+    }
+
+    // larger folding factors need fewer layers, and at least one of them results in a smaller
+    // proof than the default
+    assert!(layers[0] > layers[1] && layers[1] > layers[2]);
+    assert!(sizes[1] < sizes[0] || sizes[2] < sizes[0]);
+
+    // a longer remainder replaces the last layer of the proof
+    let options = ProofOptions::default().with_max_remainder_length(1024);
+    let (outputs, proof) = super::execute(&program, &inputs, 1, &options).unwrap();
+    assert_eq!(layers[0] - 1, proof.degree_proof().layers.len());
+    assert_eq!(Ok(()), super::verify(program.hash(), &[], &outputs, &proof));
+
+    // the verifier can require a minimum level of security
+    let security_level = options.security_level(true);
+    assert_eq!(Ok(()), super::verify_with_min_security(program.hash(), &[], &outputs, security_level, &proof));
+    let err = super::verify_with_min_security(program.hash(), &[], &outputs, security_level + 1, &proof);
+    assert_eq!(Err(VerificationError::InsufficientSecurity { required: security_level + 1, actual: security_level }), err);
+}
+
+#[test]
+fn fri_parameters() {
+    let program = assembly::compile("begin push.3 mul end").unwrap();
+    let inputs = ProgramInputs::from_public(&[5]);
+
+    for &extension_factor in [16, 32, 64, 128, 256].iter() {
+        for &folding_factor in [4, 8, 16].iter() {
+            for &max_remainder_length in [128, 256, 512, 1024].iter() {
+                if max_remainder_length < folding_factor * folding_factor { continue; }
+                let options = ProofOptions::new(extension_factor, 20, 0, HashFunction::Blake3)
+                    .with_folding_factor(folding_factor)
+                    .with_max_remainder_length(max_remainder_length);
+                let (outputs, proof) = super::execute(&program, &inputs, 1, &options).unwrap();
+                let result = super::verify(program.hash(), inputs.get_public_inputs(), &outputs, &proof);
+                assert_eq!(Ok(()), result, "extension factor: {}, folding factor: {}, max remainder length: {}",
+                    extension_factor, folding_factor, max_remainder_length);
+            }
+        }
+    }
+}
+
+#[test]
+#[should_panic(expected = "max remainder length 128 is too small for folding factor 16")]
+fn fri_remainder_too_short() {
+    ProofOptions::default().with_folding_factor(16).with_max_remainder_length(128);
+}
+
+#[test]
+fn hash_functions() {
+    let program = assembly::compile("begin push.3 push.5 read add mul end").unwrap();
+    let inputs = ProgramInputs::new(&[], &[2], &[]);
+
+    for &hash_fn in [HashFunction::Blake3, HashFunction::Sha256, HashFunction::Rescue].iter() {
+        // Rescue is slow enough to make grinding impractical in tests
+        let options = ProofOptions::default().with_hash_fn(hash_fn).with_grinding_bits(0);
+        let (outputs, proof) = super::execute(&program, &inputs, 1, &options).unwrap();
+        assert_eq!(vec![21], outputs);
+        assert_eq!(hash_fn, proof.options().hash_fn());
+        assert_eq!(Ok(()), super::verify(program.hash(), &[], &outputs, &proof));
+    }
+
+    // hash function is the last byte of a serialized proof; a proof built with Blake3 does not
+    // verify under Rescue, and unknown hash functions are rejected
+    let options = ProofOptions::default().with_grinding_bits(0);
+    let (outputs, proof) = super::execute(&program, &inputs, 1, &options).unwrap();
+    let mut proof_bytes = bincode::serialize(&proof).unwrap();
+    *proof_bytes.last_mut().unwrap() = 2;
+    let proof: stark::StarkProof = bincode::deserialize(&proof_bytes).unwrap();
+    assert_eq!(HashFunction::Rescue, proof.options().hash_fn());
+    assert!(super::verify(program.hash(), &[], &outputs, &proof).is_err());
+
+    *proof_bytes.last_mut().unwrap() = 3;
+    assert!(bincode::deserialize::<stark::StarkProof>(&proof_bytes).is_err());
+}
+
+#[test]
+fn security_policy() {
+    let program = assembly::compile("begin push.3 push.5 read add mul end").unwrap();
+    let inputs = ProgramInputs::new(&[], &[2], &[]);
+    let strict = SecurityPolicy::new(100)
+        .with_min_queries(40)
+        .with_min_extension_factor(32)
+        .with_hash_fns(&[HashFunction::Blake3]);
+
+    // a weak proof verifies on its own, but is rejected under a strict policy
+    let weak_options = ProofOptions::new(16, 4, 0, HashFunction::Blake3);
+    assert_eq!(4, weak_options.security_level(true));
+    let (outputs, proof) = super::execute(&program, &inputs, 1, &weak_options).unwrap();
+    assert_eq!(Ok(()), super::verify(program.hash(), &[], &outputs, &proof));
+    let err = super::verify_with_policy(program.hash(), &[], &outputs, &proof, &strict).err().unwrap();
+    assert_eq!(VerificationError::InsufficientSecurity { required: 100, actual: 4 }, err);
+    let permissive = SecurityPolicy::default();
+    assert_eq!(Ok(()), super::verify_with_policy(program.hash(), &[], &outputs, &proof, &permissive));
+
+    // every requirement of the policy is enforced
+    let options = ProofOptions::default();
+    let (outputs, proof) = super::execute(&program, &inputs, 1, &options).unwrap();
+    assert_eq!(Ok(()), super::verify_with_policy(program.hash(), &[], &outputs, &proof, &strict));
+
+    let policy = strict.clone().with_min_queries(64);
+    let err = super::verify_with_policy(program.hash(), &[], &outputs, &proof, &policy).err().unwrap();
+    assert_eq!(VerificationError::PolicyViolation(
+        String::from("proof uses 50 queries, but at least 64 are required")), err);
+
+    let policy = strict.clone().with_min_extension_factor(64);
+    let err = super::verify_with_policy(program.hash(), &[], &outputs, &proof, &policy).err().unwrap();
+    assert_eq!(VerificationError::PolicyViolation(
+        String::from("proof uses extension factor 32, but at least 64 is required")), err);
+
+    let policy = strict.clone().with_hash_fns(&[HashFunction::Sha256, HashFunction::Rescue]);
+    let err = super::verify_with_policy(program.hash(), &[], &outputs, &proof, &policy).err().unwrap();
+    assert_eq!(VerificationError::PolicyViolation(
+        String::from("proof uses Blake3 hash function, which is not one of [Sha256, Rescue]")), err);
+}
+
+#[test]
+fn zero_knowledge() {
+    let program = assembly::compile("begin push.3 push.5 read add mul end").unwrap();
+    let inputs = ProgramInputs::new(&[], &[2], &[]);
+    let options = ProofOptions::new(32, 8, 0, HashFunction::Blake3).with_zero_knowledge(true);
+    assert_eq!(34, options.blinding_factor());
+    assert_eq!(256, options.min_trace_length());
+
+    // blinded proofs verify, and the trace is padded enough to hide the revealed states
+    let (outputs, proof1) = super::execute(&program, &inputs, 1, &options.clone().with_seed([1; 32])).unwrap();
+    assert_eq!(vec![21], outputs);
+    assert!(proof1.is_zero_knowledge());
+    assert_eq!(256, proof1.trace_length());
+    assert_eq!(Ok(()), super::verify(program.hash(), &[], &outputs, &proof1));
+
+    // the same seed produces the same proof, while a different seed changes all commitments
+    let (_, proof2) = super::execute(&program, &inputs, 1, &options.clone().with_seed([1; 32])).unwrap();
+    assert_eq!(bincode::serialize(&proof1).unwrap(), bincode::serialize(&proof2).unwrap());
+    let (_, proof3) = super::execute(&program, &inputs, 1, &options.clone().with_seed([2; 32])).unwrap();
+    assert_ne!(proof1.trace_root(), proof3.trace_root());
+    assert_ne!(proof1.constraint_root(), proof3.constraint_root());
+    assert_ne!(proof1.zk_values().unwrap().mask_root, proof3.zk_values().unwrap().mask_root);
+    assert_eq!(Ok(()), super::verify(program.hash(), &[], &outputs, &proof3));
+
+    // without a seed, randomness is drawn from the system
+    let (_, proof4) = super::execute(&program, &inputs, 1, &options).unwrap();
+    assert_ne!(proof1.trace_root(), proof4.trace_root());
+    assert_eq!(Ok(()), super::verify(program.hash(), &[], &outputs, &proof4));
+
+    // a proof which claims zero-knowledge options but carries no blinding values is rejected
+    let (_, plain) = super::execute(&program, &inputs, 1, &ProofOptions::new(32, 8, 0, HashFunction::Blake3)).unwrap();
+    assert!(!plain.is_zero_knowledge());
+    let mut proof_bytes = bincode::serialize(&plain).unwrap();
+    let options_bytes = bincode::serialize(plain.options()).unwrap();
+    let zk_options_bytes = bincode::serialize(&options).unwrap();
+    let start = proof_bytes.len() - options_bytes.len();
+    proof_bytes.truncate(start);
+    proof_bytes.extend(zk_options_bytes);
+    let forged: stark::StarkProof = bincode::deserialize(&proof_bytes).unwrap();
+    let err = super::verify(program.hash(), &[], &outputs, &forged).err().unwrap();
+    assert!(matches!(err, VerificationError::MalformedProof(_)));
+}
+
+#[test]
+fn deterministic_proofs() {
+    let program = assembly::compile("begin push.0 push.1 while.true push.1 add dup push.10 eq not end end").unwrap();
+    let inputs = ProgramInputs::none();
+    let prove = |options: &ProofOptions| {
+        let (outputs, proof) = super::execute(&program, &inputs, 1, options).unwrap();
+        assert_eq!(Ok(()), super::verify(program.hash(), &[], &outputs, &proof));
+        bincode::serialize(&proof).unwrap()
+    };
+
+    // without zero-knowledge, proofs do not use any prover-side randomness
+    let options = ProofOptions::new(32, 8, 0, HashFunction::Blake3);
+    assert_eq!(prove(&options), prove(&options));
+    assert_eq!(prove(&options), prove(&options.clone().with_seed([7; 32])));
+
+    // with zero-knowledge, proofs are byte-identical only when generated from the same seed
+    let options = options.with_zero_knowledge(true);
+    let seeded = options.clone().with_seed([7; 32]);
+    assert_eq!(prove(&seeded), prove(&seeded));
+    assert_ne!(prove(&seeded), prove(&options.clone().with_seed([8; 32])));
+    assert_ne!(prove(&options), prove(&options));
+}
+
+#[test]
+fn proof_size_breakdown() {
+    let program = assembly::compile("begin push.0 push.1 while.true push.1 add dup push.30 eq not end end").unwrap();
+    let inputs = ProgramInputs::none();
+
+    for options in [ProofOptions::default(), ProofOptions::new(32, 8, 0, HashFunction::Blake3).with_zero_knowledge(true)].iter() {
+        let (_, proof) = super::execute(&program, &inputs, 1, options).unwrap();
+        let report = proof.size_breakdown();
+        let proof_bytes = bincode::serialize(&proof).unwrap();
+
+        assert_eq!(options.num_queries(), proof.num_queries());
+        assert_eq!(proof.num_queries(), report.num_queries);
+        assert_eq!(proof.trace_length(), report.trace_length);
+        assert_eq!(proof.fri_layer_count(), report.fri_layers.len());
+        assert_eq!(proof.degree_proof().layers.len(), proof.fri_layer_count());
+        assert_eq!(proof_bytes.len(), report.total);
+        assert_eq!(64, report.commitments);
+        assert!(report.path_bytes_per_query > 0);
+        assert_eq!(proof.is_zero_knowledge(), report.zk_values > 1);
+
+        // only length prefixes of nested lists are not attributed to any component
+        assert!(report.components_total() <= report.total);
+        assert!(report.total - report.components_total() <= 16);
+
+        // the report can be logged as JSON
+        let json = serde_json::to_string(&report).unwrap();
+        assert_eq!(report, serde_json::from_str(&json).unwrap());
+    }
+}
+
+#[test]
+fn verify_many_proofs() {
+    let program = assembly::compile("begin push.3 push.5 read add mul end").unwrap();
+    let other = assembly::compile("begin push.0 push.1 while.true push.1 add dup push.10 eq not end end").unwrap();
+    let options = ProofOptions::default().with_grinding_bits(8);
+
+    let mut proofs = Vec::new();
+    for &secret in [2, 4, 6, 8].iter() {
+        let (outputs, proof) = super::execute(&program, &ProgramInputs::new(&[], &[secret], &[]), 1, &options).unwrap();
+        proofs.push((program.hash(), outputs, proof));
+    }
+    let (outputs, proof) = super::execute(&other, &ProgramInputs::none(), 1, &options).unwrap();
+    proofs.push((other.hash(), outputs, proof));
+
+    // corrupt one of the queried trace states of the third proof
+    let proof = &mut proofs[2].2;
+    let mut proof_bytes = bincode::serialize(proof).unwrap();
+    let position = proof_bytes.len() / 4;
+    proof_bytes[position] ^= 1;
+    *proof = bincode::deserialize(&proof_bytes).unwrap();
+
+    let items: Vec<_> = proofs.iter().map(|(program_hash, outputs, proof)| VerificationItem {
+        program_hash, public_inputs: &[], outputs, proof
+    }).collect();
+    let results = super::verify_many(&items);
+    let expected: Vec<_> = items.iter()
+        .map(|item| super::verify(item.program_hash, item.public_inputs, item.outputs, item.proof))
+        .collect();
+    assert_eq!(expected, results);
+    assert_eq!(1, results.iter().filter(|result| result.is_err()).count());
+    assert!(results[2].is_err());
+    assert!(super::verify_many(&[]).is_empty());
+}
+
+#[test]
+fn protocol_version() {
+    let program = assembly::compile("begin push.3 push.5 read add mul end").unwrap();
+    let inputs = ProgramInputs::new(&[], &[2], &[]);
+    let (outputs, proof) = super::execute(&program, &inputs, 1, &ProofOptions::default()).unwrap();
+    assert_eq!(stark::PROTOCOL_VERSION, proof.version());
+    assert_eq!(&stark::air_fingerprint(), proof.air_fingerprint());
+
+    // version is the first field of a serialized proof, and is checked before anything else
+    let mut proof_bytes = bincode::serialize(&proof).unwrap();
+    proof_bytes[..4].copy_from_slice(&(stark::PROTOCOL_VERSION + 1).to_le_bytes());
+    let old_proof: stark::StarkProof = bincode::deserialize(&proof_bytes).unwrap();
+    let err = super::verify(program.hash(), &[], &outputs, &old_proof).err().unwrap();
+    assert_eq!(VerificationError::VersionMismatch { proof: stark::PROTOCOL_VERSION + 1, supported: stark::PROTOCOL_VERSION }, err);
+    let err = super::verify_with_policy(program.hash(), &[], &outputs, &old_proof, &SecurityPolicy::new(200)).err().unwrap();
+    assert_eq!(VerificationError::VersionMismatch { proof: stark::PROTOCOL_VERSION + 1, supported: stark::PROTOCOL_VERSION }, err);
+
+    // the fingerprint of the trace layout follows the version
+    let mut proof_bytes = bincode::serialize(&proof).unwrap();
+    proof_bytes[4] ^= 1;
+    let other_layout: stark::StarkProof = bincode::deserialize(&proof_bytes).unwrap();
+    let err = super::verify(program.hash(), &[], &outputs, &other_layout).err().unwrap();
+    assert!(matches!(err, VerificationError::MalformedProof(_)));
+}
+
+/// Fails if constants defining the execution trace layout or the constraints have changed. If
+/// this happens, proofs generated before the change cannot be verified anymore: increment
+/// `stark::PROTOCOL_VERSION` and record the new fingerprint for it below.
+#[test]
+fn air_fingerprint() {
+    let fingerprints = [
+        (1, "cee08055944de3a56a89d7493ead31dfb616137b78d6faf336d17160f012c05e"),
+        (2, "a110727f83a852ba5e073a9233afdd9b53c6a8da908be46f10c44f8957689d9d"),
+        (3, "3752663323577abfcd9c4c6cdea61e11bca9dd48ea2f7c401e9ec25d67f72387"),
+        (4, "3752663323577abfcd9c4c6cdea61e11bca9dd48ea2f7c401e9ec25d67f72387"),
+    ];
+    let (version, expected) = fingerprints[fingerprints.len() - 1];
+    let actual = hex::encode(stark::air_fingerprint());
+    assert_eq!(version, stark::PROTOCOL_VERSION, "fingerprint of protocol version {} is not recorded", stark::PROTOCOL_VERSION);
+    assert_eq!(expected, actual, "trace layout has changed; protocol version must be incremented");
 }
 
 // TODO: add more tests
 
 // HELPER FUNCTIONS
 // ================================================================================================
-fn build_program(instructions: Vec<OpCode>, push_values: &[u128]) -> Program {
 
-    // build hint map for PUSh operations
-    let mut j = 0;
-    let mut hints = HashMap::new();
-    for i in 0..instructions.len() {
-        match instructions[i] {
-            OpCode::Push => {
-                assert!(j < push_values.len(), "not enough push values");
-                hints.insert(i, OpHint::PushValue(push_values[j]));
-                j += 1;
-            },
-            _ => ()
+/// Executes the program, lowers stack depth by one at every step of the trace, and proves the
+/// resulting trace without checking that it satisfies transition constraints.
+fn prove_with_lowered_depth(program: &Program, inputs: &ProgramInputs, options: &ProofOptions) -> Result<stark::StarkProof, String> {
+    let (mut trace, ctx_depth, loop_depth) = processor::execute(program, inputs).unwrap();
+    let depth_start = trace.len() - NUM_DEPTH_BITS;
+    for value in trace[depth_start].iter_mut() {
+        *value = field::sub(*value, 1);
+    }
+    let trace = TraceTable::new(trace, ctx_depth, loop_depth, options.extension_factor());
+
+    stark::SKIP_TRANSITION_CHECKS.with(|s| s.set(true));
+    let result = stark::prove(trace, &[], &[], &[], options);
+    stark::SKIP_TRANSITION_CHECKS.with(|s| s.set(false));
+    return result.map(|(proof, _)| proof);
+}
+
+/// Builds a pseudo-random Merkle authentication path for a leaf at the specified `index` in a
+/// tree of the specified `depth`; returns contents of input tapes A and B in the layout expected
+/// by `mtree.verify` instruction (prefixed by the leaf), and the root of the path.
+fn build_merkle_path(depth: usize, index: usize) -> (Vec<u128>, Vec<u128>, Vec<u128>) {
+    let mut node = vec![field::rand(), field::rand()];
+    let mut a = vec![node[0]];
+    let mut b = vec![node[1]];
+
+    for i in 0..(depth - 1) {
+        let bit = ((index >> i) & 1) as u128;
+        let sibling = [field::rand(), field::rand()];
+        a.extend_from_slice(&[field::ZERO, sibling[0]]);
+        b.extend_from_slice(&[bit, sibling[1]]);
+
+        node = if bit == 0 {
+            hasher::digest(&[node[0], node[1], sibling[0], sibling[1]])
         }
+        else {
+            hasher::digest(&[sibling[0], sibling[1], node[0], node[1]])
+        };
+    }
+
+    return (a, b, node);
+}
+
+/// Hashes bytes the same way `packed_bytes` test program does: bytes of each 15-byte chunk are
+/// hashed 4 stack items at a time until 2 items remain, and the result is merged with the
+/// result for the previous chunk.
+fn hash_bytes(message: &[u8]) -> Vec<u128> {
+    fn hash_top4(stack: &mut Vec<u128>) {
+        let mut digest = hasher::digest(&[stack[3], stack[2], stack[1], stack[0]]);
+        digest.reverse();
+        stack.splice(0..4, digest);
+    }
+
+    let mut result: Vec<u128> = Vec::new();
+    for chunk in message.chunks(15) {
+        let mut stack: Vec<u128> = chunk.iter().map(|&b| b as u128).collect();
+        if stack.len() % 2 == 1 { stack.push(0); }
+        while stack.len() > 2 { hash_top4(&mut stack); }
+
+        stack.extend_from_slice(&result);
+        if stack.len() == 4 { hash_top4(&mut stack); }
+        result = stack;
     }
-    assert!(j == push_values.len(), "too many push values");
+    return result;
+}
 
-    let root = vec![ProgramBlock::Span(Span::new(instructions, hints))];
+fn build_program(instructions: Vec<OpCode>, push_values: &[u128]) -> Program {
+
+    // build hint map for PUSH operations; PUSH operations left without values are caught
+    // when the span is built
+    let push_steps = (0..instructions.len()).filter(|&i| instructions[i] == OpCode::Push);
+    assert!(push_steps.clone().count() >= push_values.len(), "too many push values");
+    let hints: HashMap<usize, OpHint> = push_steps.zip(push_values.iter())
+        .map(|(i, &value)| (i, OpHint::PushValue(value)))
+        .collect();
+
+    let root = vec![ProgramBlock::Span(Span::new(instructions, hints).unwrap())];
     return Program::new(Group::new(root));
-}
\ No newline at end of file
+}
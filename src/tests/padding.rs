@@ -0,0 +1,82 @@
+use crate::{ ProofOptions, ProgramInputs, Program, Statement, assembly };
+use super::super::{ execute, verify, verify_statement };
+
+#[test]
+fn padded_proof_matches_long_execution() {
+    let program = build_program();
+    let options = ProofOptions::default().with_min_trace_length(1024);
+
+    // without any loop iterations, the program executes in 64 steps
+    let (_, short_proof) = execute(&program, &build_inputs(0), 1, &ProofOptions::default()).unwrap();
+    assert_eq!(64, short_proof.trace_length());
+
+    let short_inputs = build_inputs(0);
+    let (outputs, short_proof) = execute(&program, &short_inputs, 1, &options).unwrap();
+    assert_eq!(vec![3], outputs);
+    assert_eq!(1024, short_proof.trace_length());
+    assert_eq!(Ok(()), verify(program.hash(), short_inputs.get_public_inputs(), &outputs, &short_proof));
+
+    let long_inputs = build_inputs(NUM_ITERATIONS);
+    let (outputs, long_proof) = execute(&program, &long_inputs, 1, &ProofOptions::default()).unwrap();
+    assert_eq!(1024, long_proof.trace_length());
+    assert_eq!(Ok(()), verify(program.hash(), long_inputs.get_public_inputs(), &outputs, &long_proof));
+
+    // proof sizes may differ only by a few Merkle paths because query positions are random
+    let short_size = bincode::serialize(&short_proof).unwrap().len();
+    let long_size = bincode::serialize(&long_proof).unwrap().len();
+    assert!(short_size * 10 > long_size * 9 && long_size * 10 > short_size * 9,
+        "padded proof of {} bytes differs from proof of {} bytes", short_size, long_size);
+}
+
+#[test]
+fn statement_requires_min_trace_length() {
+    let program = build_program();
+    let inputs = build_inputs(0);
+
+    let (outputs, proof) = execute(&program, &inputs, 1, &ProofOptions::default()).unwrap();
+    let mut statement = Statement::new(program.hash(), inputs.get_public_inputs(), &outputs);
+    assert_eq!(Ok(()), verify_statement(&statement, &proof));
+
+    statement.min_trace_length = 1024;
+    let result = verify_statement(&statement, &proof);
+    assert_eq!(Err(String::from("verification of trace length failed: expected at least 1024 steps, but was 64")), result.map_err(|err| err.to_string()));
+
+    let options = ProofOptions::default().with_min_trace_length(1024);
+    let (_, proof) = execute(&program, &inputs, 1, &options).unwrap();
+    assert_eq!(Ok(()), verify_statement(&statement, &proof));
+}
+
+#[test]
+#[should_panic(expected = "min_trace_length must be a power of 2")]
+fn min_trace_length_not_power_of_two() {
+    ProofOptions::default().with_min_trace_length(100);
+}
+
+#[test]
+#[should_panic(expected = "min_trace_length cannot be smaller than 16")]
+fn min_trace_length_too_small() {
+    ProofOptions::default().with_min_trace_length(8);
+}
+
+// HELPER FUNCTIONS
+// ================================================================================================
+
+/// Number of loop iterations which makes the program execute in more than 512 steps.
+const NUM_ITERATIONS: usize = 40;
+
+/// Doubles the public input once for every 1 on the secret tape.
+fn build_program() -> Program {
+    return assembly::compile("
+        begin
+            read
+            while.true
+                push.2 mul read
+            end
+        end").unwrap();
+}
+
+fn build_inputs(num_iterations: usize) -> ProgramInputs {
+    let mut tape = vec![1; num_iterations];
+    tape.push(0);
+    return ProgramInputs::new(&[3], &tape, &[]);
+}
@@ -0,0 +1,112 @@
+use crate::{ ProofOptions, ProgramInputs, SessionProof, ExecutionError, assembly };
+use super::super::{ prove_session, verify_session, execute_procedure, verify, verify_statement };
+
+#[test]
+fn prove_verify_session() {
+    let library = assembly::compile("
+        begin
+            if.true
+                if.true add else mul end
+            else
+                push.3 add
+            end
+        end").unwrap();
+
+    let calls = vec![
+        (0, ProgramInputs::from_public(&[2, 3]), 1),
+        (1, ProgramInputs::from_public(&[2, 3]), 1),
+        (2, ProgramInputs::from_public(&[4]), 1),
+        (0, ProgramInputs::from_public(&[10, 20]), 1),
+        (2, ProgramInputs::from_public(&[1]), 1),
+    ];
+
+    let options = ProofOptions::default();
+    let (mut statements, proof) = prove_session(&library, &calls, &options).unwrap();
+    let outputs: Vec<u128> = statements.iter().map(|s| s.outputs[0]).collect();
+    assert_eq!(vec![5, 6, 7, 30, 4], outputs);
+    assert_eq!(vec![2, 3], statements[0].inputs);
+    assert_eq!(vec![true, true], statements[0].proc_path);
+    assert_eq!(vec![1, 1, 2, 3], statements[0].stack_inputs());
+
+    // paths of procedures called more than once are stored only once
+    assert_eq!(5, proof.num_calls());
+    assert_eq!(3, proof.paths().len());
+    assert_eq!(&[false], proof.procedure_path(4));
+
+    let results = verify_session(library.hash(), &statements, &proof).unwrap();
+    assert!(results.iter().all(|r| *r == Ok(())));
+
+    // corrupting outputs of one call fails only that call
+    statements[2].outputs[0] = 8;
+    let results = verify_session(library.hash(), &statements, &proof).unwrap();
+    for (i, result) in results.iter().enumerate() {
+        assert_eq!(i != 2, *result == Ok(()));
+    }
+
+    // a statement cannot claim a call to a different procedure
+    statements[2].outputs[0] = 7;
+    statements[0].set_proc_path(&[true, false]);
+    let results = verify_session(library.hash(), &statements, &proof).unwrap();
+    assert!(results[0].is_err());
+
+    // the number of statements must match the number of calls
+    assert!(verify_session(library.hash(), &statements[..4], &proof).is_err());
+    assert!(verify_session(library.hash(), &[], &SessionProof::new()).unwrap().is_empty());
+}
+
+#[test]
+fn execute_each_procedure() {
+    let library = assembly::compile("
+        begin
+            if.true
+                add
+            else
+                mul
+            end
+        end").unwrap();
+
+    let options = ProofOptions::default();
+    let inputs = ProgramInputs::from_public(&[3, 4]);
+
+    let (add_statement, add_proof) = execute_procedure(&library, 0, &inputs, 1, &options).unwrap();
+    assert_eq!(vec![7], add_statement.outputs);
+    assert_eq!(vec![3, 4], add_statement.inputs);
+    assert_eq!(vec![true], add_statement.proc_path);
+
+    let (mul_statement, mul_proof) = execute_procedure(&library, 1, &inputs, 1, &options).unwrap();
+    assert_eq!(vec![12], mul_statement.outputs);
+    assert_eq!(vec![false], mul_statement.proc_path);
+
+    // both proofs are verified against the hash of the same library
+    assert_eq!(add_statement.program_hash, mul_statement.program_hash);
+    assert_eq!(Ok(()), verify_statement(&add_statement, &add_proof));
+    assert_eq!(Ok(()), verify_statement(&mul_statement, &mul_proof));
+
+    // the selector binds the proof to the procedure which was executed
+    let result = verify(library.hash(), &add_statement.stack_inputs(), &add_statement.outputs, &mul_proof);
+    assert!(result.is_err());
+
+    let mut statement = add_statement.clone();
+    statement.set_proc_path(&mul_statement.proc_path);
+    assert!(verify_statement(&statement, &add_proof).is_err());
+}
+
+#[test]
+fn execute_missing_procedure() {
+    let library = assembly::compile("begin if.true add else mul end end").unwrap();
+    let inputs = ProgramInputs::from_public(&[3, 4]);
+    let options = ProofOptions::default();
+
+    let err = execute_procedure(&library, 2, &inputs, 1, &options).err().unwrap();
+    assert_eq!(ExecutionError::InvalidProcedure { index: 2, count: 2 }, err);
+    assert_eq!("procedure 2 does not exist in a library of 2 procedures", err.to_string());
+
+    let calls = vec![(0, inputs.clone(), 1), (3, inputs.clone(), 1)];
+    let err = prove_session(&library, &calls, &options).err().unwrap();
+    assert_eq!(ExecutionError::InvalidProcedure { index: 3, count: 2 }, err);
+
+    // a program which is not a library has no procedures
+    let program = assembly::compile("begin add end").unwrap();
+    let err = execute_procedure(&program, 0, &inputs, 1, &options).err().unwrap();
+    assert_eq!(ExecutionError::InvalidLibrary, err);
+}
@@ -0,0 +1,78 @@
+use crate::{ ProofOptions, ProgramInputs, assembly, stdlib, math::field, crypto::ots::SecretKey };
+use super::super::{ execute, verify };
+
+#[test]
+fn ots_verify_valid() {
+    let key = SecretKey::new(16, [1; 32]);
+    let public_key = key.public_key();
+
+    // the program computes the message 5 * 9 + 7 = 52 and verifies the signature over it
+    let program = build_program(16);
+    let signature = key.sign(52);
+    assert!(signature.verify(52, public_key));
+
+    let (tape_a, tape_b) = signature.to_tapes();
+    let inputs = ProgramInputs::new(&public_key, &tape_a, &tape_b);
+    let options = ProofOptions::default();
+
+    let (outputs, proof) = execute(&program, &inputs, 1, &options).unwrap();
+    assert_eq!(outputs, [0]);
+
+    let result = verify(program.hash(), inputs.get_public_inputs(), &outputs, &proof);
+    assert_eq!(Ok(()), result);
+}
+
+#[test]
+#[should_panic(expected = "ASSERTEQ failed")]
+fn ots_verify_wrong_message() {
+    let key = SecretKey::new(16, [1; 32]);
+    let public_key = key.public_key();
+
+    // the signature is over a message which differs from 52 in a single bit
+    let program = build_program(16);
+    let signature = key.sign(52 ^ 4);
+
+    let (tape_a, tape_b) = signature.to_tapes();
+    let inputs = ProgramInputs::new(&public_key, &tape_a, &tape_b);
+    execute(&program, &inputs, 1, &ProofOptions::default()).unwrap();
+}
+
+#[test]
+#[should_panic(expected = "ASSERTEQ failed")]
+fn ots_verify_flipped_digit() {
+    let key = SecretKey::new(16, [1; 32]);
+    let public_key = key.public_key();
+
+    // flip the least significant bit of the message in an otherwise valid signature
+    let program = build_program(16);
+    let mut signature = key.sign(52);
+    signature.set_digit(15, 1);
+
+    let (tape_a, tape_b) = signature.to_tapes();
+    let inputs = ProgramInputs::new(&public_key, &tape_a, &tape_b);
+    execute(&program, &inputs, 1, &ProofOptions::default()).unwrap();
+}
+
+#[test]
+#[should_panic(expected = "ASSERTEQ failed")]
+fn ots_verify_altered_chain() {
+    let key = SecretKey::new(16, [1; 32]);
+    let public_key = key.public_key();
+
+    let program = build_program(16);
+    let mut signature = key.sign(52);
+    let mut chain = signature.chains()[3];
+    chain[0] = field::add(chain[0], 1);
+    signature.set_chain(3, chain);
+
+    let (tape_a, tape_b) = signature.to_tapes();
+    let inputs = ProgramInputs::new(&public_key, &tape_a, &tape_b);
+    execute(&program, &inputs, 1, &ProofOptions::default()).unwrap();
+}
+
+// HELPER FUNCTIONS
+// ================================================================================================
+fn build_program(message_bits: u32) -> crate::Program {
+    let source = format!("begin push.5 push.9 mul push.7 add {} end", stdlib::ots_verify(message_bits));
+    return assembly::compile(&source).unwrap();
+}
@@ -0,0 +1,165 @@
+use std::collections::HashMap;
+use crate::{
+    ProofOptions, ProgramInputs, TapeId, ExecutionError, OpCode, assembly, compute_merkle_root,
+    math::field, utils::hasher, processor };
+use super::super::{ execute, execute_and_record, verify };
+
+#[test]
+fn resolve_merkle_path_on_demand() {
+    let depth = 4;
+    let index = 5;
+    let tree = build_merkle_tree(depth);
+    let leaf = tree[0][index];
+    let root = tree[depth - 1][0];
+    let program = assembly::compile(&format!("begin dup.4 mtree.verify.{} end", depth)).unwrap();
+
+    // smpath reads an index bit and then a sibling for every level of the tree; the node being
+    // authenticated sits at the top of the stack during both reads, and so the provider can find
+    // its position in the tree and look up the sibling
+    let positions = index_nodes(&tree);
+    let mut read_bit = true;
+    let mut next_b = field::ZERO;
+    let inputs = ProgramInputs::with_tape_provider(&[leaf[1], leaf[0], root[1], root[0]], move |tape, stack| {
+        if tape == TapeId::B {
+            return next_b;
+        }
+        let node = [stack.get(1).unwrap(), stack.get(0).unwrap()];
+        let (level, position) = positions[&node];
+        read_bit = !read_bit;
+        if !read_bit {
+            next_b = (position & 1) as u128;
+            return field::ZERO;
+        }
+        let sibling = tree[level][position ^ 1];
+        next_b = sibling[1];
+        return sibling[0];
+    });
+
+    let options = ProofOptions::default();
+    let (outputs, proof, record) = execute_and_record(&program, &inputs, 4, &options).unwrap();
+    assert_eq!(inputs.get_public_inputs(), &outputs[..]);
+    assert_eq!(Ok(()), verify(program.hash(), inputs.get_public_inputs(), &outputs, &proof));
+
+    // provided values are recorded in the same way as values of static tapes
+    assert_eq!(2 * (depth - 1), record.secret_inputs()[0].len());
+    let replay_inputs = ProgramInputs::from_witness_record(&record);
+    let (expected, _, _) = processor::execute(&program, &replay_inputs).unwrap();
+    let (trace, _, _) = processor::execute(&program, &inputs).unwrap();
+    assert_eq!(expected, trace);
+}
+
+#[test]
+fn provide_pmpath_nodes() {
+    let depth = 5;
+    let index = 11;
+    let tree = build_merkle_tree(depth);
+    let leaf = tree[0][index];
+    let root = tree[depth - 1][0];
+    let program = assembly::compile(&format!("begin pmpath.{} end", depth)).unwrap();
+
+    // pmpath reads all nodes of the path up front, while the leaf index is 3rd item of the stack
+    let mut level = [0, 0];
+    let inputs = ProgramInputs::with_tape_provider(&[leaf[1], leaf[0], index as u128], move |tape, stack| {
+        let (t, column) = if tape == TapeId::A { (0, 0) } else { (1, 1) };
+        let position = (stack.get(2).unwrap() as usize) >> level[t];
+        let sibling = tree[level[t]][position ^ 1];
+        level[t] += 1;
+        return sibling[column];
+    });
+
+    let (outputs, proof) = execute(&program, &inputs, 2, &ProofOptions::default()).unwrap();
+    assert_eq!(vec![root[1], root[0]], outputs);
+    assert_eq!(Ok(()), verify(program.hash(), inputs.get_public_inputs(), &outputs, &proof));
+}
+
+#[test]
+fn merkle_path_inputs() {
+    let depth = 5;
+    let tree = build_merkle_tree(depth);
+    let root = tree[depth - 1][0];
+    let program = assembly::compile(&format!("begin dup.2 read.ab mtree.verify.{} end", depth)).unwrap();
+    let options = ProofOptions::default();
+
+    for &index in [0, 6, 9, 15].iter() {
+        let path: Vec<[u128; 2]> = (0..(depth - 1)).map(|level| tree[level][(index >> level) ^ 1]).collect();
+        assert_eq!(root, compute_merkle_root(tree[0][index], &path, index));
+
+        let inputs = ProgramInputs::for_merkle_path(tree[0][index], &path, index);
+        assert_eq!(&[root[1], root[0]], inputs.get_public_inputs());
+        let (outputs, proof) = execute(&program, &inputs, 2, &options).unwrap();
+        assert_eq!(vec![root[1], root[0]], outputs);
+        assert_eq!(Ok(()), verify(program.hash(), inputs.get_public_inputs(), &outputs, &proof));
+
+        // a path for another leaf does not resolve to the same root
+        assert_ne!(root, compute_merkle_root(tree[0][index ^ 1], &path, index));
+        let inputs = ProgramInputs::for_merkle_path(tree[0][index ^ 1], &path, index);
+        let inputs = ProgramInputs::new(&[root[1], root[0]], inputs.tape_a(), inputs.tape_b());
+        let err = execute(&program, &inputs, 2, &options).err().unwrap();
+        assert_eq!(Some(OpCode::AssertEq), err.op());
+    }
+}
+
+#[test]
+fn provider_errors() {
+    // the provider sees the stack as it was right before each read
+    let program = assembly::compile("begin read read add end").unwrap();
+    let inputs = ProgramInputs::with_tape_provider(&[], |_, stack| stack.depth() as u128 + 5);
+    let (outputs, _) = crate::run(&program, &inputs, 1).unwrap();
+    assert_eq!(vec![11], outputs);
+
+    // values which are not valid field elements are rejected
+    let inputs = ProgramInputs::with_tape_provider(&[], |_, _| field::MODULUS);
+    let err = crate::run(&program, &inputs, 1).err().unwrap();
+    assert_eq!(ExecutionError::InvalidOperand { step: 2, op: OpCode::Read, value: field::MODULUS }, err);
+
+    // without a provider, reading from an empty tape fails
+    let err = crate::run(&program, &ProgramInputs::none(), 1).err().unwrap();
+    assert_eq!(ExecutionError::EmptyTape { step: 2, op: OpCode::Read, tape: 'A' }, err);
+}
+
+#[test]
+fn provider_across_threads() {
+    fn assert_thread_safe<T: Send + Sync + std::panic::UnwindSafe>(_: &T) {}
+
+    // clones of the inputs share the provider, and can be executed on other threads
+    let program = assembly::compile("begin read read add end").unwrap();
+    let mut counter = 0;
+    let inputs = ProgramInputs::with_tape_provider(&[], move |_, _| { counter += 1; counter });
+    assert_thread_safe(&inputs);
+
+    let handle = {
+        let (program, inputs) = (program.clone(), inputs.clone());
+        std::thread::spawn(move || crate::run(&program, &inputs, 1).unwrap().0)
+    };
+    assert_eq!(vec![3], handle.join().unwrap());
+    assert_eq!(vec![7], crate::run(&program, &inputs, 1).unwrap().0);
+}
+
+// HELPER FUNCTIONS
+// ================================================================================================
+
+/// Builds a Merkle tree of the specified depth with pseudo-random leaves; level 0 of the tree
+/// holds the leaves, and the last level holds the root.
+fn build_merkle_tree(depth: usize) -> Vec<Vec<[u128; 2]>> {
+    let leaves = (0..(1 << (depth - 1))).map(|_| [field::rand(), field::rand()]).collect();
+    let mut tree: Vec<Vec<[u128; 2]>> = vec![leaves];
+    while tree.last().unwrap().len() > 1 {
+        let level = tree.last().unwrap().chunks(2).map(|pair| {
+            let digest = hasher::digest(&[pair[0][0], pair[0][1], pair[1][0], pair[1][1]]);
+            [digest[0], digest[1]]
+        }).collect();
+        tree.push(level);
+    }
+    return tree;
+}
+
+/// Maps every node of the tree to its level and position within the level.
+fn index_nodes(tree: &[Vec<[u128; 2]>]) -> HashMap<[u128; 2], (usize, usize)> {
+    let mut positions = HashMap::new();
+    for (level, nodes) in tree.iter().enumerate() {
+        for (position, &node) in nodes.iter().enumerate() {
+            positions.insert(node, (level, position));
+        }
+    }
+    return positions;
+}
@@ -0,0 +1,84 @@
+use crate::{ ProofOptions, ProgramInputs, WitnessRecord, assembly, math::field, processor };
+use super::super::{ execute_and_record, verify };
+
+#[test]
+fn replay_merkle_path_verification() {
+    let depth = 6;
+    let (path, index) = generate_authentication_path(depth);
+    let program = assembly::compile(&format!(
+        "begin read.ab dup.2 smpath.{} swap.2 push.{} roll.4 swap swap.2 pmpath.{} end",
+        depth, index, depth)).unwrap();
+
+    // append values which the program never reads to both tapes
+    let inputs = generate_program_inputs(&path, index);
+    let [mut tape_a, mut tape_b] = inputs.get_secret_inputs().clone();
+    tape_a.extend_from_slice(&[7, 8, 9]);
+    tape_b.extend_from_slice(&[10, 11]);
+    let inputs = ProgramInputs::new(&[], &tape_a, &tape_b);
+
+    let options = ProofOptions::default();
+    let (outputs, proof, record) = execute_and_record(&program, &inputs, 4, &options).unwrap();
+    assert_eq!(Ok(()), verify(program.hash(), &[], &outputs, &proof));
+
+    // the record contains only the values consumed by the program
+    assert_eq!(program.hash(), record.program_hash());
+    assert_eq!(&tape_a[..tape_a.len() - 3], &record.secret_inputs()[0][..]);
+    assert_eq!(&tape_b[..tape_b.len() - 2], &record.secret_inputs()[1][..]);
+
+    // replaying the execution from a deserialized record produces the same trace
+    let record = WitnessRecord::from_bytes(&record.to_bytes()).unwrap();
+    let replay_inputs = ProgramInputs::from_witness_record(&record);
+    let (expected, _, _) = processor::execute(&program, &inputs).unwrap();
+    let (trace, _, _) = processor::execute(&program, &replay_inputs).unwrap();
+    assert_eq!(bincode::serialize(&expected).unwrap(), bincode::serialize(&trace).unwrap());
+}
+
+#[test]
+fn record_hint_generated_values() {
+    // values placed onto the tapes by execution hints are not recorded
+    let program = assembly::compile("begin read read eq end").unwrap();
+    let inputs = ProgramInputs::new(&[], &[3, 3, 5], &[]);
+    let (_, _, _, record) = processor::execute_and_record(&program, &inputs).unwrap();
+    assert_eq!([vec![3, 3], vec![]], *record.secret_inputs());
+
+    let (expected, _, _) = processor::execute(&program, &inputs).unwrap();
+    let (trace, _, _) = processor::execute(&program, &ProgramInputs::from_witness_record(&record)).unwrap();
+    assert_eq!(expected, trace);
+
+    assert!(WitnessRecord::from_bytes(&[1, 2, 3]).is_err());
+}
+
+// HELPER FUNCTIONS
+// ================================================================================================
+
+/// Builds inputs for a program which verifies the same Merkle path first using smpath and then
+/// using pmpath operation.
+fn generate_program_inputs(path: &[Vec<u128>; 2], index: usize) -> ProgramInputs {
+    let mut a = vec![path[0][0]];
+    let mut b = vec![path[1][0]];
+    let n = path[0].len();
+    let mut index = index + usize::pow(2, (n - 1) as u32);
+
+    for i in 1..n {
+        a.push(field::ZERO);
+        b.push((index & 1) as u128);
+        index = index >> 1;
+        a.push(path[0][i]);
+        b.push(path[1][i]);
+    }
+
+    for i in 1..n {
+        a.push(path[0][i]);
+        b.push(path[1][i]);
+    }
+
+    return ProgramInputs::new(&[], &a, &b);
+}
+
+fn generate_authentication_path(n: usize) -> ([Vec<u128>; 2], usize) {
+    let s1 = [1u8; 32];
+    let s2 = [2u8; 32];
+    let leaves = u128::pow(2, (n - 1) as u32);
+    let leaf_index = (field::prng(s1) % leaves) as usize;
+    return ([field::prng_vector(s1, n), field::prng_vector(s2, n)], leaf_index);
+}
@@ -0,0 +1,232 @@
+use crate::math::field;
+
+// CONSTANTS
+// ================================================================================================
+
+/// Number of bytes needed to hold any element of the field.
+pub const ELEMENT_BYTES: usize = ((128 - field::MODULUS.leading_zeros() + 7) / 8) as usize;
+
+/// Maximum number of bytes in a LEB128-encoded u64 value.
+const MAX_VARINT_BYTES: usize = 10;
+
+// BYTE WRITER
+// ================================================================================================
+
+/// Writes values into a compact byte encoding: lengths and small integers are written as LEB128
+/// varints, field elements are written in little-endian order using `ELEMENT_BYTES` bytes each.
+pub struct ByteWriter {
+    bytes: Vec<u8>,
+}
+
+impl ByteWriter {
+
+    pub fn new() -> ByteWriter {
+        return ByteWriter { bytes: Vec::new() };
+    }
+
+    pub fn write_u8(&mut self, value: u8) {
+        self.bytes.push(value);
+    }
+
+    pub fn write_varint(&mut self, mut value: u64) {
+        while value >= 0x80 {
+            self.bytes.push((value as u8) | 0x80);
+            value >>= 7;
+        }
+        self.bytes.push(value as u8);
+    }
+
+    pub fn write_element(&mut self, value: u128) {
+        self.bytes.extend_from_slice(&value.to_le_bytes()[..ELEMENT_BYTES]);
+    }
+
+    /// Writes elements without their count; the reader must know the count from context.
+    pub fn write_element_row(&mut self, values: &[u128]) {
+        values.iter().for_each(|&value| self.write_element(value));
+    }
+
+    pub fn write_elements(&mut self, values: &[u128]) {
+        self.write_varint(values.len() as u64);
+        self.write_element_row(values);
+    }
+
+    pub fn write_digest(&mut self, digest: &[u8; 32]) {
+        self.bytes.extend_from_slice(digest);
+    }
+
+    pub fn write_digests(&mut self, digests: &[[u8; 32]]) {
+        self.write_varint(digests.len() as u64);
+        digests.iter().for_each(|digest| self.write_digest(digest));
+    }
+
+    pub fn write_bytes(&mut self, bytes: &[u8]) {
+        self.write_varint(bytes.len() as u64);
+        self.bytes.extend_from_slice(bytes);
+    }
+
+    pub fn into_bytes(self) -> Vec<u8> {
+        return self.bytes;
+    }
+}
+
+// BYTE READER
+// ================================================================================================
+
+/// Reads values written by `ByteWriter`; all reads fail cleanly on truncated or malformed input,
+/// and lists longer than the caller-specified maximum are rejected before any memory is allocated.
+pub struct ByteReader<'a> {
+    bytes   : &'a [u8],
+    position: usize,
+}
+
+impl <'a> ByteReader<'a> {
+
+    pub fn new(bytes: &'a [u8]) -> ByteReader<'a> {
+        return ByteReader { bytes, position: 0 };
+    }
+
+    pub fn read_u8(&mut self) -> Result<u8, String> {
+        return Ok(self.read_slice(1)?[0]);
+    }
+
+    pub fn read_varint(&mut self) -> Result<u64, String> {
+        let mut result = 0u64;
+        for i in 0..MAX_VARINT_BYTES {
+            let byte = self.read_u8()?;
+            result |= ((byte & 0x7F) as u64) << (7 * i);
+            if byte & 0x80 == 0 {
+                return Ok(result);
+            }
+        }
+        return Err(format!("invalid varint at byte {}", self.position));
+    }
+
+    /// Reads a length of a list and makes sure it does not exceed `max`; `name` describes
+    /// the list in the error message.
+    pub fn read_len(&mut self, max: usize, name: &str) -> Result<usize, String> {
+        let length = self.read_varint()?;
+        if length > max as u64 {
+            return Err(format!("number of {} {} exceeds maximum of {}", name, length, max));
+        }
+        return Ok(length as usize);
+    }
+
+    pub fn read_element(&mut self) -> Result<u128, String> {
+        let mut bytes = [0u8; 16];
+        bytes[..ELEMENT_BYTES].copy_from_slice(self.read_slice(ELEMENT_BYTES)?);
+        let value = u128::from_le_bytes(bytes);
+        if value >= field::MODULUS {
+            return Err(format!("invalid field element at byte {}", self.position - ELEMENT_BYTES));
+        }
+        return Ok(value);
+    }
+
+    pub fn read_element_row(&mut self, count: usize) -> Result<Vec<u128>, String> {
+        self.check_remaining(count, ELEMENT_BYTES)?;
+        return (0..count).map(|_| self.read_element()).collect();
+    }
+
+    pub fn read_elements(&mut self, max: usize, name: &str) -> Result<Vec<u128>, String> {
+        let count = self.read_len(max, name)?;
+        return self.read_element_row(count);
+    }
+
+    pub fn read_digest(&mut self) -> Result<[u8; 32], String> {
+        let mut result = [0u8; 32];
+        result.copy_from_slice(self.read_slice(32)?);
+        return Ok(result);
+    }
+
+    pub fn read_digests(&mut self, max: usize, name: &str) -> Result<Vec<[u8; 32]>, String> {
+        let count = self.read_len(max, name)?;
+        self.check_remaining(count, 32)?;
+        return (0..count).map(|_| self.read_digest()).collect();
+    }
+
+    pub fn read_bytes(&mut self, max: usize, name: &str) -> Result<&'a [u8], String> {
+        let count = self.read_len(max, name)?;
+        return self.read_slice(count);
+    }
+
+    /// Makes sure that all bytes have been read.
+    pub fn finish(self) -> Result<(), String> {
+        if self.position != self.bytes.len() {
+            return Err(format!("{} unexpected bytes at the end of input", self.bytes.len() - self.position));
+        }
+        return Ok(());
+    }
+
+    // HELPER METHODS
+    // --------------------------------------------------------------------------------------------
+    fn read_slice(&mut self, length: usize) -> Result<&'a [u8], String> {
+        self.check_remaining(length, 1)?;
+        let result = &self.bytes[self.position..(self.position + length)];
+        self.position += length;
+        return Ok(result);
+    }
+
+    fn check_remaining(&self, count: usize, item_size: usize) -> Result<(), String> {
+        let remaining = self.bytes.len() - self.position;
+        if count.saturating_mul(item_size) > remaining {
+            return Err(format!("unexpected end of input at byte {}", self.bytes.len()));
+        }
+        return Ok(());
+    }
+}
+
+// TESTS
+// ================================================================================================
+#[cfg(test)]
+mod tests {
+
+    use crate::math::field;
+    use super::{ ByteWriter, ByteReader };
+
+    #[test]
+    fn round_trip() {
+        let mut writer = ByteWriter::new();
+        writer.write_u8(7);
+        writer.write_varint(0);
+        writer.write_varint(300);
+        writer.write_varint(u64::MAX);
+        writer.write_elements(&[0, 1, field::MODULUS - 1]);
+        writer.write_digests(&[[1; 32], [2; 32]]);
+        writer.write_bytes(&[3, 4, 5]);
+        let bytes = writer.into_bytes();
+
+        let mut reader = ByteReader::new(&bytes);
+        assert_eq!(Ok(7), reader.read_u8());
+        assert_eq!(Ok(0), reader.read_varint());
+        assert_eq!(Ok(300), reader.read_varint());
+        assert_eq!(Ok(u64::MAX), reader.read_varint());
+        assert_eq!(Ok(vec![0, 1, field::MODULUS - 1]), reader.read_elements(3, "elements"));
+        assert_eq!(Ok(vec![[1; 32], [2; 32]]), reader.read_digests(2, "digests"));
+        assert_eq!(Ok(&[3u8, 4, 5][..]), reader.read_bytes(3, "bytes"));
+        assert_eq!(Ok(()), reader.finish());
+    }
+
+    #[test]
+    fn malformed_input() {
+        let mut writer = ByteWriter::new();
+        writer.write_elements(&[1, 2, 3]);
+        let bytes = writer.into_bytes();
+
+        // lists longer than the maximum are rejected
+        let err = ByteReader::new(&bytes).read_elements(2, "values").err().unwrap();
+        assert_eq!("number of values 3 exceeds maximum of 2", err);
+
+        // truncated input is rejected
+        let err = ByteReader::new(&bytes[..40]).read_elements(3, "values").err().unwrap();
+        assert_eq!("unexpected end of input at byte 40", err);
+
+        // values outside of the field are rejected
+        let mut writer = ByteWriter::new();
+        writer.write_element(field::MODULUS);
+        assert!(ByteReader::new(&writer.into_bytes()).read_element().is_err());
+
+        // trailing bytes are rejected
+        let mut reader = ByteReader::new(&bytes);
+        reader.read_u8().unwrap();
+        assert!(reader.finish().is_err());
+    }
+}
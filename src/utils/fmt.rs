@@ -0,0 +1,114 @@
+use std::sync::atomic::{ AtomicU8, Ordering };
+use crate::math::field;
+
+// CONSTANTS
+// ================================================================================================
+static DISPLAY_RADIX: AtomicU8 = AtomicU8::new(Radix::Dec as u8);
+
+// TYPES AND INTERFACES
+// ================================================================================================
+
+/// Notation used to render field elements as text.
+#[repr(u8)]
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Radix {
+    /// Hexadecimal digits prefixed with 0x.
+    Hex     = 0,
+    /// Decimal digits.
+    Dec     = 1,
+    /// Decimal digits; values greater than (p - 1) / 2 are rendered as negative numbers.
+    Signed  = 2,
+}
+
+impl std::str::FromStr for Radix {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Radix, String> {
+        return match s {
+            "hex"       => Ok(Radix::Hex),
+            "dec"       => Ok(Radix::Dec),
+            "signed"    => Ok(Radix::Signed),
+            _ => Err(format!("'{}' is not a valid radix; expected hex, dec, or signed", s)),
+        };
+    }
+}
+
+// FORMATTING FUNCTIONS
+// ================================================================================================
+
+/// Renders field element `x` in the specified `radix`.
+pub fn fmt_element(x: u128, radix: Radix) -> String {
+    return match radix {
+        Radix::Hex => format!("{:#x}", x),
+        Radix::Dec => format!("{}", x),
+        Radix::Signed => {
+            if x > field::M / 2 { format!("-{}", field::M - x) } else { format!("{}", x) }
+        },
+    };
+}
+
+/// Renders a list of field elements in the specified `radix` as [x0, x1, ...].
+pub fn fmt_elements(values: &[u128], radix: Radix) -> String {
+    let values: Vec<String> = values.iter().map(|&x| fmt_element(x, radix)).collect();
+    return format!("[{}]", values.join(", "));
+}
+
+/// Sets the radix used by Display implementations which print field elements (e.g. execution
+/// trace states); this affects only how values are printed. The default is `Radix::Dec`.
+pub fn set_display_radix(radix: Radix) {
+    DISPLAY_RADIX.store(radix as u8, Ordering::Relaxed);
+}
+
+/// Returns the radix used by Display implementations to print field elements.
+pub fn display_radix() -> Radix {
+    return match DISPLAY_RADIX.load(Ordering::Relaxed) {
+        0 => Radix::Hex,
+        2 => Radix::Signed,
+        _ => Radix::Dec,
+    };
+}
+
+// TESTS
+// ================================================================================================
+#[cfg(test)]
+mod tests {
+
+    use crate::math::field;
+    use super::{ Radix, fmt_element, fmt_elements };
+
+    #[test]
+    fn fmt_element_hex() {
+        assert_eq!("0x0", fmt_element(0, Radix::Hex));
+        assert_eq!("0x1", fmt_element(1, Radix::Hex));
+        assert_eq!("0xffffffffffffffffffffd30000000000", fmt_element(field::M - 1, Radix::Hex));
+        assert_eq!("0xdeadbeef0123456789abcdef01234567", fmt_element(HASH_VALUE, Radix::Hex));
+    }
+
+    #[test]
+    fn fmt_element_dec() {
+        assert_eq!("0", fmt_element(0, Radix::Dec));
+        assert_eq!("1", fmt_element(1, Radix::Dec));
+        assert_eq!("340282366920938463463374557953744961536", fmt_element(field::M - 1, Radix::Dec));
+        assert_eq!(HASH_VALUE.to_string(), fmt_element(HASH_VALUE, Radix::Dec));
+    }
+
+    #[test]
+    fn fmt_element_signed() {
+        assert_eq!("0", fmt_element(0, Radix::Signed));
+        assert_eq!("1", fmt_element(1, Radix::Signed));
+        assert_eq!("-1", fmt_element(field::M - 1, Radix::Signed));
+        assert_eq!(format!("{}", field::M / 2), fmt_element(field::M / 2, Radix::Signed));
+        assert_eq!(format!("-{}", field::M / 2), fmt_element(field::M / 2 + 1, Radix::Signed));
+        assert_eq!(format!("-{}", field::M - HASH_VALUE), fmt_element(HASH_VALUE, Radix::Signed));
+    }
+
+    #[test]
+    fn fmt_element_list() {
+        assert_eq!("[0x0, 0x1]", fmt_elements(&[0, 1], Radix::Hex));
+        assert_eq!("[2, -2]", fmt_elements(&[2, field::M - 2], Radix::Signed));
+        assert_eq!("[]", fmt_elements(&[], Radix::Dec));
+    }
+
+    // a value which looks like an element of a hash digest
+    const HASH_VALUE: u128 = 0xdeadbeef0123456789abcdef01234567;
+}
@@ -1,5 +1,6 @@
 use crate::{
     math::field,
+    crypto::rescue::constants::{ ALPHA, INV_ALPHA, MDS, INV_MDS },
     HASH_STATE_WIDTH as STATE_WIDTH,
     HASH_STATE_RATE as STATE_RATE,
     HASH_DIGEST_SIZE as DIGEST_SIZE,
@@ -91,27 +92,8 @@ pub fn apply_inv_mds(state: &mut[u128]) {
 // 128-BIT RESCUE CONSTANTS
 // ================================================================================================
 
-const ALPHA: u128 = 3;
-const INV_ALPHA: u128 = 226854911280625642308916371969163307691;
-
-const MDS: [u128; STATE_WIDTH * STATE_WIDTH] = [
-     34702391375697798808541201166389247321, 292720401120629668097050277338444166479, 252221686506898646925660607780980529565,   1545301432720594930091500405440765270, 249229091188143033873076468277345141138, 220001593723324427188563221285612032538,
-    223274184432289781839114239013770504955, 330042960751289206923775620692185805456,  68147806084648525660922442535124284349, 170632587193822854126540173326689266153, 250033902372207462477717017592730125263, 241770281110130121239200125437407593586,
-     59697037488579951129595490016876870776, 173037025415440639734730939871096987969, 244520331803890388707106378055030145592,  34432552219210978837375640622811234255, 224883744083395074894597669527169800639, 118987613174044827738657284387435362389,
-    178405816334148045547444196947551204988, 329492269239016599078865693624718656026,   5932101030068686798137276449370802175,  82061764821777249869371835777715849101, 306668779179264388848571277248016578389, 111826390250505084847238806440816480816,
-    311483585658925440282415980040674425324, 145759142525852648614462741190071048842, 298009719049064449565063658657087558281,  20897600766797241015108657845791963643,  10708575082009935910638872278310543860,   1925891850054765549789187338759995098,
-    215280917571128372543200644166029121446,  95409967251054914374823434415713274752,  47264968375673684314231886553980659586, 324414896710549426218067045352609729751, 134533192639212680415562336758249126966, 113819576569856286031671903516923963713,
-];
-
-const INV_MDS: [u128; STATE_WIDTH * STATE_WIDTH] = [
-    262838870629088612431704202279372804365, 222534852307924763539037405488664736598,  81646375972783381860153911140700582070, 199701217011341155249764385939633347172, 332690827334260982039678112409792234722,  89516833564756742553232000561318423105,
-    268971102785434627130755364883988908171, 281455956826013141038766773877631706708, 260031388727053904917456703760391819397, 129628250235822118843226947626941528745,   4752366532746298637564749858084292362,  41511161206792419767881138471159674575,
-    182137736677911549469583431255398613256,  81590632530312527113593374981163544706, 187388592404931394753634116375361630728,  33930556286904149060086783748137598339, 128856701558652053153053295206366630120, 142358475095617426664163236851553505832,
-    227338232821163582236298610629943480668, 331216968806018107216634963151792902695,  26461805327234621438733072541631103386, 256026201749452500286052447974298083810, 225021687632788174205391941469031171903, 142987865655544874008196227497386450956,
-     23528566895021558524617283665890082663, 236073665414937855350933025880234841420, 333046083413429707379387119916134573299, 306789363216075136579531522242680359507,  28410362227208796996681096912722982356, 315464216130582396582421428659045349544,
-     83197014978047724714246600214591095407,  26699280864421082988027855316107178960, 198532124113408992589453646783315962129,  33447687267235554595784456310784250249, 230423441211289196836867098686067243907,   6651208139349692977552460975523401420,
-];
-
+/// Round constants for a single cycle of 16 rounds; the table is derived from the master table
+/// of round constants via `crypto::rescue::constants::build_cycle_constants()`.
 pub const ARK: [[u128; CYCLE_LENGTH]; STATE_WIDTH * 2] = [
     [ 73742662193393629993182617210984534396,  53265540956785335308970867946461681393,  14395595548581550072136442264588359269, 122001776241989922016768881111033630021,  60517382118002481956993039132628798754, 242872884766759335785324964049644229294,   4363347423120340347647334422662129280,  36224510031696203479366212612960872957,  48405253030503584410290697712994785780,  81691558114273932307586556761543100315, 315851285839738308287329276161693313425, 326468515245013538774703881972225680443,  43697512293048123577843997788308773455, 311182552853825261047305944842224924215,  23833044413239455428827669432473543240,   7640791703119561504971867271087353186],
     [294241649061853322876594266104693176711,  37163237225742447359704121711857363416, 122453723578185362799857252115182955415,  45955200056324872841369110391855073949, 118224404177203231307646344308524770691, 334905318181122708043147970432770442813, 151456178618798089303785904835852898400, 158324780313294970656577210958221752332,  94987431711345870355583825474329298047, 314293870425266938862923101612602484635, 153975056764703018977481562856167540343, 321383880935903155966493388921501530915,  50057060110310193394516504439805601601, 101740347373933108709122003348416870840,  80845608757236703492016225128275757615, 209519938465996994070512842713405349097],
@@ -4,6 +4,23 @@ use std::{ mem, slice, ops::Range };
 // ================================================================================================
 pub mod hasher;
 pub mod sponge;
+pub mod fmt;
+pub mod bytes;
+
+// CONCURRENCY
+// ================================================================================================
+
+/// Set when the crate is built with `concurrent` feature; trace extension and Merkle tree
+/// construction are then distributed across all available cores.
+pub const CONCURRENT: bool = cfg!(feature = "concurrent");
+
+/// Returns the number of threads used for trace extension and Merkle tree construction.
+pub fn num_threads() -> usize {
+    #[cfg(feature = "concurrent")]
+    return rayon::current_num_threads();
+    #[cfg(not(feature = "concurrent"))]
+    return 1;
+}
 
 // VECTOR FUNCTIONS
 // ================================================================================================
@@ -1,4 +1,5 @@
 use crate::math::{ field };
+use crate::crypto::rescue::constants::{ ALPHA, INV_ALPHA, SPONGE_MDS as MDS, SPONGE_INV_MDS as INV_MDS };
 use crate::{
     BASE_CYCLE_LENGTH as NUM_ROUNDS,
     SPONGE_WIDTH as STATE_WIDTH,
@@ -80,23 +81,9 @@ pub fn apply_inv_mds(state: &mut[u128]) {
 
 // 128-BIT RESCUE CONSTANTS
 // ================================================================================================
-const ALPHA: u128 = 3;
-const INV_ALPHA: u128 = 226854911280625642308916371969163307691;
-
-const MDS: [u128; STATE_WIDTH * STATE_WIDTH] = [
-    315189521614069403867817270152032075784,  10737242274749505456268020883296531251, 164166492670388427786346110319108935134, 282318813916891806489021925524031494414,
-    339659984245804546554434478921876908973,  97319381058524916656000376979320858814, 141017807671871944240242749183803053011, 271669633517564511702965675947905678154,
-    330029911818464578106380298339390343164,  37351365266361901988170671462637236976, 260386862860725098262319886102680637202, 161805458319902660573511017706877187889,
-    220775330812333365987157544144955631442, 172992909845374020745323861886602514703,   8447293670850292346208742365584924315, 276315004099450287580088164954743181255,
-];
-
-const INV_MDS: [u128; STATE_WIDTH * STATE_WIDTH] = [
-    212015899302823985314659753132599968692, 222079945358547787481366483464725880498, 313947036552775452548888741999726656951,  94528877516599685906969597450601957552,
-    201841258819571375352239737215387725848,  42276963631701875238524357392500799145, 332890116061360870847041499810748569092,   3939991425276748394854935956419873430,
-    239100689228321601709623770733501932352, 178946314809288623489527367841505752988, 270128331008291756180543638308504150653, 315661002876081483102676309387501623498,
-    298377528588644746682709801175581650901, 114666605273067789843953739274063213369, 279054651722812961169783459501878203576, 308067640269163823896854342618197051588,
-];
 
+/// Round constants for a single cycle of 16 rounds; the table is derived from the master table
+/// of round constants via `crypto::rescue::constants::build_cycle_constants()`.
 pub const ARK: [[u128; NUM_ROUNDS]; STATE_WIDTH * 2] = [
     [ 73742662193393629993182617210984534396, 190338348930091047298074165559397264378, 135862987622353414661673448620033990934,  14395595548581550072136442264588359269, 178527953570703982986577498890483203023,  89333775516890774827962437297764936547,  60517382118002481956993039132628798754, 300207915911051460908298688414919921093, 287288998844960276649854461883880913666,   4363347423120340347647334422662129280, 169061616865327291005064664270275836534,  55063854082489962294956447144901184837,  48405253030503584410290697712994785780,  26236509279945457822369793146288866403,   8168599451814692118441936734435571667, 315851285839738308287329276161693313425],
     [170640173476284978302806154399958141555, 225556280578098393163620719229418290860,  43697512293048123577843997788308773455, 334227022756371766478760448625337379424, 188323096432976273265052369652285099186,  23833044413239455428827669432473543240, 258001239441974384951891541079242930440, 219177966622498447376602481443936826442, 294241649061853322876594266104693176711, 179443458614881887600494128053111694648, 171502007855719014010389694111716628578, 122453723578185362799857252115182955415,  97063282200318501142854934314343169049, 154737674033120948700227987365296907637, 118224404177203231307646344308524770691,  67833038363599207475373040930824843019],
@@ -0,0 +1,43 @@
+use distaff::{ assembly, ProgramInputs, ProofOptions, execute, verify, testing::coverage };
+
+// COVERAGE GATE
+// ================================================================================================
+
+/// Proves and verifies a set of programs which together execute every user operation and take
+/// every control flow path, and fails if the coverage collector missed any of them. The test
+/// runs in its own process, and so the report does not depend on other tests.
+#[test]
+fn verify_opcode_coverage() {
+    let options = ProofOptions::default();
+
+    // stack manipulation, math, and hashing operations
+    let program = assembly::compile("
+        begin
+            push.1 push.1 and push.0 or assert push.0 not assert push.7 push.7 assert.eq
+            push.3 push.5 add push.2 mul inv neg
+            read read.ab read.4 dup dup.2 dup.4 dup.8 pad.2 drop drop.4
+            swap swap.2 swap.4 roll.4 roll.8 drop.4 drop.4
+            push.1 push.4 push.5 choose push.1 push.1 push.2 push.3 push.4 choose.2 drop.4
+            push.0 push.2 push.3 push.4 push.5 cswap.2 drop.4
+            push.1 push.2 push.3 push.4 push.5 push.6 push.7 push.8 push.9 cswap.4 drop.4 drop.4
+            push.5 push.5 eq push.10 gt.8 exp.4 rc.8 hash.2
+        end").unwrap();
+    let inputs = ProgramInputs::new(&[], &[1, 2, 3, 4, 5], &[6, 7, 8]);
+    let (outputs, proof) = execute(&program, &inputs, 1, &options).unwrap();
+    verify(program.hash(), &[], &outputs, &proof).unwrap();
+
+    // both branches of a conditional, and loops which are skipped, exited, and wrapped
+    let program = assembly::compile("
+        begin
+            read if.true push.3 else push.4 end
+            read while.true read end
+        end").unwrap();
+    for tape_a in [vec![1, 0], vec![0, 1, 1, 0]].iter() {
+        let inputs = ProgramInputs::new(&[], tape_a, &[]);
+        let (outputs, proof) = execute(&program, &inputs, 1, &options).unwrap();
+        verify(program.hash(), &[], &outputs, &proof).unwrap();
+    }
+
+    let report = coverage::report();
+    assert!(report.uncovered.is_empty(), "no verified proofs for: {}", report.uncovered.join(", "));
+}
@@ -0,0 +1,74 @@
+use std::alloc::{ GlobalAlloc, Layout, System };
+use std::sync::atomic::{ AtomicUsize, Ordering };
+use distaff::{ assembly, ProgramInputs, ProofOptions, HashFunction, TraceTable, verify };
+
+// COUNTING ALLOCATOR
+// ================================================================================================
+
+/// Tracks the number of currently allocated bytes and the peak of that number; integration tests
+/// run in their own process, and so all allocations counted here come from this test.
+struct CountingAllocator;
+
+static ALLOCATED: AtomicUsize = AtomicUsize::new(0);
+static PEAK: AtomicUsize = AtomicUsize::new(0);
+
+unsafe impl GlobalAlloc for CountingAllocator {
+
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        record_growth(layout.size());
+        return System.alloc(layout);
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        ALLOCATED.fetch_sub(layout.size(), Ordering::SeqCst);
+        System.dealloc(ptr, layout);
+    }
+
+    unsafe fn realloc(&self, ptr: *mut u8, layout: Layout, new_size: usize) -> *mut u8 {
+        if new_size > layout.size() {
+            record_growth(new_size - layout.size());
+        }
+        else {
+            ALLOCATED.fetch_sub(layout.size() - new_size, Ordering::SeqCst);
+        }
+        return System.realloc(ptr, layout, new_size);
+    }
+}
+
+#[global_allocator]
+static ALLOCATOR: CountingAllocator = CountingAllocator;
+
+fn record_growth(size: usize) {
+    let allocated = ALLOCATED.fetch_add(size, Ordering::SeqCst) + size;
+    PEAK.fetch_max(allocated, Ordering::SeqCst);
+}
+
+// TESTS
+// ================================================================================================
+
+#[test]
+fn prover_memory() {
+    let program = assembly::compile("begin push.1 push.1 repeat.200 swap dup.2 drop add end end").unwrap();
+    let inputs = ProgramInputs::none();
+    let options = ProofOptions::new(64, 50, 0, HashFunction::Blake3);
+
+    let baseline = ALLOCATED.load(Ordering::SeqCst);
+    PEAK.store(baseline, Ordering::SeqCst);
+    let (outputs, proof) = distaff::execute(&program, &inputs, 1, &options).unwrap();
+    let peak = PEAK.load(Ordering::SeqCst) - baseline;
+
+    // the prover used to keep the entire extended trace in memory, and so its peak usage could
+    // never be below the size of the extended trace; now only states over the constraint
+    // evaluation domain are kept, and the rest of the extended trace is never held in memory
+    let registers = proof.trace_layout().width();
+    let steps = proof.trace_length();
+    let extended_trace_size = registers * steps * options.extension_factor() * 16;
+    assert!(peak < extended_trace_size,
+        "peak allocation of {} bytes is not below extended trace size of {} bytes", peak, extended_trace_size);
+
+    // the estimate covers trace extension and commitment, which is only a part of the peak
+    let estimate = TraceTable::memory_estimate(registers, steps, options.extension_factor());
+    assert!(estimate < peak, "memory estimate of {} bytes exceeds peak allocation of {} bytes", estimate, peak);
+
+    assert_eq!(Ok(()), verify(program.hash(), inputs.get_public_inputs(), &outputs, &proof));
+}